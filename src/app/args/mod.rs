@@ -1,24 +1,234 @@
 use std::marker::PhantomData;
 
 use super::*;
+use audio::audio_constructor::{
+    DEFAULT_GRAIN_SIZE_MS, DEFAULT_PITCH_SHIFT_BLOCK_SIZE,
+    MAX_PITCH_SHIFT_BLOCK_SIZE,
+};
+use audio::fx_control::{
+    parse_delay_filter, parse_delay_tap, parse_delay_time, DelayParameters,
+};
+use audio::input::{MAX_INPUT_GAIN_DB, MIN_INPUT_GAIN_DB};
+use audio::offline::{DEFAULT_RENDER_PATH, DEFAULT_RENDER_SAMPLE_RATE};
+use audio::modulation::{parse_mod_route, DEFAULT_MOD_LFO};
+use crate::dsp::filtering::simple::dc_filter::{
+    DCFilterSlope, DEFAULT_DC_FILTER_FREQ_HZ, MAX_DC_FILTER_FREQ_HZ,
+    MIN_DC_FILTER_FREQ_HZ,
+};
+use crate::dsp::dynamics::gate::{
+    DEFAULT_GATE_OFFSET_DB, MIN_GATE_REDUCTION_DB,
+};
+use crate::dsp::dynamics::limiter::{
+    LimiterParameters, MAX_LIMITER_CEILING_DB, MAX_LIMITER_RELEASE_MS,
+    MIN_LIMITER_CEILING_DB, MIN_LIMITER_RELEASE_MS,
+};
+use audio::true_peak::{
+    DEFAULT_TRUE_PEAK_THRESHOLD_DB, MAX_TRUE_PEAK_THRESHOLD_DB,
+    MIN_TRUE_PEAK_THRESHOLD_DB,
+};
+use audio::voice::glide::{GlideMode, GlideParameters, MAX_GLIDE_TIME_MS};
+use audio::voice::stealing::VoiceStealing;
+use audio::voice::sub::{
+    SubParameters, SubShape, MAX_SUB_CROSSOVER_HZ, MAX_SUB_LEVEL_DB,
+    MIN_SUB_CROSSOVER_HZ, MIN_SUB_LEVEL_DB,
+};
+use audio::voice::unison::{
+    UnisonParameters, MAX_UNISON_DETUNE_CENTS, MAX_UNISON_VOICES,
+};
+use hands::mask_painter::MaskPaintConfig;
+use midi::rtp::DEFAULT_RTP_MIDI_PORT;
+use crate::dsp::synthesis::granular::{
+    MAX_GRAIN_SIZE_MS, MAX_PITCH_SPRAY, MIN_GRAIN_SIZE_MS,
+};
+use crate::dsp::delay::stereo_delay::MAX_DELAY_TAPS;
+use crate::dsp::modulation::lfo::{MAX_LFO_RATE_HZ, MIN_LFO_RATE_HZ};
+use crate::dsp::modulation::matrix::MAX_MOD_ROUTES;
+use crate::dsp::{
+    GateThreshold, LfoParameters, LfoRate, LfoShape, ModRoute, NoiseColor,
+};
+use midi::sender::MIDIProtocol;
+use midi::timecode::FrameRate;
+use view::attract::DEFAULT_ATTRACT_DELAY;
+use view::pulse::DEFAULT_PULSE_INTENSITY;
+use osc::{
+    discovery::DEFAULT_DISCOVERY_TIMEOUT,
+    jitter::MAX_JITTER_BUFFER_FRAMES,
+    redundancy::{RedundancyRole, DEFAULT_REDUNDANCY_PORT},
+    transport::OSCTransportKind,
+    DEFAULT_OSC_RX_HOST, DEFAULT_OSC_TX_HOST,
+};
+use std::time::Duration;
+
+/// The default largest pitch shift, in semitones.
+pub const DEFAULT_PITCH_SHIFT_RANGE: f64 = 12.0;
+/// The smallest block size which the pitch shifter may use.
+pub const MIN_PITCH_SHIFT_BLOCK_SIZE: usize = 1 << 8;
 
 #[allow(clippy::struct_excessive_bools)]
 pub struct Arguments {
     pub osc_rx_port: u16,
     pub osc_tx_port: u16,
+    pub osc_rx_host: String,
+    /// The hosts and ports which EME requests are sent to.
+    pub osc_tx_destinations: Vec<(String, u16)>,
     pub auto_start_send: bool,
     pub show_state_data: bool,
     pub auto_change_mode: bool,
     pub print: bool,
     pub debug: bool,
+    pub osc_map_path: Option<String>,
+    pub tracking_config_path: Option<String>,
+    pub profiles_path: Option<String>,
+    /// The name of a built-in mapping template, or the path to one.
+    pub mapping: Option<String>,
+    pub profile_name: Option<String>,
+    pub rooms_path: Option<String>,
+    /// The name of the room whose EQ is used at startup.
+    pub room_name: Option<String>,
+    /// The file which on-screen text is loaded from, for other languages.
+    pub strings_path: Option<String>,
+    /// The file which the visuals' color palettes are loaded from.
+    pub palette_path: Option<String>,
+    /// How strongly the visuals pulse with the beat and the output's low
+    /// end, from `0.0` to `1.0`.
+    pub pulse_intensity: f32,
+    /// How long without hands before the instructional animation plays, in
+    /// seconds, if it does.
+    pub attract_delay: Option<f64>,
+    pub state_path: Option<String>,
+    /// Whether to load the saved state at startup.
+    pub resume: bool,
+    pub osc_record_path: Option<String>,
+    /// The file which holds the key that control messages must be signed
+    /// with, if they must be.
+    pub control_key_path: Option<String>,
+    pub osc_replay_path: Option<String>,
+    pub osc_bundles: bool,
+    pub osc_transport: OSCTransportKind,
+    pub gesture_tempo: bool,
+    pub eme_acks: bool,
+    pub osc_stale_timeout: f64,
+    pub osc_dead_timeout: f64,
+    /// The host and port which the app's state is published to, if any.
+    pub state_osc_destination: Option<(String, u16)>,
+    /// How the hands paint the spectral filter's mask.
+    pub mask_paint: MaskPaintConfig,
+    /// The axis of the first hand (`0` to `2` for x, y and z) which controls
+    /// the pitch shift, if any.
+    pub pitch_axis: Option<usize>,
+    /// The largest pitch shift, in semitones, either side of the axis'
+    /// center.
+    pub pitch_range: f64,
+    pub preserve_formants: bool,
+    pub pitch_block_size: usize,
+    /// How many hand frames the jitter buffer holds back, or `0` if it is
+    /// disabled.
+    pub jitter_buffer_frames: usize,
+    /// The host and port which tracker restart requests are sent to, if any.
+    pub tracker_restart_destination: Option<(String, u16)>,
+    /// How long to look for the tracker and the EME on the local network,
+    /// if they are discovered rather than taken from the arguments.
+    pub discovery_timeout: Option<Duration>,
+    /// The host and port of the other instance, if two instances run side
+    /// by side for redundancy.
+    pub redundancy_peer: Option<(String, u16)>,
+    /// The port which the other instance's heartbeats are received on.
+    pub redundancy_port: u16,
+    /// Which role this instance prefers, if running redundantly.
+    pub redundancy_role: RedundancyRole,
+    /// A MIDI file which is rendered offline instead of running the app,
+    /// if any.
+    pub render_midi_path: Option<String>,
+    /// An OSC recording (made with `--record`) whose hand data is replayed
+    /// in offline renders, if any.
+    pub render_gestures_path: Option<String>,
+    /// The WAV file which offline renders are written to.
+    pub render_output_path: String,
+    /// The sample rate of offline renders, in Hz.
+    pub render_sample_rate: f64,
+    /// A WAV or AIFF file which is played for each note, if any.
+    pub sample_path: Option<String>,
+    pub sample_looping: bool,
+    /// The multi-channel WAV file which each stem of the output is recorded
+    /// to, if any.
+    pub stems_path: Option<String>,
+    /// The first-order B-format WAV file which the master is recorded to
+    /// alongside the stems, if any.
+    pub bformat_path: Option<String>,
+    /// Whether the output starts rendered binaurally, for monitoring on
+    /// headphones.
+    pub binaural: bool,
+    /// The RTP-MIDI peer which CCs are sent to instead of a MIDI port, if
+    /// any.
+    pub rtp_midi_peer: Option<(String, u16)>,
+    /// The protocol which the MIDI destination receives.
+    pub midi_protocol: MIDIProtocol,
+    /// Whether CCs are sent with MIDI running status.
+    pub midi_running_status: bool,
+    /// The frame rate of the MIDI Time Code sent with the transport, if it
+    /// is sent.
+    pub mtc_rate: Option<FrameRate>,
+    /// Whether the default input device is mixed into the output.
+    pub audio_input: bool,
+    /// The input gain, in decibels.
+    pub input_gain_db: f64,
+    /// The threshold of the input's noise gate, if it is enabled.
+    pub input_gate: Option<GateThreshold>,
+    /// Whether ringing frequencies are notched out of the output.
+    pub feedback_suppressor: bool,
+    /// Whether the granular engine replays recent audio as grains.
+    pub granular: bool,
+    /// The length of each grain, in milliseconds.
+    pub grain_size_ms: f64,
+    /// The widest random pitch offset of each grain, in semitones.
+    pub grain_pitch_spray: f64,
+    /// The arc which automatic mode changes and the tempo follow over the
+    /// session, if any.
+    pub intensity_arc: Option<IntensityArc>,
+    /// The spectral slope of the noise exciter.
+    pub noise_color: NoiseColor,
+    /// How each voice's generators are stacked.
+    pub unison: UnisonParameters,
+    /// How each new voice glides from the previous voice's pitch.
+    pub glide: GlideParameters,
+    /// The settings of the sub oscillator, if it is enabled.
+    pub sub: Option<SubParameters>,
+    /// How voices are chosen to be stolen.
+    pub voice_stealing: VoiceStealing,
+    /// The most voices which may sound at once.
+    pub max_polyphony: usize,
+    /// The routes of the effects' modulation matrix.
+    pub mod_routes: Vec<ModRoute>,
+    /// The settings of the modulation LFO.
+    pub mod_lfo: LfoParameters,
+    /// How the delay is set up.
+    pub delay: DelayParameters,
+    /// The cutoff of the output's DC and subsonic filter.
+    pub dc_filter_freq: f64,
+    pub dc_filter_slope: DCFilterSlope,
+    /// The settings of the output limiter, if it is enabled.
+    pub limiter: Option<LimiterParameters>,
+    /// The true peak above which the output is logged as over, in decibels.
+    pub true_peak_threshold_db: f64,
+    /// How closely the hand must match a stored pose to recall its scene.
+    pub pose_tolerance: f64,
+    /// How long without any interaction before the app winds down and
+    /// sleeps, in minutes, if it does.
+    pub wind_down_minutes: Option<f64>,
 
     _pd: PhantomData<()>,
 }
 
 impl Arguments {
     pub fn from_env() -> Result<Self, String> {
-        let mut args = std::env::args();
+        Self::from_args(std::env::args())
+    }
 
+    /// Parses the arguments in `args`, where the first is the program's
+    /// name.
+    pub fn from_args(
+        mut args: impl Iterator<Item = String>,
+    ) -> Result<Self, String> {
         _ = args.next();
 
         let first_arg = args.next();
@@ -48,8 +258,229 @@ impl Arguments {
         let mut auto_change_mode = true;
         let mut print = true;
         let mut debug = false;
+        let mut osc_map_path = None;
+        let mut tracking_config_path = None;
+        let mut profiles_path = None;
+        let mut mapping = None;
+        let mut profile_name = None;
+        let mut rooms_path = None;
+        let mut room_name = None;
+        let mut strings_path = None;
+        let mut palette_path = None;
+        let mut pulse_intensity = DEFAULT_PULSE_INTENSITY;
+        let mut attract_delay = Some(DEFAULT_ATTRACT_DELAY);
+        let mut state_path = None;
+        let mut resume = false;
+        let mut osc_record_path = None;
+        let mut control_key_path = None;
+        let mut osc_replay_path = None;
+        let mut osc_bundles = true;
+        let mut osc_transport = OSCTransportKind::default();
+        let mut gesture_tempo = false;
+        let mut eme_acks = false;
+        let mut osc_rx_host = String::from(DEFAULT_OSC_RX_HOST);
+        let mut osc_tx_hosts = String::from(DEFAULT_OSC_TX_HOST);
+        let mut osc_stale_timeout = DEFAULT_OSC_STALE_TIMEOUT;
+        let mut osc_dead_timeout = DEFAULT_OSC_DEAD_TIMEOUT;
+        let mut state_osc_host = String::from(DEFAULT_OSC_TX_HOST);
+        let mut state_osc_port = None;
+        let mut mask_paint = MaskPaintConfig::default();
+        let mut pitch_axis = None;
+        let mut pitch_range = DEFAULT_PITCH_SHIFT_RANGE;
+        let mut preserve_formants = false;
+        let mut pitch_block_size = DEFAULT_PITCH_SHIFT_BLOCK_SIZE;
+        let mut jitter_buffer_frames = 0;
+        let mut tracker_restart_host = String::from(DEFAULT_OSC_TX_HOST);
+        let mut tracker_restart_port = None;
+        let mut discovery_timeout = None;
+        let mut redundancy_peer = None;
+        let mut redundancy_port = DEFAULT_REDUNDANCY_PORT;
+        let mut redundancy_role = RedundancyRole::Primary;
+        let mut render_midi_path = None;
+        let mut render_gestures_path = None;
+        let mut render_output_path = String::from(DEFAULT_RENDER_PATH);
+        let mut render_sample_rate = DEFAULT_RENDER_SAMPLE_RATE;
+        let mut sample_path = None;
+        let mut sample_looping = false;
+        let mut stems_path = None;
+        let mut bformat_path = None;
+        let mut binaural = false;
+        let mut rtp_midi_peer = None;
+        let mut midi_running_status = false;
+        let mut midi_protocol = MIDIProtocol::default();
+        let mut mtc_rate = None;
+        let mut audio_input = false;
+        let mut input_gain_db = 0.0;
+        let mut input_gate = None;
+        let mut feedback_suppressor = false;
+        let mut granular = false;
+        let mut grain_size_ms = DEFAULT_GRAIN_SIZE_MS;
+        let mut grain_pitch_spray = 0.0;
+        let mut arc_minutes = None;
+        let mut arc_peak = DEFAULT_ARC_PEAK;
+        let mut arc_tempo_range = DEFAULT_ARC_TEMPO_RANGE;
+        let mut arc_modes = None;
+        let mut noise_color = NoiseColor::default();
+        let mut unison = UnisonParameters::default();
+        let mut glide = GlideParameters::default();
+        let mut sub = None;
+        let mut voice_stealing = VoiceStealing::default();
+        let mut max_polyphony = NUM_VOICES as usize;
+        let mut mod_routes = Vec::new();
+        let mut mod_lfo = DEFAULT_MOD_LFO;
+        let mut delay = DelayParameters::default();
+        let mut dc_filter_freq = DEFAULT_DC_FILTER_FREQ_HZ;
+        let mut dc_filter_slope = DCFilterSlope::default();
+        let mut limiter = LimiterParameters::default();
+        let mut no_limiter = false;
+        let mut true_peak_threshold_db = DEFAULT_TRUE_PEAK_THRESHOLD_DB;
+        let mut pose_tolerance = DEFAULT_POSE_TOLERANCE;
+        let mut wind_down_minutes = None;
+
+        while let Some(mut arg) = args.next() {
+            // paths are case-sensitive, so these are checked before lowercasing
+            if let Some(path) = arg.strip_prefix("--osc-map=") {
+                osc_map_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--tracking-config=") {
+                tracking_config_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--profiles=") {
+                profiles_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(name) = arg.strip_prefix("--mapping=") {
+                mapping = Some(name.to_string());
+                continue;
+            }
+
+            if let Some(name) = arg.strip_prefix("--profile=") {
+                profile_name = Some(name.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--rooms=") {
+                rooms_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(name) = arg.strip_prefix("--room=") {
+                room_name = Some(name.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--strings=") {
+                strings_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--palette=") {
+                palette_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(intensity) = arg.strip_prefix("--pulse-intensity=") {
+                pulse_intensity = match intensity.parse::<f32>() {
+                    Ok(i) if (0.0..=1.0).contains(&i) => i,
+                    _ => {
+                        return Err(String::from(
+                            "pulse intensity must be from 0 to 1",
+                        ));
+                    }
+                };
+                continue;
+            }
+
+            if let Some(secs) = arg.strip_prefix("--attract-delay=") {
+                attract_delay = match secs.parse::<f64>() {
+                    Ok(secs) if (1.0..=3600.0).contains(&secs) => Some(secs),
+                    _ => {
+                        return Err(String::from(
+                            "attract delay must be from 1 to 3600 seconds",
+                        ));
+                    }
+                };
+                continue;
+            }
+
+            if arg == "--no-attract" {
+                attract_delay = None;
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--state=") {
+                state_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--sample=") {
+                sample_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--stems=") {
+                stems_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--bformat=") {
+                bformat_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--control-key=") {
+                control_key_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--render=") {
+                render_midi_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--render-gestures=") {
+                render_gestures_path = Some(path.to_string());
+                continue;
+            }
+
+            if let Some(path) = arg.strip_prefix("--render-out=") {
+                render_output_path = path.to_string();
+                continue;
+            }
+
+            if let Some(hz) = arg.strip_prefix("--render-rate=") {
+                render_sample_rate = match hz.parse::<f64>() {
+                    Ok(hz) if (8000.0..=384_000.0).contains(&hz) => hz,
+                    _ => {
+                        return Err(String::from(
+                            "render sample rate must be from 8000 to 384000 Hz",
+                        ));
+                    }
+                };
+                continue;
+            }
+
+            if arg == "--record" || arg == "--replay" {
+                let Some(path) = args.next()
+                else {
+                    return Err(format!("missing file path for {arg}"));
+                };
+
+                if arg == "--record" {
+                    osc_record_path = Some(path);
+                }
+                else {
+                    osc_replay_path = Some(path);
+                }
+
+                continue;
+            }
 
-        for mut arg in args {
             arg = arg.to_lowercase();
 
             if arg.contains("--auto-start") {
@@ -71,20 +502,792 @@ impl Arguments {
             if arg.contains("--debug") {
                 debug = true;
             }
+
+            if arg.contains("--no-osc-bundles") {
+                osc_bundles = false;
+            }
+
+            if arg.contains("--gesture-tempo") {
+                gesture_tempo = true;
+            }
+
+            if arg.contains("--eme-acks") {
+                eme_acks = true;
+            }
+
+            if arg.contains("--resume") {
+                resume = true;
+            }
+
+            if arg.contains("--standby") {
+                redundancy_role = RedundancyRole::Standby;
+            }
+
+            if arg.contains("--preserve-formants") {
+                preserve_formants = true;
+            }
+
+            if arg.contains("--sample-loop") {
+                sample_looping = true;
+            }
+
+            if arg.contains("--midi-running-status") {
+                midi_running_status = true;
+            }
+
+            if arg.contains("--audio-input") {
+                audio_input = true;
+            }
+
+            if arg.contains("--granular") {
+                granular = true;
+            }
+
+            if arg.contains("--binaural") {
+                binaural = true;
+            }
+
+            if arg.contains("--feedback-suppressor") {
+                feedback_suppressor = true;
+            }
+
+            if let Some(ms) = arg.strip_prefix("--grain-size=") {
+                grain_size_ms = match ms.parse::<f64>() {
+                    Ok(ms)
+                        if (MIN_GRAIN_SIZE_MS..=MAX_GRAIN_SIZE_MS)
+                            .contains(&ms) =>
+                    {
+                        ms
+                    }
+                    _ => {
+                        return Err(format!(
+                            "grain size must be from {MIN_GRAIN_SIZE_MS} to {MAX_GRAIN_SIZE_MS} ms"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(st) = arg.strip_prefix("--grain-pitch-spray=") {
+                grain_pitch_spray = match st.parse::<f64>() {
+                    Ok(st) if (0.0..=MAX_PITCH_SPRAY).contains(&st) => st,
+                    _ => {
+                        return Err(format!(
+                            "grain pitch spray must be from 0 to {MAX_PITCH_SPRAY} semitones"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(mins) = arg.strip_prefix("--intensity-arc=") {
+                arc_minutes = match mins.parse::<f64>() {
+                    Ok(mins) if (1.0..=1440.0).contains(&mins) => Some(mins),
+                    _ => {
+                        return Err(String::from(
+                            "intensity arc must be from 1 to 1440 minutes",
+                        ));
+                    }
+                };
+            }
+
+            if let Some(peak) = arg.strip_prefix("--arc-peak=") {
+                arc_peak = match peak.parse::<f64>() {
+                    Ok(peak) if (0.0..=1.0).contains(&peak) => peak,
+                    _ => {
+                        return Err(String::from(
+                            "arc peak must be from 0 to 1",
+                        ));
+                    }
+                };
+            }
+
+            if let Some(pct) = arg.strip_prefix("--arc-tempo-range=") {
+                let max = MAX_ARC_TEMPO_RANGE * 100.0;
+
+                arc_tempo_range = match pct.parse::<f64>() {
+                    Ok(pct) if (0.0..=max).contains(&pct) => pct * 0.01,
+                    _ => {
+                        return Err(format!(
+                            "arc tempo range must be from 0 to {max} %"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(modes) = arg.strip_prefix("--arc-modes=") {
+                arc_modes = Some(modes.to_string());
+            }
+
+            if let Some(name) = arg.strip_prefix("--noise-color=") {
+                let Some(color) = NoiseColor::from_name(name)
+                else {
+                    return Err(format!("unknown noise color \"{name}\""));
+                };
+
+                noise_color = color;
+            }
+
+            if let Some(n) = arg.strip_prefix("--unison=") {
+                unison.voices = match n.parse::<usize>() {
+                    Ok(n) if (1..=MAX_UNISON_VOICES).contains(&n) => n,
+                    _ => {
+                        return Err(format!(
+                            "unison must be from 1 to {MAX_UNISON_VOICES} voices"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(cents) = arg.strip_prefix("--unison-detune=") {
+                unison.detune_cents = match cents.parse::<f64>() {
+                    Ok(c) if (0.0..=MAX_UNISON_DETUNE_CENTS).contains(&c) => c,
+                    _ => {
+                        return Err(format!(
+                            "unison detune must be from 0 to {MAX_UNISON_DETUNE_CENTS} cents"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(spread) = arg.strip_prefix("--unison-spread=") {
+                unison.spread = match spread.parse::<f64>() {
+                    Ok(s) if (0.0..=1.0).contains(&s) => s,
+                    _ => {
+                        return Err(String::from(
+                            "unison spread must be from 0 to 1",
+                        ));
+                    }
+                };
+            }
+
+            if arg.contains("--unison-phase-lock") {
+                unison.random_phase = false;
+            }
+
+            if let Some(ms) = arg.strip_prefix("--glide=") {
+                glide.time_ms = match ms.parse::<f64>() {
+                    Ok(ms) if (0.0..=MAX_GLIDE_TIME_MS).contains(&ms) => ms,
+                    _ => {
+                        return Err(format!(
+                            "glide must be from 0 to {MAX_GLIDE_TIME_MS} ms"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(name) = arg.strip_prefix("--glide-mode=") {
+                let Some(mode) = GlideMode::from_name(name)
+                else {
+                    return Err(format!(
+                        "unknown glide mode \"{name}\" (expected \"time\" or \"rate\")"
+                    ));
+                };
+
+                glide.mode = mode;
+            }
+
+            if arg.contains("--glide-always") {
+                glide.legato_only = false;
+            }
+
+            // any of the sub's settings enable it
+            if arg.starts_with("--sub") {
+                sub.get_or_insert_with(SubParameters::default);
+            }
+
+            if let Some(name) = arg.strip_prefix("--sub-shape=") {
+                let Some(shape) = SubShape::from_name(name)
+                else {
+                    return Err(format!(
+                        "unknown sub shape \"{name}\" (expected \"sine\" or \"tri\")"
+                    ));
+                };
+
+                sub.get_or_insert_with(SubParameters::default).shape = shape;
+            }
+
+            if let Some(db) = arg.strip_prefix("--sub-level=") {
+                sub.get_or_insert_with(SubParameters::default).level_db =
+                    match db.parse::<f64>() {
+                        Ok(db)
+                            if (MIN_SUB_LEVEL_DB..=MAX_SUB_LEVEL_DB)
+                                .contains(&db) =>
+                        {
+                            db
+                        }
+                        _ => {
+                            return Err(format!(
+                                "sub level must be from {MIN_SUB_LEVEL_DB} to {MAX_SUB_LEVEL_DB} dB"
+                            ));
+                        }
+                    };
+            }
+
+            if let Some(hz) = arg.strip_prefix("--sub-crossover=") {
+                sub.get_or_insert_with(SubParameters::default).crossover_hz =
+                    match hz.parse::<f64>() {
+                        Ok(hz)
+                            if (MIN_SUB_CROSSOVER_HZ..=MAX_SUB_CROSSOVER_HZ)
+                                .contains(&hz) =>
+                        {
+                            hz
+                        }
+                        _ => {
+                            return Err(format!(
+                                "sub crossover must be from {MIN_SUB_CROSSOVER_HZ} to {MAX_SUB_CROSSOVER_HZ} Hz"
+                            ));
+                        }
+                    };
+            }
+
+            if let Some(name) = arg.strip_prefix("--voice-stealing=") {
+                let Some(stealing) = VoiceStealing::from_name(name)
+                else {
+                    return Err(format!(
+                        "unknown voice stealing policy \"{name}\""
+                    ));
+                };
+
+                voice_stealing = stealing;
+            }
+
+            if let Some(n) = arg.strip_prefix("--polyphony=") {
+                max_polyphony = match n.parse::<usize>() {
+                    Ok(n) if (1..=NUM_VOICES as usize).contains(&n) => n,
+                    _ => {
+                        return Err(format!(
+                            "polyphony must be from 1 to {NUM_VOICES} voices"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(route) = arg.strip_prefix("--mod-route=") {
+                if mod_routes.len() == MAX_MOD_ROUTES {
+                    return Err(format!(
+                        "at most {MAX_MOD_ROUTES} modulation routes may be used"
+                    ));
+                }
+
+                mod_routes.push(parse_mod_route(route)?);
+            }
+
+            if let Some(name) = arg.strip_prefix("--mod-lfo-shape=") {
+                let Some(shape) = LfoShape::from_name(name)
+                else {
+                    return Err(format!("unknown LFO shape \"{name}\""));
+                };
+
+                mod_lfo.shape = shape;
+            }
+
+            if let Some(rate) = arg.strip_prefix("--mod-lfo-rate=") {
+                let Some(rate) = LfoRate::parse(rate)
+                else {
+                    return Err(format!(
+                        "LFO rate must be from {MIN_LFO_RATE_HZ} to {MAX_LFO_RATE_HZ} Hz, or a note division such as \"1/4\""
+                    ));
+                };
+
+                mod_lfo.rate = rate;
+            }
+
+            if arg.contains("--mod-lfo-unipolar") {
+                mod_lfo.unipolar = true;
+            }
+
+            if arg.contains("--mod-lfo-retrigger") {
+                mod_lfo.retrigger = true;
+            }
+
+            if arg.contains("--delay-ping-pong") {
+                delay.ping_pong = true;
+            }
+
+            if let Some(time) = arg.strip_prefix("--delay-time=") {
+                delay.time = Some(parse_delay_time(time)?);
+            }
+
+            if let Some(tap) = arg.strip_prefix("--delay-tap=") {
+                if delay.taps.len() == MAX_DELAY_TAPS {
+                    return Err(format!(
+                        "at most {MAX_DELAY_TAPS} delay taps may be used"
+                    ));
+                }
+
+                delay.taps.push(parse_delay_tap(tap)?);
+            }
+
+            if let Some(filter) = arg.strip_prefix("--delay-filter=") {
+                delay.feedback_filter = Some(parse_delay_filter(filter)?);
+            }
+
+            if let Some(freq) = arg.strip_prefix("--dc-filter-freq=") {
+                dc_filter_freq = match freq.parse::<f64>() {
+                    Ok(freq)
+                        if (MIN_DC_FILTER_FREQ_HZ..=MAX_DC_FILTER_FREQ_HZ)
+                            .contains(&freq) =>
+                    {
+                        freq
+                    }
+                    _ => {
+                        return Err(format!(
+                            "DC filter frequency must be from {MIN_DC_FILTER_FREQ_HZ} to {MAX_DC_FILTER_FREQ_HZ} Hz"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(slope) = arg.strip_prefix("--dc-filter-slope=") {
+                dc_filter_slope = slope
+                    .parse::<u32>()
+                    .ok()
+                    .and_then(DCFilterSlope::from_db_per_octave)
+                    .ok_or_else(|| {
+                        format!(
+                            "DC filter slope must be 12 or 24 dB, got \"{slope}\""
+                        )
+                    })?;
+            }
+
+            if arg.contains("--no-limiter") {
+                no_limiter = true;
+            }
+
+            if let Some(db) = arg.strip_prefix("--limiter-ceiling=") {
+                limiter.ceiling_db = match db.parse::<f64>() {
+                    Ok(db)
+                        if (MIN_LIMITER_CEILING_DB..=MAX_LIMITER_CEILING_DB)
+                            .contains(&db) =>
+                    {
+                        db
+                    }
+                    _ => {
+                        return Err(format!(
+                            "limiter ceiling must be from {MIN_LIMITER_CEILING_DB} to {MAX_LIMITER_CEILING_DB} dB"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(ms) = arg.strip_prefix("--limiter-release=") {
+                limiter.release_ms = match ms.parse::<f64>() {
+                    Ok(ms)
+                        if (MIN_LIMITER_RELEASE_MS..=MAX_LIMITER_RELEASE_MS)
+                            .contains(&ms) =>
+                    {
+                        ms
+                    }
+                    _ => {
+                        return Err(format!(
+                            "limiter release must be from {MIN_LIMITER_RELEASE_MS} to {MAX_LIMITER_RELEASE_MS} ms"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(db) = arg.strip_prefix("--true-peak-threshold=") {
+                true_peak_threshold_db = match db.parse::<f64>() {
+                    Ok(db)
+                        if (MIN_TRUE_PEAK_THRESHOLD_DB
+                            ..=MAX_TRUE_PEAK_THRESHOLD_DB)
+                            .contains(&db) =>
+                    {
+                        db
+                    }
+                    _ => {
+                        return Err(format!(
+                            "true peak threshold must be from {MIN_TRUE_PEAK_THRESHOLD_DB} to {MAX_TRUE_PEAK_THRESHOLD_DB} dB"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(tol) = arg.strip_prefix("--pose-tolerance=") {
+                pose_tolerance = match tol.parse::<f64>() {
+                    Ok(tol) if tol > 0.0 && tol <= MAX_POSE_TOLERANCE => tol,
+                    _ => {
+                        return Err(format!(
+                            "pose tolerance must be from 0 to {MAX_POSE_TOLERANCE}"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(mins) = arg.strip_prefix("--wind-down=") {
+                wind_down_minutes = match mins.parse::<f64>() {
+                    Ok(mins) if (1.0..=1440.0).contains(&mins) => Some(mins),
+                    _ => {
+                        return Err(String::from(
+                            "wind down must be from 1 to 1440 minutes",
+                        ));
+                    }
+                };
+            }
+
+            if let Some(host) = arg.strip_prefix("--osc-rx-host=") {
+                osc_rx_host = host.to_string();
+            }
+
+            // a comma-separated list, where each host may have its own port
+            if let Some(hosts) = arg.strip_prefix("--osc-tx-host=") {
+                osc_tx_hosts = hosts.to_string();
+            }
+
+            if let Some(host) = arg.strip_prefix("--state-osc-host=") {
+                state_osc_host = host.to_string();
+            }
+
+            if let Some(port) = arg.strip_prefix("--state-osc-port=") {
+                state_osc_port = Some(port.parse::<u16>().map_err(|e| {
+                    format!("invalid state OSC port \"{port}\": {e}")
+                })?);
+            }
+
+            if let Some(host) = arg.strip_prefix("--tracker-restart-host=") {
+                tracker_restart_host = host.to_string();
+            }
+
+            if let Some(port) = arg.strip_prefix("--tracker-restart-port=") {
+                tracker_restart_port = Some(port.parse::<u16>().map_err(|e| {
+                    format!("invalid tracker restart port \"{port}\": {e}")
+                })?);
+            }
+
+            if arg == "--discover" {
+                discovery_timeout = Some(DEFAULT_DISCOVERY_TIMEOUT);
+            }
+
+            if let Some(secs) = arg.strip_prefix("--discover=") {
+                discovery_timeout =
+                    Some(Duration::from_secs_f64(parse_timeout(secs)?));
+            }
+
+            if let Some(bands) = arg.strip_prefix("--mask-bands=") {
+                mask_paint.num_bands = match bands.parse::<usize>() {
+                    Ok(n) if n > 0 => n,
+                    _ => return Err(format!("invalid mask bands \"{bands}\"")),
+                };
+            }
+
+            if let Some(secs) = arg.strip_prefix("--mask-smoothing=") {
+                mask_paint.smoothing_time = parse_mask_time(secs)?;
+            }
+
+            if let Some(secs) = arg.strip_prefix("--mask-decay=") {
+                mask_paint.decay_time = parse_mask_time(secs)?;
+            }
+
+            if let Some(axis) = arg.strip_prefix("--pitch-axis=") {
+                pitch_axis = match axis {
+                    "x" => Some(0),
+                    "y" => Some(1),
+                    "z" => Some(2),
+                    _ => return Err(format!("unknown pitch axis \"{axis}\"")),
+                };
+            }
+
+            if let Some(range) = arg.strip_prefix("--pitch-range=") {
+                pitch_range = match range.parse::<f64>() {
+                    Ok(r) if r.is_finite() && r > 0.0 => r,
+                    _ => return Err(format!("invalid pitch range \"{range}\"")),
+                };
+            }
+
+            if let Some(size) = arg.strip_prefix("--pitch-block-size=") {
+                pitch_block_size = match size.parse::<usize>() {
+                    Ok(s)
+                        if s.is_power_of_two()
+                            && (MIN_PITCH_SHIFT_BLOCK_SIZE
+                                ..=MAX_PITCH_SHIFT_BLOCK_SIZE)
+                                .contains(&s) =>
+                    {
+                        s
+                    }
+                    _ => {
+                        return Err(format!(
+                            "pitch block size must be a power of two from {MIN_PITCH_SHIFT_BLOCK_SIZE} to {MAX_PITCH_SHIFT_BLOCK_SIZE}"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(gain) = arg.strip_prefix("--input-gain=") {
+                input_gain_db = match gain.parse::<f64>() {
+                    Ok(g)
+                        if (MIN_INPUT_GAIN_DB..=MAX_INPUT_GAIN_DB)
+                            .contains(&g) =>
+                    {
+                        g
+                    }
+                    _ => {
+                        return Err(format!(
+                            "input gain must be from {MIN_INPUT_GAIN_DB} to {MAX_INPUT_GAIN_DB} dB"
+                        ));
+                    }
+                };
+            }
+
+            // the threshold is learned unless it is given
+            if arg.contains("--input-gate") {
+                input_gate.get_or_insert(GateThreshold::Learned {
+                    offset_db: DEFAULT_GATE_OFFSET_DB,
+                });
+            }
+
+            if let Some(db) = arg.strip_prefix("--input-gate-threshold=") {
+                input_gate = match db.parse::<f64>() {
+                    Ok(db) if (MIN_GATE_REDUCTION_DB..=0.0).contains(&db) => {
+                        Some(GateThreshold::Fixed { threshold_db: db })
+                    }
+                    _ => {
+                        return Err(format!(
+                            "input gate threshold must be from {MIN_GATE_REDUCTION_DB} to 0 dB"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(peer) = arg.strip_prefix("--rtp-midi=") {
+                rtp_midi_peer =
+                    Some(parse_destination(peer, DEFAULT_RTP_MIDI_PORT)?);
+            }
+
+            if let Some(peer) = arg.strip_prefix("--redundancy-peer=") {
+                redundancy_peer =
+                    Some(parse_destination(peer, DEFAULT_REDUNDANCY_PORT)?);
+            }
+
+            if let Some(port) = arg.strip_prefix("--redundancy-port=") {
+                redundancy_port = port.parse::<u16>().map_err(|e| {
+                    format!("invalid redundancy port \"{port}\": {e}")
+                })?;
+            }
+
+            if let Some(frames) = arg.strip_prefix("--jitter-buffer=") {
+                jitter_buffer_frames = match frames.parse::<usize>() {
+                    Ok(n) if n <= MAX_JITTER_BUFFER_FRAMES => n,
+                    _ => {
+                        return Err(format!(
+                            "jitter buffer must be from 0 to {MAX_JITTER_BUFFER_FRAMES} frames"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(secs) = arg.strip_prefix("--osc-stale-timeout=") {
+                osc_stale_timeout = parse_timeout(secs)?;
+            }
+
+            if let Some(secs) = arg.strip_prefix("--osc-dead-timeout=") {
+                osc_dead_timeout = parse_timeout(secs)?;
+            }
+
+            if let Some(name) = arg.strip_prefix("--midi-protocol=") {
+                let Some(protocol) = MIDIProtocol::from_name(name)
+                else {
+                    return Err(format!("unknown MIDI protocol \"{name}\""));
+                };
+
+                midi_protocol = protocol;
+            }
+
+            if let Some(name) = arg.strip_prefix("--mtc=") {
+                let Some(rate) = FrameRate::from_name(name)
+                else {
+                    return Err(format!(
+                        "unknown MTC frame rate \"{name}\", expected 24, 25, 29.97 or 30"
+                    ));
+                };
+
+                mtc_rate = Some(rate);
+            }
+
+            if let Some(name) = arg.strip_prefix("--osc-transport=") {
+                let Some(kind) = OSCTransportKind::from_name(name)
+                else {
+                    return Err(format!("unknown OSC transport \"{name}\""));
+                };
+
+                osc_transport = kind;
+            }
+        }
+
+        if bformat_path.is_some() && stems_path.is_none() {
+            return Err(String::from(
+                "B-format is recorded alongside the stems, so needs --stems",
+            ));
+        }
+
+        if rtp_midi_peer.is_some() && midi_protocol == MIDIProtocol::UMP {
+            return Err(String::from(
+                "RTP-MIDI only carries MIDI 1.0, so cannot be used with UMP",
+            ));
         }
 
+        if osc_dead_timeout < osc_stale_timeout {
+            return Err(String::from(
+                "OSC dead timeout must not be shorter than the stale timeout",
+            ));
+        }
+
+        let intensity_arc = match arc_minutes {
+            Some(mins) => {
+                let arc =
+                    IntensityArc::new(mins * 60.0, arc_peak, arc_tempo_range);
+
+                match &arc_modes {
+                    Some(modes) => Some(arc.with_mode_names(modes)?),
+                    None => Some(arc),
+                }
+            }
+            None => None,
+        };
+
+        let tx_port = unsafe { tx_port.unwrap_unchecked() };
+        let osc_tx_destinations = osc_tx_hosts
+            .split(',')
+            .map(|dest| parse_destination(dest.trim(), tx_port))
+            .collect::<Result<Vec<_>, _>>()?;
+
         unsafe {
             Ok(Self {
                 osc_rx_port: rx_port.unwrap_unchecked(),
-                osc_tx_port: tx_port.unwrap_unchecked(),
+                osc_tx_port: tx_port,
+                osc_rx_host,
+                osc_tx_destinations,
                 auto_start_send,
                 show_state_data,
                 auto_change_mode,
                 print,
                 debug,
+                osc_map_path,
+                tracking_config_path,
+                profiles_path,
+                mapping,
+                profile_name,
+                rooms_path,
+                room_name,
+                strings_path,
+                palette_path,
+                pulse_intensity,
+                attract_delay,
+                state_path,
+                resume,
+                osc_record_path,
+                control_key_path,
+                osc_replay_path,
+                osc_bundles,
+                osc_transport,
+                gesture_tempo,
+                eme_acks,
+                osc_stale_timeout,
+                osc_dead_timeout,
+                state_osc_destination: state_osc_port
+                    .map(|port| (state_osc_host, port)),
+                mask_paint,
+                pitch_axis,
+                pitch_range,
+                preserve_formants,
+                pitch_block_size,
+                jitter_buffer_frames,
+                tracker_restart_destination: tracker_restart_port
+                    .map(|port| (tracker_restart_host, port)),
+                discovery_timeout,
+                redundancy_peer,
+                redundancy_port,
+                redundancy_role,
+                render_midi_path,
+                render_gestures_path,
+                render_output_path,
+                render_sample_rate,
+                sample_path,
+                sample_looping,
+                stems_path,
+                bformat_path,
+                binaural,
+                rtp_midi_peer,
+                midi_protocol,
+                midi_running_status,
+                mtc_rate,
+                audio_input,
+                input_gain_db,
+                input_gate,
+                feedback_suppressor,
+                granular,
+                grain_size_ms,
+                grain_pitch_spray,
+                intensity_arc,
+                noise_color,
+                unison,
+                glide,
+                sub,
+                voice_stealing,
+                max_polyphony,
+                mod_routes,
+                mod_lfo,
+                delay,
+                dc_filter_freq,
+                dc_filter_slope,
+                limiter: (!no_limiter).then_some(limiter),
+                true_peak_threshold_db,
+                pose_tolerance,
+                wind_down_minutes,
 
                 _pd: PhantomData,
             })
         }
     }
 }
+
+fn parse_timeout(secs: &str) -> Result<f64, String> {
+    match secs.parse::<f64>() {
+        Ok(t) if t.is_finite() && t > 0.0 => Ok(t),
+        _ => Err(format!("invalid timeout \"{secs}\"")),
+    }
+}
+
+/// Parses a spectral mask time, which may be `0` to disable smoothing or
+/// decay.
+fn parse_mask_time(secs: &str) -> Result<f64, String> {
+    match secs.parse::<f64>() {
+        Ok(t) if t.is_finite() && t >= 0.0 => Ok(t),
+        _ => Err(format!("invalid mask time \"{secs}\"")),
+    }
+}
+
+/// Parses a destination (such as an OSC target) of the form `host`,
+/// `host:port` or `[ipv6]:port`. If no port is given, `default_port` is used.
+fn parse_destination(
+    dest: &str,
+    default_port: u16,
+) -> Result<(String, u16), String> {
+    let (host, port) = if let Some(rest) = dest.strip_prefix('[') {
+        let Some((host, port)) = rest.split_once(']')
+        else {
+            return Err(format!("invalid destination \"{dest}\""));
+        };
+
+        (host, port.strip_prefix(':'))
+    }
+    // more than one colon means a bare IPv6 address, which has no port
+    else if dest.matches(':').count() == 1 {
+        let (host, port) = unsafe { dest.split_once(':').unwrap_unchecked() };
+        (host, Some(port))
+    }
+    else {
+        (dest, None)
+    };
+
+    if host.is_empty() {
+        return Err(format!("missing host in destination \"{dest}\""));
+    }
+
+    let port = match port {
+        Some(port) => port
+            .parse::<u16>()
+            .map_err(|e| format!("invalid port in \"{dest}\": {e}"))?,
+        None => default_port,
+    };
+
+    Ok((host.to_string(), port))
+}
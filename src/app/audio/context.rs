@@ -1,8 +1,12 @@
 //! Contextual audio data.
 
 use super::*;
+use crate::app::args::Arguments;
 use crate::app::audio::VoiceEvent;
-use std::sync::{mpsc::Receiver, Arc};
+use std::sync::{
+    mpsc::{self, Receiver},
+    Arc,
+};
 
 /// TODO this is a bit of a weird intermediate struct used for building
 /// and holding data, which could could be extracted elsewhere...
@@ -13,6 +17,96 @@ pub struct AudioContext {
     pub sample_rate: f64,
     pub spectral_mask_output: Option<triple_buffer::Output<SpectralMask>>,
     pub reso_bank_data_output: Option<triple_buffer::Output<ResoBankData>>,
+    /// The analyzed output spectrum, in decibels per bin.
+    pub spectrum_input: Option<triple_buffer::Input<Vec<f64>>>,
+    /// The envelope of each of the output's perceptual bands.
+    pub band_energy_input: Option<triple_buffer::Input<Vec<f64>>>,
+    /// The DSP load of the audio callback.
+    pub dsp_load_input: Option<triple_buffer::Input<DspLoad>>,
+    /// The audio file which is played for each note, if any.
+    pub sample_path: Option<String>,
+    pub sample_looping: bool,
+    /// The output stream's side of the audio input, if it is enabled.
+    pub audio_input: Option<AudioInput>,
+    /// Whether the granular engine is enabled.
+    pub granular: bool,
+    /// The spectral slope of the noise exciter.
+    pub noise_color: NoiseColor,
+    /// How each voice's generators are stacked.
+    pub unison: UnisonParameters,
+    /// How each new voice glides from the previous voice's pitch.
+    pub glide: GlideParameters,
+    /// The settings of the sub oscillator, if it is enabled.
+    pub sub: Option<SubParameters>,
+    /// How voices are chosen to be stolen.
+    pub voice_stealing: VoiceStealing,
+    /// The most voices which may sound at once.
+    pub max_polyphony: usize,
+    /// The routes of the effects' modulation matrix.
+    pub mod_routes: Vec<ModRoute>,
+    /// The settings of the modulation LFO.
+    pub mod_lfo: LfoParameters,
+    /// How the delay is set up.
+    pub delay: DelayParameters,
+    /// The cutoff of the output's DC and subsonic filter.
+    pub dc_filter_freq: f64,
+    pub dc_filter_slope: DCFilterSlope,
+    /// The settings of the output limiter, if it is enabled.
+    pub limiter: Option<LimiterParameters>,
+    /// The output's feedback suppressor, if it is enabled.
+    pub feedback: Option<FeedbackSuppression>,
+    /// The true-peak meter of the output.
+    pub true_peak: Option<TruePeakMeter>,
+    /// The stem recorder of the output, if it is enabled.
+    pub stems: Option<StemTaps>,
+    /// Whether the output starts rendered binaurally, for headphones.
+    pub binaural: bool,
     pub voice_event_sender: Sender<VoiceEvent>,
     pub voice_event_receiver: Option<Receiver<VoiceEvent>>,
 }
+
+impl AudioContext {
+    /// Returns a context which sets up the voices and effects as in `args`,
+    /// without any analysis, input, metering or recording.
+    pub fn from_args(
+        args: &Arguments,
+        sample_rate: f64,
+        voice_event_sender: Sender<VoiceEvent>,
+        voice_event_receiver: Receiver<VoiceEvent>,
+    ) -> Self {
+        // notes are sent over the message channels instead
+        let (_, note_channel_receiver) = mpsc::channel();
+
+        Self {
+            note_channel_receiver,
+            sample_rate,
+            spectral_mask_output: None,
+            reso_bank_data_output: None,
+            spectrum_input: None,
+            band_energy_input: None,
+            dsp_load_input: None,
+            sample_path: args.sample_path.clone(),
+            sample_looping: args.sample_looping,
+            audio_input: None,
+            granular: args.granular,
+            noise_color: args.noise_color,
+            unison: args.unison,
+            glide: args.glide,
+            sub: args.sub,
+            voice_stealing: args.voice_stealing,
+            max_polyphony: args.max_polyphony,
+            mod_routes: args.mod_routes.clone(),
+            mod_lfo: args.mod_lfo,
+            delay: args.delay.clone(),
+            dc_filter_freq: args.dc_filter_freq,
+            dc_filter_slope: args.dc_filter_slope,
+            limiter: args.limiter,
+            feedback: None,
+            true_peak: None,
+            stems: None,
+            binaural: args.binaural,
+            voice_event_sender,
+            voice_event_receiver: Some(voice_event_receiver),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Feedback suppression of the output, for installations where microphones
+//! and speakers share a space. The active notches are shared with the UI
+//! thread so that they can be listed.
+
+use super::*;
+use crate::dsp::spectral::feedback::MAX_FEEDBACK_NOTCHES;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The feedback suppressor's notches, shared with the UI thread.
+#[derive(Debug, Default)]
+pub struct FeedbackControl {
+    freqs: [AtomicF64; MAX_FEEDBACK_NOTCHES],
+    depths_db: [AtomicF64; MAX_FEEDBACK_NOTCHES],
+    num_notches: AtomicUsize,
+    /// Set by the UI thread to remove all of the notches.
+    clear: AtomicBool,
+}
+
+impl FeedbackControl {
+    /// The active notches, from oldest to newest.
+    pub fn notches(&self) -> Vec<FeedbackNotch> {
+        let num_notches = self.num_notches.lr();
+
+        self.freqs
+            .iter()
+            .zip(&self.depths_db)
+            .take(num_notches)
+            .map(|(freq, depth_db)| FeedbackNotch {
+                freq: freq.lr(),
+                depth_db: depth_db.lr(),
+            })
+            .collect()
+    }
+
+    /// Asks the audio thread to remove all of the notches.
+    pub fn request_clear(&self) {
+        self.clear.sr(true);
+    }
+}
+
+/// The audio thread's side of the feedback suppressor.
+#[derive(Debug)]
+pub struct FeedbackSuppression {
+    suppressor: FeedbackSuppressor,
+    control: Arc<FeedbackControl>,
+}
+
+impl FeedbackSuppression {
+    pub fn new(control: Arc<FeedbackControl>, sample_rate: f64) -> Self {
+        Self { suppressor: FeedbackSuppressor::new(sample_rate), control }
+    }
+
+    /// Looks for ringing in the analyzed output `spectrum`.
+    pub fn analyze(&mut self, spectrum: &[f64]) {
+        self.suppressor.analyze(spectrum);
+    }
+
+    /// Notches the main channels of `buffer`.
+    pub fn process(&mut self, buffer: &mut AudioBuffer) {
+        if self.control.clear.swap(false, Ordering::Relaxed) {
+            self.suppressor.clear();
+        }
+
+        if self.suppressor.take_updated() {
+            self.update_control();
+        }
+
+        if !self.suppressor.is_active() {
+            return;
+        }
+
+        let num_channels = buffer.channels();
+
+        for frame in 0..buffer.len_frames() {
+            let idx = frame * num_channels;
+
+            (buffer[idx], buffer[idx + 1]) = self
+                .suppressor
+                .process_stereo(buffer[idx], buffer[idx + 1]);
+        }
+    }
+
+    fn update_control(&self) {
+        let mut num_notches = 0;
+
+        for (i, notch) in self.suppressor.notches().enumerate() {
+            self.control.freqs[i].sr(notch.freq);
+            self.control.depths_db[i].sr(notch.depth_db);
+            num_notches += 1;
+        }
+
+        self.control.num_notches.sr(num_notches);
+    }
+}
@@ -0,0 +1,279 @@
+//! Control of the internal effects from outside the audio thread, such as
+//! by OSC.
+
+use super::*;
+use crate::dsp::distortion::decimation::{
+    MAX_BIT_DEPTH, MAX_DOWNSAMPLE_FACTOR, MIN_BIT_DEPTH,
+};
+use crate::dsp::distortion::exciter::MAX_EXCITER_DRIVE_DB;
+use crate::dsp::dynamics::transient::MAX_TRANSIENT_GAIN_DB;
+use room_eq::EqBands;
+
+/// The lowest master gain which may be set, in decibels.
+pub const MIN_MASTER_GAIN_DB: f64 = -60.0;
+/// The highest master gain which may be set, in decibels.
+pub const MAX_MASTER_GAIN_DB: f64 = 12.0;
+/// The shortest delay time which may be set, in milliseconds.
+pub const MIN_DELAY_TIME_MS: f64 = 1.0;
+/// The longest delay time which may be set, in milliseconds. This is also
+/// the length of the delay's buffer.
+pub const MAX_DELAY_TIME_MS: f64 = 2000.0;
+/// The feedback of the delay.
+pub const DELAY_FEEDBACK: f64 = 0.35;
+/// The smoothing time of gain and mix changes.
+pub const FX_SMOOTHING_TIME_MS: f64 = 20.0;
+/// The lowest cutoff of the delay's feedback filter.
+pub const MIN_DELAY_FILTER_FREQ_HZ: f64 = 20.0;
+/// The highest cutoff of the delay's feedback filter.
+pub const MAX_DELAY_FILTER_FREQ_HZ: f64 = 20000.0;
+
+/// How the delay is set up at startup.
+#[derive(Clone, Debug, Default)]
+pub struct DelayParameters {
+    pub ping_pong: bool,
+    /// The delay time, if it isn't the default.
+    pub time: Option<DelayTime>,
+    /// Extra taps, which are mixed into the delay's output.
+    pub taps: Vec<DelayTap>,
+    /// The filter of the feedback path, if it is filtered.
+    pub feedback_filter: Option<BiquadParams>,
+}
+
+impl DelayParameters {
+    /// Applies the parameters to `delay`.
+    pub fn apply(&self, delay: &mut StereoDelay) {
+        delay.ping_pong(self.ping_pong);
+        delay.set_feedback_filter(self.feedback_filter.as_ref());
+
+        if let Some(time) = self.time {
+            delay.set_time(time);
+        }
+
+        delay.clear_taps();
+
+        for tap in &self.taps {
+            delay.add_tap(*tap);
+        }
+    }
+}
+
+/// Parses a delay time in milliseconds (e.g. `"375"`), or a note division
+/// which follows the tempo (e.g. `"1/8."`).
+///
+/// # Errors
+///
+/// Returns an error if `time` is neither, or is out of range.
+pub fn parse_delay_time(time: &str) -> Result<DelayTime, String> {
+    if time.contains('/') {
+        return note_division_beats(time)
+            .map(DelayTime::Beats)
+            .ok_or_else(|| format!("invalid note division \"{time}\""));
+    }
+
+    match time.parse::<f64>() {
+        Ok(ms) if (MIN_DELAY_TIME_MS..=MAX_DELAY_TIME_MS).contains(&ms) => {
+            Ok(DelayTime::Secs(ms / 1000.0))
+        }
+        _ => Err(format!(
+            "delay time must be from {MIN_DELAY_TIME_MS} to {MAX_DELAY_TIME_MS} ms, or a note division such as \"1/8\""
+        )),
+    }
+}
+
+/// Parses a tap in the form `<time>:<level>[:<pan>]`, e.g. `"1/8:0.5:-1"`.
+///
+/// # Errors
+///
+/// Returns an error if any part of the tap is invalid or out of range.
+pub fn parse_delay_tap(tap: &str) -> Result<DelayTap, String> {
+    let parts: Vec<&str> = tap.split(':').collect();
+
+    let (time, level, pan) = match parts.as_slice() {
+        [time, level] => (time, level, None),
+        [time, level, pan] => (time, level, Some(pan)),
+        _ => {
+            return Err(format!(
+                "invalid delay tap \"{tap}\" (expected <time>:<level>[:<pan>])"
+            ))
+        }
+    };
+
+    let level = match level.parse::<f64>() {
+        Ok(level) if (0.0..=1.0).contains(&level) => level,
+        _ => return Err(String::from("delay tap level must be from 0 to 1")),
+    };
+
+    let pan = match pan.map(|pan| pan.parse::<f64>()) {
+        None => 0.0,
+        Some(Ok(pan)) if (-1.0..=1.0).contains(&pan) => pan,
+        _ => return Err(String::from("delay tap pan must be from -1 to 1")),
+    };
+
+    Ok(DelayTap { time: parse_delay_time(time)?, level, pan })
+}
+
+/// Parses the delay's feedback filter in the form `<type>:<freq>`, where
+/// the type is `"lowpass"` or `"highpass"`, e.g. `"lowpass:3000"`.
+///
+/// # Errors
+///
+/// Returns an error if the type is unknown or the frequency is out of
+/// range.
+pub fn parse_delay_filter(filter: &str) -> Result<BiquadParams, String> {
+    let Some((filter_type, freq)) = filter.split_once(':')
+    else {
+        return Err(format!(
+            "invalid delay filter \"{filter}\" (expected <type>:<freq>)"
+        ));
+    };
+
+    let filter_type = match filter_type.to_lowercase().as_str() {
+        "lowpass" => FilterType::Lowpass,
+        "highpass" => FilterType::Highpass,
+        _ => {
+            return Err(format!(
+                "unknown delay filter type \"{filter_type}\" (expected \"lowpass\" or \"highpass\")"
+            ))
+        }
+    };
+
+    let (min, max) = (MIN_DELAY_FILTER_FREQ_HZ, MAX_DELAY_FILTER_FREQ_HZ);
+
+    match freq.parse::<f64>() {
+        Ok(freq) if (min..=max).contains(&freq) => Ok(BiquadParams {
+            freq,
+            filter_type,
+            ..Default::default()
+        }),
+        _ => Err(format!(
+            "delay filter frequency must be from {min} to {max} Hz"
+        )),
+    }
+}
+
+/// Messages used to control the internal effects.
+// NOTE(jamie): the EQ bands aren't boxed, as the messages are received (and
+// dropped) on the audio thread, which shouldn't deallocate.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Copy, Debug)]
+pub enum FXMessage {
+    /// Sets the gain of the output (excluding the metronome) in decibels.
+    SetMasterGain(f64),
+    /// Sets the dry/wet mix of the spectral filter, from `0.0` to `1.0`.
+    SetSpectralMix(f64),
+    /// Sets the delay time in milliseconds.
+    SetDelayTime(f64),
+    /// Sets the level of the delay, from `0.0` to `1.0`.
+    SetDelayMix(f64),
+    /// Sets the gain of the transient shaper's attacks in decibels.
+    SetTransientAttack(f64),
+    /// Sets the gain of the transient shaper's sustains in decibels.
+    SetTransientSustain(f64),
+    /// Sets the drive of the exciter in decibels.
+    SetExciterDrive(f64),
+    /// Sets the level of the exciter's harmonics, from `0.0` to `1.0`.
+    SetExciterMix(f64),
+    /// Sets the bit depth of the bitcrusher.
+    SetCrushBits(f64),
+    /// Sets how many samples the bitcrusher holds each sample for.
+    SetCrushDownsample(f64),
+    /// Sets how far the bitcrusher's downsampling is randomly varied, from
+    /// `0.0` to `1.0`.
+    SetCrushJitter(f64),
+    /// Sets the level of the crushed signal, from `0.0` to `1.0`.
+    SetCrushMix(f64),
+    /// Replaces the bands of the master EQ.
+    SetEqBands(EqBands),
+    /// Fades the whole output, including the metronome, to `gain_db` over
+    /// `fade_secs`. Used to wind down after a long time without interaction.
+    FadeOutput { gain_db: f64, fade_secs: f64 },
+    /// Sets whether the audio thread sleeps, skipping all of its processing
+    /// so that the output is silent.
+    SetAsleep(bool),
+    /// Sets whether the main channels are rendered binaurally, for
+    /// monitoring on headphones.
+    SetBinaural(bool),
+}
+
+impl FXMessage {
+    /// The names of each parameter, as used by
+    /// [`from_name()`](Self::from_name).
+    pub const NAMES: [&'static str; 13] = [
+        "master_gain",
+        "spectral_mix",
+        "delay_time",
+        "delay_mix",
+        "transient_attack",
+        "transient_sustain",
+        "exciter_drive",
+        "exciter_mix",
+        "crush_bits",
+        "crush_downsample",
+        "crush_jitter",
+        "crush_mix",
+        "binaural",
+    ];
+
+    /// Creates a message which sets the parameter named `name` to `value`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `name` isn't a known parameter, or if `value` is
+    /// out of range for it.
+    pub fn from_name(name: &str, value: f64) -> Result<Self, String> {
+        let (msg, min, max) = match name {
+            "master_gain" => (
+                Self::SetMasterGain(value),
+                MIN_MASTER_GAIN_DB,
+                MAX_MASTER_GAIN_DB,
+            ),
+            "spectral_mix" => (Self::SetSpectralMix(value), 0.0, 1.0),
+            "delay_time" => (
+                Self::SetDelayTime(value),
+                MIN_DELAY_TIME_MS,
+                MAX_DELAY_TIME_MS,
+            ),
+            "delay_mix" => (Self::SetDelayMix(value), 0.0, 1.0),
+            "transient_attack" => (
+                Self::SetTransientAttack(value),
+                -MAX_TRANSIENT_GAIN_DB,
+                MAX_TRANSIENT_GAIN_DB,
+            ),
+            "transient_sustain" => (
+                Self::SetTransientSustain(value),
+                -MAX_TRANSIENT_GAIN_DB,
+                MAX_TRANSIENT_GAIN_DB,
+            ),
+            "exciter_drive" => {
+                (Self::SetExciterDrive(value), 0.0, MAX_EXCITER_DRIVE_DB)
+            }
+            "exciter_mix" => (Self::SetExciterMix(value), 0.0, 1.0),
+            "crush_bits" => {
+                (Self::SetCrushBits(value), MIN_BIT_DEPTH, MAX_BIT_DEPTH)
+            }
+            "crush_downsample" => (
+                Self::SetCrushDownsample(value),
+                1.0,
+                MAX_DOWNSAMPLE_FACTOR,
+            ),
+            "crush_jitter" => (Self::SetCrushJitter(value), 0.0, 1.0),
+            "crush_mix" => (Self::SetCrushMix(value), 0.0, 1.0),
+            // anything from a half up selects binaural monitoring
+            "binaural" => (Self::SetBinaural(value >= 0.5), 0.0, 1.0),
+            _ => {
+                return Err(format!(
+                    "unknown effect parameter \"{name}\" (expected one of: {})",
+                    Self::NAMES.join(", ")
+                ))
+            }
+        };
+
+        if !(min..=max).contains(&value) {
+            return Err(format!(
+                "{name} must be between {min} and {max}, got {value}"
+            ));
+        }
+
+        Ok(msg)
+    }
+}
@@ -0,0 +1,178 @@
+//! Live audio input, which is captured by its own stream and mixed into the
+//! output before the FX, so it is processed like the voices.
+
+use super::*;
+use crossbeam_channel::{bounded, Receiver as CCReceiver, Sender as CCSender};
+use std::sync::atomic::AtomicBool;
+
+/// The number of frames which may be queued between the input and output
+/// streams. Frames captured while the queue is full are dropped.
+const INPUT_QUEUE_FRAMES: usize = BUFFER_SIZE * 8;
+/// How quickly the input meter falls, in milliseconds.
+const METER_RELEASE_TIME_MS: f64 = 300.0;
+/// How long gain changes are smoothed over, in milliseconds.
+const GAIN_SMOOTHING_TIME_MS: f64 = 20.0;
+
+/// The lowest and highest input gains, in decibels.
+pub const MIN_INPUT_GAIN_DB: f64 = -60.0;
+pub const MAX_INPUT_GAIN_DB: f64 = 24.0;
+
+/// Control of the audio input, shared with the UI thread.
+#[derive(Debug, Default)]
+pub struct AudioInputControl {
+    /// Whether the input is mixed into the output.
+    pub enabled: AtomicBool,
+    /// The input gain, in decibels.
+    pub gain_db: AtomicF64,
+    /// The metered peak level of the input after its gain, in decibels.
+    pub level_db: AtomicF64,
+    /// Whether the input is gated, so that the room's noise doesn't excite
+    /// the effects between phrases.
+    pub gate_enabled: AtomicBool,
+    /// Whether the gate is learning the room's noise floor. The input is
+    /// passed through ungated while it learns.
+    pub gate_learning: AtomicBool,
+    /// The learned noise floor, in decibels.
+    pub gate_floor_db: AtomicF64,
+    /// The gain of the gate, in decibels.
+    pub gate_gain_db: AtomicF64,
+}
+
+/// The input stream's model, which forwards captured frames to the output
+/// stream.
+pub struct InputCapture {
+    sender: CCSender<[f64; 2]>,
+}
+
+/// The input stream's capture callback.
+pub fn capture(
+    model: &mut InputCapture,
+    buffer: &nannou_audio::Buffer<f64>,
+) {
+    for frame in buffer.frames() {
+        let left = frame[0];
+        let right = frame.get(1).copied().unwrap_or(left);
+
+        // if the output stream has stalled, the newest frames are dropped
+        _ = model.sender.try_send([left, right]);
+    }
+}
+
+/// The output stream's side of the audio input.
+#[derive(Debug)]
+pub struct AudioInput {
+    receiver: CCReceiver<[f64; 2]>,
+    control: Arc<AudioInputControl>,
+
+    gain: Smoother<f64>,
+    gate: NoiseGate,
+    peak: f64,
+    meter_release_coef: f64,
+}
+
+impl AudioInput {
+    /// Returns the input stream's model, and the output stream's side of the
+    /// input, whose gate uses `gate_threshold`.
+    pub fn new(
+        control: Arc<AudioInputControl>,
+        gate_threshold: GateThreshold,
+        sample_rate: f64,
+    ) -> (InputCapture, Self) {
+        let (sender, receiver) = bounded(INPUT_QUEUE_FRAMES);
+        let gain = db_to_level(control.gain_db.lr());
+
+        let mut gate = NoiseGate::new(sample_rate);
+        gate.set_threshold(gate_threshold);
+        control.gate_floor_db.sr(MINUS_INFINITY_DB);
+
+        let input = Self {
+            receiver,
+            control,
+
+            gain: Smoother::new(GAIN_SMOOTHING_TIME_MS, gain, sample_rate),
+            gate,
+            peak: 0.0,
+            meter_release_coef: (-1000.0
+                / (METER_RELEASE_TIME_MS * sample_rate))
+                .exp(),
+        };
+
+        (InputCapture { sender }, input)
+    }
+
+    /// Whether the input is mixed into the output.
+    pub fn is_enabled(&self) -> bool {
+        self.control.enabled.lr()
+    }
+
+    /// Adds queued input to the interleaved stereo `buffer` from
+    /// `block_start` to `block_end`, and updates the input meter.
+    pub fn process_block(
+        &mut self,
+        buffer: &mut [f64],
+        block_start: usize,
+        block_end: usize,
+    ) {
+        if !self.is_enabled() {
+            // the queue is drained so that stale input isn't played later
+            while self.receiver.try_recv().is_ok() {}
+            self.gate.reset();
+            return;
+        }
+
+        let gate_enabled = self.control.gate_enabled.lr();
+        self.gate.set_learning(self.control.gate_learning.lr());
+
+        let gain_db = self
+            .control
+            .gain_db
+            .lr()
+            .clamp(MIN_INPUT_GAIN_DB, MAX_INPUT_GAIN_DB);
+        self.gain.set_target_value(db_to_level(gain_db));
+
+        for sample_idx in block_start..block_end {
+            // an empty queue (e.g. at startup) is treated as silence
+            let [left, right] = self.receiver.try_recv().unwrap_or([0.0; 2]);
+            let gain = self.gain.next();
+
+            let (left, right) = (left * gain, right * gain);
+
+            // the input is metered before the gate, so the room's level is
+            // shown while the gate is closed
+            let level = left.abs().max(right.abs());
+            self.peak = if level > self.peak {
+                level
+            }
+            else {
+                self.peak * self.meter_release_coef
+            };
+
+            let (left, right) = if gate_enabled {
+                self.gate.process_stereo(left, right)
+            }
+            else {
+                (left, right)
+            };
+
+            buffer[sample_idx * 2] += left;
+            buffer[sample_idx * 2 + 1] += right;
+        }
+
+        self.control
+            .level_db
+            .sr(level_to_db(self.peak).max(MINUS_INFINITY_DB));
+
+        if let Some(floor_db) = self.gate.noise_floor_db() {
+            self.control
+                .gate_floor_db
+                .sr(floor_db.max(MINUS_INFINITY_DB));
+        }
+
+        self.control.gate_gain_db.sr(if gate_enabled {
+            self.gate.gain_db().max(MINUS_INFINITY_DB)
+        }
+        else {
+            0.0
+        });
+    }
+}
@@ -0,0 +1,179 @@
+//! Metronome and count-in, driven by the internal clock.
+
+use super::*;
+use crate::dsp::ClickGenerator;
+
+/// The default number of beats per bar for the metronome.
+pub const DEFAULT_BEATS_PER_BAR: u32 = 4;
+/// The default (linear) level of the metronome.
+pub const DEFAULT_METRONOME_LEVEL: f64 = 0.5;
+
+/// The frequency of the click on the first beat of each bar.
+const ACCENT_CLICK_FREQ_HZ: f64 = 2000.0;
+/// The frequency of the click on every other beat.
+const BEAT_CLICK_FREQ_HZ: f64 = 1000.0;
+/// How much quieter unaccented beats are compared to accented ones.
+const UNACCENTED_GAIN: f64 = 0.6;
+
+/// Messages used to control the metronome from outside the audio thread.
+#[derive(Clone, Copy, Debug)]
+pub enum MetronomeMessage {
+    /// Enables or disables the metronome.
+    Enable(bool),
+    /// Plays `num_bars` bars of clicks, regardless of whether the metronome
+    /// is enabled or not.
+    CountIn(u32),
+    /// Sets the tempo of the metronome in BPM.
+    SetTempo(f64),
+    /// Sets the number of beats in each bar.
+    SetBeatsPerBar(u32),
+    /// Sets the (linear) level of the metronome.
+    SetLevel(f64),
+    /// Re-aligns the metronome so that the next sample is a downbeat.
+    Reset,
+}
+
+/// A metronome which produces clicks in time with the internal clock, with
+/// the first beat of each bar accented.
+///
+/// If the output buffer has more channels than [`NUM_CHANNELS`], the clicks
+/// are written to the first channel after the main outputs (a "cue" output),
+/// otherwise they are mixed into the main outputs.
+#[derive(Debug, Clone)]
+pub struct Metronome {
+    click: ClickGenerator,
+
+    bpm: f64,
+    beats_per_bar: u32,
+    level: f64,
+
+    enabled: bool,
+    count_in_beats: u32,
+
+    samples_until_beat: f64,
+    beat_idx: u32,
+
+    sample_rate: f64,
+}
+
+impl Metronome {
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            click: ClickGenerator::new(sample_rate),
+
+            bpm: DEFAULT_BPM,
+            beats_per_bar: DEFAULT_BEATS_PER_BAR,
+            level: DEFAULT_METRONOME_LEVEL,
+
+            enabled: false,
+            count_in_beats: 0,
+
+            samples_until_beat: 0.0,
+            beat_idx: 0,
+
+            sample_rate,
+        }
+    }
+
+    /// Handles a single control message.
+    pub fn handle_message(&mut self, message: MetronomeMessage) {
+        match message {
+            MetronomeMessage::Enable(enabled) => {
+                if enabled && !self.is_ticking() {
+                    self.reset();
+                }
+
+                self.enabled = enabled;
+            }
+            MetronomeMessage::CountIn(num_bars) => {
+                self.reset();
+                self.count_in_beats = num_bars * self.beats_per_bar;
+            }
+            MetronomeMessage::SetTempo(bpm) => {
+                self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+            }
+            MetronomeMessage::SetBeatsPerBar(beats) => {
+                self.beats_per_bar = beats.max(1);
+                self.beat_idx %= self.beats_per_bar;
+            }
+            MetronomeMessage::SetLevel(level) => {
+                self.level = level.clamp(0.0, 1.0);
+            }
+            MetronomeMessage::Reset => self.reset(),
+        }
+    }
+
+    /// Re-aligns the metronome so that the next sample is a downbeat.
+    pub fn reset(&mut self) {
+        self.samples_until_beat = 0.0;
+        self.beat_idx = 0;
+    }
+
+    /// Whether the metronome is producing (or will produce) any output.
+    pub fn is_active(&self) -> bool {
+        self.is_ticking() || self.click.is_active()
+    }
+
+    /// Whether the metronome is currently counting beats.
+    pub fn is_ticking(&self) -> bool {
+        self.enabled || self.count_in_beats > 0
+    }
+
+    /// Returns the number of samples in each beat at the current tempo.
+    pub fn samples_per_beat(&self) -> f64 {
+        self.sample_rate * 60.0 / self.bpm
+    }
+
+    /// Processes the metronome for the whole of `buffer`, adding its output
+    /// to either the cue or main channels.
+    pub fn process(&mut self, buffer: &mut AudioBuffer) {
+        if !self.is_active() {
+            return;
+        }
+
+        let num_channels = buffer.channels();
+        let has_cue_output = num_channels > NUM_CHANNELS;
+
+        for frame in 0..buffer.len_frames() {
+            if self.is_ticking() {
+                if self.samples_until_beat <= 0.0 {
+                    self.tick();
+                    self.samples_until_beat += self.samples_per_beat();
+                }
+
+                self.samples_until_beat -= 1.0;
+            }
+
+            let sample = self.click.process();
+
+            if has_cue_output {
+                buffer[frame * num_channels + NUM_CHANNELS] += sample;
+            }
+            else {
+                for ch in 0..num_channels {
+                    buffer[frame * num_channels + ch] += sample;
+                }
+            }
+        }
+    }
+
+    /// Triggers a click for the current beat and moves to the next one.
+    fn tick(&mut self) {
+        if self.beat_idx == 0 {
+            self.click.trigger(ACCENT_CLICK_FREQ_HZ, self.level);
+        }
+        else {
+            self.click
+                .trigger(BEAT_CLICK_FREQ_HZ, self.level * UNACCENTED_GAIN);
+        }
+
+        self.beat_idx = (self.beat_idx + 1) % self.beats_per_bar;
+        self.count_in_beats = self.count_in_beats.saturating_sub(1);
+    }
+}
+
+impl Default for Metronome {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE })
+    }
+}
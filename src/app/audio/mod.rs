@@ -3,20 +3,39 @@
 use super::*;
 use crate::dsp::*;
 
-use nannou_audio::Buffer;
 use std::sync::atomic::AtomicUsize;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use thread_pool::ThreadPool;
 
 pub mod context;
+pub mod feedback;
+pub mod fx_control;
+pub mod input;
+pub mod metronome;
 pub mod model;
+pub mod modulation;
+pub mod offline;
 pub mod process;
+pub mod profiling;
+pub mod room_eq;
+pub mod stems;
+pub mod true_peak;
 pub mod voice;
 
 pub use context::AudioContext;
+pub use feedback::{FeedbackControl, FeedbackSuppression};
+pub use fx_control::{DelayParameters, FXMessage};
+pub use input::{AudioInput, AudioInputControl, InputCapture};
+pub use metronome::{Metronome, MetronomeMessage};
 pub use model::*;
-pub use process::process;
+pub use modulation::{ModInput, ModTarget, NUM_MOD_GESTURES};
+pub use process::{process, render_stream};
+pub use profiling::{DspLoad, DspProfiler, DspStage};
+pub use room_eq::RoomPresetStore;
+pub use stems::{Stem, StemTaps};
+pub use true_peak::{TruePeakControl, TruePeakIncident, TruePeakMeter};
 pub use voice::*;
 
+/// The number of buffers which the DSP load is averaged over.
 pub const DSP_LOAD_AVERAGING_SAMPLES: usize = 32;
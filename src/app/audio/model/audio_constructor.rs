@@ -2,23 +2,34 @@
 
 use super::builder::*;
 use super::*;
+use crate::app::audio::fx_control::*;
 use crate::dsp::*;
 use atomic_float::AtomicF64;
 use std::sync::atomic::AtomicUsize;
 use triple_buffer::Output;
 
 pub const DEFAULT_SPECTRAL_BLOCK_SIZE: usize = 1 << 10; // 1024
+pub const DEFAULT_PITCH_SHIFT_BLOCK_SIZE: usize = 1 << 11; // 2048
+pub const MAX_PITCH_SHIFT_BLOCK_SIZE: usize = 1 << 13; // 8192
+pub const SPECTRUM_ANALYZER_BLOCK_SIZE: usize = 1 << 11; // 2048
+pub const NUM_AUDIO_BANDS: usize = 32;
 pub const DEFAULT_GAIN: f64 = 1.5;
 pub const MAX_NUM_RESONATORS: usize = 32;
+pub const DEFAULT_DELAY_TIME_MS: f64 = 250.0;
+/// How much recent audio the granular engine holds.
+pub const GRANULAR_CAPTURE_SECS: f64 = 8.0;
+pub const DEFAULT_GRAIN_DENSITY: f64 = 20.0;
+pub const DEFAULT_GRAIN_SIZE_MS: f64 = 80.0;
 
 pub fn build_audio_model(
     mut context: AudioContext,
 ) -> AudioPackage {
     let sr = context.sample_rate;
+    let binaural = context.binaural;
     AudioModelBuilder::new(context)
         .processors(audio_processors(sr, sr))
         .generation(audio_generation(sr))
-        .data(audio_data(sr, sr))
+        .data(audio_data(sr, sr, binaural))
         .buffers(audio_buffers())
         .build()
 }
@@ -28,22 +39,76 @@ fn audio_processors(
     sample_rate: f64,
     upsampled_rate: f64,
 ) -> AudioProcessors {
-    AudioProcessors {}
+    let mut spectral_filter =
+        SpectralFilter::new(NUM_CHANNELS, DEFAULT_SPECTRAL_BLOCK_SIZE);
+
+    // the mask starts closed, so it's opened until the hands paint it
+    let mut mask = SpectralMask::new(DEFAULT_SPECTRAL_BLOCK_SIZE)
+        .with_size(DEFAULT_SPECTRAL_BLOCK_SIZE / 2);
+    mask.fill(1.0);
+    spectral_filter.set_mask(&mask);
+
+    let mut delay =
+        StereoDelay::new(MAX_DELAY_TIME_MS / 1000.0, sample_rate)
+            .with_delay_time(DEFAULT_DELAY_TIME_MS / 1000.0);
+    delay.set_feedback_amount(DELAY_FEEDBACK);
+
+    AudioProcessors {
+        spectral_filter,
+        pitch_shifter: PitchShifter::new(
+            NUM_CHANNELS, MAX_PITCH_SHIFT_BLOCK_SIZE,
+        ),
+        spectrum_analyzer: SpectrumAnalyzer::new(
+            NUM_CHANNELS, SPECTRUM_ANALYZER_BLOCK_SIZE,
+        ),
+        band_analyzer: BandAnalyzer::new(
+            NUM_CHANNELS,
+            SPECTRUM_ANALYZER_BLOCK_SIZE,
+            NUM_AUDIO_BANDS,
+            BandScale::Mel,
+        ),
+        delay,
+        transient_shaper: TransientShaper::new(sample_rate),
+        exciter: Exciter::new(sample_rate),
+        bitcrusher: Bitcrusher::new(sample_rate),
+        modulation: crate::app::audio::modulation::build_mod_matrix(
+            sample_rate,
+        ),
+        eq: ParametricEq::new(sample_rate),
+        tilt: SpectralTilt::new(sample_rate),
+        feedback: None,
+        dc_filter: DCFilter::new(sample_rate, DCFilterSlope::default()),
+        limiter: None,
+        true_peak: None,
+        stems: None,
+        binaural: BinauralDecoder::stereo(sample_rate),
+        profiler: DspProfiler::new(),
+    }
 }
 
 fn audio_generation(sample_rate: f64) -> AudioGeneration {
-    AudioGeneration {}
+    AudioGeneration {
+        metronome: Metronome::new(sample_rate),
+        sample_player: None,
+        input: None,
+        granular: None,
+    }
 }
 
 fn audio_data(
     sample_rate: f64,
     upsampled_rate: f64,
+    binaural: bool,
 ) -> AudioData {
+    let mut output_fade =
+        Smoother::new(FX_SMOOTHING_TIME_MS, 1.0, sample_rate);
+    output_fade.set_start_value(1.0);
+
     AudioData {
         voice_gain: Smoother::new(1.0, 0.01, sample_rate),
-        // master_gain: Arc::new(SmootherAtomic::new(
-        //     1.0, DEFAULT_GAIN, upsampled_rate,
-        // )),
+        master_gain: Smoother::new(FX_SMOOTHING_TIME_MS, 1.0, sample_rate),
+        output_fade,
+        is_asleep: false,
         sample_rate: Arc::new(AtomicF64::new(sample_rate)),
         upsampled_rate: Arc::new(AtomicF64::new(upsampled_rate)),
         latency_samples: 0,
@@ -52,17 +117,38 @@ fn audio_data(
         )),
         is_processing: false,
         idle_timer_samples: 0,
-        average_load: vec![0.0; DSP_LOAD_AVERAGING_SAMPLES],
-        average_pos: 0,
         sample_timer: 0,
         callback_time_elapsed: Arc::new(Mutex::new(std::time::Instant::now())),
 
-        delay_time_ms: 250.0,
+        delay_time_ms: DEFAULT_DELAY_TIME_MS,
+        delay_mix: Smoother::new(FX_SMOOTHING_TIME_MS, 0.0, sample_rate),
+        binaural_mix: Smoother::new(
+            FX_SMOOTHING_TIME_MS,
+            if binaural { 1.0 } else { 0.0 },
+            sample_rate,
+        ),
+
+        pitch_shift: Arc::new(PitchShiftControl {
+            block_size: AtomicUsize::new(DEFAULT_PITCH_SHIFT_BLOCK_SIZE),
+            ..Default::default()
+        }),
+        granular: Arc::new(GranularControl {
+            density: AtomicF64::new(DEFAULT_GRAIN_DENSITY),
+            size_ms: AtomicF64::new(DEFAULT_GRAIN_SIZE_MS),
+            ..Default::default()
+        }),
+        pluck: Arc::new(PluckControl {
+            brightness: AtomicF64::new(PluckParameters::default().brightness),
+            decay_secs: AtomicF64::new(PluckParameters::default().decay_secs),
+        }),
+        modulation: Arc::default(),
+        tilt: Arc::default(),
     }
 }
 
 fn audio_buffers() -> AudioBuffers {
     AudioBuffers {
+        output_buffer: AudioBuffer::new(NUM_CHANNELS, BUFFER_SIZE),
         master_gain_buffer: vec![
             DEFAULT_GAIN;
             BUFFER_SIZE
@@ -1,6 +1,8 @@
 //! Audio model constructor.
 
+use super::audio_constructor::GRANULAR_CAPTURE_SECS;
 use super::*;
+use atomic::Atomic;
 use crossbeam_channel::{bounded, unbounded};
 use std::cell::RefCell;
 
@@ -15,6 +17,11 @@ pub struct AudioPackage {
     pub model: AudioModel,
     pub callback_timer_ref: Arc<Mutex<std::time::Instant>>,
     pub sample_rate_ref: Arc<AtomicF64>,
+    pub pitch_shift_ref: Arc<PitchShiftControl>,
+    pub granular_ref: Arc<GranularControl>,
+    pub pluck_ref: Arc<PluckControl>,
+    pub modulation_ref: Arc<ModulationControl>,
+    pub tilt_ref: Arc<TiltControl>,
     pub message_channels: AudioMessageSenders,
 }
 
@@ -37,16 +44,28 @@ impl AudioModelBuilder {
     /// Panics if the `voice_event_receiver` field of `context` is `None`,
     /// or if the internal thread pool fails to spawn threads.
     pub fn new(mut context: AudioContext) -> Self {
+        let mut voice_handler = VoiceHandler::build(
+            context.voice_event_receiver.take().unwrap(),
+            Arc::new(AtomicF64::new(context.sample_rate)),
+        );
+        // voices can't start without a generator type
+        voice_handler.attach_generator_osc(Arc::new(Atomic::new(
+            ExciterOscillator::default(),
+        )));
+        voice_handler.set_noise_color(context.noise_color);
+        voice_handler.set_unison_parameters(context.unison);
+        voice_handler.set_glide_parameters(context.glide);
+        voice_handler.set_sub_parameters(context.sub);
+        voice_handler.set_voice_stealing(context.voice_stealing);
+        voice_handler.set_max_polyphony(context.max_polyphony);
+
         Self {
             model: AudioModel {
                 generation: AudioGeneration::default(),
                 processors: AudioProcessors::default(),
                 data: AudioData::default(),
                 buffers: AudioBuffers::default(),
-                voice_handler: VoiceHandler::build(
-                    context.voice_event_receiver.take().unwrap(),
-                    Arc::new(AtomicF64::new(context.sample_rate)),
-                ),
+                voice_handler,
                 context,
                 message_channels: RefCell::new(AudioMessageReceivers::default()),
                 thread_pool: ThreadPool::build(4).unwrap(),
@@ -108,23 +127,100 @@ impl AudioModelBuilder {
             "AudioModelBuilder::build(): failed to verify preparation checksum, please call all the required methods"
         );
 
+        self.load_sample_player();
+        self.model.generation.input = self.model.context.audio_input.take();
+        self.model.processors.feedback = self.model.context.feedback.take();
+        self.model.processors.true_peak = self.model.context.true_peak.take();
+        self.model.processors.stems = self.model.context.stems.take();
+
+        let dc_filter = &mut self.model.processors.dc_filter;
+        dc_filter.set_freq(self.model.context.dc_filter_freq);
+        dc_filter.set_slope(self.model.context.dc_filter_slope);
+
+        self.build_limiter();
+        self.model.generation.granular =
+            self.model.context.granular.then(|| {
+                GranularEngine::new(
+                    GRANULAR_CAPTURE_SECS,
+                    self.model.context.sample_rate,
+                )
+            });
+
+        let modulation = &mut self.model.processors.modulation;
+
+        for route in &self.model.context.mod_routes {
+            modulation.add_route(*route);
+        }
+
+        if let Some(ModSource::Lfo(lfo)) =
+            modulation.source_mut(ModInput::Lfo.idx())
+        {
+            lfo.set_parameters(self.model.context.mod_lfo);
+        }
+
+        self.model.context.delay.apply(&mut self.model.processors.delay);
+
         AudioPackage {
             callback_timer_ref: Arc::clone(
                 &self.model.data.callback_time_elapsed,
             ),
             sample_rate_ref: Arc::clone(&self.model.data.sample_rate),
+            pitch_shift_ref: Arc::clone(&self.model.data.pitch_shift),
+            granular_ref: Arc::clone(&self.model.data.granular),
+            pluck_ref: Arc::clone(&self.model.data.pluck),
+            modulation_ref: Arc::clone(&self.model.data.modulation),
+            tilt_ref: Arc::clone(&self.model.data.tilt),
             message_channels: self.message_channels(),
             model: self.model,
         }
     }
 
+    /// Creates the output limiter, whose look-ahead is the model's latency.
+    fn build_limiter(&mut self) {
+        let limiter = self.model.context.limiter.map(|params| {
+            Limiter::new(params, self.model.context.sample_rate)
+        });
+
+        self.model.data.latency_samples =
+            limiter.as_ref().map_or(0, |l| l.latency() as u32);
+        self.model.processors.limiter = limiter;
+    }
+
+    /// Opens the sample player, which streams on the model's thread pool.
+    fn load_sample_player(&mut self) {
+        let Some(path) = self.model.context.sample_path.take()
+        else {
+            return;
+        };
+
+        match SamplePlayer::open(
+            &path,
+            &self.model.thread_pool,
+            self.model.context.sample_rate,
+        ) {
+            Ok(mut player) => {
+                player.set_looping(self.model.context.sample_looping);
+                self.model.generation.sample_player = Some(player);
+            }
+            Err(e) => eprintln!("failed to load sample \"{path}\": {e}"),
+        }
+    }
+
     fn message_channels(&mut self) -> AudioMessageSenders {
         let mut msg_ch = self.model.message_channels.borrow_mut();
         let (note_event, receiver) = bounded(MAX_NOTE_EVENTS_PER_BUFFER);
         msg_ch.note_event = Some(receiver);
 
+        let (metronome, receiver) = bounded(MAX_METRONOME_MESSAGES_PER_BUFFER);
+        msg_ch.metronome = Some(receiver);
+
+        let (fx, receiver) = bounded(MAX_FX_MESSAGES_PER_BUFFER);
+        msg_ch.fx = Some(receiver);
+
         AudioMessageSenders {
             note_event,
+            metronome,
+            fx,
         }
     }
 
@@ -1,6 +1,7 @@
 //! Audio state.
 
 use crossbeam_channel::{Receiver as CCReceiver, Sender as CCSender};
+use std::sync::atomic::AtomicBool;
 use std::time::Instant;
 
 use super::*;
@@ -8,19 +9,119 @@ use super::*;
 /// All signal processors.
 #[derive(Default)]
 pub struct AudioProcessors {
-    //
+    /// Filters the voices with the mask painted by the hands.
+    pub spectral_filter: SpectralFilter,
+    pub pitch_shifter: PitchShifter,
+    /// Analyzes the output for the spectrum drawn behind the hands.
+    pub spectrum_analyzer: SpectrumAnalyzer,
+    /// Follows the output's perceptual band energies, which CCs may react to.
+    pub band_analyzer: BandAnalyzer,
+    /// A delay after the spectral filter, which is silent until its mix is
+    /// raised.
+    pub delay: StereoDelay,
+    /// Reshapes the attacks and sustains of the voices, and is neutral until
+    /// its gains are moved.
+    pub transient_shaper: TransientShaper,
+    /// Adds harmonics to the highs, and is silent until its mix is raised.
+    pub exciter: Exciter,
+    /// Crushes and decimates the output, and is dry until its mix is raised.
+    pub bitcrusher: Bitcrusher,
+    /// Routes the LFO, envelope and gestures to the effects.
+    pub modulation: ModMatrix,
+    /// Corrects the output for the room, and is flat until a room is
+    /// selected.
+    pub eq: ParametricEq,
+    /// Brightens or darkens the whole output, and is flat until it is
+    /// moved.
+    pub tilt: SpectralTilt,
+    /// Notches ringing frequencies out of the output, if it is enabled.
+    pub feedback: Option<FeedbackSuppression>,
+    /// Removes DC and subsonics from the output, ahead of the limiter.
+    pub dc_filter: DCFilter,
+    /// Holds the output under its ceiling, unless it is disabled.
+    pub limiter: Option<Limiter>,
+    /// Meters the true peak of the output, if it is enabled.
+    pub true_peak: Option<TruePeakMeter>,
+    /// Records each stage of the signal chain as a stem, if it is enabled.
+    pub stems: Option<StemTaps>,
+    /// Renders the output for headphones, while binaural monitoring is
+    /// selected.
+    pub binaural: BinauralDecoder,
+    /// Times each stage of the signal chain, for the DSP load meter.
+    pub profiler: DspProfiler,
+}
+
+/// Control of the pitch shifter, shared with the UI thread.
+#[derive(Debug, Default)]
+pub struct PitchShiftControl {
+    /// Whether the pitch shifter is processed at all, as it adds latency.
+    pub enabled: AtomicBool,
+    pub semitones: AtomicF64,
+    pub preserve_formants: AtomicBool,
+    pub block_size: AtomicUsize,
+}
+
+/// Control of the granular engine, shared with the UI and parameter
+/// threads.
+#[derive(Debug, Default)]
+pub struct GranularControl {
+    /// The number of grains spawned per second.
+    pub density: AtomicF64,
+    pub size_ms: AtomicF64,
+    /// The widest random pitch offset of each grain, in semitones.
+    pub pitch_spray: AtomicF64,
+    /// Where grains start in the recording, from `0.0` (the newest audio) to
+    /// `1.0` (the oldest).
+    pub position: AtomicF64,
+}
+
+/// Control of the plucked string voices, shared with the parameter thread.
+#[derive(Debug, Default)]
+pub struct PluckControl {
+    /// How bright each string is, from `0.0` to `1.0`.
+    pub brightness: AtomicF64,
+    /// The time for each string to decay by 60 dB, in seconds.
+    pub decay_secs: AtomicF64,
+}
+
+/// Control of the spectral tilt, shared with the parameter thread.
+#[derive(Debug, Default)]
+pub struct TiltControl {
+    /// The tilt in decibels, where positive values are brighter.
+    pub tilt_db: AtomicF64,
+}
+
+/// The gesture-derived modulation sources, shared with the parameter
+/// thread.
+#[derive(Debug, Default)]
+pub struct ModulationControl {
+    /// The value of each gesture, from `0.0` to `1.0`.
+    pub gestures: [AtomicF64; NUM_MOD_GESTURES],
 }
 
 /// Audio generation types.
 #[derive(Default)]
 pub struct AudioGeneration {
-    //
+    pub metronome: Metronome,
+    /// Plays the audio file passed via arguments, if any, alongside the
+    /// voices.
+    pub sample_player: Option<SamplePlayer>,
+    /// Live input from the input stream, if it is open.
+    pub input: Option<AudioInput>,
+    /// Replays the recent voices, sample and input as grains, if it is
+    /// enabled.
+    pub granular: Option<GranularEngine>,
 }
 
 /// Audio-related data.
 pub struct AudioData {
     pub voice_gain: Smoother<f64>,
-    // pub master_gain: Arc<SmootherAtomic<f64>>,
+    /// The gain of the output (excluding the metronome).
+    pub master_gain: Smoother<f64>,
+    /// The gain of the whole output, which is faded out while winding down.
+    pub output_fade: Smoother<f64>,
+    /// Whether all processing is skipped, until woken.
+    pub is_asleep: bool,
     pub sample_rate: Arc<AtomicF64>,
     pub upsampled_rate: Arc<AtomicF64>,
 
@@ -31,21 +132,31 @@ pub struct AudioData {
     pub is_processing: bool,
     pub idle_timer_samples: u64,
 
-    pub average_load: Vec<f64>,
-    pub average_pos: usize,
-
     pub delay_time_ms: f64,
+    /// The level of the delay in the output.
+    pub delay_mix: Smoother<f64>,
+    /// How much of the output is rendered binaurally, which is crossfaded
+    /// as the monitoring is switched.
+    pub binaural_mix: Smoother<f64>,
 
     pub sample_timer: u32,
 
     pub callback_time_elapsed: Arc<Mutex<Instant>>,
+
+    pub pitch_shift: Arc<PitchShiftControl>,
+    pub granular: Arc<GranularControl>,
+    pub pluck: Arc<PluckControl>,
+    pub modulation: Arc<ModulationControl>,
+    pub tilt: Arc<TiltControl>,
 }
 
 impl Default for AudioData {
     fn default() -> Self {
         Self {
             voice_gain: Smoother::default(),
-            // master_gain: Arc::new(SmootherAtomic::default()),
+            master_gain: Smoother::default(),
+            output_fade: Smoother::default(),
+            is_asleep: false,
             sample_rate: Arc::default(),
             upsampled_rate: Arc::default(),
 
@@ -56,14 +167,19 @@ impl Default for AudioData {
             is_processing: Default::default(),
             idle_timer_samples: Default::default(),
 
-            average_load: Vec::default(),
-            average_pos: Default::default(),
-
             delay_time_ms: 250.0,
+            delay_mix: Smoother::default(),
+            binaural_mix: Smoother::default(),
 
             sample_timer: 0,
 
             callback_time_elapsed: Arc::new(Mutex::new(Instant::now())),
+
+            pitch_shift: Arc::default(),
+            granular: Arc::default(),
+            pluck: Arc::default(),
+            modulation: Arc::default(),
+            tilt: Arc::default(),
         }
     }
 }
@@ -71,6 +187,9 @@ impl Default for AudioData {
 /// Audio-related buffers.
 #[derive(Default)]
 pub struct AudioBuffers {
+    /// The buffer which the signal chain is processed in, before it is
+    /// copied to the audio stream.
+    pub output_buffer: AudioBuffer,
     pub master_gain_buffer: Vec<f64>,
 
     pub oversampling_buffer: OversamplingBuffer,
@@ -81,9 +200,13 @@ pub struct AudioBuffers {
 #[derive(Default)]
 pub struct AudioMessageReceivers {
     pub note_event: Option<CCReceiver<NoteEvent>>,
+    pub metronome: Option<CCReceiver<MetronomeMessage>>,
+    pub fx: Option<CCReceiver<FXMessage>>,
 }
 
 /// Audio message channel senders.
 pub struct AudioMessageSenders {
     pub note_event: CCSender<NoteEvent>,
+    pub metronome: CCSender<MetronomeMessage>,
+    pub fx: CCSender<FXMessage>,
 }
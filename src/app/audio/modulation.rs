@@ -0,0 +1,199 @@
+//! Modulation of the internal effects by an LFO, an envelope and gestures.
+
+use super::*;
+use crate::dsp::modulation::matrix::MOD_CURVE_RANGE;
+use super::model::audio_constructor::DEFAULT_DELAY_TIME_MS;
+use super::fx_control::{MAX_DELAY_TIME_MS, MIN_DELAY_TIME_MS};
+use std::fmt::{Display, Formatter};
+
+/// The number of gesture-derived modulation sources.
+pub const NUM_MOD_GESTURES: usize = 2;
+/// The default settings of the modulation LFO: a slow, free-running sine.
+pub const DEFAULT_MOD_LFO: LfoParameters = LfoParameters {
+    shape: LfoShape::Sine,
+    rate: LfoRate::Free(0.25),
+    unipolar: false,
+    retrigger: false,
+};
+
+/// The attack, decay and release times of the modulation envelope, in
+/// milliseconds, which are slower than the voices' so that effects swell.
+const MOD_ENVELOPE_TIMES_MS: (f64, f64, f64) = (200.0, 800.0, 1500.0);
+const MOD_ENVELOPE_SUSTAIN: f64 = 0.6;
+
+/// The sources which modulation routes may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModInput {
+    /// An LFO, which may be synced to the tempo.
+    Lfo,
+    /// An envelope which is triggered by each note.
+    Envelope,
+    /// A value set by the parameter updater from the hands, from `0.0` to
+    /// `1.0`.
+    Gesture(usize),
+}
+
+impl ModInput {
+    /// Parses a `ModInput` from its name, e.g. `"lfo"` or `"gesture-2"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "lfo" => Some(Self::Lfo),
+            "envelope" => Some(Self::Envelope),
+            name => name
+                .strip_prefix("gesture-")
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|n| (1..=NUM_MOD_GESTURES).contains(n))
+                .map(|n| Self::Gesture(n - 1)),
+        }
+    }
+
+    /// The index of the source in the modulation matrix.
+    pub const fn idx(self) -> usize {
+        match self {
+            Self::Lfo => 0,
+            Self::Envelope => 1,
+            Self::Gesture(n) => 2 + n,
+        }
+    }
+}
+
+impl Display for ModInput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Lfo => write!(f, "LFO"),
+            Self::Envelope => write!(f, "Envelope"),
+            Self::Gesture(n) => write!(f, "Gesture {}", n + 1),
+        }
+    }
+}
+
+/// The effect parameters which modulation routes may target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModTarget {
+    /// The delay time, in milliseconds.
+    DelayTime,
+    /// The dry/wet mix of the spectral filter.
+    SpectralMix,
+}
+
+impl ModTarget {
+    const ALL: [Self; 2] = [Self::DelayTime, Self::SpectralMix];
+
+    /// Parses a `ModTarget` from its name, i.e. `"delay-time"` or
+    /// `"spectral-mix"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "delay-time" => Some(Self::DelayTime),
+            "spectral-mix" => Some(Self::SpectralMix),
+            _ => None,
+        }
+    }
+
+    /// The index of the destination in the modulation matrix.
+    pub const fn idx(self) -> usize {
+        self as usize
+    }
+
+    /// The unmodulated destination.
+    fn destination(self) -> ModDestination {
+        match self {
+            Self::DelayTime => ModDestination::new(
+                DEFAULT_DELAY_TIME_MS,
+                MIN_DELAY_TIME_MS,
+                MAX_DELAY_TIME_MS,
+            ),
+            Self::SpectralMix => ModDestination::new(1.0, 0.0, 1.0),
+        }
+    }
+}
+
+impl Display for ModTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DelayTime => write!(f, "Delay time"),
+            Self::SpectralMix => write!(f, "Spectral mix"),
+        }
+    }
+}
+
+/// Parses a route in the form `<source>:<target>:<depth>[:<curve>]`, e.g.
+/// `"lfo:delay-time:0.1"`.
+///
+/// # Errors
+///
+/// Returns an error if any part of the route is unknown or out of range.
+pub fn parse_mod_route(route: &str) -> Result<ModRoute, String> {
+    let parts: Vec<&str> = route.split(':').collect();
+
+    let (source, target, depth, curve) = match parts.as_slice() {
+        [source, target, depth] => (source, target, depth, None),
+        [source, target, depth, curve] => {
+            (source, target, depth, Some(curve))
+        }
+        _ => {
+            return Err(format!(
+                "invalid modulation route \"{route}\" (expected <source>:<target>:<depth>[:<curve>])"
+            ))
+        }
+    };
+
+    let source = ModInput::from_name(source)
+        .ok_or_else(|| format!("unknown modulation source \"{source}\""))?;
+    let target = ModTarget::from_name(target)
+        .ok_or_else(|| format!("unknown modulation target \"{target}\""))?;
+
+    let depth = match depth.parse::<f64>() {
+        Ok(d) if (-1.0..=1.0).contains(&d) => d,
+        _ => {
+            return Err(String::from(
+                "modulation depth must be from -1 to 1",
+            ))
+        }
+    };
+
+    let mut route = ModRoute::new(source.idx(), target.idx(), depth);
+
+    if let Some(curve) = curve {
+        let (min, max) = MOD_CURVE_RANGE;
+
+        route = match curve.parse::<f64>() {
+            Ok(c) if (min..=max).contains(&c) => route.with_curve(c),
+            _ => {
+                return Err(format!(
+                    "modulation curve must be from {min} to {max}"
+                ))
+            }
+        };
+    }
+
+    Ok(route)
+}
+
+/// Builds the modulation matrix with each source and target, but no routes.
+pub fn build_mod_matrix(sample_rate: f64) -> ModMatrix {
+    let mut matrix = ModMatrix::new(MAX_BUFFER_SIZE);
+
+    let lfo = Lfo::new(DEFAULT_MOD_LFO, sample_rate);
+    matrix.set_source(ModInput::Lfo.idx(), Some(ModSource::Lfo(lfo)));
+
+    let (attack, decay, release) = MOD_ENVELOPE_TIMES_MS;
+    let mut envelope = AdsrEnvelope::new(sample_rate);
+    envelope.set_parameters(attack, decay, MOD_ENVELOPE_SUSTAIN, release);
+    matrix.set_source(
+        ModInput::Envelope.idx(),
+        Some(ModSource::Envelope(envelope)),
+    );
+
+    for n in 0..NUM_MOD_GESTURES {
+        matrix.set_source(
+            ModInput::Gesture(n).idx(),
+            Some(ModSource::external(0.0, sample_rate)),
+        );
+    }
+
+    for target in ModTarget::ALL {
+        matrix.set_destination(target.idx(), target.destination());
+    }
+
+    matrix
+}
@@ -0,0 +1,397 @@
+//! Offline rendering of a performance to a WAV file, faster than real time.
+//!
+//! The whole signal chain is processed as it is for the audio stream, but
+//! without one, so the notes of a MIDI file are played with the voices and
+//! effects set up by the arguments. Each note is placed on its exact sample,
+//! rather than on the sample estimated from the time since the last audio
+//! callback.
+//!
+//! The hand data of an OSC recording (made with `--record`) may also be
+//! replayed, which drives the audio parameters as in a performance. The hands
+//! are used as they were received, without the app's damping, calibration or
+//! timeouts, and only the attachments which target audio parameters are
+//! applied, as MIDI CCs aren't rendered.
+
+use super::*;
+use crate::app::args::Arguments;
+use crate::app::hands::address_map::OSCAddressMap;
+use crate::app::hands::hand_parser::HandParser;
+use crate::app::hands::hand_types::RawHandPairCOM;
+use crate::app::midi::file::{read_midi_file, TimedNote};
+use crate::app::osc::recorder::read_recording;
+use nannou_osc::rosc::OscPacket;
+use std::io;
+use std::iter::Peekable;
+use std::time::{Duration, Instant};
+
+/// The default file which renders are written to.
+pub const DEFAULT_RENDER_PATH: &str = "render.wav";
+/// The default sample rate of renders.
+pub const DEFAULT_RENDER_SAMPLE_RATE: f64 = 48000.0;
+/// How long the render continues after the last note or gesture, so that the
+/// releases and effect tails are kept, in seconds.
+const RENDER_TAIL_SECS: f64 = 4.0;
+
+/// Processes the signal chain without an audio stream.
+pub struct OfflineRenderer {
+    audio: AudioModel,
+    senders: AudioMessageSenders,
+    buffer: AudioBuffer,
+    sample_rate: f64,
+    /// The next frame to be rendered.
+    frame: u64,
+    /// The output frames of the last block, as 32-bit floats.
+    samples: Vec<f32>,
+
+    controls: AudioControls,
+    gestures: Option<GestureReplay>,
+}
+
+impl OfflineRenderer {
+    /// Returns a renderer which sets up the voices and effects as in `args`,
+    /// at `sample_rate`.
+    ///
+    /// As the effects read the global sample rate, this should only be used
+    /// while no audio stream is running.
+    pub fn new(args: &Arguments, sample_rate: f64) -> Self {
+        unsafe {
+            SAMPLE_RATE = sample_rate;
+        }
+
+        let (voice_event_sender, voice_event_receiver) = channel();
+        let context = AudioContext::from_args(
+            args,
+            sample_rate,
+            voice_event_sender,
+            voice_event_receiver,
+        );
+        let package = audio_constructor::build_audio_model(context);
+
+        // the position and density are set by the gestures, if any
+        package.granular_ref.size_ms.sr(args.grain_size_ms);
+        package.granular_ref.pitch_spray.sr(args.grain_pitch_spray);
+
+        Self {
+            audio: package.model,
+            senders: package.message_channels,
+            buffer: AudioBuffer::new(NUM_CHANNELS, BUFFER_SIZE),
+            sample_rate,
+            frame: 0,
+            samples: Vec::with_capacity(BUFFER_SIZE * NUM_CHANNELS),
+
+            controls: AudioControls {
+                granular: package.granular_ref,
+                pluck: package.pluck_ref,
+                modulation: package.modulation_ref,
+                tilt: package.tilt_ref,
+            },
+            gestures: None,
+        }
+    }
+
+    /// Replays the hand data in `packets` (in order of time, from the start
+    /// of the render), which is parsed with `address_map`.
+    #[must_use]
+    pub fn with_gestures(
+        mut self,
+        packets: Vec<(Duration, OscPacket)>,
+        address_map: OSCAddressMap,
+    ) -> Self {
+        self.gestures = Some(GestureReplay {
+            end_secs: packets.last().map_or(0.0, |(t, _)| t.as_secs_f64()),
+            packets: packets.into_iter().peekable(),
+            parser: HandParser::new(address_map),
+            hands: RawHandPairCOM::default(),
+            driver: AudioAttachmentDriver::new(self.controls.clone()),
+        });
+
+        self
+    }
+
+    pub const fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Renders `notes` (in order of time, from the start of the render) and
+    /// any gestures, followed by a tail for the releases and effects, to a
+    /// WAV file at `path`. Returns the number of frames which were rendered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be written, or if the render
+    /// would be too long for a WAV file.
+    pub fn render_to_file(
+        mut self,
+        notes: &[TimedNote],
+        path: &str,
+    ) -> io::Result<u64> {
+        let len_frames = self.len_frames(notes);
+
+        let len_bytes = len_frames * (NUM_CHANNELS * 4) as u64;
+        if len_bytes > stems::MAX_WAV_DATA_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the render is too long for a WAV file",
+            ));
+        }
+
+        let mut wav =
+            stems::create_wav(path, NUM_CHANNELS, self.sample_rate as u32)?;
+        let mut notes = notes.iter().peekable();
+
+        while self.frame < len_frames {
+            let max_frames = (len_frames - self.frame) as usize;
+            self.process_block(&mut notes, max_frames);
+
+            wav.write_samples(&self.samples)?;
+        }
+
+        wav.update_header()?;
+
+        Ok(self.frame)
+    }
+
+    /// The number of frames in a render of `notes`, including the tail.
+    fn len_frames(&self, notes: &[TimedNote]) -> u64 {
+        let notes_end = notes.last().map_or(0.0, |note| note.secs);
+        let gestures_end = self.gestures.as_ref().map_or(0.0, |g| g.end_secs);
+        let end_secs = notes_end.max(gestures_end);
+
+        ((end_secs + RENDER_TAIL_SECS) * self.sample_rate).ceil() as u64
+    }
+
+    /// Processes a block of up to `max_frames`, and plays any of `notes`
+    /// which start or stop within it. Gestures received by the start of the
+    /// block are applied to the whole block.
+    fn process_block<'a>(
+        &mut self,
+        notes: &mut std::iter::Peekable<impl Iterator<Item = &'a TimedNote>>,
+        max_frames: usize,
+    ) {
+        let start = self.frame;
+        let mut len = BUFFER_SIZE.min(max_frames);
+        let mut num_queued = 0;
+
+        while let Some(&&note) = notes.peek() {
+            let frame = ((note.secs * self.sample_rate).round() as u64)
+                .max(start);
+
+            if frame >= start + len as u64 {
+                break;
+            }
+
+            // the audio thread only takes so many events per buffer, so the
+            // block ends early if there are more
+            if num_queued == MAX_NOTE_EVENTS_PER_BUFFER {
+                len = ((frame - start) as usize).max(1);
+                break;
+            }
+
+            let timing = (frame - start) as u32;
+            let event = if note.is_on {
+                NoteEvent::NoteOn { note: note.note as f64, timing }
+            }
+            else {
+                NoteEvent::NoteOff { note: note.note as f64, timing }
+            };
+
+            if self.senders.note_event.try_send(event).is_ok() {
+                num_queued += 1;
+            }
+
+            notes.next();
+        }
+
+        if let Some(gestures) = &mut self.gestures {
+            gestures.advance(
+                start as f64 / self.sample_rate,
+                len as f64 / self.sample_rate,
+            );
+        }
+
+        self.buffer.clear(NUM_CHANNELS, len);
+        process(&mut self.audio, &mut self.buffer);
+
+        self.samples.clear();
+        self.samples.extend(self.buffer.iter().map(|&x| x as f32));
+        self.frame += len as u64;
+    }
+}
+
+/// Replays the hand data of an OSC recording into the audio attachments.
+struct GestureReplay {
+    packets: Peekable<std::vec::IntoIter<(Duration, OscPacket)>>,
+    parser: HandParser,
+    hands: RawHandPairCOM,
+    driver: AudioAttachmentDriver,
+    /// The time of the last packet, in seconds.
+    end_secs: f64,
+}
+
+impl GestureReplay {
+    /// Parses each packet received by `secs`, then updates the attachments
+    /// over `delta_time` seconds.
+    fn advance(&mut self, secs: f64, delta_time: f64) {
+        while let Some((_, packet)) =
+            self.packets.next_if(|(time, _)| time.as_secs_f64() <= secs)
+        {
+            // packets without hand data, such as handshakes, are skipped
+            if let Ok(pair) = self.parser.parse_hands(packet.into()) {
+                self.hands.pair = pair;
+                self.hands.com.set_from(&pair);
+            }
+        }
+
+        self.driver.update(&self.hands, delta_time as f32);
+    }
+}
+
+/// Renders the MIDI file and gesture recording set in `args` (either of
+/// which may be missing) to the WAV file set in `args`, with the voices and
+/// effects set up as in `args`.
+///
+/// # Errors
+///
+/// Returns an error if the MIDI file, gesture recording, or OSC address map
+/// could not be read, or if the WAV file could not be written.
+pub fn render(args: &Arguments) -> Result<(), String> {
+    let notes = match args.render_midi_path.as_deref() {
+        Some(path) => read_midi_file(path)?,
+        None => Vec::new(),
+    };
+
+    let mut renderer = OfflineRenderer::new(args, args.render_sample_rate);
+
+    if let Some(path) = args.render_gestures_path.as_deref() {
+        let packets = read_recording(path)
+            .map_err(|e| format!("failed to read \"{path}\": {e}"))?;
+        let address_map = match args.osc_map_path.as_deref() {
+            Some(path) => OSCAddressMap::from_file(path)?,
+            None => OSCAddressMap::default(),
+        };
+
+        renderer = renderer.with_gestures(packets, address_map);
+    }
+
+    let output_path = &args.render_output_path;
+    let sample_rate = renderer.sample_rate();
+
+    let start = Instant::now();
+    let num_frames = renderer
+        .render_to_file(&notes, output_path)
+        .map_err(|e| format!("failed to write \"{output_path}\": {e}"))?;
+
+    let secs = num_frames as f64 / sample_rate;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!(
+        "rendered {secs:.1} s to \"{output_path}\" in {elapsed:.1} s ({:.1}x real time)",
+        secs / elapsed.max(f64::EPSILON)
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::hands::NUM_HAND_VERTICES;
+    use crate::dsp::synthesis::audio_file::AudioFileReader;
+    use nannou_osc::{Message, Type};
+
+    // the global sample rate is left at its default, as other tests read it
+    const SAMPLE_RATE: f64 = 44100.0;
+
+    fn renderer() -> OfflineRenderer {
+        let args = ["maestro", "9000", "9001"].map(String::from);
+        let args = Arguments::from_args(args.into_iter()).unwrap();
+
+        OfflineRenderer::new(&args, SAMPLE_RATE)
+    }
+
+    /// A packet with one hand, whose points are all at `y`, in the layout of
+    /// the default address map.
+    fn hand_at(secs: f64, y: f64) -> (Duration, OscPacket) {
+        let list = |v: f64| vec![v.to_string(); NUM_HAND_VERTICES].join(",");
+        let packet = OscPacket::Message(Message {
+            addr: String::from("/hands"),
+            args: vec![
+                Type::String(list(0.5)),
+                Type::String(list(y)),
+                Type::String(list(0.0)),
+                Type::String(String::from("None")),
+            ],
+        });
+
+        (Duration::from_secs_f64(secs), packet)
+    }
+
+    fn note(secs: f64, is_on: bool) -> TimedNote {
+        TimedNote { secs, note: 60, is_on }
+    }
+
+    #[test]
+    fn renders_last_until_the_tail_ends() {
+        let notes = [note(0.0, true), note(0.25, false)];
+        let tail = (RENDER_TAIL_SECS * SAMPLE_RATE).ceil() as u64;
+
+        assert_eq!(renderer().len_frames(&[]), tail);
+        assert_eq!(
+            renderer().len_frames(&notes),
+            ((0.25 + RENDER_TAIL_SECS) * SAMPLE_RATE).ceil() as u64
+        );
+
+        // the gestures may outlast the notes
+        let renderer = renderer()
+            .with_gestures(vec![hand_at(1.0, 0.5)], OSCAddressMap::default());
+        assert_eq!(
+            renderer.len_frames(&notes),
+            ((1.0 + RENDER_TAIL_SECS) * SAMPLE_RATE).ceil() as u64
+        );
+    }
+
+    #[test]
+    fn notes_are_rendered_to_a_wav_file() {
+        let path = std::env::temp_dir().join("maestro_offline_test.wav");
+        let path = path.to_string_lossy().into_owned();
+
+        let notes = [note(0.0, true), note(0.25, false)];
+        let renderer = renderer();
+        let expected_frames = renderer.len_frames(&notes);
+
+        let num_frames = renderer.render_to_file(&notes, &path).unwrap();
+        assert_eq!(num_frames, expected_frames);
+
+        let mut reader = AudioFileReader::open(&path).unwrap();
+        assert_eq!(reader.info().num_channels, NUM_CHANNELS);
+        assert_eq!(reader.info().num_frames, num_frames as usize);
+
+        let mut frames = vec![[0.0; 2]; (0.25 * SAMPLE_RATE) as usize];
+        reader.read_frames(0, &mut frames).unwrap();
+        assert!(frames.iter().any(|f| f[0] != 0.0 || f[1] != 0.0));
+    }
+
+    #[test]
+    fn gestures_drive_the_audio_parameters() {
+        // the tilt follows the first hand's height
+        let packets = vec![hand_at(0.0, 0.2), hand_at(1.0, 0.8)];
+        let mut renderer =
+            renderer().with_gestures(packets, OSCAddressMap::default());
+        let tilt = Arc::clone(&renderer.controls.tilt);
+        let mut notes = [].iter().peekable();
+
+        while renderer.frame < SAMPLE_RATE as u64 {
+            renderer.process_block(&mut notes, BUFFER_SIZE);
+        }
+
+        // raised hands brighten the output
+        assert!(tilt.tilt_db.lr() > 0.0);
+
+        while renderer.frame < 2 * SAMPLE_RATE as u64 {
+            renderer.process_block(&mut notes, BUFFER_SIZE);
+        }
+
+        assert!(tilt.tilt_db.lr() < 0.0);
+        assert!(renderer.gestures.unwrap().packets.next().is_none());
+    }
+}
@@ -9,9 +9,26 @@ use super::*;
 
 const SIGNAL_EPSILON: f64 = MINUS_INFINITY_GAIN / 5.0;
 
-/// The main audio processing callback.
-pub fn process(audio: &mut AudioModel, buffer: &mut Buffer<f64>) {
-    let dsp_start = std::time::Instant::now();
+/// The output stream's callback, which processes the signal chain in the
+/// model's own buffer, and copies it to the stream's.
+pub fn render_stream(
+    audio: &mut AudioModel,
+    buffer: &mut nannou_audio::Buffer<f64>,
+) {
+    // the model's buffer only allocates if the stream's buffer grows
+    let mut output = std::mem::take(&mut audio.buffers.output_buffer);
+    output.clear(buffer.channels(), buffer.len_frames());
+
+    process(audio, &mut output);
+
+    buffer.copy_from_slice(&output);
+    audio.buffers.output_buffer = output;
+}
+
+/// The main audio processing callback, which processes the signal chain
+/// into `buffer`, whether it is from the audio stream or not.
+pub fn process(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    audio.processors.profiler.begin();
 
     // This works by breaking down the buffer into smaller discrete blocks.
     // For each block, it first processes incoming note events, which are
@@ -23,6 +40,23 @@ pub fn process(audio: &mut AudioModel, buffer: &mut Buffer<f64>) {
     let audio_is_idle = audio.is_idle();
     let buffer_len = buffer.len_frames();
 
+    handle_metronome_messages(audio);
+    handle_fx_messages(audio);
+    begin_stems(audio, buffer_len);
+
+    let metronome_is_active = audio.generation.metronome.is_active();
+    let sample_player_is_active = audio
+        .generation
+        .sample_player
+        .as_ref()
+        .is_some_and(|player| player.is_active());
+    let input_is_enabled = audio
+        .generation
+        .input
+        .as_ref()
+        .is_some_and(|input| input.is_enabled());
+    let granular_is_enabled = audio.generation.granular.is_some();
+
     // best not to block at all here - if the VoiceHandler lock can't be
     // obtained, then the note events won't be processed for this buffer.
     // let mut note_handler_guard = context.note_handler.try_lock().ok();
@@ -36,16 +70,62 @@ pub fn process(audio: &mut AudioModel, buffer: &mut Buffer<f64>) {
         .and_then(|ch| ch.try_recv().ok());
 
     let voice_handler = &mut audio.voice_handler;
+    let sample_player = &mut audio.generation.sample_player;
+    let audio_input = &mut audio.generation.input;
+    let granular = &mut audio.generation.granular;
+
+    if let Some(engine) = granular.as_mut() {
+        let control = &audio.data.granular;
+
+        engine.set_density(control.density.lr());
+        engine.set_size_ms(control.size_ms.lr());
+        engine.set_pitch_spray(control.pitch_spray.lr());
+        engine.set_position(control.position.lr());
+    }
+
+    let pluck = PluckParameters {
+        brightness: audio.data.pluck.brightness.lr(),
+        decay_secs: audio.data.pluck.decay_secs.lr(),
+    };
+
+    // only updates the strings when the parameters move
+    if pluck != *voice_handler.pluck_parameters() {
+        voice_handler.set_pluck_parameters(pluck);
+    }
 
     // if there is no note event, no active voice, and there was no audio
     // processed in the last frame, most of the signal processing can be
-    // skipped.
-    if next_event.is_none() && !voice_handler.is_voice_active() && audio_is_idle
+    // skipped. while asleep, it is all skipped regardless.
+    if audio.data.is_asleep
+        || (next_event.is_none()
+            && !voice_handler.is_voice_active()
+            && audio_is_idle
+            && !metronome_is_active
+            && !sample_player_is_active
+            && !input_is_enabled
+            && !granular_is_enabled)
     {
+        if let Some(limiter) = audio.processors.limiter.as_mut() {
+            limiter.reset();
+        }
+
+        if let Some(meter) = audio.processors.true_peak.as_mut() {
+            meter.process_silence(buffer_len);
+        }
+
+        audio.processors.binaural.reset();
+
+        // the stems are kept in time with the output while it is idle
+        finish_stems(audio, buffer);
+
+        finish_profile(audio, DspStage::Output, buffer_len);
         callback_timer(audio);
         return;
     }
 
+    // the voice handler is still borrowed, so only the profiler is
+    audio.processors.profiler.lap(DspStage::Control);
+
     let mut block_start: usize = 0;
     let mut block_end = MAX_BLOCK_SIZE.min(buffer_len);
 
@@ -57,6 +137,12 @@ pub fn process(audio: &mut AudioModel, buffer: &mut Buffer<f64>) {
                 // if the event is now (or before the block), match
                 // the event and handle its voice accordingly.
                 Some(event) if (event.timing() as usize) <= block_start => {
+                    // blocks are split at each event, so the sample starts
+                    // on the event's exact sample
+                    if let Some(player) = sample_player.as_mut() {
+                        player.handle_note_event(&event);
+                    }
+
                     match event {
                         NoteEvent::NoteOn { note, .. } => {
                             voice_handler.start_voice(
@@ -64,9 +150,11 @@ pub fn process(audio: &mut AudioModel, buffer: &mut Buffer<f64>) {
                                 audio.data.sample_rate.lr(),
                                 None,
                             );
+                            audio.processors.modulation.set_trigger(true);
                         }
                         NoteEvent::NoteOff { note, .. } => {
                             voice_handler.start_release_for_voice(None, note);
+                            audio.processors.modulation.set_trigger(false);
                         }
                     }
 
@@ -95,17 +183,204 @@ pub fn process(audio: &mut AudioModel, buffer: &mut Buffer<f64>) {
 
         voice_handler.process_block(buffer, block_start, block_end, gain);
 
+        if let Some(player) = sample_player.as_mut() {
+            player.process_block(buffer, block_start, block_end);
+        }
+
+        // the input is mixed in before the FX, so that it is filtered
+        if let Some(input) = audio_input.as_mut() {
+            input.process_block(buffer, block_start, block_end);
+        }
+
+        // grains are recorded from (and added to) everything above
+        if let Some(engine) = granular.as_mut() {
+            engine.process_block(buffer, block_start, block_end);
+        }
+
         voice_handler.terminate_finished_voices();
 
         block_start = block_end;
         block_end = (block_end + MAX_BLOCK_SIZE).min(buffer_len);
     }
 
+    tap_stem(audio, Stem::Dry, buffer);
+    profile(audio, DspStage::Voices);
+
     // audio effects/processors
     process_fx(audio, buffer);
+    analyze_spectrum(audio, buffer);
+    analyze_bands(audio, buffer);
+    profile(audio, DspStage::Analysis);
+
+    // the metronome is processed after the FX so that it is left dry
+    audio.generation.metronome.process(buffer);
+
+    process_output_fade(audio, buffer);
+    process_dc_filter(audio, buffer);
+    process_limiter(audio, buffer);
+    finish_stems(audio, buffer);
+
+    // the stems are of the speaker mix, so headphone monitoring is left out
+    process_binaural(audio, buffer);
+
+    // metered last, so that everything which is output is included
+    if let Some(meter) = audio.processors.true_peak.as_mut() {
+        meter.process(buffer);
+    }
+
+    finish_profile(audio, DspStage::Output, buffer_len);
     callback_timer(audio);
 }
 
+/// Passes any received control messages to the metronome.
+fn handle_metronome_messages(audio: &mut AudioModel) {
+    let channels = audio.message_channels.borrow();
+
+    if let Some(ch) = channels.metronome.as_ref() {
+        while let Ok(msg) = ch.try_recv() {
+            // synced LFOs and delay times follow the same tempo as the
+            // metronome
+            if let MetronomeMessage::SetTempo(bpm) = msg {
+                audio.processors.modulation.set_bpm(bpm);
+                audio.processors.delay.set_bpm(bpm);
+            }
+
+            audio.generation.metronome.handle_message(msg);
+        }
+    }
+}
+
+/// Applies any received effect control messages.
+fn handle_fx_messages(audio: &mut AudioModel) {
+    let channels = audio.message_channels.borrow();
+
+    let Some(ch) = channels.fx.as_ref()
+    else {
+        return;
+    };
+
+    while let Ok(msg) = ch.try_recv() {
+        match msg {
+            FXMessage::SetMasterGain(gain_db) => {
+                audio.data.master_gain.set_target_value(db_to_level(gain_db));
+            }
+            FXMessage::SetSpectralMix(mix) => {
+                let modulation = &mut audio.processors.modulation;
+                let target = ModTarget::SpectralMix.idx();
+                modulation.set_base(target, mix);
+
+                // modulated parameters are set as the effects are processed
+                if !modulation.is_modulated(target) {
+                    audio.processors.spectral_filter.set_mix(mix);
+                }
+            }
+            FXMessage::SetDelayTime(time_ms) => {
+                let modulation = &mut audio.processors.modulation;
+                let target = ModTarget::DelayTime.idx();
+                modulation.set_base(target, time_ms);
+
+                audio.data.delay_time_ms = time_ms;
+                if !modulation.is_modulated(target) {
+                    audio.processors.delay.set_delay_time(time_ms / 1000.0);
+                }
+            }
+            FXMessage::SetDelayMix(mix) => {
+                let delay_mix = &mut audio.data.delay_mix;
+
+                // the delay isn't fed while it's silent, so its contents
+                // are stale
+                if !delay_mix.is_active() && delay_mix.current_value() <= 0.0
+                {
+                    audio.processors.delay.clear();
+                }
+
+                delay_mix.set_target_value(mix);
+            }
+            FXMessage::SetTransientAttack(gain_db) => {
+                audio.processors.transient_shaper.set_attack_db(gain_db);
+            }
+            FXMessage::SetTransientSustain(gain_db) => {
+                audio.processors.transient_shaper.set_sustain_db(gain_db);
+            }
+            FXMessage::SetExciterDrive(drive_db) => {
+                audio.processors.exciter.set_drive_db(drive_db);
+            }
+            FXMessage::SetExciterMix(mix) => {
+                audio.processors.exciter.set_mix(mix);
+            }
+            FXMessage::SetCrushBits(bits) => {
+                audio.processors.bitcrusher.set_bit_depth(bits);
+            }
+            FXMessage::SetCrushDownsample(factor) => {
+                audio.processors.bitcrusher.set_downsample_factor(factor);
+            }
+            FXMessage::SetCrushJitter(jitter) => {
+                audio.processors.bitcrusher.set_jitter(jitter);
+            }
+            FXMessage::SetCrushMix(mix) => {
+                audio.processors.bitcrusher.set_mix(mix);
+            }
+            FXMessage::SetEqBands(bands) => {
+                audio.processors.eq.set_bands(bands.iter().flatten());
+            }
+            FXMessage::FadeOutput { gain_db, fade_secs } => {
+                let fade = &mut audio.data.output_fade;
+                fade.set_smoothing_period(fade_secs * 1000.0);
+                fade.set_target_value(db_to_level(gain_db));
+            }
+            FXMessage::SetAsleep(asleep) => {
+                audio.data.is_asleep = asleep;
+            }
+            FXMessage::SetBinaural(binaural) => {
+                let binaural_mix = &mut audio.data.binaural_mix;
+
+                // the decoder isn't fed while it's deselected, so its tails
+                // are stale
+                if !binaural_mix.is_active()
+                    && binaural_mix.current_value() <= 0.0
+                {
+                    audio.processors.binaural.reset();
+                }
+
+                binaural_mix.set_target_value(if binaural { 1.0 } else { 0.0 });
+            }
+        }
+    }
+}
+
+/// Starts recording the stems of a buffer of `len_frames`, if they are
+/// being recorded.
+fn begin_stems(audio: &mut AudioModel, len_frames: usize) {
+    if let Some(stems) = audio.processors.stems.as_mut() {
+        stems.begin(len_frames);
+    }
+}
+
+/// Records the main channels of `buffer` as `stem`, if the stems are being
+/// recorded.
+fn tap_stem(audio: &mut AudioModel, stem: Stem, buffer: &AudioBuffer) {
+    if let Some(stems) = audio.processors.stems.as_mut() {
+        stems.tap(stem, buffer);
+    }
+}
+
+/// Records `buffer` as the master stem, and sends the buffer's stems to be
+/// written.
+fn finish_stems(audio: &mut AudioModel, buffer: &AudioBuffer) {
+    let Some(stems) = audio.processors.stems.as_mut()
+    else {
+        return;
+    };
+
+    stems.tap(Stem::Master, buffer);
+    stems.finish();
+
+    // the writer thread has already reported why it stopped
+    if stems.is_stopped() {
+        audio.processors.stems = None;
+    }
+}
+
 /// Sets the audio callback timer.
 fn callback_timer(audio: &AudioModel) {
     // the chance of not being able to acquire the lock is very small here,
@@ -120,8 +395,402 @@ fn callback_timer(audio: &AudioModel) {
     }
 }
 
+/// Attributes the time since the last stage to `stage`.
+fn profile(audio: &mut AudioModel, stage: DspStage) {
+    audio.processors.profiler.lap(stage);
+}
+
+/// Attributes the rest of the buffer to `stage`, and publishes the DSP
+/// load to the UI thread if it has been averaged.
+fn finish_profile(audio: &mut AudioModel, stage: DspStage, len_frames: usize) {
+    let profiler = &mut audio.processors.profiler;
+    profiler.lap(stage);
+
+    let load = profiler.finish(len_frames, audio.data.sample_rate.lr());
+
+    if let (Some(load), Some(input)) =
+        (load, audio.context.dsp_load_input.as_mut())
+    {
+        input.write(load);
+    }
+}
+
+/// Analyzes the output spectrum, and publishes it to the UI thread if it
+/// changed.
+fn analyze_spectrum(audio: &mut AudioModel, buffer: &AudioBuffer) {
+    let Some(input) = audio.context.spectrum_input.as_mut()
+    else {
+        return;
+    };
+
+    let analyzer = &mut audio.processors.spectrum_analyzer;
+    analyzer.process_block(buffer);
+
+    if analyzer.take_updated() {
+        if let Some(feedback) = audio.processors.feedback.as_mut() {
+            feedback.analyze(analyzer.spectrum());
+        }
+
+        // the buffer is reused, so nothing is allocated here
+        input.input_buffer_mut().copy_from_slice(analyzer.spectrum());
+        input.publish();
+    }
+}
+
+/// Follows the output's perceptual band energies, and publishes them to the
+/// parameter updater if they changed.
+fn analyze_bands(audio: &mut AudioModel, buffer: &AudioBuffer) {
+    let Some(input) = audio.context.band_energy_input.as_mut()
+    else {
+        return;
+    };
+
+    let analyzer = &mut audio.processors.band_analyzer;
+    analyzer.process_block(buffer);
+
+    if analyzer.take_updated() {
+        input.input_buffer_mut().copy_from_slice(analyzer.envelopes());
+        input.publish();
+    }
+}
+
 /// Processes all audio FX.
 #[allow(clippy::needless_range_loop)]
-fn process_fx(audio: &mut AudioModel, buffer: &mut Buffer<f64>) {
-    //
+fn process_fx(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    process_modulation(audio, buffer.len_frames());
+    profile(audio, DspStage::Control);
+
+    let spectral_filter = &mut audio.processors.spectral_filter;
+
+    if let Some(mask) = audio.context.spectral_mask_output.as_mut()
+        && mask.updated()
+    {
+        spectral_filter.set_mask(mask.read());
+    }
+
+    spectral_filter.process_block(buffer);
+    profile(audio, DspStage::SpectralFilter);
+
+    let control = &audio.data.pitch_shift;
+
+    if control.enabled.lr() {
+        let pitch_shifter = &mut audio.processors.pitch_shifter;
+
+        let block_size = control.block_size.lr();
+        if block_size != pitch_shifter.block_size() {
+            pitch_shifter.set_block_size(block_size);
+        }
+
+        pitch_shifter.set_preserve_formants(control.preserve_formants.lr());
+        pitch_shifter.set_shift_semitones(control.semitones.lr());
+        pitch_shifter.process_block(buffer);
+    }
+
+    tap_stem(audio, Stem::PostSpectral, buffer);
+    profile(audio, DspStage::PitchShifter);
+
+    process_transient_shaper(audio, buffer);
+    process_exciter(audio, buffer);
+    process_bitcrusher(audio, buffer);
+    profile(audio, DspStage::Effects);
+
+    process_delay(audio, buffer);
+    profile(audio, DspStage::Delay);
+
+    process_tilt(audio, buffer);
+    process_eq(audio, buffer);
+    process_master_gain(audio, buffer);
+    profile(audio, DspStage::Eq);
+
+    // the notches are analyzed along with the output, so that notched
+    // frequencies stop being detected
+    if let Some(feedback) = audio.processors.feedback.as_mut() {
+        feedback.process(buffer);
+    }
+}
+
+/// Processes the modulation matrix for the buffer, and applies it to the
+/// parameters which are only updated once per buffer.
+fn process_modulation(audio: &mut AudioModel, buffer_len: usize) {
+    let modulation = &mut audio.processors.modulation;
+
+    for (n, gesture) in audio.data.modulation.gestures.iter().enumerate() {
+        modulation.set_external(ModInput::Gesture(n).idx(), gesture.lr());
+    }
+
+    modulation.process_block(buffer_len);
+
+    let target = ModTarget::SpectralMix.idx();
+    if modulation.is_modulated(target) {
+        audio.processors.spectral_filter.set_mix(modulation.value(target));
+    }
+}
+
+/// Shapes the transients of the main channels, unless the shaper is
+/// neutral.
+fn process_transient_shaper(
+    audio: &mut AudioModel,
+    buffer: &mut AudioBuffer,
+) {
+    let shaper = &mut audio.processors.transient_shaper;
+
+    if shaper.is_neutral() {
+        return;
+    }
+
+    let num_channels = buffer.channels();
+
+    for frame in 0..buffer.len_frames() {
+        let idx = frame * num_channels;
+
+        (buffer[idx], buffer[idx + 1]) =
+            shaper.process_stereo(buffer[idx], buffer[idx + 1]);
+    }
+}
+
+/// Excites the highs of the main channels, unless the exciter is silent.
+fn process_exciter(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    let exciter = &mut audio.processors.exciter;
+
+    if exciter.is_silent() {
+        return;
+    }
+
+    process_stereo_blocks(buffer, |left, right| {
+        exciter.process_block(left, right);
+    });
+}
+
+/// Crushes the main channels, unless the bitcrusher is dry.
+fn process_bitcrusher(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    let bitcrusher = &mut audio.processors.bitcrusher;
+
+    if bitcrusher.is_dry() {
+        return;
+    }
+
+    let num_channels = buffer.channels();
+
+    for frame in 0..buffer.len_frames() {
+        let idx = frame * num_channels;
+
+        (buffer[idx], buffer[idx + 1]) =
+            bitcrusher.process_stereo(buffer[idx], buffer[idx + 1]);
+    }
+}
+
+/// Adds the delay to the main channels, unless its mix is silent.
+fn process_delay(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    let mix = &mut audio.data.delay_mix;
+
+    if !mix.is_active() && mix.current_value() <= 0.0 {
+        return;
+    }
+
+    let delay = &mut audio.processors.delay;
+    let num_channels = buffer.channels();
+
+    let target = ModTarget::DelayTime.idx();
+    let delay_times = audio
+        .processors
+        .modulation
+        .is_modulated(target)
+        .then(|| audio.processors.modulation.values(target));
+    let mut stems = audio.processors.stems.as_mut();
+
+    // the delay time may be shorter than a block, so its feedback (and the
+    // filters on it) can't be processed in blocks
+    for frame in 0..buffer.len_frames() {
+        let idx = frame * num_channels;
+        let level = mix.next();
+
+        if let Some(times) = delay_times {
+            delay.set_delay_time(times[frame] / 1000.0);
+        }
+
+        let (left, right) =
+            delay.process_stereo(buffer[idx], buffer[idx + 1]);
+
+        buffer[idx] += left * level;
+        buffer[idx + 1] += right * level;
+
+        if let Some(stems) = &mut stems {
+            stems.add(Stem::FxReturns, frame, left * level, right * level);
+        }
+    }
+}
+
+/// Tilts the spectrum of the main channels, as set by the parameter thread.
+fn process_tilt(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    let tilt = &mut audio.processors.tilt;
+    tilt.set_tilt_db(audio.data.tilt.tilt_db.lr());
+
+    if tilt.is_flat() {
+        return;
+    }
+
+    process_stereo_blocks(buffer, |left, right| {
+        tilt.process_block(left, right);
+    });
+}
+
+/// Applies the room's EQ to the main channels, unless it is flat.
+fn process_eq(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    let eq = &mut audio.processors.eq;
+
+    if eq.is_flat() {
+        return;
+    }
+
+    process_channel_blocks(buffer, |block, ch| eq.process_block(block, ch));
+}
+
+/// Removes DC and subsonics from the main channels, which gestures may
+/// otherwise modulate into the output.
+fn process_dc_filter(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    let dc_filter = &mut audio.processors.dc_filter;
+
+    process_channel_blocks(buffer, |block, ch| {
+        dc_filter.process_block(block, ch);
+    });
+}
+
+/// Limits the main channels, as the final stage before the output.
+fn process_limiter(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    let Some(limiter) = audio.processors.limiter.as_mut()
+    else {
+        return;
+    };
+
+    let num_channels = buffer.channels();
+
+    for frame in 0..buffer.len_frames() {
+        let idx = frame * num_channels;
+
+        (buffer[idx], buffer[idx + 1]) =
+            limiter.process_stereo(buffer[idx], buffer[idx + 1]);
+    }
+}
+
+/// Renders the main channels for headphones while binaural monitoring is
+/// selected, crossfading as it is switched.
+fn process_binaural(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    let mix = &mut audio.data.binaural_mix;
+
+    if !mix.is_active() && mix.current_value() <= 0.0 {
+        return;
+    }
+
+    let decoder = &mut audio.processors.binaural;
+    let num_channels = buffer.channels();
+
+    for frame in 0..buffer.len_frames() {
+        let idx = frame * num_channels;
+        let level = mix.next();
+
+        let (dry_l, dry_r) = (buffer[idx], buffer[idx + 1]);
+        let (left, right) = decoder.process(&[dry_l, dry_r]);
+
+        buffer[idx] = lerp(dry_l, left, level);
+        buffer[idx + 1] = lerp(dry_r, right, level);
+    }
+}
+
+/// Applies the wind-down fade to every channel, including the metronome.
+fn process_output_fade(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    let fade = &mut audio.data.output_fade;
+
+    if !fade.is_active() && fade.current_value() == 1.0 {
+        return;
+    }
+
+    let num_channels = buffer.channels();
+
+    for frame in 0..buffer.len_frames() {
+        let idx = frame * num_channels;
+        let level = fade.next();
+
+        for ch in 0..num_channels {
+            buffer[idx + ch] *= level;
+        }
+    }
+}
+
+/// Applies the master gain to the main channels, so the metronome is left
+/// unaffected.
+fn process_master_gain(audio: &mut AudioModel, buffer: &mut AudioBuffer) {
+    let gain = &mut audio.data.master_gain;
+
+    if !gain.is_active() && gain.current_value() == 1.0 {
+        return;
+    }
+
+    let num_channels = buffer.channels();
+
+    for frame in 0..buffer.len_frames() {
+        let idx = frame * num_channels;
+        let level = gain.next();
+
+        for ch in 0..NUM_CHANNELS.min(num_channels) {
+            buffer[idx + ch] *= level;
+        }
+    }
+}
+
+/// Copies each main channel of `buffer` into contiguous blocks of up to
+/// [`MAX_BLOCK_SIZE`] frames, which `process_channel` processes in place
+/// with the index of their channel.
+fn process_channel_blocks(
+    buffer: &mut AudioBuffer,
+    mut process_channel: impl FnMut(&mut [f64], usize),
+) {
+    let num_channels = buffer.channels();
+    let len_frames = buffer.len_frames();
+    let mut block = [0.0; MAX_BLOCK_SIZE];
+
+    for start in (0..len_frames).step_by(MAX_BLOCK_SIZE) {
+        let block = &mut block[..MAX_BLOCK_SIZE.min(len_frames - start)];
+
+        for ch in 0..NUM_CHANNELS.min(num_channels) {
+            for (i, sample) in block.iter_mut().enumerate() {
+                *sample = buffer[(start + i) * num_channels + ch];
+            }
+
+            process_channel(block, ch);
+
+            for (i, &sample) in block.iter().enumerate() {
+                buffer[(start + i) * num_channels + ch] = sample;
+            }
+        }
+    }
+}
+
+/// Copies the left and right channels of `buffer` into contiguous blocks of
+/// up to [`MAX_BLOCK_SIZE`] frames, which `process_pair` processes in place.
+/// This suits stereo processors whose channels share their state.
+fn process_stereo_blocks(
+    buffer: &mut AudioBuffer,
+    mut process_pair: impl FnMut(&mut [f64], &mut [f64]),
+) {
+    let num_channels = buffer.channels();
+    let len_frames = buffer.len_frames();
+    let mut left = [0.0; MAX_BLOCK_SIZE];
+    let mut right = [0.0; MAX_BLOCK_SIZE];
+
+    for start in (0..len_frames).step_by(MAX_BLOCK_SIZE) {
+        let len = MAX_BLOCK_SIZE.min(len_frames - start);
+        let (left, right) = (&mut left[..len], &mut right[..len]);
+
+        for i in 0..len {
+            let idx = (start + i) * num_channels;
+            (left[i], right[i]) = (buffer[idx], buffer[idx + 1]);
+        }
+
+        process_pair(left, right);
+
+        for i in 0..len {
+            let idx = (start + i) * num_channels;
+            (buffer[idx], buffer[idx + 1]) = (left[i], right[i]);
+        }
+    }
 }
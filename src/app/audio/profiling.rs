@@ -0,0 +1,228 @@
+//! Profiling of the audio callback, so that the DSP load (and which stage
+//! of the signal chain it comes from) can be shown.
+//!
+//! The time spent in each stage is summed over [`DSP_LOAD_AVERAGING_SAMPLES`]
+//! buffers, and published to the UI thread as a fraction of the time those
+//! buffers last for.
+
+use super::*;
+use std::fmt::{Display, Formatter};
+use std::time::Instant;
+
+/// The number of profiled stages.
+pub const NUM_DSP_STAGES: usize = 9;
+
+/// A stage of the signal chain which is timed separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DspStage {
+    /// Control messages and the modulation matrix.
+    Control,
+    /// The voices, sample player, input and granular engine.
+    Voices,
+    SpectralFilter,
+    PitchShifter,
+    /// The transient shaper, exciter and bitcrusher.
+    Effects,
+    Delay,
+    /// The tilt, EQ and master gain.
+    Eq,
+    /// The spectrum and band analyzers, and the feedback suppressor.
+    Analysis,
+    /// The metronome, and everything after it up to the true-peak meter.
+    Output,
+}
+
+impl DspStage {
+    pub const ALL: [Self; NUM_DSP_STAGES] = [
+        Self::Control,
+        Self::Voices,
+        Self::SpectralFilter,
+        Self::PitchShifter,
+        Self::Effects,
+        Self::Delay,
+        Self::Eq,
+        Self::Analysis,
+        Self::Output,
+    ];
+
+    pub const fn idx(self) -> usize {
+        self as usize
+    }
+}
+
+impl Display for DspStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Control => "control",
+            Self::Voices => "voices",
+            Self::SpectralFilter => "spectral filter",
+            Self::PitchShifter => "pitch shifter",
+            Self::Effects => "effects",
+            Self::Delay => "delay",
+            Self::Eq => "tilt and EQ",
+            Self::Analysis => "analysis",
+            Self::Output => "output",
+        })
+    }
+}
+
+/// The DSP load over a number of buffers, where `1.0` is all of the time
+/// which the buffers last for.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DspLoad {
+    /// The average load.
+    pub load: f64,
+    /// The highest load of a single buffer.
+    pub peak: f64,
+    /// The average load of each stage, which sum to `load`.
+    pub stages: [f64; NUM_DSP_STAGES],
+}
+
+impl DspLoad {
+    /// The stages in order of their load, highest first.
+    pub fn stages_by_load(&self) -> [(DspStage, f64); NUM_DSP_STAGES] {
+        let mut stages = DspStage::ALL.map(|s| (s, self.stages[s.idx()]));
+        stages.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        stages
+    }
+}
+
+/// Times the stages of the audio callback.
+#[derive(Clone, Debug)]
+pub struct DspProfiler {
+    /// When the current stage started.
+    lap_start: Instant,
+    /// The time spent in the current buffer, in seconds.
+    buffer_secs: f64,
+    /// The time spent in each stage since the last load, in seconds.
+    stage_secs: [f64; NUM_DSP_STAGES],
+    /// The time which the buffers since the last load last for, in seconds.
+    period_secs: f64,
+    peak: f64,
+    num_buffers: usize,
+}
+
+impl DspProfiler {
+    pub fn new() -> Self {
+        Self {
+            lap_start: Instant::now(),
+            buffer_secs: 0.0,
+            stage_secs: [0.0; NUM_DSP_STAGES],
+            period_secs: 0.0,
+            peak: 0.0,
+            num_buffers: 0,
+        }
+    }
+
+    /// Starts timing a buffer.
+    pub fn begin(&mut self) {
+        self.lap_start = Instant::now();
+        self.buffer_secs = 0.0;
+    }
+
+    /// Attributes the time since the last lap (or since the buffer began)
+    /// to `stage`.
+    pub fn lap(&mut self, stage: DspStage) {
+        let now = Instant::now();
+        let secs = now.duration_since(self.lap_start).as_secs_f64();
+        self.lap_start = now;
+
+        self.record(stage, secs);
+    }
+
+    /// Finishes timing a buffer of `len_frames`, and returns the load if
+    /// enough buffers have been timed.
+    pub fn finish(
+        &mut self,
+        len_frames: usize,
+        sample_rate: f64,
+    ) -> Option<DspLoad> {
+        let period = len_frames as f64 / sample_rate;
+
+        if period > 0.0 {
+            self.peak = self.peak.max(self.buffer_secs / period);
+        }
+
+        self.period_secs += period;
+        self.num_buffers += 1;
+
+        if self.num_buffers < DSP_LOAD_AVERAGING_SAMPLES
+            || self.period_secs <= 0.0
+        {
+            return None;
+        }
+
+        let stages = self.stage_secs.map(|secs| secs / self.period_secs);
+        let load = DspLoad {
+            load: stages.iter().sum(),
+            peak: self.peak,
+            stages,
+        };
+
+        self.stage_secs = [0.0; NUM_DSP_STAGES];
+        self.period_secs = 0.0;
+        self.peak = 0.0;
+        self.num_buffers = 0;
+
+        Some(load)
+    }
+
+    fn record(&mut self, stage: DspStage, secs: f64) {
+        self.stage_secs[stage.idx()] += secs;
+        self.buffer_secs += secs;
+    }
+}
+
+impl Default for DspProfiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_load_is_averaged_over_the_buffers() {
+        let mut profiler = DspProfiler::new();
+        let sample_rate = 1000.0;
+
+        // each buffer lasts 0.1 s, and the last is the most expensive
+        for n in 1..=DSP_LOAD_AVERAGING_SAMPLES {
+            profiler.begin();
+            profiler.record(DspStage::Voices, 0.01);
+            profiler.record(DspStage::Delay, 0.02);
+
+            if n == DSP_LOAD_AVERAGING_SAMPLES {
+                profiler.record(DspStage::Delay, 0.05);
+            }
+
+            let load = profiler.finish(100, sample_rate);
+            assert_eq!(load.is_some(), n == DSP_LOAD_AVERAGING_SAMPLES);
+
+            let Some(load) = load
+            else {
+                continue;
+            };
+
+            let extra = 0.5 / DSP_LOAD_AVERAGING_SAMPLES as f64;
+            assert!((load.stages[DspStage::Voices.idx()] - 0.1).abs() < 1e-9);
+            assert!(
+                (load.stages[DspStage::Delay.idx()] - 0.2 - extra).abs()
+                    < 1e-9
+            );
+            assert!((load.load - 0.3 - extra).abs() < 1e-9);
+            assert!((load.peak - 0.8).abs() < 1e-9);
+
+            assert_eq!(load.stages_by_load()[0].0, DspStage::Delay);
+        }
+
+        // the next window starts from nothing
+        profiler.begin();
+        profiler.finish(100, sample_rate);
+        assert_eq!(profiler.num_buffers, 1);
+        assert!(profiler.stage_secs.iter().all(|&s| s == 0.0));
+    }
+}
@@ -0,0 +1,308 @@
+//! Corrective EQ presets for each room the installation is set up in.
+//!
+//! Rooms are stored together in a JSON file of the form:
+//!
+//! ```json
+//! {
+//!     "active": "Gallery",
+//!     "rooms": [
+//!         {
+//!             "name": "Gallery",
+//!             "bands": [
+//!                 { "type": "low-shelf", "freq": 120, "gain": -4, "q": 0.7 },
+//!                 { "type": "peak", "freq": 2500, "gain": -2.5, "q": 2 }
+//!             ]
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! Each band's `"gain"` defaults to `0` and its `"q"` to
+//! [`BUTTERWORTH_Q`]. While no room is active, the EQ is flat.
+
+use super::*;
+use crate::dsp::filtering::parametric_eq::MAX_EQ_BANDS;
+use serde_json::{json, Value};
+
+/// The bands of the master EQ, as sent to the audio thread.
+pub type EqBands = [Option<EqBand>; MAX_EQ_BANDS];
+
+/// The name shown while no room is active.
+pub const FLAT_ROOM_NAME: &str = "Flat";
+
+fn filter_type_from_name(name: &str) -> Option<FilterType> {
+    match name.to_lowercase().as_str() {
+        "peak" => Some(FilterType::Peak),
+        "low-shelf" => Some(FilterType::Lowshelf),
+        "high-shelf" => Some(FilterType::Highshelf),
+        "lowpass" => Some(FilterType::Lowpass),
+        "highpass" => Some(FilterType::Highpass),
+        _ => None,
+    }
+}
+
+const fn filter_type_name(filter_type: FilterType) -> &'static str {
+    match filter_type {
+        FilterType::Lowshelf => "low-shelf",
+        FilterType::Highshelf => "high-shelf",
+        FilterType::Lowpass => "lowpass",
+        FilterType::Highpass => "highpass",
+        _ => "peak",
+    }
+}
+
+/// The corrective EQ of one room.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoomPreset {
+    pub name: String,
+    pub bands: Vec<EqBand>,
+}
+
+impl RoomPreset {
+    /// Parses a room from a JSON value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` is not a valid room.
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let Some(name) = value.get("name").and_then(Value::as_str)
+        else {
+            return Err(String::from("room is missing a \"name\""));
+        };
+
+        let err = |e: String| format!("in room \"{name}\": {e}");
+
+        let Some(bands) = value.get("bands").and_then(Value::as_array)
+        else {
+            return Err(err("expected a \"bands\" array".into()));
+        };
+
+        if bands.len() > MAX_EQ_BANDS {
+            return Err(err(format!(
+                "at most {MAX_EQ_BANDS} bands may be used"
+            )));
+        }
+
+        let bands = bands
+            .iter()
+            .map(|band| Self::band_from_json(band).map_err(err))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { name: String::from(name), bands })
+    }
+
+    fn band_from_json(value: &Value) -> Result<EqBand, String> {
+        let number = |key: &str| -> Result<Option<f64>, String> {
+            value.get(key).map_or(Ok(None), |v| {
+                v.as_f64()
+                    .filter(|v| v.is_finite())
+                    .map(Some)
+                    .ok_or_else(|| format!("\"{key}\" must be a number"))
+            })
+        };
+
+        let filter_type = match value.get("type").and_then(Value::as_str) {
+            Some(name) => filter_type_from_name(name)
+                .ok_or_else(|| format!("unknown band type \"{name}\""))?,
+            None => return Err(String::from("band is missing a \"type\"")),
+        };
+
+        let Some(freq) = number("freq")?.filter(|f| *f > 0.0)
+        else {
+            return Err(String::from("band needs a positive \"freq\""));
+        };
+
+        Ok(EqBand {
+            filter_type,
+            freq,
+            gain_db: number("gain")?.unwrap_or(0.0),
+            q: number("q")?.unwrap_or(BUTTERWORTH_Q),
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let bands: Vec<Value> = self
+            .bands
+            .iter()
+            .map(|band| {
+                json!({
+                    "type": filter_type_name(band.filter_type),
+                    "freq": band.freq,
+                    "gain": band.gain_db,
+                    "q": band.q,
+                })
+            })
+            .collect();
+
+        json!({ "name": self.name, "bands": bands })
+    }
+
+    /// The room's bands, in the form sent to the audio thread.
+    pub fn eq_bands(&self) -> EqBands {
+        let mut bands = [None; MAX_EQ_BANDS];
+
+        for (slot, band) in bands.iter_mut().zip(&self.bands) {
+            *slot = Some(*band);
+        }
+
+        bands
+    }
+}
+
+// *** *** *** //
+
+/// All known rooms, and which one is active.
+#[derive(Clone, Debug, Default)]
+pub struct RoomPresetStore {
+    /// Where the rooms are saved to, if anywhere.
+    path: Option<String>,
+    rooms: Vec<RoomPreset>,
+    /// The active room, or `None` if the EQ is flat.
+    active_idx: Option<usize>,
+}
+
+impl RoomPresetStore {
+    /// A store without any rooms, which is never saved.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads rooms from the file at `path`, which the active room will also
+    /// be saved to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read rooms \"{path}\": {e}"))?;
+
+        let mut store = Self::from_json_str(&contents)?;
+        store.path = Some(String::from(path));
+
+        Ok(store)
+    }
+
+    /// Parses rooms from a JSON string. The returned store is never saved.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` is not a valid rooms file.
+    pub fn from_json_str(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        let Some(rooms) = value.get("rooms").and_then(Value::as_array)
+        else {
+            return Err(String::from("expected a \"rooms\" array"));
+        };
+
+        let mut store = Self {
+            path: None,
+            rooms: rooms
+                .iter()
+                .map(RoomPreset::from_json)
+                .collect::<Result<_, _>>()?,
+            active_idx: None,
+        };
+
+        if let Some(active) = value.get("active").and_then(Value::as_str) {
+            _ = store.select(active);
+        }
+
+        Ok(store)
+    }
+
+    /// Writes all rooms, and which one is active, to the store's file, if
+    /// it has one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be written.
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path
+        else {
+            return Ok(());
+        };
+
+        let contents = serde_json::to_string_pretty(&self.to_json())
+            .map_err(|e| e.to_string())?;
+
+        std::fs::write(path, contents)
+            .map_err(|e| format!("failed to write rooms \"{path}\": {e}"))
+    }
+
+    /// All rooms as JSON, in the same form which is parsed by
+    /// [`from_json_str()`](Self::from_json_str).
+    pub fn to_json(&self) -> Value {
+        let rooms: Vec<Value> =
+            self.rooms.iter().map(RoomPreset::to_json).collect();
+
+        let mut value = json!({ "rooms": rooms });
+
+        if let Some(room) = self.active() {
+            value["active"] = json!(room.name);
+        }
+
+        value
+    }
+
+    /// The active room, or `None` if the EQ is flat.
+    pub fn active(&self) -> Option<&RoomPreset> {
+        self.active_idx.map(|idx| &self.rooms[idx])
+    }
+
+    /// The name of the active room.
+    pub fn active_name(&self) -> &str {
+        self.active().map_or(FLAT_ROOM_NAME, |room| room.name.as_str())
+    }
+
+    /// The bands of the active room, which are all `None` if the EQ is flat.
+    pub fn active_bands(&self) -> EqBands {
+        self.active().map_or([None; MAX_EQ_BANDS], RoomPreset::eq_bands)
+    }
+
+    /// Makes the room called `name` active.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no room called `name`.
+    pub fn select(&mut self, name: &str) -> Result<(), String> {
+        let Some(idx) = self.rooms.iter().position(|r| r.name == name)
+        else {
+            return Err(format!("no room called \"{name}\""));
+        };
+
+        self.active_idx = Some(idx);
+        Ok(())
+    }
+
+    /// Makes the room at `idx` active, or flattens the EQ if `idx` is
+    /// `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no room at `idx`.
+    pub fn select_idx(&mut self, idx: Option<usize>) -> Result<(), String> {
+        if let Some(idx) = idx
+            && idx >= self.rooms.len()
+        {
+            return Err(format!(
+                "there is no room {} ({} rooms are loaded)",
+                idx + 1,
+                self.rooms.len()
+            ));
+        }
+
+        self.active_idx = idx;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.rooms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rooms.is_empty()
+    }
+}
@@ -0,0 +1,626 @@
+//! Recording of the output as separate stems, so that installation sessions
+//! can be remixed later.
+//!
+//! The audio thread taps the signal at each [`Stem`]'s stage of
+//! [`process()`](super::process), and hands each buffer's frames to a
+//! writer thread. All of the stems are written to a single multi-channel
+//! 32-bit float WAV file, as a stereo pair per stem in the order of
+//! [`Stem::ALL`].
+//!
+//! The master may also be recorded as first-order ambisonics, in a 4-channel
+//! B-format WAV alongside the stems, so that sessions can later be decoded
+//! for other speaker layouts or rendered binaurally. The output is stereo,
+//! so the master is encoded as a pair of speakers in front of the listener.
+//!
+//! WAV files can't hold more than 4 GiB of audio, so long sessions are
+//! split across numbered files, such as `session.wav`, `session_2.wav` and
+//! so on.
+
+use super::*;
+use crate::dsp::util::ambisonics::{
+    AmbisonicEncoder, NUM_BFORMAT_CHANNELS,
+};
+use crossbeam_channel::{bounded, Receiver as CCReceiver, Sender as CCSender};
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::thread;
+
+/// The number of buffers which may be queued for the writer thread.
+/// Buffers which arrive while the queue is full are written as silence, so
+/// that the stems stay in time.
+const BLOCK_QUEUE_LEN: usize = 64;
+/// How often the size of the file is written to its header, in seconds, so
+/// that a recording survives a crash.
+const HEADER_UPDATE_INTERVAL_SECS: f64 = 1.0;
+/// The most audio which is written to each file, in bytes.
+pub(super) const MAX_WAV_DATA_BYTES: u64 = u32::MAX as u64 - 1024;
+
+/// A stage of the signal chain which is recorded as a stem.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stem {
+    /// The voices, sample player, input and granular engine, before any
+    /// processing.
+    Dry,
+    /// The output of the spectral filter and pitch shifter.
+    PostSpectral,
+    /// The returns of the send effects, without the signal which was sent
+    /// to them.
+    FxReturns,
+    /// The final output.
+    Master,
+}
+
+impl Stem {
+    /// Every stem, in the order which they are written.
+    pub const ALL: [Self; 4] =
+        [Self::Dry, Self::PostSpectral, Self::FxReturns, Self::Master];
+
+    /// The first channel of the stem in the recording.
+    const fn channel_offset(self) -> usize {
+        self as usize * NUM_CHANNELS
+    }
+}
+
+/// The number of channels in a stem recording.
+pub const NUM_STEM_CHANNELS: usize = Stem::ALL.len() * NUM_CHANNELS;
+
+/// A buffer's worth of stem frames, sent to the writer thread.
+struct StemBlock {
+    /// The interleaved frames of every stem.
+    samples: Vec<f32>,
+    /// The number of frames which were dropped before this block, which are
+    /// written as silence.
+    missed_frames: usize,
+}
+
+/// The audio thread's side of the stem recorder, which taps each stage of
+/// the signal chain.
+pub struct StemTaps {
+    /// The frames of the current buffer, if a block was available for it.
+    block: Option<Vec<f32>>,
+    len_frames: usize,
+    /// The number of frames which couldn't be sent to the writer thread.
+    missed_frames: usize,
+
+    blocks: CCSender<StemBlock>,
+    /// Blocks which the writer thread has emptied, so that the audio thread
+    /// doesn't allocate.
+    spare: CCReceiver<Vec<f32>>,
+    /// Whether the writer thread has stopped.
+    is_stopped: bool,
+}
+
+impl StemTaps {
+    /// Starts recording stems at `sample_rate` to `path` on a new thread,
+    /// and the master as B-format to `bformat_path` if it is provided.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either file could not be created, or the writer
+    /// thread could not be started.
+    pub fn record(
+        path: &str,
+        bformat_path: Option<&str>,
+        sample_rate: f64,
+    ) -> io::Result<Self> {
+        let writer =
+            StemWriter::create(path, bformat_path, sample_rate as u32)?;
+        let (taps, blocks, spare) = Self::with_queue();
+
+        thread::Builder::new()
+            .name(String::from("maestro_stem_recorder"))
+            .spawn(move || writer.run(&blocks, &spare))?;
+
+        Ok(taps)
+    }
+
+    /// Returns the taps, the receiver of their blocks, and the sender of
+    /// emptied blocks, with every block allocated ahead of time.
+    fn with_queue() -> (Self, CCReceiver<StemBlock>, CCSender<Vec<f32>>) {
+        let (blocks, block_receiver) = bounded(BLOCK_QUEUE_LEN);
+        let (spare_sender, spare) = bounded(BLOCK_QUEUE_LEN);
+
+        for _ in 0..BLOCK_QUEUE_LEN {
+            let block = Vec::with_capacity(MAX_BUFFER_SIZE * NUM_STEM_CHANNELS);
+            _ = spare_sender.try_send(block);
+        }
+
+        let taps = Self {
+            block: None,
+            len_frames: 0,
+            missed_frames: 0,
+            blocks,
+            spare,
+            is_stopped: false,
+        };
+
+        (taps, block_receiver, spare_sender)
+    }
+
+    /// Starts a buffer of `len_frames` frames, which every stem is silent
+    /// for until it is tapped.
+    pub fn begin(&mut self, len_frames: usize) {
+        self.len_frames = len_frames;
+
+        if self.block.is_none() {
+            match self.spare.try_recv() {
+                Ok(block) => self.block = Some(block),
+                Err(e) => self.is_stopped |= e.is_disconnected(),
+            }
+        }
+
+        if let Some(block) = &mut self.block {
+            block.clear();
+            block.resize(len_frames * NUM_STEM_CHANNELS, 0.0);
+        }
+    }
+
+    /// Records the main channels of `buffer` as `stem`.
+    pub fn tap(&mut self, stem: Stem, buffer: &AudioBuffer) {
+        let num_channels = buffer.channels();
+
+        for frame in 0..buffer.len_frames().min(self.len_frames) {
+            let idx = frame * num_channels;
+            self.add(stem, frame, buffer[idx], buffer[idx + 1]);
+        }
+    }
+
+    /// Adds a stereo frame to `stem` at `frame` of the current buffer.
+    pub fn add(&mut self, stem: Stem, frame: usize, left: f64, right: f64) {
+        let Some(block) = &mut self.block
+        else {
+            return;
+        };
+
+        let idx = frame * NUM_STEM_CHANNELS + stem.channel_offset();
+        block[idx] += left as f32;
+        block[idx + 1] += right as f32;
+    }
+
+    /// Sends the current buffer to the writer thread.
+    pub fn finish(&mut self) {
+        let Some(samples) = self.block.take()
+        else {
+            self.missed_frames += self.len_frames;
+            return;
+        };
+
+        let block =
+            StemBlock { samples, missed_frames: self.missed_frames };
+
+        match self.blocks.try_send(block) {
+            Ok(()) => self.missed_frames = 0,
+            Err(e) => {
+                self.is_stopped = e.is_disconnected();
+                self.missed_frames += self.len_frames;
+                self.block = Some(e.into_inner().samples);
+            }
+        }
+    }
+
+    /// Records a buffer of `len_frames` of silence.
+    pub fn record_silence(&mut self, len_frames: usize) {
+        self.begin(len_frames);
+        self.finish();
+    }
+
+    /// Whether the writer thread has stopped, such as after a write error.
+    pub const fn is_stopped(&self) -> bool {
+        self.is_stopped
+    }
+}
+
+impl std::fmt::Debug for StemTaps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StemTaps")
+            .field("missed_frames", &self.missed_frames)
+            .field("is_stopped", &self.is_stopped)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The writer thread's side of the stem recorder.
+struct StemWriter {
+    wav: WavWriter<BufWriter<File>>,
+    path: String,
+    /// The B-format recording of the master, if it is recorded.
+    bformat: Option<BFormatWriter>,
+    /// The number of the file being written, from `1`.
+    part: usize,
+    sample_rate: u32,
+}
+
+impl StemWriter {
+    fn create(
+        path: &str,
+        bformat_path: Option<&str>,
+        sample_rate: u32,
+    ) -> io::Result<Self> {
+        let bformat = bformat_path
+            .map(|path| BFormatWriter::create(path, sample_rate))
+            .transpose()?;
+
+        Ok(Self {
+            wav: create_wav(path, NUM_STEM_CHANNELS, sample_rate)?,
+            path: String::from(path),
+            bformat,
+            part: 1,
+            sample_rate,
+        })
+    }
+
+    /// Writes each received block until the audio thread stops.
+    fn run(
+        mut self,
+        blocks: &CCReceiver<StemBlock>,
+        spare: &CCSender<Vec<f32>>,
+    ) {
+        let update_interval = (self.sample_rate as f64
+            * HEADER_UPDATE_INTERVAL_SECS) as usize;
+        let mut frames_since_update = 0;
+
+        for StemBlock { samples, missed_frames } in blocks {
+            let num_frames = missed_frames + samples.len() / NUM_STEM_CHANNELS;
+
+            let result = self
+                .write_silence(missed_frames)
+                .and_then(|()| self.write(&samples));
+
+            _ = spare.try_send(samples);
+
+            frames_since_update += num_frames;
+            let result = result.and_then(|()| {
+                if frames_since_update < update_interval {
+                    return Ok(());
+                }
+
+                frames_since_update = 0;
+                self.update_headers()
+            });
+
+            if let Err(e) = result {
+                eprintln!("failed to record stems to \"{}\": {e}", self.path);
+                return;
+            }
+        }
+
+        if let Err(e) = self.update_headers() {
+            eprintln!("failed to finish stems at \"{}\": {e}", self.path);
+        }
+    }
+
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.split_if_full(samples.len())?;
+
+        if let Some(bformat) = &mut self.bformat {
+            bformat.write(samples)?;
+        }
+
+        self.wav.write_samples(samples)
+    }
+
+    fn write_silence(&mut self, num_frames: usize) -> io::Result<()> {
+        self.split_if_full(num_frames * NUM_STEM_CHANNELS)?;
+
+        if let Some(bformat) = &mut self.bformat {
+            bformat.wav.write_silence(num_frames)?;
+        }
+
+        self.wav.write_silence(num_frames)
+    }
+
+    fn update_headers(&mut self) -> io::Result<()> {
+        if let Some(bformat) = &mut self.bformat {
+            bformat.wav.update_header()?;
+        }
+
+        self.wav.update_header()
+    }
+
+    /// Moves on to the next file if `num_samples` won't fit in this one.
+    fn split_if_full(&mut self, num_samples: usize) -> io::Result<()> {
+        let bytes = (num_samples * std::mem::size_of::<f32>()) as u64;
+
+        if self.wav.data_bytes() + bytes <= MAX_WAV_DATA_BYTES {
+            return Ok(());
+        }
+
+        self.update_headers()?;
+        self.part += 1;
+        self.wav = create_wav(
+            &part_path(&self.path, self.part),
+            NUM_STEM_CHANNELS,
+            self.sample_rate,
+        )?;
+
+        // the B-format is smaller, but is split with the stems so that
+        // their parts line up
+        if let Some(bformat) = &mut self.bformat {
+            bformat.wav = create_wav(
+                &part_path(&bformat.path, self.part),
+                NUM_BFORMAT_CHANNELS,
+                self.sample_rate,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the master stem as first-order B-format.
+struct BFormatWriter {
+    wav: WavWriter<BufWriter<File>>,
+    path: String,
+    encoder: AmbisonicEncoder,
+    /// The encoded frames of the current block.
+    samples: Vec<f32>,
+}
+
+impl BFormatWriter {
+    fn create(path: &str, sample_rate: u32) -> io::Result<Self> {
+        Ok(Self {
+            wav: create_wav(path, NUM_BFORMAT_CHANNELS, sample_rate)?,
+            path: String::from(path),
+            encoder: AmbisonicEncoder::stereo(),
+            samples: Vec::with_capacity(
+                MAX_BUFFER_SIZE * NUM_BFORMAT_CHANNELS,
+            ),
+        })
+    }
+
+    /// Encodes the master stem of a block of stem frames, and writes it.
+    fn write(&mut self, stems: &[f32]) -> io::Result<()> {
+        let master = Stem::Master.channel_offset();
+        self.samples.clear();
+
+        for frame in stems.chunks_exact(NUM_STEM_CHANNELS) {
+            let left = frame[master] as f64;
+            let right = frame[master + 1] as f64;
+
+            let bformat = self.encoder.encode(&[left, right]);
+            self.samples.extend(bformat.map(|sample| sample as f32));
+        }
+
+        self.wav.write_samples(&self.samples)
+    }
+}
+
+pub(super) fn create_wav(
+    path: &str,
+    num_channels: usize,
+    sample_rate: u32,
+) -> io::Result<WavWriter<BufWriter<File>>> {
+    let file = BufWriter::new(File::create(path)?);
+    WavWriter::new(file, num_channels as u16, sample_rate)
+}
+
+/// The path of the `part`th file of the recording at `path`, counting from
+/// `1`.
+fn part_path(path: &str, part: usize) -> String {
+    if part <= 1 {
+        return String::from(path);
+    }
+
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}_{part}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_{part}"),
+    };
+
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+// *** *** *** //
+
+/// The byte offset of the RIFF chunk's size.
+const RIFF_SIZE_OFFSET: u64 = 4;
+/// The byte offset of the data chunk's size.
+const DATA_SIZE_OFFSET: u64 = 64;
+/// The sub-format GUID of 32-bit float samples in `WAVE_FORMAT_EXTENSIBLE`.
+const FLOAT_SUBFORMAT: [u8; 16] = [
+    0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA,
+    0x00, 0x38, 0x9B, 0x71,
+];
+
+/// Writes 32-bit float samples to a `WAVE_FORMAT_EXTENSIBLE` file.
+pub(super) struct WavWriter<W: Write + Seek> {
+    writer: W,
+    num_channels: usize,
+    data_bytes: u64,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Writes the header of a file with `num_channels` at `sample_rate`.
+    fn new(
+        mut writer: W,
+        num_channels: u16,
+        sample_rate: u32,
+    ) -> io::Result<Self> {
+        let block_align = num_channels * 4;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVEfmt ")?;
+        writer.write_all(&40u32.to_le_bytes())?;
+        // WAVE_FORMAT_EXTENSIBLE
+        writer.write_all(&0xFFFEu16.to_le_bytes())?;
+        writer.write_all(&num_channels.to_le_bytes())?;
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        writer.write_all(
+            &(sample_rate * block_align as u32).to_le_bytes(),
+        )?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&32u16.to_le_bytes())?;
+        writer.write_all(&22u16.to_le_bytes())?;
+        writer.write_all(&32u16.to_le_bytes())?;
+        // neither the stems nor the B-format are assigned to speakers
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&FLOAT_SUBFORMAT)?;
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+
+        Ok(Self {
+            writer,
+            num_channels: num_channels as usize,
+            data_bytes: 0,
+        })
+    }
+
+    pub(super) fn write_samples(&mut self, samples: &[f32]) -> io::Result<()> {
+        for sample in samples {
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+
+        self.data_bytes += (samples.len() * 4) as u64;
+        Ok(())
+    }
+
+    fn write_silence(&mut self, num_frames: usize) -> io::Result<()> {
+        let silence = [0.0; NUM_STEM_CHANNELS];
+
+        for _ in 0..num_frames {
+            self.write_samples(&silence[..self.num_channels])?;
+        }
+
+        Ok(())
+    }
+
+    const fn data_bytes(&self) -> u64 {
+        self.data_bytes
+    }
+
+    /// Writes the size of the audio so far to the header.
+    pub(super) fn update_header(&mut self) -> io::Result<()> {
+        let data_size = self.data_bytes as u32;
+        let riff_size = data_size + (DATA_SIZE_OFFSET as u32 + 4 - 8);
+
+        self.writer.seek(SeekFrom::Start(RIFF_SIZE_OFFSET))?;
+        self.writer.write_all(&riff_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(DATA_SIZE_OFFSET))?;
+        self.writer.write_all(&data_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::synthesis::audio_file::AudioFileReader;
+    use std::io::Cursor;
+
+    #[test]
+    fn stems_are_written_as_a_multi_channel_wav() {
+        let mut wav =
+            WavWriter::new(Cursor::new(Vec::new()), 8, 48000).unwrap();
+
+        let frame: Vec<f32> = (0..8).map(|i| i as f32 / 8.0).collect();
+        wav.write_samples(&frame).unwrap();
+        wav.write_silence(2).unwrap();
+        wav.update_header().unwrap();
+
+        let mut bytes = wav.writer;
+        bytes.set_position(0);
+
+        let mut reader = AudioFileReader::new(bytes).unwrap();
+        let info = reader.info();
+        assert_eq!(info.num_channels, 8);
+        assert_eq!(info.num_frames, 3);
+        assert!((info.sample_rate - 48000.0).abs() < f64::EPSILON);
+
+        let mut frames = [[1.0; 2]; 3];
+        assert_eq!(reader.read_frames(0, &mut frames).unwrap(), 3);
+        assert_eq!(frames, [[0.0, 0.125], [0.0; 2], [0.0; 2]]);
+    }
+
+    #[test]
+    fn taps_are_interleaved_by_stem() {
+        let (mut taps, blocks, _spare) = StemTaps::with_queue();
+
+        taps.begin(2);
+        taps.add(Stem::Dry, 0, 0.5, -0.5);
+        taps.add(Stem::FxReturns, 1, 0.25, 0.25);
+        taps.add(Stem::FxReturns, 1, 0.25, 0.0);
+        taps.finish();
+
+        let block = blocks.try_recv().unwrap();
+        assert_eq!(block.missed_frames, 0);
+        assert_eq!(
+            block.samples,
+            [
+                0.5, -0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, //
+                0.0, 0.0, 0.0, 0.0, 0.5, 0.25, 0.0, 0.0,
+            ]
+        );
+    }
+
+    #[test]
+    fn dropped_buffers_are_counted_as_silence() {
+        let (mut taps, blocks, spare) = StemTaps::with_queue();
+
+        // the queue fills up, as nothing is received
+        for _ in 0..BLOCK_QUEUE_LEN + 3 {
+            taps.record_silence(4);
+        }
+
+        assert_eq!(blocks.len(), BLOCK_QUEUE_LEN);
+        assert!(!taps.is_stopped());
+
+        // the writer returns each block once it has been written
+        spare.send(blocks.try_recv().unwrap().samples).unwrap();
+        taps.record_silence(4);
+
+        let last = blocks.try_iter().last().unwrap();
+        assert_eq!(last.missed_frames, 12);
+
+        drop((blocks, spare));
+        taps.record_silence(4);
+        assert!(taps.is_stopped());
+
+        assert_eq!(part_path("out/session.wav", 1), "out/session.wav");
+        assert_eq!(part_path("out/session.wav", 3), "out/session_3.wav");
+    }
+
+    #[test]
+    fn master_is_recorded_as_bformat() {
+        let dir = std::env::temp_dir();
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let stems_path = path("maestro_stems_test.wav");
+        let bformat_path = path("maestro_bformat_test.wav");
+
+        let mut writer =
+            StemWriter::create(&stems_path, Some(&bformat_path), 48000)
+                .unwrap();
+
+        // a frame of the master on the left, then one on the right
+        let master = Stem::Master.channel_offset();
+        let mut block = vec![0.0; 2 * NUM_STEM_CHANNELS];
+        block[master] = 1.0;
+        block[NUM_STEM_CHANNELS + master + 1] = 1.0;
+
+        writer.write_silence(1).unwrap();
+        writer.write(&block).unwrap();
+        writer.update_headers().unwrap();
+        drop(writer);
+
+        let mut reader = AudioFileReader::open(&bformat_path).unwrap();
+        assert_eq!(reader.info().num_channels, 4);
+        assert_eq!(reader.info().num_frames, 3);
+
+        // the W and Y channels
+        let mut frames = [[1.0; 2]; 3];
+        assert_eq!(reader.read_frames(0, &mut frames).unwrap(), 3);
+
+        let expected = [[0.0, 0.0], [1.0, 0.5], [1.0, -0.5]];
+        for (frame, expected) in frames.iter().zip(expected) {
+            assert!((frame[0] - expected[0]).abs() < 1e-6);
+            assert!((frame[1] - expected[1]).abs() < 1e-6);
+        }
+
+        _ = std::fs::remove_file(stems_path);
+        _ = std::fs::remove_file(bformat_path);
+    }
+}
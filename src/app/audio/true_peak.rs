@@ -0,0 +1,186 @@
+//! True-peak metering of the output, which records each incident of the
+//! output going over a threshold so that an installation's headroom can be
+//! checked after the fact.
+
+use super::*;
+use crossbeam_channel::{bounded, Receiver as CCReceiver, Sender as CCSender};
+use std::fmt::{Display, Formatter};
+
+/// The default level above which the output is considered to be over, in
+/// decibels true peak.
+pub const DEFAULT_TRUE_PEAK_THRESHOLD_DB: f64 = -1.0;
+/// The lowest and highest thresholds, in decibels true peak.
+pub const MIN_TRUE_PEAK_THRESHOLD_DB: f64 = -24.0;
+pub const MAX_TRUE_PEAK_THRESHOLD_DB: f64 = 0.0;
+
+/// How quickly the meter falls, in milliseconds.
+const METER_RELEASE_TIME_MS: f64 = 300.0;
+/// The number of incidents which may be queued for the UI thread. Incidents
+/// which occur while the queue is full are only counted in the held peak.
+const INCIDENT_QUEUE_LEN: usize = 64;
+
+/// The true-peak meter, shared with the UI thread.
+#[derive(Debug)]
+pub struct TruePeakControl {
+    /// The metered true peak of the output, in decibels.
+    pub level_db: AtomicF64,
+    /// The highest true peak since the meter was last reset, in decibels.
+    pub max_db: AtomicF64,
+    pub threshold_db: AtomicF64,
+}
+
+impl TruePeakControl {
+    pub fn new(threshold_db: f64) -> Self {
+        Self {
+            level_db: AtomicF64::new(MINUS_INFINITY_DB),
+            max_db: AtomicF64::new(MINUS_INFINITY_DB),
+            threshold_db: AtomicF64::new(threshold_db),
+        }
+    }
+
+    /// Clears the held peak.
+    pub fn reset_max(&self) {
+        self.max_db.sr(MINUS_INFINITY_DB);
+    }
+
+    /// Whether the held peak is over the threshold.
+    pub fn is_over(&self) -> bool {
+        self.max_db.lr() > self.threshold_db.lr()
+    }
+}
+
+impl Default for TruePeakControl {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRUE_PEAK_THRESHOLD_DB)
+    }
+}
+
+/// A continuous run of the output over the threshold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TruePeakIncident {
+    /// The highest true peak of the incident, in decibels.
+    pub peak_db: f64,
+    pub duration_secs: f64,
+}
+
+impl Display for TruePeakIncident {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:+.1} dBTP for {:.0} ms",
+            self.peak_db,
+            self.duration_secs * 1000.0
+        )
+    }
+}
+
+/// The audio thread's side of the true-peak meter.
+#[derive(Debug)]
+pub struct TruePeakMeter {
+    detector: TruePeakDetector,
+    control: Arc<TruePeakControl>,
+    incidents: CCSender<TruePeakIncident>,
+
+    level: f64,
+    meter_release_coef: f64,
+
+    /// The peak level and length in samples of the current incident, if the
+    /// output is over the threshold.
+    incident: Option<(f64, u32)>,
+
+    sample_rate: f64,
+}
+
+impl TruePeakMeter {
+    /// Returns the meter, and the receiver of its incidents.
+    pub fn new(
+        control: Arc<TruePeakControl>,
+        sample_rate: f64,
+    ) -> (Self, CCReceiver<TruePeakIncident>) {
+        let (incidents, receiver) = bounded(INCIDENT_QUEUE_LEN);
+
+        let meter = Self {
+            detector: TruePeakDetector::new(),
+            control,
+            incidents,
+
+            level: 0.0,
+            meter_release_coef: (-1000.0
+                / (METER_RELEASE_TIME_MS * sample_rate))
+                .exp(),
+
+            incident: None,
+
+            sample_rate,
+        };
+
+        (meter, receiver)
+    }
+
+    /// Meters the main channels of `buffer`.
+    pub fn process(&mut self, buffer: &AudioBuffer) {
+        let threshold = db_to_level(self.control.threshold_db.lr());
+        let num_channels = buffer.channels();
+
+        let mut block_peak: f64 = 0.0;
+
+        for frame in 0..buffer.len_frames() {
+            let idx = frame * num_channels;
+            let peak =
+                self.detector.process_stereo(buffer[idx], buffer[idx + 1]);
+
+            block_peak = block_peak.max(peak);
+
+            self.level = if peak > self.level {
+                peak
+            }
+            else {
+                self.level * self.meter_release_coef
+            };
+
+            if peak > threshold {
+                let (max, len) = self.incident.get_or_insert((0.0, 0));
+                *max = max.max(peak);
+                *len += 1;
+            }
+            else {
+                self.end_incident();
+            }
+        }
+
+        self.update_control(block_peak);
+    }
+
+    /// Lets the meter fall over `num_frames` of silence, without processing
+    /// them.
+    pub fn process_silence(&mut self, num_frames: usize) {
+        self.detector.reset();
+        self.end_incident();
+
+        self.level *= self.meter_release_coef.powi(num_frames as i32);
+        self.update_control(0.0);
+    }
+
+    fn update_control(&self, block_peak: f64) {
+        let block_peak_db = level_to_db(block_peak).max(MINUS_INFINITY_DB);
+
+        if block_peak_db > self.control.max_db.lr() {
+            self.control.max_db.sr(block_peak_db);
+        }
+
+        self.control
+            .level_db
+            .sr(level_to_db(self.level).max(MINUS_INFINITY_DB));
+    }
+
+    /// Reports the current incident, if there is one.
+    fn end_incident(&mut self) {
+        if let Some((peak, len)) = self.incident.take() {
+            // if the UI thread has fallen behind, the incident is dropped
+            _ = self.incidents.try_send(TruePeakIncident {
+                peak_db: level_to_db(peak),
+                duration_secs: len as f64 / self.sample_rate,
+            });
+        }
+    }
+}
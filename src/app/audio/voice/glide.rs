@@ -0,0 +1,73 @@
+//! Portamento between the pitches of consecutive voices.
+
+use std::fmt::{Display, Formatter};
+
+/// The longest glide time, in milliseconds.
+pub const MAX_GLIDE_TIME_MS: f64 = 5000.0;
+
+/// How the duration of a glide is found.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GlideMode {
+    /// Every glide takes the glide time, however far it goes.
+    #[default]
+    ConstantTime,
+    /// The glide time is the time taken to glide one octave, so wider
+    /// glides take longer.
+    ConstantRate,
+}
+
+impl GlideMode {
+    /// Parses a `GlideMode` from its name, i.e. `"time"` or `"rate"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "time" => Some(Self::ConstantTime),
+            "rate" => Some(Self::ConstantRate),
+            _ => None,
+        }
+    }
+}
+
+impl Display for GlideMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ConstantTime => write!(f, "Constant time"),
+            Self::ConstantRate => write!(f, "Constant rate"),
+        }
+    }
+}
+
+/// How each new voice glides from the previous voice's pitch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlideParameters {
+    /// The glide time in milliseconds, where `0.0` disables gliding.
+    pub time_ms: f64,
+    pub mode: GlideMode,
+    /// Whether new voices only glide while another voice is held, so that
+    /// detached notes start at their own pitch.
+    pub legato_only: bool,
+}
+
+impl GlideParameters {
+    /// The time taken to glide from `from` to `to` (both MIDI notes), in
+    /// milliseconds.
+    pub fn duration_ms(&self, from: f64, to: f64) -> f64 {
+        match self.mode {
+            GlideMode::ConstantTime => self.time_ms,
+            GlideMode::ConstantRate => self.time_ms * (to - from).abs() / 12.0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.time_ms > 0.0
+    }
+}
+
+impl Default for GlideParameters {
+    fn default() -> Self {
+        Self {
+            time_ms: 0.0,
+            mode: GlideMode::default(),
+            legato_only: true,
+        }
+    }
+}
@@ -1,7 +1,16 @@
 //! Polyphonic voices.
 
 pub mod audio_note;
+pub mod glide;
+pub mod stealing;
+pub mod sub;
+pub mod unison;
+#[allow(clippy::module_inception)]
 pub mod voice;
 
 pub use audio_note::{NoteEvent, NoteHandler};
+pub use glide::{GlideMode, GlideParameters};
+pub use stealing::VoiceStealing;
+pub use sub::{SubParameters, SubShape};
+pub use unison::UnisonParameters;
 pub use voice::{Voice, VoiceEvent, VoiceHandler};
@@ -0,0 +1,120 @@
+//! Voice stealing policies, and the fast release of stolen voices.
+
+use super::voice::Voice;
+use std::fmt::{Display, Formatter};
+
+/// The time taken for a stolen voice to fade out, in milliseconds.
+pub const STEAL_FADE_MS: f64 = 5.0;
+
+/// How a voice is chosen to make room for a new one.
+///
+/// Whichever policy is used, releasing voices are stolen before held voices.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VoiceStealing {
+    /// Steals the voice which started first.
+    #[default]
+    Oldest,
+    /// Steals the voice with the lowest envelope level.
+    Quietest,
+    /// Steals a voice playing the same note, even if there is room for
+    /// another voice, and otherwise the oldest.
+    SameNote,
+    /// Never steals voices, so new notes are dropped if there is no room.
+    None,
+}
+
+impl VoiceStealing {
+    /// Parses a `VoiceStealing` from its name, e.g. `"same-note"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "oldest" => Some(Self::Oldest),
+            "quietest" => Some(Self::Quietest),
+            "same-note" => Some(Self::SameNote),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    /// The index of the voice in `voices` which should be stolen, if any.
+    ///
+    /// `note` is the note of the new voice.
+    pub fn choose(self, voices: &[Option<Voice>], note: f64) -> Option<usize> {
+        let active = voices
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| v.as_ref().map(|v| (i, v)));
+
+        match self {
+            Self::Oldest => active
+                .min_by_key(|(_, v)| (!v.releasing, v.id))
+                .map(|(i, _)| i),
+            Self::Quietest => active
+                .min_by(|(_, a), (_, b)| {
+                    a.releasing.cmp(&b.releasing).reverse().then_with(|| {
+                        let level_a = a.envelope.current_value();
+                        let level_b = b.envelope.current_value();
+
+                        level_a.total_cmp(&level_b)
+                    })
+                })
+                .map(|(i, _)| i),
+            Self::SameNote => Self::same_note(voices, note)
+                .or_else(|| Self::Oldest.choose(voices, note)),
+            Self::None => None,
+        }
+    }
+
+    /// The index of the voice in `voices` playing `note`, if any.
+    pub fn same_note(voices: &[Option<Voice>], note: f64) -> Option<usize> {
+        voices
+            .iter()
+            .position(|v| v.as_ref().is_some_and(|v| v.note == note))
+    }
+}
+
+impl Display for VoiceStealing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Oldest => write!(f, "Oldest"),
+            Self::Quietest => write!(f, "Quietest"),
+            Self::SameNote => write!(f, "Same note"),
+            Self::None => write!(f, "None"),
+        }
+    }
+}
+
+/// A voice which has been stolen, and which fades out over
+/// [`STEAL_FADE_MS`] so that it doesn't click.
+#[derive(Clone, Debug)]
+pub struct StolenVoice {
+    pub voice: Voice,
+    /// The current gain of the fade, from `1.0` to `0.0`.
+    gain: f64,
+    /// The amount the gain falls by each sample.
+    step: f64,
+}
+
+impl StolenVoice {
+    pub fn new(voice: Voice, sample_rate: f64) -> Self {
+        Self {
+            voice,
+            gain: 1.0,
+            step: 1000.0 / (STEAL_FADE_MS * sample_rate),
+        }
+    }
+
+    /// Advances the fade by one sample, returning its gain.
+    pub fn next_gain(&mut self) -> f64 {
+        self.gain = (self.gain - self.step).max(0.0);
+        self.gain
+    }
+
+    pub const fn gain(&self) -> f64 {
+        self.gain
+    }
+
+    /// Whether the voice has faded out completely.
+    pub fn is_finished(&self) -> bool {
+        self.gain <= 0.0
+    }
+}
@@ -0,0 +1,241 @@
+//! A sub oscillator which plays an octave below the lowest held note, to
+//! anchor the low end without relying on the external rig.
+
+use crate::dsp::synthesis::*;
+use crate::dsp::AdsrEnvelope;
+use crate::prelude::*;
+use crate::dsp::util::AudioBuffer;
+use std::fmt::{Display, Formatter};
+
+/// The lowest and highest levels of the sub, in decibels.
+pub const MIN_SUB_LEVEL_DB: f64 = -60.0;
+pub const MAX_SUB_LEVEL_DB: f64 = 12.0;
+/// The lowest and highest crossover frequencies of the sub.
+pub const MIN_SUB_CROSSOVER_HZ: f64 = 40.0;
+pub const MAX_SUB_CROSSOVER_HZ: f64 = 250.0;
+
+/// How far below the lowest note the sub plays, in semitones.
+const SUB_OCTAVE_SEMITONES: f64 = 12.0;
+/// How long the sub glides between notes, in milliseconds.
+const SUB_GLIDE_TIME_MS: f64 = 30.0;
+/// How long level changes are smoothed over, in milliseconds.
+const LEVEL_SMOOTHING_TIME_MS: f64 = 50.0;
+
+/// The waveform of the sub.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SubShape {
+    #[default]
+    Sine,
+    /// A triangle, whose odd harmonics help the sub carry on small
+    /// speakers.
+    Triangle,
+}
+
+impl SubShape {
+    /// Parses a `SubShape` from its name, i.e. `"sine"` or `"tri"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "sine" => Some(Self::Sine),
+            "tri" | "triangle" => Some(Self::Triangle),
+            _ => None,
+        }
+    }
+}
+
+impl Display for SubShape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sine => write!(f, "Sine"),
+            Self::Triangle => write!(f, "Triangle"),
+        }
+    }
+}
+
+/// The settings of the sub oscillator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubParameters {
+    pub shape: SubShape,
+    /// The level of the sub below its crossover, in decibels.
+    pub level_db: f64,
+    /// The frequency above which the sub rolls off, so that it hands the
+    /// higher notes over to the main voices.
+    pub crossover_hz: f64,
+    pub attack_ms: f64,
+    pub release_ms: f64,
+}
+
+impl SubParameters {
+    /// The level of the sub at `pitch` (a MIDI note), which follows a
+    /// second-order lowpass at the crossover.
+    fn level_at(&self, pitch: f64) -> f64 {
+        let ratio = note_to_freq(pitch) / self.crossover_hz;
+        db_to_level(self.level_db) / (ratio.powi(4) + 1.0).sqrt()
+    }
+}
+
+impl Default for SubParameters {
+    fn default() -> Self {
+        Self {
+            shape: SubShape::default(),
+            level_db: -6.0,
+            crossover_hz: 100.0,
+            attack_ms: 20.0,
+            release_ms: 300.0,
+        }
+    }
+}
+
+/// A monophonic oscillator which tracks the lowest held note an octave
+/// down, gliding between notes rather than retriggering.
+#[derive(Debug, Clone)]
+pub struct SubOscillator {
+    params: SubParameters,
+    generator: Generator,
+    envelope: AdsrEnvelope,
+
+    /// The lowest held note, if any.
+    note: Option<f64>,
+    /// The sub's pitch as a MIDI note.
+    pitch: Smoother<f64>,
+    level: Smoother<f64>,
+
+    sample_rate: f64,
+}
+
+impl SubOscillator {
+    pub fn new(params: SubParameters, sample_rate: f64) -> Self {
+        let mut sub = Self {
+            params,
+            generator: Self::build_generator(params.shape, 0.0, sample_rate),
+            envelope: AdsrEnvelope::new(sample_rate),
+
+            note: None,
+            pitch: Smoother::new(SUB_GLIDE_TIME_MS, 0.0, sample_rate),
+            level: Smoother::new(LEVEL_SMOOTHING_TIME_MS, 0.0, sample_rate),
+
+            sample_rate,
+        };
+
+        sub.set_parameters(params);
+        sub
+    }
+
+    pub const fn parameters(&self) -> &SubParameters {
+        &self.params
+    }
+
+    pub fn set_parameters(&mut self, params: SubParameters) {
+        if params.shape != self.params.shape {
+            self.generator = Self::build_generator(
+                params.shape,
+                self.pitch.current_value(),
+                self.sample_rate,
+            );
+        }
+
+        self.params = SubParameters {
+            level_db: params.level_db.clamp(MIN_SUB_LEVEL_DB, MAX_SUB_LEVEL_DB),
+            crossover_hz: params
+                .crossover_hz
+                .clamp(MIN_SUB_CROSSOVER_HZ, MAX_SUB_CROSSOVER_HZ),
+            ..params
+        };
+
+        self.envelope.set_parameters(
+            self.params.attack_ms,
+            0.0,
+            1.0,
+            self.params.release_ms,
+        );
+
+        self.level
+            .set_target_value(self.params.level_at(self.pitch.target_value()));
+    }
+
+    /// Follows `note`, the lowest held note, or releases the sub if no
+    /// note is held.
+    pub fn set_note(&mut self, note: Option<f64>) {
+        if note == self.note {
+            return;
+        }
+
+        match (self.note, note) {
+            (_, None) => self.envelope.set_trigger(false),
+            (held, Some(note)) => {
+                let pitch = note - SUB_OCTAVE_SEMITONES;
+
+                // a new phrase starts at its own pitch, while notes within
+                // it glide
+                if held.is_none() && self.envelope.is_idle() {
+                    self.pitch.set_target_value(pitch);
+                    self.pitch.set_start_value(pitch);
+                    self.level.set_target_value(self.params.level_at(pitch));
+                    self.level.set_start_value(self.level.target_value());
+                    self.update_freq(pitch);
+                }
+                else {
+                    self.pitch.set_target_value(pitch);
+                    self.level.set_target_value(self.params.level_at(pitch));
+                }
+
+                self.envelope.set_trigger(true);
+            }
+        }
+
+        self.note = note;
+    }
+
+    /// Whether the sub is sounding.
+    pub fn is_active(&self) -> bool {
+        !self.envelope.is_idle()
+    }
+
+    /// Adds the sub to the main channels of `buffer` between `block_start`
+    /// and `block_end`, scaled by `gain`.
+    pub fn process_block(
+        &mut self,
+        buffer: &mut AudioBuffer,
+        block_start: usize,
+        block_end: usize,
+        gain: &[f64; MAX_BLOCK_SIZE],
+    ) {
+        if !self.is_active() && self.note.is_none() {
+            return;
+        }
+
+        for (value_idx, sample_idx) in (block_start..block_end).enumerate() {
+            if self.pitch.is_active() {
+                let pitch = self.pitch.next();
+                self.update_freq(pitch);
+            }
+
+            let amp =
+                gain[value_idx] * self.envelope.next() * self.level.next();
+            let sample = self.generator.process().0 * amp;
+
+            // * 2 because the channels are interleaved
+            buffer[sample_idx * 2] += sample;
+            buffer[sample_idx * 2 + 1] += sample;
+        }
+    }
+
+    fn update_freq(&mut self, pitch: f64) {
+        self.generator.change_freq(note_to_freq(pitch), self.sample_rate);
+    }
+
+    /// Builds a generator of `shape` at `pitch` (a MIDI note).
+    fn build_generator(
+        shape: SubShape,
+        pitch: f64,
+        sample_rate: f64,
+    ) -> Generator {
+        let freq = note_to_freq(pitch);
+
+        match shape {
+            SubShape::Sine => Generator::Sine(SineOsc::new(freq, sample_rate)),
+            SubShape::Triangle => {
+                Generator::Tri(PolyBlepTri::new(freq, sample_rate))
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Unison stacking of detuned oscillators within each voice.
+
+use crate::dsp::synthesis::Generator;
+use crate::prelude::*;
+use std::f64::consts::{FRAC_PI_4, SQRT_2};
+
+/// The most oscillators which a single voice may stack.
+pub const MAX_UNISON_VOICES: usize = 8;
+/// The widest detune either side of a voice's note, in cents.
+pub const MAX_UNISON_DETUNE_CENTS: f64 = 100.0;
+/// The most oscillators which may sound at once across all voices. Voices
+/// are stolen to stay within this, so wide stacks reduce the polyphony.
+pub const MAX_ACTIVE_OSCILLATORS: usize = NUM_VOICES as usize * 2;
+
+/// How each voice's oscillators are stacked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnisonParameters {
+    /// The number of oscillators per voice, from `1` to `MAX_UNISON_VOICES`.
+    pub voices: usize,
+    /// The detune of the outermost oscillators either side of the note, in
+    /// cents.
+    pub detune_cents: f64,
+    /// How far the oscillators are spread across the stereo field, from
+    /// `0.0` (centered) to `1.0` (hard left to hard right).
+    pub spread: f64,
+    /// Whether each oscillator starts at a random phase, which avoids the
+    /// stack starting with a loud, phase-aligned transient.
+    pub random_phase: bool,
+}
+
+impl UnisonParameters {
+    /// The position of oscillator `idx` in the stack, from `-1.0` to `1.0`.
+    fn position(&self, idx: usize) -> f64 {
+        if self.voices > 1 {
+            (idx as f64 / (self.voices - 1) as f64).mul_add(2.0, -1.0)
+        }
+        else {
+            0.0
+        }
+    }
+
+    /// The pitch offset of oscillator `idx` from the voice's note, in
+    /// semitones.
+    pub fn detune_for(&self, idx: usize) -> f64 {
+        self.position(idx) * self.detune_cents * 0.01
+    }
+
+    /// The left and right gains of oscillator `idx`, which are equal-power
+    /// panned and scaled so that the stack is about as loud as a single
+    /// oscillator.
+    pub fn gains_for(&self, idx: usize) -> (f64, f64) {
+        let pan = self.position(idx) * self.spread;
+        let angle = (pan + 1.0) * FRAC_PI_4;
+
+        // detuned oscillators are uncorrelated, so their powers add
+        let norm = SQRT_2 / (self.voices.max(1) as f64).sqrt();
+
+        (angle.cos() * norm, angle.sin() * norm)
+    }
+}
+
+impl Default for UnisonParameters {
+    fn default() -> Self {
+        Self {
+            voices: 1,
+            detune_cents: 15.0,
+            spread: 0.5,
+            random_phase: true,
+        }
+    }
+}
+
+/// One oscillator in a voice's unison stack.
+#[derive(Clone, Debug)]
+pub struct UnisonLayer {
+    pub generator: Generator,
+    /// The oscillator's pitch offset from the voice's note, in semitones.
+    pub detune: f64,
+    /// The phase which the oscillator starts at, from `0.0` to `1.0`.
+    pub phase: f64,
+    /// The oscillator's left and right gains.
+    pub gains: (f64, f64),
+}
+
+impl UnisonLayer {
+    /// A layer for oscillator `idx` of a stack described by `parameters`.
+    pub fn new(
+        generator: Generator,
+        parameters: &UnisonParameters,
+        idx: usize,
+    ) -> Self {
+        let phase = if parameters.random_phase && parameters.voices > 1 {
+            random_f64()
+        }
+        else {
+            0.0
+        };
+
+        let mut layer = Self {
+            generator,
+            detune: parameters.detune_for(idx),
+            phase,
+            gains: parameters.gains_for(idx),
+        };
+
+        layer.generator.set_phase(phase);
+        layer
+    }
+
+    /// A single, centered layer.
+    pub fn centered(generator: Generator) -> Self {
+        Self::new(generator, &UnisonParameters::default(), 0)
+    }
+
+    /// Produces a stereo sample, with the layer's gains applied.
+    pub fn process(&mut self) -> (f64, f64) {
+        let (l, r) = self.generator.process();
+
+        (l * self.gains.0, r * self.gains.1)
+    }
+}
@@ -1,10 +1,17 @@
 //! Polyphonic voice types and management.
 
 use atomic::Atomic;
-use nannou_audio::Buffer;
+use crate::dsp::util::AudioBuffer;
 use std::sync::{mpsc, Arc, Mutex};
 
 use super::audio_note::NoteHandler;
+use super::glide::{GlideMode, GlideParameters, MAX_GLIDE_TIME_MS};
+use super::stealing::{StolenVoice, VoiceStealing};
+use super::sub::{SubOscillator, SubParameters};
+use super::unison::{
+    UnisonLayer, UnisonParameters, MAX_ACTIVE_OSCILLATORS,
+    MAX_UNISON_DETUNE_CENTS, MAX_UNISON_VOICES,
+};
 use crate::app::ExciterOscillator;
 use crate::dsp::synthesis::*;
 use crate::dsp::*;
@@ -23,6 +30,9 @@ pub struct Voice {
     pub id: u64,
     /// The MIDI note of the voice.
     pub note: f64,
+    /// The voice's current pitch as a MIDI note, which glides towards
+    /// `note`.
+    pub pitch: Smoother<f64>,
 
     /// The voice's ADSR envelope.
     pub envelope: AdsrEnvelope,
@@ -37,8 +47,8 @@ pub struct Voice {
     pub generator_type: Arc<Atomic<ExciterOscillator>>,
     pub curr_generator: ExciterOscillator,
 
-    /// The audio generator stored within the voice.
-    pub generator: Generator,
+    /// The voice's unison stack of audio generators.
+    pub layers: Vec<UnisonLayer>,
 }
 
 impl Voice {
@@ -53,16 +63,80 @@ impl Voice {
         Self {
             id,
             note,
+            pitch: Smoother::new(0.0, note, sample_rate.lr()),
             envelope: envelope.unwrap_or_default(),
             releasing: false,
             sample_rate,
             curr_generator: generator_type_ref.lr(),
             generator_type: generator_type_ref,
-            generator,
+            layers: vec![UnisonLayer::centered(generator)],
         }
     }
 
-    pub fn update_generator(&mut self) {
+    /// Produces a stereo sample from the sum of the voice's layers.
+    pub fn process(&mut self) -> (f64, f64) {
+        if self.pitch.is_active() {
+            let pitch = self.pitch.next();
+            self.set_layer_pitches(pitch);
+        }
+
+        self.layers.iter_mut().fold((0.0, 0.0), |(l, r), layer| {
+            let (layer_l, layer_r) = layer.process();
+            (l + layer_l, r + layer_r)
+        })
+    }
+
+    /// Glides the voice's pitch from `from` (a MIDI note) to its note over
+    /// `duration_ms`.
+    pub fn glide_from(&mut self, from: f64, duration_ms: f64) {
+        let sample_rate = self.sample_rate.lr();
+
+        self.pitch = Smoother::new(duration_ms, self.note, sample_rate);
+        self.pitch.set_start_value(from);
+
+        let pitch = self.pitch.next();
+        self.set_layer_pitches(pitch);
+    }
+
+    /// The voice's current pitch as a MIDI note.
+    pub fn current_pitch(&self) -> f64 {
+        if self.pitch.is_active() {
+            self.pitch.current_value()
+        }
+        else {
+            self.note
+        }
+    }
+
+    /// Sets the frequency of each layer from `pitch`, with its detune.
+    fn set_layer_pitches(&mut self, pitch: f64) {
+        let sample_rate = self.sample_rate.lr();
+
+        for layer in &mut self.layers {
+            layer
+                .generator
+                .change_freq(note_to_freq(pitch + layer.detune), sample_rate);
+        }
+    }
+
+    /// Sets the trigger of the voice's envelope and generators.
+    pub fn set_trigger(&mut self, trigger: bool) {
+        self.envelope.set_trigger(trigger);
+
+        for layer in &mut self.layers {
+            layer.generator.set_trigger(trigger);
+        }
+    }
+
+    /// Rebuilds the voice's generators if the generator type has changed.
+    /// `fm_parameters`, `pluck_parameters` and `noise_color` are used if the
+    /// new generator is an FM synth, a plucked string or noise.
+    pub fn update_generator(
+        &mut self,
+        fm_parameters: &FmParameters,
+        pluck_parameters: &PluckParameters,
+        noise_color: NoiseColor,
+    ) {
         let new_type = self.generator_type.lr();
 
         if new_type == self.curr_generator {
@@ -71,23 +145,65 @@ impl Voice {
 
         self.curr_generator = new_type;
 
-        let freq = note_to_freq(self.note);
+        let pitch = self.current_pitch();
+
+        for i in 0..self.layers.len() {
+            let freq = note_to_freq(pitch + self.layers[i].detune);
+            let mut generator = self.build_generator(
+                freq,
+                fm_parameters,
+                pluck_parameters,
+                noise_color,
+            );
+            generator.set_phase(self.layers[i].phase);
+
+            self.layers[i].generator = generator;
+        }
+    }
+
+    /// Builds a generator of the current type at `freq`.
+    fn build_generator(
+        &self,
+        freq: f64,
+        fm_parameters: &FmParameters,
+        pluck_parameters: &PluckParameters,
+        noise_color: NoiseColor,
+    ) -> Generator {
         let sample_rate = self.sample_rate.lr();
 
-        self.generator = match new_type {
+        match self.curr_generator {
             ExciterOscillator::Sine => {
                 Generator::Sine(SineOsc::new(freq, sample_rate))
             }
             ExciterOscillator::Tri => {
-                Generator::Tri(TriOsc::new(freq, sample_rate))
+                Generator::Tri(PolyBlepTri::new(freq, sample_rate))
             }
             ExciterOscillator::Saw => {
-                Generator::Saw(Phasor::new(freq, sample_rate))
+                Generator::Saw(PolyBlepSaw::new(freq, sample_rate))
             }
             ExciterOscillator::Square => {
-                Generator::Square(SquareOsc::new(freq, sample_rate))
+                Generator::Square(PolyBlepSquare::new(freq, sample_rate))
+            }
+            ExciterOscillator::Fm => {
+                let mut fm = FmSynth::new(fm_parameters, freq, sample_rate);
+                fm.set_trigger(!self.releasing);
+
+                Generator::Fm(Box::new(fm))
+            }
+            ExciterOscillator::Pluck => {
+                let mut string =
+                    KarplusStrong::new(pluck_parameters, freq, sample_rate);
+
+                if !self.releasing {
+                    string.pluck();
+                }
+
+                Generator::Pluck(string)
+            }
+            ExciterOscillator::Noise => {
+                let noise = ColoredNoise::new(noise_color, sample_rate);
+                Generator::Noise(Box::new(noise))
             }
-            ExciterOscillator::Noise => Generator::Noise,
         }
     }
 }
@@ -99,11 +215,29 @@ pub struct VoiceHandler {
     // pub note_handler_ref: Arc<Mutex<NoteHandler>>,
     /// The array of voices.
     pub voices: [Option<Voice>; NUM_VOICES as usize],
+    /// Voices which have been stolen and are fading out.
+    stolen: [Option<StolenVoice>; NUM_VOICES as usize],
     voice_event_receiver: mpsc::Receiver<VoiceEvent>,
     /// Internal counter for assigning new IDs.
     id_counter: u64,
     generator: Option<Arc<Atomic<ExciterOscillator>>>,
     sample_rate: Arc<AtomicF64>,
+    /// The parameters of each voice's FM synth.
+    fm_parameters: FmParameters,
+    /// The parameters of each voice's plucked string.
+    pluck_parameters: PluckParameters,
+    /// The color of each voice's noise.
+    noise_color: NoiseColor,
+    /// How each new voice's generators are stacked.
+    unison: UnisonParameters,
+    /// How each new voice glides from the previous voice's pitch.
+    glide: GlideParameters,
+    /// How voices are chosen to be stolen.
+    stealing: VoiceStealing,
+    /// The most voices which may be held or releasing at once.
+    max_polyphony: usize,
+    /// Plays an octave below the lowest held note, if it is enabled.
+    sub: Option<SubOscillator>,
 }
 
 impl VoiceHandler {
@@ -118,10 +252,19 @@ impl VoiceHandler {
         Self {
             // note_handler_ref,
             voices: std::array::from_fn(|_| None),
+            stolen: std::array::from_fn(|_| None),
             voice_event_receiver,
             id_counter: 0,
             generator: None,
             sample_rate: sample_rate_ref,
+            fm_parameters: FmParameters::default(),
+            pluck_parameters: PluckParameters::default(),
+            noise_color: NoiseColor::default(),
+            unison: UnisonParameters::default(),
+            glide: GlideParameters::default(),
+            stealing: VoiceStealing::default(),
+            max_polyphony: NUM_VOICES as usize,
+            sub: None,
         }
     }
 
@@ -140,7 +283,7 @@ impl VoiceHandler {
 
     pub fn process_block(
         &mut self,
-        buffer: &mut Buffer<f64>,
+        buffer: &mut AudioBuffer,
         block_start: usize,
         block_end: usize,
         gain: [f64; MAX_BLOCK_SIZE],
@@ -163,29 +306,73 @@ impl VoiceHandler {
                 .envelope
                 .next_block(&mut voice_amp_envelope, block_len);
 
-            voice.update_generator();
+            voice.update_generator(
+                &self.fm_parameters,
+                &self.pluck_parameters,
+                self.noise_color,
+            );
 
             for (value_idx, sample_idx) in (block_start..block_end).enumerate()
             {
                 let amp = gain[value_idx] * voice_amp_envelope[value_idx];
 
-                let (sample_l, sample_r) = voice.generator.process();
+                let (sample_l, sample_r) = voice.process();
 
                 // * 2 because the channels are interleaved
                 buffer[sample_idx * 2] += sample_l * amp;
                 buffer[sample_idx * 2 + 1] += sample_r * amp;
             }
         }
+
+        for stolen in self.stolen.iter_mut().flatten() {
+            stolen
+                .voice
+                .envelope
+                .next_block(&mut voice_amp_envelope, block_len);
+
+            for (value_idx, sample_idx) in (block_start..block_end).enumerate()
+            {
+                let amp = gain[value_idx]
+                    * voice_amp_envelope[value_idx]
+                    * stolen.next_gain();
+
+                let (sample_l, sample_r) = stolen.voice.process();
+
+                buffer[sample_idx * 2] += sample_l * amp;
+                buffer[sample_idx * 2 + 1] += sample_r * amp;
+            }
+        }
+
+        if let Some(sub) = self.sub.as_mut() {
+            sub.process_block(buffer, block_start, block_end, &gain);
+        }
     }
 
-    /// Starts a new voice.
+    /// Starts a new voice, stealing voices to make room for it if needed.
+    ///
+    /// Returns `None` if there is no room and the stealing policy is
+    /// [`VoiceStealing::None`], in which case the note is dropped.
     #[allow(clippy::missing_panics_doc)] // this function should not panic
     pub fn start_voice(
         &mut self,
         note: f64,
         sample_rate: f64,
         envelope: Option<AdsrEnvelope>,
-    ) -> &mut Voice {
+    ) -> Option<&mut Voice> {
+        // found before any voice is stolen, so that a stolen voice can
+        // still be glided from
+        let glide_from = self.glide_source();
+
+        if self.stealing == VoiceStealing::SameNote
+            && let Some(idx) = VoiceStealing::same_note(&self.voices, note)
+        {
+            self.steal_voice(idx);
+        }
+
+        if !self.make_room_for(note, self.unison.voices) {
+            return None;
+        }
+
         let next_voice_id = self.next_voice_id();
         let gen = self
             .generator
@@ -195,37 +382,42 @@ impl VoiceHandler {
         let mut new_voice = Voice {
             id: next_voice_id,
             note,
+            pitch: Smoother::new(0.0, note, sample_rate),
             envelope: envelope.unwrap_or_default(),
             releasing: false,
             sample_rate: Arc::clone(&self.sample_rate),
             generator_type: Arc::clone(gen),
             curr_generator: ExciterOscillator::Noise,
-            generator: { Generator::Noise },
+            layers: (0..self.unison.voices)
+                .map(|i| {
+                    let noise = Box::new(ColoredNoise::new(
+                        self.noise_color,
+                        sample_rate,
+                    ));
+                    UnisonLayer::new(Generator::Noise(noise), &self.unison, i)
+                })
+                .collect(),
         };
 
-        new_voice.update_generator();
-
-        new_voice.envelope.set_trigger(true);
+        new_voice.update_generator(
+            &self.fm_parameters,
+            &self.pluck_parameters,
+            self.noise_color,
+        );
 
-        // is there a free voice?
-        if let Some(free_idx) =
-            self.voices.iter().position(|voice| voice.is_none())
-        {
-            self.voices[free_idx] = Some(new_voice);
-            return self.voices[free_idx].as_mut().unwrap();
+        if let Some(from) = glide_from {
+            new_voice.glide_from(from, self.glide.duration_ms(from, note));
         }
 
-        // as we know voices are in use, we can use unwrap_unchecked()
-        // to avoid some unnecessary checks.
-        let oldest_voice = unsafe {
-            self.voices
-                .iter_mut()
-                .min_by_key(|voice| voice.as_ref().unwrap_unchecked().id)
-                .unwrap_unchecked()
-        };
+        new_voice.set_trigger(true);
+
+        // make_room_for() guarantees that there is a free voice
+        let free_idx = self.voices.iter().position(Option::is_none)?;
 
-        *oldest_voice = Some(new_voice);
-        return oldest_voice.as_mut().unwrap();
+        self.voices[free_idx] = Some(new_voice);
+        self.update_sub_note();
+
+        self.voices[free_idx].as_mut()
     }
 
     /// Starts a voice's release stage.
@@ -236,21 +428,17 @@ impl VoiceHandler {
     ) {
         for voice in &mut self.voices {
             match voice {
-                Some(Voice {
-                    id: candidate_id,
-                    note: candidate_note,
-                    releasing,
-                    envelope,
-                    ..
-                }) if voice_id == Some(*candidate_id)
-                    || note == *candidate_note =>
+                Some(voice)
+                    if voice_id == Some(voice.id) || note == voice.note =>
                 {
-                    *releasing = true;
-                    envelope.set_trigger(false);
+                    voice.releasing = true;
+                    voice.set_trigger(false);
                 }
                 _ => (),
             }
         }
+
+        self.update_sub_note();
     }
 
     /// Starts the release stage for all active voices.
@@ -258,18 +446,28 @@ impl VoiceHandler {
         self.voices.iter_mut().for_each(|v| {
             if let Some(voice) = v {
                 voice.releasing = true;
-                voice.envelope.set_trigger(false);
+                voice.set_trigger(false);
             }
         });
+
+        self.update_sub_note();
     }
 
-    /// Immediately terminates all active voices.
+    /// Immediately terminates all active voices, including stolen voices
+    /// which are fading out.
     pub fn kill_active_voices(&mut self) {
         self.voices.iter_mut().for_each(|v| {
             if v.is_some() {
                 *v = None;
             }
         });
+
+        self.stolen.iter_mut().for_each(|v| *v = None);
+
+        let sample_rate = self.sample_rate.lr();
+        self.sub = self.sub.as_ref().map(|sub| {
+            SubOscillator::new(*sub.parameters(), sample_rate)
+        });
     }
 
     /// Terminates all voices which are releasing and which have an
@@ -283,11 +481,350 @@ impl VoiceHandler {
                 _ => (),
             }
         }
+
+        for stolen in &mut self.stolen {
+            if stolen.as_ref().is_some_and(StolenVoice::is_finished) {
+                *stolen = None;
+            }
+        }
+    }
+
+    /// Sets the frequency ratio of an FM operator, relative to each voice's
+    /// note.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `operator` is out of range.
+    pub fn set_fm_ratio(&mut self, operator: usize, ratio: f64) {
+        self.fm_parameters.ratios[operator] = ratio;
+        self.update_fm_parameters();
+    }
+
+    /// Sets the modulation index of an FM operator, in radians.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `operator` is out of range.
+    pub fn set_fm_index(&mut self, operator: usize, index: f64) {
+        self.fm_parameters.indices[operator] = index;
+        self.update_fm_parameters();
+    }
+
+    /// Sets how much the FM operator `source` modulates `destination`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either operator is out of range.
+    pub fn set_fm_modulation(
+        &mut self,
+        source: usize,
+        destination: usize,
+        amount: f64,
+    ) {
+        self.fm_parameters.set_modulation(source, destination, amount);
+        self.update_fm_parameters();
+    }
+
+    /// Sets all of the FM parameters at once.
+    pub fn set_fm_parameters(&mut self, parameters: FmParameters) {
+        self.fm_parameters = parameters;
+        self.update_fm_parameters();
+    }
+
+    pub const fn fm_parameters(&self) -> &FmParameters {
+        &self.fm_parameters
+    }
+
+    /// Sets the brightness of each plucked string, from `0.0` to `1.0`.
+    pub fn set_pluck_brightness(&mut self, brightness: f64) {
+        self.pluck_parameters.brightness = brightness.clamp(0.0, 1.0);
+        self.update_pluck_parameters();
+    }
+
+    /// Sets the time for each plucked string to decay by 60 dB.
+    pub fn set_pluck_decay(&mut self, decay_secs: f64) {
+        self.pluck_parameters.decay_secs = decay_secs;
+        self.update_pluck_parameters();
+    }
+
+    /// Sets all of the plucked string parameters at once.
+    pub fn set_pluck_parameters(&mut self, parameters: PluckParameters) {
+        self.pluck_parameters = parameters;
+        self.update_pluck_parameters();
+    }
+
+    pub const fn pluck_parameters(&self) -> &PluckParameters {
+        &self.pluck_parameters
+    }
+
+    /// Sets the color of each noise voice.
+    pub fn set_noise_color(&mut self, color: NoiseColor) {
+        self.noise_color = color;
+
+        for layer in self.active_layers_mut() {
+            if let Generator::Noise(noise) = &mut layer.generator {
+                noise.set_color(color);
+            }
+        }
+    }
+
+    pub const fn noise_color(&self) -> NoiseColor {
+        self.noise_color
+    }
+
+    /// Sets the number of generators stacked in each new voice.
+    pub fn set_unison_voices(&mut self, voices: usize) {
+        self.unison.voices = voices.clamp(1, MAX_UNISON_VOICES);
+    }
+
+    /// Sets the detune of the outermost unison generators, in cents.
+    pub fn set_unison_detune(&mut self, cents: f64) {
+        self.unison.detune_cents = cents.clamp(0.0, MAX_UNISON_DETUNE_CENTS);
+        self.update_unison_layers();
+    }
+
+    /// Sets how far the unison generators are spread across the stereo
+    /// field, from `0.0` to `1.0`.
+    pub fn set_unison_spread(&mut self, spread: f64) {
+        self.unison.spread = spread.clamp(0.0, 1.0);
+        self.update_unison_layers();
+    }
+
+    /// Sets all of the unison parameters at once. The number of generators
+    /// only affects new voices.
+    pub fn set_unison_parameters(&mut self, parameters: UnisonParameters) {
+        self.set_unison_voices(parameters.voices);
+        self.unison.random_phase = parameters.random_phase;
+        self.unison.detune_cents =
+            parameters.detune_cents.clamp(0.0, MAX_UNISON_DETUNE_CENTS);
+        self.set_unison_spread(parameters.spread);
+    }
+
+    pub const fn unison_parameters(&self) -> &UnisonParameters {
+        &self.unison
+    }
+
+    /// Sets how voices are chosen to be stolen.
+    pub fn set_voice_stealing(&mut self, stealing: VoiceStealing) {
+        self.stealing = stealing;
+    }
+
+    pub const fn voice_stealing(&self) -> VoiceStealing {
+        self.stealing
+    }
+
+    /// Sets the most voices which may be held or releasing at once, from
+    /// `1` to `NUM_VOICES`. Voices above the new limit are stolen, unless
+    /// the stealing policy is [`VoiceStealing::None`].
+    pub fn set_max_polyphony(&mut self, max_polyphony: usize) {
+        self.max_polyphony = max_polyphony.clamp(1, NUM_VOICES as usize);
+
+        while self.num_active_voices() > self.max_polyphony {
+            // no voice plays a NaN note, so same-note stealing falls back to
+            // the oldest voice
+            let Some(idx) = self.stealing.choose(&self.voices, f64::NAN)
+            else {
+                return;
+            };
+
+            self.steal_voice(idx);
+        }
+    }
+
+    pub const fn max_polyphony(&self) -> usize {
+        self.max_polyphony
+    }
+
+    /// Sets the glide time in milliseconds, where `0.0` disables gliding.
+    pub fn set_glide_time(&mut self, time_ms: f64) {
+        self.glide.time_ms = time_ms.clamp(0.0, MAX_GLIDE_TIME_MS);
+    }
+
+    pub fn set_glide_mode(&mut self, mode: GlideMode) {
+        self.glide.mode = mode;
+    }
+
+    /// Sets all of the glide parameters at once. Voices which are already
+    /// gliding are unaffected.
+    pub fn set_glide_parameters(&mut self, parameters: GlideParameters) {
+        self.set_glide_time(parameters.time_ms);
+        self.glide.mode = parameters.mode;
+        self.glide.legato_only = parameters.legato_only;
+    }
+
+    pub const fn glide_parameters(&self) -> &GlideParameters {
+        &self.glide
+    }
+
+    /// Sets up the sub oscillator, or disables it if `parameters` is
+    /// `None`.
+    pub fn set_sub_parameters(&mut self, parameters: Option<SubParameters>) {
+        match (self.sub.as_mut(), parameters) {
+            (Some(sub), Some(parameters)) => sub.set_parameters(parameters),
+            (None, Some(parameters)) => {
+                let mut sub =
+                    SubOscillator::new(parameters, self.sample_rate.lr());
+                sub.set_note(self.lowest_held_note());
+
+                self.sub = Some(sub);
+            }
+            (_, None) => self.sub = None,
+        }
+    }
+
+    pub fn sub_parameters(&self) -> Option<&SubParameters> {
+        self.sub.as_ref().map(SubOscillator::parameters)
     }
 
-    /// Returns whether there is at least one voice active or not.
+    /// Returns whether there is at least one voice active or not, including
+    /// stolen voices which are fading out.
     pub fn is_voice_active(&self) -> bool {
         self.voices.iter().any(|v| v.is_some())
+            || self.stolen.iter().any(|v| v.is_some())
+            || self.sub.as_ref().is_some_and(SubOscillator::is_active)
+    }
+
+    /// Applies the FM parameters to each active FM voice.
+    fn update_fm_parameters(&mut self) {
+        let parameters = self.fm_parameters;
+
+        for layer in self.active_layers_mut() {
+            if let Generator::Fm(fm) = &mut layer.generator {
+                fm.set_parameters(&parameters);
+            }
+        }
+    }
+
+    /// Applies the plucked string parameters to each active string voice.
+    fn update_pluck_parameters(&mut self) {
+        let parameters = self.pluck_parameters;
+
+        for layer in self.active_layers_mut() {
+            if let Generator::Pluck(string) = &mut layer.generator {
+                string.set_parameters(&parameters);
+            }
+        }
+    }
+
+    /// Applies the unison detune and spread to each active voice, keeping
+    /// its number of generators.
+    fn update_unison_layers(&mut self) {
+        let sample_rate = self.sample_rate.lr();
+
+        for voice in self.voices.iter_mut().flatten() {
+            let stack = UnisonParameters {
+                voices: voice.layers.len(),
+                ..self.unison
+            };
+
+            let pitch = voice.current_pitch();
+
+            for (i, layer) in voice.layers.iter_mut().enumerate() {
+                layer.detune = stack.detune_for(i);
+                layer.gains = stack.gains_for(i);
+                layer.generator.change_freq(
+                    note_to_freq(pitch + layer.detune),
+                    sample_rate,
+                );
+            }
+        }
+    }
+
+    /// The pitch which a new voice should glide from, which is the current
+    /// pitch of the most recent voice (or of the most recent held voice,
+    /// if gliding is legato only).
+    fn glide_source(&self) -> Option<f64> {
+        if !self.glide.is_enabled() {
+            return None;
+        }
+
+        self.voices
+            .iter()
+            .flatten()
+            .filter(|v| !(self.glide.legato_only && v.releasing))
+            .max_by_key(|v| v.id)
+            .map(Voice::current_pitch)
+    }
+
+    /// Steals voices until there is room for a voice at `note` with
+    /// `num_layers` generators. Returns `false` if there isn't room, which
+    /// only happens if the stealing policy doesn't steal.
+    fn make_room_for(&mut self, note: f64, num_layers: usize) -> bool {
+        loop {
+            let has_voice = self.num_active_voices() < self.max_polyphony;
+            let has_layers = self.num_active_layers() + num_layers
+                <= MAX_ACTIVE_OSCILLATORS;
+
+            if has_voice && has_layers {
+                return true;
+            }
+
+            let Some(idx) = self.stealing.choose(&self.voices, note)
+            else {
+                return false;
+            };
+
+            self.steal_voice(idx);
+        }
+    }
+
+    /// The lowest note of the voices which are held.
+    fn lowest_held_note(&self) -> Option<f64> {
+        self.voices
+            .iter()
+            .flatten()
+            .filter(|v| !v.releasing)
+            .map(|v| v.note)
+            .min_by(f64::total_cmp)
+    }
+
+    /// Moves the sub oscillator to the lowest held note.
+    fn update_sub_note(&mut self) {
+        let note = self.lowest_held_note();
+
+        if let Some(sub) = self.sub.as_mut() {
+            sub.set_note(note);
+        }
+    }
+
+    /// Moves the voice at `idx` to the stolen voices, so that it fades out.
+    /// If too many voices are already fading out, the quietest of them is
+    /// terminated immediately.
+    fn steal_voice(&mut self, idx: usize) {
+        let Some(voice) = self.voices[idx].take()
+        else {
+            return;
+        };
+
+        let stolen = StolenVoice::new(voice, self.sample_rate.lr());
+
+        let slot = match self.stolen.iter().position(Option::is_none) {
+            Some(slot) => slot,
+            None => self
+                .stolen
+                .iter()
+                .enumerate()
+                .filter_map(|(i, v)| v.as_ref().map(|v| (i, v.gain())))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map_or(0, |(i, _)| i),
+        };
+
+        self.stolen[slot] = Some(stolen);
+        self.update_sub_note();
+    }
+
+    /// The number of voices which are held or releasing.
+    fn num_active_voices(&self) -> usize {
+        self.voices.iter().flatten().count()
+    }
+
+    /// The total number of generators across all active voices.
+    fn num_active_layers(&self) -> usize {
+        self.voices.iter().flatten().map(|v| v.layers.len()).sum()
+    }
+
+    fn active_layers_mut(&mut self) -> impl Iterator<Item = &mut UnisonLayer> {
+        self.voices.iter_mut().flatten().flat_map(|v| v.layers.iter_mut())
     }
 
     fn next_voice_id(&mut self) -> u64 {
@@ -0,0 +1,193 @@
+//! Validation of loaded configuration, which reports problems as a list of
+//! diagnostics rather than failing or silently ignoring entries.
+
+use super::*;
+use crossbeam_channel::{bounded, Receiver};
+use std::thread;
+
+/// How serious a configuration problem is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Something is likely a mistake, but the config still works.
+    Warning,
+    /// Part of the config is invalid, so defaults are used in its place.
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single problem with a configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Which config the problem is in, e.g. `"tracking config"`.
+    pub source: String,
+    pub message: String,
+    /// How the problem may be fixed, if known.
+    pub hint: Option<String>,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in {}: {}", self.severity, self.source, self.message)?;
+
+        if let Some(hint) = &self.hint {
+            write!(f, " ({hint})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A list of diagnostics for one configuration.
+#[derive(Clone, Debug)]
+pub struct Diagnostics {
+    source: String,
+    list: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// Returns an empty list for the config called `source`.
+    pub fn new(source: &str) -> Self {
+        Self { source: String::from(source), list: Vec::new() }
+    }
+
+    pub fn warning(&mut self, message: impl Into<String>) -> &mut Diagnostic {
+        self.push(Severity::Warning, message.into())
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) -> &mut Diagnostic {
+        self.push(Severity::Error, message.into())
+    }
+
+    fn push(&mut self, severity: Severity, message: String) -> &mut Diagnostic {
+        self.list.push(Diagnostic {
+            severity,
+            source: self.source.clone(),
+            message,
+            hint: None,
+        });
+
+        unsafe { self.list.last_mut().unwrap_unchecked() }
+    }
+
+    /// Reports a warning for each key of `value` which isn't in `known`,
+    /// as such keys would otherwise be silently ignored.
+    pub fn check_keys(
+        &mut self,
+        value: &serde_json::Value,
+        context: &str,
+        known: &[&str],
+    ) {
+        let Some(obj) = value.as_object()
+        else {
+            return;
+        };
+
+        for key in obj.keys().filter(|key| !known.contains(&key.as_str())) {
+            self.warning(format!("unknown key \"{key}\" in {context}"))
+                .with_hint(format!("expected one of: {}", known.join(", ")));
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.list
+    }
+}
+
+impl Diagnostic {
+    pub fn with_hint(&mut self, hint: impl Into<String>) -> &mut Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+// *** *** *** //
+
+/// The config files which are validated.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigPaths {
+    pub osc_map: Option<String>,
+    pub tracking_config: Option<String>,
+    pub profiles: Option<String>,
+    /// The mapping template's name or path.
+    pub mapping: Option<String>,
+}
+
+impl ConfigPaths {
+    pub fn from_args(args: &args::Arguments) -> Self {
+        Self {
+            osc_map: args.osc_map_path.clone(),
+            tracking_config: args.tracking_config_path.clone(),
+            profiles: args.profiles_path.clone(),
+            mapping: args.mapping.clone(),
+        }
+    }
+}
+
+/// Validates all configs on a background thread, so that startup isn't
+/// delayed by it.
+pub struct ConfigValidator {
+    result: Option<Receiver<Vec<Diagnostic>>>,
+}
+
+impl ConfigValidator {
+    /// Starts validating the built-in CC attachments and the configs at
+    /// `paths`.
+    pub fn spawn(paths: ConfigPaths) -> Self {
+        let (tx, rx) = bounded(1);
+
+        let spawned = thread::Builder::new()
+            .name(String::from("maestro_config_validation"))
+            .spawn(move || {
+                _ = tx.send(validate_configs(&paths));
+            });
+
+        if let Err(e) = &spawned {
+            eprintln!("failed to start config validation: {e}");
+        }
+
+        Self { result: spawned.is_ok().then_some(rx) }
+    }
+
+    /// Returns the diagnostics once validation has finished. This only
+    /// returns `Some` once.
+    pub fn try_take(&mut self) -> Option<Vec<Diagnostic>> {
+        let diagnostics = self.result.as_ref()?.try_recv().ok()?;
+        self.result = None;
+
+        Some(diagnostics)
+    }
+}
+
+/// A function which validates the config file at a path.
+type Diagnose = fn(&str) -> Diagnostics;
+
+/// Validates every config, returning all of their diagnostics with the most
+/// serious first.
+pub fn validate_configs(paths: &ConfigPaths) -> Vec<Diagnostic> {
+    let mut all =
+        params::diagnose_cc_attachments(paths.mapping.as_deref()).into_vec();
+
+    let files: [(&Option<String>, Diagnose); 3] = [
+        (&paths.osc_map, hands::address_map::diagnose_address_map),
+        (&paths.tracking_config, params::diagnose_tracking_config),
+        (&paths.profiles, params::diagnose_profiles),
+    ];
+
+    for (path, diagnose) in files {
+        if let Some(path) = path {
+            all.extend(diagnose(path).into_vec());
+        }
+    }
+
+    all.sort_by(|a, b| b.severity.cmp(&a.severity));
+    all
+}
@@ -0,0 +1,517 @@
+//! Configurable mapping of OSC addresses and arguments to hand data.
+//!
+//! An address map is loaded from a JSON file of the form:
+//!
+//! ```json
+//! {
+//!     "routes": [
+//!         { "address": "/hands/landmarks", "args": ["xyz"] },
+//!         { "address": "/hands/gesture", "args": ["ignore", "gestures"] }
+//!     ]
+//! }
+//! ```
+//!
+//! Each route's `args` describe, in order, what each OSC argument holds.
+//! String arguments are treated as comma-separated lists (brackets and quotes
+//! are stripped), and numeric arguments are treated as single values.
+
+use super::*;
+use crate::app::diagnostics::Diagnostics;
+use nannou_osc::Type;
+use serde_json::Value;
+
+/// The address which matches any incoming OSC address.
+pub const WILDCARD_ADDRESS: &str = "*";
+
+/// What a single OSC argument holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandArgField {
+    /// X coordinates for each vertex.
+    X,
+    /// Y coordinates for each vertex.
+    Y,
+    /// Z coordinates for each vertex.
+    Z,
+    /// Interleaved x, y, and z coordinates for each vertex. If the argument
+    /// is numeric, all remaining numeric arguments are consumed, so it must
+    /// be the last field of a route.
+    XYZ,
+    /// Gesture names for each hand.
+    Gestures,
+    /// An argument which should be skipped.
+    Ignore,
+}
+
+impl HandArgField {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "x" => Some(Self::X),
+            "y" => Some(Self::Y),
+            "z" => Some(Self::Z),
+            "xyz" => Some(Self::XYZ),
+            "gesture" | "gestures" => Some(Self::Gestures),
+            "ignore" | "_" => Some(Self::Ignore),
+            _ => None,
+        }
+    }
+}
+
+/// Values collected from one or more OSC messages, prior to being assigned to
+/// hands.
+#[derive(Clone, Debug, Default)]
+pub struct HandValues {
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
+    pub z: Vec<f64>,
+    pub gestures: Vec<String>,
+}
+
+impl HandValues {
+    pub fn clear(&mut self) {
+        self.x.clear();
+        self.y.clear();
+        self.z.clear();
+        self.gestures.clear();
+    }
+}
+
+/// A mapping from one OSC address to an ordered set of argument fields.
+#[derive(Clone, Debug)]
+pub struct OSCAddressRoute {
+    pub address: String,
+    pub args: Vec<HandArgField>,
+}
+
+impl OSCAddressRoute {
+    /// Whether `address` is handled by this route. A trailing `*` in the
+    /// route's address matches any suffix.
+    pub fn matches(&self, address: &str) -> bool {
+        if let Some(prefix) = self.address.strip_suffix(WILDCARD_ADDRESS) {
+            address.starts_with(prefix)
+        }
+        else {
+            self.address == address
+        }
+    }
+
+    /// Parses `args` according to the route, and appends the results to
+    /// `values`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are fewer arguments than the route expects,
+    /// or if an argument could not be parsed.
+    pub fn apply(
+        &self,
+        args: Vec<Type>,
+        values: &mut HandValues,
+    ) -> Result<(), String> {
+        let mut args = args.into_iter().peekable();
+
+        for &field in &self.args {
+            let Some(arg) = args.next()
+            else {
+                return Err(format!(
+                    "expected an argument for {field:?} at address \"{}\"",
+                    self.address
+                ));
+            };
+
+            match field {
+                HandArgField::Ignore => {}
+                HandArgField::X => push_values(&arg, &mut values.x)?,
+                HandArgField::Y => push_values(&arg, &mut values.y)?,
+                HandArgField::Z => push_values(&arg, &mut values.z)?,
+                HandArgField::Gestures => {
+                    if let Type::String(s) = arg {
+                        values.gestures.extend(
+                            split_list(&s).map(|g| g.to_string()),
+                        );
+                    }
+                    else {
+                        return Err(String::from(
+                            "expected a string argument for gestures",
+                        ));
+                    }
+                }
+                HandArgField::XYZ => {
+                    let mut xyz = Vec::new();
+                    push_values(&arg, &mut xyz)?;
+
+                    if numeric_value(&arg).is_some() {
+                        while let Some(next) = args.peek()
+                            && let Some(v) = numeric_value(next)
+                        {
+                            xyz.push(v);
+                            _ = args.next();
+                        }
+                    }
+
+                    if xyz.len() % 3 != 0 {
+                        return Err(format!(
+                            "received {} interleaved coordinates, which is not a multiple of 3",
+                            xyz.len()
+                        ));
+                    }
+
+                    for p in xyz.chunks_exact(3) {
+                        values.x.push(p[0]);
+                        values.y.push(p[1]);
+                        values.z.push(p[2]);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A set of routes which map OSC addresses to hand data, loaded at startup.
+#[derive(Clone, Debug)]
+pub struct OSCAddressMap {
+    routes: Vec<OSCAddressRoute>,
+}
+
+impl OSCAddressMap {
+    /// Loads an address map from a JSON file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid map.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            format!("failed to read OSC address map \"{path}\": {e}")
+        })?;
+
+        Self::from_json_str(&contents)
+    }
+
+    /// Parses an address map from a JSON string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` is not a valid address map.
+    pub fn from_json_str(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        let Some(routes) = value.get("routes").and_then(|r| r.as_array())
+        else {
+            return Err(String::from(
+                "OSC address map is missing a \"routes\" array",
+            ));
+        };
+
+        let mut map = Self { routes: Vec::with_capacity(routes.len()) };
+
+        for route in routes {
+            let Some(address) = route.get("address").and_then(|a| a.as_str())
+            else {
+                return Err(String::from("route is missing an \"address\""));
+            };
+
+            let Some(arg_names) = route.get("args").and_then(|a| a.as_array())
+            else {
+                return Err(format!(
+                    "route \"{address}\" is missing an \"args\" array"
+                ));
+            };
+
+            let mut args = Vec::with_capacity(arg_names.len());
+
+            for name in arg_names {
+                let name = name.as_str().unwrap_or_default();
+                let Some(field) = HandArgField::from_name(name)
+                else {
+                    return Err(format!(
+                        "unknown argument \"{name}\" for route \"{address}\""
+                    ));
+                };
+
+                if args.last() == Some(&HandArgField::XYZ) {
+                    return Err(format!(
+                        "\"xyz\" must be the last argument of route \
+                         \"{address}\""
+                    ));
+                }
+
+                args.push(field);
+            }
+
+            map.routes.push(OSCAddressRoute {
+                address: address.to_string(),
+                args,
+            });
+        }
+
+        if map.routes.is_empty() {
+            return Err(String::from("OSC address map has no routes"));
+        }
+
+        Ok(map)
+    }
+
+    /// Returns the first route which matches `address`, if any.
+    pub fn route_for(&self, address: &str) -> Option<&OSCAddressRoute> {
+        self.routes.iter().find(|r| r.matches(address))
+    }
+
+    /// Returns all the routes in the map.
+    pub fn routes(&self) -> &[OSCAddressRoute] {
+        &self.routes
+    }
+}
+
+impl Default for OSCAddressMap {
+    /// The layout sent by the MediaPipe tracker: four strings holding the x,
+    /// y, and z coordinates and gesture names, on any address.
+    fn default() -> Self {
+        Self {
+            routes: vec![OSCAddressRoute {
+                address: WILDCARD_ADDRESS.to_string(),
+                args: vec![
+                    HandArgField::X,
+                    HandArgField::Y,
+                    HandArgField::Z,
+                    HandArgField::Gestures,
+                ],
+            }],
+        }
+    }
+}
+
+fn numeric_value(arg: &Type) -> Option<f64> {
+    match arg {
+        Type::Float(f) => Some(*f as f64),
+        Type::Double(d) => Some(*d),
+        Type::Int(i) => Some(*i as f64),
+        Type::Long(l) => Some(*l as f64),
+        _ => None,
+    }
+}
+
+fn push_values(arg: &Type, target: &mut Vec<f64>) -> Result<(), String> {
+    if let Type::String(s) = arg {
+        for v in split_list(s) {
+            target.push(
+                v.parse()
+                    .map_err(|_| format!("failed to parse value \"{v}\""))?,
+            );
+        }
+
+        Ok(())
+    }
+    else if let Some(v) = numeric_value(arg) {
+        target.push(v);
+        Ok(())
+    }
+    else {
+        Err(format!("unsupported argument type: {arg:?}"))
+    }
+}
+
+fn split_list(s: &str) -> impl Iterator<Item = &str> {
+    s.split(',')
+        .map(|x| x.trim_matches(|c: char| c.is_whitespace() || "[]'\"".contains(c)))
+        .filter(|x| !x.is_empty())
+}
+
+/// Checks the address map at `path` for problems which would stop it from
+/// loading, or which would cause routes to be ignored.
+pub fn diagnose_address_map(path: &str) -> Diagnostics {
+    let mut diags = Diagnostics::new("OSC address map");
+
+    let value = match std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read \"{path}\": {e}"))
+        .and_then(|s| {
+            serde_json::from_str::<Value>(&s).map_err(|e| e.to_string())
+        }) {
+        Ok(value) => value,
+        Err(e) => {
+            diags.error(e).with_hint("the default address map is used");
+            return diags;
+        }
+    };
+
+    if let Err(e) = OSCAddressMap::from_json_str(&value.to_string()) {
+        diags.error(e).with_hint("the default address map is used");
+    }
+
+    diags.check_keys(&value, "the address map", &["routes"]);
+
+    let routes = value.get("routes").and_then(Value::as_array);
+    let mut seen: Vec<&str> = Vec::new();
+    let mut provides_positions = false;
+
+    for route in routes.into_iter().flatten() {
+        diags.check_keys(route, "a route", &["address", "args"]);
+
+        let Some(address) = route.get("address").and_then(Value::as_str)
+        else {
+            continue;
+        };
+
+        let fields: Vec<HandArgField> = route
+            .get("args")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|name| name.as_str().and_then(HandArgField::from_name))
+            .collect();
+
+        if fields.is_empty() {
+            diags.warning(format!("route \"{address}\" has no arguments"));
+        }
+
+        provides_positions |= fields.iter().any(|f| {
+            matches!(f, HandArgField::X | HandArgField::XYZ)
+        });
+
+        let shadowed_by = seen.iter().find(|prev| {
+            OSCAddressRoute { address: (*prev).to_string(), args: Vec::new() }
+                .matches(address)
+        });
+
+        if let Some(prev) = shadowed_by {
+            diags
+                .warning(format!(
+                    "route \"{address}\" is never used, as \"{prev}\" matches it first"
+                ))
+                .with_hint("move the more specific route earlier");
+        }
+
+        seen.push(address);
+    }
+
+    if routes.is_some_and(|r| !r.is_empty()) && !provides_positions {
+        diags
+            .warning("no route provides hand positions")
+            .with_hint("add an \"x\" or \"xyz\" argument to a route");
+    }
+
+    diags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(address: &str, args: &[HandArgField]) -> OSCAddressRoute {
+        OSCAddressRoute { address: address.to_string(), args: args.to_vec() }
+    }
+
+    #[test]
+    fn routes_match_exact_and_wildcard_addresses() {
+        let exact = route("/hands/landmarks", &[]);
+        assert!(exact.matches("/hands/landmarks"));
+        assert!(!exact.matches("/hands/landmarks/left"));
+
+        let wildcard = route("/hands/*", &[]);
+        assert!(wildcard.matches("/hands/landmarks"));
+        assert!(wildcard.matches("/hands/"));
+        assert!(!wildcard.matches("/gestures"));
+
+        assert!(route(WILDCARD_ADDRESS, &[]).matches("/anything"));
+    }
+
+    #[test]
+    fn the_first_matching_route_is_used() {
+        let map = OSCAddressMap::from_json_str(
+            r#"{ "routes": [
+                { "address": "/hands/gesture", "args": ["_", "gestures"] },
+                { "address": "/hands/*", "args": ["x", "y", "z"] }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            map.route_for("/hands/gesture").unwrap().args,
+            [HandArgField::Ignore, HandArgField::Gestures]
+        );
+        assert_eq!(map.route_for("/hands/landmarks").unwrap().args.len(), 3);
+        assert!(map.route_for("/other").is_none());
+    }
+
+    #[test]
+    fn invalid_maps_are_rejected() {
+        let invalid = [
+            "not json",
+            r#"{ "routes": [] }"#,
+            r#"{ "routes": [{ "args": ["x"] }] }"#,
+            r#"{ "routes": [{ "address": "/a" }] }"#,
+            r#"{ "routes": [{ "address": "/a", "args": ["w"] }] }"#,
+            // a numeric xyz would leave nothing for the gestures
+            r#"{ "routes": [
+                { "address": "/a", "args": ["xyz", "gestures"] }
+            ] }"#,
+        ];
+
+        for json in invalid {
+            assert!(OSCAddressMap::from_json_str(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn string_arguments_are_split_into_lists() {
+        let mut values = HandValues::default();
+        let args = vec![
+            Type::String(String::from("[0.1, 0.2]")),
+            Type::String(String::from("0.3,0.4")),
+            Type::String(String::from("'0.5', '0.6'")),
+            Type::String(String::from("['Open_Palm', 'None']")),
+        ];
+
+        OSCAddressMap::default().routes()[0].apply(args, &mut values).unwrap();
+
+        assert_eq!(values.x, [0.1, 0.2]);
+        assert_eq!(values.y, [0.3, 0.4]);
+        assert_eq!(values.z, [0.5, 0.6]);
+        assert_eq!(values.gestures, ["Open_Palm", "None"]);
+    }
+
+    #[test]
+    fn numeric_xyz_consumes_the_remaining_arguments() {
+        let route = route(
+            "/hands/landmarks",
+            &[HandArgField::Gestures, HandArgField::XYZ],
+        );
+        let mut values = HandValues::default();
+        let args = vec![
+            Type::String(String::from("Victory")),
+            Type::Float(0.1),
+            Type::Double(0.2),
+            Type::Int(3),
+            Type::Float(0.4),
+            Type::Float(0.5),
+            Type::Long(6),
+        ];
+
+        route.apply(args, &mut values).unwrap();
+
+        assert_eq!(values.gestures, ["Victory"]);
+        assert_eq!(values.x.len(), 2);
+        assert!((values.y[0] - 0.2).abs() < 1e-6);
+        assert!((values.z[1] - 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn malformed_arguments_are_errors() {
+        let xyz = route("/a", &[HandArgField::XYZ]);
+        let mut values = HandValues::default();
+
+        assert!(xyz
+            .apply(vec![Type::Float(0.1), Type::Float(0.2)], &mut values)
+            .is_err());
+        assert!(xyz.apply(Vec::new(), &mut values).is_err());
+
+        let x = route("/a", &[HandArgField::X]);
+        assert!(x
+            .apply(vec![Type::String(String::from("a, b"))], &mut values)
+            .is_err());
+        assert!(x.apply(vec![Type::Bool(true)], &mut values).is_err());
+
+        let gestures = route("/a", &[HandArgField::Gestures]);
+        assert!(gestures.apply(vec![Type::Int(1)], &mut values).is_err());
+    }
+}
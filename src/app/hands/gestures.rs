@@ -0,0 +1,303 @@
+//! Discrete gesture events, detected from the continuous hand data.
+//!
+//! Most of the app maps the hands to parameters continuously, but some
+//! features need to know when a gesture has been *made*, such as a fist
+//! being closed or a swipe across the camera. [`GestureEvents`] follows each
+//! hand and publishes an event as each gesture is completed, which any
+//! number of features may read during the same update.
+
+use super::hand_types::{Finger, HandGesture, RawHand, RawHandPairCOM};
+use super::*;
+use std::collections::VecDeque;
+
+/// How long a recognized gesture must be held before it is published, in
+/// seconds.
+const GESTURE_HOLD_TIME: f64 = 0.3;
+/// How far the thumb and index finger must pinch to publish a pinch.
+const PINCH_THRESHOLD: f64 = 0.8;
+/// How far the thumb and index finger must open again before the next
+/// pinch is published.
+const PINCH_RELEASE: f64 = 0.5;
+/// How far a hand must move horizontally to swipe, as a proportion of the
+/// camera's view.
+const SWIPE_DISTANCE: f64 = 0.25;
+/// The time which a swipe must be made within, in seconds.
+const SWIPE_TIME: f64 = 0.4;
+/// The time after a swipe before the next one is published, in seconds.
+const SWIPE_COOLDOWN: f64 = 0.6;
+
+/// The direction of a swipe, from the camera's point of view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+}
+
+/// A gesture which has just been completed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GestureEvent {
+    /// A palm was opened, and held open.
+    Open,
+    /// A fist was closed, and held closed.
+    Closed,
+    /// A thumb was raised, and held up.
+    ThumbUp,
+    /// The thumb and index finger were pinched together.
+    Pinch,
+    /// A hand moved quickly across the camera's view.
+    Swipe(SwipeDirection),
+}
+
+/// What has been detected of a single hand.
+#[derive(Clone, Debug, Default)]
+struct HandTracker {
+    /// The gesture which the hand is making, and how long it has been made
+    /// for in seconds.
+    held: Option<(HandGesture, f64)>,
+    /// Whether the held gesture has been published.
+    held_published: bool,
+    is_pinched: bool,
+    /// The horizontal position of the hand's center of mass over the last
+    /// [`SWIPE_TIME`], with the time which each was recorded at.
+    trail: VecDeque<(f64, f64)>,
+    swipe_cooldown: f64,
+}
+
+impl HandTracker {
+    /// Follows `hand`, whose center of mass is `com`, and pushes any
+    /// gestures which it completes to `events`.
+    fn update(
+        &mut self,
+        hand: &RawHand,
+        com: DVec3,
+        now: f64,
+        dt: f64,
+        events: &mut Vec<GestureEvent>,
+    ) {
+        self.update_held(hand.gesture, dt, events);
+
+        let pinch = hand.get_pinch_for(Finger::Index);
+
+        if !self.is_pinched && pinch >= PINCH_THRESHOLD {
+            self.is_pinched = true;
+            events.push(GestureEvent::Pinch);
+        }
+        else if self.is_pinched && pinch <= PINCH_RELEASE {
+            self.is_pinched = false;
+        }
+
+        self.update_swipe(com.x, now, dt, events);
+    }
+
+    fn update_held(
+        &mut self,
+        gesture: HandGesture,
+        dt: f64,
+        events: &mut Vec<GestureEvent>,
+    ) {
+        let held_for = match self.held {
+            Some((held, held_for)) if held == gesture => held_for + dt,
+            _ => {
+                self.held_published = false;
+                0.0
+            }
+        };
+
+        self.held = Some((gesture, held_for));
+
+        if self.held_published || held_for < GESTURE_HOLD_TIME {
+            return;
+        }
+
+        let event = match gesture {
+            HandGesture::Open => GestureEvent::Open,
+            HandGesture::Closed => GestureEvent::Closed,
+            HandGesture::ThumbUp => GestureEvent::ThumbUp,
+            _ => return,
+        };
+
+        self.held_published = true;
+        events.push(event);
+    }
+
+    fn update_swipe(
+        &mut self,
+        x: f64,
+        now: f64,
+        dt: f64,
+        events: &mut Vec<GestureEvent>,
+    ) {
+        self.swipe_cooldown = (self.swipe_cooldown - dt).max(0.0);
+
+        self.trail.push_back((now, x));
+        while self.trail.front().is_some_and(|&(t, _)| now - t > SWIPE_TIME) {
+            _ = self.trail.pop_front();
+        }
+
+        if self.swipe_cooldown > 0.0 {
+            return;
+        }
+
+        let (min, max) = self
+            .trail
+            .iter()
+            .fold((x, x), |(min, max), &(_, x)| (min.min(x), max.max(x)));
+
+        // the hand must have ended the swipe at one of its extremes
+        let direction = if max - min < SWIPE_DISTANCE {
+            return;
+        }
+        else if epsilon_eq(x, max) {
+            SwipeDirection::Right
+        }
+        else if epsilon_eq(x, min) {
+            SwipeDirection::Left
+        }
+        else {
+            return;
+        };
+
+        self.swipe_cooldown = SWIPE_COOLDOWN;
+        self.trail.clear();
+        events.push(GestureEvent::Swipe(direction));
+    }
+}
+
+/// Publishes the gestures which each hand completes.
+#[derive(Clone, Debug, Default)]
+pub struct GestureEvents {
+    trackers: [HandTracker; 2],
+    /// The events published by the last update.
+    events: Vec<GestureEvent>,
+    /// The time since the events were first updated, in seconds.
+    time: f64,
+}
+
+impl GestureEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the published events with the gestures which `hands`
+    /// completed over the last `dt` seconds.
+    pub fn update(&mut self, hands: &RawHandPairCOM, dt: f64) {
+        self.time += dt;
+        self.events.clear();
+
+        let pairs = [
+            (&hands.pair.first, hands.com.first),
+            (&hands.pair.second, hands.com.second),
+        ];
+
+        for (tracker, pair) in self.trackers.iter_mut().zip(pairs) {
+            if let (Some(hand), Some(com)) = pair {
+                tracker.update(hand, com, self.time, dt, &mut self.events);
+            }
+            else {
+                // a hand which returns starts its gestures over
+                *tracker = HandTracker::default();
+            }
+        }
+    }
+
+    /// The gestures which were completed during the last update.
+    pub fn events(&self) -> &[GestureEvent] {
+        &self.events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::params::test_utils::{hands_from, SyntheticHand};
+
+    const DT: f64 = 1.0 / 60.0;
+
+    /// Updates `events` with `hand` for `secs` seconds, and returns every
+    /// event which was published.
+    fn hold(
+        events: &mut GestureEvents,
+        hand: SyntheticHand,
+        secs: f64,
+    ) -> Vec<GestureEvent> {
+        let mut published = Vec::new();
+
+        for _ in 0..(secs / DT) as usize {
+            events.update(&hands_from(Some(hand)), DT);
+            published.extend_from_slice(events.events());
+        }
+
+        published
+    }
+
+    #[test]
+    fn held_gestures_are_published_once() {
+        let mut events = GestureEvents::new();
+        let fist = SyntheticHand {
+            gesture: HandGesture::Closed,
+            ..Default::default()
+        };
+
+        assert!(hold(&mut events, fist, 0.1).is_empty());
+        assert_eq!(hold(&mut events, fist, 2.0), [GestureEvent::Closed]);
+
+        let open = SyntheticHand::default();
+        assert_eq!(hold(&mut events, open, 1.0), [GestureEvent::Open]);
+    }
+
+    #[test]
+    fn pinches_must_be_released_before_the_next() {
+        let mut events = GestureEvents::new();
+        let pinched = SyntheticHand { pinch: 1.0, ..Default::default() };
+        let released = SyntheticHand::default();
+
+        let num_pinches = |events: &mut GestureEvents, hand| {
+            hold(events, hand, 0.1)
+                .iter()
+                .filter(|&&e| e == GestureEvent::Pinch)
+                .count()
+        };
+
+        assert_eq!(num_pinches(&mut events, pinched), 1);
+        assert_eq!(num_pinches(&mut events, pinched), 0);
+        assert_eq!(num_pinches(&mut events, released), 0);
+        assert_eq!(num_pinches(&mut events, pinched), 1);
+    }
+
+    #[test]
+    fn fast_movements_are_swipes() {
+        let mut events = GestureEvents::new();
+        let mut published = Vec::new();
+
+        for i in 0..30 {
+            let x = 0.8 - 0.5 * i as f64 / 30.0;
+            let hand = SyntheticHand {
+                com: DVec3::new(x, 0.5, 0.5),
+                ..Default::default()
+            };
+
+            published.extend(hold(&mut events, hand, DT));
+        }
+
+        assert!(published
+            .contains(&GestureEvent::Swipe(SwipeDirection::Left)));
+
+        // a slow movement isn't a swipe
+        let mut events = GestureEvents::new();
+        let mut published = Vec::new();
+
+        for i in 0..300 {
+            let x = 0.3 + 0.5 * i as f64 / 300.0;
+            let hand = SyntheticHand {
+                com: DVec3::new(x, 0.5, 0.5),
+                ..Default::default()
+            };
+
+            published.extend(hold(&mut events, hand, DT));
+        }
+
+        assert!(!published
+            .iter()
+            .any(|e| matches!(e, GestureEvent::Swipe(_))));
+    }
+}
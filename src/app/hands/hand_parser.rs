@@ -1,7 +1,10 @@
+use super::address_map::{HandValues, OSCAddressMap};
 use super::hand_types::*;
+use super::handshake::{TrackerHandshake, HANDSHAKE_OSC_ADDRESS};
+use super::packed::{decode_packed_frame, is_packed_frame};
 use super::*;
 
-use nannou_osc::Packet;
+use nannou_osc::{Message, Packet, Type};
 use serde_json::{Error, Value};
 
 const JSON_HAND_SCHEMA: &str =
@@ -9,7 +12,8 @@ const JSON_HAND_SCHEMA: &str =
 
 pub struct HandParser {
     validator: jsonschema::Validator,
-    curr_strings: [String; 4],
+    address_map: OSCAddressMap,
+    values: HandValues,
 
     first_hand_buf: [DVec3; NUM_HAND_VERTICES],
     second_hand_buf: [DVec3; NUM_HAND_VERTICES],
@@ -18,10 +22,13 @@ pub struct HandParser {
     second_hand_gesture: HandGesture,
 
     has_second: bool,
+
+    /// Why the tracker's hand data can't be parsed, if it is incompatible.
+    incompatibility: Option<String>,
 }
 
 impl HandParser {
-    pub fn new() -> Self {
+    pub fn new(address_map: OSCAddressMap) -> Self {
         let schema: Value = serde_json::from_str(JSON_HAND_SCHEMA)
             .expect("failed to parse hand schema");
         let validator = jsonschema::Validator::new(&schema)
@@ -29,7 +36,8 @@ impl HandParser {
 
         Self {
             validator,
-            curr_strings: [const { String::new() }; 4],
+            address_map,
+            values: HandValues::default(),
 
             first_hand_buf: [DVec3::default(); NUM_HAND_VERTICES],
             second_hand_buf: [DVec3::default(); NUM_HAND_VERTICES],
@@ -38,67 +46,103 @@ impl HandParser {
             second_hand_gesture: HandGesture::default(),
 
             has_second: false,
+
+            incompatibility: None,
         }
     }
 
+    /// Checks a tracker's handshake message. Hand data is rejected while the
+    /// latest handshake is incompatible.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handshake is malformed or incompatible.
+    pub fn handle_handshake(
+        &mut self,
+        msg: &Message,
+    ) -> Result<TrackerHandshake, String> {
+        let result = TrackerHandshake::from_message(msg)
+            .map_err(|e| format!("malformed tracker handshake: {e}"))
+            .and_then(|handshake| {
+                handshake.check_compatibility()?;
+                Ok(handshake)
+            });
+
+        self.incompatibility = result.as_ref().err().cloned();
+
+        result
+    }
+
+    /// Why the tracker's hand data is being rejected, if it is.
+    pub fn incompatibility(&self) -> Option<&str> {
+        self.incompatibility.as_deref()
+    }
+
     pub fn parse_hands(
         &mut self,
         packet: Packet,
     ) -> Result<RawHandPair, String> {
         let msgs = packet.into_msgs();
 
+        // trackers may bundle their handshake with hand data, which is
+        // checked first as it decides whether the data is used
+        for m in msgs.iter().filter(|m| m.addr == HANDSHAKE_OSC_ADDRESS) {
+            _ = self.handle_handshake(m);
+        }
+
+        if let Some(e) = &self.incompatibility {
+            return Err(e.clone());
+        }
+
+        let mut num_matched = 0;
+
+        self.values.clear();
+
         for m in msgs {
-            let strings: Vec<String> =
-                m.args.into_iter().filter_map(|a| a.string()).collect();
-
-            if strings.len() != 4 {
-                return Err(format!(
-                    "received {} strings, but 4 were expected",
-                    strings.len()
-                ));
+            if m.addr == HANDSHAKE_OSC_ADDRESS {
+                continue;
             }
 
-            self.curr_strings
-                .iter_mut()
-                .zip(strings.into_iter())
-                .for_each(|(x, new)| {
-                    *x = new.replace(['[', ']', '\''], "");
+            // packed frames hold all hand data, so are used on their own
+            if let [Type::Blob(data)] = m.args.as_slice()
+                && is_packed_frame(data)
+            {
+                let hands = decode_packed_frame(data)?;
+
+                return Ok(RawHandPair {
+                    second: hands.second.filter(|_| self.has_second),
+                    ..hands
                 });
+            }
+
+            let Some(route) = self.address_map.route_for(&m.addr)
+            else {
+                continue;
+            };
+
+            route.apply(m.args, &mut self.values)?;
+            num_matched += 1;
+        }
+
+        if num_matched == 0 {
+            return Err(String::from(
+                "no route in the OSC address map matched the received packet",
+            ));
         }
 
-        if !self.parse_curr_strings() {
+        if !self.parse_values() {
             return Err(String::from("mismatch in number of coordinates"));
         }
 
         Ok(self.construct_hand_pair())
     }
 
-    fn parse_curr_strings(&mut self) -> bool {
-        let (mut x_count, mut y_count, mut z_count) = (0, 0, 0);
-        let mut x_vals = [0.0; NUM_HAND_VERTICES * 2];
-        let mut y_vals = [0.0; NUM_HAND_VERTICES * 2];
-        let mut z_vals = [0.0; NUM_HAND_VERTICES * 2];
-
-        for (i, x) in self.curr_strings[0].split(',').enumerate() {
-            // dbg!(x);
-            let val = x.trim().parse().expect("failed to parse value");
-            x_vals[i] = val;
-            x_count += 1;
-        }
-
-        for (i, y) in self.curr_strings[1].split(',').enumerate() {
-            let val = y.trim().parse().expect("failed to parse value");
-            y_vals[i] = val;
-            y_count += 1;
-        }
+    fn parse_values(&mut self) -> bool {
+        let HandValues { x, y, z, gestures } = &self.values;
+        let (x_count, y_count, z_count) = (x.len(), y.len(), z.len());
 
-        for (i, z) in self.curr_strings[2].split(',').enumerate() {
-            let val = z.trim().parse().expect("failed to parse value");
-            z_vals[i] = val;
-            z_count += 1;
-        }
-
-        if (x_count + y_count + z_count) % NUM_HAND_VERTICES != 0
+        if x_count == 0
+            || (x_count + y_count + z_count) % NUM_HAND_VERTICES != 0
             || x_count != y_count
             || x_count != z_count
         {
@@ -107,11 +151,11 @@ impl HandParser {
         }
 
         let copy_to_buf = |b: &mut [DVec3; NUM_HAND_VERTICES], off| {
-            for (((x, y), z), target) in x_vals
+            for (((x, y), z), target) in x
                 .iter()
                 .skip(off)
-                .zip(y_vals.iter().skip(off))
-                .zip(z_vals.iter().skip(off))
+                .zip(y.iter().skip(off))
+                .zip(z.iter().skip(off))
                 .zip(b.iter_mut())
             {
                 target.x = *x;
@@ -120,23 +164,20 @@ impl HandParser {
             }
         };
 
-        let gestures: Vec<&str> = self.curr_strings[3].split(',').collect();
-
         // NOTE(jamie): for now we don't consider the second hand, so it remains
         // false.
         // self.has_second = x_count / NUM_HAND_VERTICES > 1;
 
         copy_to_buf(&mut self.first_hand_buf, 0);
-        if !gestures.is_empty() {
-            self.first_hand_gesture = HandGesture::from(gestures[0].trim());
+        if let Some(g) = gestures.first() {
+            self.first_hand_gesture = HandGesture::from(g.trim());
         }
 
         if self.has_second {
             copy_to_buf(&mut self.second_hand_buf, NUM_HAND_VERTICES);
 
-            if gestures.len() > 1 {
-                self.second_hand_gesture =
-                    HandGesture::from(gestures[1].trim());
+            if let Some(g) = gestures.get(1) {
+                self.second_hand_gesture = HandGesture::from(g.trim());
             }
         }
 
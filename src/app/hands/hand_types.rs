@@ -3,6 +3,7 @@ use std::{mem::transmute, ops::Rem};
 use nannou::color::{Alpha, IntoColor};
 use rand::seq::IndexedRandom;
 
+use crate::app::view::palette::{Palette, PaletteLayer};
 use crate::util;
 
 use super::*;
@@ -36,6 +37,8 @@ pub enum HandGesture {
     Closed,
     ThumbUp,
     ThumbDown,
+    /// Two fingers pointing up (a "V" shape).
+    Victory,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -106,13 +109,19 @@ impl HandGesture {
         matches!(self, Self::ThumbDown)
     }
 
+    pub const fn is_victory(self) -> bool {
+        matches!(self, Self::Victory)
+    }
+
     pub const fn get_draw_color(self) -> Rgba {
         if LIGHT_MODE {
             match self {
                 Self::Unknown => UNKNOWN_HAND_COLOR,
                 Self::Open => OPEN_HAND_COLOR,
                 Self::Closed => CLOSED_HAND_COLOR,
-                Self::ThumbUp | Self::ThumbDown => THUMB_UP_DOWN_HAND_COLOR,
+                Self::ThumbUp | Self::ThumbDown | Self::Victory => {
+                    THUMB_UP_DOWN_HAND_COLOR
+                }
             }
         }
         else {
@@ -120,7 +129,7 @@ impl HandGesture {
                 Self::Unknown => DARK_UNKNOWN_HAND_COLOR,
                 Self::Open => DARK_OPEN_HAND_COLOR,
                 Self::Closed => DARK_CLOSED_HAND_COLOR,
-                Self::ThumbUp | Self::ThumbDown => {
+                Self::ThumbUp | Self::ThumbDown | Self::Victory => {
                     DARK_THUMB_UP_DOWN_HAND_COLOR
                 }
             }
@@ -133,6 +142,19 @@ impl HandGesture {
             "Closed_Fist" => Self::Closed,
             "Thumb_Up" => Self::ThumbUp,
             "Thumb_Down" => Self::ThumbDown,
+            "Victory" => Self::Victory,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// The gesture numbered `idx` in packed hand frames.
+    pub const fn from_index(idx: u8) -> Self {
+        match idx {
+            1 => Self::Open,
+            2 => Self::Closed,
+            3 => Self::ThumbUp,
+            4 => Self::ThumbDown,
+            5 => Self::Victory,
             _ => Self::Unknown,
         }
     }
@@ -208,7 +230,11 @@ impl RawHand {
         let dist = self.points[THUMB_TIP_VERTEX_INDEX]
             .distance(self.points[finger.index()]);
 
-        1.0 - normalize((dist / reference_dist).clamp(0.0, 1.0), MIN_DIST, 1.0)
+        // distances below the minimum would otherwise exceed a full pinch
+        let closeness =
+            normalize((dist / reference_dist).clamp(0.0, 1.0), MIN_DIST, 1.0);
+
+        (1.0 - closeness).clamp(0.0, 1.0)
     }
 }
 
@@ -240,7 +266,30 @@ pub struct RawHandPairCOM {
 pub struct CCUpdateData<'a> {
     pub hands: &'a RawHandPairCOM,
     pub velocities: &'a (f32, f32),
+    /// The envelope of each of the audio output's perceptual bands, from
+    /// lowest to highest, in `[0.0, 1.0]`.
+    pub audio_bands: &'a [f64],
     pub mode_sweep: Option<f64>,
+    /// The session's planned intensity, from `0.0` to `1.0`, if it follows
+    /// an intensity arc.
+    pub intensity: Option<f64>,
+}
+
+#[cfg(test)]
+impl<'a> CCUpdateData<'a> {
+    /// Update data for `hands` alone, with no audio bands or mode sweep.
+    pub const fn synthetic(
+        hands: &'a RawHandPairCOM,
+        velocities: &'a (f32, f32),
+    ) -> Self {
+        Self {
+            hands,
+            velocities,
+            audio_bands: &[],
+            mode_sweep: None,
+            intensity: None,
+        }
+    }
 }
 
 impl RawHandPairCOM {
@@ -292,6 +341,31 @@ impl Updatable for RawHandPairCOM {
     }
 }
 
+impl RawHandPairCOM {
+    /// Draws the hands tinted by `palette` with a glow of `glow` (from
+    /// `0.0` to `1.0`), and their centers of mass in its accent color.
+    ///
+    /// Each hand point is scaled by `glyph_scale`.
+    pub fn draw_with_palette(
+        &self,
+        draw: &Draw,
+        frame: &Frame,
+        palette: &Palette,
+        glow: f32,
+        glyph_scale: f32,
+    ) {
+        let tint = |col| palette.tint_hand(col);
+
+        self.pair.draw_glow(draw, frame, glow, glyph_scale, tint);
+        self.pair.draw_tinted(draw, frame, glyph_scale, tint);
+        self.com.draw_colored(
+            draw,
+            frame,
+            palette.color(PaletteLayer::Accents),
+        );
+    }
+}
+
 impl Drawable for RawHandPairCOM {
     fn draw(&self, draw: &Draw, frame: &Frame) {
         self.pair.draw(draw, frame);
@@ -344,13 +418,69 @@ fn rgb_to_hsl(color: Rgb<f32>) -> (f32, f32, f32) {
 
 impl Drawable for RawHandPair {
     fn draw(&self, draw: &Draw, frame: &Frame) {
+        self.draw_tinted(draw, frame, 1.0, |col| col);
+    }
+}
+
+impl RawHandPair {
+    /// Draws a soft glow around each hand point, in its gesture's color
+    /// passed through `tint`.
+    fn draw_glow(
+        &self,
+        draw: &Draw,
+        frame: &Frame,
+        glow: f32,
+        glyph_scale: f32,
+        tint: impl Fn(Rgba) -> Rgba,
+    ) {
+        const NUM_LAYERS: usize = 3;
+        const MAX_ALPHA: f32 = 0.25;
+
+        if glow <= 0.0 {
+            return;
+        }
+
+        let wh = frame.rect().wh().as_f64();
+
+        for hand in [&self.first, &self.second].into_iter().flatten() {
+            let col = tint(hand.gesture.get_draw_color());
+
+            for (i, p) in hand.points.iter().enumerate() {
+                let (point, depth) = to_xy_and_depth(*p, wh);
+                let scale = if is_outer_vertex(i) { 1.5 } else { 1.0 }
+                    * glyph_scale;
+
+                // wider layers are fainter, so the glow falls off
+                for layer in 1..=NUM_LAYERS {
+                    let size = 8.0 * scale * (1.0 + layer as f32 * 1.5);
+                    let alpha = depth as f32 * glow * MAX_ALPHA / layer as f32;
+
+                    draw.ellipse()
+                        .xy(point.as_f32())
+                        .wh(Vec2::splat(size))
+                        .color(Rgba { alpha, ..col })
+                        .finish();
+                }
+            }
+        }
+    }
+
+    /// Draws each hand in its gesture's color, passed through `tint`, with
+    /// each point scaled by `glyph_scale`.
+    fn draw_tinted(
+        &self,
+        draw: &Draw,
+        frame: &Frame,
+        glyph_scale: f32,
+        tint: impl Fn(Rgba) -> Rgba,
+    ) {
         let wh = frame.rect().wh().as_f64();
 
-        let width = 8.0;
+        let width = 8.0 * glyph_scale;
         let dims = Vec2::splat(width);
 
         if let Some(first) = &self.first {
-            let mut col = first.gesture.get_draw_color();
+            let mut col = tint(first.gesture.get_draw_color());
 
             for (i, p) in first.points.iter().enumerate() {
                 let (point, depth) = to_xy_and_depth(*p, wh);
@@ -380,7 +510,7 @@ impl Drawable for RawHandPair {
         }
 
         if let Some(second) = &self.second {
-            let mut col = second.gesture.get_draw_color();
+            let mut col = tint(second.gesture.get_draw_color());
 
             for (i, p) in second.points.iter().enumerate() {
                 let (point, depth) = to_xy_and_depth(*p, wh);
@@ -567,6 +697,16 @@ impl COMPair {
 
 impl Drawable for COMPair {
     fn draw(&self, draw: &Draw, frame: &Frame) {
+        let col =
+            if LIGHT_MODE { DEFAULT_COM_COLOR } else { DARK_DEFAULT_COM_COLOR };
+
+        self.draw_colored(draw, frame, col);
+    }
+}
+
+impl COMPair {
+    /// Draws each center of mass in `col`.
+    fn draw_colored(&self, draw: &Draw, frame: &Frame, col: Rgba) {
         const WEIGHT_MUL: f32 = 0.1;
 
         let wh = frame.rect().wh().as_f64();
@@ -575,8 +715,6 @@ impl Drawable for COMPair {
         let dims = Vec2::splat(width);
 
         let (first_txt, second_txt) = self.get_text();
-        let col =
-            if LIGHT_MODE { DEFAULT_COM_COLOR } else { DARK_DEFAULT_COM_COLOR };
 
         if let Some(first) = &self.first {
             let (point, _) = to_xy_and_depth(*first, wh);
@@ -0,0 +1,166 @@
+//! The handshake which hand trackers send to describe their output.
+//!
+//! Trackers should send `/maestro/tracker/hello` when they start (and may
+//! repeat it periodically) with the arguments:
+//!
+//! 1. the protocol version (an int), which must be
+//!    [`TRACKER_PROTOCOL_VERSION`],
+//! 2. the coordinate space (a string), which must be `"normalized"`,
+//! 3. the number of landmarks per hand (an int), which must be
+//!    [`NUM_HAND_VERTICES`],
+//! 4. the frame rate in Hz (a float or int).
+//!
+//! Trackers which never send a handshake are still accepted, but once a
+//! handshake has been received, hand data is only parsed while the tracker is
+//! compatible.
+
+use super::*;
+use nannou_osc::{Message, Type};
+
+/// The OSC address which trackers send their handshake on.
+pub const HANDSHAKE_OSC_ADDRESS: &str = "/maestro/tracker/hello";
+/// The OSC address which asks the tracker to restart, e.g. when its data has
+/// degraded. The only argument is a description of why (a string).
+pub const TRACKER_RESTART_OSC_ADDRESS: &str = "/maestro/tracker/restart";
+/// The version of the tracker → core protocol which is understood.
+pub const TRACKER_PROTOCOL_VERSION: i32 = 1;
+
+/// The space which a tracker's landmark coordinates are in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoordinateSpace {
+    /// Each axis is normalized to `[0, 1]` across the camera's view.
+    Normalized,
+    /// `x` and `y` are in pixels of the camera image.
+    Pixels,
+    /// Coordinates are in meters, relative to the camera or a hand.
+    World,
+}
+
+impl CoordinateSpace {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "normalized" | "normalised" => Some(Self::Normalized),
+            "pixels" | "image" => Some(Self::Pixels),
+            "world" | "meters" | "metres" => Some(Self::World),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Normalized => "normalized",
+            Self::Pixels => "pixels",
+            Self::World => "world",
+        }
+    }
+}
+
+/// A tracker's description of its output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackerHandshake {
+    pub version: i32,
+    pub coordinate_space: CoordinateSpace,
+    pub landmark_count: usize,
+    pub frame_rate: f64,
+}
+
+impl TrackerHandshake {
+    /// Parses a handshake from its OSC message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message's arguments don't form a handshake.
+    pub fn from_message(msg: &Message) -> Result<Self, String> {
+        let int = |idx: usize, name: &str| match msg.args.get(idx) {
+            Some(Type::Int(v)) => Ok(*v),
+            Some(Type::Long(v)) => i32::try_from(*v)
+                .map_err(|_| format!("handshake {name} {v} is out of range")),
+            _ => Err(format!("handshake argument {idx} ({name}) must be an int")),
+        };
+
+        let version = int(0, "protocol version")?;
+
+        let coordinate_space = match msg.args.get(1) {
+            Some(Type::String(name)) => CoordinateSpace::from_name(name)
+                .ok_or_else(|| format!("unknown coordinate space \"{name}\""))?,
+            _ => {
+                return Err(String::from(
+                    "handshake argument 1 (coordinate space) must be a string",
+                ));
+            }
+        };
+
+        let landmark_count = usize::try_from(int(2, "landmark count")?)
+            .map_err(|_| String::from("landmark count must not be negative"))?;
+
+        let frame_rate = match msg.args.get(3) {
+            Some(Type::Float(v)) => *v as f64,
+            Some(Type::Double(v)) => *v,
+            Some(Type::Int(v)) => *v as f64,
+            _ => {
+                return Err(String::from(
+                    "handshake argument 3 (frame rate) must be a number",
+                ));
+            }
+        };
+
+        Ok(Self { version, coordinate_space, landmark_count, frame_rate })
+    }
+
+    /// Checks whether hand data from the tracker can be parsed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first incompatibility, if any.
+    pub fn check_compatibility(&self) -> Result<(), String> {
+        if self.version != TRACKER_PROTOCOL_VERSION {
+            let hint = if self.version > TRACKER_PROTOCOL_VERSION {
+                "update maestro"
+            }
+            else {
+                "update the tracker"
+            };
+
+            return Err(format!(
+                "tracker uses protocol v{}, but v{TRACKER_PROTOCOL_VERSION} is required ({hint})",
+                self.version
+            ));
+        }
+
+        if self.coordinate_space != CoordinateSpace::Normalized {
+            return Err(format!(
+                "tracker sends {} coordinates, but normalized coordinates are required",
+                self.coordinate_space.name()
+            ));
+        }
+
+        if self.landmark_count != NUM_HAND_VERTICES {
+            return Err(format!(
+                "tracker sends {} landmarks per hand, but {NUM_HAND_VERTICES} are required",
+                self.landmark_count
+            ));
+        }
+
+        if !(self.frame_rate.is_finite() && self.frame_rate > 0.0) {
+            return Err(format!(
+                "tracker reported an invalid frame rate of {}",
+                self.frame_rate
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for TrackerHandshake {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "protocol v{}, {} coordinates, {} landmarks at {:.0} Hz",
+            self.version,
+            self.coordinate_space.name(),
+            self.landmark_count,
+            self.frame_rate
+        )
+    }
+}
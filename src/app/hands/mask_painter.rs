@@ -0,0 +1,154 @@
+//! Painting of the spectral filter's mask with hand movements.
+//!
+//! The mask is split into logarithmically-spaced bands. Each hand paints the
+//! band under its x position, where an open hand passes the band through and
+//! a closed hand carves it out. Bands which aren't being painted decay back
+//! to passing through.
+
+use super::*;
+use crate::dsp::SpectralMask;
+
+/// The default number of bands which hands paint.
+pub const DEFAULT_MASK_BANDS: usize = 16;
+/// The default time (in seconds) which painted bands take to reach a hand's
+/// gain.
+pub const DEFAULT_MASK_SMOOTHING_TIME: f64 = 0.05;
+/// The default time (in seconds) which painted bands take to decay back to
+/// passing through.
+pub const DEFAULT_MASK_DECAY_TIME: f64 = 2.0;
+
+/// The range of hand openness which is mapped to gain.
+const MIN_OPENNESS: f64 = 0.72;
+const MAX_OPENNESS: f64 = 2.0;
+
+/// How painting is mapped to the mask.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaskPaintConfig {
+    pub num_bands: usize,
+    pub smoothing_time: f64,
+    pub decay_time: f64,
+}
+
+impl Default for MaskPaintConfig {
+    fn default() -> Self {
+        Self {
+            num_bands: DEFAULT_MASK_BANDS,
+            smoothing_time: DEFAULT_MASK_SMOOTHING_TIME,
+            decay_time: DEFAULT_MASK_DECAY_TIME,
+        }
+    }
+}
+
+/// Paints a [`SpectralMask`] from hand positions and openness.
+#[derive(Clone, Debug)]
+pub struct SpectralMaskPainter {
+    config: MaskPaintConfig,
+    /// The current gain of each band.
+    bands: Vec<f64>,
+    /// The first bin of each band, followed by the number of bins.
+    band_edges: Vec<usize>,
+    mask: SpectralMask,
+}
+
+impl SpectralMaskPainter {
+    /// Returns a painter for a mask with `num_bins` bins, which must be a
+    /// power of two.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_bins` is not a power of two, or is greater than
+    /// [`MAX_SPECTRAL_BLOCK_SIZE`].
+    pub fn new(num_bins: usize, config: MaskPaintConfig) -> Self {
+        let num_bands = config.num_bands.clamp(1, num_bins);
+        let mut mask = SpectralMask::new(num_bins).with_size(num_bins);
+        mask.fill(1.0);
+
+        Self {
+            config: MaskPaintConfig { num_bands, ..config },
+            bands: vec![1.0; num_bands],
+            band_edges: log_band_edges(num_bands, num_bins),
+            mask,
+        }
+    }
+
+    /// Paints the mask with `hands` over `dt` seconds.
+    pub fn update(&mut self, hands: &RawHandPairCOM, dt: f64) {
+        let (first, second) = hands.get_openness();
+        let painted = [(hands.com.first, first), (hands.com.second, second)];
+
+        let mut is_painted = [false; 2];
+        let mut targets = [(0, 0.0); 2];
+
+        for (i, (com, openness)) in painted.into_iter().enumerate() {
+            if let (Some(com), Some(openness)) = (com, openness) {
+                let band = (com.x.clamp(0.0, 1.0) * self.bands.len() as f64)
+                    as usize;
+
+                targets[i] = (
+                    band.min(self.bands.len() - 1),
+                    normalize(openness, MIN_OPENNESS, MAX_OPENNESS)
+                        .clamp(0.0, 1.0),
+                );
+                is_painted[i] = true;
+            }
+        }
+
+        let smoothing = one_pole_coeff(self.config.smoothing_time, dt);
+        let decay = one_pole_coeff(self.config.decay_time, dt);
+
+        for (band, gain) in self.bands.iter_mut().enumerate() {
+            let target = (0..2)
+                .filter(|&i| is_painted[i] && targets[i].0 == band)
+                .map(|i| targets[i].1)
+                .reduce(f64::min);
+
+            *gain = match target {
+                Some(target) => lerp(*gain, target, smoothing),
+                None => lerp(*gain, 1.0, decay),
+            };
+        }
+
+        for (band, gain) in self.bands.iter().enumerate() {
+            let (start, end) =
+                (self.band_edges[band], self.band_edges[band + 1]);
+            self.mask[start..end].fill(*gain);
+        }
+    }
+
+    pub const fn mask(&self) -> &SpectralMask {
+        &self.mask
+    }
+}
+
+/// How far a one-pole filter with a time constant of `time` seconds moves
+/// towards its target over `dt` seconds.
+fn one_pole_coeff(time: f64, dt: f64) -> f64 {
+    if time <= 0.0 {
+        return 1.0;
+    }
+
+    1.0 - (-dt / time).exp()
+}
+
+/// Splits `num_bins` into `num_bands` logarithmically-spaced bands, returning
+/// the first bin of each band followed by `num_bins`. Each band holds at
+/// least one bin.
+fn log_band_edges(num_bands: usize, num_bins: usize) -> Vec<usize> {
+    let mut edges = Vec::with_capacity(num_bands + 1);
+    edges.push(0);
+
+    let max = (num_bins as f64).ln();
+
+    for band in 1..num_bands {
+        let edge =
+            (max * band as f64 / num_bands as f64).exp().round() as usize;
+        let prev = edges[band - 1];
+
+        // low bands are narrower than a bin, so they are spread out, leaving
+        // enough bins for the remaining bands
+        edges.push(edge.max(prev + 1).min(num_bins - (num_bands - band)));
+    }
+
+    edges.push(num_bins);
+    edges
+}
@@ -1,14 +1,22 @@
+use address_map::OSCAddressMap;
 use args::Arguments;
 use hand_parser::HandParser;
 use hand_types::{
     COMPair, RawHand, RawHandPair, RawHandPairCOM, ValidRawHandPair,
 };
-use osc::OSCReceiver;
+use osc::{heartbeat::ConnectionHealth, OSCReceiver};
+use quality::HandQualityMonitor;
 
 use super::*;
 
-mod hand_parser;
+pub mod address_map;
+pub mod gestures;
+pub mod hand_parser;
 pub mod hand_types;
+pub mod handshake;
+pub mod mask_painter;
+pub mod packed;
+pub mod quality;
 
 pub const NUM_HAND_VERTICES: usize = 21;
 pub const HAND_DETECTION_TIMEOUT: f64 = 1.0;
@@ -96,13 +104,16 @@ pub struct HandManager {
     latest_target: ValidRawHandPair,
     invalid_timeout: InvalidHandTimeout,
 
+    /// Measures the quality of the received hand data.
+    quality: HandQualityMonitor,
+
     can_update: bool,
 }
 
 impl HandManager {
-    pub fn new(osc_receiver: OSCReceiver) -> Self {
+    pub fn new(osc_receiver: OSCReceiver, address_map: OSCAddressMap) -> Self {
         Self {
-            parser: HandParser::new(),
+            parser: HandParser::new(address_map),
             osc_receiver,
 
             damped_hands: RawHandPairCOM::default(),
@@ -116,6 +127,8 @@ impl HandManager {
                 second_to: false,
             },
 
+            quality: HandQualityMonitor::default(),
+
             can_update: false,
         }
     }
@@ -124,6 +137,55 @@ impl HandManager {
         &self.damped_hands
     }
 
+    /// Returns all control messages received since this was last called.
+    pub fn take_control_messages(&mut self) -> Vec<nannou_osc::Message> {
+        self.osc_receiver.take_control_messages()
+    }
+
+    /// Checks a hand tracker's handshake message, reporting whether its hand
+    /// data can be used.
+    pub fn handle_handshake(&mut self, msg: &nannou_osc::Message) {
+        match self.parser.handle_handshake(msg) {
+            Ok(handshake) => {
+                println!("hand tracker connected: {handshake}");
+
+                // a (re)started tracker shouldn't inherit old alerts
+                self.quality.reset();
+            }
+            Err(e) => eprintln!("incompatible hand tracker: {e}"),
+        }
+    }
+
+    /// Why the hand tracker's data is being rejected, if it is.
+    pub fn tracker_incompatibility(&self) -> Option<&str> {
+        self.parser.incompatibility()
+    }
+
+    /// The quality metrics and alerts of the received hand data.
+    pub const fn quality(&self) -> &HandQualityMonitor {
+        &self.quality
+    }
+
+    /// Returns whether the hand data's quality degraded since this was last
+    /// called.
+    pub fn take_quality_degraded(&mut self) -> bool {
+        self.quality.take_newly_degraded()
+    }
+
+    /// The health of the connection to the hand tracker.
+    pub fn connection_health(&self) -> ConnectionHealth {
+        self.osc_receiver.health()
+    }
+
+    /// How long ago the last packet was received from the hand tracker, in
+    /// seconds.
+    pub fn last_packet_age(&self) -> Option<f64> {
+        self.osc_receiver
+            .monitor()
+            .packet_age()
+            .map(|age| age.as_secs_f64())
+    }
+
     pub fn start_update(&mut self) {
         self.can_update = true;
     }
@@ -260,6 +322,9 @@ impl HandManager {
 impl Updatable for HandManager {
     fn update(&mut self, update: &Update) {
         if !self.can_update {
+            // packets are still drained so that the connection's health is
+            // tracked while not updating
+            _ = self.osc_receiver.try_recv();
             return;
         }
 
@@ -277,15 +342,22 @@ impl Updatable for HandManager {
 
         let packet = unsafe { latest_packet.unwrap_unchecked() };
 
+        let was_compatible = self.parser.incompatibility().is_none();
         let hands = self.parser.parse_hands(packet);
 
         if let Err(e) = &hands {
-            println!("FAILED to parse hands: {e}");
+            // incompatibilities are only reported when they start, rather
+            // than for every packet
+            if was_compatible {
+                println!("FAILED to parse hands: {e}");
+            }
+
             return;
         }
 
         let hands = unsafe { hands.unwrap_unchecked() };
 
+        self.quality.record_frame(&hands);
         self.update_from(&hands, update.since_last.as_secs_f64());
         self.damped_hands.update(update);
     }
@@ -0,0 +1,242 @@
+//! A compact binary encoding of hand frames, for trackers which run at high
+//! frame rates.
+//!
+//! A packed frame is sent as a single OSC blob argument (on any address),
+//! which is detected by its magic bytes. All values are little-endian:
+//!
+//! | Bytes | Contents                                                     |
+//! |-------|--------------------------------------------------------------|
+//! | 2     | the magic bytes `"MH"`                                       |
+//! | 1     | the format version, which must be [`PACKED_FORMAT_VERSION`]  |
+//! | 1     | flags: bit 0 is set if the first hand is present, and bit 1 |
+//! |       | if the second hand is present                                |
+//!
+//! followed by, for each hand which is present:
+//!
+//! | Bytes | Contents                                                     |
+//! |-------|--------------------------------------------------------------|
+//! | 1     | bits 0-3 hold the gesture, and bit 4 is set if the hand's   |
+//! |       | landmarks were predicted rather than detected                |
+//! | 126   | the `x`, `y` and `z` of each of the 21 landmarks, as `i16`  |
+//! |       | fixed-point values with 14 fractional bits                   |
+//!
+//! Gestures are numbered `0` (unknown), `1` (open), `2` (closed), `3` (thumb
+//! up), `4` (thumb down) and `5` (victory). Each hand takes 127 bytes, rather
+//! than over 250 bytes as a list of floats.
+
+use super::hand_types::{HandGesture, RawHand, RawHandPair};
+use super::*;
+
+/// The bytes which every packed frame starts with.
+pub const PACKED_MAGIC: [u8; 2] = *b"MH";
+/// The version of the packed format which is understood.
+pub const PACKED_FORMAT_VERSION: u8 = 1;
+
+const HEADER_SIZE: usize = 4;
+const PACKED_HAND_SIZE: usize = 1 + NUM_HAND_VERTICES * 3 * 2;
+const FIXED_POINT_SCALE: f64 = (1 << 14) as f64;
+
+const FIRST_HAND_FLAG: u8 = 1;
+const SECOND_HAND_FLAG: u8 = 1 << 1;
+const GESTURE_MASK: u8 = 0x0F;
+const PREDICTED_FLAG: u8 = 1 << 4;
+
+/// Whether `data` holds a packed frame, rather than some other blob.
+pub fn is_packed_frame(data: &[u8]) -> bool {
+    data.starts_with(&PACKED_MAGIC)
+}
+
+/// Decodes a packed frame. Hands whose landmarks were predicted are treated
+/// as missing.
+///
+/// # Errors
+///
+/// Returns an error if `data` is not a valid packed frame.
+pub fn decode_packed_frame(data: &[u8]) -> Result<RawHandPair, String> {
+    if data.len() < HEADER_SIZE || !is_packed_frame(data) {
+        return Err(String::from("not a packed hand frame"));
+    }
+
+    let version = data[2];
+    if version != PACKED_FORMAT_VERSION {
+        return Err(format!(
+            "packed hand frames use v{version}, but v{PACKED_FORMAT_VERSION} is required"
+        ));
+    }
+
+    let flags = data[3];
+    let present =
+        [flags & FIRST_HAND_FLAG != 0, flags & SECOND_HAND_FLAG != 0];
+    let num_hands = present.iter().filter(|&&p| p).count();
+
+    let expected_len = HEADER_SIZE + num_hands * PACKED_HAND_SIZE;
+    if data.len() != expected_len {
+        return Err(format!(
+            "packed hand frame is {} bytes, but {expected_len} were expected",
+            data.len()
+        ));
+    }
+
+    let mut hands = data[HEADER_SIZE..].chunks_exact(PACKED_HAND_SIZE);
+    let mut decoded = [None, None];
+
+    for (hand, is_present) in decoded.iter_mut().zip(present) {
+        if is_present {
+            // the length was checked above
+            *hand = decode_hand(unsafe { hands.next().unwrap_unchecked() });
+        }
+    }
+
+    let [first, second] = decoded;
+    Ok(RawHandPair { first, second })
+}
+
+fn decode_hand(data: &[u8]) -> Option<RawHand> {
+    let state = data[0];
+
+    if state & PREDICTED_FLAG != 0 {
+        return None;
+    }
+
+    let mut hand = RawHand {
+        points: [DVec3::ZERO; NUM_HAND_VERTICES],
+        gesture: HandGesture::from_index(state & GESTURE_MASK),
+    };
+
+    let fixed = |bytes: &[u8]| {
+        i16::from_le_bytes([bytes[0], bytes[1]]) as f64
+            / FIXED_POINT_SCALE
+    };
+
+    for (point, xyz) in hand.points.iter_mut().zip(data[1..].chunks_exact(6))
+    {
+        point.x = fixed(&xyz[0..2]);
+        point.y = fixed(&xyz[2..4]);
+        point.z = fixed(&xyz[4..6]);
+    }
+
+    Some(hand)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A packed hand whose landmarks are all `xyz`, in fixed-point.
+    fn packed_hand(state: u8, xyz: [i16; 3]) -> Vec<u8> {
+        let mut bytes = vec![state];
+
+        for _ in 0..NUM_HAND_VERTICES {
+            for v in xyz {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    fn packed_frame(flags: u8, hands: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = vec![b'M', b'H', PACKED_FORMAT_VERSION, flags];
+        bytes.extend(hands.iter().flatten());
+        bytes
+    }
+
+    #[test]
+    fn landmarks_use_14_fractional_bits() {
+        let frame = packed_frame(
+            FIRST_HAND_FLAG,
+            &[packed_hand(0, [1 << 14, -(1 << 13), 1])],
+        );
+        let hands = decode_packed_frame(&frame).unwrap();
+        let first = hands.first.unwrap();
+
+        assert!(hands.second.is_none());
+        assert!(first
+            .points
+            .iter()
+            .all(|&p| p == dvec3(1.0, -0.5, 1.0 / 16384.0)));
+    }
+
+    #[test]
+    fn flags_select_the_hands_which_are_present() {
+        let (open, victory) = (1, 5);
+
+        let second_only = packed_frame(
+            SECOND_HAND_FLAG,
+            &[packed_hand(victory, [0, 0, 0])],
+        );
+        let hands = decode_packed_frame(&second_only).unwrap();
+        assert!(hands.first.is_none());
+        assert_eq!(hands.second.unwrap().gesture, HandGesture::Victory);
+
+        let both = packed_frame(
+            FIRST_HAND_FLAG | SECOND_HAND_FLAG,
+            &[packed_hand(open, [0, 0, 0]), packed_hand(0, [0, 0, 0])],
+        );
+        let hands = decode_packed_frame(&both).unwrap();
+        assert_eq!(hands.first.unwrap().gesture, HandGesture::Open);
+        assert_eq!(hands.second.unwrap().gesture, HandGesture::Unknown);
+
+        let none = decode_packed_frame(&packed_frame(0, &[])).unwrap();
+        assert!(none.first.is_none() && none.second.is_none());
+    }
+
+    #[test]
+    fn gestures_ignore_the_upper_bits() {
+        // bits 5-7 are unused, and an unknown gesture index is unknown
+        let frame = packed_frame(
+            FIRST_HAND_FLAG | SECOND_HAND_FLAG,
+            &[packed_hand(0b1110_0011, [0; 3]), packed_hand(0x0F, [0; 3])],
+        );
+        let hands = decode_packed_frame(&frame).unwrap();
+
+        assert_eq!(hands.first.unwrap().gesture, HandGesture::ThumbUp);
+        assert_eq!(hands.second.unwrap().gesture, HandGesture::Unknown);
+    }
+
+    #[test]
+    fn predicted_hands_are_missing() {
+        let frame = packed_frame(
+            FIRST_HAND_FLAG | SECOND_HAND_FLAG,
+            &[
+                packed_hand(PREDICTED_FLAG | 2, [100; 3]),
+                packed_hand(2, [100; 3]),
+            ],
+        );
+        let hands = decode_packed_frame(&frame).unwrap();
+
+        assert!(hands.first.is_none());
+        assert_eq!(hands.second.unwrap().gesture, HandGesture::Closed);
+    }
+
+    #[test]
+    fn wrong_lengths_are_rejected() {
+        let hand = packed_hand(0, [0; 3]);
+
+        // too short, too long, and a hand without its flag
+        let mut short = packed_frame(FIRST_HAND_FLAG, &[hand.clone()]);
+        short.pop();
+        let mut long = packed_frame(FIRST_HAND_FLAG, &[hand.clone()]);
+        long.push(0);
+        let unflagged = packed_frame(0, &[hand]);
+
+        for frame in [short, long, unflagged] {
+            let err = decode_packed_frame(&frame).unwrap_err();
+            assert!(err.contains("expected"), "{err}");
+        }
+
+        assert!(decode_packed_frame(b"MH").is_err());
+        assert!(decode_packed_frame(&[]).is_err());
+    }
+
+    #[test]
+    fn other_versions_and_blobs_are_rejected() {
+        let mut frame = packed_frame(0, &[]);
+        frame[2] = PACKED_FORMAT_VERSION + 1;
+        let err = decode_packed_frame(&frame).unwrap_err();
+        assert!(err.contains(&format!("v{}", PACKED_FORMAT_VERSION + 1)));
+
+        assert!(!is_packed_frame(b"XH\x01\x00"));
+        assert!(decode_packed_frame(b"XH\x01\x00").is_err());
+    }
+}
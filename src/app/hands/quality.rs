@@ -0,0 +1,276 @@
+//! Running quality metrics of the received hand data.
+//!
+//! Tracking quality can degrade mid-performance (e.g. if the lighting
+//! changes), which shows up as a lower frame rate, shakier landmarks, or
+//! hands which keep dropping out. These are measured continuously, and an
+//! alert is raised once any of them stays past its threshold.
+
+use super::*;
+use hand_types::RawHandPair;
+use std::time::Instant;
+
+/// The lowest acceptable hand frame rate, in Hz.
+pub const DEFAULT_MIN_FRAME_RATE: f64 = 20.0;
+/// The highest acceptable landmark jitter, as an RMS distance in normalized
+/// coordinates.
+pub const DEFAULT_MAX_JITTER_RMS: f64 = 0.008;
+/// The highest acceptable number of hand dropouts per minute.
+pub const DEFAULT_MAX_DROPOUTS_PER_MIN: f64 = 12.0;
+
+/// The shortest time (in seconds) between requests for the tracker to
+/// restart.
+pub const TRACKER_RESTART_COOLDOWN: f64 = 60.0;
+
+/// How long (in seconds) a metric must stay past its threshold before an
+/// alert is raised, and back within it before the alert is cleared.
+const ALERT_HOLD_TIME: f64 = 3.0;
+/// How quickly the frame rate and jitter follow each new frame.
+const METRIC_SMOOTHING: f64 = 0.05;
+/// The time constant (in seconds) of the dropout rate.
+const DROPOUT_WINDOW: f64 = 30.0;
+/// The frame interval (in seconds) beyond which frames are considered
+/// missing, rather than slow; these are reported by the connection's health.
+const MAX_FRAME_INTERVAL: f64 = 1.0;
+
+/// The levels at which hand data is considered degraded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QualityThresholds {
+    pub min_frame_rate: f64,
+    pub max_jitter_rms: f64,
+    pub max_dropouts_per_min: f64,
+}
+
+impl Default for QualityThresholds {
+    fn default() -> Self {
+        Self {
+            min_frame_rate: DEFAULT_MIN_FRAME_RATE,
+            max_jitter_rms: DEFAULT_MAX_JITTER_RMS,
+            max_dropouts_per_min: DEFAULT_MAX_DROPOUTS_PER_MIN,
+        }
+    }
+}
+
+/// A way in which the hand data has degraded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QualityAlert {
+    /// Frames are arriving slower than the minimum frame rate.
+    LowFrameRate(f64),
+    /// Landmarks are shaking more than the maximum jitter.
+    HighJitter(f64),
+    /// Hands are being lost more often than the maximum dropout rate.
+    FrequentDropouts(f64),
+}
+
+impl std::fmt::Display for QualityAlert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LowFrameRate(fps) => {
+                write!(f, "hand frame rate is low ({fps:.0} fps)")
+            }
+            Self::HighJitter(rms) => {
+                write!(f, "hand landmarks are jittery (RMS {rms:.4})")
+            }
+            Self::FrequentDropouts(rate) => {
+                write!(f, "hands are dropping out ({rate:.1}/min)")
+            }
+        }
+    }
+}
+
+/// The current quality metrics.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QualityMetrics {
+    /// The rate at which hand frames are received, in Hz.
+    pub frame_rate: f64,
+    /// The RMS frame-to-frame jitter of the landmarks, with steady motion
+    /// removed.
+    pub jitter_rms: f64,
+    /// How often a tracked hand is lost, per minute.
+    pub dropouts_per_min: f64,
+}
+
+impl std::fmt::Display for QualityMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.0} fps, jitter {:.4}, {:.1} dropouts/min",
+            self.frame_rate, self.jitter_rms, self.dropouts_per_min
+        )
+    }
+}
+
+/// Whether one metric is past its threshold, with hysteresis over time.
+#[derive(Clone, Copy, Debug, Default)]
+struct AlertState {
+    /// When the metric last crossed its threshold.
+    crossed_at: Option<Instant>,
+    is_active: bool,
+}
+
+impl AlertState {
+    /// Updates the state, returning `true` if the alert was just raised.
+    fn update(&mut self, is_degraded: bool, now: Instant) -> bool {
+        if is_degraded == self.is_active {
+            self.crossed_at = None;
+            return false;
+        }
+
+        let crossed_at = *self.crossed_at.get_or_insert(now);
+
+        if (now - crossed_at).as_secs_f64() < ALERT_HOLD_TIME {
+            return false;
+        }
+
+        self.is_active = is_degraded;
+        self.crossed_at = None;
+
+        is_degraded
+    }
+}
+
+/// Measures the quality of received hand data.
+#[derive(Debug)]
+pub struct HandQualityMonitor {
+    thresholds: QualityThresholds,
+    metrics: QualityMetrics,
+
+    last_frame: Option<Instant>,
+    /// The landmarks of each hand in the previous two frames, newest first.
+    history: [[Option<RawHand>; 2]; 2],
+    /// The decaying number of dropouts.
+    dropout_count: f64,
+
+    alerts: [AlertState; 3],
+    /// Whether an alert was raised since this was last checked.
+    newly_degraded: bool,
+}
+
+impl HandQualityMonitor {
+    pub fn new(thresholds: QualityThresholds) -> Self {
+        Self {
+            thresholds,
+            metrics: QualityMetrics::default(),
+
+            last_frame: None,
+            history: [[None; 2]; 2],
+            dropout_count: 0.0,
+
+            alerts: [AlertState::default(); 3],
+            newly_degraded: false,
+        }
+    }
+
+    /// Records a received hand frame.
+    pub fn record_frame(&mut self, hands: &RawHandPair) {
+        let now = Instant::now();
+
+        if let Some(last) = self.last_frame {
+            let interval = (now - last).as_secs_f64();
+
+            if interval < MAX_FRAME_INTERVAL {
+                self.dropout_count *= (-interval / DROPOUT_WINDOW).exp();
+
+                let fps = interval.max(f64::EPSILON).recip();
+
+                // the first measurement is taken as-is, so that the rate
+                // doesn't start out looking low
+                if self.metrics.frame_rate <= 0.0 {
+                    self.metrics.frame_rate = fps;
+                }
+                else {
+                    self.metrics.frame_rate +=
+                        (fps - self.metrics.frame_rate) * METRIC_SMOOTHING;
+                }
+            }
+        }
+
+        self.last_frame = Some(now);
+
+        let mut jitter_sq = 0.0;
+        let mut num_points = 0u32;
+
+        let hands = [hands.first, hands.second];
+
+        for (history, hand) in self.history.iter_mut().zip(hands) {
+            if history[0].is_some() && hand.is_none() {
+                self.dropout_count += 1.0;
+            }
+
+            // the second difference of each landmark cancels out steady
+            // motion, leaving frame-to-frame shake
+            if let (Some(curr), [Some(prev), Some(prev2)]) = (hand, *history) {
+                for ((c, p), p2) in
+                    curr.points.iter().zip(&prev.points).zip(&prev2.points)
+                {
+                    jitter_sq += (*c - *p * 2.0 + *p2).length_squared();
+                    num_points += 1;
+                }
+            }
+
+            *history = [hand, history[0]];
+        }
+
+        if num_points > 0 {
+            let rms = (jitter_sq / num_points as f64).sqrt();
+            self.metrics.jitter_rms +=
+                (rms - self.metrics.jitter_rms) * METRIC_SMOOTHING;
+        }
+
+        self.metrics.dropouts_per_min =
+            self.dropout_count / DROPOUT_WINDOW * 60.0;
+
+        self.update_alerts(now);
+    }
+
+    /// Clears all metrics and alerts, e.g. when the tracker reconnects.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.thresholds);
+    }
+
+    pub const fn metrics(&self) -> &QualityMetrics {
+        &self.metrics
+    }
+
+    /// The ways in which the hand data is currently degraded.
+    pub fn alerts(&self) -> Vec<QualityAlert> {
+        let m = &self.metrics;
+
+        [
+            QualityAlert::LowFrameRate(m.frame_rate),
+            QualityAlert::HighJitter(m.jitter_rms),
+            QualityAlert::FrequentDropouts(m.dropouts_per_min),
+        ]
+        .into_iter()
+        .zip(&self.alerts)
+        .filter_map(|(alert, state)| state.is_active.then_some(alert))
+        .collect()
+    }
+
+    /// Returns whether an alert was raised since this was last called.
+    pub fn take_newly_degraded(&mut self) -> bool {
+        std::mem::take(&mut self.newly_degraded)
+    }
+
+    fn update_alerts(&mut self, now: Instant) {
+        let m = &self.metrics;
+        let t = &self.thresholds;
+
+        let degraded = [
+            m.frame_rate < t.min_frame_rate,
+            m.jitter_rms > t.max_jitter_rms,
+            m.dropouts_per_min > t.max_dropouts_per_min,
+        ];
+
+        for (state, is_degraded) in self.alerts.iter_mut().zip(degraded) {
+            if state.update(is_degraded, now) {
+                self.newly_degraded = true;
+            }
+        }
+    }
+}
+
+impl Default for HandQualityMonitor {
+    fn default() -> Self {
+        Self::new(QualityThresholds::default())
+    }
+}
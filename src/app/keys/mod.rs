@@ -3,6 +3,13 @@ use model::MIDISendMode;
 use super::*;
 
 pub fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model.note_interaction();
+
+    if model.is_editing_mapping() && mapping_editor_key_pressed(app, model, key)
+    {
+        return;
+    }
+
     match key {
         Key::Minus | Key::Underline => {
             if app.keys.mods.shift() && model.midi_send_value >= 10 {
@@ -31,14 +38,84 @@ pub fn key_pressed(app: &App, model: &mut Model, key: Key) {
                 u8::min(model.midi_send_channel + inc, (1 << 4) - 1);
         }
         Key::N => model.midi_send_mode = MIDISendMode::MIDINote,
-        Key::C => model.midi_send_mode = MIDISendMode::MIDIControlChange,
+        Key::C => {
+            if app.keys.mods.shift() {
+                model.toggle_calibration();
+            }
+            else {
+                model.midi_send_mode = MIDISendMode::MIDIControlChange;
+            }
+        }
         Key::Return => model.send_midi(),
 
-        Key::T => model.send_and_update(true),
+        Key::T => {
+            if app.keys.mods.shift() {
+                model.continue_update();
+            }
+            else {
+                model.send_and_update(true);
+            }
+        }
         Key::S => model.send_and_update(false),
 
+        Key::B => model.tap_tempo(),
+
+        Key::P => model.next_profile(),
+
+        Key::R => model.reset_true_peak(),
+        Key::X => model.clear_feedback_notches(),
+
+        Key::Key0 => model.select_room(None),
+        Key::Key1 => model.select_room(Some(0)),
+        Key::Key2 => model.select_room(Some(1)),
+        Key::Key3 => model.select_room(Some(2)),
+        Key::Key4 => model.select_room(Some(3)),
+        Key::Key5 => model.select_room(Some(4)),
+        Key::Key6 => model.select_room(Some(5)),
+        Key::Key7 => model.select_room(Some(6)),
+        Key::Key8 => model.select_room(Some(7)),
+        Key::Key9 => model.select_room(Some(8)),
+
+        Key::F5 => model.save_state(),
+        Key::F6 => model.export_config(),
+        Key::F9 => model.load_state(),
+
+        Key::D => model.dump_parameter_bank(),
+
+        Key::O => {
+            if app.keys.mods.shift() {
+                model.clear_poses();
+            }
+            else {
+                model.store_pose();
+            }
+        }
+
+        Key::LBracket | Key::RBracket => {
+            let step = if app.keys.mods.shift() { 6.0 } else { 1.0 };
+            let sign = if key == Key::LBracket { -1.0 } else { 1.0 };
+
+            model.adjust_input_gain(step * sign);
+        }
+
+        Key::G => model.toggle_input_gate_learning(),
+
+        Key::Up => model.adjust_pulse_intensity(0.1),
+        Key::Down => model.adjust_pulse_intensity(-0.1),
+
         Key::H => model.show_state_data = !model.show_state_data,
 
+        Key::E => model.toggle_mapping_editor(),
+
+        Key::M => {
+            if app.keys.mods.shift() {
+                model.count_in(1);
+            }
+            else {
+                model.toggle_metronome();
+            }
+        }
+
         _ => {}
     }
 }
@@ -46,3 +123,48 @@ pub fn key_pressed(app: &App, model: &mut Model, key: Key) {
 pub fn key_released(_app: &App, model: &mut Model, key: Key) {
     //
 }
+
+/// Handles the keys which control the mapping editor, returning whether
+/// `key` was handled.
+fn mapping_editor_key_pressed(app: &App, model: &mut Model, key: Key) -> bool {
+    let steps = if app.keys.mods.shift() { 10 } else { 1 };
+
+    match key {
+        Key::Up => model.select_mapping_route(-1),
+        Key::Down => model.select_mapping_route(1),
+        Key::Tab => {
+            let offset = if app.keys.mods.shift() { -1 } else { 1 };
+            model.select_mapping_field(offset);
+        }
+        Key::Left => model.adjust_mapping(-steps),
+        Key::Right => model.adjust_mapping(steps),
+        Key::Return => model.save_mapping(),
+        _ => return false,
+    }
+
+    true
+}
+
+/// Selects the mapping editor's route under the mouse.
+pub fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton) {
+    if button == MouseButton::Left && model.is_editing_mapping() {
+        model.select_mapping_route_at(app.mouse.y, app.window_rect().top());
+    }
+}
+
+/// Adjusts the mapping editor's selected field with the mouse wheel.
+pub fn mouse_wheel(
+    _app: &App,
+    model: &mut Model,
+    delta: MouseScrollDelta,
+    _phase: TouchPhase,
+) {
+    let y = match delta {
+        MouseScrollDelta::LineDelta(_, y) => y,
+        MouseScrollDelta::PixelDelta(pos) => pos.y as f32,
+    };
+
+    if y != 0.0 {
+        model.adjust_mapping(y.signum() as i32);
+    }
+}
@@ -0,0 +1,313 @@
+//! Reading of the notes in Standard MIDI Files, so that a performance can
+//! be rendered offline.
+//!
+//! Only the notes and tempo changes are read. The notes of every track and
+//! channel are merged, and timed in seconds with the file's tempo map.
+
+use super::*;
+
+/// The tempo of a file until it sets one, in microseconds per beat
+/// (120 BPM).
+const DEFAULT_TEMPO: u32 = 500_000;
+
+/// A note which starts or stops at a time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedNote {
+    /// The time from the start of the file, in seconds.
+    pub secs: f64,
+    pub note: u8,
+    /// Whether the note starts, rather than stops.
+    pub is_on: bool,
+}
+
+/// What a track event does, at a time in ticks.
+enum TrackEvent {
+    Note { note: u8, is_on: bool },
+    /// A new tempo, in microseconds per beat.
+    Tempo(u32),
+}
+
+/// Reads the notes of the MIDI file at `path`, in order of time.
+///
+/// # Errors
+///
+/// Returns an error if the file could not be read, or isn't a valid MIDI
+/// file.
+pub fn read_midi_file(path: &str) -> Result<Vec<TimedNote>, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("failed to read MIDI file \"{path}\": {e}"))?;
+
+    parse_midi_file(&bytes)
+}
+
+/// Parses the notes of a MIDI file, in order of time.
+///
+/// # Errors
+///
+/// Returns an error if `bytes` isn't a valid MIDI file.
+pub fn parse_midi_file(bytes: &[u8]) -> Result<Vec<TimedNote>, String> {
+    let mut chunks = Chunks { bytes };
+
+    let Some((b"MThd", header)) = chunks.next()
+    else {
+        return Err(String::from("not a MIDI file"));
+    };
+
+    let &[_, _, _, _, div_hi, div_lo, ..] = header
+    else {
+        return Err(String::from("the MIDI header is too short"));
+    };
+
+    // the duration of a tick, in seconds per microsecond of tempo, or in
+    // seconds if the division is in SMPTE frames
+    let division = u16::from_be_bytes([div_hi, div_lo]);
+    let (tick_scale, uses_tempo) = if division & 0x8000 == 0 {
+        (1e-6 / division.max(1) as f64, true)
+    }
+    else {
+        // the upper byte is the negated frame rate, of which only four are
+        // valid (29 is 29.97 drop-frame)
+        let fps = match (div_hi as i8).checked_neg() {
+            Some(29) => 30_000.0 / 1001.0,
+            Some(fps @ (24 | 25 | 30)) => fps as f64,
+            _ => return Err(format!("invalid SMPTE frame rate {div_hi:#04x}")),
+        };
+        let ticks_per_frame = div_lo.max(1) as f64;
+
+        ((fps * ticks_per_frame).recip(), false)
+    };
+
+    let mut events = Vec::new();
+
+    for (kind, data) in chunks {
+        // unknown chunks are skipped, as the format allows
+        if kind == b"MTrk" {
+            read_track(data, &mut events)?;
+        }
+    }
+
+    // the sort is stable, so events at the same tick keep their order
+    events.sort_by_key(|&(tick, _)| tick);
+
+    let mut notes = Vec::with_capacity(events.len());
+    let mut tempo = DEFAULT_TEMPO;
+    let mut last_tick = 0;
+    let mut secs = 0.0;
+
+    for (tick, event) in events {
+        let tick_secs = if uses_tempo {
+            tick_scale * tempo as f64
+        }
+        else {
+            tick_scale
+        };
+
+        secs += (tick - last_tick) as f64 * tick_secs;
+        last_tick = tick;
+
+        match event {
+            TrackEvent::Note { note, is_on } => {
+                notes.push(TimedNote { secs, note, is_on });
+            }
+            TrackEvent::Tempo(t) => tempo = t,
+        }
+    }
+
+    Ok(notes)
+}
+
+/// Appends the notes and tempo changes of a track to `events`, at their
+/// time in ticks.
+fn read_track(
+    data: &[u8],
+    events: &mut Vec<(u64, TrackEvent)>,
+) -> Result<(), String> {
+    let truncated = || String::from("a MIDI track is truncated");
+
+    let mut pos = 0;
+    let mut tick = 0;
+    let mut running_status = None;
+
+    while pos < data.len() {
+        tick += read_var_len(data, &mut pos).ok_or_else(truncated)? as u64;
+
+        let &byte = data.get(pos).ok_or_else(truncated)?;
+
+        match byte {
+            // meta events
+            0xFF => {
+                let &kind = data.get(pos + 1).ok_or_else(truncated)?;
+                pos += 2;
+
+                let len = read_var_len(data, &mut pos).ok_or_else(truncated)?;
+                let meta = data
+                    .get(pos..pos + len as usize)
+                    .ok_or_else(truncated)?;
+                pos += len as usize;
+
+                match (kind, meta) {
+                    (0x2F, _) => break,
+                    (0x51, &[a, b, c]) => {
+                        let tempo = u32::from_be_bytes([0, a, b, c]);
+                        events.push((tick, TrackEvent::Tempo(tempo)));
+                    }
+                    _ => {}
+                }
+            }
+            // system exclusive messages
+            0xF0 | 0xF7 => {
+                pos += 1;
+                let len = read_var_len(data, &mut pos).ok_or_else(truncated)?;
+                pos += len as usize;
+            }
+            _ => {
+                // data bytes continue the last status
+                let status = if byte & 0x80 == 0 {
+                    running_status.ok_or_else(|| {
+                        String::from("a MIDI track has no running status")
+                    })?
+                }
+                else {
+                    pos += 1;
+                    running_status = Some(byte);
+                    byte
+                };
+
+                let len = match status & 0xF0 {
+                    0xC0 | 0xD0 => 1,
+                    0x80..=0xE0 => 2,
+                    _ => {
+                        return Err(format!(
+                            "unexpected MIDI status byte {status:#04X}"
+                        ))
+                    }
+                };
+
+                let message =
+                    data.get(pos..pos + len).ok_or_else(truncated)?;
+                pos += len;
+
+                // a note on with no velocity is a note off
+                if let (0x80 | 0x90, &[note, velocity]) =
+                    (status & 0xF0, message)
+                {
+                    let is_on = status & 0xF0 == 0x90 && velocity > 0;
+                    events.push((tick, TrackEvent::Note { note, is_on }));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a variable-length quantity at `pos`, and moves past it.
+fn read_var_len(data: &[u8], pos: &mut usize) -> Option<u32> {
+    let mut value = 0_u32;
+
+    // quantities are at most four bytes long
+    for _ in 0..4 {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+
+        value = (value << 7) | (byte & 0x7F) as u32;
+
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+/// An iterator over the chunks of a MIDI file, as their kind and data.
+struct Chunks<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = (&'a [u8; 4], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kind = self.bytes.get(..4)?.try_into().ok()?;
+        let len = self.bytes.get(4..8)?.try_into().ok()?;
+        let rest = &self.bytes[8..];
+        let len = (u32::from_be_bytes(len) as usize).min(rest.len());
+        let (data, rest) = rest.split_at(len);
+
+        self.bytes = rest;
+        Some((kind, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let len = (data.len() as u32).to_be_bytes();
+        [&kind[..], &len, data].concat()
+    }
+
+    #[test]
+    fn notes_are_timed_with_the_tempo_map() {
+        // format 1, two tracks, 96 ticks per beat
+        let header = chunk(b"MThd", &[0, 1, 0, 2, 0, 96]);
+        // 60 BPM from the start, then 120 BPM from the second beat
+        let tempo = chunk(b"MTrk", &[
+            0x00, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40, // 1,000,000 us
+            0x60, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // 500,000 us
+            0x00, 0xFF, 0x2F, 0x00,
+        ]);
+        // C4 for a beat, then E4 for a beat, with running status and a
+        // note on with no velocity as the second note off
+        let notes = chunk(b"MTrk", &[
+            0x00, 0x90, 60, 100, //
+            0x60, 0x80, 60, 0, //
+            0x00, 0x90, 64, 100, //
+            0x60, 64, 0, //
+            0x00, 0xFF, 0x2F, 0x00,
+        ]);
+
+        let bytes = [header, tempo, notes].concat();
+        let parsed = parse_midi_file(&bytes).unwrap();
+
+        let expected = [
+            (0.0, 60, true),
+            (1.0, 60, false),
+            (1.0, 64, true),
+            (1.5, 64, false),
+        ];
+
+        assert_eq!(parsed.len(), expected.len());
+
+        for (note, (secs, n, is_on)) in parsed.iter().zip(expected) {
+            assert!((note.secs - secs).abs() < 1e-9);
+            assert_eq!((note.note, note.is_on), (n, is_on));
+        }
+
+        assert!(parse_midi_file(b"RIFF").is_err());
+    }
+
+    #[test]
+    fn smpte_divisions_are_validated() {
+        let track = chunk(b"MTrk", &[
+            0x00, 0x90, 60, 100, //
+            0x50, 0x80, 60, 0, //
+            0x00, 0xFF, 0x2F, 0x00,
+        ]);
+        let file = |div_hi: u8| {
+            // 25 FPS with 80 ticks per frame is 2,000 ticks per second
+            let header = chunk(b"MThd", &[0, 0, 0, 1, div_hi, 80]);
+            parse_midi_file(&[header, track.clone()].concat())
+        };
+
+        let notes = file(-25_i8 as u8).unwrap();
+        assert!((notes[1].secs - 0.04).abs() < 1e-9);
+
+        // 0x80 can't be negated, and 0xFF is -1 FPS
+        for div_hi in [0x80, 0xFF, -23_i8 as u8] {
+            assert!(file(div_hi).is_err(), "{div_hi:#04x}");
+        }
+    }
+}
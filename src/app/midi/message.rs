@@ -7,6 +7,8 @@ const EMPTY_BYTE: u8 = 0b_0000_0000;
 const MAX_7_BIT_INT: u8 = 1 << 7;
 /// All MIDI channels must be below this value.
 const MAX_4_BIT_INT: u8 = 1 << 4;
+/// All MIDI time code pieces must be below this value.
+const MAX_TIME_CODE_PIECE: u8 = 8;
 /// Controllers from 120-127 are reserved for "Channel Mode Messages", which are
 /// special instructions. So only controllers 0-119 are available for general
 /// use.
@@ -23,10 +25,30 @@ const CHANNEL_BIT_MASK: u8 = 0b0000_1111;
 const MIDI_NOTE_OFF: u8 = 0x80;
 /// Value for MIDI note on messages.
 const MIDI_NOTE_ON: u8 = 0x90;
+/// Value for MIDI 2.0 assignable per-note controller messages, which have no
+/// MIDI 1.0 equivalent.
+const MIDI_PER_NOTE_CONTROLLER: u8 = 0x10;
+/// Value for MIDI polyphonic key pressure messages.
+const MIDI_POLY_PRESSURE: u8 = 0xA0;
 /// Value for MIDI control change messages.
 const MIDI_CONTROL_CHANGE: u8 = 0xB0;
 /// Value for MIDI program change messages.
 const MIDI_PROGRAM_CHANGE: u8 = 0xC0;
+/// Value for MIDI channel pressure messages.
+const MIDI_CHANNEL_PRESSURE: u8 = 0xD0;
+/// Value for MIDI time code quarter frame messages.
+const MIDI_TIME_CODE: u8 = 0xF1;
+/// Value for MIDI song select messages.
+const MIDI_SONG_SELECT: u8 = 0xF3;
+/// Value for MIDI song position pointer messages. This is a system message, so
+/// it has no channel.
+const MIDI_SONG_POSITION_POINTER: u8 = 0xF2;
+
+/// Status bytes from this value are system messages, which have no channel.
+pub const SYSTEM_STATUS: u8 = 0xF0;
+/// Status bytes from this value are system real-time messages, which are a
+/// single byte and don't affect running status.
+pub const SYSTEM_REAL_TIME_STATUS: u8 = 0xF8;
 
 const MAX_14_BIT_CONTROLLER_NUMBER: u8 = 32;
 const MAX_14_BIT_INT: u16 = 1 << 14;
@@ -39,7 +61,18 @@ pub enum MIDIMessage {
     NoteOn { note: u8, velocity: u8, ch: u8 },
     ControlChange { controller: u8, value: u8, ch: u8 },
     ControlChange14Bit { controller: u8, value: u16, ch: u8 },
+    /// A CC with 32-bit resolution, which is only sent at full resolution
+    /// as a UMP. See [`MIDIMessage::to_midi_1()`].
+    ControlChange32Bit { controller: u8, value: u32, ch: u8 },
+    /// A MIDI 2.0 assignable per-note controller, which is only sent as a
+    /// UMP. See [`MIDIMessage::to_midi_1()`].
+    PerNoteController { note: u8, index: u8, value: u32, ch: u8 },
+    PolyPressure { note: u8, pressure: u8, ch: u8 },
     ProgramChange { program: u8, ch: u8 },
+    SongPositionPointer { position: u16 },
+    /// One of the eight pieces of a MIDI Time Code position, where `value`
+    /// is the 4-bit nibble of that piece.
+    TimeCodeQuarterFrame { piece: u8, value: u8 },
 }
 
 impl MIDIMessage {
@@ -150,6 +183,87 @@ impl MIDIMessage {
             ch: channel,
         }
     }
+
+    /// Returns a 32-bit MIDI CC message with the controller number and value.
+    ///
+    /// # Panics
+    ///
+    /// If the provided values are invalid for MIDI messages, this function will
+    /// panic.
+    pub fn control_change_32_bit(
+        controller_number: u8,
+        controller_value: u32,
+        channel: u8,
+    ) -> Self {
+        assert!(
+            controller_number < MAX_CONTROLLER_NUMBER,
+            "got invalid controller number of {controller_number}"
+        );
+        assert!(
+            channel < MAX_4_BIT_INT,
+            "got invalid MIDI channel of {channel}"
+        );
+
+        Self::ControlChange32Bit {
+            controller: controller_number,
+            value: controller_value,
+            ch: channel,
+        }
+    }
+
+    /// Returns a per-note controller message for `note`, with the controller
+    /// index and value.
+    ///
+    /// # Panics
+    ///
+    /// If the provided values are invalid for MIDI messages, this function will
+    /// panic.
+    pub fn per_note_controller(
+        note_value: u8,
+        controller_index: u8,
+        controller_value: u32,
+        channel: u8,
+    ) -> Self {
+        assert!(
+            note_value < MAX_7_BIT_INT,
+            "got invalid note value of {note_value}"
+        );
+        assert!(
+            channel < MAX_4_BIT_INT,
+            "got invalid MIDI channel of {channel}"
+        );
+
+        Self::PerNoteController {
+            note: note_value,
+            index: controller_index,
+            value: controller_value,
+            ch: channel,
+        }
+    }
+
+    /// Returns a MIDI polyphonic key pressure message for `note`.
+    ///
+    /// # Panics
+    ///
+    /// If the provided values are invalid for MIDI messages, this function will
+    /// panic.
+    pub fn poly_pressure(note_value: u8, pressure: u8, channel: u8) -> Self {
+        assert!(
+            note_value < MAX_7_BIT_INT,
+            "got invalid note value of {note_value}"
+        );
+        assert!(
+            pressure < MAX_7_BIT_INT,
+            "got invalid pressure of {pressure}"
+        );
+        assert!(
+            channel < MAX_4_BIT_INT,
+            "got invalid MIDI channel of {channel}"
+        );
+
+        Self::PolyPressure { note: note_value, pressure, ch: channel }
+    }
+
     /// Returns a MIDI program change message with the provided program number.
     ///
     /// # Panics
@@ -169,13 +283,56 @@ impl MIDIMessage {
         Self::ProgramChange { program: program_number, ch: channel }
     }
 
+    /// Returns a MIDI song position pointer message, where `position` is the
+    /// number of 16th notes since the start of the song.
+    ///
+    /// # Panics
+    ///
+    /// If the provided position is invalid for MIDI messages, this function
+    /// will panic.
+    pub fn song_position_pointer(position: u16) -> Self {
+        assert!(
+            position < MAX_14_BIT_INT,
+            "got invalid song position of {position}"
+        );
+
+        Self::SongPositionPointer { position }
+    }
+
+    /// Returns a MIDI time code quarter frame message, which carries `value`
+    /// as the nibble for `piece` of the time code.
+    ///
+    /// # Panics
+    ///
+    /// If the provided piece or value are invalid for MIDI messages, this
+    /// function will panic.
+    pub fn time_code_quarter_frame(piece: u8, value: u8) -> Self {
+        assert!(
+            piece < MAX_TIME_CODE_PIECE,
+            "got invalid time code piece of {piece}"
+        );
+        assert!(
+            value < MAX_4_BIT_INT,
+            "got invalid time code value of {value}"
+        );
+
+        Self::TimeCodeQuarterFrame { piece, value }
+    }
+
+    /// The channel of the message. System messages (such as song position
+    /// pointers and time code) have no channel, so this returns `0` for them.
     pub const fn channel(self) -> u8 {
         match self {
             Self::NoteOff { ch, .. }
             | Self::NoteOn { ch, .. }
             | Self::ControlChange { ch, .. }
             | Self::ControlChange14Bit { ch, .. }
+            | Self::ControlChange32Bit { ch, .. }
+            | Self::PerNoteController { ch, .. }
+            | Self::PolyPressure { ch, .. }
             | Self::ProgramChange { ch, .. } => ch,
+            Self::SongPositionPointer { .. }
+            | Self::TimeCodeQuarterFrame { .. } => 0,
         }
     }
 
@@ -188,20 +345,63 @@ impl MIDIMessage {
         }
     }
 
-    /// Whether the MIDI message is a 14-bit CC message.
+    /// Whether the MIDI message is sent as a 14-bit CC message over MIDI 1.0.
     pub const fn is_14_bit(self) -> bool {
-        matches!(self, Self::ControlChange14Bit { .. })
+        matches!(self.to_midi_1(), Self::ControlChange14Bit { .. })
     }
 
+    /// The number of bytes the message takes over MIDI 1.0.
     pub const fn size_bytes(self) -> usize {
         if self.is_14_bit() {
             6
         }
+        else if let Self::TimeCodeQuarterFrame { .. } = self {
+            2
+        }
         else {
             3
         }
     }
 
+    /// The number of bytes the message takes as a UMP.
+    pub const fn ump_size_bytes(self) -> usize {
+        match self {
+            // a 32-bit system message
+            Self::SongPositionPointer { .. }
+            | Self::TimeCodeQuarterFrame { .. } => 4,
+            // a 64-bit MIDI 2.0 channel voice message
+            _ => 8,
+        }
+    }
+
+    /// Returns the message in a form which MIDI 1.0 can carry. 32-bit CCs
+    /// become 14-bit CCs if the controller has an LSB pair, or 7-bit CCs
+    /// otherwise, and per-note controllers become polyphonic key pressure.
+    pub const fn to_midi_1(self) -> Self {
+        match self {
+            Self::ControlChange32Bit { controller, value, ch } => {
+                if controller < MAX_14_BIT_CONTROLLER_NUMBER {
+                    Self::ControlChange14Bit {
+                        controller,
+                        value: (value >> 18) as u16,
+                        ch,
+                    }
+                }
+                else {
+                    Self::ControlChange {
+                        controller,
+                        value: (value >> 25) as u8,
+                        ch,
+                    }
+                }
+            }
+            Self::PerNoteController { note, value, ch, .. } => {
+                Self::PolyPressure { note, pressure: (value >> 25) as u8, ch }
+            }
+            _ => self,
+        }
+    }
+
     /// Returns the MIDI message as a 3-byte array. This method guarantees that
     /// the provided bytes are valid for MIDI messages.
     ///
@@ -211,6 +411,8 @@ impl MIDIMessage {
     /// this with [`MIDIMessage::is_14_bit()`], and use
     /// [`MIDIMessage::as_bytes_double()`] to obtain the bytes.
     pub fn as_bytes(mut self) -> [u8; 3] {
+        self = self.to_midi_1();
+
         assert!(
             !self.is_14_bit(),
             "cannot convert 14-bit midi CC to 3-byte message"
@@ -232,10 +434,23 @@ impl MIDIMessage {
                 data[0] = controller;
                 data[1] = value;
             }
+            Self::PolyPressure { note, pressure, .. } => {
+                data[0] = note;
+                data[1] = pressure;
+            }
             Self::ProgramChange { program, .. } => {
                 data[0] = program;
             }
-            Self::ControlChange14Bit { .. } => {}
+            Self::SongPositionPointer { position } => {
+                data[0] = (position & GENERIC_MIDI_VALUE_MASK as u16) as u8;
+                data[1] = (position >> 7) as u8 & GENERIC_MIDI_VALUE_MASK;
+            }
+            Self::TimeCodeQuarterFrame { piece, value } => {
+                data[0] = (piece << 4) | value;
+            }
+            Self::ControlChange14Bit { .. }
+            | Self::ControlChange32Bit { .. }
+            | Self::PerNoteController { .. } => {}
         }
 
         [status_byte, data[0], data[1]]
@@ -245,7 +460,9 @@ impl MIDIMessage {
         const LSB_MASK: u16 = GENERIC_MIDI_VALUE_MASK as u16;
         const MSB_MASK: u16 = LSB_MASK << 7;
 
-        if let Self::ControlChange14Bit { controller, value, ch } = self {
+        if let Self::ControlChange14Bit { controller, value, ch } =
+            self.to_midi_1()
+        {
             let msb = ((value & MSB_MASK) >> 7) as u8;
             let lsb = (value & LSB_MASK) as u8;
 
@@ -293,35 +510,70 @@ impl MIDIMessage {
                 *value &= GENERIC_MIDI_VALUE_MASK;
                 *ch &= CHANNEL_BIT_MASK;
             }
+            Self::PolyPressure { note, pressure, ch } => {
+                *note &= GENERIC_MIDI_VALUE_MASK;
+                *pressure &= GENERIC_MIDI_VALUE_MASK;
+                *ch &= CHANNEL_BIT_MASK;
+            }
             Self::ProgramChange { program, ch } => {
                 *program &= GENERIC_MIDI_VALUE_MASK;
                 *ch &= CHANNEL_BIT_MASK;
             }
-            Self::ControlChange14Bit { .. } => {}
+            Self::SongPositionPointer { position } => {
+                *position &= MAX_14_BIT_INT - 1;
+            }
+            Self::TimeCodeQuarterFrame { piece, value } => {
+                *piece &= MAX_TIME_CODE_PIECE - 1;
+                *value &= CHANNEL_BIT_MASK;
+            }
+            Self::ControlChange14Bit { .. }
+            | Self::ControlChange32Bit { .. }
+            | Self::PerNoteController { .. } => {}
         }
     }
 
-    const fn to_status_byte(self) -> u8 {
-        let channel = match self {
-            Self::NoteOff { ch, .. }
-            | Self::NoteOn { ch, .. }
-            | Self::ControlChange { ch, .. }
-            | Self::ControlChange14Bit { ch, .. }
-            | Self::ProgramChange { ch, .. } => ch,
-        } & CHANNEL_BIT_MASK;
+    pub(super) const fn to_status_byte(self) -> u8 {
+        match self {
+            Self::SongPositionPointer { .. } => {
+                return MIDI_SONG_POSITION_POINTER;
+            }
+            Self::TimeCodeQuarterFrame { .. } => return MIDI_TIME_CODE,
+            _ => {}
+        }
+
+        let channel = self.channel() & CHANNEL_BIT_MASK;
 
         let status = match self {
             Self::NoteOff { .. } => MIDI_NOTE_OFF,
             Self::NoteOn { .. } => MIDI_NOTE_ON,
             Self::ControlChange { .. } => MIDI_CONTROL_CHANGE,
             Self::ControlChange14Bit { .. } => MIDI_CONTROL_CHANGE,
+            Self::ControlChange32Bit { .. } => MIDI_CONTROL_CHANGE,
+            // this is the MIDI 2.0 status of assignable per-note controllers
+            Self::PerNoteController { .. } => MIDI_PER_NOTE_CONTROLLER,
+            Self::PolyPressure { .. } => MIDI_POLY_PRESSURE,
             Self::ProgramChange { .. } => MIDI_PROGRAM_CHANGE,
+            Self::SongPositionPointer { .. } => MIDI_SONG_POSITION_POINTER,
+            Self::TimeCodeQuarterFrame { .. } => MIDI_TIME_CODE,
         } & STATUS_BIT_MASK;
 
         channel | status
     }
 }
 
+/// The number of data bytes which follow the status byte `status`.
+pub const fn data_len(status: u8) -> usize {
+    match status & STATUS_BIT_MASK {
+        MIDI_PROGRAM_CHANGE | MIDI_CHANNEL_PRESSURE => 1,
+        SYSTEM_STATUS => match status {
+            MIDI_TIME_CODE | MIDI_SONG_SELECT => 1,
+            MIDI_SONG_POSITION_POINTER => 2,
+            _ => 0,
+        },
+        _ => 2,
+    }
+}
+
 impl std::fmt::Display for MIDIMessage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -361,6 +613,35 @@ impl std::fmt::Display for MIDIMessage {
                     ch + 1
                 )
             }
+            Self::ControlChange32Bit { controller, value, ch } => {
+                write!(
+                    f,
+                    "MIDI CC 32-bit #{} with value {} (channel {})",
+                    controller,
+                    value,
+                    ch + 1
+                )
+            }
+            Self::PerNoteController { note, index, value, ch } => {
+                write!(
+                    f,
+                    "MIDI per-note controller #{} for note #{} with value {} \
+                     (channel {})",
+                    index,
+                    note,
+                    value,
+                    ch + 1
+                )
+            }
+            Self::PolyPressure { note, pressure, ch } => {
+                write!(
+                    f,
+                    "MIDI poly pressure {} for note #{} (channel {})",
+                    pressure,
+                    note,
+                    ch + 1
+                )
+            }
             Self::ProgramChange { program, ch } => {
                 write!(
                     f,
@@ -369,6 +650,12 @@ impl std::fmt::Display for MIDIMessage {
                     ch + 1
                 )
             }
+            Self::SongPositionPointer { position } => {
+                write!(f, "MIDI song position pointer at 16th #{position}")
+            }
+            Self::TimeCodeQuarterFrame { piece, value } => {
+                write!(f, "MIDI time code piece #{piece} with value {value}")
+            }
         }
     }
 }
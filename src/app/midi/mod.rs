@@ -1,8 +1,13 @@
 // use midir;
 use super::*;
 
+pub mod file;
 pub mod message;
+pub mod rtp;
 pub mod sender;
+pub mod timecode;
+pub mod transport;
+pub mod ump;
 
 pub const MIN_NOTE_VELOCITY: u8 = 0;
 pub const MAX_NOTE_VELOCITY: u8 = 127;
@@ -0,0 +1,638 @@
+//! An RTP-MIDI session initiator, which sends MIDI to another machine over
+//! the network rather than through a local MIDI interface.
+//!
+//! Sessions use Apple's network MIDI protocol (AppleMIDI): the peer is
+//! invited on its control port and then on its data port (the next port
+//! up), after which clock synchronization doubles as a keep-alive. MIDI is
+//! sent as RTP packets as described in RFC 6295, without a recovery journal.
+
+use super::*;
+use message::{data_len, SYSTEM_REAL_TIME_STATUS, SYSTEM_STATUS};
+use std::error::Error;
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use transport::MIDITransport;
+
+/// The port which RTP-MIDI peers usually listen on for control packets.
+pub const DEFAULT_RTP_MIDI_PORT: u16 = 5004;
+
+/// The first two bytes of every AppleMIDI command packet.
+const SIGNATURE: [u8; 2] = [0xFF, 0xFF];
+const PROTOCOL_VERSION: u32 = 2;
+
+const INVITATION: [u8; 2] = *b"IN";
+const INVITATION_ACCEPTED: [u8; 2] = *b"OK";
+const INVITATION_REJECTED: [u8; 2] = *b"NO";
+const END_SESSION: [u8; 2] = *b"BY";
+const SYNCHRONIZATION: [u8; 2] = *b"CK";
+
+/// RTP version 2, without padding, extensions or CSRCs.
+const RTP_HEADER_FLAGS: u8 = 0x80;
+/// The dynamic payload type which AppleMIDI uses.
+const RTP_MIDI_PAYLOAD_TYPE: u8 = 0x61;
+/// The longest MIDI list which a command section header can describe.
+const MAX_MIDI_LIST_LEN: usize = 0x0FFF;
+/// The longest MIDI list which fits in a one-byte command section header.
+const MAX_SHORT_MIDI_LIST_LEN: usize = 0x0F;
+/// Marks a command section header as two bytes long.
+const LONG_HEADER_FLAG: u8 = 0x80;
+
+/// How long to wait before re-sending an invitation. This doubles with each
+/// unanswered invitation, up to [`MAX_INVITATION_INTERVAL`].
+const INVITATION_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_INVITATION_INTERVAL: Duration = Duration::from_secs(8);
+/// How often the clocks are synchronized once a session is established. The
+/// first few synchronizations are sent more quickly, as peers expect.
+const SYNC_INTERVAL: Duration = Duration::from_secs(10);
+const FAST_SYNC_INTERVAL: Duration = Duration::from_millis(1500);
+const NUM_FAST_SYNCS: u32 = 6;
+/// How long the peer may be silent before the session is considered lost.
+const SESSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many times to look for a free pair of adjacent ports.
+const MAX_PORT_BIND_ATTEMPTS: usize = 16;
+const MAX_PACKET_SIZE: usize = 1024;
+
+/// The peer's port which an invitation is sent to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SessionPort {
+    Control,
+    Data,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SessionState {
+    /// Inviting the peer on one of its ports.
+    Inviting {
+        port: SessionPort,
+        attempts: u32,
+        last_sent: Option<Instant>,
+    },
+    Connected,
+}
+
+impl SessionState {
+    const fn inviting() -> Self {
+        Self::Inviting {
+            port: SessionPort::Control,
+            attempts: 0,
+            last_sent: None,
+        }
+    }
+}
+
+/// An RTP-MIDI session with a single peer, which this app initiates.
+///
+/// The session is (re-)established automatically while it is polled, and
+/// MIDI sent while it isn't established is discarded.
+pub struct RTPMIDISession {
+    /// The name which the peer sees for this session.
+    session_name: String,
+    /// The peer's control port.
+    peer: SocketAddr,
+    /// The peer's name and address, for display.
+    peer_name: String,
+
+    control: UdpSocket,
+    data: UdpSocket,
+
+    /// Identifies this end of the session.
+    ssrc: u32,
+    /// Identifies the current invitation.
+    token: u32,
+    state: SessionState,
+
+    /// The origin of the session clock.
+    start: Instant,
+    last_sync: Option<Instant>,
+    num_syncs: u32,
+    /// When a packet was last received from the peer.
+    last_heard: Instant,
+
+    sequence: u16,
+    packet: Vec<u8>,
+    buf: Vec<u8>,
+}
+
+impl RTPMIDISession {
+    /// Returns a session called `name` which invites the peer whose control
+    /// port is at `peer`. The first invitation is sent immediately.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session's sockets could not be created.
+    pub fn connect(name: &str, peer: SocketAddr) -> io::Result<Self> {
+        let (control, data) = bind_port_pair(peer)?;
+
+        let mut session = Self {
+            session_name: name.to_string(),
+            peer,
+            peer_name: format!("RTP-MIDI {peer}"),
+
+            control,
+            data,
+
+            ssrc: rand::random(),
+            token: rand::random(),
+            state: SessionState::inviting(),
+
+            start: Instant::now(),
+            last_sync: None,
+            num_syncs: 0,
+            last_heard: Instant::now(),
+
+            sequence: rand::random(),
+            packet: Vec::with_capacity(MAX_PACKET_SIZE),
+            buf: vec![0; MAX_PACKET_SIZE],
+        };
+
+        session.poll();
+
+        Ok(session)
+    }
+
+    /// Whether the session is established.
+    pub fn is_connected(&self) -> bool {
+        self.state == SessionState::Connected
+    }
+
+    /// The peer's data port, which is one above its control port.
+    fn peer_data(&self) -> SocketAddr {
+        let mut addr = self.peer;
+        addr.set_port(addr.port().wrapping_add(1));
+        addr
+    }
+
+    /// The session clock, in units of 100 microseconds.
+    fn timestamp(&self) -> u64 {
+        (self.start.elapsed().as_micros() / 100) as u64
+    }
+
+    /// Handles every packet which has been received on both ports.
+    fn receive(&mut self) {
+        // taken so that packets can be handled whilst borrowing it
+        let mut buf = std::mem::take(&mut self.buf);
+
+        for port in [SessionPort::Control, SessionPort::Data] {
+            loop {
+                let socket = match port {
+                    SessionPort::Control => &self.control,
+                    SessionPort::Data => &self.data,
+                };
+
+                let len = match socket.recv_from(&mut buf) {
+                    Ok((len, addr)) if addr.ip() == self.peer.ip() => len,
+                    // packets from anyone other than the peer are ignored
+                    Ok(_) => continue,
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        eprintln!("failed to receive RTP-MIDI packet: {e}");
+                        break;
+                    }
+                };
+
+                self.handle_packet(port, &buf[..len]);
+            }
+        }
+
+        self.buf = buf;
+    }
+
+    fn handle_packet(&mut self, port: SessionPort, packet: &[u8]) {
+        // MIDI from the peer is ignored, as the session only sends
+        if packet.len() < 4 || packet[..2] != SIGNATURE {
+            return;
+        }
+
+        let command = [packet[2], packet[3]];
+        self.last_heard = Instant::now();
+
+        match command {
+            INVITATION_ACCEPTED => self.handle_accepted(port, packet),
+            INVITATION_REJECTED => {
+                if read_u32(packet, 8) == Some(self.token) {
+                    eprintln!("{} rejected the invitation", self.peer_name);
+                }
+            }
+            END_SESSION => {
+                if self.is_connected() {
+                    eprintln!("{} ended the session", self.peer_name);
+
+                    // the session has already ended, so isn't ended again
+                    self.state = SessionState::inviting();
+                    self.reconnect();
+                }
+            }
+            SYNCHRONIZATION if port == SessionPort::Data => {
+                self.handle_sync(packet);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_accepted(&mut self, port: SessionPort, packet: &[u8]) {
+        if read_u32(packet, 8) != Some(self.token) {
+            return;
+        }
+
+        let SessionState::Inviting { port: invited, .. } = self.state
+        else {
+            return;
+        };
+
+        if invited != port {
+            return;
+        }
+
+        match port {
+            SessionPort::Control => {
+                self.state = SessionState::Inviting {
+                    port: SessionPort::Data,
+                    attempts: 0,
+                    last_sent: None,
+                };
+            }
+            SessionPort::Data => {
+                println!("RTP-MIDI session with {} established", self.peer);
+
+                self.state = SessionState::Connected;
+                self.last_sync = None;
+                self.num_syncs = 0;
+            }
+        }
+
+        // the next step is taken straight away
+        self.poll_state();
+    }
+
+    /// Answers the peer's side of a clock synchronization.
+    fn handle_sync(&mut self, packet: &[u8]) {
+        const TIMESTAMPS_OFFSET: usize = 12;
+        const SYNC_PACKET_SIZE: usize = TIMESTAMPS_OFFSET + 24;
+
+        if packet.len() < SYNC_PACKET_SIZE {
+            return;
+        }
+
+        let count = packet[8];
+        let mut timestamps = [0; 3];
+
+        for (i, ts) in timestamps.iter_mut().enumerate() {
+            *ts = read_u64(packet, TIMESTAMPS_OFFSET + i * 8).unwrap_or(0);
+        }
+
+        match count {
+            // the peer started a synchronization
+            0 => {
+                timestamps[1] = self.timestamp();
+                self.send_sync(1, timestamps);
+            }
+            // the peer answered ours
+            1 => {
+                timestamps[2] = self.timestamp();
+                self.send_sync(2, timestamps);
+            }
+            _ => {}
+        }
+    }
+
+    /// Sends invitations or synchronizations if they are due, and restarts
+    /// the session if the peer has gone quiet.
+    fn poll_state(&mut self) {
+        let now = Instant::now();
+
+        match self.state {
+            SessionState::Inviting { port, attempts, last_sent } => {
+                let interval = INVITATION_INTERVAL
+                    .saturating_mul(1 << attempts.min(3))
+                    .min(MAX_INVITATION_INTERVAL);
+
+                if last_sent.is_some_and(|t| now - t < interval) {
+                    return;
+                }
+
+                self.send_invitation(port);
+                self.state = SessionState::Inviting {
+                    port,
+                    attempts: attempts.saturating_add(1),
+                    last_sent: Some(now),
+                };
+            }
+            SessionState::Connected => {
+                if now - self.last_heard > SESSION_TIMEOUT {
+                    eprintln!(
+                        "lost RTP-MIDI session with {}, reconnecting",
+                        self.peer
+                    );
+                    self.reconnect();
+                    return;
+                }
+
+                let interval = if self.num_syncs < NUM_FAST_SYNCS {
+                    FAST_SYNC_INTERVAL
+                }
+                else {
+                    SYNC_INTERVAL
+                };
+
+                if self.last_sync.is_some_and(|t| now - t < interval) {
+                    return;
+                }
+
+                self.send_sync(0, [self.timestamp(), 0, 0]);
+                self.last_sync = Some(now);
+                self.num_syncs = self.num_syncs.saturating_add(1);
+            }
+        }
+    }
+
+    /// Ends the session and starts inviting the peer again.
+    fn reconnect(&mut self) {
+        self.end_session();
+
+        self.token = rand::random();
+        self.state = SessionState::inviting();
+        self.last_heard = Instant::now();
+    }
+
+    fn end_session(&mut self) {
+        if !self.is_connected() {
+            return;
+        }
+
+        let mut packet = command_packet(END_SESSION);
+        packet.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+        packet.extend_from_slice(&self.token.to_be_bytes());
+        packet.extend_from_slice(&self.ssrc.to_be_bytes());
+
+        self.send_to(SessionPort::Control, &packet);
+    }
+
+    fn send_invitation(&self, port: SessionPort) {
+        let mut packet = command_packet(INVITATION);
+        packet.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+        packet.extend_from_slice(&self.token.to_be_bytes());
+        packet.extend_from_slice(&self.ssrc.to_be_bytes());
+        packet.extend_from_slice(self.session_name.as_bytes());
+        packet.push(0);
+
+        self.send_to(port, &packet);
+    }
+
+    fn send_sync(&self, count: u8, timestamps: [u64; 3]) {
+        let mut packet = command_packet(SYNCHRONIZATION);
+        packet.extend_from_slice(&self.ssrc.to_be_bytes());
+        packet.extend_from_slice(&[count, 0, 0, 0]);
+
+        for ts in timestamps {
+            packet.extend_from_slice(&ts.to_be_bytes());
+        }
+
+        self.send_to(SessionPort::Data, &packet);
+    }
+
+    fn send_to(&self, port: SessionPort, packet: &[u8]) {
+        let result = match port {
+            SessionPort::Control => self.control.send_to(packet, self.peer),
+            SessionPort::Data => self.data.send_to(packet, self.peer_data()),
+        };
+
+        if let Err(e) = result {
+            eprintln!("failed to send RTP-MIDI packet: {e}");
+        }
+    }
+
+    /// Sends `list` (an encoded MIDI list) in a single RTP packet.
+    fn send_midi_list(&mut self, list: &[u8]) -> io::Result<()> {
+        let timestamp = self.timestamp() as u32;
+
+        self.packet.clear();
+        self.packet.extend_from_slice(&[
+            RTP_HEADER_FLAGS,
+            RTP_MIDI_PAYLOAD_TYPE,
+        ]);
+        self.packet.extend_from_slice(&self.sequence.to_be_bytes());
+        self.packet.extend_from_slice(&timestamp.to_be_bytes());
+        self.packet.extend_from_slice(&self.ssrc.to_be_bytes());
+        push_command_section_header(&mut self.packet, list.len());
+        self.packet.extend_from_slice(list);
+
+        self.sequence = self.sequence.wrapping_add(1);
+
+        self.data.send_to(&self.packet, self.peer_data()).map(|_| ())
+    }
+}
+
+impl MIDITransport for RTPMIDISession {
+    fn send(&mut self, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        if !self.is_connected() {
+            return Err(format!(
+                "RTP-MIDI session with {} is not established",
+                self.peer
+            )
+            .into());
+        }
+
+        let mut list = Vec::with_capacity(bytes.len() * 2);
+
+        for (status, data) in MIDICommands::new(bytes) {
+            // one byte is left for each command's delta time
+            if list.len() + data.len() + 2 > MAX_MIDI_LIST_LEN {
+                self.send_midi_list(&list)?;
+                list.clear();
+            }
+
+            // every command after the first must have a delta time
+            if !list.is_empty() {
+                list.push(0);
+            }
+
+            // running status isn't used, as commands may span packets
+            list.push(status);
+            list.extend_from_slice(data);
+        }
+
+        if !list.is_empty() {
+            self.send_midi_list(&list)?;
+        }
+
+        Ok(())
+    }
+
+    fn poll(&mut self) {
+        self.receive();
+        self.poll_state();
+    }
+
+    fn name(&self) -> &str {
+        &self.peer_name
+    }
+}
+
+impl Drop for RTPMIDISession {
+    fn drop(&mut self) {
+        self.end_session();
+    }
+}
+
+// *** *** *** //
+
+/// Splits MIDI bytes, which may use running status, into commands of a
+/// status byte and its data bytes. Incomplete commands are skipped.
+struct MIDICommands<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    running_status: Option<u8>,
+}
+
+impl<'a> MIDICommands<'a> {
+    const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0, running_status: None }
+    }
+}
+
+impl<'a> Iterator for MIDICommands<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let byte = *self.bytes.get(self.pos)?;
+
+            let status = if byte & 0x80 == 0 {
+                // a data byte without a status can't be interpreted
+                let Some(status) = self.running_status
+                else {
+                    self.pos += 1;
+                    continue;
+                };
+
+                status
+            }
+            else {
+                self.pos += 1;
+
+                if byte < SYSTEM_REAL_TIME_STATUS {
+                    // only channel messages may use running status
+                    self.running_status =
+                        (byte < SYSTEM_STATUS).then_some(byte);
+                }
+
+                byte
+            };
+
+            let end = self.pos + data_len(status);
+
+            let Some(data) = self.bytes.get(self.pos..end)
+            else {
+                self.pos = self.bytes.len();
+                return None;
+            };
+
+            self.pos = end;
+
+            return Some((status, data));
+        }
+    }
+}
+
+/// Returns an AppleMIDI command packet, without its payload.
+fn command_packet(command: [u8; 2]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(64);
+    packet.extend_from_slice(&SIGNATURE);
+    packet.extend_from_slice(&command);
+    packet
+}
+
+/// Pushes the header of a command section containing a MIDI list of `len`
+/// bytes. There is no journal, and the first command has no delta time.
+fn push_command_section_header(packet: &mut Vec<u8>, len: usize) {
+    debug_assert!(len <= MAX_MIDI_LIST_LEN);
+
+    if len <= MAX_SHORT_MIDI_LIST_LEN {
+        packet.push(len as u8);
+    }
+    else {
+        packet.push(LONG_HEADER_FLAG | (len >> 8) as u8);
+        packet.push((len & 0xFF) as u8);
+    }
+}
+
+/// Binds sockets on a pair of adjacent ports, as some peers assume the data
+/// port is one above the control port.
+fn bind_port_pair(peer: SocketAddr) -> io::Result<(UdpSocket, UdpSocket)> {
+    let unspecified = osc::transport::unspecified_addr_for(peer);
+
+    for _ in 0..MAX_PORT_BIND_ATTEMPTS {
+        let control = UdpSocket::bind(unspecified)?;
+
+        let mut data_addr = control.local_addr()?;
+        let Some(data_port) = data_addr.port().checked_add(1)
+        else {
+            continue;
+        };
+        data_addr.set_port(data_port);
+
+        if let Ok(data) = UdpSocket::bind(data_addr) {
+            control.set_nonblocking(true)?;
+            data.set_nonblocking(true)?;
+
+            return Ok((control, data));
+        }
+    }
+
+    Err(io::Error::new(
+        ErrorKind::AddrInUse,
+        "failed to find a free pair of ports for RTP-MIDI",
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let bytes = bytes.get(offset..offset + 8)?;
+    Some(u64::from_be_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands_expand_running_status() {
+        let bytes = [0xB0, 1, 10, 2, 20, 0xC1, 5, 0x90, 60, 100];
+        let commands = MIDICommands::new(&bytes).collect::<Vec<_>>();
+
+        assert_eq!(
+            commands,
+            [
+                (0xB0, &[1, 10][..]),
+                (0xB0, &[2, 20][..]),
+                (0xC1, &[5][..]),
+                (0x90, &[60, 100][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commands_skip_incomplete_data() {
+        let bytes = [5, 0xF8, 0xB0, 1];
+        let commands = MIDICommands::new(&bytes).collect::<Vec<_>>();
+
+        assert_eq!(commands, [(0xF8, &[][..])]);
+    }
+
+    #[test]
+    fn test_command_section_header() {
+        let mut short = Vec::new();
+        push_command_section_header(&mut short, 6);
+        assert_eq!(short, [6]);
+
+        let mut long = Vec::new();
+        push_command_section_header(&mut long, 0x123);
+        assert_eq!(long, [0x81, 0x23]);
+    }
+}
@@ -3,23 +3,170 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use message::MIDIMessage;
+use message::{
+    data_len, MIDIMessage, SYSTEM_REAL_TIME_STATUS, SYSTEM_STATUS,
+};
 use midir::{MidiIO, MidiOutput, MidiOutputPort};
 use rand::seq::IndexedRandom;
 use timer::TimerThread;
+use transport::{MIDIPortTransport, MIDITransport};
 
 use super::*;
 
 const MIDI_QUEUE_PREALLOC_SIZE: usize = 8192;
 
+/// The protocol which a MIDI destination receives messages in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MIDIProtocol {
+    #[default]
+    MIDI1,
+    /// MIDI 2.0 Universal MIDI Packets, which carry 32-bit controllers and
+    /// per-note controllers at full resolution.
+    UMP,
+}
+
+impl MIDIProtocol {
+    /// Parses a protocol from its name, i.e. `"midi1"` or `"ump"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "midi1" | "midi" => Some(Self::MIDI1),
+            "ump" | "midi2" => Some(Self::UMP),
+            _ => None,
+        }
+    }
+
+    /// The number of bytes which `message` takes in this protocol.
+    pub const fn size_bytes(self, message: MIDIMessage) -> usize {
+        match self {
+            Self::MIDI1 => message.size_bytes(),
+            Self::UMP => message.ump_size_bytes(),
+        }
+    }
+}
+
+impl std::fmt::Display for MIDIProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MIDI1 => write!(f, "MIDI 1.0"),
+            Self::UMP => write!(f, "MIDI 2.0 (UMP)"),
+        }
+    }
+}
+
+/// Encodes MIDI messages into bytes in a [`MIDIProtocol`], optionally using
+/// running status for MIDI 1.0.
+///
+/// With running status, a message's status byte is omitted if it is the same
+/// as the previous message's, which saves a third of the bytes for dense CC
+/// streams. Some devices mishandle this, so it is disabled by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MIDIEncoder {
+    protocol: MIDIProtocol,
+    /// The UMP group which messages are sent in.
+    ump_group: u8,
+    running_status: bool,
+    /// The status byte which the receiver will assume, if any.
+    last_status: Option<u8>,
+}
+
+impl MIDIEncoder {
+    pub const fn new(running_status: bool) -> Self {
+        Self {
+            protocol: MIDIProtocol::MIDI1,
+            ump_group: 0,
+            running_status,
+            last_status: None,
+        }
+    }
+
+    pub fn set_protocol(&mut self, protocol: MIDIProtocol) {
+        self.protocol = protocol;
+        self.reset();
+    }
+
+    pub const fn protocol(&self) -> MIDIProtocol {
+        self.protocol
+    }
+
+    /// Sets the UMP group (from `0` to `15`) which messages are sent in.
+    pub fn set_ump_group(&mut self, group: u8) {
+        self.ump_group = group & 0x0F;
+    }
+
+    pub const fn ump_group(&self) -> u8 {
+        self.ump_group
+    }
+
+    pub fn set_running_status(&mut self, enabled: bool) {
+        self.running_status = enabled;
+        self.reset();
+    }
+
+    pub const fn running_status(&self) -> bool {
+        self.running_status
+    }
+
+    /// Forgets the previous status byte, so that the next message is sent in
+    /// full. This should be called at the start of each packet.
+    pub fn reset(&mut self) {
+        self.last_status = None;
+    }
+
+    /// Appends the bytes of `message` to `bytes`.
+    pub fn encode(&mut self, message: &MIDIMessage, bytes: &mut Vec<u8>) {
+        if self.protocol == MIDIProtocol::UMP {
+            ump::encode(message, self.ump_group, bytes);
+        }
+        else if message.is_14_bit() {
+            let msg = message.as_bytes_double();
+            self.encode_raw(&msg[..3], bytes);
+            self.encode_raw(&msg[3..], bytes);
+        }
+        else {
+            self.encode_raw(&message.as_bytes(), bytes);
+        }
+    }
+
+    fn encode_raw(&mut self, msg: &[u8], bytes: &mut Vec<u8>) {
+        let status = msg[0];
+        let data = &msg[1..=data_len(status)];
+
+        if status >= SYSTEM_STATUS {
+            // system common messages cancel running status
+            if status < SYSTEM_REAL_TIME_STATUS {
+                self.last_status = None;
+            }
+
+            bytes.push(status);
+        }
+        else if !self.running_status || self.last_status != Some(status) {
+            bytes.push(status);
+
+            if self.running_status {
+                self.last_status = Some(status);
+            }
+        }
+
+        bytes.extend_from_slice(data);
+    }
+}
+
 pub struct MIDISender {
-    output: midir::MidiOutputConnection,
-    port: midir::MidiOutputPort,
-    bound_port_name: String,
+    output: Box<dyn MIDITransport>,
     queue: Vec<u8>,
+    encoder: MIDIEncoder,
 }
 
 impl MIDISender {
+    /// Returns a new `MIDISender` which sends through `output`.
+    pub fn with_transport(output: Box<dyn MIDITransport>) -> Self {
+        Self {
+            output,
+            queue: Vec::with_capacity(MIDI_QUEUE_PREALLOC_SIZE),
+            encoder: MIDIEncoder::default(),
+        }
+    }
+
     /// Returns a new `MIDISender` which binds to the first available MIDI port.
     ///
     /// # Errors
@@ -53,12 +200,9 @@ impl MIDISender {
 
         let output = output.connect(&first_port, &port_name)?;
 
-        Ok(Self {
-            output,
-            port: first_port,
-            bound_port_name,
-            queue: Vec::with_capacity(MIDI_QUEUE_PREALLOC_SIZE),
-        })
+        Ok(Self::with_transport(Box::new(MIDIPortTransport::new(
+            output, first_port, bound_port_name,
+        ))))
     }
 
     /// Returns a new `MIDISender` which tries to bind to a port containing the
@@ -118,46 +262,52 @@ impl MIDISender {
 
         let output = output.connect(&port, &port_name)?;
 
-        Ok(Self {
-            output,
-            port,
-            bound_port_name,
-            queue: Vec::with_capacity(MIDI_QUEUE_PREALLOC_SIZE),
-        })
+        Ok(Self::with_transport(Box::new(MIDIPortTransport::new(
+            output, port, bound_port_name,
+        ))))
     }
 
     /// Enqueues the provided message to an internal queue, ready to be send via
     /// [`MIDISender::send_queue()`].
     pub fn enqueue(&mut self, message: &MIDIMessage) {
-        if message.is_14_bit() {
-            for byte in message.as_bytes_double() {
-                self.queue.push(byte);
-            }
-        }
-        else {
-            for byte in message.as_bytes() {
-                self.queue.push(byte);
-            }
-        }
+        self.encoder.encode(message, &mut self.queue);
     }
 
     pub fn clear_queue(&mut self) {
         self.queue.clear();
+        self.encoder.reset();
+    }
+
+    /// Sets whether queued messages are sent with running status. See
+    /// [`MIDIEncoder`].
+    pub fn set_running_status(&mut self, enabled: bool) {
+        self.encoder.set_running_status(enabled);
+    }
+
+    /// Sets the protocol which the destination receives messages in.
+    pub fn set_protocol(&mut self, protocol: MIDIProtocol) {
+        self.encoder.set_protocol(protocol);
     }
 
     pub fn queue(&self) -> &[u8] {
         &self.queue
     }
 
+    /// Services the sender's transport. See [`MIDITransport::poll()`].
+    pub fn poll(&mut self) {
+        self.output.poll();
+    }
+
     /// Sends the internal queue of `MIDIMessage` bytes to the bound MIDI port.
     ///
     /// # Errors
     ///
     /// Returns an error if the MIDI message failed to send.
-    pub fn send_queue(&mut self) -> Result<(), midir::SendError> {
+    pub fn send_queue(&mut self) -> Result<(), Box<dyn Error>> {
         let result = self.output.send(&self.queue);
 
-        self.queue.clear();
+        // each send is its own packet, which must start with a status byte
+        self.clear_queue();
         Ok(())
     }
 
@@ -169,7 +319,14 @@ impl MIDISender {
     pub fn send_direct(
         &mut self,
         message: &MIDIMessage,
-    ) -> Result<(), midir::SendError> {
+    ) -> Result<(), Box<dyn Error>> {
+        if self.encoder.protocol() == MIDIProtocol::UMP {
+            let mut bytes = Vec::with_capacity(message.ump_size_bytes());
+            ump::encode(message, self.encoder.ump_group(), &mut bytes);
+
+            return self.output.send(&bytes);
+        }
+
         if message.is_14_bit() {
             let msg = message.as_bytes_double();
             // let bytes = format!(
@@ -189,18 +346,12 @@ impl MIDISender {
         }
     }
 
-    pub const fn get_port(&self) -> &MidiOutputPort {
-        &self.port
-    }
-
     pub fn close(self) {
-        _ = self.output.close();
+        drop(self.output);
     }
 
-    // hush now clippy for you are wrong in this case >:|
-    #[allow(clippy::missing_const_for_fn)]
     pub fn bound_port_name(&self) -> &str {
-        &self.bound_port_name
+        self.output.name()
     }
 }
 
@@ -217,9 +368,18 @@ impl MIDISenderTimedThread {
         substr: &str,
         receiver: CCReceiver<Vec<MIDIMessage>>,
     ) -> Result<Self, Box<dyn Error>> {
-        let midi_sender = Arc::new(Mutex::new(
+        Ok(Self::with_sender(
             MIDISender::new_with_port_containing(name, substr)?,
-        ));
+            receiver,
+        ))
+    }
+
+    /// Returns a thread which sends received messages through `sender`.
+    pub fn with_sender(
+        sender: MIDISender,
+        receiver: CCReceiver<Vec<MIDIMessage>>,
+    ) -> Self {
+        let midi_sender = Arc::new(Mutex::new(sender));
 
         let rx = Arc::new(Mutex::new(receiver));
         let tx = Arc::clone(&midi_sender);
@@ -228,6 +388,8 @@ impl MIDISenderTimedThread {
             if let Ok(mut receiver) = rx.lock()
                 && let Ok(mut sender) = tx.lock()
             {
+                sender.poll();
+
                 while let Ok(buf) = receiver.try_recv()
                     && !buf.is_empty()
                 {
@@ -242,7 +404,30 @@ impl MIDISenderTimedThread {
             }
         });
 
-        Ok(Self { sender: midi_sender, thread })
+        Self { sender: midi_sender, thread }
+    }
+
+    /// Sets whether messages are sent with running status.
+    pub fn set_running_status(&self, enabled: bool) {
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.set_running_status(enabled);
+        }
+    }
+
+    /// Sets the protocol which messages are sent in.
+    pub fn set_protocol(&self, protocol: MIDIProtocol) {
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.set_protocol(protocol);
+        }
+    }
+
+    /// The name of the port or peer which messages are sent to, if the
+    /// sender can be reached.
+    pub fn bound_port_name(&self) -> Option<String> {
+        self.sender
+            .lock()
+            .ok()
+            .map(|sender| sender.bound_port_name().to_string())
     }
 
     pub fn start_send(&mut self) {
@@ -253,3 +438,91 @@ impl MIDISenderTimedThread {
         self.thread.stop_after_num_callbacks(1, Some(1.0));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(
+        encoder: &mut MIDIEncoder,
+        messages: &[MIDIMessage],
+    ) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for msg in messages {
+            encoder.encode(msg, &mut bytes);
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn test_full_status_without_running_status() {
+        let mut encoder = MIDIEncoder::new(false);
+        let bytes = encode(
+            &mut encoder,
+            &[
+                MIDIMessage::control_change(1, 10, 0),
+                MIDIMessage::control_change(2, 20, 0),
+            ],
+        );
+
+        assert_eq!(bytes, [0xB0, 1, 10, 0xB0, 2, 20]);
+    }
+
+    #[test]
+    fn test_running_status_omits_repeated_status() {
+        let mut encoder = MIDIEncoder::new(true);
+        let bytes = encode(
+            &mut encoder,
+            &[
+                MIDIMessage::control_change_14_bit(3, 0x3FFF, 0),
+                MIDIMessage::control_change(4, 40, 0),
+                MIDIMessage::control_change(5, 50, 1),
+            ],
+        );
+
+        assert_eq!(bytes, [0xB0, 35, 0x7F, 3, 0x7F, 4, 40, 0xB1, 5, 50]);
+    }
+
+    #[test]
+    fn test_system_messages_cancel_running_status() {
+        let mut encoder = MIDIEncoder::new(true);
+        let bytes = encode(
+            &mut encoder,
+            &[
+                MIDIMessage::note_on(60, 100, 0),
+                MIDIMessage::song_position_pointer(0),
+                MIDIMessage::note_on(62, 100, 0),
+            ],
+        );
+
+        assert_eq!(bytes, [0x90, 60, 100, 0xF2, 0, 0, 0x90, 62, 100]);
+    }
+
+    #[test]
+    fn test_program_change_has_one_data_byte() {
+        let mut encoder = MIDIEncoder::new(true);
+        let bytes = encode(
+            &mut encoder,
+            &[
+                MIDIMessage::program_change(7, 0),
+                MIDIMessage::program_change(8, 0),
+            ],
+        );
+
+        assert_eq!(bytes, [0xC0, 7, 8]);
+    }
+
+    #[test]
+    fn test_reset_sends_full_status() {
+        let mut encoder = MIDIEncoder::new(true);
+        let msg = MIDIMessage::control_change(1, 1, 0);
+
+        let mut bytes = encode(&mut encoder, &[msg]);
+        encoder.reset();
+        encoder.encode(&msg, &mut bytes);
+
+        assert_eq!(bytes, [0xB0, 1, 1, 0xB0, 1, 1]);
+    }
+}
@@ -0,0 +1,323 @@
+//! Generation of MIDI Time Code (MTC), so that video playback systems can
+//! chase the transport.
+//!
+//! MTC sends a position as eight quarter frame messages, each carrying a
+//! nibble of the time code, over two frames. A receiver reassembles the
+//! pieces, and adds the two frames which they took to arrive.
+
+use super::*;
+use message::MIDIMessage;
+
+/// The number of quarter frame pieces in each time code.
+const NUM_PIECES: u64 = 8;
+/// The number of quarter frames which the generator may fall behind (or
+/// ahead) before it starts a new sequence at the transport's position.
+const MAX_QUARTER_FRAME_DRIFT: u64 = NUM_PIECES;
+/// The number of frames in each 10-minute block of 29.97 drop-frame time
+/// code, in which the frame numbers `0` and `1` are dropped from the start
+/// of every minute except the first.
+const DROP_FRAMES_PER_10_MINUTES: u64 = 17982;
+/// The number of frames in each minute of 29.97 drop-frame time code,
+/// except the first of each 10 minutes.
+const DROP_FRAMES_PER_MINUTE: u64 = 1798;
+
+/// The frame rates which MTC can carry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameRate {
+    Fps24,
+    #[default]
+    Fps25,
+    /// 29.97 frames per second, with drop-frame numbering.
+    Fps2997Drop,
+    Fps30,
+}
+
+impl FrameRate {
+    /// Parses a frame rate from its name, i.e. `"24"`, `"25"`, `"29.97"` or
+    /// `"30"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "24" => Some(Self::Fps24),
+            "25" => Some(Self::Fps25),
+            "29.97" | "29.97df" | "df" => Some(Self::Fps2997Drop),
+            "30" => Some(Self::Fps30),
+            _ => None,
+        }
+    }
+
+    /// The number of frames each second.
+    pub fn fps(self) -> f64 {
+        match self {
+            Self::Fps24 => 24.0,
+            Self::Fps25 => 25.0,
+            Self::Fps2997Drop => 30000.0 / 1001.0,
+            Self::Fps30 => 30.0,
+        }
+    }
+
+    /// The number of frame numbers in each second of time code.
+    pub const fn frames_per_second(self) -> u64 {
+        match self {
+            Self::Fps24 => 24,
+            Self::Fps25 => 25,
+            Self::Fps2997Drop | Self::Fps30 => 30,
+        }
+    }
+
+    /// The rate as it is encoded in the hours of a time code.
+    const fn code(self) -> u8 {
+        match self {
+            Self::Fps24 => 0,
+            Self::Fps25 => 1,
+            Self::Fps2997Drop => 2,
+            Self::Fps30 => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fps24 => write!(f, "24 fps"),
+            Self::Fps25 => write!(f, "25 fps"),
+            Self::Fps2997Drop => write!(f, "29.97 fps drop-frame"),
+            Self::Fps30 => write!(f, "30 fps"),
+        }
+    }
+}
+
+/// A position in hours, minutes, seconds and frames. Hours wrap after a
+/// day, as MTC can't carry more.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+}
+
+impl Timecode {
+    /// The time code of the frame at `secs` seconds.
+    pub fn from_secs(secs: f64, rate: FrameRate) -> Self {
+        Self::from_frames((secs.max(0.0) * rate.fps()).floor() as u64, rate)
+    }
+
+    /// The time code of the `frame`th frame.
+    pub fn from_frames(mut frame: u64, rate: FrameRate) -> Self {
+        if rate == FrameRate::Fps2997Drop {
+            // skips the frame numbers which are dropped
+            let blocks = frame / DROP_FRAMES_PER_10_MINUTES;
+            let rem = frame % DROP_FRAMES_PER_10_MINUTES;
+            let minutes = rem.saturating_sub(2) / DROP_FRAMES_PER_MINUTE;
+
+            frame += 18 * blocks + 2 * minutes;
+        }
+
+        let fps = rate.frames_per_second();
+        let secs = frame / fps;
+
+        Self {
+            hours: (secs / 3600 % 24) as u8,
+            minutes: (secs / 60 % 60) as u8,
+            seconds: (secs % 60) as u8,
+            frames: (frame % fps) as u8,
+        }
+    }
+
+    /// The nibble carried by `piece` of this time code's quarter frames.
+    const fn nibble(self, piece: u8, rate: FrameRate) -> u8 {
+        let value = match piece / 2 {
+            0 => self.frames,
+            1 => self.seconds,
+            2 => self.minutes,
+            // the top of the hours also carries the frame rate
+            _ => self.hours | (rate.code() << 5),
+        };
+
+        if piece % 2 == 0 {
+            value & 0x0F
+        }
+        else {
+            value >> 4
+        }
+    }
+}
+
+impl std::fmt::Display for Timecode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}:{:02}:{:02}",
+            self.hours, self.minutes, self.seconds, self.frames
+        )
+    }
+}
+
+/// Generates the MTC quarter frames for a timeline, as it moves forward.
+#[derive(Clone, Copy, Debug)]
+pub struct MTCGenerator {
+    rate: FrameRate,
+    /// The index of the next quarter frame to send, counted from the start
+    /// of the timeline, or `None` if the generator has been relocated.
+    next_quarter_frame: Option<u64>,
+}
+
+impl MTCGenerator {
+    pub const fn new(rate: FrameRate) -> Self {
+        Self { rate, next_quarter_frame: None }
+    }
+
+    pub const fn rate(&self) -> FrameRate {
+        self.rate
+    }
+
+    /// Starts a new sequence of quarter frames from the next position,
+    /// which should be called whenever the timeline jumps.
+    pub fn relocate(&mut self) {
+        self.next_quarter_frame = None;
+    }
+
+    /// Appends each quarter frame which is due by `secs` seconds along the
+    /// timeline to `messages`.
+    pub fn update(&mut self, secs: f64, messages: &mut Vec<MIDIMessage>) {
+        let due = (secs.max(0.0) * self.rate.fps() * 4.0).floor() as u64;
+
+        let mut next = match self.next_quarter_frame {
+            Some(next)
+                if next <= due + MAX_QUARTER_FRAME_DRIFT
+                    && due < next + MAX_QUARTER_FRAME_DRIFT =>
+            {
+                next
+            }
+            // sequences start on even frames, so the receiver knows which
+            // frame the time code is for
+            _ => due.div_ceil(NUM_PIECES) * NUM_PIECES,
+        };
+
+        while next <= due {
+            messages.push(self.quarter_frame(next));
+            next += 1;
+        }
+
+        self.next_quarter_frame = Some(next);
+    }
+
+    /// The `index`th quarter frame of the timeline, which carries a piece
+    /// of the time code of the frame at which its sequence started.
+    fn quarter_frame(&self, index: u64) -> MIDIMessage {
+        let piece = (index % NUM_PIECES) as u8;
+        let frame = (index / NUM_PIECES) * 2;
+        let timecode = Timecode::from_frames(frame, self.rate);
+
+        MIDIMessage::time_code_quarter_frame(
+            piece,
+            timecode.nibble(piece, self.rate),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reassembles the time code carried by a full sequence of quarter
+    /// frames, and its frame rate.
+    fn reassemble(messages: &[MIDIMessage]) -> (Timecode, u8) {
+        let mut nibbles = [0; NUM_PIECES as usize];
+
+        for msg in messages {
+            let [status, data, _] = msg.as_bytes();
+            assert_eq!(status, 0xF1);
+            nibbles[(data >> 4) as usize] = data & 0x0F;
+        }
+
+        let byte = |i: usize| nibbles[i] | (nibbles[i + 1] << 4);
+        let hours = byte(6);
+
+        let timecode = Timecode {
+            hours: hours & 0x1F,
+            minutes: byte(4),
+            seconds: byte(2),
+            frames: byte(0),
+        };
+
+        (timecode, hours >> 5)
+    }
+
+    #[test]
+    fn drop_frame_skips_frame_numbers() {
+        let rate = FrameRate::Fps2997Drop;
+        let tc = |frame| Timecode::from_frames(frame, rate).to_string();
+
+        assert_eq!(tc(1799), "00:00:59:29");
+        assert_eq!(tc(1800), "00:01:00:02");
+        // every 10th minute keeps its first frames
+        assert_eq!(tc(17982), "00:10:00:00");
+        assert_eq!(tc(17982 + 1800), "00:11:00:02");
+
+        // real time, rather than frame numbers
+        let hour = Timecode::from_secs(3600.0, rate);
+        assert_eq!(hour.to_string(), "01:00:00:00");
+
+        let rate = FrameRate::Fps25;
+        let tc = Timecode::from_secs(3723.5, rate);
+        assert_eq!(tc.to_string(), "01:02:03:12");
+    }
+
+    #[test]
+    fn quarter_frames_carry_the_time_code() {
+        let rate = FrameRate::Fps30;
+        let mut mtc = MTCGenerator::new(rate);
+        let mut messages = Vec::new();
+
+        // the sequence starts on the next even frame
+        let start = 3723.0 + 5.5 / rate.fps();
+        mtc.update(start, &mut messages);
+        assert!(messages.is_empty());
+
+        let quarter_frame = 1.0 / (rate.fps() * 4.0);
+        mtc.update(start + quarter_frame * 9.5, &mut messages);
+        assert_eq!(messages.len(), 8);
+
+        let (timecode, code) = reassemble(&messages);
+        assert_eq!(timecode.to_string(), "01:02:03:06");
+        assert_eq!(code, 3);
+
+        // the following sequence is two frames later
+        messages.clear();
+        mtc.update(start + quarter_frame * 17.5, &mut messages);
+
+        assert_eq!(messages.len(), 8);
+        assert_eq!(reassemble(&messages).0.to_string(), "01:02:03:08");
+    }
+
+    #[test]
+    fn jumps_start_a_new_sequence() {
+        let rate = FrameRate::Fps24;
+        let mut mtc = MTCGenerator::new(rate);
+        let mut messages = Vec::new();
+        let quarter_frame = 1.0 / (rate.fps() * 4.0);
+
+        for i in 0..192 {
+            mtc.update((i as f64 + 0.5) * quarter_frame, &mut messages);
+        }
+
+        assert_eq!(messages.len(), 192);
+
+        // jumping backwards restarts the pieces from the new position
+        messages.clear();
+        mtc.update(1.0, &mut messages);
+        mtc.update(1.0 + quarter_frame * 7.5, &mut messages);
+
+        let (timecode, code) = reassemble(&messages);
+        assert_eq!(messages.len(), 8);
+        assert_eq!(timecode.to_string(), "00:00:01:00");
+        assert_eq!(code, 0);
+
+        // as does falling far behind
+        messages.clear();
+        mtc.update(60.0, &mut messages);
+        mtc.update(60.0 + quarter_frame * 7.5, &mut messages);
+        assert_eq!(reassemble(&messages).0.to_string(), "00:01:00:00");
+    }
+}
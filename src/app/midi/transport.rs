@@ -0,0 +1,55 @@
+//! Outputs which MIDI bytes are sent through.
+
+use super::*;
+use midir::{MidiOutputConnection, MidiOutputPort};
+use std::error::Error;
+
+/// A means of sending MIDI bytes to a device.
+pub trait MIDITransport: Send {
+    /// Sends `bytes`, which hold one or more complete MIDI messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes could not be sent.
+    fn send(&mut self, bytes: &[u8]) -> Result<(), Box<dyn Error>>;
+
+    /// Services the transport, such as keeping a network session alive.
+    /// This should be called regularly, even when nothing is sent.
+    fn poll(&mut self) {}
+
+    /// The name of the port or peer which the transport sends to.
+    fn name(&self) -> &str;
+}
+
+// *** *** *** //
+
+/// Sends MIDI through a local MIDI port.
+pub struct MIDIPortTransport {
+    output: MidiOutputConnection,
+    port: MidiOutputPort,
+    name: String,
+}
+
+impl MIDIPortTransport {
+    pub fn new(
+        output: MidiOutputConnection,
+        port: MidiOutputPort,
+        name: String,
+    ) -> Self {
+        Self { output, port, name }
+    }
+
+    pub const fn port(&self) -> &MidiOutputPort {
+        &self.port
+    }
+}
+
+impl MIDITransport for MIDIPortTransport {
+    fn send(&mut self, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.output.send(bytes).map_err(Into::into)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
@@ -0,0 +1,178 @@
+//! Encoding of MIDI messages as Universal MIDI Packets (UMPs), as used by
+//! MIDI 2.0.
+//!
+//! Channel messages are sent as 64-bit MIDI 2.0 channel voice messages, so
+//! 32-bit CCs and per-note controllers keep their full resolution. 7- and
+//! 14-bit values are scaled up as described in the MIDI 2.0 translation
+//! specification, so that their minimum, center and maximum are preserved.
+
+use super::*;
+use message::MIDIMessage;
+
+/// The message type of 32-bit system common and real-time messages.
+const SYSTEM_MESSAGE_TYPE: u8 = 0x1;
+/// The message type of 64-bit MIDI 2.0 channel voice messages.
+const MIDI_2_CHANNEL_VOICE_MESSAGE_TYPE: u8 = 0x4;
+
+/// Appends `message` to `bytes` as a UMP in `group` (from `0` to `15`).
+/// Each 32-bit word is big-endian.
+pub fn encode(message: &MIDIMessage, group: u8, bytes: &mut Vec<u8>) {
+    let group = group & 0x0F;
+    let status = message.to_status_byte();
+
+    if let MIDIMessage::SongPositionPointer { .. }
+    | MIDIMessage::TimeCodeQuarterFrame { .. } = message
+    {
+        let [status, lsb, msb] = message.as_bytes();
+        bytes.extend_from_slice(&[
+            (SYSTEM_MESSAGE_TYPE << 4) | group,
+            status,
+            lsb,
+            msb,
+        ]);
+        return;
+    }
+
+    let (index, attribute, data) = match *message {
+        MIDIMessage::NoteOff { note, velocity, .. }
+        | MIDIMessage::NoteOn { note, velocity, .. } => {
+            // the velocity is the upper 16 bits, with no attribute
+            let velocity = scale_up(velocity as u32, 7, 16);
+            (note, 0, velocity << 16)
+        }
+        MIDIMessage::ControlChange { controller, value, .. } => {
+            (controller, 0, scale_up(value as u32, 7, 32))
+        }
+        MIDIMessage::ControlChange14Bit { controller, value, .. } => {
+            (controller, 0, scale_up(value as u32, 14, 32))
+        }
+        MIDIMessage::ControlChange32Bit { controller, value, .. } => {
+            (controller, 0, value)
+        }
+        MIDIMessage::PerNoteController { note, index, value, .. } => {
+            (note, index, value)
+        }
+        MIDIMessage::PolyPressure { note, pressure, .. } => {
+            (note, 0, scale_up(pressure as u32, 7, 32))
+        }
+        // the program is the upper byte, without a bank
+        MIDIMessage::ProgramChange { program, .. } => {
+            (0, 0, (program as u32) << 24)
+        }
+        MIDIMessage::SongPositionPointer { .. }
+        | MIDIMessage::TimeCodeQuarterFrame { .. } => unreachable!(),
+    };
+
+    bytes.extend_from_slice(&[
+        (MIDI_2_CHANNEL_VOICE_MESSAGE_TYPE << 4) | group,
+        status,
+        index,
+        attribute,
+    ]);
+    bytes.extend_from_slice(&data.to_be_bytes());
+}
+
+/// Scales `value` from `src_bits` to `dst_bits` of resolution, so that the
+/// minimum, center and maximum values map to each other.
+pub fn scale_up(value: u32, src_bits: u32, dst_bits: u32) -> u32 {
+    debug_assert!(0 < src_bits && src_bits < dst_bits && dst_bits <= 32);
+
+    let scale_bits = dst_bits - src_bits;
+    let mut scaled = value << scale_bits;
+
+    // values up to the center are simply shifted
+    if value <= 1 << (src_bits - 1) {
+        return scaled;
+    }
+
+    // values above the center repeat their lower bits, so that the maximum
+    // reaches the maximum
+    let repeat_bits = src_bits - 1;
+    let repeat_mask = (1 << repeat_bits) - 1;
+    let mut repeat = value & repeat_mask;
+
+    if scale_bits > repeat_bits {
+        repeat <<= scale_bits - repeat_bits;
+    }
+    else {
+        repeat >>= repeat_bits - scale_bits;
+    }
+
+    while repeat != 0 {
+        scaled |= repeat;
+        repeat >>= repeat_bits;
+    }
+
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_up_preserves_min_center_max() {
+        assert_eq!(scale_up(0, 7, 32), 0);
+        assert_eq!(scale_up(64, 7, 32), 0x8000_0000);
+        assert_eq!(scale_up(127, 7, 32), u32::MAX);
+
+        assert_eq!(scale_up(0, 7, 16), 0);
+        assert_eq!(scale_up(64, 7, 16), 0x8000);
+        assert_eq!(scale_up(127, 7, 16), 0xFFFF);
+
+        assert_eq!(scale_up(0x2000, 14, 32), 0x8000_0000);
+        assert_eq!(scale_up(0x3FFF, 14, 32), u32::MAX);
+    }
+
+    #[test]
+    fn test_encode_32_bit_cc() {
+        let mut bytes = Vec::new();
+        let msg = MIDIMessage::control_change_32_bit(74, 0x1234_5678, 2);
+        encode(&msg, 1, &mut bytes);
+
+        assert_eq!(bytes, [0x41, 0xB2, 74, 0, 0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(bytes.len(), msg.ump_size_bytes());
+    }
+
+    #[test]
+    fn test_encode_note_and_per_note_controller() {
+        let mut bytes = Vec::new();
+        encode(&MIDIMessage::note_on(60, 127, 0), 0, &mut bytes);
+        encode(
+            &MIDIMessage::per_note_controller(60, 3, u32::MAX, 0),
+            0,
+            &mut bytes,
+        );
+
+        assert_eq!(
+            bytes,
+            [
+                0x40, 0x90, 60, 0, 0xFF, 0xFF, 0, 0, //
+                0x40, 0x10, 60, 3, 0xFF, 0xFF, 0xFF, 0xFF,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_song_position() {
+        let mut bytes = Vec::new();
+        let msg = MIDIMessage::song_position_pointer(200);
+        encode(&msg, 0, &mut bytes);
+
+        assert_eq!(bytes, [0x10, 0xF2, 200 & 0x7F, 200 >> 7]);
+        assert_eq!(bytes.len(), msg.ump_size_bytes());
+    }
+
+    #[test]
+    fn test_midi_1_fallback() {
+        let msg = MIDIMessage::control_change_32_bit(5, u32::MAX, 0);
+        assert!(msg.is_14_bit());
+        assert_eq!(msg.as_bytes_double(), [0xB0, 37, 0x7F, 0xB0, 5, 0x7F]);
+
+        let msg = MIDIMessage::control_change_32_bit(40, u32::MAX, 0);
+        assert_eq!(msg.as_bytes(), [0xB0, 40, 0x7F]);
+
+        let msg = MIDIMessage::per_note_controller(60, 1, 0x8000_0000, 0);
+        assert_eq!(msg.as_bytes(), [0xA0, 60, 64]);
+    }
+}
@@ -7,6 +7,7 @@ use nannou_audio;
 
 pub mod args;
 pub mod audio;
+pub mod diagnostics;
 pub mod hands;
 pub mod keys;
 pub mod midi;
@@ -14,6 +15,7 @@ mod model;
 pub mod musical;
 pub mod osc;
 pub mod params;
+pub mod strings;
 pub mod update;
 pub mod view;
 
@@ -24,6 +26,19 @@ use update::update;
 
 /// Runs the app via Nannou.
 pub fn run_app() {
+    // a MIDI file or gesture recording is rendered offline instead, without
+    // any window or stream
+    if let Ok(args) = args::Arguments::from_env()
+        && (args.render_midi_path.is_some()
+            || args.render_gestures_path.is_some())
+    {
+        if let Err(e) = audio::offline::render(&args) {
+            eprintln!("failed to render: {e}");
+        }
+
+        return;
+    }
+
     nannou::app(model::Model::build)
         .loop_mode(RefreshSync)
         .update(update)
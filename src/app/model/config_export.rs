@@ -0,0 +1,52 @@
+//! Exporting the currently effective configuration back to config files.
+//!
+//! Runtime changes (such as recalibration, restored state or an edited
+//! mapping) are written to the files passed via `--tracking-config`,
+//! `--profiles` and `--mapping`, or to the default export paths if those
+//! weren't given. Files whose contents already
+//! match the current configuration are left untouched.
+
+use super::*;
+use serde_json::Value;
+
+/// Where the tracking config is exported to if `--tracking-config` wasn't
+/// given.
+pub const DEFAULT_TRACKING_EXPORT_PATH: &str = "./maestro_tracking.json";
+/// Where profiles are exported to if `--profiles` wasn't given.
+pub const DEFAULT_PROFILES_EXPORT_PATH: &str = "./maestro_profiles.json";
+/// Where an edited mapping is saved to if `--mapping` wasn't given a file.
+pub const DEFAULT_MAPPING_EXPORT_PATH: &str = "./maestro_mapping.json";
+
+/// What happened to a config file when it was exported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportOutcome {
+    /// The file was created or its contents changed.
+    Written,
+    /// The file already held the current configuration.
+    Unchanged,
+}
+
+/// Writes `value` to the file at `path`, unless the file already holds an
+/// equivalent JSON value.
+///
+/// # Errors
+///
+/// Returns an error if the file could not be written.
+pub fn export_json(path: &str, value: &Value) -> Result<ExportOutcome, String> {
+    // unreadable or invalid files are simply overwritten
+    let existing = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Value>(&s).ok());
+
+    if existing.as_ref() == Some(value) {
+        return Ok(ExportOutcome::Unchanged);
+    }
+
+    let contents =
+        serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+
+    std::fs::write(path, contents)
+        .map_err(|e| format!("failed to write \"{path}\": {e}"))?;
+
+    Ok(ExportOutcome::Written)
+}
@@ -1,8 +1,13 @@
 //! App constructors.
 
 use super::*;
-use crate::app::audio::audio_constructor::MAX_NUM_RESONATORS;
-use crate::dsp::ResoBankData;
+use crate::app::audio::audio_constructor::{
+    MAX_NUM_RESONATORS, NUM_AUDIO_BANDS, SPECTRUM_ANALYZER_BLOCK_SIZE,
+};
+use crate::dsp::dynamics::gate::DEFAULT_GATE_OFFSET_DB;
+use crate::dsp::{
+    spectral::analyzer::SPECTRUM_FLOOR_DB, GateThreshold, ResoBankData,
+};
 use std::sync::atomic::Ordering::Relaxed;
 use std::sync::mpsc;
 
@@ -14,6 +19,8 @@ pub fn build_window(app: &App, width: u32, height: u32) -> Id {
         .msaa_samples(1)
         .key_pressed(keys::key_pressed)
         .key_released(keys::key_released)
+        .mouse_pressed(keys::mouse_pressed)
+        .mouse_wheel(keys::mouse_wheel)
         .view(view)
         .title("Maestro")
         .build()
@@ -23,17 +30,30 @@ pub fn build_window(app: &App, width: u32, height: u32) -> Id {
 pub struct AudioSystem {
     pub(super) stream: Stream<AudioModel>,
     pub(super) sample_rate_ref: Arc<AtomicF64>,
+    pub(super) pitch_shift_ref: Arc<PitchShiftControl>,
+    pub(super) granular_ref: Arc<GranularControl>,
+    pub(super) pluck_ref: Arc<PluckControl>,
+    pub(super) modulation_ref: Arc<ModulationControl>,
+    pub(super) tilt_ref: Arc<TiltControl>,
     pub(super) senders: AudioMessageSenders,
     pub(super) callback_timer_ref: CallbackTimerRef,
     pub(super) note_handler: NoteHandlerRef,
     pub(super) voice_event_sender: mpsc::Sender<VoiceEvent>,
     pub(super) spectral_mask: triple_buffer::Input<SpectralMask>,
     pub(super) reso_bank_data: triple_buffer::Input<ResoBankData>,
+    pub(super) spectrum: triple_buffer::Output<Vec<f64>>,
+    pub(super) band_energies: triple_buffer::Output<Vec<f64>>,
+    pub(super) dsp_load: triple_buffer::Output<DspLoad>,
+    pub(super) input_stream: Option<Stream<InputCapture>>,
+    pub(super) input_control: Arc<AudioInputControl>,
+    pub(super) feedback_control: Option<Arc<FeedbackControl>>,
+    pub(super) true_peak_control: Arc<TruePeakControl>,
+    pub(super) true_peak_incidents: Receiver<TruePeakIncident>,
 }
 
 /// Builds the audio stream, audio message channel senders, and input note
 /// handler.
-pub fn build_audio_system() -> AudioSystem {
+pub fn build_audio_system(args: &args::Arguments) -> AudioSystem {
     set_sample_rate();
 
     // setup audio structs
@@ -50,17 +70,79 @@ pub fn build_audio_system() -> AudioSystem {
         ))
         .split();
 
+    let (spectrum_input, spectrum) = triple_buffer::TripleBuffer::new(&vec![
+        SPECTRUM_FLOOR_DB;
+        SPECTRUM_ANALYZER_BLOCK_SIZE / 2 + 1
+    ])
+    .split();
+
+    let (band_energy_input, band_energies) =
+        triple_buffer::TripleBuffer::new(&vec![0.0; NUM_AUDIO_BANDS]).split();
+
+    let (dsp_load_input, dsp_load) =
+        triple_buffer::TripleBuffer::new(&DspLoad::default()).split();
+
     let (voice_event_sender, voice_event_receiver) = mpsc::channel();
-    let (note_channel_sender, note_channel_receiver) = mpsc::channel();
+
+    let input_control = Arc::new(AudioInputControl::default());
+    input_control.enabled.sr(args.audio_input);
+    input_control.gain_db.sr(args.input_gain_db);
+    input_control.gate_enabled.sr(args.input_gate.is_some());
+
+    let (input_capture, audio_input) = if args.audio_input {
+        let (capture, input) = AudioInput::new(
+            Arc::clone(&input_control),
+            args.input_gate.unwrap_or(GateThreshold::Learned {
+                offset_db: DEFAULT_GATE_OFFSET_DB,
+            }),
+            unsafe { SAMPLE_RATE },
+        );
+        (Some(capture), Some(input))
+    }
+    else {
+        (None, None)
+    };
+
+    let feedback_control = args
+        .feedback_suppressor
+        .then(|| Arc::new(FeedbackControl::default()));
+    let feedback = feedback_control.as_ref().map(|control| {
+        FeedbackSuppression::new(Arc::clone(control), unsafe { SAMPLE_RATE })
+    });
+
+    let true_peak_control =
+        Arc::new(TruePeakControl::new(args.true_peak_threshold_db));
+    let (true_peak, true_peak_incidents) = TruePeakMeter::new(
+        Arc::clone(&true_peak_control),
+        unsafe { SAMPLE_RATE },
+    );
+
+    // failing to record stems isn't fatal
+    let stems = args.stems_path.as_ref().and_then(|path| {
+        let bformat_path = args.bformat_path.as_deref();
+
+        StemTaps::record(path, bformat_path, unsafe { SAMPLE_RATE })
+            .map_err(|e| eprintln!("failed to record stems to \"{path}\": {e}"))
+            .ok()
+    });
 
     // build the audio context
     let audio_context = AudioContext {
-        note_channel_receiver,
-        sample_rate: unsafe { SAMPLE_RATE },
         spectral_mask_output: Some(spectral_mask_output),
         reso_bank_data_output: Some(reso_bank_data_output),
-        voice_event_sender: voice_event_sender.clone(),
-        voice_event_receiver: Some(voice_event_receiver),
+        spectrum_input: Some(spectrum_input),
+        band_energy_input: Some(band_energy_input),
+        dsp_load_input: Some(dsp_load_input),
+        audio_input,
+        feedback,
+        true_peak: Some(true_peak),
+        stems,
+        ..AudioContext::from_args(
+            args,
+            unsafe { SAMPLE_RATE },
+            voice_event_sender.clone(),
+            voice_event_receiver,
+        )
     };
 
     // setup audio stream
@@ -70,12 +152,17 @@ pub fn build_audio_system() -> AudioSystem {
         model: audio_model,
         callback_timer_ref,
         sample_rate_ref,
+        pitch_shift_ref,
+        granular_ref,
+        pluck_ref,
+        modulation_ref,
+        tilt_ref,
         message_channels: senders,
     } = audio_constructor::build_audio_model(audio_context);
 
     let stream = audio_host
         .new_output_stream(audio_model)
-        .render(audio::process)
+        .render(audio::render_stream)
         .channels(NUM_CHANNELS)
         .sample_rate(sample_rate_ref.load(Relaxed) as u32)
         .frames_per_buffer(BUFFER_SIZE)
@@ -84,16 +171,48 @@ pub fn build_audio_system() -> AudioSystem {
 
     stream.play().unwrap();
 
+    // the input is optional, so failing to open it isn't fatal
+    let input_stream = input_capture.and_then(|capture| {
+        let result = audio_host
+            .new_input_stream(capture)
+            .capture(audio::input::capture)
+            .sample_rate(sample_rate_ref.load(Relaxed) as u32)
+            .frames_per_buffer(BUFFER_SIZE)
+            .build()
+            .map_err(|e| e.to_string())
+            .and_then(|stream| {
+                stream.play().map_err(|e| e.to_string())?;
+                Ok(stream)
+            });
+
+        result
+            .map_err(|e| eprintln!("failed to open audio input: {e}"))
+            .ok()
+    });
+
     // construct audio system
     AudioSystem {
         stream,
         sample_rate_ref,
+        pitch_shift_ref,
+        granular_ref,
+        pluck_ref,
+        modulation_ref,
+        tilt_ref,
         senders,
         callback_timer_ref,
         note_handler,
         voice_event_sender,
         spectral_mask,
         reso_bank_data,
+        spectrum,
+        band_energies,
+        dsp_load,
+        input_stream,
+        input_control,
+        feedback_control,
+        true_peak_control,
+        true_peak_incidents,
     }
 }
 
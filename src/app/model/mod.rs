@@ -2,10 +2,22 @@
 
 use super::audio::audio_constructor;
 use super::audio::*;
-use super::view::view;
+use super::view::{
+    attract::AttractLoop,
+    palette::{Palette, PaletteLayer},
+    post_fx::PostFxChain,
+    pulse::BeatPulse,
+    ticker::Ticker,
+    tutorial::Tutorial,
+    view, StatusCheck,
+};
 use super::*;
 use crate::app::midi::MAX_NOTE_VELOCITY;
+use crate::app::diagnostics::{
+    ConfigPaths, ConfigValidator, Diagnostic, Severity,
+};
 use crate::app::params::*;
+use crate::app::strings::StringTable;
 use crate::dsp::{
     BiquadFilter, BiquadParams, Filter, FilterType, ResoBankData,
     ResonatorBankParams, SpectralMask, BUTTERWORTH_Q,
@@ -13,29 +25,72 @@ use crate::dsp::{
 use crate::prelude::interp::linear_unclamped;
 use atomic::Atomic;
 use crossbeam_channel::{unbounded, Receiver, Sender};
+use hands::gestures::{GestureEvent, GestureEvents};
 use hands::hand_types::RawHandPairCOM;
+use hands::address_map::OSCAddressMap;
+use hands::handshake::TRACKER_RESTART_OSC_ADDRESS;
+use hands::mask_painter::SpectralMaskPainter;
+use hands::quality::TRACKER_RESTART_COOLDOWN;
 use hands::HandManager;
 use midi::message::MIDIMessage;
+use midi::rtp::RTPMIDISession;
 use midi::sender::{MIDISender, MIDISenderTimedThread};
 use nannou::draw::mesh::Colors;
 use nannou::prelude::WindowId as Id;
 use nannou_audio::Stream;
-use osc::EMERequestOSCSender;
+use nannou_osc::rosc::OscPacket;
+use nannou_osc::Type as OSCType;
+use osc::{
+    broadcast::OSCStateBroadcaster,
+    heartbeat::ConnectionHealth,
+    redundancy::{RedundancyEvent, RedundancyLink, ReplicatedState},
+    transport::OSCTransport,
+    EMERequestOSCSender,
+};
 use std::f64::consts::SQRT_2;
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     sync::{mpsc, Arc, Mutex, RwLock},
-    time::Instant,
+    time::{Duration, Instant},
 };
 use timer::TimerThread;
 use triple_buffer::triple_buffer;
 
+mod config_export;
 mod constructors;
+mod saved_state;
+mod wind_down;
 use constructors::*;
+pub use config_export::{
+    export_json, ExportOutcome, DEFAULT_MAPPING_EXPORT_PATH,
+    DEFAULT_PROFILES_EXPORT_PATH, DEFAULT_TRACKING_EXPORT_PATH,
+};
+pub use saved_state::{SavedState, StateSettings, DEFAULT_STATE_PATH};
+use wind_down::{
+    WindDown, WindDownStage, IDLE_HUM_GAIN_DB, WAKE_FADE_SECS,
+    WIND_DOWN_FADE_SECS,
+};
 
 type CallbackTimerRef = Arc<Mutex<Instant>>;
 
+/// The number of config diagnostics which are drawn in the window. All of
+/// them are logged.
+const MAX_DRAWN_CONFIG_DIAGNOSTICS: usize = 4;
+
+/// The number of true-peak incidents which are kept. All of them are logged.
+const TRUE_PEAK_LOG_LEN: usize = 32;
+
+/// How long the audio callback may go uncalled before the audio device is
+/// shown as failing, in seconds.
+const AUDIO_CALLBACK_TIMEOUT_SECS: f64 = 0.5;
+
+/// The distance from the top of the window to the mapping editor's first
+/// route.
+const MAPPING_EDITOR_TOP: f32 = 100.0;
+/// The height of each of the mapping editor's routes.
+const MAPPING_EDITOR_LINE_HEIGHT: f32 = 20.0;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MIDISendMode {
     MIDIControlChange,
@@ -63,6 +118,35 @@ pub struct Model {
 
     /// Input to the spectral mask channel, sent to the audio thread.
     pub spectral_mask: triple_buffer::Input<SpectralMask>,
+    /// Paints the spectral mask with the hands.
+    mask_painter: SpectralMaskPainter,
+    /// Controls the audio thread's pitch shifter.
+    pitch_shift: Arc<PitchShiftControl>,
+    /// The axis of the first hand which controls the pitch shift, if any,
+    /// and the largest shift in semitones.
+    pitch_axis: Option<(usize, f64)>,
+    /// The output spectrum, analyzed on the audio thread.
+    spectrum: triple_buffer::Output<Vec<f64>>,
+    /// The latest output spectrum, in decibels per bin.
+    spectrum_levels: Vec<f64>,
+    /// The DSP load of the audio callback, timed on the audio thread.
+    dsp_load: triple_buffer::Output<DspLoad>,
+    /// The latest DSP load, and that of each stage.
+    latest_dsp_load: DspLoad,
+    /// The audio input stream, if it is enabled and was opened.
+    audio_input_stream: Option<nannou_audio::Stream<InputCapture>>,
+    /// Controls the audio thread's input gain, and holds its meter.
+    input_control: Arc<AudioInputControl>,
+    /// Holds the audio thread's feedback notches, if the suppressor is
+    /// enabled.
+    feedback_control: Option<Arc<FeedbackControl>>,
+    /// Holds the audio thread's true-peak meter of the output.
+    true_peak_control: Arc<TruePeakControl>,
+    true_peak_incidents: Receiver<TruePeakIncident>,
+    /// The latest times the output went over the true-peak threshold.
+    true_peak_log: VecDeque<TruePeakIncident>,
+    /// The number of incidents since the meter was last reset.
+    true_peak_overs: usize,
 
     /// Channel to send voice events (such as killing all voices).
     pub voice_event_sender: mpsc::Sender<VoiceEvent>,
@@ -89,16 +173,71 @@ pub struct Model {
     pub midi_send_channel: u8,
     pub show_state_data: bool,
 
-    rx_tx_ports: (u16, u16),
+    /// The OSC receive and send addresses, as `host:port` strings.
+    osc_addrs: (String, String),
 
     midi_timed_thread: MIDISenderTimedThread,
 
     eme_osc_sender: EMERequestOSCSender,
+    /// Publishes the app's state to external visualizers, if enabled.
+    state_broadcaster: Option<OSCStateBroadcaster>,
     params: ParameterHandler,
 
     gesture_input: triple_buffer::Input<RawHandPairCOM>,
 
     is_sending: bool,
+    metronome_enabled: bool,
+
+    bpm: f64,
+    tap_tempo: TapTempo,
+    /// The active calibration wizard, if calibration is in progress.
+    calibration: Option<CalibrationWizard>,
+    /// The mapping editor, if the mapping is being edited.
+    mapping_editor: Option<MappingEditor>,
+    profiles: ProfileStore,
+    /// The master EQ preset of each room.
+    rooms: RoomPresetStore,
+    /// The text shown on screen, in the installation's language.
+    strings: StringTable,
+    /// The visuals' colors, which follow the hands and the mode.
+    palette: Palette,
+    /// Applies post effects to the visuals before they are presented.
+    post_fx: PostFxChain,
+    /// Pulses the visuals with the beat and the output's low end.
+    pulse: BeatPulse,
+    /// Messages from curators, which are set over OSC.
+    ticker: Ticker,
+    /// Shows the gestures when nobody is playing, if it is enabled.
+    attract: Option<AttractLoop>,
+    /// The gestures which the hands completed during the last update.
+    gestures: GestureEvents,
+    /// Walks new visitors through the gestures.
+    tutorial: Tutorial,
+    /// The tutorial's reward chime, and when it is released.
+    reward_release: Option<(f64, Instant)>,
+    /// Where the app's state is saved to and loaded from.
+    state_path: String,
+
+    /// The config files which were loaded at startup.
+    config_paths: ConfigPaths,
+    config_validator: ConfigValidator,
+    /// Problems found in the configs, with the most serious first.
+    config_diagnostics: Vec<Diagnostic>,
+
+    /// Sends restart requests to the hand tracker, if enabled.
+    tracker_control: Option<Box<dyn OSCTransport>>,
+    /// When a restart request was last sent to the hand tracker.
+    last_restart_request: Option<Instant>,
+    /// The link to the other instance, if two run side by side. Only the
+    /// active instance sends MIDI and OSC.
+    redundancy: Option<RedundancyLink>,
+
+    /// Winds the app down after a long time without interaction, if
+    /// enabled.
+    wind_down: Option<WindDown>,
+    /// Whether sending was stopped by winding down, and so continues on
+    /// waking.
+    resume_on_wake: bool,
 
     debug_mode: bool,
 }
@@ -111,16 +250,39 @@ impl Model {
     /// Panics if a new window cannot be initialized.
     #[allow(clippy::too_many_lines)]
     pub fn build(app: &App) -> Self {
+        let args = args::Arguments::from_env();
+        if let Err(e) = &args {
+            panic!("failed to obtain arguments: {e}");
+        }
+        let mut args = args.unwrap();
+
+        if let Some(timeout) = args.discovery_timeout {
+            osc::discovery::discover_endpoints(&mut args, timeout);
+        }
+
         let AudioSystem {
             stream: audio_stream,
             sample_rate_ref,
+            pitch_shift_ref,
+            granular_ref,
+            pluck_ref,
+            modulation_ref,
+            tilt_ref,
             senders: audio_senders,
             callback_timer_ref: audio_callback_timer,
             note_handler,
             voice_event_sender,
             spectral_mask,
             reso_bank_data,
-        } = build_audio_system();
+            spectrum,
+            band_energies,
+            dsp_load,
+            input_stream: audio_input_stream,
+            input_control,
+            feedback_control,
+            true_peak_control,
+            true_peak_incidents,
+        } = build_audio_system(&args);
 
         let (_w, _h) = (WINDOW_SIZE.x as f32, WINDOW_SIZE.y as f32);
 
@@ -132,29 +294,171 @@ impl Model {
 
         // *** *** *** //
 
-        let args = args::Arguments::from_env();
-        if let Err(e) = &args {
-            panic!("failed to obtain arguments: {e}");
-        }
-        let args = args.unwrap();
-
         let (gesture_input, gesture_output) =
             triple_buffer(&RawHandPairCOM::default());
 
-        let (param_handler, param_receivers) =
-            ParameterHandler::new(gesture_output, &args);
+        // the position and density are set by the parameter updater
+        granular_ref.size_ms.sr(args.grain_size_ms);
+        granular_ref.pitch_spray.sr(args.grain_pitch_spray);
+
+        let audio_controls = AudioControls {
+            granular: granular_ref,
+            pluck: pluck_ref,
+            modulation: modulation_ref,
+            tilt: tilt_ref,
+        };
+
+        let (param_handler, param_receivers) = ParameterHandler::new(
+            gesture_output, band_energies, audio_controls, &args,
+        );
 
         let (eme_sender, osc_receiver) = osc::create_osc_sender_and_receiver(
-            &args, param_receivers.eme_receiver,
+            &args,
+            param_receivers.eme_receiver,
+            param_receivers.eme_event_sender,
         )
         .expect("failed to create eme sender & osc receiver");
 
+        let state_broadcaster =
+            args.state_osc_destination.as_ref().map(|(host, port)| {
+                let addr = osc::resolve_addr(host, *port)
+                    .expect("failed to resolve state OSC address");
+                let mut broadcaster = OSCStateBroadcaster::new(
+                    addr,
+                    args.osc_transport,
+                    param_receivers.state_frames,
+                )
+                .expect("failed to create state OSC broadcaster");
+
+                broadcaster.start();
+                println!("publishing state over OSC to {}", broadcaster.addr());
+
+                broadcaster
+            });
+
+        let tracker_control =
+            args.tracker_restart_destination.as_ref().map(|(host, port)| {
+                let addr = osc::resolve_addr(host, *port)
+                    .expect("failed to resolve tracker restart OSC address");
+
+                args.osc_transport
+                    .connect(addr)
+                    .expect("failed to create tracker restart OSC sender")
+            });
+
+        let redundancy = args.redundancy_peer.as_ref().map(|(host, port)| {
+            let peer = osc::resolve_addr(host, *port)
+                .expect("failed to resolve redundancy peer address");
+            let link = RedundancyLink::new(
+                args.redundancy_role,
+                args.redundancy_port,
+                peer,
+            )
+            .expect("failed to bind redundancy port");
+
+            println!(
+                "running as the {} instance alongside {peer}",
+                link.role()
+            );
+
+            link
+        });
+
+        let osc_address_map = args
+            .osc_map_path
+            .as_deref()
+            .map_or_else(|| Ok(OSCAddressMap::default()), OSCAddressMap::from_file)
+            .unwrap_or_else(|e| {
+                eprintln!("failed to load OSC address map: {e}");
+                OSCAddressMap::default()
+            });
+
+        // started before the configs are loaded, as it reads them itself
+        let config_paths = ConfigPaths::from_args(&args);
+        let config_validator = ConfigValidator::spawn(config_paths.clone());
+
+        let mut profiles = args
+            .profiles_path
+            .as_deref()
+            .map_or_else(|| Ok(ProfileStore::new()), ProfileStore::from_file)
+            .unwrap_or_else(|e| {
+                // NOTE(jamie): the fallback store is never saved, so the
+                // invalid file isn't overwritten.
+                eprintln!("failed to load profiles: {e}");
+                ProfileStore::new()
+            });
+
+        if let Some(name) = &args.profile_name {
+            profiles.select_or_create(name);
+        }
+
+        param_handler.apply_profile(profiles.active());
+
+        let mut rooms = args
+            .rooms_path
+            .as_deref()
+            .map_or_else(
+                || Ok(RoomPresetStore::new()),
+                RoomPresetStore::from_file,
+            )
+            .unwrap_or_else(|e| {
+                eprintln!("failed to load rooms: {e}");
+                RoomPresetStore::new()
+            });
+
+        if let Some(name) = &args.room_name
+            && let Err(e) = rooms.select(name)
+        {
+            eprintln!("failed to select room: {e}");
+        }
+
+        let strings = args
+            .strings_path
+            .as_deref()
+            .map_or_else(|| Ok(StringTable::new()), StringTable::from_file)
+            .unwrap_or_else(|e| {
+                eprintln!("failed to load strings: {e}");
+                StringTable::new()
+            });
+
+        let palette = args
+            .palette_path
+            .as_deref()
+            .map_or_else(|| Ok(Palette::new()), Palette::from_file)
+            .unwrap_or_else(|e| {
+                eprintln!("failed to load palette: {e}");
+                Palette::new()
+            });
+
+        let msg = FXMessage::SetEqBands(rooms.active_bands());
+        if let Err(e) = audio_senders.fx.try_send(msg) {
+            eprintln!("failed to send effect message: {e}");
+        }
+
         // *** *** *** //
 
-        let midi_timed_thread = MIDISenderTimedThread::new(
-            "maestro_timed_midi", "maestro", param_receivers.midi_receiver,
-        )
-        .expect("failed to create timed MIDI sender thread");
+        let midi_timed_thread = if let Some((host, port)) = &args.rtp_midi_peer
+        {
+            let addr = osc::resolve_addr(host, *port)
+                .expect("failed to resolve RTP-MIDI peer address");
+            let session = RTPMIDISession::connect("maestro", addr)
+                .expect("failed to create RTP-MIDI session");
+
+            println!("sending MIDI to RTP-MIDI peer {addr}");
+
+            MIDISenderTimedThread::with_sender(
+                MIDISender::with_transport(Box::new(session)),
+                param_receivers.midi_receiver,
+            )
+        }
+        else {
+            MIDISenderTimedThread::new(
+                "maestro_timed_midi", "maestro", param_receivers.midi_receiver,
+            )
+            .expect("failed to create timed MIDI sender thread")
+        };
+        midi_timed_thread.set_running_status(args.midi_running_status);
+        midi_timed_thread.set_protocol(args.midi_protocol);
 
         // *** *** *** //
 
@@ -174,9 +478,26 @@ impl Model {
 
             voice_event_sender,
 
-            hand_manager: HandManager::new(osc_receiver),
+            hand_manager: HandManager::new(osc_receiver, osc_address_map),
 
             spectral_mask,
+            mask_painter: SpectralMaskPainter::new(
+                audio_constructor::DEFAULT_SPECTRAL_BLOCK_SIZE / 2,
+                args.mask_paint,
+            ),
+            pitch_shift: pitch_shift_ref,
+            pitch_axis: args.pitch_axis.map(|axis| (axis, args.pitch_range)),
+            spectrum_levels: Vec::new(),
+            spectrum,
+            dsp_load,
+            latest_dsp_load: DspLoad::default(),
+            audio_input_stream,
+            input_control,
+            feedback_control,
+            true_peak_control,
+            true_peak_incidents,
+            true_peak_log: VecDeque::with_capacity(TRUE_PEAK_LOG_LEN),
+            true_peak_overs: 0,
 
             midi_sender: MIDISender::new_with_port_containing(
                 "maestro_test_midi", "maestro",
@@ -185,7 +506,14 @@ impl Model {
 
             sample_rate_ref,
 
-            rx_tx_ports: (args.osc_rx_port, args.osc_tx_port),
+            osc_addrs: (
+                format!("{}:{}", args.osc_rx_host, args.osc_rx_port),
+                args.osc_tx_destinations
+                    .iter()
+                    .map(|(host, port)| format!("{host}:{port}"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
 
             midi_send_mode: MIDISendMode::MIDIControlChange,
             midi_send_value: 0,
@@ -195,14 +523,56 @@ impl Model {
             midi_timed_thread,
 
             eme_osc_sender: eme_sender,
+            state_broadcaster,
             params: param_handler,
             gesture_input,
 
             is_sending: false,
+            metronome_enabled: false,
+
+            bpm: DEFAULT_BPM,
+            tap_tempo: TapTempo::new(),
+            calibration: None,
+            mapping_editor: None,
+            profiles,
+            rooms,
+            strings,
+            palette,
+            post_fx: PostFxChain::new(),
+            pulse: BeatPulse::new(args.pulse_intensity),
+            ticker: Ticker::new(),
+            attract: args.attract_delay.map(AttractLoop::new),
+            gestures: GestureEvents::new(),
+            tutorial: Tutorial::new(),
+            reward_release: None,
+            state_path: args
+                .state_path
+                .clone()
+                .unwrap_or_else(|| String::from(DEFAULT_STATE_PATH)),
+
+            config_paths,
+            config_validator,
+            config_diagnostics: Vec::new(),
+
+            tracker_control,
+            last_restart_request: None,
+            redundancy,
+
+            wind_down: args.wind_down_minutes.map(WindDown::new),
+            resume_on_wake: false,
 
             debug_mode: args.debug,
         };
 
+        let pitch_shift = &result.pitch_shift;
+        pitch_shift.enabled.sr(args.pitch_axis.is_some());
+        pitch_shift.preserve_formants.sr(args.preserve_formants);
+        pitch_shift.block_size.sr(args.pitch_block_size);
+
+        if args.resume {
+            result.load_state();
+        }
+
         if args.auto_start_send {
             result.send_and_update(true);
         }
@@ -227,6 +597,10 @@ impl Model {
     }
 
     pub fn send_midi(&mut self) {
+        if !self.is_output_active() {
+            return;
+        }
+
         let mut is_note = false;
 
         let msg = match self.midi_send_mode {
@@ -264,18 +638,218 @@ impl Model {
     }
 
     pub fn format_state(&self) -> String {
-        format!(
-            "OSC/MIDI are {}\nBound to OSC ports #{} (receive) and #{} (send)\nBound to MIDI port \"{}\"",
-            if self.is_sending {
-                "active (press 'S' to stop)" 
-            } 
-            else {
-                "inactive (press 'T' to start)" 
+        let strings = &self.strings;
+
+        let mut lines = vec![String::from(if self.is_sending {
+            strings.get("state.sending_active")
+        }
+        else {
+            strings.get("state.sending_inactive")
+        })];
+
+        // before the transport starts, the connections are on the status
+        // screen instead
+        if self.is_sending {
+            lines.push(strings.format(
+                "state.osc_bound",
+                &[
+                    ("receive", &self.osc_addrs.0),
+                    ("send", &self.format_osc_destinations()),
+                ],
+            ));
+            lines.push(strings.format(
+                "state.midi_bound",
+                &[("port", &self.midi_sender.bound_port_name())],
+            ));
+        }
+
+        lines.push(String::from(if self.metronome_enabled {
+            strings.get("state.metronome_on")
+        }
+        else {
+            strings.get("state.metronome_off")
+        }));
+        lines.push(
+            strings
+                .format("state.tempo", &[("bpm", &format!("{:.1}", self.bpm))]),
+        );
+
+        if self.is_sending {
+            lines.push(strings.format(
+                "state.tracker",
+                &[("health", &self.format_osc_health())],
+            ));
+        }
+
+        lines.push(strings.format(
+            "state.profile",
+            &[("name", &self.profiles.active().name)],
+        ));
+        lines.push(
+            strings
+                .format("state.room", &[("name", &self.rooms.active_name())]),
+        );
+        lines.push(strings.format(
+            "state.true_peak_threshold",
+            &[(
+                "threshold",
+                &format!("{:+.1}", self.true_peak_control.threshold_db.lr()),
+            )],
+        ));
+        lines.push(strings.format(
+            "state.pulse",
+            &[("percent", &(self.pulse.intensity() * 100.0).round())],
+        ));
+
+        lines.join("\n")
+    }
+
+    /// Whether OSC and MIDI are being sent, i.e. the transport has started.
+    pub const fn is_sending(&self) -> bool {
+        self.is_sending
+    }
+
+    /// The text shown on screen, in the installation's language.
+    pub const fn strings(&self) -> &StringTable {
+        &self.strings
+    }
+
+    /// The visuals' colors.
+    pub const fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub const fn post_fx(&self) -> &PostFxChain {
+        &self.post_fx
+    }
+
+    /// The visuals' beat and audio pulses.
+    pub const fn pulse(&self) -> &BeatPulse {
+        &self.pulse
+    }
+
+    /// The instructional animation, if it is enabled.
+    pub const fn attract(&self) -> Option<&AttractLoop> {
+        self.attract.as_ref()
+    }
+
+    /// Checks each of the subsystems which the transport relies on, for the
+    /// status screen.
+    pub fn status_checks(&self) -> Vec<StatusCheck> {
+        let strings = &self.strings;
+        let midi_port = self.midi_timed_thread.bound_port_name();
+        let num_failing = self
+            .eme_osc_sender
+            .destinations()
+            .iter()
+            .filter(|dest| dest.is_failing())
+            .count();
+        let callback_age = self
+            .audio_callback_timer
+            .lock()
+            .map_or(f64::INFINITY, |timer| timer.elapsed().as_secs_f64());
+        let incompatibility = self.hand_manager.tracker_incompatibility();
+
+        vec![
+            StatusCheck {
+                name: String::from(strings.get("status.midi_port")),
+                is_ok: midi_port.is_some(),
+                detail: midi_port.unwrap_or_else(|| {
+                    String::from(strings.get("status.midi_unavailable"))
+                }),
             },
-            self.rx_tx_ports.0,
-            self.rx_tx_ports.1,
-            self.midi_sender.bound_port_name(),
-        )
+            StatusCheck {
+                name: String::from(strings.get("status.osc_bind")),
+                is_ok: num_failing == 0,
+                detail: strings.format(
+                    "status.osc_detail",
+                    &[
+                        ("receive", &self.osc_addrs.0),
+                        ("send", &self.format_osc_destinations()),
+                    ],
+                ),
+            },
+            StatusCheck {
+                name: String::from(strings.get("status.audio_device")),
+                is_ok: callback_age < AUDIO_CALLBACK_TIMEOUT_SECS,
+                detail: if callback_age < AUDIO_CALLBACK_TIMEOUT_SECS {
+                    strings.format(
+                        "status.audio_detail",
+                        &[
+                            (
+                                "sample_rate",
+                                &format!("{:.0}", self.sample_rate_ref.lr()),
+                            ),
+                            ("buffer_size", &BUFFER_SIZE),
+                        ],
+                    )
+                }
+                else {
+                    strings.format(
+                        "status.audio_no_callback",
+                        &[("secs", &format!("{callback_age:.1}"))],
+                    )
+                },
+            },
+            StatusCheck {
+                name: String::from(strings.get("status.tracker")),
+                is_ok: self.osc_health() == ConnectionHealth::Connected
+                    && incompatibility.is_none(),
+                detail: incompatibility.map_or_else(
+                    || self.format_osc_health(),
+                    ToString::to_string,
+                ),
+            },
+        ]
+    }
+
+    /// The health of the connection to the hand tracker.
+    pub fn osc_health(&self) -> ConnectionHealth {
+        self.hand_manager.connection_health()
+    }
+
+    fn format_osc_destinations(&self) -> String {
+        let num_failing = self
+            .eme_osc_sender
+            .destinations()
+            .iter()
+            .filter(|dest| dest.is_failing())
+            .count();
+
+        if num_failing == 0 {
+            self.osc_addrs.1.clone()
+        }
+        else {
+            self.strings.format(
+                "state.osc_failing",
+                &[
+                    ("destinations", &self.osc_addrs.1),
+                    ("num_failing", &num_failing),
+                ],
+            )
+        }
+    }
+
+    fn format_osc_health(&self) -> String {
+        match self.hand_manager.last_packet_age() {
+            Some(age) if self.osc_health() != ConnectionHealth::Connected => {
+                self.strings.format(
+                    "state.tracker_last_packet",
+                    &[
+                        ("health", &self.osc_health()),
+                        ("age", &format!("{age:.1}")),
+                    ],
+                )
+            }
+            _ if self.osc_health() == ConnectionHealth::Connected => {
+                format!(
+                    "{} ({})",
+                    self.osc_health(),
+                    self.hand_manager.quality().metrics()
+                )
+            }
+            _ => self.osc_health().to_string(),
+        }
     }
 
     pub fn format_midi_ping(&self) -> String {
@@ -288,11 +862,17 @@ impl Model {
                     self.midi_send_value
                 )
                 .map_or_else(
-                    || String::from("no attachment"),
+                    || {
+                        String::from(
+                            self.strings.get("state.midi_ping_unattached"),
+                        )
+                    },
                     |s| {
                         format!("\"{s}{}\"", 
                             if self.params.is_14_bit(self.midi_send_channel,
-                                self.midi_send_value) { " (14-bit)" } 
+                                self.midi_send_value) {
+                                self.strings.get("state.midi_ping_14_bit")
+                            } 
                             else { "" }
                         )
                     },
@@ -302,51 +882,1504 @@ impl Model {
             String::new()
         };
 
-        format!(
-            "Ready to ping {send_mode} #{} to channel {} ({cc_label})",
-            self.midi_send_value,
-            self.midi_send_channel + 1,
+        self.strings.format(
+            "state.midi_ping",
+            &[
+                ("mode", &send_mode),
+                ("value", &self.midi_send_value),
+                ("channel", &(self.midi_send_channel + 1)),
+                ("label", &cc_label),
+            ],
         )
     }
 
-    pub fn send_and_update(&mut self, send_update: bool) {
-        // we don't need to clear the request channel as the queue size is
-        // greater than 1
-        // self.eme_osc_sender.clear_request_channel();
+    /// Toggles the metronome on or off.
+    pub fn toggle_metronome(&mut self) {
+        self.metronome_enabled = !self.metronome_enabled;
+        self.send_metronome_message(MetronomeMessage::Enable(
+            self.metronome_enabled,
+        ));
+    }
 
-        if send_update {
-            self.params.reset_updater();
-            self.params.start_update();
+    /// Plays `num_bars` bars of metronome clicks, starting immediately.
+    pub fn count_in(&mut self, num_bars: u32) {
+        self.send_metronome_message(MetronomeMessage::CountIn(num_bars));
+    }
 
-            self.hand_manager.start_update();
-            self.eme_osc_sender.start_send();
-            self.midi_timed_thread.start_send();
+    /// Registers a tap for tap-tempo, and sets the tempo once enough taps
+    /// have been received.
+    pub fn tap_tempo(&mut self) {
+        if let Some(bpm) = self.tap_tempo.tap() {
+            self.set_bpm(bpm);
         }
-        else {
-            self.params.stop_update();
+    }
+
+    /// Starts the reach calibration wizard, or cancels it if it is already
+    /// running.
+    pub fn toggle_calibration(&mut self) {
+        if self.calibration.is_some() {
+            self.finish_calibration();
+            println!("cancelled reach calibration");
+
+            return;
+        }
+
+        // hand data is needed for calibration even if nothing is being sent
+        self.hand_manager.start_update();
+        self.calibration = Some(CalibrationWizard::new());
+    }
+
+    fn finish_calibration(&mut self) {
+        self.calibration = None;
 
+        if !self.is_sending {
             self.hand_manager.stop_update();
-            self.eme_osc_sender.stop_send();
-            self.midi_timed_thread.stop_send();
         }
+    }
 
-        self.is_sending = send_update;
+    fn update_calibration(&mut self) {
+        let Some(wizard) = &mut self.calibration
+        else {
+            return;
+        };
+
+        let pos = self.hand_manager.damped_hands().com.first;
+
+        if let Some(extents) = wizard.update(pos) {
+            self.finish_calibration();
+            self.params.set_reach_extents(extents);
+
+            println!("calibrated reach to {extents:?}");
+
+            // the calibration is kept with the active profile
+            self.profiles.active_mut().reach = extents;
+
+            if let Err(e) = self.profiles.save() {
+                eprintln!("failed to save profiles: {e}");
+            }
+        }
     }
-}
 
-impl Updatable for Model {
-    fn update(&mut self, update: &Update) {
-        self.hand_manager.update(update);
-        self.gesture_input.write(*self.hand_manager.damped_hands());
+    /// Switches to the next user profile.
+    pub fn next_profile(&mut self) {
+        let profile = self.profiles.select_next();
+        self.params.apply_profile(profile);
+
+        println!("switched to profile \"{}\"", profile.name);
     }
-}
 
-impl Drawable for Model {
-    fn draw(&self, draw: &Draw, frame: &Frame) {
-        if !self.show_state_data {
+    /// Switches to the user profile called `name`.
+    pub fn select_profile(&mut self, name: &str) {
+        match self.profiles.select(name) {
+            Ok(profile) => {
+                self.params.apply_profile(profile);
+                println!("switched to profile \"{name}\"");
+            }
+            Err(e) => eprintln!("failed to select profile: {e}"),
+        }
+    }
+
+    /// Switches the master EQ to the room at `idx`, or flattens it if `idx`
+    /// is `None`.
+    pub fn select_room(&mut self, idx: Option<usize>) {
+        if let Err(e) = self.rooms.select_idx(idx) {
+            eprintln!("failed to select room: {e}");
+            return;
+        }
+
+        let msg = FXMessage::SetEqBands(self.rooms.active_bands());
+        if let Err(e) = self.audio_senders.fx.try_send(msg) {
+            eprintln!("failed to send effect message: {e}");
+        }
+
+        // the active room is remembered for the next time the app starts
+        if let Err(e) = self.rooms.save() {
+            eprintln!("failed to save rooms: {e}");
+        }
+
+        println!("switched to room \"{}\"", self.rooms.active_name());
+    }
+
+    /// The runtime settings which a saved state overrides.
+    fn state_settings(&self) -> StateSettings {
+        StateSettings {
+            metronome_enabled: self.metronome_enabled,
+            show_state_data: self.show_state_data,
+            midi_send_mode: self.midi_send_mode,
+            midi_send_value: self.midi_send_value,
+            midi_send_channel: self.midi_send_channel,
+        }
+    }
+
+    /// Captures the transport, mode, parameters, settings and calibration.
+    pub fn saved_state(&self) -> Option<SavedState> {
+        Some(SavedState {
+            profile: self.profiles.active().name.clone(),
+            settings: self.state_settings(),
+            params: self.params.snapshot()?,
+        })
+    }
+
+    /// Saves the app's state to the state file.
+    pub fn save_state(&self) {
+        let Some(state) = self.saved_state()
+        else {
+            eprintln!("failed to save state: parameters are unavailable");
+            return;
+        };
+
+        let result = serde_json::to_string_pretty(&state.to_json())
+            .map_err(|e| e.to_string())
+            .and_then(|contents| {
+                std::fs::write(&self.state_path, contents)
+                    .map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(()) => println!("saved state to \"{}\"", self.state_path),
+            Err(e) => eprintln!("failed to save state: {e}"),
+        }
+    }
+
+    /// Stores the first hand's pose, which recalls the current CC values
+    /// whenever it is held.
+    pub fn store_pose(&self) {
+        match self.params.store_pose() {
+            Ok(num) => println!("stored pose {num}"),
+            Err(e) => eprintln!("failed to store pose: {e}"),
+        }
+    }
+
+    pub fn clear_poses(&self) {
+        self.params.clear_poses();
+        println!("cleared all stored poses");
+    }
+
+    /// Prints which CCs are pending in the parameter bank, to debug why CCs
+    /// are or aren't sent.
+    pub fn dump_parameter_bank(&self) {
+        match self.params.parameter_bank_diff() {
+            Some(diff) if diff.is_empty() => {
+                println!("parameter bank: no CCs are pending");
+            }
+            Some(diff) => print!("{diff}"),
+            None => {
+                eprintln!(
+                    "failed to dump parameter bank: parameters are unavailable"
+                );
+            }
+        }
+    }
+
+    /// Writes the currently effective tracking config and profiles (including
+    /// the current calibration) back to their config files, so that changes
+    /// made while performing aren't lost.
+    pub fn export_config(&self) {
+        let Some(tracking) = self.params.base_tracking_config()
+        else {
+            eprintln!("failed to export config: parameters are unavailable");
+            return;
+        };
+
+        let mut profiles = self.profiles.clone();
+        profiles.active_mut().reach = self.params.reach_extents();
+
+        let exports = [
+            (
+                "tracking config",
+                self.config_paths
+                    .tracking_config
+                    .as_deref()
+                    .unwrap_or(DEFAULT_TRACKING_EXPORT_PATH),
+                tracking.to_json(),
+            ),
+            (
+                "profiles",
+                profiles.path().unwrap_or(DEFAULT_PROFILES_EXPORT_PATH),
+                profiles.to_json(),
+            ),
+        ];
+
+        for (name, path, value) in exports {
+            match export_json(path, &value) {
+                Ok(ExportOutcome::Written) => {
+                    println!("exported {name} to \"{path}\"");
+                }
+                Ok(ExportOutcome::Unchanged) => {
+                    println!("\"{path}\" already holds the current {name}");
+                }
+                Err(e) => eprintln!("failed to export {name}: {e}"),
+            }
+        }
+    }
+
+    /// Opens the mapping editor with the current mapping, or closes it if it
+    /// is already open.
+    pub fn toggle_mapping_editor(&mut self) {
+        if let Some(editor) = self.mapping_editor.take() {
+            if editor.is_modified() {
+                println!("closed the mapping editor with unsaved changes");
+            }
+
+            return;
+        }
+
+        self.mapping_editor = Some(MappingEditor::new(
+            self.params.mapping_template(),
+            self.params.mapping_sources(),
+        ));
+    }
+
+    pub const fn is_editing_mapping(&self) -> bool {
+        self.mapping_editor.is_some()
+    }
+
+    /// Selects the route `offset` places from the selected one.
+    pub fn select_mapping_route(&mut self, offset: isize) {
+        if let Some(editor) = &mut self.mapping_editor {
+            editor.select_route(offset);
+        }
+    }
+
+    /// Selects the route drawn at `y`, where `top` is the top of the window.
+    pub fn select_mapping_route_at(&mut self, y: f32, top: f32) {
+        let Some(editor) = &mut self.mapping_editor
+        else {
+            return;
+        };
+
+        let row = (top - MAPPING_EDITOR_TOP - y) / MAPPING_EDITOR_LINE_HEIGHT;
+
+        // the first route is centered on its line
+        let row = (row + 0.5).floor();
+
+        if row >= 0.0 {
+            editor.select_route_at(editor.visible_rows().start + row as usize);
+        }
+    }
+
+    /// Selects the field `offset` places from the selected one.
+    pub fn select_mapping_field(&mut self, offset: isize) {
+        if let Some(editor) = &mut self.mapping_editor {
+            editor.select_field(offset);
+        }
+    }
+
+    /// Steps the selected field of the selected route, and applies the
+    /// edited mapping straight away.
+    pub fn adjust_mapping(&mut self, steps: i32) {
+        let Some(editor) = &mut self.mapping_editor
+        else {
+            return;
+        };
+
+        if editor.adjust(steps)
+            && let Err(e) = self.params.set_mapping(editor.template())
+        {
+            eprintln!("failed to apply mapping: {e}");
+        }
+    }
+
+    /// Saves the edited mapping to the file passed via `--mapping`, or to
+    /// the default export path if a built-in template (or nothing) was
+    /// passed.
+    pub fn save_mapping(&mut self) {
+        let Some(editor) = &mut self.mapping_editor
+        else {
+            return;
+        };
+
+        let path = self
+            .config_paths
+            .mapping
+            .as_deref()
+            .filter(|m| MappingTemplate::built_in(m).is_none())
+            .unwrap_or(DEFAULT_MAPPING_EXPORT_PATH);
+
+        match export_json(path, &editor.template().to_json()) {
+            Ok(ExportOutcome::Written) => {
+                println!("saved mapping to \"{path}\"");
+                editor.mark_saved();
+            }
+            Ok(ExportOutcome::Unchanged) => {
+                println!("\"{path}\" already holds the current mapping");
+                editor.mark_saved();
+            }
+            Err(e) => eprintln!("failed to save mapping: {e}"),
+        }
+    }
+
+    /// Loads the app's state from the state file, if it exists.
+    pub fn load_state(&mut self) {
+        let result = std::fs::read_to_string(&self.state_path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| {
+                serde_json::from_str(&contents).map_err(|e| e.to_string())
+            })
+            .and_then(|value| {
+                SavedState::from_json(value, self.state_settings())
+            });
+
+        match result {
+            Ok(state) => {
+                self.restore_state(&state);
+                println!("loaded state from \"{}\"", self.state_path);
+            }
+            Err(e) => eprintln!(
+                "failed to load state \"{}\": {e}",
+                self.state_path
+            ),
+        }
+    }
+
+    /// Restores a state captured by [`saved_state()`](Self::saved_state).
+    pub fn restore_state(&mut self, state: &SavedState) {
+        if self.profiles.select(&state.profile).is_ok() {
+            self.params.apply_profile(self.profiles.active());
+        }
+        else {
+            eprintln!(
+                "no profile called \"{}\", keeping \"{}\"",
+                state.profile,
+                self.profiles.active().name
+            );
+        }
+
+        // the snapshot's calibration overrides the profile's
+        self.params.restore_snapshot(&state.params);
+
+        self.bpm = state.params.bpm();
+        self.send_metronome_message(MetronomeMessage::SetTempo(self.bpm));
+
+        let settings = state.settings;
+
+        if settings.metronome_enabled != self.metronome_enabled {
+            self.toggle_metronome();
+        }
+
+        self.show_state_data = settings.show_state_data;
+        self.midi_send_mode = settings.midi_send_mode;
+        self.midi_send_value = settings.midi_send_value;
+        self.midi_send_channel = settings.midi_send_channel;
+    }
+
+    /// Whether this instance may send MIDI and OSC, which it may unless it
+    /// is on standby for another instance.
+    fn is_output_active(&self) -> bool {
+        self.redundancy.as_ref().map_or(true, RedundancyLink::is_active)
+    }
+
+    /// Exchanges heartbeats with the other instance, takes over from it or
+    /// gives way to it, and replicates the state to it while active.
+    fn update_redundancy(&mut self) {
+        match self.redundancy.as_mut().and_then(RedundancyLink::update) {
+            Some(RedundancyEvent::Promoted(state)) => self.take_over(state),
+            Some(RedundancyEvent::Demoted) => {
+                println!("the other instance has taken over, standing by");
+
+                self.eme_osc_sender.stop_send();
+                self.midi_timed_thread.stop_send();
+            }
+            None => {}
+        }
+
+        if !self
+            .redundancy
+            .as_ref()
+            .is_some_and(RedundancyLink::is_replication_due)
+        {
             return;
         }
 
+        let Some(json) = self
+            .saved_state()
+            .and_then(|state| serde_json::to_string(&state.to_json()).ok())
+        else {
+            return;
+        };
+
+        if let Some(link) = self.redundancy.as_mut() {
+            link.replicate(json, self.is_sending);
+        }
+    }
+
+    /// Starts sending MIDI and OSC in place of the other instance, from the
+    /// state which it last replicated.
+    fn take_over(&mut self, state: Option<ReplicatedState>) {
+        println!("taking over as the active instance");
+
+        if let Some(replicated) = state {
+            let result = serde_json::from_str(&replicated.json)
+                .map_err(|e| e.to_string())
+                .and_then(|value| {
+                    SavedState::from_json(value, self.state_settings())
+                });
+
+            match result {
+                Ok(state) => self.restore_state(&state),
+                Err(e) => {
+                    eprintln!("failed to restore the replicated state: {e}");
+                }
+            }
+
+            if replicated.is_sending && !self.is_sending {
+                self.continue_update();
+                return;
+            }
+        }
+
+        if self.is_sending {
+            self.start_senders();
+        }
+    }
+
+    /// Paints the spectral mask with the hands and sends it to the audio
+    /// thread.
+    fn update_spectral_mask(&mut self, update: &Update) {
+        self.mask_painter.update(
+            self.hand_manager.damped_hands(),
+            update.since_last.as_secs_f64(),
+        );
+        self.spectral_mask.write(self.mask_painter.mask().clone());
+    }
+
+    /// Maps the first hand's position to the pitch shift, which returns to
+    /// no shift while the hand isn't tracked.
+    fn update_pitch_shift(&self) {
+        let Some((axis, range)) = self.pitch_axis
+        else {
+            return;
+        };
+
+        let semitones =
+            self.hand_manager.damped_hands().com.first.map_or(0.0, |com| {
+                map(com[axis].clamp(0.0, 1.0), 0.0, 1.0, -range, range)
+            });
+
+        self.pitch_shift.semitones.sr(semitones);
+    }
+
+    /// Morphs the palette with the hands, following the mode sweep of the
+    /// parameter updater so that the visuals and the audio change together.
+    fn update_palette(&mut self, update: &Update) {
+        let transition = self
+            .params
+            .mode_transition()
+            .unwrap_or_else(|| ModeTransition::settled(Mode::default()));
+
+        self.palette.update(
+            self.hand_manager.damped_hands(),
+            self.params.hand_velocity(),
+            transition,
+            update.since_last.as_secs_f64(),
+        );
+    }
+
+    /// Pulses the visuals with the transport's beats and the output's band
+    /// energies.
+    fn update_pulse(&mut self, update: &Update) {
+        self.pulse.update(
+            self.params.musical_position(),
+            &self.params.audio_bands(),
+            update.since_last.as_secs_f64(),
+        );
+    }
+
+    /// Whether any hands are being tracked.
+    fn hands_present(&self) -> bool {
+        let hands = &self.hand_manager.damped_hands().pair;
+        hands.first.is_some() || hands.second.is_some()
+    }
+
+    /// Plays the instructional animation while there are no hands.
+    fn update_attract(&mut self, update: &Update) {
+        let hands_present = self.hands_present();
+
+        if let Some(attract) = &mut self.attract {
+            attract.update(hands_present, update.since_last.as_secs_f64());
+        }
+    }
+
+    /// Starts the tutorial if it is accepted from the idle screen, and
+    /// rewards each of its steps with a chime.
+    fn update_tutorial(&mut self, update: &Update) {
+        let hands_present = self.hands_present();
+        let events = self.gestures.events();
+
+        if !self.tutorial.is_active()
+            && events.contains(&GestureEvent::ThumbUp)
+            && let Some(attract) = &mut self.attract
+            && attract.is_inviting()
+        {
+            attract.dismiss_invite();
+            self.tutorial.start();
+        }
+
+        let completed = self.tutorial.update(
+            events,
+            hands_present,
+            update.since_last.as_secs_f64(),
+        );
+
+        if let Some(step) = completed {
+            self.play_reward_chime(step.reward_note());
+        }
+
+        if let Some((note, release)) = self.reward_release
+            && release <= Instant::now()
+        {
+            self.send_note_event(NoteEvent::NoteOff { note, timing: 0 });
+            self.reward_release = None;
+        }
+    }
+
+    /// Plays `note` through the synth as a reward, releasing any previous
+    /// reward first.
+    fn play_reward_chime(&mut self, note: f64) {
+        const CHIME_LENGTH: Duration = Duration::from_millis(400);
+
+        if let Some((previous, _)) = self.reward_release.take() {
+            self.send_note_event(NoteEvent::NoteOff {
+                note: previous,
+                timing: 0,
+            });
+        }
+
+        self.send_note_event(NoteEvent::NoteOn { note, timing: 0 });
+        self.reward_release = Some((note, Instant::now() + CHIME_LENGTH));
+    }
+
+    fn send_note_event(&self, event: NoteEvent) {
+        if let Err(e) = self.audio_senders.note_event.try_send(event) {
+            eprintln!("failed to send note event: {e}");
+        }
+    }
+
+    /// Takes the latest output spectrum from the audio thread.
+    fn update_spectrum(&mut self) {
+        if !self.spectrum.updated() {
+            return;
+        }
+
+        let levels = self.spectrum.read();
+
+        self.spectrum_levels.clear();
+        self.spectrum_levels.extend_from_slice(levels);
+    }
+
+    /// Takes the latest DSP load from the audio thread.
+    fn update_dsp_load(&mut self) {
+        if self.dsp_load.updated() {
+            self.latest_dsp_load = *self.dsp_load.read();
+        }
+    }
+
+    /// Draws the output spectrum along the bottom of the window, with a
+    /// logarithmic frequency axis.
+    pub fn draw_spectrum(&self, draw: &Draw, frame: &Frame) {
+        const MIN_FREQ: f64 = 20.0;
+        const MIN_DB: f64 = -90.0;
+        const HEIGHT_FRACTION: f32 = 0.35;
+
+        let num_bins = self.spectrum_levels.len();
+
+        if num_bins < 2 {
+            return;
+        }
+
+        let r = frame.rect();
+        let height = r.h() * HEIGHT_FRACTION;
+        let nyquist = self.sample_rate_ref.lr() * 0.5;
+        let log_range = (nyquist / MIN_FREQ).ln();
+
+        let points = self
+            .spectrum_levels
+            .iter()
+            .enumerate()
+            .filter_map(|(k, &db)| {
+                let freq = k as f64 / (num_bins - 1) as f64 * nyquist;
+
+                (freq >= MIN_FREQ).then(|| {
+                    let x = (freq / MIN_FREQ).ln() / log_range;
+                    let y = ((db - MIN_DB) / -MIN_DB).clamp(0.0, 1.0);
+
+                    vec2(
+                        r.left() + x as f32 * r.w(),
+                        r.bottom() + y as f32 * height,
+                    )
+                })
+            });
+
+        let color = self.palette.color(PaletteLayer::Accents);
+
+        draw.polyline().weight(1.5).color(color).points(points);
+    }
+
+    /// Reports any newly degraded hand data, and asks the tracker to restart
+    /// if enabled.
+    fn check_hand_quality(&mut self) {
+        if !self.hand_manager.take_quality_degraded() {
+            return;
+        }
+
+        let reason = self
+            .hand_manager
+            .quality()
+            .alerts()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        eprintln!("hand data has degraded: {reason}");
+
+        if !self.is_output_active() {
+            return;
+        }
+
+        let Some(control) = self.tracker_control.as_mut()
+        else {
+            return;
+        };
+
+        if self.last_restart_request.is_some_and(|t| {
+            t.elapsed().as_secs_f64() < TRACKER_RESTART_COOLDOWN
+        }) {
+            return;
+        }
+
+        let packet = OscPacket::Message(nannou_osc::Message {
+            addr: TRACKER_RESTART_OSC_ADDRESS.to_string(),
+            args: vec![OSCType::String(reason)],
+        });
+
+        match control.send(&packet) {
+            Ok(()) => println!("asked the hand tracker to restart"),
+            Err(e) => {
+                eprintln!("failed to ask the hand tracker to restart: {e}");
+            }
+        }
+
+        self.last_restart_request = Some(Instant::now());
+    }
+
+    /// Collects the config diagnostics once validation has finished, and
+    /// logs them.
+    fn update_config_diagnostics(&mut self) {
+        let Some(diagnostics) = self.config_validator.try_take()
+        else {
+            return;
+        };
+
+        for diagnostic in &diagnostics {
+            eprintln!("{diagnostic}");
+        }
+
+        self.config_diagnostics = diagnostics;
+    }
+
+    /// Logs the incidents of the output going over the true-peak threshold.
+    fn update_true_peak(&mut self) {
+        for incident in self.true_peak_incidents.try_iter() {
+            eprintln!("output over true-peak threshold: {incident}");
+
+            if self.true_peak_log.len() == TRUE_PEAK_LOG_LEN {
+                self.true_peak_log.pop_front();
+            }
+
+            self.true_peak_log.push_back(incident);
+            self.true_peak_overs += 1;
+        }
+    }
+
+    /// Handles any control messages received over OSC.
+    fn handle_control_messages(&mut self) {
+        for msg in self.hand_manager.take_control_messages() {
+            match (msg.addr.as_str(), msg.args.first()) {
+                (osc::PROFILE_OSC_ADDRESS, Some(OSCType::String(name))) => {
+                    self.select_profile(name);
+                }
+                (osc::EXPORT_OSC_ADDRESS, _) => self.export_config(),
+                (osc::TICKER_OSC_ADDRESS, _) => self.schedule_ticker(&msg),
+                (osc::TICKER_CLEAR_OSC_ADDRESS, _) => {
+                    self.ticker.clear(Instant::now());
+                }
+                (addr, _) if addr.starts_with(osc::FX_OSC_PREFIX) => {
+                    self.handle_fx_control_message(&msg);
+                }
+                (hands::handshake::HANDSHAKE_OSC_ADDRESS, _) => {
+                    self.hand_manager.handle_handshake(&msg);
+                }
+                _ => eprintln!("unhandled OSC control message {}", msg.addr),
+            }
+        }
+    }
+
+    /// Schedules a ticker message received over OSC.
+    fn schedule_ticker(&mut self, msg: &nannou_osc::Message) {
+        let now = Instant::now();
+
+        if let Err(e) = self.ticker.schedule_from_osc(&msg.args, now) {
+            eprintln!("invalid OSC ticker message: {e}");
+        }
+    }
+
+    /// Validates an effect parameter received over OSC, and sends it to the
+    /// audio thread.
+    fn handle_fx_control_message(&self, msg: &nannou_osc::Message) {
+        let name = &msg.addr[osc::FX_OSC_PREFIX.len()..];
+
+        let value = match msg.args.first() {
+            Some(OSCType::Float(value)) => *value as f64,
+            Some(OSCType::Double(value)) => *value,
+            Some(OSCType::Int(value)) => *value as f64,
+            _ => {
+                eprintln!("OSC control message {} expects a number", msg.addr);
+                return;
+            }
+        };
+
+        match FXMessage::from_name(name, value) {
+            Ok(fx) => {
+                if let Err(e) = self.audio_senders.fx.try_send(fx) {
+                    eprintln!("failed to send effect message: {e}");
+                }
+            }
+            Err(e) => eprintln!("invalid OSC control message: {e}"),
+        }
+    }
+
+    /// Sets the tempo of the internal clock.
+    pub fn set_bpm(&mut self, bpm: f64) {
+        self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+
+        self.params.set_bpm(self.bpm);
+        self.send_metronome_message(MetronomeMessage::SetTempo(self.bpm));
+    }
+
+    /// Changes the audio input's gain by `delta_db` decibels.
+    pub fn adjust_input_gain(&self, delta_db: f64) {
+        let gain_db = (self.input_control.gain_db.lr() + delta_db)
+            .clamp(input::MIN_INPUT_GAIN_DB, input::MAX_INPUT_GAIN_DB);
+
+        self.input_control.gain_db.sr(gain_db);
+    }
+
+    /// Changes the intensity of the visuals' pulses by `delta`.
+    pub fn adjust_pulse_intensity(&mut self, delta: f32) {
+        self.pulse.set_intensity(self.pulse.intensity() + delta);
+    }
+
+    /// Starts or stops the input gate learning the room's noise floor, which
+    /// should be done while the performer is silent.
+    pub fn toggle_input_gate_learning(&self) {
+        if self.audio_input_stream.is_none() {
+            return;
+        }
+
+        let control = &self.input_control;
+        let learning = !control.gate_learning.lr();
+        control.gate_learning.sr(learning);
+
+        if learning {
+            println!("learning the input's noise floor...");
+        }
+        else {
+            println!(
+                "learned the input's noise floor at {:.1} dB",
+                control.gate_floor_db.lr()
+            );
+        }
+    }
+
+    /// Clears the held true peak and the log of incidents.
+    pub fn reset_true_peak(&mut self) {
+        self.true_peak_control.reset_max();
+        self.true_peak_log.clear();
+        self.true_peak_overs = 0;
+    }
+
+    /// Removes all of the feedback suppressor's notches, if it is enabled.
+    pub fn clear_feedback_notches(&self) {
+        if let Some(control) = self.feedback_control.as_ref() {
+            control.request_clear();
+            println!("cleared the feedback notches");
+        }
+    }
+
+    /// Wakes the app if it is winding down, and restarts the time until it
+    /// next does.
+    pub fn note_interaction(&mut self) {
+        let Some(stage) =
+            self.wind_down.as_mut().and_then(WindDown::note_interaction)
+        else {
+            return;
+        };
+
+        if stage == WindDownStage::Asleep {
+            self.send_fx_message(FXMessage::SetAsleep(false));
+        }
+
+        self.send_fx_message(FXMessage::FadeOutput {
+            gain_db: 0.0,
+            fade_secs: WAKE_FADE_SECS,
+        });
+
+        if std::mem::take(&mut self.resume_on_wake) {
+            self.continue_update();
+        }
+        else if !self.is_sending && self.calibration.is_none() {
+            self.hand_manager.stop_update();
+        }
+
+        println!("woke from winding down");
+    }
+
+    /// Moves through the stages of winding down while nothing interacts
+    /// with the app. Hands being tracked count as interaction.
+    fn update_wind_down(&mut self) {
+        if self.wind_down.is_none() {
+            return;
+        }
+
+        let hands = &self.hand_manager.damped_hands().pair;
+        if hands.first.is_some() || hands.second.is_some() {
+            self.note_interaction();
+        }
+
+        let Some(stage) = self.wind_down.as_mut().and_then(WindDown::update)
+        else {
+            return;
+        };
+
+        match stage {
+            WindDownStage::FadingOut => {
+                println!("winding down after a long time without interaction");
+
+                self.send_fx_message(FXMessage::FadeOutput {
+                    gain_db: IDLE_HUM_GAIN_DB,
+                    fade_secs: WIND_DOWN_FADE_SECS,
+                });
+            }
+            WindDownStage::Humming => {
+                // the EME sender keeps sending until asleep, so that its stop
+                // request is delivered
+                if self.is_sending {
+                    self.params.stop_update();
+                    self.midi_timed_thread.stop_send();
+                    self.is_sending = false;
+                    self.resume_on_wake = true;
+                }
+
+                let event = VoiceEvent::ReleaseAll;
+                if let Err(e) = self.voice_event_sender.send(event) {
+                    eprintln!("failed to send voice event: {e}");
+                }
+
+                // hands are still tracked so that they can wake the app
+                self.hand_manager.start_update();
+            }
+            WindDownStage::Asleep => {
+                if self.resume_on_wake {
+                    self.eme_osc_sender.stop_send();
+                }
+
+                self.send_fx_message(FXMessage::SetAsleep(true));
+                println!("audio is asleep until the next interaction");
+            }
+            WindDownStage::Active => {}
+        }
+    }
+
+    fn send_fx_message(&self, msg: FXMessage) {
+        if let Err(e) = self.audio_senders.fx.try_send(msg) {
+            eprintln!("failed to send effect message: {e}");
+        }
+    }
+
+    fn send_metronome_message(&self, msg: MetronomeMessage) {
+        if let Err(e) = self.audio_senders.metronome.try_send(msg) {
+            eprintln!("failed to send metronome message: {e}");
+        }
+    }
+
+    pub fn send_and_update(&mut self, send_update: bool) {
+        // we don't need to clear the request channel as the queue size is
+        // greater than 1
+        // self.eme_osc_sender.clear_request_channel();
+
+        if send_update {
+            // keep the metronome's downbeat aligned with the transport start
+            self.send_metronome_message(MetronomeMessage::Reset);
+
+            self.params.reset_updater();
+            self.params.start_update();
+
+            self.start_senders();
+        }
+        else {
+            self.params.stop_update();
+
+            if self.calibration.is_none() {
+                self.hand_manager.stop_update();
+            }
+
+            self.eme_osc_sender.stop_send();
+            self.midi_timed_thread.stop_send();
+        }
+
+        self.is_sending = send_update;
+    }
+
+    /// Resumes sending from where the transport was stopped, rather than from
+    /// the start.
+    pub fn continue_update(&mut self) {
+        if self.is_sending {
+            return;
+        }
+
+        self.params.reset_updater();
+        self.params.continue_update();
+
+        self.start_senders();
+
+        self.is_sending = true;
+    }
+
+    /// Draws the names of any CCs waiting to be picked up, along with which
+    /// way the hand needs to move to pick them up.
+    fn draw_pickup_ccs(&self, draw: &Draw, frame: &Frame) {
+        let pickups = self.params.pickup_cc_names();
+
+        if pickups.is_empty() {
+            return;
+        }
+
+        let names: Vec<String> = pickups
+            .iter()
+            .map(|(name, rise)| match rise {
+                Some(true) => self
+                    .strings
+                    .format("overlay.pickup_raise", &[("name", name)]),
+                Some(false) => self
+                    .strings
+                    .format("overlay.pickup_lower", &[("name", name)]),
+                None => (*name).to_string(),
+            })
+            .collect();
+
+        let msg = self
+            .strings
+            .format("overlay.pickup", &[("names", &names.join(", "))]);
+        let top = frame.rect().top();
+
+        draw.text(&msg)
+            .color(Rgba::new(0.3, 0.7, 1.0, 1.0))
+            .xy(vec2(0.0, top - 60.0))
+            .wh(vec2(800.0, 40.0))
+            .justify(text::Justify::Center)
+            .font_size(14);
+    }
+
+    fn start_senders(&mut self) {
+        self.hand_manager.start_update();
+
+        // a standby instance follows the hands, but leaves the output to
+        // the active instance until it takes over
+        if !self.is_output_active() {
+            return;
+        }
+
+        self.eme_osc_sender.start_send();
+        self.midi_timed_thread.start_send();
+    }
+
+    /// Draws which axis the hands are locked to, or a prompt to choose one,
+    /// while the axis lock is held.
+    fn draw_axis_lock(&self, draw: &Draw, frame: &Frame) {
+        let Some(axis) = self.params.locked_axis()
+        else {
+            return;
+        };
+
+        let msg = axis.map_or_else(
+            || String::from(self.strings.get("overlay.axis_lock_choose")),
+            |axis| self.strings.format("overlay.axis_lock", &[("axis", &axis)]),
+        );
+        let top = frame.rect().top();
+
+        draw.text(&msg)
+            .color(Rgba::new(0.6, 1.0, 0.4, 1.0))
+            .xy(vec2(0.0, top - 90.0))
+            .wh(vec2(800.0, 40.0))
+            .justify(text::Justify::Center)
+            .font_size(14);
+    }
+
+    fn draw_calibration_prompt(&self, draw: &Draw, frame: &Frame) {
+        let Some((prompt_key, remaining)) =
+            self.calibration.as_ref().and_then(CalibrationWizard::prompt)
+        else {
+            return;
+        };
+
+        let msg = self.strings.format(
+            "calibration.prompt",
+            &[
+                ("prompt", &self.strings.get(prompt_key)),
+                ("remaining", &format!("{remaining:.0}")),
+            ],
+        );
+
+        draw.text(&msg)
+            .color(Rgba::new(1.0, 1.0, 1.0, 1.0))
+            .line_spacing(6.0)
+            .xy(vec2(0.0, 0.0))
+            .wh(vec2(800.0, 80.0))
+            .justify(text::Justify::Center)
+            .font_size(24);
+    }
+
+    fn draw_eme_failure(&self, draw: &Draw, frame: &Frame) {
+        let Some(failure) =
+            self.params.last_eme_failure(EME_FAILURE_DISPLAY_TIME)
+        else {
+            return;
+        };
+
+        let msg = self
+            .strings
+            .format("overlay.eme_failure", &[("failure", &failure)]);
+        let top = frame.rect().top();
+
+        draw.text(&msg)
+            .color(Rgba::new(1.0, 0.3, 0.3, 1.0))
+            .xy(vec2(0.0, top - 120.0))
+            .wh(vec2(800.0, 40.0))
+            .justify(text::Justify::Center)
+            .font_size(14);
+    }
+
+    fn draw_quality_alerts(&self, draw: &Draw, frame: &Frame) {
+        let alerts = self.hand_manager.quality().alerts();
+
+        if alerts.is_empty() {
+            return;
+        }
+
+        let msg = alerts
+            .iter()
+            .map(|alert| {
+                self.strings
+                    .format("overlay.tracking_degraded", &[("alert", alert)])
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let top = frame.rect().top();
+
+        draw.text(&msg)
+            .color(Rgba::new(1.0, 0.6, 0.1, 1.0))
+            .line_spacing(4.0)
+            .xy(vec2(0.0, top - 240.0))
+            .wh(vec2(800.0, 60.0))
+            .justify(text::Justify::Center)
+            .font_size(12);
+    }
+
+    fn draw_tracker_incompatibility(&self, draw: &Draw, frame: &Frame) {
+        let Some(reason) = self.hand_manager.tracker_incompatibility()
+        else {
+            return;
+        };
+
+        let msg = self
+            .strings
+            .format("overlay.tracker_ignored", &[("reason", &reason)]);
+        let top = frame.rect().top();
+
+        draw.text(&msg)
+            .color(Rgba::new(1.0, 0.3, 0.3, 1.0))
+            .xy(vec2(0.0, top - 150.0))
+            .wh(vec2(800.0, 40.0))
+            .justify(text::Justify::Center)
+            .font_size(14);
+    }
+
+    /// Draws the most serious config problems at the bottom of the window.
+    fn draw_config_diagnostics(&self, draw: &Draw, frame: &Frame) {
+        let Some(first) = self.config_diagnostics.first()
+        else {
+            return;
+        };
+
+        let num_errors = self
+            .config_diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        let num_warnings = self.config_diagnostics.len() - num_errors;
+
+        let shown = self
+            .config_diagnostics
+            .iter()
+            .take(MAX_DRAWN_CONFIG_DIAGNOSTICS)
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let header = self.strings.format(
+            "overlay.config_diagnostics",
+            &[("num_errors", &num_errors), ("num_warnings", &num_warnings)],
+        );
+        let msg = format!("{header}\n{shown}");
+        let color = if first.severity == Severity::Error {
+            Rgba::new(1.0, 0.3, 0.3, 1.0)
+        }
+        else {
+            Rgba::new(1.0, 0.8, 0.2, 1.0)
+        };
+
+        draw.text(&msg)
+            .color(color)
+            .line_spacing(4.0)
+            .xy(vec2(0.0, frame.rect().top() - 190.0))
+            .wh(vec2(800.0, 100.0))
+            .justify(text::Justify::Center)
+            .font_size(12);
+    }
+
+    /// Draws the mapping editor's routes, with the selected route
+    /// highlighted.
+    fn draw_mapping_editor(&self, draw: &Draw, frame: &Frame) {
+        let Some(editor) = &self.mapping_editor
+        else {
+            return;
+        };
+
+        let top = frame.rect().top();
+        let unsaved = if editor.is_modified() {
+            self.strings.get("overlay.mapping_editor_unsaved")
+        }
+        else {
+            ""
+        };
+        let header = self
+            .strings
+            .format("overlay.mapping_editor", &[("unsaved", &unsaved)]);
+
+        draw.text(&header)
+            .color(Rgba::new(1.0, 1.0, 1.0, 1.0))
+            .line_spacing(4.0)
+            .xy(vec2(0.0, top - MAPPING_EDITOR_TOP + 40.0))
+            .wh(vec2(800.0, 40.0))
+            .justify(text::Justify::Center)
+            .font_size(14);
+
+        let rows = editor.visible_rows();
+
+        for (i, line) in editor.visible_lines().iter().enumerate() {
+            let color = if rows.start + i == editor.selected() {
+                Rgba::new(0.3, 0.7, 1.0, 1.0)
+            }
+            else {
+                Rgba::new(0.6, 0.6, 0.6, 1.0)
+            };
+            let y = (i as f32).mul_add(-MAPPING_EDITOR_LINE_HEIGHT, top)
+                - MAPPING_EDITOR_TOP;
+
+            draw.text(line)
+                .color(color)
+                .xy(vec2(0.0, y))
+                .wh(vec2(800.0, MAPPING_EDITOR_LINE_HEIGHT))
+                .left_justify()
+                .font_size(12);
+        }
+    }
+
+    fn draw_input_meter(&self, draw: &Draw, frame: &Frame) {
+        if self.audio_input_stream.is_none() {
+            return;
+        }
+
+        let control = &self.input_control;
+        let level_db = control.level_db.lr();
+        let gain_db = control.gain_db.lr();
+
+        let level = if level_db <= MINUS_INFINITY_DB {
+            String::from("-inf")
+        }
+        else {
+            format!("{level_db:.1}")
+        };
+        // the meter turns red as the input nears clipping
+        let color = if level_db > -1.0 {
+            Rgba::new(1.0, 0.3, 0.3, 1.0)
+        }
+        else {
+            Rgba::new(0.5, 0.5, 0.5, 1.0)
+        };
+
+        let gate = if control.gate_learning.lr() {
+            String::from(self.strings.get("meter.input_gate_learning"))
+        }
+        else if control.gate_enabled.lr() {
+            self.strings.format(
+                "meter.input_gate",
+                &[("gain", &format!("{:.0}", control.gate_gain_db.lr()))],
+            )
+        }
+        else {
+            String::new()
+        };
+
+        let msg = self.strings.format(
+            "meter.input",
+            &[
+                ("level", &level),
+                ("gain", &format!("{gain_db:+.1}")),
+                ("gate", &gate),
+            ],
+        );
+        let r = frame.rect();
+
+        draw.text(&msg)
+            .color(color)
+            .xy(vec2(r.left() + 190.0, r.bottom() + 20.0))
+            .wh(vec2(360.0, 20.0))
+            .left_justify()
+            .font_size(12);
+    }
+
+    /// Lists the feedback suppressor's notches above the true-peak meter,
+    /// newest first.
+    fn draw_feedback_notches(&self, draw: &Draw, frame: &Frame) {
+        let Some(control) = self.feedback_control.as_ref()
+        else {
+            return;
+        };
+
+        let notches = control.notches();
+        let r = frame.rect();
+        let x = r.right() - 220.0;
+
+        let header = if notches.is_empty() {
+            String::from(self.strings.get("meter.feedback_none"))
+        }
+        else {
+            self.strings
+                .format("meter.feedback", &[("num_notches", &notches.len())])
+        };
+
+        draw.text(&header)
+            .color(Rgba::new(0.5, 0.5, 0.5, 1.0))
+            .xy(vec2(x, r.bottom() + 40.0))
+            .wh(vec2(420.0, 20.0))
+            .right_justify()
+            .font_size(12);
+
+        for (i, notch) in notches.iter().rev().enumerate() {
+            let msg =
+                format!("{:.0} Hz, -{:.0} dB", notch.freq, notch.depth_db);
+
+            draw.text(&msg)
+                .color(Rgba::new(1.0, 0.6, 0.1, 1.0))
+                .xy(vec2(x, r.bottom() + 56.0 + i as f32 * 16.0))
+                .wh(vec2(420.0, 20.0))
+                .right_justify()
+                .font_size(12);
+        }
+    }
+
+    fn draw_true_peak_meter(&self, draw: &Draw, frame: &Frame) {
+        let format_db = |db: f64| {
+            if db <= MINUS_INFINITY_DB {
+                String::from("-inf")
+            }
+            else {
+                format!("{db:+.1}")
+            }
+        };
+
+        let control = &self.true_peak_control;
+        let mut msg = self.strings.format(
+            "meter.output",
+            &[
+                ("level", &format_db(control.level_db.lr())),
+                ("max", &format_db(control.max_db.lr())),
+                ("overs", &self.true_peak_overs),
+            ],
+        );
+
+        if let Some(last) = self.true_peak_log.back() {
+            msg.push_str(
+                &self.strings.format("meter.output_last", &[("last", last)]),
+            );
+        }
+
+        // the meter stays red until it is reset
+        let color = if control.is_over() {
+            Rgba::new(1.0, 0.3, 0.3, 1.0)
+        }
+        else {
+            Rgba::new(0.5, 0.5, 0.5, 1.0)
+        };
+
+        let r = frame.rect();
+
+        draw.text(&msg)
+            .color(color)
+            .xy(vec2(r.right() - 220.0, r.bottom() + 20.0))
+            .wh(vec2(420.0, 20.0))
+            .right_justify()
+            .font_size(12);
+    }
+
+    /// Draws the DSP load as a bar in the top left of the window, with the
+    /// load of each stage below it, highest first.
+    fn draw_dsp_load(&self, draw: &Draw, frame: &Frame) {
+        const BAR_WIDTH: f32 = 200.0;
+        const BAR_HEIGHT: f32 = 8.0;
+
+        let load = &self.latest_dsp_load;
+        let percent = |load: f64| format!("{:.1}", load * 100.0);
+
+        // the bar turns red as the callback nears its deadline
+        let color = if load.peak > 0.9 {
+            Rgba::new(1.0, 0.3, 0.3, 1.0)
+        }
+        else if load.load > 0.5 {
+            Rgba::new(1.0, 0.8, 0.2, 1.0)
+        }
+        else {
+            Rgba::new(0.5, 0.5, 0.5, 1.0)
+        };
+
+        let r = frame.rect();
+        let top_left = vec2(r.left() + 20.0, r.top() - 20.0);
+        let fill = BAR_WIDTH * load.load.clamp(0.0, 1.0) as f32;
+
+        draw.rect()
+            .xy(top_left + vec2(BAR_WIDTH * 0.5, -BAR_HEIGHT * 0.5))
+            .wh(vec2(BAR_WIDTH, BAR_HEIGHT))
+            .no_fill()
+            .stroke_color(color)
+            .stroke_weight(1.0);
+        draw.rect()
+            .xy(top_left + vec2(fill * 0.5, -BAR_HEIGHT * 0.5))
+            .wh(vec2(fill, BAR_HEIGHT))
+            .color(color);
+
+        let header = self.strings.format(
+            "meter.dsp",
+            &[("load", &percent(load.load)), ("peak", &percent(load.peak))],
+        );
+        let stages = load.stages_by_load().map(|(stage, load)| {
+            self.strings.format(
+                "meter.dsp_stage",
+                &[("stage", &stage), ("load", &percent(load))],
+            )
+        });
+        let msg = format!("{header}\n{}", stages.join("\n"));
+
+        draw.text(&msg)
+            .color(Rgba::new(0.5, 0.5, 0.5, 1.0))
+            .line_spacing(4.0)
+            .xy(top_left + vec2(BAR_WIDTH * 0.5, -BAR_HEIGHT - 110.0))
+            .wh(vec2(BAR_WIDTH, 200.0))
+            .left_justify()
+            .align_text_top()
+            .font_size(12);
+    }
+
+    /// Draws the names of any latched CCs at the top of the window, so that
+    /// it's clear why they aren't responding to hand motion.
+    fn draw_latched_ccs(&self, draw: &Draw, frame: &Frame) {
+        let names = self.params.latched_cc_names();
+
+        if names.is_empty() {
+            return;
+        }
+
+        let msg = self
+            .strings
+            .format("overlay.latched", &[("names", &names.join(", "))]);
+        let top = frame.rect().top();
+
+        draw.text(&msg)
+            .color(Rgba::new(1.0, 0.6, 0.1, 1.0))
+            .xy(vec2(0.0, top - 30.0))
+            .wh(vec2(800.0, 40.0))
+            .justify(text::Justify::Center)
+            .font_size(14);
+    }
+}
+
+impl Updatable for Model {
+    fn update(&mut self, update: &Update) {
+        self.hand_manager.update(update);
+        self.gesture_input.write(*self.hand_manager.damped_hands());
+        self.gestures.update(
+            self.hand_manager.damped_hands(),
+            update.since_last.as_secs_f64(),
+        );
+        self.update_spectral_mask(update);
+        self.update_pitch_shift();
+        self.update_spectrum();
+        self.update_dsp_load();
+        self.update_palette(update);
+        self.update_pulse(update);
+        self.update_attract(update);
+        self.update_tutorial(update);
+        self.update_calibration();
+        self.handle_control_messages();
+        self.update_redundancy();
+        self.ticker.update(Instant::now());
+        self.update_config_diagnostics();
+        self.update_true_peak();
+        self.check_hand_quality();
+        self.update_wind_down();
+
+        if let Some(bpm) = self.params.take_tempo_change() {
+            self.bpm = bpm;
+            self.send_metronome_message(MetronomeMessage::SetTempo(bpm));
+        }
+    }
+}
+
+impl Drawable for Model {
+    fn draw(&self, draw: &Draw, frame: &Frame) {
+        self.draw_latched_ccs(draw, frame);
+        self.draw_pickup_ccs(draw, frame);
+        self.draw_axis_lock(draw, frame);
+        self.draw_eme_failure(draw, frame);
+        self.draw_tracker_incompatibility(draw, frame);
+        self.draw_calibration_prompt(draw, frame);
+        self.draw_config_diagnostics(draw, frame);
+        self.draw_quality_alerts(draw, frame);
+        self.draw_input_meter(draw, frame);
+        self.draw_true_peak_meter(draw, frame);
+        self.draw_feedback_notches(draw, frame);
+        self.draw_mapping_editor(draw, frame);
+        self.ticker.draw(draw, frame);
+
+        if self.tutorial.is_active() {
+            self.tutorial.draw(
+                draw,
+                frame,
+                &self.strings,
+                self.palette.color(PaletteLayer::Accents),
+            );
+        }
+        else if let Some(attract) = &self.attract {
+            attract.draw_caption(draw, frame, &self.strings);
+        }
+
+        if !self.show_state_data {
+            return;
+        }
+
+        self.draw_dsp_load(draw, frame);
+
         let ping_msg = self.format_midi_ping();
         let state_msg = self.format_state();
 
@@ -364,7 +2397,7 @@ impl Drawable for Model {
             .color(Rgba::new(0.5, 0.5, 0.5, 1.0))
             .line_spacing(4.5)
             .xy(vec2(0.0, bottom + 40.0))
-            .wh(vec2(400.0, 80.0))
+            .wh(vec2(480.0, 160.0))
             .justify(text::Justify::Center)
             .font_size(12);
     }
@@ -0,0 +1,332 @@
+//! Saving and restoring the app's state.
+//!
+//! The state is stored in a JSON file of the form:
+//!
+//! ```json
+//! {
+//!     "version": 2,
+//!     "profile": "Jamie",
+//!     "settings": {
+//!         "metronome": false,
+//!         "show_state_data": true,
+//!         "midi_send_mode": "cc",
+//!         "midi_send_value": 0,
+//!         "midi_send_channel": 0
+//!     },
+//!     "params": {
+//!         "mode": "A",
+//!         "previous_mode": "C",
+//!         "eme": {
+//!             "arrangement": "default",
+//!             "playing": true,
+//!             "position": [0.0, 0.5],
+//!             "volume": 0.8
+//!         },
+//!         "transport": { "bpm": 120.0, "beats_per_bar": 4, "beats": 32.0 },
+//!         "reach": { "x": [0.1, 0.9], "y": [0.05, 0.8], "z": [0.0, 1.0] },
+//!         "ccs": [{ "channel": 0, "cc": 1, "value": 0.5 }],
+//!         "poses": [{ "landmarks": [[0.0, 0.0, 0.0], ...], "ccs": [...] }]
+//!     }
+//! }
+//! ```
+//!
+//! Files written by older versions are migrated to the current version when
+//! they are loaded.
+
+use super::*;
+use serde_json::{json, Value};
+
+/// The default file which the state is saved to.
+pub const DEFAULT_STATE_PATH: &str = "./maestro_state.json";
+
+/// The version of the state file format which is written.
+pub const STATE_VERSION: u64 = 2;
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// Upgrades state files to the next version, where `STATE_MIGRATIONS[n]`
+/// upgrades a version `n + 1` file to version `n + 2`.
+const STATE_MIGRATIONS: [Migration; STATE_VERSION as usize - 1] =
+    [migrate_eme_state];
+
+/// Settings which can be changed at runtime, and so override their initial
+/// values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateSettings {
+    pub metronome_enabled: bool,
+    pub show_state_data: bool,
+    pub midi_send_mode: MIDISendMode,
+    pub midi_send_value: u8,
+    pub midi_send_channel: u8,
+}
+
+impl StateSettings {
+    fn from_json(value: &Value, defaults: Self) -> Result<Self, String> {
+        let flag = |key: &str, default: bool| match value.get(key) {
+            Some(v) => {
+                v.as_bool().ok_or_else(|| format!("\"{key}\" must be a bool"))
+            }
+            None => Ok(default),
+        };
+
+        let byte = |key: &str, default: u8, max: u8| match value.get(key) {
+            Some(v) => v
+                .as_u64()
+                .filter(|v| *v <= max as u64)
+                .map(|v| v as u8)
+                .ok_or_else(|| format!("\"{key}\" must be from 0 to {max}")),
+            None => Ok(default),
+        };
+
+        let midi_send_mode = match value.get("midi_send_mode") {
+            Some(mode) => match mode.as_str() {
+                Some("cc") => MIDISendMode::MIDIControlChange,
+                Some("note") => MIDISendMode::MIDINote,
+                _ => return Err(format!("unknown MIDI send mode {mode}")),
+            },
+            None => defaults.midi_send_mode,
+        };
+
+        Ok(Self {
+            metronome_enabled: flag("metronome", defaults.metronome_enabled)?,
+            show_state_data: flag("show_state_data", defaults.show_state_data)?,
+            midi_send_mode,
+            midi_send_value: byte(
+                "midi_send_value",
+                defaults.midi_send_value,
+                (1 << 7) - 1,
+            )?,
+            midi_send_channel: byte(
+                "midi_send_channel",
+                defaults.midi_send_channel,
+                (1 << 4) - 1,
+            )?,
+        })
+    }
+
+    fn to_json(self) -> Value {
+        json!({
+            "metronome": self.metronome_enabled,
+            "show_state_data": self.show_state_data,
+            "midi_send_mode": match self.midi_send_mode {
+                MIDISendMode::MIDIControlChange => "cc",
+                MIDISendMode::MIDINote => "note",
+            },
+            "midi_send_value": self.midi_send_value,
+            "midi_send_channel": self.midi_send_channel,
+        })
+    }
+}
+
+/// Everything needed to restore the app to where it was.
+#[derive(Clone, Debug)]
+pub struct SavedState {
+    /// The name of the active user profile.
+    pub profile: String,
+    pub settings: StateSettings,
+    pub params: ParameterSnapshot,
+}
+
+impl SavedState {
+    /// Parses a state file's contents, migrating it from older versions if
+    /// needed. Any settings which are missing are taken from `defaults`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state is invalid, or was written by a newer
+    /// version.
+    pub fn from_json(
+        mut value: Value,
+        defaults: StateSettings,
+    ) -> Result<Self, String> {
+        migrate(&mut value)?;
+
+        let Some(profile) = value.get("profile").and_then(Value::as_str)
+        else {
+            return Err(String::from("state is missing a \"profile\""));
+        };
+
+        let settings = value.get("settings").map_or(Ok(defaults), |v| {
+            StateSettings::from_json(v, defaults)
+                .map_err(|e| format!("in settings: {e}"))
+        })?;
+
+        let Some(params) = value.get("params")
+        else {
+            return Err(String::from("state is missing \"params\""));
+        };
+
+        Ok(Self {
+            profile: String::from(profile),
+            settings,
+            params: ParameterSnapshot::from_json(params)
+                .map_err(|e| format!("in params: {e}"))?,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "version": STATE_VERSION,
+            "profile": self.profile,
+            "settings": self.settings.to_json(),
+            "params": self.params.to_json(),
+        })
+    }
+}
+
+/// Upgrades `value` to the current version of the state format.
+fn migrate(value: &mut Value) -> Result<(), String> {
+    let Some(version) = value.get("version").and_then(Value::as_u64)
+    else {
+        return Err(String::from("state is missing a \"version\""));
+    };
+
+    if version == 0 || version > STATE_VERSION {
+        return Err(format!(
+            "unsupported state version {version} (expected up to {STATE_VERSION})"
+        ));
+    }
+
+    for (idx, migration) in
+        STATE_MIGRATIONS.iter().enumerate().skip(version as usize - 1)
+    {
+        let from = idx + 1;
+        migration(value)
+            .map_err(|e| format!("failed to migrate from version {from}: {e}"))?;
+
+        value["version"] = json!(from + 1);
+    }
+
+    Ok(())
+}
+
+/// Moves `params.eme_arrangement` into `params.eme`, which also holds the
+/// EME's playback state.
+fn migrate_eme_state(value: &mut Value) -> Result<(), String> {
+    let Some(params) = value.get_mut("params").and_then(Value::as_object_mut)
+    else {
+        return Err(String::from("state is missing \"params\""));
+    };
+
+    let arrangement = params.remove("eme_arrangement").unwrap_or(json!(""));
+
+    // playback wasn't saved before, but the EME plays whenever the
+    // parameters are being sent
+    params.insert(
+        String::from("eme"),
+        json!({ "arrangement": arrangement, "playing": true }),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULTS: StateSettings = StateSettings {
+        metronome_enabled: false,
+        show_state_data: true,
+        midi_send_mode: MIDISendMode::MIDIControlChange,
+        midi_send_value: 0,
+        midi_send_channel: 0,
+    };
+
+    fn params_json() -> Value {
+        json!({
+            "mode": "B",
+            "previous_mode": "A",
+            "eme": {
+                "arrangement": "strings",
+                "playing": false,
+                "position": [-0.25, 0.75],
+                "volume": 0.5
+            },
+            "transport": { "bpm": 96.0, "beats_per_bar": 3, "beats": 12.0 },
+            "reach": { "x": [0.1, 0.9], "y": [0.05, 0.8], "z": [0.0, 1.0] },
+            "ccs": [
+                { "channel": 0, "cc": 1, "value": 0.5 },
+                { "channel": 2, "cc": 74, "value": 0.25 }
+            ]
+        })
+    }
+
+    #[test]
+    fn state_survives_a_round_trip() {
+        let state = SavedState::from_json(
+            json!({
+                "version": STATE_VERSION,
+                "profile": "Jamie",
+                "settings": {
+                    "metronome": true,
+                    "show_state_data": false,
+                    "midi_send_mode": "note",
+                    "midi_send_value": 64,
+                    "midi_send_channel": 3
+                },
+                "params": params_json(),
+            }),
+            DEFAULTS,
+        )
+        .unwrap();
+
+        let json = state.to_json();
+        assert_eq!(json["version"], json!(STATE_VERSION));
+
+        let loaded = SavedState::from_json(json, DEFAULTS).unwrap();
+        assert_eq!(loaded.profile, "Jamie");
+        assert_eq!(loaded.settings, state.settings);
+        assert_eq!(loaded.params, state.params);
+        assert_eq!(
+            loaded.params,
+            ParameterSnapshot::from_json(&params_json()).unwrap()
+        );
+    }
+
+    #[test]
+    fn version_1_states_are_migrated() {
+        let mut params = params_json();
+        let params_map = params.as_object_mut().unwrap();
+        params_map.remove("eme");
+        params_map.insert(String::from("eme_arrangement"), json!("strings"));
+
+        let mut value =
+            json!({ "version": 1, "profile": "Jamie", "params": params });
+        migrate(&mut value).unwrap();
+
+        assert_eq!(value["version"], json!(STATE_VERSION));
+        assert!(value["params"].get("eme_arrangement").is_none());
+        assert_eq!(
+            value["params"]["eme"],
+            json!({ "arrangement": "strings", "playing": true })
+        );
+
+        let mut expected = params_json();
+        expected["eme"] = json!({ "arrangement": "strings", "playing": true });
+
+        let state = SavedState::from_json(value, DEFAULTS).unwrap();
+        // missing settings are taken from the defaults
+        assert_eq!(state.settings, DEFAULTS);
+        assert_eq!(
+            state.params,
+            ParameterSnapshot::from_json(&expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected() {
+        for version in [json!(0), json!(STATE_VERSION + 1), json!("2")] {
+            let value = json!({
+                "version": version,
+                "profile": "Jamie",
+                "params": params_json(),
+            });
+            let err = SavedState::from_json(value, DEFAULTS).unwrap_err();
+            assert!(err.contains("version"), "{err}");
+        }
+
+        let unversioned =
+            json!({ "profile": "Jamie", "params": params_json() });
+        assert!(SavedState::from_json(unversioned, DEFAULTS).is_err());
+    }
+}
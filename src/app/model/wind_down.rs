@@ -0,0 +1,93 @@
+//! Winding down after a long time without interaction, so that the app
+//! doesn't keep playing to an empty room overnight.
+
+use std::time::{Duration, Instant};
+
+/// How long the output takes to fade to the idle hum, in seconds.
+pub const WIND_DOWN_FADE_SECS: f64 = 60.0;
+/// How long the idle hum is held before the audio sleeps, in seconds.
+pub const IDLE_HUM_SECS: f64 = 120.0;
+/// The gain of the output during the idle hum, in decibels.
+pub const IDLE_HUM_GAIN_DB: f64 = -36.0;
+/// How long the output takes to fade back in after waking, in seconds.
+pub const WAKE_FADE_SECS: f64 = 2.0;
+
+/// The stages of winding down, in the order they are entered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WindDownStage {
+    /// There was interaction recently.
+    Active,
+    /// The output is fading to the idle hum.
+    FadingOut,
+    /// Sending has stopped and the notes are released, leaving a quiet hum.
+    Humming,
+    /// The audio thread sleeps until the next interaction.
+    Asleep,
+}
+
+/// Tracks how long it has been since the last interaction, and which stage
+/// of winding down that leads to.
+#[derive(Debug)]
+pub struct WindDown {
+    timeout: Duration,
+    last_interaction: Instant,
+    stage: WindDownStage,
+}
+
+impl WindDown {
+    /// Winds down after `timeout_mins` minutes without interaction.
+    pub fn new(timeout_mins: f64) -> Self {
+        Self {
+            timeout: Duration::from_secs_f64(timeout_mins * 60.0),
+            last_interaction: Instant::now(),
+            stage: WindDownStage::Active,
+        }
+    }
+
+    /// Restarts the timeout, and returns the stage which was woken from if
+    /// the app had started winding down.
+    pub fn note_interaction(&mut self) -> Option<WindDownStage> {
+        self.last_interaction = Instant::now();
+
+        let stage = std::mem::replace(&mut self.stage, WindDownStage::Active);
+        (stage != WindDownStage::Active).then_some(stage)
+    }
+
+    /// Returns the next stage if it has been reached. Stages are entered
+    /// one at a time, so none are skipped even if this isn't called for a
+    /// while.
+    pub fn update(&mut self) -> Option<WindDownStage> {
+        let next = match self.stage {
+            WindDownStage::Active => WindDownStage::FadingOut,
+            WindDownStage::FadingOut => WindDownStage::Humming,
+            WindDownStage::Humming => WindDownStage::Asleep,
+            WindDownStage::Asleep => return None,
+        };
+
+        (self.due_stage() >= next).then(|| {
+            self.stage = next;
+            next
+        })
+    }
+
+    /// The stage which the time since the last interaction leads to.
+    fn due_stage(&self) -> WindDownStage {
+        let idle = self.last_interaction.elapsed();
+        let Some(past) = idle.checked_sub(self.timeout)
+        else {
+            return WindDownStage::Active;
+        };
+
+        let past_secs = past.as_secs_f64();
+
+        if past_secs < WIND_DOWN_FADE_SECS {
+            WindDownStage::FadingOut
+        }
+        else if past_secs < WIND_DOWN_FADE_SECS + IDLE_HUM_SECS {
+            WindDownStage::Humming
+        }
+        else {
+            WindDownStage::Asleep
+        }
+    }
+}
@@ -1,4 +1,4 @@
-//! Musical chord representations. Unused in this project.
+//! Musical chord representations.
 
 use crate::prelude::*;
 
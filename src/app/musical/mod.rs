@@ -4,6 +4,10 @@ pub mod chord;
 pub mod note;
 pub mod rhythm16;
 pub mod scale;
+pub mod tempo;
+pub mod transport;
 
 pub use note::*;
 pub use scale::Scale;
+pub use tempo::{GestureTempoEstimator, TapTempo};
+pub use transport::{MusicalPosition, Transport};
@@ -1,7 +1,14 @@
 //! Bitwise rhythm representations. Unused in this device.
 
+use super::MusicalPosition;
+
 pub trait BitwiseRhythm16 {
     fn is_beat(&self, idx: usize) -> bool;
+
+    /// Whether the 16th note at `position` is a beat.
+    fn is_beat_at(&self, position: &MusicalPosition) -> bool {
+        self.is_beat(position.total_sixteenths() as usize)
+    }
 }
 
 // it's bitwise time baby
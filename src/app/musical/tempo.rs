@@ -0,0 +1,211 @@
+//! Tempo detection from key taps and hand motion.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// The slowest tempo which can be detected.
+pub const MIN_DETECTED_BPM: f64 = 60.0;
+/// The fastest tempo which can be detected.
+pub const MAX_DETECTED_BPM: f64 = 180.0;
+
+/// If two taps are further apart than this, the earlier one is discarded.
+const MAX_TAP_INTERVAL_SECS: f64 = 2.0;
+/// The number of tap intervals averaged.
+const NUM_TAP_INTERVALS: usize = 4;
+
+/// Velocity peaks below this value are ignored.
+const PEAK_VELOCITY_THRESHOLD: f64 = 0.15;
+/// The number of peak intervals used to estimate the tempo.
+const NUM_PEAK_INTERVALS: usize = 8;
+/// The minimum number of peak intervals needed before estimating the tempo.
+const MIN_PEAK_INTERVALS: usize = 4;
+/// If no peak occurs for this long, the previous peaks are discarded.
+const MAX_PEAK_INTERVAL_SECS: f64 = 60.0 / MIN_DETECTED_BPM * 2.0;
+/// Peaks closer together than this are treated as one.
+const MIN_PEAK_INTERVAL_SECS: f64 = 60.0 / MAX_DETECTED_BPM;
+
+/// The confidence needed before a gesture tempo estimate is used.
+pub const DEFAULT_TEMPO_CONFIDENCE_THRESHOLD: f64 = 0.8;
+
+/// Folds `bpm` into the detectable range by doubling or halving it.
+fn fold_bpm(mut bpm: f64) -> f64 {
+    if !bpm.is_finite() || bpm <= 0.0 {
+        return bpm;
+    }
+
+    while bpm < MIN_DETECTED_BPM {
+        bpm *= 2.0;
+    }
+    while bpm > MAX_DETECTED_BPM {
+        bpm *= 0.5;
+    }
+
+    bpm
+}
+
+// *** *** *** //
+
+/// Computes a tempo from the average interval between key taps.
+#[derive(Clone, Debug, Default)]
+pub struct TapTempo {
+    last_tap: Option<Instant>,
+    intervals: VecDeque<f64>,
+}
+
+impl TapTempo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a tap, returning the tapped tempo once there are at least
+    /// two taps.
+    pub fn tap(&mut self) -> Option<f64> {
+        let now = Instant::now();
+
+        if let Some(last) = self.last_tap.replace(now) {
+            let interval = now.duration_since(last).as_secs_f64();
+
+            if interval > MAX_TAP_INTERVAL_SECS {
+                self.intervals.clear();
+                return None;
+            }
+
+            if self.intervals.len() == NUM_TAP_INTERVALS {
+                _ = self.intervals.pop_front();
+            }
+
+            self.intervals.push_back(interval);
+        }
+
+        if self.intervals.is_empty() {
+            return None;
+        }
+
+        let avg =
+            self.intervals.iter().sum::<f64>() / self.intervals.len() as f64;
+
+        Some(60.0 / avg)
+    }
+
+    pub fn reset(&mut self) {
+        self.last_tap = None;
+        self.intervals.clear();
+    }
+}
+
+// *** *** *** //
+
+/// A tempo estimate and how confident the estimator is in it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TempoEstimate {
+    pub bpm: f64,
+    /// How consistent the detected periods are, in the range `[0, 1]`.
+    pub confidence: f64,
+}
+
+/// Estimates a tempo from the periodicity of peaks in hand velocity.
+///
+/// This is experimental: motion is rarely strictly periodic, so estimates
+/// should be gated by their confidence before being used.
+#[derive(Clone, Debug)]
+pub struct GestureTempoEstimator {
+    /// The last two velocity values, used to detect peaks.
+    history: [f64; 2],
+    history_time: Instant,
+
+    last_peak: Option<Instant>,
+    intervals: VecDeque<f64>,
+}
+
+impl GestureTempoEstimator {
+    pub fn new() -> Self {
+        Self {
+            history: [0.0; 2],
+            history_time: Instant::now(),
+            last_peak: None,
+            intervals: VecDeque::with_capacity(NUM_PEAK_INTERVALS),
+        }
+    }
+
+    /// Processes a new (normalized) hand velocity value.
+    pub fn push(&mut self, velocity: f64) {
+        let now = Instant::now();
+        let [prev, prev_prev] = self.history;
+
+        // the previous value is a peak if it's larger than both of its
+        // neighbours
+        if prev > PEAK_VELOCITY_THRESHOLD
+            && prev > prev_prev
+            && prev >= velocity
+        {
+            self.on_peak(self.history_time);
+        }
+
+        if let Some(last) = self.last_peak
+            && now.duration_since(last).as_secs_f64() > MAX_PEAK_INTERVAL_SECS
+        {
+            self.reset();
+        }
+
+        self.history = [velocity, prev];
+        self.history_time = now;
+    }
+
+    /// The current tempo estimate, if enough peaks have been detected.
+    pub fn estimate(&self) -> Option<TempoEstimate> {
+        if self.intervals.len() < MIN_PEAK_INTERVALS {
+            return None;
+        }
+
+        let mut sorted: Vec<f64> = self.intervals.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+
+        let median = sorted[sorted.len() / 2];
+
+        // the mean absolute deviation relative to the median is used as a
+        // measure of how periodic the peaks are
+        let deviation = sorted.iter().map(|x| (x - median).abs()).sum::<f64>()
+            / sorted.len() as f64;
+
+        let confidence = (1.0 - deviation / median).clamp(0.0, 1.0);
+
+        Some(TempoEstimate { bpm: fold_bpm(60.0 / median), confidence })
+    }
+
+    /// The current tempo estimate, only if its confidence is at least
+    /// `threshold`.
+    pub fn confident_bpm(&self, threshold: f64) -> Option<f64> {
+        self.estimate()
+            .filter(|est| est.confidence >= threshold)
+            .map(|est| est.bpm)
+    }
+
+    pub fn reset(&mut self) {
+        self.last_peak = None;
+        self.intervals.clear();
+    }
+
+    fn on_peak(&mut self, time: Instant) {
+        if let Some(last) = self.last_peak {
+            let interval = time.duration_since(last).as_secs_f64();
+
+            if interval < MIN_PEAK_INTERVAL_SECS {
+                return;
+            }
+
+            if self.intervals.len() == NUM_PEAK_INTERVALS {
+                _ = self.intervals.pop_front();
+            }
+
+            self.intervals.push_back(interval);
+        }
+
+        self.last_peak = Some(time);
+    }
+}
+
+impl Default for GestureTempoEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
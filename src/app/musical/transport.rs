@@ -0,0 +1,199 @@
+//! Transport location tracking in bars and beats.
+
+use crate::app::audio::metronome::DEFAULT_BEATS_PER_BAR;
+use crate::settings::DEFAULT_BPM;
+use std::time::Instant;
+
+/// The number of 16th notes in each beat. MIDI Song Position Pointer messages
+/// count in 16th notes (or "MIDI beats").
+pub const SIXTEENTHS_PER_BEAT: u32 = 4;
+
+/// The largest position which can be represented by a MIDI Song Position
+/// Pointer message.
+const MAX_SONG_POSITION: u32 = (1 << 14) - 1;
+
+/// A position in musical time, relative to the start of the transport.
+///
+/// Bars and beats are zero-indexed, but are displayed one-indexed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MusicalPosition {
+    /// The current bar.
+    pub bar: u32,
+    /// The current beat within the bar.
+    pub beat: u32,
+    /// How far through the current beat the position is, in `[0, 1)`.
+    pub beat_fraction: f64,
+    /// The total number of beats since the transport started.
+    pub total_beats: f64,
+}
+
+impl MusicalPosition {
+    fn from_beats(total_beats: f64, beats_per_bar: u32) -> Self {
+        let whole_beats = total_beats.floor() as u32;
+
+        Self {
+            bar: whole_beats / beats_per_bar,
+            beat: whole_beats % beats_per_bar,
+            beat_fraction: total_beats.fract(),
+            total_beats,
+        }
+    }
+
+    /// The total number of 16th notes since the transport started.
+    pub fn total_sixteenths(&self) -> u32 {
+        (self.total_beats * f64::from(SIXTEENTHS_PER_BEAT)).floor() as u32
+    }
+
+    /// The current 16th note within the bar.
+    pub fn sixteenth_in_bar(&self) -> u32 {
+        self.beat * SIXTEENTHS_PER_BEAT
+            + (self.beat_fraction * f64::from(SIXTEENTHS_PER_BEAT)) as u32
+    }
+
+    /// The position as a MIDI Song Position Pointer value, i.e. the number of
+    /// 16th notes since the start of the song, clamped to 14 bits.
+    pub fn song_position(&self) -> u16 {
+        self.total_sixteenths().min(MAX_SONG_POSITION) as u16
+    }
+}
+
+impl std::fmt::Display for MusicalPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.bar + 1, self.beat + 1)
+    }
+}
+
+/// Tracks the musical position of the transport from when it was started,
+/// based on its tempo and time signature.
+#[derive(Clone, Copy, Debug)]
+pub struct Transport {
+    bpm: f64,
+    beats_per_bar: u32,
+
+    /// When the transport was last started, continued, or re-timed.
+    reference_time: Instant,
+    /// The number of beats which had elapsed at `reference_time`.
+    reference_beats: f64,
+    /// The number of seconds which had elapsed at `reference_time`.
+    reference_secs: f64,
+
+    is_running: bool,
+}
+
+impl Transport {
+    /// Returns a new, stopped `Transport`.
+    pub fn new(bpm: f64, beats_per_bar: u32) -> Self {
+        Self {
+            bpm,
+            beats_per_bar: beats_per_bar.max(1),
+            reference_time: Instant::now(),
+            reference_beats: 0.0,
+            reference_secs: 0.0,
+            is_running: false,
+        }
+    }
+
+    /// Starts the transport from the beginning.
+    pub fn start(&mut self) {
+        self.reference_beats = 0.0;
+        self.reference_secs = 0.0;
+        self.reference_time = Instant::now();
+        self.is_running = true;
+    }
+
+    /// Continues the transport from wherever it was stopped.
+    pub fn continue_playback(&mut self) {
+        if self.is_running {
+            return;
+        }
+
+        self.reference_time = Instant::now();
+        self.is_running = true;
+    }
+
+    /// Stops the transport, holding its current position.
+    pub fn stop(&mut self) {
+        self.reference_beats = self.beats();
+        self.reference_secs = self.secs();
+        self.is_running = false;
+    }
+
+    /// Moves the transport to `beats` beats from its start, without changing
+    /// whether it is running. The elapsed time is moved as if the transport
+    /// had always been at the current tempo.
+    pub fn seek(&mut self, beats: f64) {
+        self.reference_beats = beats.max(0.0);
+        self.reference_secs = self.reference_beats * 60.0 / self.bpm;
+        self.reference_time = Instant::now();
+    }
+
+    pub const fn is_running(&self) -> bool {
+        self.is_running
+    }
+
+    /// Sets the tempo of the transport without moving its current position.
+    pub fn set_bpm(&mut self, bpm: f64) {
+        self.rebase();
+        self.bpm = bpm;
+    }
+
+    pub const fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    /// Sets the number of beats in each bar. The total number of beats is
+    /// kept, so the current bar may change.
+    pub fn set_beats_per_bar(&mut self, beats_per_bar: u32) {
+        self.beats_per_bar = beats_per_bar.max(1);
+    }
+
+    pub const fn beats_per_bar(&self) -> u32 {
+        self.beats_per_bar
+    }
+
+    /// The total number of beats since the transport started.
+    pub fn beats(&self) -> f64 {
+        if self.is_running {
+            let elapsed = self.reference_time.elapsed().as_secs_f64();
+            self.reference_beats + elapsed * self.bpm / 60.0
+        }
+        else {
+            self.reference_beats
+        }
+    }
+
+    /// The total time which the transport has been running for, in seconds.
+    /// Unlike the number of beats, this isn't affected by tempo changes.
+    pub fn secs(&self) -> f64 {
+        if self.is_running {
+            self.reference_secs + self.reference_time.elapsed().as_secs_f64()
+        }
+        else {
+            self.reference_secs
+        }
+    }
+
+    /// The current position of the transport.
+    pub fn position(&self) -> MusicalPosition {
+        MusicalPosition::from_beats(self.beats(), self.beats_per_bar)
+    }
+
+    /// The length of `num_bars` bars in seconds at the current tempo.
+    pub fn bars_to_secs(&self, num_bars: f64) -> f64 {
+        num_bars * f64::from(self.beats_per_bar) * 60.0 / self.bpm
+    }
+
+    /// Moves the reference point to now, so that tempo changes only affect
+    /// the position from this point onwards.
+    fn rebase(&mut self) {
+        self.reference_beats = self.beats();
+        self.reference_secs = self.secs();
+        self.reference_time = Instant::now();
+    }
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self::new(DEFAULT_BPM, DEFAULT_BEATS_PER_BAR)
+    }
+}
@@ -0,0 +1,309 @@
+//! Authentication of control messages with a pre-shared key, so that only
+//! operators who hold the key can control the app over a shared network.
+//!
+//! An authenticated control message carries two extra arguments after its
+//! own:
+//!
+//! 1. a counter (a long), which must increase with each message, such as
+//!    the sender's time in milliseconds,
+//! 2. a signature (a blob), which is the HMAC-SHA256 of the OSC-encoded
+//!    message with the counter, but without the signature.
+//!
+//! The counter stops a recorded message from being replayed. Messages are
+//! authenticated but not encrypted, as none of them are secret.
+//!
+//! The tracker handshake is never authenticated, as trackers don't hold the
+//! key.
+
+use super::*;
+use nannou_osc::rosc::{encoder, OscPacket};
+use nannou_osc::{Message, Type};
+
+/// The length of a SHA-256 digest, and so of a signature, in bytes.
+pub const SIGNATURE_LEN: usize = 32;
+/// The shortest key which is accepted, in bytes.
+pub const MIN_KEY_LEN: usize = 16;
+
+const SHA256_BLOCK_LEN: usize = 64;
+
+/// Why a control message was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthFailure {
+    /// The message doesn't end with a counter and a signature.
+    Unsigned,
+    /// The signature doesn't match the message.
+    BadSignature,
+    /// The counter isn't greater than the last accepted message's.
+    Replayed,
+}
+
+impl std::fmt::Display for AuthFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Unsigned => "the message isn't signed",
+            Self::BadSignature => "the signature doesn't match",
+            Self::Replayed => "the counter has already been used",
+        })
+    }
+}
+
+/// Verifies and signs control messages with a pre-shared key.
+#[derive(Clone, Debug)]
+pub struct ControlAuthenticator {
+    key: Vec<u8>,
+    /// The counter of the last accepted message.
+    last_counter: Option<i64>,
+}
+
+impl ControlAuthenticator {
+    /// # Errors
+    ///
+    /// Returns an error if `key` is shorter than [`MIN_KEY_LEN`].
+    pub fn new(key: &[u8]) -> Result<Self, String> {
+        if key.len() < MIN_KEY_LEN {
+            return Err(format!(
+                "the control key must be at least {MIN_KEY_LEN} bytes long"
+            ));
+        }
+
+        Ok(Self { key: key.to_vec(), last_counter: None })
+    }
+
+    /// Reads the key from the file at `path`, ignoring any whitespace
+    /// around it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be read, or if the key is too
+    /// short.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let key = std::fs::read(path).map_err(|e| {
+            format!("failed to read control key \"{path}\": {e}")
+        })?;
+
+        let is_key = |b: &u8| !b.is_ascii_whitespace();
+        let start = key.iter().position(is_key).unwrap_or(key.len());
+        let end = key.iter().rposition(is_key).map_or(start, |i| i + 1);
+
+        Self::new(&key[start..end])
+    }
+
+    /// Checks that `msg` is signed with the key, and returns it without its
+    /// counter and signature.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the message was rejected, if it was.
+    pub fn verify(&mut self, mut msg: Message) -> Result<Message, AuthFailure> {
+        let (Some(Type::Blob(signature)), Some(&Type::Long(counter))) =
+            (msg.args.pop(), msg.args.last())
+        else {
+            return Err(AuthFailure::Unsigned);
+        };
+
+        let expected = self.signature(&msg);
+
+        if !constant_time_eq(&signature, &expected) {
+            return Err(AuthFailure::BadSignature);
+        }
+
+        if self.last_counter.is_some_and(|last| counter <= last) {
+            return Err(AuthFailure::Replayed);
+        }
+
+        self.last_counter = Some(counter);
+        msg.args.pop();
+
+        Ok(msg)
+    }
+
+    /// Appends `counter` and the signature to `msg`.
+    pub fn sign(&self, mut msg: Message, counter: i64) -> Message {
+        msg.args.push(Type::Long(counter));
+
+        let signature = self.signature(&msg);
+        msg.args.push(Type::Blob(signature.to_vec()));
+
+        msg
+    }
+
+    /// The signature of `msg`, which already holds its counter.
+    fn signature(&self, msg: &Message) -> [u8; SIGNATURE_LEN] {
+        let packet = OscPacket::Message(msg.clone());
+        // messages which can't be encoded are signed as empty, which can't
+        // match a genuine signature
+        let bytes = encoder::encode(&packet).unwrap_or_default();
+
+        hmac_sha256(&self.key, &bytes)
+    }
+}
+
+/// Compares two byte strings in a time which doesn't depend on where they
+/// differ, so that a signature can't be guessed byte by byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// The HMAC of `message`, as in RFC 2104, with SHA-256.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; SIGNATURE_LEN] {
+    let mut block = [0; SHA256_BLOCK_LEN];
+
+    // keys longer than a block are hashed first
+    if key.len() > SHA256_BLOCK_LEN {
+        block[..SIGNATURE_LEN].copy_from_slice(&sha256(key));
+    }
+    else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let pad = |byte: u8| block.map(|k| k ^ byte);
+
+    let inner = sha256(&[&pad(0x36)[..], message].concat());
+    sha256(&[&pad(0x5C)[..], &inner[..]].concat())
+}
+
+/// The SHA-256 digest of `data`, as in FIPS 180-4.
+fn sha256(data: &[u8]) -> [u8; SIGNATURE_LEN] {
+    const K: [u32; 64] = [
+        0x428A_2F98, 0x7137_4491, 0xB5C0_FBCF, 0xE9B5_DBA5, 0x3956_C25B,
+        0x59F1_11F1, 0x923F_82A4, 0xAB1C_5ED5, 0xD807_AA98, 0x1283_5B01,
+        0x2431_85BE, 0x550C_7DC3, 0x72BE_5D74, 0x80DE_B1FE, 0x9BDC_06A7,
+        0xC19B_F174, 0xE49B_69C1, 0xEFBE_4786, 0x0FC1_9DC6, 0x240C_A1CC,
+        0x2DE9_2C6F, 0x4A74_84AA, 0x5CB0_A9DC, 0x76F9_88DA, 0x983E_5152,
+        0xA831_C66D, 0xB003_27C8, 0xBF59_7FC7, 0xC6E0_0BF3, 0xD5A7_9147,
+        0x06CA_6351, 0x1429_2967, 0x27B7_0A85, 0x2E1B_2138, 0x4D2C_6DFC,
+        0x5338_0D13, 0x650A_7354, 0x766A_0ABB, 0x81C2_C92E, 0x9272_2C85,
+        0xA2BF_E8A1, 0xA81A_664B, 0xC24B_8B70, 0xC76C_51A3, 0xD192_E819,
+        0xD699_0624, 0xF40E_3585, 0x106A_A070, 0x19A4_C116, 0x1E37_6C08,
+        0x2748_774C, 0x34B0_BCB5, 0x391C_0CB3, 0x4ED8_AA4A, 0x5B9C_CA4F,
+        0x682E_6FF3, 0x748F_82EE, 0x78A5_636F, 0x84C8_7814, 0x8CC7_0208,
+        0x90BE_FFFA, 0xA450_6CEB, 0xBEF9_A3F7, 0xC671_78F2,
+    ];
+
+    let mut state: [u32; 8] = [
+        0x6A09_E667, 0xBB67_AE85, 0x3C6E_F372, 0xA54F_F53A, 0x510E_527F,
+        0x9B05_688C, 0x1F83_D9AB, 0x5BE0_CD19,
+    ];
+
+    // the data is padded with a one bit, then zeros, then its length in
+    // bits, to a whole number of blocks
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % SHA256_BLOCK_LEN != SHA256_BLOCK_LEN - 8 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in padded.chunks_exact(SHA256_BLOCK_LEN) {
+        let mut w = [0_u32; 64];
+
+        for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7)
+                ^ w[i - 15].rotate_right(18)
+                ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17)
+                ^ w[i - 2].rotate_right(19)
+                ^ (w[i - 2] >> 10);
+
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+
+        for (&k, &w) in K.iter().zip(&w) {
+            let s1 =
+                e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            let s0 =
+                a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            (h, g, f, e) = (g, f, e, d.wrapping_add(t1));
+            (d, c, b, a) = (c, b, a, t1.wrapping_add(t2));
+        }
+
+        for (s, x) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(x);
+        }
+    }
+
+    let mut digest = [0; SIGNATURE_LEN];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn hmac_matches_the_rfc_4231_vectors() {
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        assert_eq!(
+            hex(&hmac_sha256(&[0x0B; 20], b"Hi There")),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+
+        // a key longer than a block is hashed first
+        assert_eq!(
+            hex(&hmac_sha256(
+                &[0xAA; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn only_signed_fresh_messages_are_accepted() {
+        let mut auth =
+            ControlAuthenticator::new(b"a shared secret key").unwrap();
+        let msg = Message {
+            addr: String::from("/maestro/fx/delay_mix"),
+            args: vec![Type::Float(0.5)],
+        };
+
+        let signed = auth.sign(msg.clone(), 1);
+        assert_eq!(auth.verify(signed.clone()), Ok(msg.clone()));
+
+        // the same message can't be replayed
+        assert_eq!(auth.verify(signed), Err(AuthFailure::Replayed));
+
+        // nor signed with another key, or tampered with
+        let other = ControlAuthenticator::new(b"another secret key").unwrap();
+        let forged = other.sign(msg.clone(), 2);
+        assert_eq!(auth.verify(forged), Err(AuthFailure::BadSignature));
+
+        let mut tampered = auth.sign(msg.clone(), 3);
+        tampered.args[0] = Type::Float(1.0);
+        assert_eq!(auth.verify(tampered), Err(AuthFailure::BadSignature));
+
+        assert_eq!(auth.verify(msg), Err(AuthFailure::Unsigned));
+        assert!(ControlAuthenticator::new(b"short").is_err());
+    }
+}
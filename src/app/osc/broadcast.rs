@@ -0,0 +1,144 @@
+//! OSC output of the app's own state, so that external visuals (such as
+//! TouchDesigner or Processing sketches) can follow the performance.
+//!
+//! Each update is sent as a bundle holding:
+//!
+//! - `/maestro/state/mode` with the mode's name,
+//! - `/maestro/state/hand/1` and `/maestro/state/hand/2` with the hand's
+//!   `x`, `y` and `z` position, openness and velocity, for each hand which is
+//!   being tracked,
+//! - `/maestro/state/cc` with the channel, CC number and value (from `0` to
+//!   `1`) of each active CC.
+
+use super::*;
+use nannou::prelude::DVec3;
+use nannou_osc::rosc::OscPacket;
+
+/// The OSC address which the mode is sent on.
+pub const STATE_MODE_OSC_ADDRESS: &str = "/maestro/state/mode";
+/// The OSC address prefix which each hand is sent on, followed by its number.
+pub const STATE_HAND_OSC_ADDRESS: &str = "/maestro/state/hand";
+/// The OSC address which each active CC value is sent on.
+pub const STATE_CC_OSC_ADDRESS: &str = "/maestro/state/cc";
+
+/// The state of one tracked hand.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HandFrame {
+    /// The hand's center of mass, after calibration and dead-zones.
+    pub com: DVec3,
+    pub openness: f64,
+    pub velocity: f32,
+}
+
+/// A snapshot of the state which is published to visualizers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StateFrame {
+    pub mode: &'static str,
+    pub hands: [Option<HandFrame>; 2],
+    /// The channel, CC number and value of each active CC.
+    pub ccs: Vec<(u8, u8, f32)>,
+}
+
+impl StateFrame {
+    fn to_packet(&self) -> OscPacket {
+        let message = |addr: String, args: Vec<osc::Type>| {
+            OscPacket::Message(osc::Message { addr, args })
+        };
+
+        let mut content = Vec::with_capacity(3 + self.ccs.len());
+
+        content.push(message(
+            STATE_MODE_OSC_ADDRESS.to_string(),
+            vec![osc::Type::String(self.mode.to_string())],
+        ));
+
+        for (i, hand) in self.hands.iter().enumerate() {
+            let Some(hand) = hand
+            else {
+                continue;
+            };
+
+            content.push(message(
+                format!("{STATE_HAND_OSC_ADDRESS}/{}", i + 1),
+                vec![
+                    osc::Type::Float(hand.com.x as f32),
+                    osc::Type::Float(hand.com.y as f32),
+                    osc::Type::Float(hand.com.z as f32),
+                    osc::Type::Float(hand.openness as f32),
+                    osc::Type::Float(hand.velocity),
+                ],
+            ));
+        }
+
+        content.extend(self.ccs.iter().map(|&(channel, cc, value)| {
+            message(
+                STATE_CC_OSC_ADDRESS.to_string(),
+                vec![
+                    osc::Type::Int(channel as i32),
+                    osc::Type::Int(cc as i32),
+                    osc::Type::Float(value),
+                ],
+            )
+        }));
+
+        OscPacket::Bundle(osc::Bundle {
+            timetag: timetag::IMMEDIATE,
+            content,
+        })
+    }
+}
+
+// *** *** *** //
+
+/// Periodically publishes the latest [`StateFrame`] over OSC.
+pub struct OSCStateBroadcaster {
+    timer: TimerThread,
+    addr: SocketAddr,
+}
+
+impl OSCStateBroadcaster {
+    /// Returns a broadcaster which sends each new frame from `frames` to
+    /// `addr`. Frames are only sent if they have changed since the last one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transport could not be created.
+    pub fn new(
+        addr: SocketAddr,
+        transport: OSCTransportKind,
+        frames: triple_buffer::Output<StateFrame>,
+    ) -> std::io::Result<Self> {
+        let sender = Mutex::new(transport.connect(addr)?);
+        let frames = Mutex::new(frames);
+
+        let timer = TimerThread::new(move || {
+            let (Ok(mut osc), Ok(mut frames)) = (sender.lock(), frames.lock())
+            else {
+                return;
+            };
+
+            if !frames.updated() {
+                return;
+            }
+
+            // NOTE(jamie): visualizers are optional, so failures (e.g. while
+            // nothing is listening) aren't reported.
+            _ = osc.send(&frames.read().to_packet());
+        });
+
+        Ok(Self { timer, addr })
+    }
+
+    /// The address which state is sent to.
+    pub const fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn start(&mut self) {
+        self.timer.start_hz(OSC_STATE_BROADCAST_RATE);
+    }
+
+    pub fn stop(&mut self) {
+        self.timer.stop();
+    }
+}
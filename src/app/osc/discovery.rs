@@ -0,0 +1,386 @@
+//! Discovery of the hand tracker and the EME on the local network, via
+//! multicast DNS service discovery (mDNS/DNS-SD, as used by Bonjour).
+//!
+//! Trackers should advertise [`TRACKER_SERVICE`] on the port which they
+//! receive control messages on (such as restart requests), and the EME
+//! should advertise [`EME_SERVICE`] on the port which it receives requests
+//! on. A single query is sent for both, from an ephemeral port, so that
+//! responders answer directly (RFC 6762, section 6.7) and the multicast
+//! group doesn't need to be joined.
+
+use super::*;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// The DNS-SD service type which hand trackers advertise.
+pub const TRACKER_SERVICE: &str = "_maestro-tracker._udp.local";
+/// The DNS-SD service type which the EME advertises.
+pub const EME_SERVICE: &str = "_maestro-eme._udp.local";
+/// How long to wait for responses.
+pub const DEFAULT_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+const MDNS_ADDR: SocketAddr =
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+/// How often the query is repeated while waiting, in case it was lost.
+const QUERY_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_PACKET_SIZE: usize = 9000;
+/// The most compression pointers which are followed in one name, so that
+/// malformed packets can't loop.
+const MAX_NAME_JUMPS: usize = 16;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+/// Asks for a unicast response, in the top bit of a question's class.
+const UNICAST_RESPONSE: u16 = 0x8000;
+/// Marks a packet as a response, in the header's flags.
+const RESPONSE_FLAG: u16 = 0x8000;
+const HEADER_LEN: usize = 12;
+
+/// A service instance which was found on the network.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveredService {
+    /// The service type, such as [`TRACKER_SERVICE`].
+    pub service: String,
+    /// The instance's name, such as `"Studio Tracker._maestro-tracker._udp
+    /// .local"`.
+    pub instance: String,
+    pub addr: SocketAddr,
+}
+
+/// Browses for instances of each of `services`, and returns those which
+/// respond within `timeout`.
+///
+/// # Errors
+///
+/// Returns an error if the query could not be sent.
+pub fn discover(
+    services: &[&str],
+    timeout: Duration,
+) -> std::io::Result<Vec<DiscoveredService>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    let query = encode_query(services);
+    let start = Instant::now();
+    let mut last_query: Option<Instant> = None;
+    let mut found: Vec<DiscoveredService> = Vec::new();
+    let mut buf = [0; MAX_PACKET_SIZE];
+
+    while start.elapsed() < timeout {
+        if last_query.map_or(true, |t| t.elapsed() >= QUERY_INTERVAL) {
+            socket.send_to(&query, MDNS_ADDR)?;
+            last_query = Some(Instant::now());
+        }
+
+        let wait = QUERY_INTERVAL.min(timeout.saturating_sub(start.elapsed()));
+        if wait.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(wait))?;
+
+        let Ok((len, source)) = socket.recv_from(&mut buf)
+        else {
+            continue;
+        };
+
+        for service in parse_response(&buf[..len], services, source.ip()) {
+            if !found.iter().any(|s| s.instance == service.instance) {
+                found.push(service);
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Looks for the tracker and the EME, and uses those which are found in
+/// place of the addresses in `args`. Those which aren't found keep the
+/// addresses given as arguments.
+pub fn discover_endpoints(args: &mut Arguments, timeout: Duration) {
+    let found = match discover(&[TRACKER_SERVICE, EME_SERVICE], timeout) {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("failed to discover services: {e}");
+            return;
+        }
+    };
+
+    let destination = |service: &DiscoveredService| {
+        (service.addr.ip().to_string(), service.addr.port())
+    };
+
+    if let Some(tracker) = found.iter().find(|s| s.service == TRACKER_SERVICE)
+    {
+        println!(
+            "discovered tracker \"{}\" at {}",
+            tracker.instance, tracker.addr
+        );
+        args.tracker_restart_destination = Some(destination(tracker));
+    }
+    else {
+        println!("no tracker was discovered, so the arguments are used");
+    }
+
+    let emes: Vec<_> = found
+        .iter()
+        .filter(|s| s.service == EME_SERVICE)
+        .inspect(|eme| {
+            println!("discovered EME \"{}\" at {}", eme.instance, eme.addr);
+        })
+        .map(destination)
+        .collect();
+
+    if emes.is_empty() {
+        println!("no EME was discovered, so the arguments are used");
+    }
+    else {
+        args.osc_tx_destinations = emes;
+    }
+}
+
+/// Encodes a query for the PTR records of each of `services`.
+fn encode_query(services: &[&str]) -> Vec<u8> {
+    let mut packet = vec![0; HEADER_LEN];
+    packet[4..6].copy_from_slice(&(services.len() as u16).to_be_bytes());
+
+    for service in services {
+        for label in service.trim_end_matches('.').split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+
+        packet.push(0);
+        packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        packet.extend_from_slice(&(CLASS_IN | UNICAST_RESPONSE).to_be_bytes());
+    }
+
+    packet
+}
+
+/// A resource record from a response.
+struct Record<'a> {
+    name: String,
+    kind: u16,
+    data: &'a [u8],
+    /// The offset of `data` in the packet, which names in it may point
+    /// before.
+    data_pos: usize,
+}
+
+/// Returns the instances of `services` in a response from `source`. If an
+/// instance's address isn't in the response, `source` is used.
+fn parse_response(
+    packet: &[u8],
+    services: &[&str],
+    source: IpAddr,
+) -> Vec<DiscoveredService> {
+    let Some(records) = parse_records(packet)
+    else {
+        return Vec::new();
+    };
+
+    let address_of = |host: &str| {
+        records.iter().find_map(|r| match (r.kind, r.data) {
+            (TYPE_A, &[a, b, c, d]) if names_eq(&r.name, host) => {
+                Some(IpAddr::V4(Ipv4Addr::new(a, b, c, d)))
+            }
+            (TYPE_AAAA, data) if names_eq(&r.name, host) => {
+                <[u8; 16]>::try_from(data)
+                    .ok()
+                    .map(|octets| IpAddr::V6(Ipv6Addr::from(octets)))
+            }
+            _ => None,
+        })
+    };
+
+    let mut found = Vec::new();
+
+    for ptr in records.iter().filter(|r| r.kind == TYPE_PTR) {
+        let Some(&service) = services.iter().find(|s| names_eq(&ptr.name, s))
+        else {
+            continue;
+        };
+        let Some((instance, _)) = read_name(packet, ptr.data_pos)
+        else {
+            continue;
+        };
+
+        // the SRV's data is its priority, weight and port, then its host
+        let srv = records.iter().find(|r| {
+            r.kind == TYPE_SRV
+                && r.data.len() > 6
+                && names_eq(&r.name, &instance)
+        });
+        let Some(srv) = srv
+        else {
+            continue;
+        };
+
+        let port = u16::from_be_bytes([srv.data[4], srv.data[5]]);
+        let ip = read_name(packet, srv.data_pos + 6)
+            .and_then(|(host, _)| address_of(&host))
+            .unwrap_or(source);
+
+        found.push(DiscoveredService {
+            service: service.to_string(),
+            instance,
+            addr: SocketAddr::new(ip, port),
+        });
+    }
+
+    found
+}
+
+/// Parses every resource record of a response, or `None` if the packet is
+/// malformed or isn't a response.
+fn parse_records(packet: &[u8]) -> Option<Vec<Record<'_>>> {
+    let header = packet.get(..HEADER_LEN)?;
+    let field = |i: usize| u16::from_be_bytes([header[i], header[i + 1]]);
+
+    if field(2) & RESPONSE_FLAG == 0 {
+        return None;
+    }
+
+    let num_questions = field(4);
+    let num_records =
+        field(6) as usize + field(8) as usize + field(10) as usize;
+    let mut pos = HEADER_LEN;
+
+    for _ in 0..num_questions {
+        // the type and class follow the name
+        pos = read_name(packet, pos)?.1 + 4;
+    }
+
+    let mut records = Vec::with_capacity(num_records);
+
+    for _ in 0..num_records {
+        let (name, next) = read_name(packet, pos)?;
+        let fixed = packet.get(next..next + 10)?;
+
+        let kind = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let data_len = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        let data_pos = next + 10;
+
+        records.push(Record {
+            name,
+            kind,
+            data: packet.get(data_pos..data_pos + data_len)?,
+            data_pos,
+        });
+
+        pos = data_pos + data_len;
+    }
+
+    Some(records)
+}
+
+/// Reads the (possibly compressed) name at `pos`, and returns it with the
+/// position after it.
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *packet.get(pos)? as usize;
+
+        // the top two bits mark a pointer to the rest of the name
+        if len & 0xC0 == 0xC0 {
+            jumps += 1;
+            if jumps > MAX_NAME_JUMPS {
+                return None;
+            }
+
+            let offset = ((len & 0x3F) << 8) | *packet.get(pos + 1)? as usize;
+            end.get_or_insert(pos + 2);
+            pos = offset;
+        }
+        else if len == 0 {
+            let end = end.unwrap_or(pos + 1);
+            return Some((labels.join("."), end));
+        }
+        else {
+            let label = packet.get(pos + 1..pos + 1 + len)?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            pos += 1 + len;
+        }
+    }
+}
+
+/// Whether two DNS names are equal, which ignores case and a trailing dot.
+fn names_eq(a: &str, b: &str) -> bool {
+    a.trim_end_matches('.').eq_ignore_ascii_case(b.trim_end_matches('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_name(packet: &mut Vec<u8>, name: &str) {
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+    }
+
+    fn push_record(packet: &mut Vec<u8>, kind: u16, data: &[u8]) {
+        packet.extend_from_slice(&kind.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet.extend_from_slice(&120_u32.to_be_bytes());
+        packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        packet.extend_from_slice(data);
+    }
+
+    #[test]
+    fn query_asks_for_each_service() {
+        let query = encode_query(&[TRACKER_SERVICE, EME_SERVICE]);
+        let records = parse_records(&query);
+
+        // queries aren't responses
+        assert!(records.is_none());
+        assert_eq!(&query[4..6], &[0, 2]);
+
+        let (name, next) = read_name(&query, HEADER_LEN).unwrap();
+        assert_eq!(name, TRACKER_SERVICE);
+        assert_eq!(&query[next..next + 4], &[0, 12, 0x80, 1]);
+        assert_eq!(read_name(&query, next + 4).unwrap().0, EME_SERVICE);
+    }
+
+    #[test]
+    fn response_resolves_the_instance_address() {
+        let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 3, 0, 0, 0, 1];
+
+        // the PTR's instance name is compressed, pointing at the service
+        let service_pos = packet.len();
+        push_name(&mut packet, TRACKER_SERVICE);
+        let mut instance = vec![7];
+        instance.extend_from_slice(b"Tracker");
+        instance.extend_from_slice(&[0xC0, service_pos as u8]);
+        push_record(&mut packet, TYPE_PTR, &instance);
+
+        push_name(&mut packet, "Tracker._maestro-tracker._udp.local");
+        let mut srv = vec![0, 0, 0, 0, 0x1F, 0x90];
+        push_name(&mut srv, "tracker-host.local");
+        push_record(&mut packet, TYPE_SRV, &srv);
+
+        push_name(&mut packet, "unrelated.local");
+        push_record(&mut packet, TYPE_A, &[10, 0, 0, 9]);
+
+        push_name(&mut packet, "TRACKER-HOST.local");
+        push_record(&mut packet, TYPE_A, &[192, 168, 1, 20]);
+
+        let source = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 99));
+        let found = parse_response(&packet, &[TRACKER_SERVICE], source);
+
+        assert_eq!(found, vec![DiscoveredService {
+            service: TRACKER_SERVICE.to_string(),
+            instance: "Tracker._maestro-tracker._udp.local".to_string(),
+            addr: "192.168.1.20:8080".parse().unwrap(),
+        }]);
+
+        // other services are ignored
+        assert!(parse_response(&packet, &[EME_SERVICE], source).is_empty());
+    }
+}
@@ -71,16 +71,61 @@ impl ToJson for EMEPosition {
 
 // *** *** *** //
 
+/// A jump to a named point in the arrangement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EMEJump {
+    /// Jumps to the start of a section.
+    Section(String),
+    /// Jumps to a marker.
+    Marker(String),
+}
+
+impl ToJson for EMEJump {
+    fn as_json(&self) -> Value {
+        match self {
+            Self::Section(name) => json!({ "section": name }),
+            Self::Marker(name) => json!({ "marker": name }),
+        }
+    }
+}
+
+// *** *** *** //
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct EMERequest {
+    /// Correlates the request with the EME's response, if acknowledgements
+    /// are enabled.
+    pub id: Option<u32>,
     pub arrangement: Option<String>,
     pub playback: Option<EMEPlayback>,
     pub position: Option<EMEPosition>,
+    /// The tempo in beats per minute.
+    pub tempo: Option<f64>,
+    /// The master volume, from `0` to `1`.
+    pub volume: Option<f32>,
+    pub jump: Option<EMEJump>,
+    /// How long (in seconds) to crossfade over when the arrangement or
+    /// position jumps.
+    pub crossfade: Option<f32>,
 }
 
 impl EMERequest {
     pub const fn new() -> Self {
-        Self { arrangement: None, playback: None, position: None }
+        Self {
+            id: None,
+            arrangement: None,
+            playback: None,
+            position: None,
+            tempo: None,
+            volume: None,
+            jump: None,
+            crossfade: None,
+        }
+    }
+
+    pub const fn with_id(mut self, id: u32) -> Self {
+        self.id = Some(id);
+        self
     }
 
     pub const fn with_playback(mut self, playback: EMEPlayback) -> Self {
@@ -98,6 +143,26 @@ impl EMERequest {
         self
     }
 
+    pub fn with_tempo(mut self, bpm: f64) -> Self {
+        self.tempo = Some(bpm.clamp(MIN_BPM, MAX_BPM));
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = Some(volume.clamp(0.0, 1.0));
+        self
+    }
+
+    pub fn with_jump(mut self, jump: EMEJump) -> Self {
+        self.jump = Some(jump);
+        self
+    }
+
+    pub fn with_crossfade(mut self, secs: f32) -> Self {
+        self.crossfade = Some(secs.max(0.0));
+        self
+    }
+
     pub fn is_start(&self) -> bool {
         self.playback.is_some_and(|p| p == EMEPlayback::Start)
     }
@@ -106,10 +171,26 @@ impl EMERequest {
         self.playback.is_some_and(|p| p == EMEPlayback::Stop)
     }
 
+    /// Whether the request only updates the position, so is superseded by
+    /// the next position update.
+    pub const fn is_position_only(&self) -> bool {
+        self.position.is_some()
+            && self.arrangement.is_none()
+            && self.playback.is_none()
+            && self.tempo.is_none()
+            && self.volume.is_none()
+            && self.jump.is_none()
+            && self.crossfade.is_none()
+    }
+
     pub const fn is_empty(&self) -> bool {
         self.arrangement.is_none()
             && self.playback.is_none()
             && self.position.is_none()
+            && self.tempo.is_none()
+            && self.volume.is_none()
+            && self.jump.is_none()
+            && self.crossfade.is_none()
     }
 }
 
@@ -140,11 +221,35 @@ impl ToJson for EMERequest {
             obj.insert("position".into(), json);
         }
 
+        if let Some(tempo) = self.tempo {
+            obj.insert("tempo".into(), json!(tempo));
+        }
+
+        if let Some(volume) = self.volume {
+            obj.insert("volume".into(), json!(volume));
+        }
+
+        if let Some(jump) = &self.jump {
+            let json = jump.as_json();
+            obj.insert("jump".into(), json);
+        }
+
+        if let Some(crossfade) = self.crossfade {
+            obj.insert("crossfade".into(), json!(crossfade));
+        }
+
         assert!(
             is_eme_msg_valid(&result),
             "failed to validate EME message: {result}"
         );
 
+        // NOTE(jamie): the ID is envelope data rather than part of the
+        // request itself, so it's added after validation.
+        if let Some(id) = self.id {
+            let obj = unsafe { result.as_object_mut().unwrap_unchecked() };
+            obj.insert("id".into(), json!(id));
+        }
+
         // println!("for {self:?}, got \"{result}\"");
 
         result
@@ -0,0 +1,384 @@
+//! Responses from the EME, and tracking of which requests they acknowledge.
+//!
+//! When acknowledgements are enabled, each request is sent with an `"id"`,
+//! and the EME replies on [`EME_OSC_RESPONSE_CHANNEL`] with a JSON string of
+//! the form:
+//!
+//! ```json
+//! { "id": 12, "status": "ok" }
+//! { "id": 13, "status": "error", "error": "unknown arrangement \"FOO\"" }
+//! ```
+//!
+//! The EME also replies to arrangement queries (sent on
+//! [`EME_OSC_QUERY_CHANNEL`]) on [`EME_OSC_ARRANGEMENTS_CHANNEL`], with a JSON
+//! string of the form:
+//!
+//! ```json
+//! { "arrangements": ["MAESTRO", "MAESTRO_SPARSE"] }
+//! ```
+
+use super::*;
+use nannou_osc::rosc::OscPacket;
+use serde_json::Value;
+use std::{collections::HashMap, time::Instant};
+
+/// A reply from the EME to a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EMEResponse {
+    pub id: u32,
+    /// `Err` holds the reason the EME gave for rejecting the request.
+    pub result: Result<(), String>,
+}
+
+impl EMEResponse {
+    /// Parses all responses held in `packet`, which may be a bundle. Any
+    /// messages which are not responses are ignored.
+    pub fn from_packet(packet: &OscPacket) -> Vec<Self> {
+        match packet {
+            OscPacket::Message(msg) => {
+                Self::from_message(msg).into_iter().collect()
+            }
+            OscPacket::Bundle(bundle) => {
+                bundle.content.iter().flat_map(Self::from_packet).collect()
+            }
+        }
+    }
+
+    fn from_message(msg: &osc::Message) -> Option<Self> {
+        if msg.addr != EME_OSC_RESPONSE_CHANNEL {
+            return None;
+        }
+
+        let Some(osc::Type::String(s)) = msg.args.first()
+        else {
+            return None;
+        };
+
+        Self::from_json_str(s)
+    }
+
+    /// Parses a response from a JSON string.
+    pub fn from_json_str(json: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(json).ok()?;
+        let id = u32::try_from(value.get("id")?.as_u64()?).ok()?;
+
+        let result = match value.get("status")?.as_str()? {
+            "ok" => Ok(()),
+            "error" => Err(value
+                .get("error")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+                .to_string()),
+            _ => return None,
+        };
+
+        Some(Self { id, result })
+    }
+}
+
+/// Parses the list of arrangement names held in `packet`, which may be a
+/// bundle, if it holds one.
+pub fn arrangements_from_packet(packet: &OscPacket) -> Option<Vec<String>> {
+    match packet {
+        OscPacket::Message(msg) => {
+            if msg.addr != EME_OSC_ARRANGEMENTS_CHANNEL {
+                return None;
+            }
+
+            let Some(osc::Type::String(s)) = msg.args.first()
+            else {
+                return None;
+            };
+
+            let value: Value = serde_json::from_str(s).ok()?;
+
+            value
+                .get("arrangements")?
+                .as_array()?
+                .iter()
+                .map(|name| name.as_str().map(String::from))
+                .collect()
+        }
+        OscPacket::Bundle(bundle) => {
+            bundle.content.iter().find_map(arrangements_from_packet)
+        }
+    }
+}
+
+/// Something the EME has reported back to the app.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EMEEvent {
+    /// A request was rejected, or never acknowledged.
+    RequestFailed(EMERequestFailure),
+    /// The names of the arrangements which the EME can play.
+    Arrangements(Vec<String>),
+}
+
+/// A request which the EME rejected or never acknowledged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EMERequestFailure {
+    pub request: EMERequest,
+    pub reason: String,
+}
+
+impl std::fmt::Display for EMERequestFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+// *** *** *** //
+
+#[derive(Clone, Debug)]
+struct PendingRequest {
+    request: EMERequest,
+    sent_at: Instant,
+    attempts: u32,
+}
+
+/// What to do with a request which has not been acknowledged in time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimedOutRequest {
+    /// The request should be sent again.
+    Retry(EMERequest),
+    /// The request has run out of attempts.
+    Failed(EMERequestFailure),
+}
+
+/// Assigns IDs to outgoing requests, and tracks them until they are
+/// acknowledged, rejected, or time out.
+#[derive(Clone, Debug)]
+pub struct EMEAckTracker {
+    pending: HashMap<u32, PendingRequest>,
+    next_id: u32,
+}
+
+impl EMEAckTracker {
+    pub fn new() -> Self {
+        Self { pending: HashMap::new(), next_id: 0 }
+    }
+
+    /// Assigns an ID to `request` and starts tracking it.
+    pub fn track(&mut self, request: EMERequest) -> EMERequest {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let request = request.with_id(id);
+
+        self.pending.insert(id, PendingRequest {
+            request: request.clone(),
+            sent_at: Instant::now(),
+            attempts: 1,
+        });
+
+        request
+    }
+
+    /// Stops tracking the request which `response` refers to. Returns a
+    /// failure if the EME rejected it.
+    pub fn on_response(
+        &mut self,
+        response: EMEResponse,
+    ) -> Option<EMERequestFailure> {
+        let pending = self.pending.remove(&response.id)?;
+
+        response.result.err().map(|reason| EMERequestFailure {
+            request: pending.request,
+            reason,
+        })
+    }
+
+    /// Returns all requests which haven't been acknowledged within
+    /// [`EME_RESPONSE_TIMEOUT`] seconds.
+    ///
+    /// Position-only requests are superseded by the next position, so they
+    /// are dropped rather than retried.
+    pub fn take_timed_out(&mut self) -> Vec<TimedOutRequest> {
+        self.take_timed_out_at(Instant::now())
+    }
+
+    /// [`take_timed_out()`](Self::take_timed_out) as of `now`.
+    fn take_timed_out_at(&mut self, now: Instant) -> Vec<TimedOutRequest> {
+        let mut timed_out = Vec::new();
+
+        self.pending.retain(|_, pending| {
+            let waited = now.saturating_duration_since(pending.sent_at);
+
+            if waited.as_secs_f64() < EME_RESPONSE_TIMEOUT {
+                return true;
+            }
+
+            if pending.request.is_position_only() {
+                return false;
+            }
+
+            if pending.attempts < MAX_EME_REQUEST_ATTEMPTS {
+                pending.attempts += 1;
+                pending.sent_at = now;
+                timed_out.push(TimedOutRequest::Retry(pending.request.clone()));
+
+                return true;
+            }
+
+            timed_out.push(TimedOutRequest::Failed(EMERequestFailure {
+                request: pending.request.clone(),
+                reason: format!(
+                    "no response after {} attempts",
+                    pending.attempts
+                ),
+            }));
+
+            false
+        });
+
+        timed_out
+    }
+
+    /// The number of requests awaiting a response.
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+impl Default for EMEAckTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::osc::eme_request::{EMEPlayback, EMEPosition};
+    use std::time::Duration;
+
+    /// An instant after anything sent by now has timed out.
+    fn after_timeout(now: Instant) -> Instant {
+        now + Duration::from_secs_f64(EME_RESPONSE_TIMEOUT * 1.5)
+    }
+
+    fn start_request() -> EMERequest {
+        EMERequest::new()
+            .with_arrangement("MAESTRO")
+            .with_playback(EMEPlayback::Start)
+    }
+
+    #[test]
+    fn responses_are_parsed_from_json() {
+        assert_eq!(
+            EMEResponse::from_json_str(r#"{ "id": 12, "status": "ok" }"#),
+            Some(EMEResponse { id: 12, result: Ok(()) })
+        );
+        assert_eq!(
+            EMEResponse::from_json_str(
+                r#"{ "id": 13, "status": "error", "error": "no such thing" }"#
+            ),
+            Some(EMEResponse { id: 13, result: Err("no such thing".into()) })
+        );
+        assert_eq!(
+            EMEResponse::from_json_str(r#"{ "id": 14, "status": "error" }"#)
+                .map(|r| r.result),
+            Some(Err(String::from("unknown error")))
+        );
+
+        let invalid = [
+            "not json",
+            r#"{ "status": "ok" }"#,
+            r#"{ "id": -1, "status": "ok" }"#,
+            r#"{ "id": 4294967296, "status": "ok" }"#,
+            r#"{ "id": 1 }"#,
+            r#"{ "id": 1, "status": "maybe" }"#,
+        ];
+
+        for json in invalid {
+            assert_eq!(EMEResponse::from_json_str(json), None, "{json}");
+        }
+    }
+
+    #[test]
+    fn tracked_requests_are_given_increasing_ids() {
+        let mut tracker = EMEAckTracker::new();
+
+        assert_eq!(tracker.track(start_request()).id, Some(0));
+        assert_eq!(tracker.track(start_request()).id, Some(1));
+        assert_eq!(tracker.num_pending(), 2);
+    }
+
+    #[test]
+    fn acknowledged_requests_stop_being_tracked() {
+        let mut tracker = EMEAckTracker::new();
+        let id = tracker.track(start_request()).id.unwrap();
+
+        let ack = EMEResponse { id, result: Ok(()) };
+        assert_eq!(tracker.on_response(ack.clone()), None);
+        assert_eq!(tracker.num_pending(), 0);
+
+        // a repeated or unknown response is ignored
+        assert_eq!(tracker.on_response(ack), None);
+        let now = after_timeout(Instant::now());
+        assert!(tracker.take_timed_out_at(now).is_empty());
+    }
+
+    #[test]
+    fn rejected_requests_are_failures() {
+        let mut tracker = EMEAckTracker::new();
+        let request = tracker.track(start_request());
+
+        let failure = tracker.on_response(EMEResponse {
+            id: request.id.unwrap(),
+            result: Err(String::from("unknown arrangement")),
+        });
+
+        assert_eq!(
+            failure,
+            Some(EMERequestFailure {
+                request,
+                reason: String::from("unknown arrangement"),
+            })
+        );
+        assert_eq!(tracker.num_pending(), 0);
+    }
+
+    #[test]
+    fn unacknowledged_requests_are_retried_then_given_up() {
+        let mut tracker = EMEAckTracker::new();
+        let request = tracker.track(start_request());
+        let mut now = Instant::now();
+
+        assert!(tracker.take_timed_out_at(now).is_empty());
+
+        for _ in 1..MAX_EME_REQUEST_ATTEMPTS {
+            now = after_timeout(now);
+            assert_eq!(
+                tracker.take_timed_out_at(now),
+                [TimedOutRequest::Retry(request.clone())]
+            );
+
+            // the retry restarts the timeout
+            assert!(tracker.take_timed_out_at(now).is_empty());
+        }
+
+        now = after_timeout(now);
+        let timed_out = tracker.take_timed_out_at(now);
+        let [TimedOutRequest::Failed(failure)] = timed_out.as_slice()
+        else {
+            panic!("expected a failure, got {timed_out:?}");
+        };
+
+        assert_eq!(failure.request, request);
+        assert_eq!(tracker.num_pending(), 0);
+    }
+
+    #[test]
+    fn position_only_requests_are_dropped_when_they_time_out() {
+        let mut tracker = EMEAckTracker::new();
+        let position = EMEPosition::new(0.0, 0.5);
+        tracker.track(EMERequest::new().with_position(position));
+
+        let now = after_timeout(Instant::now());
+        let timed_out = tracker.take_timed_out_at(now);
+
+        assert!(timed_out.is_empty());
+        assert_eq!(tracker.num_pending(), 0);
+    }
+}
@@ -0,0 +1,181 @@
+//! Sending OSC packets to multiple destinations at once.
+
+use super::*;
+use nannou_osc::rosc::OscPacket;
+use std::io;
+
+/// The delivery status of one fan-out destination.
+#[derive(Clone, Debug)]
+pub struct DestinationStatus {
+    pub addr: SocketAddr,
+    /// The number of packets which have been delivered.
+    pub num_sent: u64,
+    /// The number of packets which could not be delivered.
+    pub num_failed: u64,
+    /// The number of packets which have failed since the last success.
+    pub consecutive_failures: u32,
+    /// The most recent send error, which is cleared on success.
+    pub last_error: Option<String>,
+}
+
+impl DestinationStatus {
+    fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            num_sent: 0,
+            num_failed: 0,
+            consecutive_failures: 0,
+            last_error: None,
+        }
+    }
+
+    /// Whether the most recent packet failed to reach this destination.
+    pub const fn is_failing(&self) -> bool {
+        self.consecutive_failures > 0
+    }
+}
+
+/// A shared handle to the status of each destination of a
+/// [`FanOutTransport`].
+pub type DestinationStatuses = Arc<Mutex<Vec<DestinationStatus>>>;
+
+/// Sends every packet to a list of destinations, tracking failures for each
+/// one separately, so that one unreachable destination doesn't affect the
+/// others.
+pub struct FanOutTransport {
+    destinations: Vec<Box<dyn OSCTransport>>,
+    status: DestinationStatuses,
+    max_attempts: usize,
+    print_failures: bool,
+}
+
+impl FanOutTransport {
+    /// Returns a transport which sends to each of `addrs`. Each packet is
+    /// attempted up to `max_attempts` times per destination.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the underlying transports could not be
+    /// created, or if `addrs` is empty.
+    pub fn connect(
+        addrs: &[SocketAddr],
+        kind: OSCTransportKind,
+        max_attempts: usize,
+    ) -> io::Result<Self> {
+        if addrs.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "no OSC destinations were provided",
+            ));
+        }
+
+        let destinations = addrs
+            .iter()
+            .map(|addr| kind.connect(*addr))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let status = addrs.iter().copied().map(DestinationStatus::new);
+
+        Ok(Self {
+            destinations,
+            status: Arc::new(Mutex::new(status.collect())),
+            max_attempts: max_attempts.max(1),
+            print_failures: true,
+        })
+    }
+
+    /// Sets whether a message is printed when a destination starts or stops
+    /// failing.
+    pub fn with_print_failures(mut self, print_failures: bool) -> Self {
+        self.print_failures = print_failures;
+        self
+    }
+
+    /// Returns a shared handle to the status of each destination.
+    pub fn status(&self) -> DestinationStatuses {
+        Arc::clone(&self.status)
+    }
+
+    fn send_with_retries(
+        transport: &mut dyn OSCTransport,
+        packet: &OscPacket,
+        max_attempts: usize,
+    ) -> io::Result<()> {
+        let mut result = transport.send(packet);
+        let mut attempts = 1;
+
+        while result.is_err() && attempts < max_attempts {
+            attempts += 1;
+            result = transport.send(packet);
+        }
+
+        result
+    }
+}
+
+impl OSCTransport for FanOutTransport {
+    /// Sends `packet` to every destination.
+    ///
+    /// # Errors
+    ///
+    /// Returns the last error if the packet could not be delivered to *any*
+    /// destination.
+    fn send(&mut self, packet: &OscPacket) -> io::Result<()> {
+        let mut last_error = None;
+        let mut any_sent = false;
+
+        let Ok(mut statuses) = self.status.lock()
+        else {
+            return Err(Error::other("OSC destination status was poisoned"));
+        };
+
+        for (transport, status) in
+            self.destinations.iter_mut().zip(statuses.iter_mut())
+        {
+            match Self::send_with_retries(
+                transport.as_mut(), packet, self.max_attempts,
+            ) {
+                Ok(()) => {
+                    if self.print_failures && status.is_failing() {
+                        println!("OSC destination {} recovered", status.addr);
+                    }
+
+                    status.num_sent += 1;
+                    status.consecutive_failures = 0;
+                    status.last_error = None;
+                    any_sent = true;
+                }
+                Err(e) => {
+                    if self.print_failures && !status.is_failing() {
+                        eprintln!(
+                            "failed to send to OSC destination {}: {e}",
+                            status.addr
+                        );
+                    }
+
+                    status.num_failed += 1;
+                    status.consecutive_failures =
+                        status.consecutive_failures.saturating_add(1);
+                    status.last_error = Some(e.to_string());
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match last_error {
+            Some(e) if !any_sent => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the next packet received from any destination.
+    fn try_recv(&mut self) -> io::Result<Option<OscPacket>> {
+        for transport in &mut self.destinations {
+            if let Some(packet) = transport.try_recv()? {
+                return Ok(Some(packet));
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,138 @@
+//! OSC heartbeat messages and connection health monitoring.
+
+use super::*;
+use std::time::{Duration, Instant};
+
+/// The OSC address used for heartbeat messages, both sent and received.
+pub const HEARTBEAT_OSC_ADDRESS: &str = "/maestro/ping";
+
+/// Whether `packet` is a heartbeat message.
+pub fn is_heartbeat(packet: &osc::Packet) -> bool {
+    matches!(
+        packet,
+        osc::Packet::Message(msg) if msg.addr == HEARTBEAT_OSC_ADDRESS
+    )
+}
+
+/// The health of an OSC connection, based on how recently a packet was
+/// received.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionHealth {
+    /// Packets are being received.
+    Connected,
+    /// No packets have been received for a short while.
+    Stale,
+    /// No packets have been received for a long time (or at all).
+    Dead,
+}
+
+impl std::fmt::Display for ConnectionHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connected => write!(f, "connected"),
+            Self::Stale => write!(f, "stale"),
+            Self::Dead => write!(f, "dead"),
+        }
+    }
+}
+
+/// The timeouts (in seconds) after which a connection is considered stale or
+/// dead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HealthThresholds {
+    pub stale_secs: f64,
+    pub dead_secs: f64,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            stale_secs: DEFAULT_OSC_STALE_TIMEOUT,
+            dead_secs: DEFAULT_OSC_DEAD_TIMEOUT,
+        }
+    }
+}
+
+/// Tracks the age of the last received packet.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConnectionMonitor {
+    last_packet: Option<Instant>,
+    thresholds: HealthThresholds,
+}
+
+impl ConnectionMonitor {
+    pub fn new(thresholds: HealthThresholds) -> Self {
+        Self { last_packet: None, thresholds }
+    }
+
+    /// Marks that a packet was just received.
+    pub fn on_packet(&mut self) {
+        self.last_packet = Some(Instant::now());
+    }
+
+    /// How long ago the last packet was received, if any have been.
+    pub fn packet_age(&self) -> Option<Duration> {
+        self.last_packet.map(|t| t.elapsed())
+    }
+
+    pub fn health(&self) -> ConnectionHealth {
+        let Some(age) = self.packet_age()
+        else {
+            return ConnectionHealth::Dead;
+        };
+
+        let age = age.as_secs_f64();
+
+        if age >= self.thresholds.dead_secs {
+            ConnectionHealth::Dead
+        }
+        else if age >= self.thresholds.stale_secs {
+            ConnectionHealth::Stale
+        }
+        else {
+            ConnectionHealth::Connected
+        }
+    }
+
+    pub const fn thresholds(&self) -> HealthThresholds {
+        self.thresholds
+    }
+}
+
+// *** *** *** //
+
+/// Periodically sends heartbeat messages holding an incrementing counter.
+pub struct Heartbeat {
+    timer: TimerThread,
+}
+
+impl Heartbeat {
+    pub fn new(sender: Arc<Mutex<Box<dyn OSCTransport>>>) -> Self {
+        let mut counter: i32 = 0;
+
+        let timer = TimerThread::new(move || {
+            let packet = osc::rosc::OscPacket::Message(osc::Message {
+                addr: HEARTBEAT_OSC_ADDRESS.to_string(),
+                args: vec![osc::Type::Int(counter)],
+            });
+
+            counter = counter.wrapping_add(1);
+
+            // NOTE(jamie): failures are expected while the target is offline,
+            // so they aren't reported here.
+            if let Ok(mut osc) = sender.lock() {
+                _ = osc.send(&packet);
+            }
+        });
+
+        Self { timer }
+    }
+
+    pub fn start(&mut self) {
+        self.timer.start_hz(OSC_HEARTBEAT_RATE);
+    }
+
+    pub fn stop(&mut self) {
+        self.timer.stop();
+    }
+}
@@ -0,0 +1,143 @@
+//! Re-timing irregularly arriving hand frames to a steady cadence.
+//!
+//! Network and tracker scheduling jitter make frames arrive unevenly, which
+//! shows up as noise in velocities derived from them. The jitter buffer holds
+//! a few frames back and releases them at the tracker's average frame
+//! interval, trading a little latency for evenly spaced frames.
+
+use super::*;
+use std::{collections::VecDeque, time::Instant};
+
+/// The largest number of frames which the jitter buffer may hold back.
+pub const MAX_JITTER_BUFFER_FRAMES: usize = 3;
+
+/// The frame interval (in seconds) which is assumed before any have been
+/// measured.
+const DEFAULT_FRAME_INTERVAL: f64 = 1.0 / 30.0;
+/// Measured frame intervals are clamped to this range (in seconds), so that
+/// bursts and dropouts don't throw the cadence off.
+const MIN_FRAME_INTERVAL: f64 = 1.0 / 240.0;
+const MAX_FRAME_INTERVAL: f64 = 0.25;
+/// How quickly the estimated frame interval follows the measured one.
+const INTERVAL_SMOOTHING: f64 = 0.05;
+/// How much the release cadence is sped up or slowed down to keep the buffer
+/// at its target depth.
+const DRIFT_CORRECTION: f64 = 0.1;
+
+/// Holds received frames back by a fixed number of frames, and releases them
+/// at a steady rate.
+pub struct JitterBuffer {
+    /// How many frames are held back.
+    latency_frames: usize,
+    frames: VecDeque<osc::Packet>,
+
+    /// The estimated time between received frames, in seconds.
+    frame_interval: f64,
+    last_arrival: Option<Instant>,
+
+    /// When the next frame is due to be released, or `None` while the buffer
+    /// is filling.
+    next_release: Option<Instant>,
+}
+
+impl JitterBuffer {
+    /// Returns a jitter buffer which holds `latency_frames` frames back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `latency_frames` is `0` or greater than
+    /// [`MAX_JITTER_BUFFER_FRAMES`].
+    pub fn new(latency_frames: usize) -> Self {
+        assert!((1..=MAX_JITTER_BUFFER_FRAMES).contains(&latency_frames));
+
+        Self {
+            latency_frames,
+            frames: VecDeque::with_capacity(latency_frames * 2 + 1),
+
+            frame_interval: DEFAULT_FRAME_INTERVAL,
+            last_arrival: None,
+
+            next_release: None,
+        }
+    }
+
+    /// Adds a newly received frame.
+    pub fn push(&mut self, packet: osc::Packet) {
+        let now = Instant::now();
+
+        if let Some(last) = self.last_arrival {
+            let interval = (now - last)
+                .as_secs_f64()
+                .clamp(MIN_FRAME_INTERVAL, MAX_FRAME_INTERVAL);
+
+            self.frame_interval +=
+                (interval - self.frame_interval) * INTERVAL_SMOOTHING;
+        }
+
+        self.last_arrival = Some(now);
+
+        // if frames arrive faster than they're released, the oldest are
+        // dropped rather than letting the latency grow
+        if self.frames.len() == self.latency_frames * 2 {
+            _ = self.frames.pop_front();
+        }
+
+        self.frames.push_back(packet);
+    }
+
+    /// Returns the latest frame which is due to be released, if any.
+    pub fn pop(&mut self) -> Option<osc::Packet> {
+        let now = Instant::now();
+
+        let Some(mut next_release) = self.next_release
+        else {
+            // wait until enough frames are held back before starting
+            if self.frames.len() <= self.latency_frames {
+                return None;
+            }
+
+            self.next_release = Some(now + self.release_interval());
+            return self.frames.pop_front();
+        };
+
+        let mut packet = None;
+
+        while next_release <= now {
+            let Some(p) = self.frames.pop_front()
+            else {
+                // the buffer ran dry, so it is refilled before releasing
+                // frames again
+                self.next_release = None;
+                return packet;
+            };
+
+            packet = Some(p);
+            next_release += self.release_interval();
+        }
+
+        self.next_release = Some(next_release);
+        packet
+    }
+
+    /// Clears all held frames.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.last_arrival = None;
+        self.next_release = None;
+    }
+
+    /// The latency added by the buffer, in seconds.
+    pub fn latency(&self) -> f64 {
+        self.latency_frames as f64 * self.frame_interval
+    }
+
+    /// The time until the next frame is released. Frames are released
+    /// slightly faster while more than `latency_frames` are held, and slightly
+    /// slower while fewer are, so the latency stays steady.
+    fn release_interval(&self) -> std::time::Duration {
+        let depth = self.frames.len() as f64 - self.latency_frames as f64;
+        let scale = 1.0 - (depth * DRIFT_CORRECTION).clamp(-0.5, 0.5);
+
+        std::time::Duration::from_secs_f64(self.frame_interval * scale)
+    }
+}
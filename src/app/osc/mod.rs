@@ -1,107 +1,484 @@
 use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr},
-    sync::{Arc, Mutex},
+    io::{Error, ErrorKind},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs},
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::SystemTime,
 };
 
 use super::*;
 
 use args::Arguments;
+use auth::ControlAuthenticator;
 use eme_request::{EMERequest, ToJson};
+use eme_response::{
+    arrangements_from_packet, EMEAckTracker, EMEEvent, EMEResponse,
+    TimedOutRequest,
+};
+use fanout::{DestinationStatus, DestinationStatuses, FanOutTransport};
+use hands::handshake::HANDSHAKE_OSC_ADDRESS;
+use jitter::JitterBuffer;
+use recorder::{OSCRecorder, OSCReplayer};
+use heartbeat::{
+    ConnectionHealth, ConnectionMonitor, HealthThresholds, Heartbeat,
+};
 use nannou::color::ConvertInto;
-use nannou_osc::{self as osc, Connected};
+use nannou_osc as osc;
 use timer::TimerThread;
+use transport::{OSCTransport, OSCTransportKind};
 
+pub mod auth;
+pub mod broadcast;
+pub mod discovery;
 pub mod eme_request;
+pub mod eme_response;
+pub mod fanout;
+pub mod heartbeat;
+pub mod jitter;
+pub mod recorder;
+pub mod redundancy;
+pub mod timetag;
+pub mod transport;
 
-pub const OSC_IP_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+/// The default host which hand data is received on (all IPv4 interfaces).
+pub const DEFAULT_OSC_RX_HOST: &str = "0.0.0.0";
+/// The default host which EME requests are sent to.
+pub const DEFAULT_OSC_TX_HOST: &str = "127.0.0.1";
 const MAX_OSC_SEND_ATTEMPTS: usize = 16;
+/// The maximum number of received bundles which may wait for their timetag.
+const MAX_SCHEDULED_OSC_PACKETS: usize = 64;
+/// The maximum number of received control messages which may wait to be
+/// handled.
+const MAX_QUEUED_CONTROL_MESSAGES: usize = 64;
+
+/// The address prefix of OSC messages which control the app, rather than
+/// carrying hand data.
+pub const CONTROL_OSC_PREFIX: &str = "/maestro/";
+/// Selects a user profile by name (a string argument).
+pub const PROFILE_OSC_ADDRESS: &str = "/maestro/profile";
+/// Exports the current configuration to config files (no arguments).
+pub const EXPORT_OSC_ADDRESS: &str = "/maestro/export";
+/// The address prefix of messages which set an internal effect parameter
+/// (one number argument), e.g. `/maestro/fx/delay_time`.
+pub const FX_OSC_PREFIX: &str = "/maestro/fx/";
+/// Shows a message on the ticker (a string, then optionally its duration
+/// and delay in seconds).
+pub const TICKER_OSC_ADDRESS: &str = "/maestro/ticker";
+/// Fades out the ticker's messages (no arguments).
+pub const TICKER_CLEAR_OSC_ADDRESS: &str = "/maestro/ticker/clear";
+
+/// Resolves `host`, which may be a hostname or an IPv4 or IPv6 address, to
+/// a socket address with `port`. IPv6 addresses may be wrapped in brackets.
+///
+/// # Errors
+///
+/// Returns an error if the host could not be resolved.
+pub fn resolve_addr(host: &str, port: u16) -> std::io::Result<SocketAddr> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
 
-fn addr_string(port: u16) -> String {
-    format!("127.0.0.1:{port}")
+    (host, port).to_socket_addrs()?.next().ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("failed to resolve OSC host \"{host}\""),
+        )
+    })
 }
 
 // *** *** *** //
 
 pub struct OSCReceiver {
-    receiver: osc::Receiver,
+    receiver: Box<dyn OSCTransport>,
+    /// Bundles which were received before their timetag, sorted by time.
+    scheduled: Vec<(SystemTime, osc::Packet)>,
+    monitor: ConnectionMonitor,
+    recorder: Option<OSCRecorder>,
+    /// Received control messages which haven't been handled yet.
+    control: Vec<osc::Message>,
+    /// Re-times hand frames to a steady cadence, if enabled.
+    jitter: Option<JitterBuffer>,
+    /// Rejects control messages which aren't signed with the key, if one is
+    /// set.
+    authenticator: Option<ControlAuthenticator>,
 }
 
 impl OSCReceiver {
-    pub fn with_port(port: u16) -> std::io::Result<Self> {
-        Ok(Self { receiver: osc::receiver(port)? })
+    pub fn with_addr(
+        addr: SocketAddr,
+        transport: OSCTransportKind,
+        thresholds: HealthThresholds,
+    ) -> std::io::Result<Self> {
+        Ok(Self::with_transport(transport.bind(addr)?, thresholds))
+    }
+
+    /// Returns a receiver which reads packets from `transport`, such as an
+    /// [`OSCReplayer`].
+    pub fn with_transport(
+        transport: Box<dyn OSCTransport>,
+        thresholds: HealthThresholds,
+    ) -> Self {
+        Self {
+            receiver: transport,
+            scheduled: Vec::with_capacity(MAX_SCHEDULED_OSC_PACKETS),
+            monitor: ConnectionMonitor::new(thresholds),
+            recorder: None,
+            control: Vec::new(),
+            jitter: None,
+            authenticator: None,
+        }
     }
 
+    /// Sets a recorder which every received packet is written to.
+    pub fn set_recorder(&mut self, recorder: OSCRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Only accepts control messages which are signed by `authenticator`.
+    /// See the [`auth`] module for how messages are signed.
+    pub fn set_authenticator(&mut self, authenticator: ControlAuthenticator) {
+        self.authenticator = Some(authenticator);
+    }
+
+    /// Holds hand frames back by `latency_frames` frames, and releases them
+    /// at a steady rate. `0` disables the jitter buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `latency_frames` is greater than
+    /// [`MAX_JITTER_BUFFER_FRAMES`](jitter::MAX_JITTER_BUFFER_FRAMES).
+    pub fn set_jitter_buffer(&mut self, latency_frames: usize) {
+        self.jitter =
+            (latency_frames > 0).then(|| JitterBuffer::new(latency_frames));
+    }
+
+    /// Returns the latest packet which is due to be processed, if any.
+    ///
+    /// Bundles with a timetag in the future are held until their scheduled
+    /// time; all other packets are due immediately. Heartbeat messages are
+    /// only used to track the connection's health, so are never returned,
+    /// and control messages are queued for
+    /// [`take_control_messages()`](Self::take_control_messages). If the
+    /// jitter buffer is enabled, other packets are released from it at a
+    /// steady rate.
     pub fn try_recv(&mut self) -> Option<osc::Packet> {
-        let mut packet = None;
+        let now = SystemTime::now();
+
+        // scheduled bundles which are now due are applied in timetag order,
+        // before anything received since, so that newer packets win
+        let num_due = self.scheduled.partition_point(|(time, _)| *time <= now);
+        let mut packet =
+            self.scheduled.drain(..num_due).next_back().map(|(_, p)| p);
 
         while let Ok(opt) = self.receiver.try_recv()
-            && let Some((p, _)) = opt
+            && let Some(p) = opt
+        {
+            self.monitor.on_packet();
+
+            if let Some(recorder) = &mut self.recorder
+                && let Err(e) = recorder.record(&p)
+            {
+                eprintln!("failed to record OSC packet: {e}");
+                self.recorder = None;
+            }
+
+            let p = osc::Packet::from(p);
+
+            if heartbeat::is_heartbeat(&p) {
+                continue;
+            }
+
+            if let osc::Packet::Message(msg) = &p
+                && msg.addr.starts_with(CONTROL_OSC_PREFIX)
+            {
+                // trackers don't hold the key, and their handshake only
+                // describes the tracker
+                let authenticator = self
+                    .authenticator
+                    .as_mut()
+                    .filter(|_| msg.addr != HANDSHAKE_OSC_ADDRESS);
+
+                let msg = match authenticator {
+                    Some(auth) => match auth.verify(msg.clone()) {
+                        Ok(msg) => msg,
+                        Err(e) => {
+                            eprintln!(
+                                "rejected control message to {}: {e}",
+                                msg.addr
+                            );
+                            continue;
+                        }
+                    },
+                    None => msg.clone(),
+                };
+
+                if self.control.len() == MAX_QUEUED_CONTROL_MESSAGES {
+                    _ = self.control.remove(0);
+                }
+
+                self.control.push(msg);
+                continue;
+            }
+
+            match Self::scheduled_time(&p) {
+                Some(time) if time > now => self.schedule(time, p),
+                _ => {
+                    if let Some(jitter) = &mut self.jitter {
+                        jitter.push(p);
+                    }
+                    else {
+                        packet = Some(p);
+                    }
+                }
+            }
+        }
+
+        // packets released from the jitter buffer are newer than any due
+        // scheduled bundles
+        if let Some(jitter) = &mut self.jitter
+            && let Some(p) = jitter.pop()
         {
             packet = Some(p);
         }
 
         packet
     }
+
+    /// Returns all control messages received since this was last called.
+    pub fn take_control_messages(&mut self) -> Vec<osc::Message> {
+        std::mem::take(&mut self.control)
+    }
+
+    /// The health of the connection, based on the age of the last packet.
+    pub fn health(&self) -> ConnectionHealth {
+        self.monitor.health()
+    }
+
+    /// The connection monitor for received packets.
+    pub const fn monitor(&self) -> &ConnectionMonitor {
+        &self.monitor
+    }
+
+    /// Returns the number of bundles waiting for their timetag.
+    pub fn num_scheduled(&self) -> usize {
+        self.scheduled.len()
+    }
+
+    fn schedule(&mut self, time: SystemTime, packet: osc::Packet) {
+        if self.scheduled.len() == MAX_SCHEDULED_OSC_PACKETS {
+            _ = self.scheduled.remove(0);
+        }
+
+        let idx = self.scheduled.partition_point(|(t, _)| *t <= time);
+        self.scheduled.insert(idx, (time, packet));
+    }
+
+    fn scheduled_time(packet: &osc::Packet) -> Option<SystemTime> {
+        match packet {
+            osc::Packet::Bundle(bundle) => {
+                timetag::to_system_time(bundle.timetag)
+            }
+            osc::Packet::Message(_) => None,
+        }
+    }
 }
 
 // *** *** *** //
 
 pub struct EMERequestOSCSender {
-    sender: Arc<Mutex<osc::Sender<Connected>>>,
+    sender: Arc<Mutex<Box<dyn OSCTransport>>>,
+    destinations: DestinationStatuses,
+    use_bundles: Arc<AtomicBool>,
     osc_sender_timer: TimerThread,
+    heartbeat: Heartbeat,
     request_rx: Arc<Mutex<CCReceiver<EMERequest>>>,
 }
 
 impl EMERequestOSCSender {
+    /// Returns a sender which broadcasts each request to all of `addrs`.
+    /// Anything the EME reports back is sent to `event_channel`.
+    ///
+    /// If `track_acks` is true, requests are tagged with IDs and tracked
+    /// until the EME acknowledges them. Unacknowledged requests are retried,
+    /// and any which are rejected or run out of attempts are reported as
+    /// [`EMEEvent::RequestFailed`].
     pub fn new(
-        port: u16,
+        addrs: &[SocketAddr],
+        transport: OSCTransportKind,
         eme_request_channel: CCReceiver<EMERequest>,
+        event_channel: CCSender<EMEEvent>,
+        track_acks: bool,
+        print_failures: bool,
     ) -> std::io::Result<Self> {
-        // let tx_addr = SocketAddr::new(OSC_IP_ADDRESS, port);
-        let sender = osc::sender()?.connect(addr_string(port))?;
+        let fan_out =
+            FanOutTransport::connect(addrs, transport, MAX_OSC_SEND_ATTEMPTS)?
+                .with_print_failures(print_failures);
+        let destinations = fan_out.status();
+
+        let sender: Box<dyn OSCTransport> = Box::new(fan_out);
         let sender = Arc::new(Mutex::new(sender));
 
         // timer thread state
         let osc_sender = Arc::clone(&sender);
         let request_rx = Arc::new(Mutex::new(eme_request_channel));
         let request_receiver = Arc::clone(&request_rx);
+        let use_bundles = Arc::new(AtomicBool::new(true));
+        let bundles = Arc::clone(&use_bundles);
+        let mut acks = track_acks.then(EMEAckTracker::new);
 
         let osc_sender_timer = TimerThread::new(move || {
-            let osc_addr = EME_OSC_REQUEST_CHANNEL.to_string();
-
-            if let Ok(osc) = osc_sender.lock()
+            if let Ok(mut osc) = osc_sender.lock()
                 && let Ok(receiver) = request_receiver.lock()
             {
-                while let Ok(eme_request) = receiver.try_recv() {
-                    let request_str = eme_request.as_json().to_string();
-                    let args = Vec::from([osc::Type::String(request_str)]);
+                let as_bundle = bundles.lr();
+
+                Self::process_replies(
+                    osc.as_mut(), acks.as_mut(), &event_channel, as_bundle,
+                );
+
+                while let Ok(mut eme_request) = receiver.try_recv() {
+                    if let Some(tracker) = &mut acks {
+                        eme_request = tracker.track(eme_request);
+                    }
+
+                    Self::send_request(osc.as_mut(), &eme_request, as_bundle);
+                }
+            }
+        });
 
-                    let mut send_result =
-                        osc.send((osc_addr.clone(), args.clone()));
+        let heartbeat = Heartbeat::new(Arc::clone(&sender));
 
-                    let mut attempts = 1;
+        Ok(Self {
+            sender,
+            destinations,
+            use_bundles,
+            osc_sender_timer,
+            heartbeat,
+            request_rx,
+        })
+    }
+
+    /// The delivery status of each destination which requests are sent to.
+    pub fn destinations(&self) -> Vec<DestinationStatus> {
+        self.destinations
+            .lock()
+            .map(|status| status.clone())
+            .unwrap_or_default()
+    }
+
+    /// Sets whether requests are sent as timetagged OSC bundles (the
+    /// default), or as bare OSC messages.
+    pub fn set_use_bundles(&self, use_bundles: bool) {
+        self.use_bundles.sr(use_bundles);
+    }
+
+    fn send_request(
+        osc: &mut dyn OSCTransport,
+        request: &EMERequest,
+        as_bundle: bool,
+    ) {
+        let request_str = request.as_json().to_string();
+        let args = Vec::from([osc::Type::String(request_str)]);
+
+        let packet = Self::create_packet(
+            EME_OSC_REQUEST_CHANNEL.to_string(), args, as_bundle,
+        );
+
+        // NOTE(jamie): retries and failures are handled per destination by
+        // the fan-out transport.
+        _ = osc.send(&packet);
+    }
+
+    /// Asks the EME for the names of its arrangements. The reply is
+    /// reported as an [`EMEEvent::Arrangements`] while the sender is running.
+    pub fn query_arrangements(&self) {
+        let query = serde_json::json!({ "query": "arrangements" });
+        let packet = Self::create_packet(
+            EME_OSC_QUERY_CHANNEL.to_string(),
+            vec![osc::Type::String(query.to_string())],
+            false,
+        );
+
+        if let Ok(mut osc) = self.sender.lock()
+            && let Err(e) = osc.send(&packet)
+        {
+            eprintln!("failed to query EME arrangements: {e}");
+        }
+    }
 
-                    while let Err(e) = &send_result
-                        && attempts < MAX_OSC_SEND_ATTEMPTS
-                    {
-                        attempts += 1;
+    /// Handles any replies from the EME. If `tracker` is provided, responses
+    /// are matched with their requests, and any requests which haven't been
+    /// acknowledged in time are retried or reported.
+    fn process_replies(
+        osc: &mut dyn OSCTransport,
+        mut tracker: Option<&mut EMEAckTracker>,
+        events: &CCSender<EMEEvent>,
+        as_bundle: bool,
+    ) {
+        let mut new_events = Vec::new();
+
+        while let Ok(Some(packet)) = osc.try_recv() {
+            if let Some(names) = arrangements_from_packet(&packet) {
+                new_events.push(EMEEvent::Arrangements(names));
+            }
 
-                        send_result =
-                            osc.send((osc_addr.clone(), args.clone()));
+            if let Some(tracker) = tracker.as_deref_mut() {
+                new_events.extend(
+                    EMEResponse::from_packet(&packet)
+                        .into_iter()
+                        .filter_map(|response| tracker.on_response(response))
+                        .map(EMEEvent::RequestFailed),
+                );
+            }
+        }
+
+        if let Some(tracker) = tracker {
+            for timed_out in tracker.take_timed_out() {
+                match timed_out {
+                    TimedOutRequest::Retry(request) => {
+                        Self::send_request(osc, &request, as_bundle);
+                    }
+                    TimedOutRequest::Failed(failure) => {
+                        new_events.push(EMEEvent::RequestFailed(failure));
                     }
                 }
             }
-        });
+        }
 
-        Ok(Self { sender, osc_sender_timer, request_rx })
+        for event in new_events {
+            if let Err(e) = events.try_send(event) {
+                eprintln!("failed to report EME event: {e}");
+            }
+        }
+    }
+
+    /// Wraps a message in a bundle which is scheduled
+    /// [`EME_OSC_BUNDLE_LATENCY`] seconds from now, if `as_bundle` is true.
+    fn create_packet(
+        addr: String,
+        args: Vec<osc::Type>,
+        as_bundle: bool,
+    ) -> osc::rosc::OscPacket {
+        let msg = osc::Message { addr, args };
+
+        if as_bundle {
+            osc::rosc::OscPacket::Bundle(osc::Bundle {
+                timetag: timetag::from_now(EME_OSC_BUNDLE_LATENCY),
+                content: vec![osc::rosc::OscPacket::Message(msg)],
+            })
+        }
+        else {
+            osc::rosc::OscPacket::Message(msg)
+        }
     }
 
     pub fn start_send(&mut self) {
+        self.query_arrangements();
+
         self.osc_sender_timer.start_hz(OSC_SEND_RATE);
+        self.heartbeat.start();
     }
 
     pub fn stop_send(&mut self) {
         self.osc_sender_timer.stop_after_num_callbacks(1, Some(1.0));
+        self.heartbeat.stop();
     }
 
     pub fn clear_request_channel(&self) {
@@ -118,9 +495,117 @@ impl EMERequestOSCSender {
 pub fn create_osc_sender_and_receiver(
     args: &Arguments,
     eme_request_channel: CCReceiver<EMERequest>,
+    eme_event_channel: CCSender<EMEEvent>,
 ) -> std::io::Result<(EMERequestOSCSender, OSCReceiver)> {
-    Ok((
-        EMERequestOSCSender::new(args.osc_tx_port, eme_request_channel)?,
-        OSCReceiver::with_port(args.osc_rx_port)?,
-    ))
+    let tx_addrs = args
+        .osc_tx_destinations
+        .iter()
+        .map(|(host, port)| resolve_addr(host, *port))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let rx_addr = resolve_addr(&args.osc_rx_host, args.osc_rx_port)?;
+
+    let sender = EMERequestOSCSender::new(
+        &tx_addrs,
+        args.osc_transport,
+        eme_request_channel,
+        eme_event_channel,
+        args.eme_acks,
+        args.print || args.debug,
+    )?;
+    sender.set_use_bundles(args.osc_bundles);
+
+    let thresholds = HealthThresholds {
+        stale_secs: args.osc_stale_timeout,
+        dead_secs: args.osc_dead_timeout,
+    };
+
+    let mut receiver = if let Some(path) = &args.osc_replay_path {
+        let replayer = OSCReplayer::from_file(path)?;
+        OSCReceiver::with_transport(Box::new(replayer), thresholds)
+    }
+    else {
+        OSCReceiver::with_addr(rx_addr, args.osc_transport, thresholds)?
+    };
+
+    if let Some(path) = &args.osc_record_path {
+        receiver.set_recorder(OSCRecorder::create(path)?);
+    }
+
+    receiver.set_jitter_buffer(args.jitter_buffer_frames);
+
+    if let Some(path) = &args.control_key_path {
+        let authenticator = ControlAuthenticator::from_file(path)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        receiver.set_authenticator(authenticator);
+    }
+
+    Ok((sender, receiver))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nannou_osc::rosc::OscPacket;
+    use std::collections::VecDeque;
+
+    /// A transport which receives a fixed list of packets.
+    struct QueuedTransport(VecDeque<OscPacket>);
+
+    impl OSCTransport for QueuedTransport {
+        fn send(&mut self, _: &OscPacket) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn try_recv(&mut self) -> std::io::Result<Option<OscPacket>> {
+            Ok(self.0.pop_front())
+        }
+    }
+
+    fn receiver_of(msgs: Vec<osc::Message>) -> OSCReceiver {
+        let packets = msgs.into_iter().map(OscPacket::Message).collect();
+        let mut receiver = OSCReceiver::with_transport(
+            Box::new(QueuedTransport(packets)),
+            HealthThresholds::default(),
+        );
+        receiver.set_authenticator(
+            ControlAuthenticator::new(b"a shared secret key").unwrap(),
+        );
+
+        receiver
+    }
+
+    fn message(addr: &str) -> osc::Message {
+        osc::Message { addr: String::from(addr), args: Vec::new() }
+    }
+
+    #[test]
+    fn handshakes_are_not_authenticated() {
+        let handshake = osc::Message {
+            addr: String::from(HANDSHAKE_OSC_ADDRESS),
+            args: vec![osc::Type::String(String::from("tracker"))],
+        };
+        let mut receiver = receiver_of(vec![
+            message(EXPORT_OSC_ADDRESS),
+            handshake.clone(),
+        ]);
+
+        while receiver.try_recv().is_some() {}
+
+        // the unsigned control message is rejected
+        assert_eq!(receiver.take_control_messages(), [handshake]);
+    }
+
+    #[test]
+    fn signed_control_messages_are_accepted() {
+        let key = ControlAuthenticator::new(b"a shared secret key").unwrap();
+        let signed = key.sign(message(EXPORT_OSC_ADDRESS), 1);
+        let mut receiver = receiver_of(vec![signed]);
+
+        while receiver.try_recv().is_some() {}
+
+        assert_eq!(
+            receiver.take_control_messages(),
+            [message(EXPORT_OSC_ADDRESS)]
+        );
+    }
 }
@@ -0,0 +1,153 @@
+//! Recording of received OSC packets to disk, and replaying them later.
+//!
+//! Recordings are a short header followed by one entry per packet:
+//!
+//! ```text
+//! [u64 microseconds since recording started][u32 length][OSC packet bytes]
+//! ```
+//!
+//! All integers are little-endian, and packets are stored in their encoded
+//! OSC form.
+
+use super::*;
+use nannou_osc::rosc::{decoder, encoder, OscPacket};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::time::{Duration, Instant};
+
+/// Identifies (and versions) OSC recording files.
+const RECORDING_HEADER: &[u8; 8] = b"MOSCREC1";
+
+/// Writes timestamped packets to a recording file.
+pub struct OSCRecorder {
+    writer: BufWriter<File>,
+    start_time: Instant,
+}
+
+impl OSCRecorder {
+    /// Creates a new recording at `path`, overwriting any existing file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be created.
+    pub fn create(path: &str) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(RECORDING_HEADER)?;
+
+        Ok(Self { writer, start_time: Instant::now() })
+    }
+
+    /// Appends `packet` to the recording, timestamped with the current time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the packet could not be encoded or written.
+    pub fn record(&mut self, packet: &OscPacket) -> io::Result<()> {
+        let micros = self.start_time.elapsed().as_micros() as u64;
+        let bytes = encoder::encode(packet).map_err(|e| {
+            io::Error::new(ErrorKind::InvalidData, format!("{e:?}"))
+        })?;
+
+        self.writer.write_all(&micros.to_le_bytes())?;
+        self.writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&bytes)?;
+
+        // flushed per packet so that recordings survive a crash
+        self.writer.flush()
+    }
+}
+
+// *** *** *** //
+
+/// Plays back a recording made by [`OSCRecorder`] in real time, in place of a
+/// live transport.
+pub struct OSCReplayer {
+    packets: Vec<(Duration, OscPacket)>,
+    next_idx: usize,
+    start_time: Instant,
+}
+
+impl OSCReplayer {
+    /// Loads the recording at `path`. Playback starts immediately.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be read, or is not a valid
+    /// recording.
+    pub fn from_file(path: &str) -> io::Result<Self> {
+        let packets = read_recording(path)?;
+
+        Ok(Self { packets, next_idx: 0, start_time: Instant::now() })
+    }
+}
+
+impl OSCTransport for OSCReplayer {
+    fn send(&mut self, _: &OscPacket) -> io::Result<()> {
+        Err(io::Error::new(
+            ErrorKind::Unsupported,
+            "cannot send to an OSC replay",
+        ))
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<OscPacket>> {
+        let Some((time, packet)) = self.packets.get(self.next_idx)
+        else {
+            return Ok(None);
+        };
+
+        if *time > self.start_time.elapsed() {
+            return Ok(None);
+        }
+
+        self.next_idx += 1;
+
+        Ok(Some(packet.clone()))
+    }
+}
+
+/// Reads the recording at `path`, returning each packet with its time since
+/// the recording started.
+///
+/// # Errors
+///
+/// Returns an error if the file could not be read, or is not a valid
+/// recording.
+pub fn read_recording(path: &str) -> io::Result<Vec<(Duration, OscPacket)>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut header = [0; RECORDING_HEADER.len()];
+    reader.read_exact(&mut header)?;
+
+    if &header != RECORDING_HEADER {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("\"{path}\" is not an OSC recording"),
+        ));
+    }
+
+    let mut packets = Vec::new();
+    let mut time_bytes = [0; 8];
+    let mut len_bytes = [0; 4];
+
+    loop {
+        match reader.read_exact(&mut time_bytes) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+
+        reader.read_exact(&mut len_bytes)?;
+
+        let mut bytes = vec![0; u32::from_le_bytes(len_bytes) as usize];
+        reader.read_exact(&mut bytes)?;
+
+        let (_, packet) = decoder::decode_udp(&bytes).map_err(|e| {
+            io::Error::new(ErrorKind::InvalidData, format!("{e:?}"))
+        })?;
+
+        let time = Duration::from_micros(u64::from_le_bytes(time_bytes));
+        packets.push((time, packet));
+    }
+
+    Ok(packets)
+}
@@ -0,0 +1,360 @@
+//! Hot-standby redundancy between two instances of the app, so that an
+//! unattended installation keeps playing if one of them fails.
+//!
+//! Both instances exchange heartbeats, and only the active one (the
+//! primary) sends MIDI and OSC. The active instance also replicates its
+//! state to the other, which restores it when it takes over.
+//!
+//! The roles are resolved as follows:
+//!
+//! - an instance takes over once it hasn't heard from its peer for
+//!   [`REDUNDANCY_FAILOVER_TIMEOUT`] seconds (including at startup),
+//! - if neither instance is active, the preferred primary takes over,
+//! - if both are active (e.g. after the network is restored), the standby
+//!   gives way.
+//!
+//! An instance which restarts while its peer is active stays on standby, so
+//! that output isn't interrupted a second time.
+
+use super::*;
+use nannou_osc::rosc::{decoder, encoder, OscPacket};
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// The default port which heartbeats and state are received on.
+pub const DEFAULT_REDUNDANCY_PORT: u16 = 9100;
+/// The OSC address of heartbeats, which hold whether the sender is active
+/// (`1`) or not (`0`).
+pub const REDUNDANCY_HEARTBEAT_OSC_ADDRESS: &str =
+    "/maestro/redundancy/heartbeat";
+/// The OSC address of replicated state, which holds the saved state as
+/// JSON, and whether the transport is running (`1`) or not (`0`).
+pub const REDUNDANCY_STATE_OSC_ADDRESS: &str = "/maestro/redundancy/state";
+
+/// The largest packet which can be received, which is the largest UDP
+/// payload, as the replicated state may be larger than an OSC packet
+/// usually is.
+const MAX_REDUNDANCY_PACKET_SIZE: usize = 65_507;
+
+/// Which role an instance prefers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RedundancyRole {
+    Primary,
+    Standby,
+}
+
+impl std::fmt::Display for RedundancyRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Primary => write!(f, "primary"),
+            Self::Standby => write!(f, "standby"),
+        }
+    }
+}
+
+/// The state which was last replicated by the peer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplicatedState {
+    /// The saved state, as JSON.
+    pub json: String,
+    pub is_sending: bool,
+}
+
+/// A change in whether this instance is active.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RedundancyEvent {
+    /// This instance has taken over, with the state last replicated by the
+    /// peer, if any was.
+    Promoted(Option<ReplicatedState>),
+    /// The peer has taken over.
+    Demoted,
+}
+
+// *** *** *** //
+
+/// Decides whether this instance should be active, from the heartbeats of
+/// its peer.
+#[derive(Clone, Copy, Debug)]
+pub struct Failover {
+    role: RedundancyRole,
+    is_active: bool,
+    started: Instant,
+    last_heartbeat: Option<Instant>,
+    peer_active: bool,
+    timeout: Duration,
+}
+
+impl Failover {
+    /// Returns a failover which starts inactive, at `now`.
+    pub const fn new(
+        role: RedundancyRole,
+        timeout: Duration,
+        now: Instant,
+    ) -> Self {
+        Self {
+            role,
+            is_active: false,
+            started: now,
+            last_heartbeat: None,
+            peer_active: false,
+            timeout,
+        }
+    }
+
+    pub const fn role(&self) -> RedundancyRole {
+        self.role
+    }
+
+    pub const fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    /// Marks that a heartbeat was received from the peer at `now`.
+    pub fn on_heartbeat(&mut self, peer_active: bool, now: Instant) {
+        self.last_heartbeat = Some(now);
+        self.peer_active = peer_active;
+    }
+
+    /// Updates whether this instance is active at `now`, and returns
+    /// whether it has changed.
+    pub fn update(&mut self, now: Instant) -> bool {
+        let silent_since = self.last_heartbeat.unwrap_or(self.started);
+        let is_silent = now.duration_since(silent_since) >= self.timeout;
+
+        let is_active = if is_silent {
+            true
+        }
+        // wait to hear whether the peer is already active
+        else if self.last_heartbeat.is_none() {
+            self.is_active
+        }
+        else if self.peer_active {
+            self.is_active && self.role == RedundancyRole::Primary
+        }
+        else {
+            self.is_active || self.role == RedundancyRole::Primary
+        };
+
+        let changed = is_active != self.is_active;
+        self.is_active = is_active;
+
+        changed
+    }
+}
+
+// *** *** *** //
+
+/// Exchanges heartbeats and state with the peer instance.
+pub struct RedundancyLink {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    buf: Vec<u8>,
+    failover: Failover,
+    /// The state which was last replicated by the peer.
+    replicated: Option<ReplicatedState>,
+    last_heartbeat_sent: Option<Instant>,
+    last_replicated: Option<Instant>,
+}
+
+impl RedundancyLink {
+    /// Returns a link which receives on `port` and sends to `peer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the port could not be bound.
+    pub fn new(
+        role: RedundancyRole,
+        port: u16,
+        peer: SocketAddr,
+    ) -> std::io::Result<Self> {
+        let host: IpAddr = if peer.is_ipv6() {
+            Ipv6Addr::UNSPECIFIED.into()
+        }
+        else {
+            Ipv4Addr::UNSPECIFIED.into()
+        };
+
+        let socket = UdpSocket::bind(SocketAddr::new(host, port))?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            peer,
+            buf: vec![0; MAX_REDUNDANCY_PACKET_SIZE],
+            failover: Failover::new(
+                role,
+                Duration::from_secs_f64(REDUNDANCY_FAILOVER_TIMEOUT),
+                Instant::now(),
+            ),
+            replicated: None,
+            last_heartbeat_sent: None,
+            last_replicated: None,
+        })
+    }
+
+    /// The address which heartbeats and state are sent to.
+    pub const fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
+    pub const fn role(&self) -> RedundancyRole {
+        self.failover.role()
+    }
+
+    /// Whether this instance is the primary, and so may send MIDI and OSC.
+    pub const fn is_active(&self) -> bool {
+        self.failover.is_active()
+    }
+
+    /// Receives anything sent by the peer, sends a heartbeat if one is due,
+    /// and returns whether this instance has taken over or given way.
+    pub fn update(&mut self) -> Option<RedundancyEvent> {
+        let now = Instant::now();
+
+        while let Ok((len, from)) = self.socket.recv_from(&mut self.buf) {
+            if from.ip() != self.peer.ip() {
+                continue;
+            }
+
+            if let Ok((_, OscPacket::Message(msg))) =
+                decoder::decode_udp(&self.buf[..len])
+            {
+                self.on_message(msg, now);
+            }
+        }
+
+        let changed = self.failover.update(now);
+
+        if self.last_heartbeat_sent.map_or(true, |t| {
+            now.duration_since(t).as_secs_f64()
+                >= REDUNDANCY_HEARTBEAT_RATE.recip()
+        }) {
+            let args = vec![osc::Type::Int(self.is_active() as i32)];
+            self.send(REDUNDANCY_HEARTBEAT_OSC_ADDRESS, args);
+            self.last_heartbeat_sent = Some(now);
+        }
+
+        if !changed {
+            return None;
+        }
+
+        Some(if self.is_active() {
+            self.last_replicated = None;
+            RedundancyEvent::Promoted(self.replicated.take())
+        }
+        else {
+            RedundancyEvent::Demoted
+        })
+    }
+
+    /// Whether this instance should replicate its state to the peer.
+    pub fn is_replication_due(&self) -> bool {
+        self.is_active()
+            && self.last_replicated.map_or(true, |t| {
+                t.elapsed().as_secs_f64()
+                    >= REDUNDANCY_REPLICATION_RATE.recip()
+            })
+    }
+
+    /// Sends the saved state (as JSON) to the peer.
+    pub fn replicate(&mut self, json: String, is_sending: bool) {
+        let args = vec![
+            osc::Type::String(json),
+            osc::Type::Int(is_sending as i32),
+        ];
+        self.send(REDUNDANCY_STATE_OSC_ADDRESS, args);
+        self.last_replicated = Some(Instant::now());
+    }
+
+    fn on_message(&mut self, msg: osc::Message, now: Instant) {
+        match (msg.addr.as_str(), msg.args.as_slice()) {
+            (REDUNDANCY_HEARTBEAT_OSC_ADDRESS, [osc::Type::Int(active)]) => {
+                self.failover.on_heartbeat(*active != 0, now);
+            }
+            (
+                REDUNDANCY_STATE_OSC_ADDRESS,
+                [osc::Type::String(json), osc::Type::Int(is_sending)],
+            ) => {
+                self.replicated = Some(ReplicatedState {
+                    json: json.clone(),
+                    is_sending: *is_sending != 0,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn send(&self, addr: &str, args: Vec<osc::Type>) {
+        let packet = OscPacket::Message(osc::Message {
+            addr: addr.to_string(),
+            args,
+        });
+
+        // NOTE(jamie): failures are expected while the peer is offline,
+        // which is exactly when the heartbeats matter least.
+        if let Ok(bytes) = encoder::encode(&packet) {
+            _ = self.socket.send_to(&bytes, self.peer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(600);
+
+    fn after(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    fn failover(role: RedundancyRole, start: Instant) -> Failover {
+        Failover::new(role, TIMEOUT, start)
+    }
+
+    #[test]
+    fn the_standby_takes_over_when_the_primary_is_silent() {
+        let start = Instant::now();
+        let mut primary = failover(RedundancyRole::Primary, start);
+        let mut standby = failover(RedundancyRole::Standby, start);
+
+        // the primary takes over once both have heard each other
+        primary.on_heartbeat(false, after(start, 50));
+        standby.on_heartbeat(false, after(start, 50));
+        assert!(primary.update(after(start, 100)));
+        assert!(!standby.update(after(start, 100)));
+        assert!(primary.is_active() && !standby.is_active());
+
+        standby.on_heartbeat(true, after(start, 200));
+        assert!(!standby.update(after(start, 700)));
+
+        // the primary stops sending heartbeats
+        assert!(standby.update(after(start, 800)));
+        assert!(standby.is_active());
+    }
+
+    #[test]
+    fn restarted_and_duplicate_primaries_give_way() {
+        let start = Instant::now();
+
+        // a primary which restarts while the standby is active stays on
+        // standby
+        let mut primary = failover(RedundancyRole::Primary, start);
+        primary.on_heartbeat(true, after(start, 100));
+        assert!(!primary.update(after(start, 200)));
+        assert!(!primary.is_active());
+
+        // if both are active, the standby gives way
+        let mut standby = failover(RedundancyRole::Standby, start);
+        assert!(standby.update(after(start, 600)));
+        standby.on_heartbeat(true, after(start, 700));
+        assert!(standby.update(after(start, 700)));
+        assert!(!standby.is_active());
+
+        let mut primary = failover(RedundancyRole::Primary, start);
+        assert!(primary.update(after(start, 600)));
+        primary.on_heartbeat(true, after(start, 700));
+        assert!(!primary.update(after(start, 700)));
+        assert!(primary.is_active());
+    }
+}
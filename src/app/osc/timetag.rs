@@ -0,0 +1,29 @@
+//! OSC (NTP) timetag helpers for bundle scheduling.
+
+use nannou_osc::rosc::OscTime;
+use std::time::{Duration, SystemTime};
+
+/// The special OSC timetag which means "process immediately".
+pub const IMMEDIATE: OscTime = OscTime { seconds: 0, fractional: 1 };
+
+/// Whether `timetag` is the special "immediate" timetag.
+pub fn is_immediate(timetag: OscTime) -> bool {
+    timetag.seconds == IMMEDIATE.seconds
+        && timetag.fractional == IMMEDIATE.fractional
+}
+
+/// Returns a timetag `delay_secs` seconds from now.
+///
+/// Falls back to [`IMMEDIATE`] if the system clock is set before the NTP
+/// epoch.
+pub fn from_now(delay_secs: f64) -> OscTime {
+    let time = SystemTime::now() + Duration::from_secs_f64(delay_secs.max(0.0));
+
+    OscTime::try_from(time).unwrap_or(IMMEDIATE)
+}
+
+/// Converts `timetag` to a `SystemTime`, or `None` if it is the "immediate"
+/// timetag.
+pub fn to_system_time(timetag: OscTime) -> Option<SystemTime> {
+    (!is_immediate(timetag)).then(|| SystemTime::from(timetag))
+}
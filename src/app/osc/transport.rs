@@ -0,0 +1,522 @@
+//! Transports used to send and receive OSC packets.
+//!
+//! UDP is the default, but may drop packets on congested networks. The TCP
+//! transport frames each packet with SLIP (as described in the OSC 1.1
+//! specification), which guarantees delivery and ordering.
+
+use super::*;
+use nannou_osc::rosc::{decoder, encoder, OscPacket};
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// The largest OSC packet which can be received.
+const MAX_OSC_PACKET_SIZE: usize = decoder::MTU;
+/// How long to wait when trying to connect to a TCP OSC target.
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_millis(250);
+/// How long to wait after a failed connection before trying again, so that
+/// the sending thread isn't blocked by each packet while the target is down.
+const TCP_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// SLIP frame delimiter.
+const SLIP_END: u8 = 0xC0;
+/// SLIP escape byte.
+const SLIP_ESC: u8 = 0xDB;
+/// Escaped form of [`SLIP_END`].
+const SLIP_ESC_END: u8 = 0xDC;
+/// Escaped form of [`SLIP_ESC`].
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// A means of sending and receiving OSC packets.
+pub trait OSCTransport: Send {
+    /// Sends `packet` to the transport's target.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the packet could not be encoded or sent, or if the
+    /// transport has no target.
+    fn send(&mut self, packet: &OscPacket) -> io::Result<()>;
+
+    /// Returns the next received packet, if any. This never blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transport failed to read or decode a packet.
+    fn try_recv(&mut self) -> io::Result<Option<OscPacket>>;
+}
+
+/// The available OSC transports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OSCTransportKind {
+    #[default]
+    UDP,
+    /// TCP with SLIP-framed packets.
+    TCP,
+}
+
+impl OSCTransportKind {
+    /// Parses a transport from its name, i.e. `"udp"` or `"tcp"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "udp" => Some(Self::UDP),
+            "tcp" | "slip" => Some(Self::TCP),
+            _ => None,
+        }
+    }
+
+    /// Returns a transport which receives packets on `addr`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address could not be bound.
+    pub fn bind(self, addr: SocketAddr) -> io::Result<Box<dyn OSCTransport>> {
+        Ok(match self {
+            Self::UDP => Box::new(UDPTransport::bind(addr)?),
+            Self::TCP => Box::new(TCPSlipTransport::listen(addr)?),
+        })
+    }
+
+    /// Returns a transport which sends packets to `addr`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a UDP socket could not be created.
+    pub fn connect(
+        self,
+        addr: SocketAddr,
+    ) -> io::Result<Box<dyn OSCTransport>> {
+        Ok(match self {
+            Self::UDP => Box::new(UDPTransport::connect(addr)?),
+            Self::TCP => Box::new(TCPSlipTransport::connect(addr)),
+        })
+    }
+}
+
+impl std::fmt::Display for OSCTransportKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UDP => write!(f, "UDP"),
+            Self::TCP => write!(f, "TCP (SLIP)"),
+        }
+    }
+}
+
+// *** *** *** //
+
+/// Sends or receives OSC packets as UDP datagrams.
+pub struct UDPTransport {
+    socket: UdpSocket,
+    buf: Vec<u8>,
+}
+
+impl UDPTransport {
+    /// Returns a transport which receives packets on `addr`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address could not be bound.
+    pub fn bind(addr: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self { socket, buf: vec![0; MAX_OSC_PACKET_SIZE] })
+    }
+
+    /// Returns a transport which sends packets to `addr`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a socket could not be created.
+    pub fn connect(addr: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(unspecified_addr_for(addr))?;
+        socket.connect(addr)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self { socket, buf: vec![0; MAX_OSC_PACKET_SIZE] })
+    }
+}
+
+impl OSCTransport for UDPTransport {
+    fn send(&mut self, packet: &OscPacket) -> io::Result<()> {
+        let bytes = encode(packet)?;
+        self.socket.send(&bytes).map(|_| ())
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<OscPacket>> {
+        match self.socket.recv(&mut self.buf) {
+            Ok(len) => decode(&self.buf[..len]).map(Some),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// *** *** *** //
+
+/// Sends or receives SLIP-framed OSC packets over TCP.
+///
+/// When listening, any number of clients may connect. When sending, the
+/// connection is (re-)established lazily if it is lost, at most once every
+/// [`TCP_RECONNECT_INTERVAL`].
+pub struct TCPSlipTransport {
+    listener: Option<TcpListener>,
+    target: Option<SocketAddr>,
+    /// When the last connection attempt failed, if it did.
+    last_failed_connect: Option<Instant>,
+
+    streams: Vec<SlipStream>,
+    received: VecDeque<OscPacket>,
+}
+
+impl TCPSlipTransport {
+    /// Returns a transport which accepts connections on `addr`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address could not be bound.
+    pub fn listen(addr: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            listener: Some(listener),
+            target: None,
+            last_failed_connect: None,
+            streams: Vec::new(),
+            received: VecDeque::new(),
+        })
+    }
+
+    /// Returns a transport which sends packets to `addr`. The connection is
+    /// made when the first packet is sent.
+    pub fn connect(addr: SocketAddr) -> Self {
+        Self {
+            listener: None,
+            target: Some(addr),
+            last_failed_connect: None,
+            streams: Vec::with_capacity(1),
+            received: VecDeque::new(),
+        }
+    }
+
+    fn accept_connections(&mut self) {
+        let Some(listener) = &self.listener
+        else {
+            return;
+        };
+
+        while let Ok((stream, addr)) = listener.accept() {
+            if let Err(e) = stream.set_nonblocking(true) {
+                eprintln!("failed to accept OSC connection from {addr}: {e}");
+                continue;
+            }
+
+            self.streams.push(SlipStream::new(stream));
+        }
+    }
+
+    fn connected_stream(&mut self) -> io::Result<&mut SlipStream> {
+        if self.streams.is_empty() {
+            let Some(target) = self.target
+            else {
+                return Err(io::Error::new(
+                    ErrorKind::NotConnected,
+                    "OSC transport has no target",
+                ));
+            };
+
+            if self
+                .last_failed_connect
+                .is_some_and(|t| t.elapsed() < TCP_RECONNECT_INTERVAL)
+            {
+                return Err(io::Error::new(
+                    ErrorKind::NotConnected,
+                    "waiting to reconnect to the OSC target",
+                ));
+            }
+
+            let connect = |target: &SocketAddr| -> io::Result<TcpStream> {
+                let stream =
+                    TcpStream::connect_timeout(target, TCP_CONNECT_TIMEOUT)?;
+                stream.set_nodelay(true)?;
+                Ok(stream)
+            };
+
+            let stream = match connect(&target) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    self.last_failed_connect = Some(Instant::now());
+                    return Err(e);
+                }
+            };
+
+            self.last_failed_connect = None;
+            self.streams.push(SlipStream::new(stream));
+        }
+
+        Ok(&mut self.streams[0])
+    }
+}
+
+impl OSCTransport for TCPSlipTransport {
+    fn send(&mut self, packet: &OscPacket) -> io::Result<()> {
+        let frame = slip_encode(&encode(packet)?);
+        let result = self.connected_stream()?.stream.write_all(&frame);
+
+        // drop the connection so that the next send reconnects
+        if result.is_err() {
+            self.streams.clear();
+        }
+
+        result
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<OscPacket>> {
+        self.accept_connections();
+
+        let received = &mut self.received;
+
+        self.streams.retain_mut(|s| {
+            match s.read_frames(|frame| match decode(frame) {
+                Ok(packet) => received.push_back(packet),
+                Err(e) => eprintln!("failed to decode OSC packet: {e}"),
+            }) {
+                Ok(is_open) => is_open,
+                Err(e) => {
+                    eprintln!("lost OSC connection: {e}");
+                    false
+                }
+            }
+        });
+
+        Ok(self.received.pop_front())
+    }
+}
+
+/// A TCP stream with an incremental SLIP decoder.
+struct SlipStream {
+    stream: TcpStream,
+    decoder: SlipDecoder,
+    buf: Vec<u8>,
+}
+
+impl SlipStream {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            decoder: SlipDecoder::new(),
+            buf: vec![0; MAX_OSC_PACKET_SIZE],
+        }
+    }
+
+    /// Reads all available bytes from the stream, calling `on_frame` for each
+    /// complete frame. Returns `false` if the stream was closed.
+    fn read_frames(
+        &mut self,
+        mut on_frame: impl FnMut(&[u8]),
+    ) -> io::Result<bool> {
+        loop {
+            let len = match self.stream.read(&mut self.buf) {
+                Ok(0) => return Ok(false),
+                Ok(len) => len,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(true),
+                Err(e) => return Err(e),
+            };
+
+            self.decoder.push(&self.buf[..len], &mut on_frame);
+        }
+    }
+}
+
+/// Decodes SLIP frames from bytes which may arrive in any number of parts.
+struct SlipDecoder {
+    frame: Vec<u8>,
+    escaped: bool,
+}
+
+impl SlipDecoder {
+    fn new() -> Self {
+        Self { frame: Vec::with_capacity(MAX_OSC_PACKET_SIZE), escaped: false }
+    }
+
+    /// Decodes `bytes`, calling `on_frame` for each frame which they
+    /// complete.
+    fn push(&mut self, bytes: &[u8], mut on_frame: impl FnMut(&[u8])) {
+        for &byte in bytes {
+            match (byte, self.escaped) {
+                (SLIP_END, _) => {
+                    // empty frames are produced by the leading END byte
+                    if !self.frame.is_empty() {
+                        on_frame(&self.frame);
+                        self.frame.clear();
+                    }
+
+                    self.escaped = false;
+                }
+                (SLIP_ESC, false) => self.escaped = true,
+                (SLIP_ESC_END, true) => {
+                    self.frame.push(SLIP_END);
+                    self.escaped = false;
+                }
+                (SLIP_ESC_ESC, true) => {
+                    self.frame.push(SLIP_ESC);
+                    self.escaped = false;
+                }
+                (b, _) => {
+                    self.frame.push(b);
+                    self.escaped = false;
+                }
+            }
+        }
+    }
+}
+
+// *** *** *** //
+
+/// An address on any port of all local interfaces, with the same IP version
+/// as `target`.
+pub const fn unspecified_addr_for(target: SocketAddr) -> SocketAddr {
+    let ip = match target {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+
+    SocketAddr::new(ip, 0)
+}
+
+/// Encodes `bytes` as a SLIP frame, with an END byte on either side.
+fn slip_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(bytes.len() + 2);
+    frame.push(SLIP_END);
+
+    for &byte in bytes {
+        match byte {
+            SLIP_END => frame.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => frame.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+            b => frame.push(b),
+        }
+    }
+
+    frame.push(SLIP_END);
+    frame
+}
+
+fn encode(packet: &OscPacket) -> io::Result<Vec<u8>> {
+    encoder::encode(packet)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("{e:?}")))
+}
+
+fn decode(bytes: &[u8]) -> io::Result<OscPacket> {
+    decoder::decode_udp(bytes)
+        .map(|(_, packet)| packet)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("{e:?}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The frames which `decoder` decodes from each of `parts` in turn.
+    fn decode_parts(
+        decoder: &mut SlipDecoder,
+        parts: &[&[u8]],
+    ) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+
+        for part in parts {
+            decoder.push(part, |frame| frames.push(frame.to_vec()));
+        }
+
+        frames
+    }
+
+    #[test]
+    fn end_and_esc_bytes_are_escaped() {
+        let bytes = [1, SLIP_END, 2, SLIP_ESC, 3, SLIP_ESC_END, SLIP_ESC_ESC];
+        let frame = slip_encode(&bytes);
+
+        assert_eq!(
+            frame,
+            [
+                SLIP_END,
+                1,
+                SLIP_ESC,
+                SLIP_ESC_END,
+                2,
+                SLIP_ESC,
+                SLIP_ESC_ESC,
+                3,
+                SLIP_ESC_END,
+                SLIP_ESC_ESC,
+                SLIP_END
+            ]
+        );
+        // only the delimiters are END bytes
+        assert_eq!(frame.iter().filter(|&&b| b == SLIP_END).count(), 2);
+
+        let frames = decode_parts(&mut SlipDecoder::new(), &[&frame]);
+        assert_eq!(frames, [bytes.to_vec()]);
+    }
+
+    #[test]
+    fn frames_may_be_split_across_reads() {
+        let first = slip_encode(&[SLIP_ESC, 10, 11]);
+        let second = slip_encode(&[SLIP_END, 12]);
+        let bytes = [first, second].concat();
+        let mut decoder = SlipDecoder::new();
+
+        // split within an escape sequence, and between the frames
+        let frames = decode_parts(
+            &mut decoder,
+            &[&bytes[..2], &bytes[2..6], &bytes[6..8], &bytes[8..]],
+        );
+        assert_eq!(frames, [vec![SLIP_ESC, 10, 11], vec![SLIP_END, 12]]);
+
+        // a partial frame is held until it ends
+        assert!(decode_parts(&mut decoder, &[&[SLIP_END, 1, 2]]).is_empty());
+        assert_eq!(decode_parts(&mut decoder, &[&[3, SLIP_END]]), [[1, 2, 3]]);
+    }
+
+    #[test]
+    fn empty_frames_are_skipped() {
+        let frames = decode_parts(
+            &mut SlipDecoder::new(),
+            &[&[SLIP_END, SLIP_END, SLIP_END], &slip_encode(&[]), &[SLIP_END]],
+        );
+        assert!(frames.is_empty());
+    }
+
+    #[test]
+    fn encoded_packets_survive_a_round_trip() {
+        let packet = OscPacket::Message(nannou_osc::Message {
+            addr: String::from("/test"),
+            // a blob holding both special bytes
+            args: vec![nannou_osc::Type::Blob(vec![SLIP_END, SLIP_ESC, 0])],
+        });
+        let frame = slip_encode(&encode(&packet).unwrap());
+
+        let frames = decode_parts(&mut SlipDecoder::new(), &[&frame]);
+        assert_eq!(decode(&frames[0]).unwrap(), packet);
+    }
+
+    #[test]
+    fn reconnects_wait_after_a_failed_connection() {
+        // find a local port which nothing is listening on
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap();
+        let mut transport = TCPSlipTransport::connect(addr);
+        let packet = OscPacket::Message(nannou_osc::Message {
+            addr: String::from("/test"),
+            args: Vec::new(),
+        });
+
+        assert!(transport.send(&packet).is_err());
+        assert!(transport.last_failed_connect.is_some());
+
+        // the next send doesn't try to connect again
+        let err = transport.send(&packet).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotConnected);
+    }
+}
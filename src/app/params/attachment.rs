@@ -32,6 +32,59 @@ pub enum MIDICCSize {
     CC14Bit,
 }
 
+/// How important an attachment is when the MIDI byte budget is tight. CCs
+/// with a higher priority are sent first each tick.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum CCPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    /// Always sent before anything else.
+    Critical,
+}
+
+/// The smallest and largest exponents of a [`CCResponse`] curve.
+pub const CC_CURVE_RANGE: (f32, f32) = (0.1, 10.0);
+
+/// How an attachment's value is shaped before it is sent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CCResponse {
+    /// The exponent which the value is raised to, where `1.0` is linear.
+    pub curve: f32,
+    /// The value sent when the source is at its lowest.
+    pub min: f32,
+    /// The value sent when the source is at its highest, which may be below
+    /// `min` to invert the source.
+    pub max: f32,
+}
+
+impl CCResponse {
+    /// Whether the response leaves values unchanged.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Shapes a value from `0` to `1`.
+    pub fn apply(&self, value: f32) -> f32 {
+        if self.is_identity() {
+            return value;
+        }
+
+        let shaped = value.clamp(0.0, 1.0).powf(self.curve);
+
+        (self.max - self.min).mul_add(shaped, self.min)
+    }
+}
+
+impl Default for CCResponse {
+    fn default() -> Self {
+        Self { curve: 1.0, min: 0.0, max: 1.0 }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct MIDICCAttachment {
     name: String,
@@ -40,6 +93,13 @@ pub struct MIDICCAttachment {
     smoother: Option<CCSmoother>,
     size: MIDICCSize,
     update_threshold: f32,
+    latchable: bool,
+    soft_takeover: bool,
+    priority: CCPriority,
+
+    response: CCResponse,
+    /// The callback's value before the response was applied.
+    raw: f32,
 }
 
 impl MIDICCAttachment {
@@ -58,6 +118,12 @@ impl MIDICCAttachment {
             smoother: smoothing_time.map(CCSmoother::with_time),
             size,
             update_threshold: DEFAULT_MIDI_CC_UPDATE_THRESHOLD,
+            latchable: false,
+            soft_takeover: false,
+            priority: CCPriority::default(),
+
+            response: CCResponse::default(),
+            raw: 0.0,
         }
     }
 
@@ -76,6 +142,35 @@ impl MIDICCAttachment {
         self
     }
 
+    /// Sets whether the attachment's value is frozen by the latch gesture.
+    pub const fn with_latchable(&mut self, latchable: bool) -> &mut Self {
+        self.latchable = latchable;
+        self
+    }
+
+    /// Sets whether the attachment must be "picked up" after its source
+    /// changes, rather than jumping to the new value.
+    pub const fn with_soft_takeover(
+        &mut self,
+        soft_takeover: bool,
+    ) -> &mut Self {
+        self.soft_takeover = soft_takeover;
+        self
+    }
+
+    /// Sets how important the attachment is when the MIDI byte budget is
+    /// tight.
+    pub const fn with_priority(&mut self, priority: CCPriority) -> &mut Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets how the attachment's value is shaped before it is sent.
+    pub fn with_response(&mut self, response: CCResponse) -> &mut Self {
+        self.response = response;
+        self
+    }
+
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
@@ -86,21 +181,39 @@ impl MIDICCAttachment {
         cc_value: &mut f32,
         delta_time: f32,
     ) {
-        if let Some(smoother) = &mut self.smoother {
-            let mut tmp = *cc_value;
-            (self.callback)(significant_values, &mut tmp);
+        // callbacks may leave the value unchanged, so a shaped value must
+        // not be shaped again
+        let mut tmp =
+            if self.response.is_identity() { *cc_value } else { self.raw };
+        (self.callback)(significant_values, &mut tmp);
+
+        self.raw = tmp;
+        let shaped = self.response.apply(tmp);
+
+        *cc_value = match &mut self.smoother {
+            Some(smoother) => smoother.get_next(shaped, delta_time),
+            None => shaped,
+        };
+    }
 
-            *cc_value = smoother.get_next(tmp, delta_time);
-        }
-        else {
-            (self.callback)(significant_values, cc_value);
-        }
+    /// Calls the attachment's callback directly, without smoothing.
+    #[cfg(test)]
+    pub fn evaluate(&self, values: &CCUpdateData, cc_value: &mut f32) {
+        (self.callback)(values, cc_value);
     }
 
     pub fn is_active_for(&self, state: &ParameterState) -> bool {
         (self.predicate)(state)
     }
 
+    pub const fn size(&self) -> MIDICCSize {
+        self.size
+    }
+
+    pub const fn response(&self) -> CCResponse {
+        self.response
+    }
+
     pub const fn is_14_bit(&self) -> bool {
         matches!(self.size, MIDICCSize::CC14Bit)
     }
@@ -108,4 +221,16 @@ impl MIDICCAttachment {
     pub const fn update_threshold(&self) -> f32 {
         self.update_threshold
     }
+
+    pub const fn is_latchable(&self) -> bool {
+        self.latchable
+    }
+
+    pub const fn has_soft_takeover(&self) -> bool {
+        self.soft_takeover
+    }
+
+    pub const fn priority(&self) -> CCPriority {
+        self.priority
+    }
 }
@@ -0,0 +1,294 @@
+//! Attachments which map hand data to internal audio parameters, rather than
+//! to MIDI CCs.
+
+use crate::app::audio::{
+    GranularControl, ModulationControl, PluckControl, TiltControl,
+};
+use crate::app::hands::hand_types::Finger;
+use crate::dsp::filtering::tilt::MAX_TILT_DB;
+use attachment::{MIDICCAttachment, MIDICCFn, MIDICCSize};
+use hands::hand_types::CCUpdateData;
+use state::ParameterState;
+
+use super::*;
+
+const POSITION_SMOOTHING_TIME: f32 = 0.1;
+const DENSITY_SMOOTHING_TIME: f32 = 0.15;
+const BRIGHTNESS_SMOOTHING_TIME: f32 = 0.1;
+const DECAY_SMOOTHING_TIME: f32 = 0.1;
+const GESTURE_SMOOTHING_TIME: f32 = 0.1;
+const TILT_SMOOTHING_TIME: f32 = 0.2;
+
+/// The sparsest grain density which hand data maps to.
+const MIN_MAPPED_GRAIN_DENSITY: f64 = 2.0;
+/// The densest grain density which hand data maps to.
+const MAX_MAPPED_GRAIN_DENSITY: f64 = 120.0;
+
+/// The shortest string decay which hand data maps to.
+const MIN_MAPPED_PLUCK_DECAY_SECS: f64 = 0.2;
+/// The longest string decay which hand data maps to.
+const MAX_MAPPED_PLUCK_DECAY_SECS: f64 = 8.0;
+
+/// The controls of the audio engine which attachments may drive.
+#[derive(Clone)]
+pub struct AudioControls {
+    pub granular: Arc<GranularControl>,
+    pub pluck: Arc<PluckControl>,
+    pub modulation: Arc<ModulationControl>,
+    pub tilt: Arc<TiltControl>,
+}
+
+/// Internal audio parameters which attachments may target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AudioParameter {
+    /// Where grains start in the granular engine's recording.
+    GrainPosition,
+    /// How many grains are spawned per second.
+    GrainDensity,
+    /// How bright the plucked strings are.
+    PluckBrightness,
+    /// How long the plucked strings ring for.
+    PluckDecay,
+    /// One of the modulation matrix's gesture sources.
+    ModGesture(usize),
+    /// How bright or dark the whole output is.
+    SpectralTilt,
+}
+
+impl AudioParameter {
+    /// Sets the parameter from a normalized `value` (from `0.0` to `1.0`).
+    pub fn apply(self, value: f32, controls: &AudioControls) {
+        let AudioControls { granular, pluck, modulation, tilt } = controls;
+        let value = value.clamp(0.0, 1.0) as f64;
+
+        match self {
+            Self::GrainPosition => granular.position.sr(value),
+            // density is mapped exponentially, as it's perceived as a rate
+            Self::GrainDensity => granular.density.sr(
+                MIN_MAPPED_GRAIN_DENSITY
+                    * (MAX_MAPPED_GRAIN_DENSITY / MIN_MAPPED_GRAIN_DENSITY)
+                        .powf(value),
+            ),
+            Self::PluckBrightness => pluck.brightness.sr(value),
+            Self::PluckDecay => pluck.decay_secs.sr(
+                MIN_MAPPED_PLUCK_DECAY_SECS
+                    * (MAX_MAPPED_PLUCK_DECAY_SECS
+                        / MIN_MAPPED_PLUCK_DECAY_SECS)
+                        .powf(value),
+            ),
+            Self::ModGesture(n) => {
+                if let Some(gesture) = modulation.gestures.get(n) {
+                    gesture.sr(value);
+                }
+            }
+            // the middle of the range is flat
+            Self::SpectralTilt => {
+                tilt.tilt_db.sr(value.mul_add(2.0, -1.0) * MAX_TILT_DB);
+            }
+        }
+    }
+}
+
+/// A CC-style attachment whose value is sent to an internal audio parameter.
+#[derive(Clone, Debug)]
+pub struct AudioParameterAttachment {
+    target: AudioParameter,
+    attachment: MIDICCAttachment,
+    /// The latest normalized value.
+    value: f32,
+}
+
+impl AudioParameterAttachment {
+    pub const fn target(&self) -> AudioParameter {
+        self.target
+    }
+
+    pub const fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Updates the attachment's value if it is active for `state`, and
+    /// applies it to the target.
+    pub fn update(
+        &mut self,
+        values: &CCUpdateData,
+        state: &ParameterState,
+        controls: &AudioControls,
+        delta_time: f32,
+    ) {
+        if !self.attachment.is_active_for(state) {
+            return;
+        }
+
+        self.attachment.callback(values, &mut self.value, delta_time);
+        self.target.apply(self.value, controls);
+    }
+}
+
+/// Applies the audio attachments from hand data alone, without the rest of
+/// the parameter updater, such as for offline renders.
+///
+/// The attachments are active as in the default mode, and see no hand
+/// velocities or audio bands.
+pub struct AudioAttachmentDriver {
+    attachments: Vec<AudioParameterAttachment>,
+    state: ParameterState,
+    controls: AudioControls,
+}
+
+impl AudioAttachmentDriver {
+    pub fn new(controls: AudioControls) -> Self {
+        Self {
+            attachments: build_audio_attachments(),
+            state: ParameterState::default(),
+            controls,
+        }
+    }
+
+    /// Updates each attachment from `hands`, `delta_time` seconds after the
+    /// last update.
+    pub fn update(&mut self, hands: &RawHandPairCOM, delta_time: f32) {
+        let values = CCUpdateData {
+            hands,
+            velocities: &(0.0, 0.0),
+            audio_bands: &[],
+            mode_sweep: None,
+            intensity: None,
+        };
+
+        for attachment in &mut self.attachments {
+            attachment.update(
+                &values,
+                &self.state,
+                &self.controls,
+                delta_time,
+            );
+        }
+    }
+}
+
+fn add<'a>(
+    attachments: &'a mut Vec<AudioParameterAttachment>,
+    target: AudioParameter,
+    name: &str,
+    callback: MIDICCFn,
+) -> &'a mut MIDICCAttachment {
+    attachments.push(AudioParameterAttachment {
+        target,
+        attachment: MIDICCAttachment::new(
+            name,
+            callback,
+            |state: &ParameterState| true,
+            None,
+            MIDICCSize::CC7Bit,
+            DEFAULT_MIDI_CC_UPDATE_THRESHOLD,
+        ),
+        value: 0.0,
+    });
+
+    let last = attachments.len() - 1;
+    &mut attachments[last].attachment
+}
+
+pub fn build_audio_attachments() -> Vec<AudioParameterAttachment> {
+    let mut attachments = Vec::new();
+
+    add(
+        &mut attachments,
+        AudioParameter::GrainPosition,
+        "Second hand x-pos to grain position",
+        |values: &CCUpdateData, value: &mut f32| {
+            if let Some(com) = &values.hands.com.second {
+                *value = com.x as f32;
+            }
+        },
+    )
+    .with_smoothing_time(POSITION_SMOOTHING_TIME);
+
+    add(
+        &mut attachments,
+        AudioParameter::GrainDensity,
+        "Second hand openness to grain density",
+        |values: &CCUpdateData, value: &mut f32| {
+            if let Some(hand) = &values.hands.pair.second
+                && let Some(com) = values.hands.com.second
+            {
+                let openness =
+                    map(hand.get_openness_from(com), 0.72, 2.0, 0.0, 1.0)
+                        .clamp(0.0, 1.0);
+                *value = openness as f32;
+            }
+        },
+    )
+    .with_smoothing_time(DENSITY_SMOOTHING_TIME);
+
+    add(
+        &mut attachments,
+        AudioParameter::PluckBrightness,
+        "Second hand y-pos to pluck brightness",
+        |values: &CCUpdateData, value: &mut f32| {
+            if let Some(com) = &values.hands.com.second {
+                *value = 1.0 - (com.y as f32);
+            }
+        },
+    )
+    .with_smoothing_time(BRIGHTNESS_SMOOTHING_TIME);
+
+    add(
+        &mut attachments,
+        AudioParameter::PluckDecay,
+        "Second hand index finger pinch to pluck decay",
+        |values: &CCUpdateData, value: &mut f32| {
+            // pinching mutes the strings
+            if let Some(hand) = &values.hands.pair.second {
+                *value = 1.0 - hand.get_pinch_for(Finger::Index) as f32;
+            }
+        },
+    )
+    .with_smoothing_time(DECAY_SMOOTHING_TIME);
+
+    add(
+        &mut attachments,
+        AudioParameter::ModGesture(0),
+        "First hand y-pos to modulation gesture 1",
+        |values: &CCUpdateData, value: &mut f32| {
+            if let Some(com) = &values.hands.com.first {
+                *value = 1.0 - (com.y as f32);
+            }
+        },
+    )
+    .with_smoothing_time(GESTURE_SMOOTHING_TIME);
+
+    add(
+        &mut attachments,
+        AudioParameter::ModGesture(1),
+        "First hand x-pos to modulation gesture 2",
+        |values: &CCUpdateData, value: &mut f32| {
+            if let Some(com) = &values.hands.com.first {
+                *value = com.x as f32;
+            }
+        },
+    )
+    .with_smoothing_time(GESTURE_SMOOTHING_TIME);
+
+    add(
+        &mut attachments,
+        AudioParameter::SpectralTilt,
+        "Average hand height to spectral tilt",
+        |values: &CCUpdateData, value: &mut f32| {
+            let com = &values.hands.com;
+
+            *value = match (com.first, com.second) {
+                (Some(first), Some(second)) => {
+                    1.0 - ((first.y + second.y) * 0.5) as f32
+                }
+                (Some(com), None) | (None, Some(com)) => 1.0 - com.y as f32,
+                // without any hands, the tilt returns to flat
+                (None, None) => 0.5,
+            };
+        },
+    )
+    .with_smoothing_time(TILT_SMOOTHING_TIME);
+
+    attachments
+}
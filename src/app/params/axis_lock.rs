@@ -0,0 +1,99 @@
+//! Locking hand movement to a single axis via a held gesture.
+
+use super::*;
+
+/// How far a hand must move after the lock gesture starts before the locked
+/// axis is chosen.
+const AXIS_LOCK_DECISION_DISTANCE: f64 = 0.03;
+
+/// The axis which movement is constrained to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockedAxis {
+    /// Only horizontal movement is honored.
+    X,
+    /// Only vertical movement is honored.
+    Y,
+}
+
+impl std::fmt::Display for LockedAxis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::X => write!(f, "X"),
+            Self::Y => write!(f, "Y"),
+        }
+    }
+}
+
+/// Constrains a hand's position to one axis while the lock gesture is held.
+///
+/// When the gesture starts, the hand's position is held until it has moved
+/// far enough to tell which axis is intended; from then on, only movement
+/// along that axis is passed through. Depth is never constrained.
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct AxisLock {
+    anchor: Option<DVec3>,
+    axis: Option<LockedAxis>,
+}
+
+impl AxisLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the lock gesture is currently being held.
+    pub const fn is_active(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    /// The axis which movement is locked to, if it has been chosen.
+    pub const fn locked_axis(&self) -> Option<LockedAxis> {
+        self.axis
+    }
+
+    /// Constrains `pos` according to the lock, given whether the lock gesture
+    /// is currently being made. Returns `true` in the second element if the
+    /// lock was just released.
+    pub fn process(
+        &mut self,
+        is_gesture: bool,
+        pos: Option<DVec3>,
+    ) -> (Option<DVec3>, bool) {
+        let Some(pos) = pos
+        else {
+            let released = self.is_active();
+            self.release();
+            return (None, released);
+        };
+
+        if !is_gesture {
+            let released = self.is_active();
+            self.release();
+            return (Some(pos), released);
+        }
+
+        let anchor = *self.anchor.get_or_insert(pos);
+
+        if self.axis.is_none() {
+            let dx = (pos.x - anchor.x).abs();
+            let dy = (pos.y - anchor.y).abs();
+
+            if dx.max(dy) >= AXIS_LOCK_DECISION_DISTANCE {
+                self.axis =
+                    Some(if dx > dy { LockedAxis::X } else { LockedAxis::Y });
+            }
+        }
+
+        let locked = match self.axis {
+            Some(LockedAxis::X) => dvec3(pos.x, anchor.y, pos.z),
+            Some(LockedAxis::Y) => dvec3(anchor.x, pos.y, pos.z),
+            None => dvec3(anchor.x, anchor.y, pos.z),
+        };
+
+        (Some(locked), false)
+    }
+
+    pub fn release(&mut self) {
+        self.anchor = None;
+        self.axis = None;
+    }
+}
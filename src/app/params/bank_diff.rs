@@ -0,0 +1,140 @@
+//! A dump of the CCs which are waiting to be sent, for debugging why a CC
+//! is (or isn't) transmitted.
+//!
+//! A CC is only sent if it is marked as updated *and* has moved past its
+//! update threshold since it was last sent, and only if the MIDI buffer
+//! hasn't already reached [`MAX_MIDI_BUFFER_SIZE_BYTES`] that tick.
+
+use super::*;
+use attachment::MIDICCAttachment;
+use midi_types::{MIDICCIndex, MIDIParameterBank};
+use std::collections::HashSet;
+
+/// The state of one CC in the parameter bank.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BankDiffEntry {
+    /// The name of the CC's attachment, if it has one.
+    pub name: Option<String>,
+    /// The CC's channel, from `1` to `16`.
+    pub channel: u8,
+    pub cc: u8,
+    /// The current value.
+    pub value: f32,
+    /// The value which was last sent.
+    pub sent_value: f32,
+    pub update_threshold: f32,
+    /// Whether the CC will be sent regardless of its threshold.
+    pub forced: bool,
+}
+
+impl std::fmt::Display for BankDiffEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ch {:>2} cc {:>3} {:<32} {:.5} (sent {:.5}, delta {:.5}, \
+             threshold {:.5})",
+            self.channel,
+            self.cc,
+            self.name.as_deref().unwrap_or("<unattached>"),
+            self.value,
+            self.sent_value,
+            (self.value - self.sent_value).abs(),
+            self.update_threshold,
+        )?;
+
+        if self.forced {
+            write!(f, " [forced]")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The CCs which are pending in the parameter bank.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParameterBankDiff {
+    /// CCs which have moved past their update threshold (or are forced).
+    pub outside_threshold: Vec<BankDiffEntry>,
+    /// CCs which are marked as updated, but are held back because they are
+    /// within their update threshold.
+    pub updated_unsent: Vec<BankDiffEntry>,
+    /// CCs which were due to be sent last tick, but didn't fit within the
+    /// MIDI buffer's byte budget.
+    pub truncated: Vec<BankDiffEntry>,
+    /// The number of bytes sent last tick.
+    pub last_tick_bytes: usize,
+}
+
+impl ParameterBankDiff {
+    pub(super) fn new(
+        bank: &MIDIParameterBank,
+        updated: &HashSet<MIDICCIndex>,
+        truncated: &[MIDICCIndex],
+        attachments: &HashMap<MIDICCIndex, MIDICCAttachment>,
+        last_tick_bytes: usize,
+    ) -> Self {
+        let outside = bank.get_ccs_outside_of_threshold();
+
+        let entry = |idx: MIDICCIndex| {
+            let param = bank.get_cc(&idx);
+
+            BankDiffEntry {
+                name: attachments.get(&idx).map(|att| att.name().to_string()),
+                channel: idx.channel as u8 + 1,
+                cc: idx.cc as u8,
+                value: param.value,
+                sent_value: bank.get_cached_cc(&idx).value,
+                update_threshold: param.update_threshold,
+                forced: bank.is_forced(&idx),
+            }
+        };
+
+        let entries = |mut indices: Vec<MIDICCIndex>| -> Vec<BankDiffEntry> {
+            indices.sort_unstable();
+            indices.into_iter().map(&entry).collect()
+        };
+
+        Self {
+            outside_threshold: entries(outside.iter().copied().collect()),
+            updated_unsent: entries(
+                updated.difference(&outside).copied().collect(),
+            ),
+            truncated: entries(truncated.to_vec()),
+            last_tick_bytes,
+        }
+    }
+
+    /// Whether any CCs are pending.
+    pub fn is_empty(&self) -> bool {
+        self.outside_threshold.is_empty()
+            && self.updated_unsent.is_empty()
+            && self.truncated.is_empty()
+    }
+}
+
+impl std::fmt::Display for ParameterBankDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "parameter bank: sent {} of {MAX_MIDI_BUFFER_SIZE_BYTES} bytes \
+             last tick",
+            self.last_tick_bytes
+        )?;
+
+        let sections = [
+            ("outside of threshold", &self.outside_threshold),
+            ("updated but within threshold", &self.updated_unsent),
+            ("truncated by byte budget", &self.truncated),
+        ];
+
+        for (title, entries) in sections {
+            writeln!(f, "  {title} ({}):", entries.len())?;
+
+            for entry in entries {
+                writeln!(f, "    {entry}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+//! Calibration of the user's comfortable reach, so that position-derived
+//! sources span their full range for each user.
+
+use super::*;
+use serde_json::{json, Value};
+use std::time::Instant;
+
+/// How long each calibration step records for.
+const CALIBRATION_STEP_TIME: f64 = 3.0;
+/// The smallest range of movement which is accepted for an axis. Axes which
+/// move less than this during calibration are left uncalibrated.
+const MIN_CALIBRATION_SPAN: f64 = 0.05;
+
+/// The range of comfortable movement along each axis of the tracking space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReachExtents {
+    pub x: AxisRange,
+    pub y: AxisRange,
+    pub z: AxisRange,
+}
+
+impl ReachExtents {
+    /// Rescales `pos` so that the extents map to `[0, 1]` on each axis.
+    /// Positions beyond the extents are clamped.
+    pub fn normalize(&self, pos: DVec3) -> DVec3 {
+        let norm = |v: f64, range: AxisRange| {
+            normalize(v, range.min, range.max).clamp(0.0, 1.0)
+        };
+
+        dvec3(norm(pos.x, self.x), norm(pos.y, self.y), norm(pos.z, self.z))
+    }
+
+    /// Whether these extents have no effect.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Parses extents of the form `{ "x": [min, max], ... }`. Missing axes
+    /// span the full range.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any axis is not a pair of increasing numbers.
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let default = Self::default();
+
+        let axis = |name: &str, default: AxisRange| {
+            let Some(range) = value.get(name)
+            else {
+                return Ok(default);
+            };
+
+            match range.as_array().map(Vec::as_slice) {
+                Some([min, max]) => match (min.as_f64(), max.as_f64()) {
+                    (Some(min), Some(max)) if min < max => {
+                        Ok(AxisRange::new(min, max))
+                    }
+                    _ => Err(format!("invalid reach range for \"{name}\"")),
+                },
+                _ => Err(format!("reach for \"{name}\" must be [min, max]")),
+            }
+        };
+
+        Ok(Self {
+            x: axis("x", default.x)?,
+            y: axis("y", default.y)?,
+            z: axis("z", default.z)?,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "x": [self.x.min, self.x.max],
+            "y": [self.y.min, self.y.max],
+            "z": [self.z.min, self.z.max],
+        })
+    }
+}
+
+impl Default for ReachExtents {
+    fn default() -> Self {
+        let full = AxisRange::new(0.0, 1.0);
+        Self { x: full, y: full, z: full }
+    }
+}
+
+// *** *** *** //
+
+/// The steps of the calibration wizard, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CalibrationStep {
+    Left,
+    Right,
+    Up,
+    Down,
+    Forward,
+    Back,
+}
+
+impl CalibrationStep {
+    const ALL: [Self; 6] = [
+        Self::Left,
+        Self::Right,
+        Self::Up,
+        Self::Down,
+        Self::Forward,
+        Self::Back,
+    ];
+
+    /// The key of the instruction shown to the user during this step, in
+    /// the [`StringTable`](crate::app::strings::StringTable).
+    pub const fn prompt_key(self) -> &'static str {
+        match self {
+            Self::Left => "calibration.left",
+            Self::Right => "calibration.right",
+            Self::Up => "calibration.up",
+            Self::Down => "calibration.down",
+            Self::Forward => "calibration.forward",
+            Self::Back => "calibration.back",
+        }
+    }
+}
+
+/// The minimum and maximum values seen along one axis.
+#[derive(Clone, Copy, Debug)]
+struct AxisExtent {
+    min: f64,
+    max: f64,
+}
+
+impl AxisExtent {
+    const fn new() -> Self {
+        Self { min: f64::INFINITY, max: f64::NEG_INFINITY }
+    }
+
+    fn push(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// The recorded range, or `default` if not enough movement was recorded.
+    fn range_or(&self, default: AxisRange) -> AxisRange {
+        if self.max - self.min >= MIN_CALIBRATION_SPAN {
+            AxisRange::new(self.min, self.max)
+        }
+        else {
+            default
+        }
+    }
+}
+
+/// An interactive calibration mode, which asks the user to reach to the
+/// extents of their comfortable movement and records the range of each axis.
+#[derive(Clone, Debug)]
+pub struct CalibrationWizard {
+    step_idx: usize,
+    step_start: Instant,
+    extents: [AxisExtent; 3],
+}
+
+impl CalibrationWizard {
+    pub fn new() -> Self {
+        Self {
+            step_idx: 0,
+            step_start: Instant::now(),
+            extents: [AxisExtent::new(); 3],
+        }
+    }
+
+    /// The current step, or `None` if calibration has finished.
+    pub fn step(&self) -> Option<CalibrationStep> {
+        CalibrationStep::ALL.get(self.step_idx).copied()
+    }
+
+    /// The key of the instruction for the current step (see
+    /// [`CalibrationStep::prompt_key()`]), along with how many seconds of it
+    /// remain.
+    pub fn prompt(&self) -> Option<(&'static str, f64)> {
+        let step = self.step()?;
+        let remaining = CALIBRATION_STEP_TIME
+            - self.step_start.elapsed().as_secs_f64();
+
+        Some((step.prompt_key(), remaining.max(0.0)))
+    }
+
+    /// Records the latest hand position, if any. Returns the measured
+    /// extents once all steps are complete.
+    ///
+    /// Each step restarts if the hand leaves the frame, so that steps
+    /// aren't missed.
+    pub fn update(&mut self, pos: Option<DVec3>) -> Option<ReachExtents> {
+        self.step()?;
+
+        let Some(pos) = pos
+        else {
+            self.step_start = Instant::now();
+            return None;
+        };
+
+        for (extent, v) in self.extents.iter_mut().zip(pos.to_array()) {
+            extent.push(v);
+        }
+
+        if self.step_start.elapsed().as_secs_f64() < CALIBRATION_STEP_TIME {
+            return None;
+        }
+
+        self.step_idx += 1;
+        self.step_start = Instant::now();
+
+        self.step().is_none().then(|| self.extents())
+    }
+
+    /// The extents which have been recorded so far.
+    pub fn extents(&self) -> ReachExtents {
+        let default = ReachExtents::default();
+
+        ReachExtents {
+            x: self.extents[0].range_or(default.x),
+            y: self.extents[1].range_or(default.y),
+            z: self.extents[2].range_or(default.z),
+        }
+    }
+}
+
+impl Default for CalibrationWizard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
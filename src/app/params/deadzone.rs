@@ -0,0 +1,311 @@
+//! Dead-zones and hysteresis for each axis of the tracking space.
+//!
+//! These are applied to hand positions before they are mapped to parameters,
+//! so that noisy regions (such as the edges of the camera's field of view)
+//! don't modulate anything. A configuration may be loaded from a JSON file of
+//! the form:
+//!
+//! ```json
+//! {
+//!     "x": { "edge": 0.02, "center": 0.0, "hysteresis": 0.002 },
+//!     "y": { "edge": 0.02, "center": 0.0, "hysteresis": 0.002 },
+//!     "z": { "edge": 0.0, "center": 0.0, "hysteresis": 0.0 }
+//! }
+//! ```
+//!
+//! Any missing axes or fields default to zero (i.e. no effect).
+
+use super::*;
+use crate::app::diagnostics::Diagnostics;
+use serde_json::{json, Value};
+
+/// A closed range along one axis of the (normalized) tracking space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AxisRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl AxisRange {
+    pub const fn new(min: f64, max: f64) -> Self {
+        Self { min, max }
+    }
+
+    /// A range of `width` either side of the center of the axis.
+    pub fn centered(width: f64) -> Self {
+        Self::new(0.5 - width, 0.5 + width)
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+/// Dead-zones for one axis, in the normalized `[0, 1]` range.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AxisDeadZone {
+    /// The width of the dead-zone at each edge of the axis.
+    pub edge: f64,
+    /// The width of the dead-zone either side of the center of the axis.
+    pub center: f64,
+}
+
+impl AxisDeadZone {
+    /// Maps `value` so that the edge dead-zones hold at `0` and `1`, the
+    /// center dead-zone holds at `0.5`, and the live regions are stretched to
+    /// fill the rest of the range.
+    pub fn apply(&self, value: f64) -> f64 {
+        let edge = self.edge.clamp(0.0, 0.5);
+        let center = self.center.clamp(0.0, 0.5 - edge);
+
+        if edge == 0.0 && center == 0.0 {
+            return value;
+        }
+
+        let lower = AxisRange::new(edge, 0.5 - center);
+        let upper = AxisRange::new(0.5 + center, 1.0 - edge);
+
+        if value <= lower.min {
+            0.0
+        }
+        else if value < lower.max {
+            map(value, lower.min, lower.max, 0.0, 0.5)
+        }
+        else if value <= upper.min {
+            0.5
+        }
+        else if value < upper.max {
+            map(value, upper.min, upper.max, 0.5, 1.0)
+        }
+        else {
+            1.0
+        }
+    }
+}
+
+/// Dead-zone and hysteresis settings for one axis.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AxisConfig {
+    pub dead_zone: AxisDeadZone,
+    /// How far the input must move from the current output before the output
+    /// follows it.
+    pub hysteresis: f64,
+}
+
+/// Dead-zone and hysteresis settings for all three axes of the tracking space.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TrackingSpaceConfig {
+    pub x: AxisConfig,
+    pub y: AxisConfig,
+    pub z: AxisConfig,
+}
+
+impl TrackingSpaceConfig {
+    /// Loads a configuration from a JSON file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid
+    /// configuration.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            format!("failed to read tracking config \"{path}\": {e}")
+        })?;
+
+        Self::from_json_str(&contents)
+    }
+
+    /// Parses a configuration from a JSON string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` is not a valid configuration.
+    pub fn from_json_str(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        Self::from_json(&value)
+    }
+
+    /// Parses a configuration from a JSON value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` is not a valid configuration.
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        Ok(Self {
+            x: parse_axis(value, "x")?,
+            y: parse_axis(value, "y")?,
+            z: parse_axis(value, "z")?,
+        })
+    }
+
+    /// The configuration as JSON, in the same form which is parsed by
+    /// [`from_json()`](Self::from_json).
+    pub fn to_json(self) -> Value {
+        let axis = |cfg: &AxisConfig| {
+            json!({
+                "edge": cfg.dead_zone.edge,
+                "center": cfg.dead_zone.center,
+                "hysteresis": cfg.hysteresis,
+            })
+        };
+
+        json!({
+            "x": axis(&self.x),
+            "y": axis(&self.y),
+            "z": axis(&self.z),
+        })
+    }
+}
+
+fn parse_axis(value: &Value, axis: &str) -> Result<AxisConfig, String> {
+    let Some(obj) = value.get(axis)
+    else {
+        return Ok(AxisConfig::default());
+    };
+
+    let field = |name: &str| -> Result<f64, String> {
+        match obj.get(name) {
+            None => Ok(0.0),
+            Some(v) => v
+                .as_f64()
+                .filter(|v| (0.0..=0.5).contains(v))
+                .ok_or_else(|| {
+                    format!("\"{axis}.{name}\" must be a number from 0 to 0.5")
+                }),
+        }
+    };
+
+    Ok(AxisConfig {
+        dead_zone: AxisDeadZone {
+            edge: field("edge")?,
+            center: field("center")?,
+        },
+        hysteresis: field("hysteresis")?,
+    })
+}
+
+// *** *** *** //
+
+/// Hysteresis for a single source: the output only moves once the input has
+/// moved further than the threshold from it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Hysteresis {
+    output: Option<f64>,
+}
+
+impl Hysteresis {
+    pub fn process(&mut self, input: f64, threshold: f64) -> f64 {
+        let output = match self.output {
+            Some(out) if (input - out).abs() <= threshold => out,
+            // the output trails the input by the threshold, so that slow
+            // movements aren't quantized into steps
+            Some(out) => input - threshold.copysign(input - out),
+            None => input,
+        };
+
+        self.output = Some(output);
+        output
+    }
+
+    pub fn reset(&mut self) {
+        self.output = None;
+    }
+}
+
+/// Applies a [`TrackingSpaceConfig`] to hand positions, with separate
+/// hysteresis state for each axis of each hand.
+#[derive(Clone, Debug, Default)]
+pub struct TrackingSpaceFilter {
+    config: TrackingSpaceConfig,
+    hysteresis: [[Hysteresis; 3]; 2],
+}
+
+impl TrackingSpaceFilter {
+    pub fn new(config: TrackingSpaceConfig) -> Self {
+        Self { config, hysteresis: Default::default() }
+    }
+
+    /// Filters the position of a hand. `hand_idx` is `0` for the first hand
+    /// and `1` for the second. If the hand is not present, its hysteresis is
+    /// reset.
+    pub fn process(
+        &mut self,
+        hand_idx: usize,
+        pos: Option<DVec3>,
+    ) -> Option<DVec3> {
+        let state = &mut self.hysteresis[hand_idx];
+
+        let Some(pos) = pos
+        else {
+            state.iter_mut().for_each(Hysteresis::reset);
+            return None;
+        };
+
+        let axes = [self.config.x, self.config.y, self.config.z];
+        let mut out = pos.to_array();
+
+        for ((v, axis), hyst) in out.iter_mut().zip(axes).zip(state) {
+            *v = axis.dead_zone.apply(hyst.process(*v, axis.hysteresis));
+        }
+
+        Some(DVec3::new(out[0], out[1], out[2]))
+    }
+}
+
+// *** *** *** //
+
+/// Checks the tracking config at `path` for zones outside of the tracking
+/// space, zones which leave no live region, and ignored entries.
+pub fn diagnose_tracking_config(path: &str) -> Diagnostics {
+    let mut diags = Diagnostics::new("tracking config");
+
+    let value = match std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read \"{path}\": {e}"))
+        .and_then(|s| {
+            serde_json::from_str::<Value>(&s).map_err(|e| e.to_string())
+        }) {
+        Ok(value) => value,
+        Err(e) => {
+            diags.error(e).with_hint("no dead-zones are applied");
+            return diags;
+        }
+    };
+
+    if let Err(e) = TrackingSpaceConfig::from_json(&value) {
+        diags.error(e).with_hint("no dead-zones are applied");
+    }
+
+    diags.check_keys(&value, "the tracking config", &["x", "y", "z"]);
+
+    for axis in ["x", "y", "z"] {
+        let Some(obj) = value.get(axis)
+        else {
+            continue;
+        };
+
+        diags.check_keys(
+            obj,
+            &format!("axis \"{axis}\""),
+            &["edge", "center", "hysteresis"],
+        );
+
+        let field =
+            |name: &str| obj.get(name).and_then(Value::as_f64).unwrap_or(0.0);
+        let (edge, center) = (field("edge"), field("center"));
+
+        if (0.0..=0.5).contains(&edge)
+            && (0.0..=0.5).contains(&center)
+            && edge + center >= 0.5
+        {
+            diags
+                .warning(format!(
+                    "the dead-zones on \"{axis}\" cover the whole axis, so it never moves"
+                ))
+                .with_hint("make \"edge\" + \"center\" less than 0.5");
+        }
+    }
+
+    diags
+}
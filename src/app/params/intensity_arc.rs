@@ -0,0 +1,195 @@
+//! A long-horizon plan of intensity over a session, which biases automatic
+//! mode changes, the tempo and the "Session intensity" CC.
+//!
+//! The arc opens calm, rises to a peak and then winds down, where it stays
+//! once the session is over.
+
+use super::*;
+use mode::Mode;
+use rand::seq::IndexedRandom;
+use std::f64::consts::PI;
+
+/// The intensity at the start and end of the arc.
+pub const ARC_FLOOR: f64 = 0.1;
+/// The default point of the session (from `0` to `1`) where the arc peaks.
+pub const DEFAULT_ARC_PEAK: f64 = 0.6;
+/// The default tempo bias either side of the base tempo, as a proportion.
+pub const DEFAULT_ARC_TEMPO_RANGE: f64 = 0.1;
+/// The largest tempo bias either side of the base tempo, as a proportion.
+pub const MAX_ARC_TEMPO_RANGE: f64 = 0.5;
+
+/// How far a mode's level may be from the intensity and still be likely to
+/// be chosen.
+const MODE_BIAS_WIDTH: f64 = 0.35;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntensityArc {
+    duration_secs: f64,
+    /// The point of the session where the arc peaks, from `0` to `1`.
+    peak: f64,
+    /// The tempo bias either side of the base tempo, as a proportion.
+    tempo_range: f64,
+    /// The modes in order of intensity, from calmest to most intense.
+    modes: Vec<Mode>,
+}
+
+impl IntensityArc {
+    pub fn new(duration_secs: f64, peak: f64, tempo_range: f64) -> Self {
+        Self {
+            duration_secs: duration_secs.max(1.0),
+            peak: peak.clamp(0.0, 1.0),
+            tempo_range: tempo_range.clamp(0.0, MAX_ARC_TEMPO_RANGE),
+            modes: Mode::ALL.to_vec(),
+        }
+    }
+
+    /// Sets the order of the modes from calmest to most intense. Modes
+    /// which aren't included aren't biased towards.
+    pub fn with_modes(mut self, modes: Vec<Mode>) -> Self {
+        self.modes = modes;
+        self
+    }
+
+    /// Sets the order of the modes from a comma-separated list of their
+    /// names, e.g. `"A,C,B"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any name isn't a mode.
+    pub fn with_mode_names(self, names: &str) -> Result<Self, String> {
+        let modes = names
+            .split(',')
+            .map(|name| {
+                Mode::from_name(name.trim())
+                    .ok_or_else(|| format!("unknown mode \"{name}\""))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(self.with_modes(modes))
+    }
+
+    pub const fn duration_secs(&self) -> f64 {
+        self.duration_secs
+    }
+
+    /// Returns the intensity (from `ARC_FLOOR` to `1`) at `elapsed_secs`
+    /// into the session.
+    pub fn intensity_at(&self, elapsed_secs: f64) -> f64 {
+        let t = (elapsed_secs / self.duration_secs).clamp(0.0, 1.0);
+
+        // raised cosines either side of the peak
+        let rise = if t < self.peak {
+            0.5 - 0.5 * (PI * t / self.peak).cos()
+        }
+        else if self.peak < 1.0 {
+            0.5 + 0.5 * (PI * (t - self.peak) / (1.0 - self.peak)).cos()
+        }
+        else {
+            1.0
+        };
+
+        (1.0 - ARC_FLOOR).mul_add(rise, ARC_FLOOR)
+    }
+
+    /// Returns the proportion which the base tempo is scaled by at
+    /// `intensity`, which is `1` halfway up the arc.
+    pub fn tempo_scale(&self, intensity: f64) -> f64 {
+        let centered = (intensity - ARC_FLOOR) / (1.0 - ARC_FLOOR) * 2.0 - 1.0;
+
+        self.tempo_range.mul_add(centered, 1.0)
+    }
+
+    /// Returns how many bars to wait before the next mode change at
+    /// `intensity`, so that calm sections last longer.
+    pub fn mode_change_bars(&self, intensity: f64, min: u32, max: u32) -> u32 {
+        let bars = lerp(max as f64, min as f64, intensity);
+
+        (bars.round() as u32).clamp(min, max)
+    }
+
+    /// How likely `mode` is to be chosen at `intensity`.
+    pub fn mode_weight(&self, mode: Mode, intensity: f64) -> f64 {
+        let Some(idx) = self.modes.iter().position(|m| *m == mode)
+        else {
+            return 0.0;
+        };
+
+        let level = if self.modes.len() > 1 {
+            idx as f64 / (self.modes.len() - 1) as f64
+        }
+        else {
+            0.5
+        };
+
+        (-((level - intensity) / MODE_BIAS_WIDTH).powi(2)).exp()
+    }
+
+    /// Chooses one of `candidates`, biased towards the modes closest to
+    /// `intensity`. Returns `None` if no candidate is in the arc's modes.
+    pub fn choose_mode(
+        &self,
+        candidates: &[Mode],
+        intensity: f64,
+    ) -> Option<Mode> {
+        candidates
+            .choose_weighted(&mut rand::rng(), |m| {
+                self.mode_weight(*m, intensity)
+            })
+            .ok()
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-9;
+
+    #[test]
+    fn arc_opens_calm_peaks_and_winds_down() {
+        let arc = IntensityArc::new(100.0, 0.6, DEFAULT_ARC_TEMPO_RANGE);
+
+        assert!((arc.intensity_at(0.0) - ARC_FLOOR).abs() < EPSILON);
+        assert!((arc.intensity_at(60.0) - 1.0).abs() < EPSILON);
+        assert!((arc.intensity_at(100.0) - ARC_FLOOR).abs() < EPSILON);
+
+        // the wind-down is held after the session
+        assert!((arc.intensity_at(500.0) - ARC_FLOOR).abs() < EPSILON);
+
+        let rising: Vec<f64> =
+            (0..=6).map(|i| arc.intensity_at(i as f64 * 10.0)).collect();
+        assert!(rising.windows(2).all(|w| w[0] <= w[1]));
+
+        let falling: Vec<f64> =
+            (6..=10).map(|i| arc.intensity_at(i as f64 * 10.0)).collect();
+        assert!(falling.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn tempo_is_biased_around_base() {
+        let arc = IntensityArc::new(100.0, 0.5, 0.1);
+
+        assert!((arc.tempo_scale(ARC_FLOOR) - 0.9).abs() < EPSILON);
+        assert!((arc.tempo_scale(1.0) - 1.1).abs() < EPSILON);
+    }
+
+    #[test]
+    fn intense_modes_are_favored_at_peak() {
+        let arc = IntensityArc::new(100.0, 0.5, 0.0);
+
+        assert!(arc.mode_weight(Mode::C, 1.0) > arc.mode_weight(Mode::A, 1.0));
+        assert!(arc.mode_weight(Mode::A, 0.1) > arc.mode_weight(Mode::C, 0.1));
+
+        let calm_only = arc.with_modes(vec![Mode::A]);
+        assert_eq!(calm_only.choose_mode(&[Mode::B, Mode::C], 0.5), None);
+    }
+
+    #[test]
+    fn calm_sections_last_longer() {
+        let arc = IntensityArc::new(100.0, 0.5, 0.0);
+
+        assert_eq!(arc.mode_change_bars(0.0, 10, 22), 22);
+        assert_eq!(arc.mode_change_bars(1.0, 10, 22), 10);
+    }
+}
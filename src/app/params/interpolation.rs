@@ -0,0 +1,85 @@
+//! Estimating hand positions between received frames.
+//!
+//! Hand frames arrive at the tracker's (or window's) frame rate, which is
+//! usually well below `PARAM_UPDATE_RATE`. Holding each frame until the next
+//! one arrives makes high-rate CC output move in steps, so between frames
+//! each hand's position is extrapolated along its recent velocity.
+
+use super::*;
+use std::time::Instant;
+
+/// The longest time (in seconds) a position is extrapolated past its frame.
+/// After this, the position is held until the next frame arrives, so that a
+/// stalled tracker doesn't send hands drifting off.
+pub const MAX_HAND_EXTRAPOLATION_TIME: f64 = 0.05;
+
+/// How quickly the estimated velocity follows the velocity between frames,
+/// from `0.0` (never) to `1.0` (immediately).
+const VELOCITY_SMOOTHING: f64 = 0.5;
+
+/// Frames which arrive closer together than this (in seconds) don't update
+/// the velocity estimate, as their timing is dominated by jitter.
+const MIN_FRAME_INTERVAL: f64 = 0.002;
+
+/// Extrapolates one hand's position between received frames.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct HandExtrapolator {
+    position: Option<DVec3>,
+    velocity: DVec3,
+    received_at: Instant,
+}
+
+impl HandExtrapolator {
+    pub fn new() -> Self {
+        Self {
+            position: None,
+            velocity: DVec3::ZERO,
+            received_at: Instant::now(),
+        }
+    }
+
+    /// Records a newly received position.
+    pub fn receive(&mut self, pos: Option<DVec3>) {
+        let now = Instant::now();
+
+        match (self.position, pos) {
+            (Some(prev), Some(curr)) => {
+                let interval = (now - self.received_at).as_secs_f64();
+
+                if interval < MIN_FRAME_INTERVAL {
+                    // keep the previous frame's timestamp, so the velocity is
+                    // measured over the whole interval next time
+                    self.position = pos;
+                    return;
+                }
+
+                let frame_velocity = (curr - prev) / interval;
+                self.velocity =
+                    self.velocity.lerp(frame_velocity, VELOCITY_SMOOTHING);
+            }
+            // the hand was (re)detected or lost, so there is no motion to
+            // follow
+            _ => self.velocity = DVec3::ZERO,
+        }
+
+        self.position = pos;
+        self.received_at = now;
+    }
+
+    /// The estimated current position.
+    pub fn estimate(&self) -> Option<DVec3> {
+        let elapsed = self
+            .received_at
+            .elapsed()
+            .as_secs_f64()
+            .min(MAX_HAND_EXTRAPOLATION_TIME);
+
+        self.position.map(|pos| pos + self.velocity * elapsed)
+    }
+}
+
+impl Default for HandExtrapolator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,127 @@
+//! Latching (freezing) of CC values via a held gesture.
+
+use super::*;
+use midi_types::MIDICCIndex;
+use std::{collections::HashMap, time::Instant};
+
+/// How long the latch gesture must be held before it takes effect.
+const LATCH_GESTURE_HOLD_TIME: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum LatchEvent {
+    /// The latch gesture has just started, so a snapshot should be provided
+    /// via [`CCLatch::set_snapshot()`].
+    GestureStarted,
+    /// The snapshot has been latched.
+    Latched,
+    /// The latched values have been released.
+    Unlatched,
+}
+
+/// An override layer which freezes the values of latchable CCs.
+///
+/// Holding the latch gesture latches the values which the CCs had when the
+/// gesture *started* (so that forming the gesture doesn't affect them), and
+/// holding it again releases them.
+#[derive(Clone, Debug)]
+pub(super) struct CCLatch {
+    latched: HashMap<MIDICCIndex, f32>,
+    snapshot: HashMap<MIDICCIndex, f32>,
+    is_latched: bool,
+    hold_time: f64,
+
+    gesture_prev: bool,
+    gesture_posted: bool,
+    gesture_time: Instant,
+}
+
+impl CCLatch {
+    pub fn new() -> Self {
+        Self {
+            latched: HashMap::new(),
+            snapshot: HashMap::new(),
+            is_latched: false,
+            hold_time: LATCH_GESTURE_HOLD_TIME,
+
+            gesture_prev: false,
+            gesture_posted: false,
+            gesture_time: Instant::now(),
+        }
+    }
+
+    /// Scales how long the latch gesture must be held for.
+    pub fn set_hold_time_scale(&mut self, scale: f64) {
+        self.hold_time = LATCH_GESTURE_HOLD_TIME * scale;
+    }
+
+    pub const fn is_latched(&self) -> bool {
+        self.is_latched
+    }
+
+    /// The latched value for the CC at `idx`, if it is latched.
+    pub fn value_for(&self, idx: &MIDICCIndex) -> Option<f32> {
+        if self.is_latched {
+            self.latched.get(idx).copied()
+        }
+        else {
+            None
+        }
+    }
+
+    /// The indices of all currently-latched CCs.
+    pub fn latched_indices(&self) -> Vec<MIDICCIndex> {
+        if self.is_latched {
+            self.latched.keys().copied().collect()
+        }
+        else {
+            Vec::new()
+        }
+    }
+
+    /// Sets the values which will be latched once the gesture is held.
+    pub fn set_snapshot(&mut self, values: HashMap<MIDICCIndex, f32>) {
+        self.snapshot = values;
+    }
+
+    /// Updates the latch with whether the latch gesture is currently being
+    /// made.
+    pub fn update(&mut self, is_gesture: bool) -> Option<LatchEvent> {
+        let mut event = None;
+
+        if is_gesture {
+            if !self.gesture_prev {
+                self.gesture_time = Instant::now();
+                event = Some(LatchEvent::GestureStarted);
+            }
+            else if !self.gesture_posted
+                && self.gesture_time.elapsed().as_secs_f64()
+                    >= self.hold_time
+            {
+                self.gesture_posted = true;
+
+                if self.is_latched {
+                    self.release();
+                    event = Some(LatchEvent::Unlatched);
+                }
+                else {
+                    self.latched = std::mem::take(&mut self.snapshot);
+                    self.is_latched = true;
+                    event = Some(LatchEvent::Latched);
+                }
+            }
+        }
+        else {
+            self.gesture_posted = false;
+        }
+
+        self.gesture_prev = is_gesture;
+
+        event
+    }
+
+    /// Releases any latched values.
+    pub fn release(&mut self) {
+        self.latched.clear();
+        self.is_latched = false;
+    }
+}
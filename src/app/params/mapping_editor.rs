@@ -0,0 +1,307 @@
+//! Live editing of the CC mapping.
+//!
+//! The editor holds a [`MappingTemplate`] which describes the current
+//! mapping. One of its routes is selected at a time, and the selected field
+//! of that route (its source, curve or range) can be stepped up and down.
+
+use super::*;
+use attachment::CC_CURVE_RANGE;
+use std::fmt::{Display, Formatter};
+
+/// The number of routes which are shown at once.
+pub const MAPPING_EDITOR_VISIBLE_ROWS: usize = 16;
+
+/// The ratio which the curve is multiplied or divided by for each step.
+const CURVE_STEP_RATIO: f32 = 1.1;
+/// The amount which the range's bounds move by for each step.
+const RANGE_STEP: f32 = 0.01;
+
+/// A field of a route which can be edited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MappingField {
+    /// The attachment which is sent.
+    #[default]
+    Source,
+    /// The exponent which the value is shaped by.
+    Curve,
+    /// The value sent when the source is at its lowest.
+    Min,
+    /// The value sent when the source is at its highest.
+    Max,
+}
+
+impl MappingField {
+    const ALL: [Self; 4] = [Self::Source, Self::Curve, Self::Min, Self::Max];
+
+    /// The field `offset` places after this one, wrapping around.
+    fn offset(self, offset: isize) -> Self {
+        let len = Self::ALL.len() as isize;
+        let idx = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+
+        Self::ALL[(idx as isize + offset).rem_euclid(len) as usize]
+    }
+}
+
+impl Display for MappingField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Source => write!(f, "source"),
+            Self::Curve => write!(f, "curve"),
+            Self::Min => write!(f, "min"),
+            Self::Max => write!(f, "max"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MappingEditor {
+    template: MappingTemplate,
+    /// The names of every attachment which a route may send.
+    sources: Vec<String>,
+
+    selected: usize,
+    field: MappingField,
+    /// Whether the template has changed since it was last saved.
+    is_modified: bool,
+}
+
+impl MappingEditor {
+    pub fn new(template: MappingTemplate, mut sources: Vec<String>) -> Self {
+        sources.sort_unstable();
+        sources.dedup();
+
+        Self {
+            template,
+            sources,
+            selected: 0,
+            field: MappingField::default(),
+            is_modified: false,
+        }
+    }
+
+    pub const fn template(&self) -> &MappingTemplate {
+        &self.template
+    }
+
+    pub const fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub const fn field(&self) -> MappingField {
+        self.field
+    }
+
+    pub const fn is_modified(&self) -> bool {
+        self.is_modified
+    }
+
+    /// Records that the template has been saved.
+    pub fn mark_saved(&mut self) {
+        self.is_modified = false;
+    }
+
+    /// Selects the route `offset` places from the selected one, wrapping
+    /// around.
+    pub fn select_route(&mut self, offset: isize) {
+        let len = self.template.routes.len() as isize;
+
+        if len > 0 {
+            self.selected =
+                (self.selected as isize + offset).rem_euclid(len) as usize;
+        }
+    }
+
+    /// Selects the route at `idx`, if there is one.
+    pub fn select_route_at(&mut self, idx: usize) {
+        if idx < self.template.routes.len() {
+            self.selected = idx;
+        }
+    }
+
+    /// Selects the field `offset` places from the selected one, wrapping
+    /// around.
+    pub fn select_field(&mut self, offset: isize) {
+        self.field = self.field.offset(offset);
+    }
+
+    /// Steps the selected field of the selected route up or down by
+    /// `steps`. Returns whether the route changed.
+    pub fn adjust(&mut self, steps: i32) -> bool {
+        let Some(route) = self.template.routes.get_mut(self.selected)
+        else {
+            return false;
+        };
+
+        let before = route.clone();
+        let response = &mut route.response;
+
+        match self.field {
+            MappingField::Source => {
+                let len = self.sources.len() as i32;
+
+                if len > 0 {
+                    let idx = self
+                        .sources
+                        .iter()
+                        .position(|s| s.eq_ignore_ascii_case(&route.source))
+                        .map_or(0, |idx| idx as i32 + steps);
+
+                    route.source.clone_from(
+                        &self.sources[idx.rem_euclid(len) as usize],
+                    );
+                }
+            }
+            MappingField::Curve => {
+                let (min, max) = CC_CURVE_RANGE;
+                response.curve =
+                    (response.curve * CURVE_STEP_RATIO.powi(steps))
+                        .clamp(min, max);
+            }
+            MappingField::Min => {
+                response.min =
+                    (response.min + RANGE_STEP * steps as f32).clamp(0.0, 1.0);
+            }
+            MappingField::Max => {
+                response.max =
+                    (response.max + RANGE_STEP * steps as f32).clamp(0.0, 1.0);
+            }
+        }
+
+        let changed = *route != before;
+        self.is_modified |= changed;
+
+        changed
+    }
+
+    /// The range of routes which are shown, which keeps the selected route
+    /// near the middle.
+    pub fn visible_rows(&self) -> std::ops::Range<usize> {
+        let len = self.template.routes.len();
+        let start = self
+            .selected
+            .saturating_sub(MAPPING_EDITOR_VISIBLE_ROWS / 2)
+            .min(len.saturating_sub(MAPPING_EDITOR_VISIBLE_ROWS));
+
+        start..(start + MAPPING_EDITOR_VISIBLE_ROWS).min(len)
+    }
+
+    /// A line of text describing each visible route, where the selected
+    /// field of the selected route is bracketed.
+    pub fn visible_lines(&self) -> Vec<String> {
+        self.visible_rows()
+            .map(|idx| {
+                let route = &self.template.routes[idx];
+                let is_selected = idx == self.selected;
+
+                let field = |field: MappingField, text: String| {
+                    if is_selected && field == self.field {
+                        format!("[{text}]")
+                    }
+                    else {
+                        text
+                    }
+                };
+
+                format!(
+                    "{} ch {:>2} cc {:>3}  {}  curve {}  range {} to {}",
+                    if is_selected { ">" } else { " " },
+                    route.channel + 1,
+                    route.cc,
+                    field(MappingField::Source, route.source.clone()),
+                    field(
+                        MappingField::Curve,
+                        format!("{:.2}", route.response.curve)
+                    ),
+                    field(
+                        MappingField::Min,
+                        format!("{:.2}", route.response.min)
+                    ),
+                    field(
+                        MappingField::Max,
+                        format!("{:.2}", route.response.max)
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor() -> MappingEditor {
+        let template = MappingTemplate::built_in("generic-synth").unwrap();
+        let sources =
+            template.routes.iter().map(|r| r.source.clone()).collect();
+
+        MappingEditor::new(template, sources)
+    }
+
+    #[test]
+    fn selection_wraps() {
+        let mut editor = editor();
+
+        editor.select_route(-1);
+        assert_eq!(editor.selected(), 2);
+        editor.select_route(2);
+        assert_eq!(editor.selected(), 1);
+
+        editor.select_field(-1);
+        assert_eq!(editor.field(), MappingField::Max);
+        editor.select_field(1);
+        assert_eq!(editor.field(), MappingField::Source);
+    }
+
+    #[test]
+    fn sources_cycle_in_order() {
+        let mut editor = editor();
+
+        // the sources are sorted, so "openness" is followed by "proximity"
+        assert!(editor.adjust(1));
+        assert_eq!(editor.template().routes[0].source, "First hand proximity");
+        assert!(editor.is_modified());
+
+        assert!(editor.adjust(-1));
+        assert_eq!(editor.template().routes[0].source, "First hand openness");
+    }
+
+    #[test]
+    fn response_is_clamped() {
+        let mut editor = editor();
+
+        editor.select_field(2);
+        assert!(editor.adjust(10));
+        assert!((editor.template().routes[0].response.min - 0.1).abs() < 1e-6);
+        assert!(editor.adjust(-1000));
+        assert!(!editor.adjust(-1));
+        assert_eq!(editor.template().routes[0].response.min, 0.0);
+
+        editor.select_field(-1);
+        assert!(editor.adjust(1000));
+
+        let curve = editor.template().routes[0].response.curve;
+        assert_eq!(curve, CC_CURVE_RANGE.1);
+    }
+
+    #[test]
+    fn selected_row_stays_visible() {
+        let mut template = MappingTemplate::built_in("generic-synth").unwrap();
+        let route = template.routes[0].clone();
+
+        template.routes =
+            (0..40).map(|cc| MappingRoute { cc, ..route.clone() }).collect();
+
+        let mut editor = MappingEditor::new(template, Vec::new());
+
+        assert_eq!(editor.visible_rows(), 0..MAPPING_EDITOR_VISIBLE_ROWS);
+
+        editor.select_route_at(20);
+        assert!(editor.visible_rows().contains(&20));
+
+        editor.select_route(-1);
+        editor.select_route(20);
+        assert_eq!(editor.visible_rows(), 24..40);
+        assert_eq!(editor.visible_lines().len(), MAPPING_EDITOR_VISIBLE_ROWS);
+    }
+}
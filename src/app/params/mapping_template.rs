@@ -0,0 +1,476 @@
+//! Templates which rearrange the CC attachments for a downstream rig.
+//!
+//! A template is either one of the built-in templates (see
+//! [`BUILT_IN_TEMPLATE_NAMES`]), or is loaded from a JSON file of the form:
+//!
+//! ```json
+//! {
+//!     "name": "my synth",
+//!     "exclusive": true,
+//!     "routes": [
+//!         { "source": "First hand openness", "channel": 1, "cc": 1 },
+//!         { "source": "First hand y-pos", "channel": 1, "cc": 74, "bits": 7 },
+//!         {
+//!             "source": "First hand proximity", "channel": 1, "cc": 71,
+//!             "curve": 2.0, "min": 0.2, "max": 0.8
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! Each route sends an attachment (by name) to a CC, where channels are
+//! numbered from 1. A route may also shape the attachment's value with a
+//! `curve` exponent, and rescale it from `min` to `max`. If `exclusive` is
+//! `true` (the default), attachments without a route aren't sent at all;
+//! otherwise, they keep their CCs.
+
+use super::*;
+use attachment::{CCResponse, MIDICCAttachment, MIDICCSize, CC_CURVE_RANGE};
+use midi_types::{MIDICCIndex, NUM_MIDI_CCS, NUM_MIDI_CHANNELS};
+use serde_json::{json, Map, Value};
+
+/// The names of the built-in templates.
+pub const BUILT_IN_TEMPLATE_NAMES: [&str; 3] =
+    ["generic-synth", "ableton-macros", "lighting-1-8"];
+
+/// The sources sent by the built-in templates with eight controls, in order.
+const EIGHT_CONTROL_SOURCES: [&str; 8] = [
+    "First hand x-pos",
+    "First hand y-pos",
+    "First hand openness",
+    "First hand proximity",
+    "First hand index finger pinch",
+    "First hand middle finger pinch",
+    "First hand ring finger pinch",
+    "First hand velocity",
+];
+
+/// Where a template sends one attachment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MappingRoute {
+    /// The name of the attachment.
+    pub source: String,
+    /// The destination channel, from `0`.
+    pub channel: u8,
+    pub cc: u8,
+    /// The resolution to send at, if it should be changed.
+    pub size: Option<MIDICCSize>,
+    pub response: CCResponse,
+}
+
+impl MappingRoute {
+    /// A 7-bit route on the first channel.
+    fn cc7(source: &str, cc: u8) -> Self {
+        Self::cc7_on(source, 0, cc)
+    }
+
+    fn cc7_on(source: &str, channel: u8, cc: u8) -> Self {
+        Self {
+            source: source.to_string(),
+            channel,
+            cc,
+            size: Some(MIDICCSize::CC7Bit),
+            response: CCResponse::default(),
+        }
+    }
+
+    fn from_json(value: &Value) -> Result<Self, String> {
+        let Some(source) = value.get("source").and_then(Value::as_str)
+        else {
+            return Err(String::from("route is missing a \"source\""));
+        };
+
+        let number = |key: &str, min: u64, max: u64| {
+            value
+                .get(key)
+                .and_then(Value::as_u64)
+                .filter(|n| (min..=max).contains(n))
+                .ok_or_else(|| {
+                    format!(
+                        "route \"{source}\" must have a \"{key}\" from {min} to {max}"
+                    )
+                })
+        };
+
+        let float = |key: &str, (min, max): (f32, f32)| {
+            value.get(key).map_or(Ok(None), |v| {
+                v.as_f64()
+                    .map(|v| v as f32)
+                    .filter(|v| (min..=max).contains(v))
+                    .map(Some)
+                    .ok_or_else(|| {
+                        format!(
+                            "route \"{source}\" must have a \"{key}\" from {min} to {max}"
+                        )
+                    })
+            })
+        };
+
+        let channel = number("channel", 1, NUM_MIDI_CHANNELS as u64)? - 1;
+        let cc = number("cc", 0, NUM_MIDI_CCS as u64 - 1)?;
+
+        let size = match value.get("bits").map(Value::as_u64) {
+            Some(Some(7)) => Some(MIDICCSize::CC7Bit),
+            Some(Some(14)) => Some(MIDICCSize::CC14Bit),
+            Some(_) => {
+                return Err(format!(
+                    "route \"{source}\" must have 7 or 14 \"bits\""
+                ));
+            }
+            None => None,
+        };
+
+        let response = CCResponse {
+            curve: float("curve", CC_CURVE_RANGE)?.unwrap_or(1.0),
+            min: float("min", (0.0, 1.0))?.unwrap_or(0.0),
+            max: float("max", (0.0, 1.0))?.unwrap_or(1.0),
+        };
+
+        Ok(Self {
+            source: source.to_string(),
+            channel: channel as u8,
+            cc: cc as u8,
+            size,
+            response,
+        })
+    }
+
+    /// The route as JSON, leaving out anything which is the default.
+    fn to_json(&self) -> Value {
+        let mut route = Map::new();
+
+        route.insert("source".into(), json!(self.source));
+        route.insert("channel".into(), json!(self.channel + 1));
+        route.insert("cc".into(), json!(self.cc));
+
+        if let Some(size) = self.size {
+            let bits = if size == MIDICCSize::CC14Bit { 14 } else { 7 };
+            route.insert("bits".into(), json!(bits));
+        }
+
+        let default = CCResponse::default();
+        let fields = [
+            ("curve", self.response.curve, default.curve),
+            ("min", self.response.min, default.min),
+            ("max", self.response.max, default.max),
+        ];
+
+        for (key, value, default) in fields {
+            if value != default {
+                route.insert(key.into(), json!(value));
+            }
+        }
+
+        Value::Object(route)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MappingTemplate {
+    pub name: String,
+    /// Whether attachments without a route are left out.
+    pub exclusive: bool,
+    pub routes: Vec<MappingRoute>,
+}
+
+impl MappingTemplate {
+    /// Loads the built-in template called `name_or_path`, or otherwise the
+    /// template file at that path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no such built-in template, and the file
+    /// cannot be read or is not a valid template.
+    pub fn load(name_or_path: &str) -> Result<Self, String> {
+        Self::built_in(name_or_path).map_or_else(
+            || Self::from_file(name_or_path),
+            Ok,
+        )
+    }
+
+    /// The built-in template called `name`, if there is one.
+    pub fn built_in(name: &str) -> Option<Self> {
+        let routes = match name.to_lowercase().as_str() {
+            // mod wheel, filter cutoff and resonance
+            "generic-synth" => vec![
+                MappingRoute::cc7("First hand openness", 1),
+                MappingRoute::cc7("First hand y-pos", 74),
+                MappingRoute::cc7("First hand proximity", 71),
+            ],
+            // the general-purpose and undefined CCs after the mod wheel,
+            // which are free to be mapped to macros
+            "ableton-macros" => (16..)
+                .zip(EIGHT_CONTROL_SOURCES)
+                .map(|(cc, source)| MappingRoute::cc7(source, cc))
+                .collect(),
+            // faders 1 to 8 on their own channel, avoiding reserved CCs
+            "lighting-1-8" => (20..)
+                .zip(EIGHT_CONTROL_SOURCES)
+                .map(|(cc, source)| MappingRoute::cc7_on(source, 15, cc))
+                .collect(),
+            _ => return None,
+        };
+
+        Some(Self { name: name.to_lowercase(), exclusive: true, routes })
+    }
+
+    /// A template which routes each of `attachments` to the CC it already
+    /// uses, so that the current mapping can be edited and saved.
+    pub(super) fn from_attachments(
+        name: &str,
+        attachments: &HashMap<MIDICCIndex, MIDICCAttachment>,
+    ) -> Self {
+        let mut indices: Vec<&MIDICCIndex> = attachments.keys().collect();
+        indices.sort_unstable();
+
+        let routes = indices
+            .into_iter()
+            .map(|idx| {
+                let att = &attachments[idx];
+
+                MappingRoute {
+                    source: att.name().to_string(),
+                    channel: idx.channel as u8,
+                    cc: idx.cc as u8,
+                    size: Some(att.size()),
+                    response: att.response(),
+                }
+            })
+            .collect();
+
+        Self { name: name.to_string(), exclusive: true, routes }
+    }
+
+    /// Loads a template from a JSON file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid
+    /// template.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            format!("failed to read mapping template \"{path}\": {e}")
+        })?;
+
+        Self::from_json_str(&contents)
+    }
+
+    /// Parses a template from a JSON string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` is not a valid template.
+    pub fn from_json_str(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        let Some(routes) = value.get("routes").and_then(Value::as_array)
+        else {
+            return Err(String::from(
+                "mapping template is missing a \"routes\" array",
+            ));
+        };
+
+        let routes = routes
+            .iter()
+            .map(MappingRoute::from_json)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if routes.is_empty() {
+            return Err(String::from("mapping template has no routes"));
+        }
+
+        Ok(Self {
+            name: value
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or("custom")
+                .to_string(),
+            exclusive: value
+                .get("exclusive")
+                .and_then(Value::as_bool)
+                .unwrap_or(true),
+            routes,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let routes: Vec<Value> =
+            self.routes.iter().map(MappingRoute::to_json).collect();
+
+        json!({
+            "name": self.name,
+            "exclusive": self.exclusive,
+            "routes": routes,
+        })
+    }
+
+    /// Rearranges `attachments` by the template's routes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a route's source isn't an attachment, or if two
+    /// routes send to the same CC.
+    pub(super) fn apply(
+        &self,
+        attachments: HashMap<MIDICCIndex, MIDICCAttachment>,
+    ) -> Result<HashMap<MIDICCIndex, MIDICCAttachment>, String> {
+        let mut mapped = HashMap::with_capacity(attachments.len());
+
+        for route in &self.routes {
+            let Some(source) = attachments
+                .values()
+                .find(|att| att.name().eq_ignore_ascii_case(&route.source))
+            else {
+                return Err(format!("unknown source \"{}\"", route.source));
+            };
+
+            let mut attachment = source.clone();
+
+            if let Some(size) = route.size {
+                attachment.with_size(size);
+            }
+
+            attachment.with_response(route.response);
+
+            let idx = MIDICCIndex::new(route.channel, route.cc);
+
+            if mapped.insert(idx, attachment).is_some() {
+                return Err(format!(
+                    "more than one route sends to CC {} on channel {}",
+                    route.cc,
+                    route.channel + 1
+                ));
+            }
+        }
+
+        if !self.exclusive {
+            let routed: Vec<&str> =
+                self.routes.iter().map(|r| r.source.as_str()).collect();
+
+            for (idx, attachment) in attachments {
+                let is_routed = routed
+                    .iter()
+                    .any(|name| attachment.name().eq_ignore_ascii_case(name));
+
+                if !is_routed {
+                    mapped.entry(idx).or_insert(attachment);
+                }
+            }
+        }
+
+        Ok(mapped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use midi_cc_attachments::build_midi_cc_attachments;
+    use serde_json::json;
+
+    fn single_route(source: &str, channel: u64, cc: u64) -> String {
+        let route = json!({ "source": source, "channel": channel, "cc": cc });
+
+        json!({ "routes": [route] }).to_string()
+    }
+
+    #[test]
+    fn built_in_templates_apply_cleanly() {
+        for name in BUILT_IN_TEMPLATE_NAMES {
+            let template = MappingTemplate::built_in(name).unwrap();
+            let mapped =
+                template.apply(build_midi_cc_attachments()).unwrap();
+
+            assert_eq!(mapped.len(), template.routes.len(), "{name}");
+            assert!(mapped.values().all(|att| !att.is_14_bit()), "{name}");
+        }
+    }
+
+    #[test]
+    fn generic_synth_sends_to_standard_ccs() {
+        let template = MappingTemplate::built_in("generic-synth").unwrap();
+        let mapped = template.apply(build_midi_cc_attachments()).unwrap();
+
+        let name_at = |cc: u8| mapped[&MIDICCIndex::new(0, cc)].name();
+
+        assert_eq!(name_at(1), "First hand openness");
+        assert_eq!(name_at(74), "First hand y-pos");
+        assert_eq!(name_at(71), "First hand proximity");
+    }
+
+    #[test]
+    fn non_exclusive_templates_keep_other_attachments() {
+        let template = MappingTemplate::from_json_str(
+            r#"{
+                "exclusive": false,
+                "routes": [
+                    { "source": "first hand velocity", "channel": 2, "cc": 9 }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let attachments = build_midi_cc_attachments();
+        let num_attachments = attachments.len();
+        let mapped = template.apply(attachments).unwrap();
+
+        assert_eq!(mapped.len(), num_attachments);
+        assert_eq!(
+            mapped[&MIDICCIndex::new(1, 9)].name(),
+            "First hand velocity"
+        );
+    }
+
+    #[test]
+    fn edited_mapping_round_trips() {
+        let attachments = build_midi_cc_attachments();
+        let mut template =
+            MappingTemplate::from_attachments("edited", &attachments);
+
+        assert_eq!(template.routes.len(), attachments.len());
+
+        template.routes[0].response =
+            CCResponse { curve: 2.0, min: 0.25, max: 0.0 };
+
+        let json = template.to_json().to_string();
+        assert_eq!(MappingTemplate::from_json_str(&json).unwrap(), template);
+
+        // an unedited template leaves the attachments where they were
+        let unedited = MappingTemplate::from_attachments("", &attachments)
+            .apply(build_midi_cc_attachments())
+            .unwrap();
+
+        assert!(unedited.iter().all(|(idx, att)| {
+            attachments[idx].name() == att.name()
+                && attachments[idx].size() == att.size()
+        }));
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        let unknown =
+            MappingTemplate::from_json_str(&single_route("Tail wag", 1, 1))
+                .unwrap();
+        assert!(unknown.apply(build_midi_cc_attachments()).is_err());
+
+        let clash = MappingTemplate::from_json_str(
+            r#"{
+                "routes": [
+                    { "source": "Mode sweep", "channel": 1, "cc": 1 },
+                    { "source": "Session intensity", "channel": 1, "cc": 1 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(clash.apply(build_midi_cc_attachments()).is_err());
+
+        for json in [
+            String::from(r#"{ "routes": [] }"#),
+            single_route("Mode sweep", 0, 1),
+            single_route("Mode sweep", 1, 128),
+        ] {
+            assert!(MappingTemplate::from_json_str(&json).is_err(), "{json}");
+        }
+    }
+}
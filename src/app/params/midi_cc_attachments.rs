@@ -1,7 +1,11 @@
 use std::collections::HashMap;
 
-use attachment::{MIDICCAttachment, MIDICCFn, MIDICCPredicate, MIDICCSize};
+use crate::app::diagnostics::Diagnostics;
+use attachment::{
+    CCPriority, MIDICCAttachment, MIDICCFn, MIDICCPredicate, MIDICCSize,
+};
 use hands::hand_types::{CCUpdateData, Finger};
+use mapping_template::{MappingTemplate, BUILT_IN_TEMPLATE_NAMES};
 use midi_types::MIDICCIndex;
 use state::ParameterState;
 
@@ -64,7 +68,8 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
     )
     .with_smoothing_time(MOVE_SMOOTHING_TIME)
     .with_size(MIDICCSize::CC14Bit)
-    .with_update_threshold(MOVE_UPDATE_THRESHOLD);
+    .with_update_threshold(MOVE_UPDATE_THRESHOLD)
+    .with_priority(CCPriority::High);
 
     add(
         &mut hm,
@@ -80,7 +85,8 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
     )
     .with_smoothing_time(MOVE_SMOOTHING_TIME)
     .with_size(MIDICCSize::CC14Bit)
-    .with_update_threshold(MOVE_UPDATE_THRESHOLD);
+    .with_update_threshold(MOVE_UPDATE_THRESHOLD)
+    .with_priority(CCPriority::High);
 
     add(
         &mut hm,
@@ -100,7 +106,9 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
         |state: &ParameterState| true,
     )
     .with_update_threshold(OPENNESS_UPDATE_THRESHOLD)
-    .with_smoothing_time(OPENNESS_SMOOTHING_TIME);
+    .with_smoothing_time(OPENNESS_SMOOTHING_TIME)
+    .with_latchable(true)
+    .with_soft_takeover(true);
 
     add(
         &mut hm,
@@ -116,7 +124,9 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
         },
         |state: &ParameterState| true,
     )
-    .with_smoothing_time(PROXIMITY_SMOOTHING_TIME);
+    .with_smoothing_time(PROXIMITY_SMOOTHING_TIME)
+    .with_latchable(true)
+    .with_soft_takeover(true);
 
     add(
         &mut hm,
@@ -132,7 +142,9 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
         |state: &ParameterState| true,
     )
     .with_update_threshold(PINCH_UPDATE_THRESHOLD)
-    .with_smoothing_time(PINCH_SMOOTHING_TIME);
+    .with_smoothing_time(PINCH_SMOOTHING_TIME)
+    .with_latchable(true)
+    .with_soft_takeover(true);
 
     add(
         &mut hm,
@@ -148,7 +160,9 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
         |state: &ParameterState| true,
     )
     .with_update_threshold(PINCH_UPDATE_THRESHOLD)
-    .with_smoothing_time(PINCH_SMOOTHING_TIME);
+    .with_smoothing_time(PINCH_SMOOTHING_TIME)
+    .with_latchable(true)
+    .with_soft_takeover(true);
 
     add(
         &mut hm,
@@ -163,7 +177,9 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
         },
         |state: &ParameterState| true,
     )
-    .with_smoothing_time(PINCH_SMOOTHING_TIME);
+    .with_smoothing_time(PINCH_SMOOTHING_TIME)
+    .with_latchable(true)
+    .with_soft_takeover(true);
 
     add(
         &mut hm,
@@ -178,7 +194,9 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
         },
         |state: &ParameterState| true,
     )
-    .with_smoothing_time(PINCH_SMOOTHING_TIME);
+    .with_smoothing_time(PINCH_SMOOTHING_TIME)
+    .with_latchable(true)
+    .with_soft_takeover(true);
 
     add(
         &mut hm,
@@ -193,7 +211,8 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
         |state: &ParameterState| true,
     )
     .with_update_threshold(VELOCITY_UPDATE_THRESHOLD)
-    .with_smoothing_time(VELOCITY_SMOOTHING_TIME);
+    .with_smoothing_time(VELOCITY_SMOOTHING_TIME)
+    .with_priority(CCPriority::Low);
 
     // *** *** *** *** *** //
 
@@ -211,6 +230,20 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
     .with_smoothing_time(0.02)
     .with_update_threshold(0.02);
 
+    add(
+        &mut hm,
+        MIDI_CHANNEL_3,
+        MIDI_CC_1,
+        "Session intensity",
+        |values: &CCUpdateData, cc: &mut f32| {
+            if let Some(intensity) = values.intensity {
+                *cc = intensity as f32;
+            }
+        },
+        |state: &ParameterState| true,
+    )
+    .with_smoothing_time(1.0);
+
     // // Second hand
     //
     // add(
@@ -327,3 +360,176 @@ pub fn build_midi_cc_attachments() -> HashMap<MIDICCIndex, MIDICCAttachment> {
 
     hm
 }
+
+/// Builds the CC attachments, rearranged by the mapping template called (or
+/// found at) `mapping`, if any.
+///
+/// # Errors
+///
+/// Returns an error if the template cannot be loaded or applied.
+pub fn build_mapped_cc_attachments(
+    mapping: Option<&str>,
+) -> Result<HashMap<MIDICCIndex, MIDICCAttachment>, String> {
+    let attachments = build_midi_cc_attachments();
+
+    match mapping {
+        Some(mapping) => MappingTemplate::load(mapping)?.apply(attachments),
+        None => Ok(attachments),
+    }
+}
+
+/// Controller numbers which MIDI reserves for a specific purpose, so which
+/// receivers may not treat as general-purpose CCs.
+const RESERVED_MIDI_CCS: [(u8, &str); 8] = [
+    (0, "bank select"),
+    (6, "data entry"),
+    (32, "bank select LSB"),
+    (38, "data entry LSB"),
+    (98, "NRPN LSB"),
+    (99, "NRPN MSB"),
+    (100, "RPN LSB"),
+    (101, "RPN MSB"),
+];
+
+/// Checks the CC attachments (as rearranged by the `mapping` template, if
+/// any) for reserved CC numbers, 14-bit CCs which overlap other attachments,
+/// and duplicate names.
+pub fn diagnose_cc_attachments(mapping: Option<&str>) -> Diagnostics {
+    let mut diags = Diagnostics::new("CC attachments");
+
+    let attachments = match build_mapped_cc_attachments(mapping) {
+        Ok(attachments) => attachments,
+        Err(e) => {
+            diags
+                .error(format!("failed to apply mapping template: {e}"))
+                .with_hint(format!(
+                    "the built-in templates are {}",
+                    BUILT_IN_TEMPLATE_NAMES.join(", ")
+                ));
+
+            return diags;
+        }
+    };
+
+    let mut indices: Vec<&MIDICCIndex> = attachments.keys().collect();
+    indices.sort_unstable();
+
+    let mut names: HashMap<&str, MIDICCIndex> = HashMap::new();
+
+    for idx in indices {
+        let att = &attachments[idx];
+        let (channel, cc) = (idx.channel + 1, idx.cc);
+
+        // 14-bit CCs deliberately pair up CCs 0-31 with 32-63
+        if let Some((_, purpose)) =
+            RESERVED_MIDI_CCS.iter().find(|(n, _)| *n as usize == cc)
+            && !(att.is_14_bit() && cc < 32)
+        {
+            diags
+                .warning(format!(
+                    "\"{}\" uses CC {cc} on channel {channel}, which is reserved for {purpose}",
+                    att.name()
+                ))
+                .with_hint("some devices will not treat it as a normal CC");
+        }
+
+        if att.is_14_bit() {
+            if cc >= 32 {
+                diags
+                    .error(format!(
+                        "\"{}\" is 14-bit, but uses CC {cc} on channel {channel}",
+                        att.name()
+                    ))
+                    .with_hint("14-bit CCs must use CC 0 to 31");
+            }
+            else if let Some(lsb) = attachments
+                .get(&MIDICCIndex { channel: idx.channel, cc: cc + 32 })
+            {
+                diags
+                    .error(format!(
+                        "\"{}\" and \"{}\" overlap on channel {channel}, as 14-bit CC {cc} also uses CC {}",
+                        att.name(),
+                        lsb.name(),
+                        cc + 32
+                    ))
+                    .with_hint("move one of them to another CC or channel");
+            }
+        }
+
+        if let Some(other) = names.insert(att.name(), *idx) {
+            diags.warning(format!(
+                "\"{}\" is used for CC {} on channel {} and CC {cc} on channel {channel}",
+                att.name(),
+                other.cc,
+                other.channel + 1,
+            ));
+        }
+    }
+
+    diags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::*;
+
+    #[test]
+    fn all_attachments_stay_in_range() {
+        for attachment in build_midi_cc_attachments().values() {
+            assert_grid_in_range(attachment, (0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn position_follows_hand() {
+        let attachments = build_midi_cc_attachments();
+
+        let x = attachment_named(&attachments, "First hand x-pos");
+        let values = sweep(x, 11, (0.0, 1.0), |hand, v| hand.com.x = v);
+        assert_monotonic(x.name(), &values, Direction::Increasing);
+
+        // y is flipped, so that raising the hand raises the value
+        let y = attachment_named(&attachments, "First hand y-pos");
+        let values = sweep(y, 11, (0.0, 1.0), |hand, v| hand.com.y = v);
+        assert_monotonic(y.name(), &values, Direction::Decreasing);
+    }
+
+    #[test]
+    fn openness_increases_with_spread() {
+        let attachments = build_midi_cc_attachments();
+        let openness = attachment_named(&attachments, "First hand openness");
+
+        let values =
+            sweep(openness, 11, (0.2, 3.0), |hand, v| hand.spread = v);
+
+        assert_monotonic(openness.name(), &values, Direction::Increasing);
+        assert!(values[0] < values[values.len() - 1]);
+    }
+
+    #[test]
+    fn index_pinch_increases_as_fingers_close() {
+        let attachments = build_midi_cc_attachments();
+        let pinch =
+            attachment_named(&attachments, "First hand index finger pinch");
+
+        let values = sweep(pinch, 11, (0.0, 1.0), |hand, v| hand.pinch = v);
+
+        assert_monotonic(pinch.name(), &values, Direction::Increasing);
+        assert!((values[values.len() - 1] - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn velocity_passes_through() {
+        let attachments = build_midi_cc_attachments();
+        let velocity = attachment_named(&attachments, "First hand velocity");
+        let hand = Some(SyntheticHand::default());
+
+        for v in [0.0, 0.25, 0.8] {
+            assert!((evaluate(velocity, hand, v) - v).abs() < f32::EPSILON);
+        }
+
+        // without a hand, the value is left alone
+        assert!(evaluate(velocity, None, 0.5).abs() < f32::EPSILON);
+    }
+}
@@ -14,6 +14,10 @@ fn f32_to_14bit(val: f32) -> u16 {
     scale_f32(val.clamp(0.0, 1.0), 0.0, 16383.0).round() as u16
 }
 
+fn f32_to_32bit(val: f32) -> u32 {
+    (val.clamp(0.0, 1.0) as f64 * u32::MAX as f64).round() as u32
+}
+
 fn f64_to_7bit(val: f64) -> u8 {
     scale(val.clamp(0.0, 1.0), 0.0, 127.0).round() as u8
 }
@@ -47,6 +51,16 @@ impl MIDICCParameter {
             )
         }
     }
+
+    /// Returns the CC as a 32-bit message, for destinations which receive
+    /// UMPs.
+    pub fn to_high_res_midi_message(self, channel: u8) -> MIDIMessage {
+        MIDIMessage::control_change_32_bit(
+            self.cc,
+            f32_to_32bit(self.value),
+            channel,
+        )
+    }
 }
 
 // *** *** *** //
@@ -200,6 +214,16 @@ impl MIDIParameterBank {
         &mut self.note_params[idx.channel][idx.note]
     }
 
+    /// The value of the CC when it was last sent.
+    pub const fn get_cached_cc(&self, idx: &MIDICCIndex) -> &MIDICCParameter {
+        &self.cc_params_cache[idx.channel][idx.cc]
+    }
+
+    /// Whether the CC will be sent regardless of its update threshold.
+    pub fn is_forced(&self, idx: &MIDICCIndex) -> bool {
+        self.force_update_ccs.contains(idx)
+    }
+
     pub fn force_update_cc_at(&mut self, idx: MIDICCIndex) {
         self.force_update_ccs.insert(idx);
     }
@@ -1,26 +1,68 @@
 //! GUI parameters.
 
 mod attachment;
+mod audio_attachments;
+mod axis_lock;
+mod bank_diff;
+mod calibration;
+mod deadzone;
+mod intensity_arc;
+mod interpolation;
+mod latch;
+mod mapping_editor;
+mod mapping_template;
 mod midi_cc_attachments;
 mod midi_types;
 mod mode;
+mod pose;
+mod profile;
+mod snapshot;
 mod state;
+mod takeover;
 pub mod types;
 mod updater;
 
+#[cfg(test)]
+pub(crate) mod test_utils;
+
 use std::{
     collections::HashMap,
     sync::{mpsc, Arc, Mutex},
 };
 
 use attachment::MIDICCAttachment;
-use eme_request::EMERequest;
+pub use audio_attachments::{AudioAttachmentDriver, AudioControls};
+pub use axis_lock::LockedAxis;
+pub use bank_diff::{BankDiffEntry, ParameterBankDiff};
+pub use calibration::{CalibrationWizard, ReachExtents};
+pub use deadzone::{
+    diagnose_tracking_config, AxisRange, TrackingSpaceConfig,
+};
+pub use intensity_arc::{
+    IntensityArc, DEFAULT_ARC_PEAK, DEFAULT_ARC_TEMPO_RANGE,
+    MAX_ARC_TEMPO_RANGE,
+};
+pub use mapping_editor::{
+    MappingEditor, MappingField, MAPPING_EDITOR_VISIBLE_ROWS,
+};
+pub use mapping_template::{
+    MappingRoute, MappingTemplate, BUILT_IN_TEMPLATE_NAMES,
+};
+pub use midi_cc_attachments::diagnose_cc_attachments;
+pub use pose::{DEFAULT_POSE_TOLERANCE, MAX_POSE_TOLERANCE};
+pub use profile::{diagnose_profiles, ProfileStore, UserProfile};
+pub use snapshot::ParameterSnapshot;
+use eme_request::{EMEJump, EMERequest};
+use broadcast::StateFrame;
+use eme_response::EMEEvent;
 use hands::hand_types::RawHandPairCOM;
 use message::MIDIMessage;
-use midi_cc_attachments::build_midi_cc_attachments;
+use midi_cc_attachments::{
+    build_mapped_cc_attachments, build_midi_cc_attachments,
+};
 use midi_types::MIDICCIndex;
 use timer::TimerThread;
-use mode::Mode;
+pub use mode::{Mode, ModeTransition};
 pub use types::*;
 use updater::ParameterUpdater;
 
@@ -43,23 +85,37 @@ pub struct ParameterSenders {
 pub struct ParameterReceivers {
     pub midi_receiver: CCReceiver<Vec<MIDIMessage>>,
     pub eme_receiver: CCReceiver<EMERequest>,
+    /// Used to report anything the EME sends back to the updater.
+    pub eme_event_sender: CCSender<EMEEvent>,
+    /// The latest state to publish to visualizers.
+    pub state_frames: triple_buffer::Output<StateFrame>,
 }
 
 #[allow(clippy::redundant_closure_for_method_calls)]
 impl ParameterHandler {
     pub fn new(
         gesture_data: triple_buffer::Output<RawHandPairCOM>,
+        band_energies: triple_buffer::Output<Vec<f64>>,
+        audio_controls: AudioControls,
         args: &Arguments,
     ) -> (Self, ParameterReceivers) {
         let (midi_tx, midi_rx) = bounded_channel(MIDI_MESSAGE_QUEUE_SIZE);
         let (eme_tx, eme_rx) = bounded_channel(EME_OSC_MESSAGE_QUEUE_SIZE);
+        let (event_tx, event_rx) =
+            bounded_channel(EME_OSC_MESSAGE_QUEUE_SIZE);
+        let (state_frame_input, state_frame_output) =
+            triple_buffer::triple_buffer(&StateFrame::default());
 
         let updater = Arc::new(Mutex::new(ParameterUpdater::new(
             ParameterSenders {
                 midi_sender: Arc::new(Mutex::new(midi_tx)),
                 eme_sender: Arc::new(Mutex::new(eme_tx)),
             },
+            event_rx,
             gesture_data,
+            band_energies,
+            state_frame_input,
+            audio_controls,
             args,
         )));
 
@@ -70,14 +126,19 @@ impl ParameterHandler {
             }
         });
 
-        let s = Self {
-            update_thread,
-            updater,
-            cc_attachments: build_midi_cc_attachments(),
-        };
+        // failures are reported by the updater and the config validator
+        let cc_attachments =
+            build_mapped_cc_attachments(args.mapping.as_deref())
+                .unwrap_or_else(|_| build_midi_cc_attachments());
+
+        let s = Self { update_thread, updater, cc_attachments };
 
-        let rx_channels =
-            ParameterReceivers { midi_receiver: midi_rx, eme_receiver: eme_rx };
+        let rx_channels = ParameterReceivers {
+            midi_receiver: midi_rx,
+            eme_receiver: eme_rx,
+            eme_event_sender: event_tx,
+            state_frames: state_frame_output,
+        };
 
         (s, rx_channels)
     }
@@ -103,18 +164,256 @@ impl ParameterHandler {
             // playback, otherwise the EME will return an error.
             guard.set_eme_arrangement(DEFAULT_EME_ARRANGEMENT_NAME);
             guard.set_eme_playback(true);
+            guard.start_transport();
             guard.start_mode_change();
         }
     }
 
+    /// Resumes updates after [`stop_update()`](Self::stop_update) without
+    /// resetting the arrangement or the transport position.
+    pub fn continue_update(&mut self) {
+        self.update_thread.start_hz(PARAM_UPDATE_RATE);
+
+        if let Ok(mut guard) = self.updater.lock() {
+            guard.set_eme_playback(true);
+            guard.continue_transport();
+        }
+    }
+
     pub fn stop_update(&mut self) {
         if let Ok(mut guard) = self.updater.lock() {
             guard.set_eme_playback(false);
+            guard.stop_transport();
         }
 
         self.update_thread.stop();
     }
 
+    /// The current position of the transport.
+    pub fn musical_position(&self) -> Option<MusicalPosition> {
+        self.updater.lock().ok().map(|guard| guard.musical_position())
+    }
+
+    /// How far the current mode change has progressed.
+    pub fn mode_transition(&self) -> Option<ModeTransition> {
+        self.updater.lock().ok().map(|guard| guard.mode_transition())
+    }
+
+    /// The normalized velocity of the first hand, as sent to the synth.
+    pub fn hand_velocity(&self) -> f32 {
+        self.updater.lock().map_or(0.0, |guard| guard.hand_velocities().0)
+    }
+
+    /// The latest perceptual band energies of the output, each from `0.0`
+    /// to `1.0`, from lowest to highest.
+    pub fn audio_bands(&self) -> Vec<f64> {
+        self.updater
+            .lock()
+            .map_or_else(|_| Vec::new(), |guard| guard.audio_bands().to_vec())
+    }
+
+    /// The names of all CCs which are currently latched.
+    pub fn latched_cc_names(&self) -> Vec<&str> {
+        let Ok(guard) = self.updater.lock()
+        else {
+            return Vec::new();
+        };
+
+        let mut names: Vec<&str> = guard
+            .latched_cc_indices()
+            .iter()
+            .filter_map(|idx| self.cc_attachments.get(idx))
+            .map(|att| att.name())
+            .collect();
+
+        names.sort_unstable();
+        names
+    }
+
+    /// The CCs which are pending in the parameter bank, and which were
+    /// truncated by the MIDI byte budget last tick.
+    pub fn parameter_bank_diff(&self) -> Option<ParameterBankDiff> {
+        self.updater.lock().ok().map(|guard| guard.parameter_bank_diff())
+    }
+
+    /// Sets the EME's master volume, from `0` to `1`.
+    pub fn set_eme_volume(&self, volume: f32) {
+        if let Ok(mut guard) = self.updater.lock() {
+            guard.set_eme_volume(volume);
+        }
+    }
+
+    /// Jumps the EME to a section or marker, crossfading over `crossfade`
+    /// seconds if provided.
+    pub fn jump_eme_to(&self, jump: EMEJump, crossfade: Option<f32>) {
+        if let Ok(mut guard) = self.updater.lock() {
+            guard.jump_eme_to(jump, crossfade);
+        }
+    }
+
+    /// The names of the EME's arrangements, if it has reported them.
+    pub fn eme_arrangements(&self) -> Vec<String> {
+        self.updater
+            .lock()
+            .map(|guard| guard.eme_arrangements().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Applies a user profile's calibration and settings.
+    pub fn apply_profile(&self, profile: &UserProfile) {
+        if let Ok(mut guard) = self.updater.lock() {
+            guard.apply_profile(profile);
+        }
+    }
+
+    /// Stores the first hand's pose along with the current CC values, which
+    /// are recalled whenever the pose is held. Returns the pose's number.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no hand is being tracked.
+    pub fn store_pose(&self) -> Result<usize, String> {
+        self.updater
+            .lock()
+            .map_err(|_| String::from("parameters are unavailable"))?
+            .store_pose()
+    }
+
+    /// Removes all stored poses.
+    pub fn clear_poses(&self) {
+        if let Ok(mut guard) = self.updater.lock() {
+            guard.clear_poses();
+        }
+    }
+
+    /// Captures the mode, transport, calibration, CC values and poses.
+    pub fn snapshot(&self) -> Option<ParameterSnapshot> {
+        self.updater.lock().ok().map(|guard| guard.snapshot())
+    }
+
+    /// Restores the state captured by [`snapshot()`](Self::snapshot).
+    pub fn restore_snapshot(&self, snapshot: &ParameterSnapshot) {
+        if let Ok(mut guard) = self.updater.lock() {
+            guard.restore_snapshot(snapshot);
+        }
+    }
+
+    /// Sets the user's reach, which position-derived sources are rescaled
+    /// to.
+    pub fn set_reach_extents(&self, extents: ReachExtents) {
+        if let Ok(mut guard) = self.updater.lock() {
+            guard.set_reach_extents(extents);
+        }
+    }
+
+    pub fn reach_extents(&self) -> ReachExtents {
+        self.updater
+            .lock()
+            .map(|guard| guard.reach_extents())
+            .unwrap_or_default()
+    }
+
+    /// The tracking config which is used by profiles without their own.
+    pub fn base_tracking_config(&self) -> Option<TrackingSpaceConfig> {
+        self.updater.lock().ok().map(|guard| guard.base_tracking_config())
+    }
+
+    /// A description of the most recent EME request failure, if it occurred
+    /// within the last `max_age` seconds.
+    pub fn last_eme_failure(&self, max_age: f64) -> Option<String> {
+        let guard = self.updater.lock().ok()?;
+        guard.last_eme_failure(max_age).map(ToString::to_string)
+    }
+
+    /// The axis which hand movement is locked to, if any. The outer option
+    /// is `None` if no lock is active, and the inner one is `None` if the
+    /// lock is active but its axis hasn't been chosen yet.
+    pub fn locked_axis(&self) -> Option<Option<LockedAxis>> {
+        self.updater.lock().ok()?.locked_axis()
+    }
+
+    /// The names of all CCs which are waiting to be picked up by their live
+    /// source, and whether the source needs to rise (`Some(true)`) or fall
+    /// (`Some(false)`) to reach them.
+    pub fn pickup_cc_names(&self) -> Vec<(&str, Option<bool>)> {
+        let Ok(guard) = self.updater.lock()
+        else {
+            return Vec::new();
+        };
+
+        let mut names: Vec<(&str, Option<bool>)> = guard
+            .pickups()
+            .iter()
+            .filter_map(|pickup| {
+                let att = self.cc_attachments.get(&pickup.idx)?;
+                let rise = pickup.live.map(|live| live < pickup.held);
+
+                Some((att.name(), rise))
+            })
+            .collect();
+
+        names.sort_unstable_by_key(|(name, _)| *name);
+        names
+    }
+
+    /// Sets the tempo of the internal clock.
+    pub fn set_bpm(&self, bpm: f64) {
+        if let Ok(mut guard) = self.updater.lock() {
+            guard.set_bpm(bpm);
+        }
+    }
+
+    /// Returns the latest tempo detected from hand motion, if it has changed
+    /// since this was last called.
+    pub fn take_tempo_change(&self) -> Option<f64> {
+        self.updater
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.take_tempo_change())
+    }
+
+    /// Schedules a mode change to start `num_bars` bars from now.
+    pub fn schedule_mode_change_in_bars(&self, num_bars: u32) {
+        if let Ok(mut guard) = self.updater.lock() {
+            guard.schedule_mode_change_in_bars(num_bars);
+        }
+    }
+
+    /// A template which describes the current mapping.
+    pub fn mapping_template(&self) -> MappingTemplate {
+        MappingTemplate::from_attachments("custom", &self.cc_attachments)
+    }
+
+    /// The names of every attachment which a mapping may send.
+    pub fn mapping_sources(&self) -> Vec<String> {
+        build_midi_cc_attachments()
+            .values()
+            .map(|att| att.name().to_string())
+            .collect()
+    }
+
+    /// Replaces the current mapping with `template`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template cannot be applied, in which case
+    /// the current mapping is kept.
+    pub fn set_mapping(
+        &mut self,
+        template: &MappingTemplate,
+    ) -> Result<(), String> {
+        let attachments = template.apply(build_midi_cc_attachments())?;
+
+        self.updater
+            .lock()
+            .map_err(|_| String::from("parameters are unavailable"))?
+            .set_cc_attachments(attachments.clone());
+
+        self.cc_attachments = attachments;
+
+        Ok(())
+    }
+
     pub fn get_name_for_cc(&self, channel: u8, cc: u8) -> Option<&str> {
         self.cc_attachments
             .get(&MIDICCIndex::new(channel, cc))
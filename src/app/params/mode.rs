@@ -12,6 +12,9 @@ pub enum Mode {
 }
 
 impl Mode {
+    /// Every `Mode`, in sequence.
+    pub const ALL: [Self; 3] = [Self::A, Self::B, Self::C];
+
     /// Returns a random `Mode`.
     pub fn random() -> Self {
         let r = random::<u32>() % 3;
@@ -77,6 +80,30 @@ impl Mode {
         Rect::from_xy_wh(Point2::new(x, y), Vec2::new(1.0, 0.5))
     }
 
+    /// Returns the point in the EME's XY space which the `Mode` is entered
+    /// at, which is the center of its bounds.
+    pub fn eme_entry_point(self) -> Vec2 {
+        self.eme_bounds().xy()
+    }
+
+    /// Parses a `Mode` from its name, e.g. `"A"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "A" => Some(Self::A),
+            "B" => Some(Self::B),
+            "C" => Some(Self::C),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::B => "B",
+            Self::C => "C",
+        }
+    }
+
     pub const fn get_midi_note_value(self) -> u8 {
         match self {
             Self::A => 0,
@@ -86,3 +113,20 @@ impl Mode {
         }
     }
 }
+
+/// How far a change between two modes has progressed, so that the visuals
+/// can follow the sweep which the audio makes between them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModeTransition {
+    pub from: Mode,
+    pub to: Mode,
+    /// How far through the sweep, from `0.0` to `1.0`.
+    pub progress: f64,
+}
+
+impl ModeTransition {
+    /// A transition which has finished at `mode`.
+    pub const fn settled(mode: Mode) -> Self {
+        Self { from: mode, to: mode, progress: 1.0 }
+    }
+}
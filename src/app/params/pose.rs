@@ -0,0 +1,363 @@
+//! Recalling parameter scenes by holding stored hand poses.
+//!
+//! A pose is stored along with the CC values at the time (its "scene"). When
+//! the first hand later matches a stored pose, the latchable CCs morph from
+//! their live values towards the scene, and morph back once the pose is
+//! released.
+
+use super::*;
+use crate::util::interp;
+use hands::hand_types::RawHand;
+use hands::{NUM_HAND_VERTICES, WRIST_VERTEX_INDEX};
+use midi_types::MIDICCIndex;
+use serde_json::{json, Value};
+use snapshot::parse_cc_value;
+use std::collections::HashMap;
+
+/// The default average distance of each landmark from a stored pose's, in
+/// hand-relative units, within which the pose is matched.
+pub const DEFAULT_POSE_TOLERANCE: f64 = 0.5;
+/// The largest pose tolerance.
+pub const MAX_POSE_TOLERANCE: f64 = 2.0;
+
+/// A matched pose is released once it is this much further away than the
+/// tolerance, so that it doesn't flicker at the edge.
+const POSE_RELEASE_SCALE: f64 = 1.25;
+/// The time taken to morph fully towards or away from a scene, in seconds.
+const POSE_MORPH_TIME: f64 = 1.5;
+
+/// The configuration of a hand's landmarks, independent of where the hand is
+/// and how close it is to the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct HandPose {
+    /// Each landmark relative to the wrist, scaled by the hand's size.
+    landmarks: [DVec3; NUM_HAND_VERTICES],
+}
+
+impl HandPose {
+    /// Normalizes the landmarks of `hand`. Returns `None` if the hand has no
+    /// measurable size.
+    pub fn from_hand(hand: &RawHand) -> Option<Self> {
+        let size = hand.get_proximity();
+
+        if !size.is_finite() || size <= f64::EPSILON {
+            return None;
+        }
+
+        let wrist = hand.points[WRIST_VERTEX_INDEX];
+
+        Some(Self { landmarks: hand.points.map(|p| (p - wrist) / size) })
+    }
+
+    /// The average distance between each of the poses' landmarks.
+    pub fn distance(&self, other: &Self) -> f64 {
+        let sum: f64 = self
+            .landmarks
+            .iter()
+            .zip(other.landmarks.iter())
+            .map(|(a, b)| a.distance(*b))
+            .sum();
+
+        sum / NUM_HAND_VERTICES as f64
+    }
+
+    fn from_json(value: &Value) -> Result<Self, String> {
+        let points: Vec<DVec3> = value
+            .as_array()
+            .ok_or_else(|| String::from("pose \"landmarks\" must be an array"))?
+            .iter()
+            .map(|point| {
+                let coords: Option<Vec<f64>> = point
+                    .as_array()
+                    .map(|arr| arr.iter().filter_map(Value::as_f64).collect());
+
+                match coords.as_deref() {
+                    Some(&[x, y, z]) => Ok(dvec3(x, y, z)),
+                    _ => Err(String::from(
+                        "each pose landmark must be an [x, y, z] triple",
+                    )),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        let landmarks = points.try_into().map_err(|_| {
+            format!("a pose must have {NUM_HAND_VERTICES} landmarks")
+        })?;
+
+        Ok(Self { landmarks })
+    }
+
+    fn to_json(self) -> Value {
+        self.landmarks.iter().map(|p| json!([p.x, p.y, p.z])).collect()
+    }
+}
+
+/// A stored pose and the CC values which it recalls.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct PoseScene {
+    pose: HandPose,
+    cc_values: HashMap<MIDICCIndex, f32>,
+}
+
+impl PoseScene {
+    pub const fn new(
+        pose: HandPose,
+        cc_values: HashMap<MIDICCIndex, f32>,
+    ) -> Self {
+        Self { pose, cc_values }
+    }
+
+    /// Parses a scene of the form
+    /// `{ "landmarks": [[x, y, z], ...], "ccs": [...] }`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` is not a valid scene.
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let pose = HandPose::from_json(
+            value.get("landmarks").unwrap_or(&Value::Null),
+        )?;
+
+        let cc_values = match value.get("ccs") {
+            Some(Value::Array(ccs)) => ccs
+                .iter()
+                .map(parse_cc_value)
+                .collect::<Result<HashMap<_, _>, _>>()?,
+            _ => return Err(String::from("pose \"ccs\" must be an array")),
+        };
+
+        Ok(Self { pose, cc_values })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut cc_values: Vec<_> = self.cc_values.iter().collect();
+        cc_values.sort_unstable_by_key(|(idx, _)| **idx);
+
+        let ccs: Vec<Value> = cc_values
+            .into_iter()
+            .map(|(idx, value)| {
+                json!({ "channel": idx.channel, "cc": idx.cc, "value": value })
+            })
+            .collect();
+
+        json!({ "landmarks": self.pose.to_json(), "ccs": ccs })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum PoseEvent {
+    /// The scene at this index has been matched.
+    Matched(usize),
+    /// The scene at this index has been released.
+    Released(usize),
+}
+
+/// Morphs CC values towards the scene of whichever stored pose the hand is
+/// holding.
+///
+/// Only one scene is morphed at a time: another pose isn't matched until the
+/// previous scene has morphed all the way back out.
+#[derive(Clone, Debug)]
+pub(super) struct PoseRecall {
+    scenes: Vec<PoseScene>,
+    tolerance: f64,
+
+    /// The index of the scene being morphed towards or away from.
+    target: Option<usize>,
+    is_matched: bool,
+    /// How far the CCs have morphed towards the target scene, from `0` to
+    /// `1`.
+    amount: f64,
+}
+
+impl PoseRecall {
+    pub fn new(tolerance: f64) -> Self {
+        Self {
+            scenes: Vec::new(),
+            tolerance: tolerance.clamp(0.0, MAX_POSE_TOLERANCE),
+
+            target: None,
+            is_matched: false,
+            amount: 0.0,
+        }
+    }
+
+    /// Stores a scene, returning its index.
+    pub fn store(&mut self, scene: PoseScene) -> usize {
+        self.scenes.push(scene);
+        self.scenes.len() - 1
+    }
+
+    /// Replaces all stored scenes.
+    pub fn set_scenes(&mut self, scenes: Vec<PoseScene>) {
+        self.scenes = scenes;
+        self.target = None;
+        self.is_matched = false;
+        self.amount = 0.0;
+    }
+
+    /// Removes all stored scenes.
+    pub fn clear(&mut self) {
+        self.set_scenes(Vec::new());
+    }
+
+    pub fn scenes(&self) -> &[PoseScene] {
+        &self.scenes
+    }
+
+    /// Matches `hand` against the stored poses and advances the morph by
+    /// `dt` seconds.
+    pub fn update(
+        &mut self,
+        hand: Option<&RawHand>,
+        dt: f64,
+    ) -> Option<PoseEvent> {
+        let pose = hand.and_then(HandPose::from_hand);
+        let distance_to =
+            |idx: usize| pose.map(|p| p.distance(&self.scenes[idx].pose));
+
+        let was_matched = self.is_matched;
+
+        self.is_matched = match self.target {
+            Some(idx) => {
+                let threshold = if self.is_matched {
+                    self.tolerance * POSE_RELEASE_SCALE
+                }
+                else {
+                    self.tolerance
+                };
+
+                distance_to(idx).is_some_and(|dist| dist < threshold)
+            }
+            None => {
+                self.target = pose.and_then(|p| self.closest_match(&p));
+                self.target.is_some()
+            }
+        };
+
+        let step = dt / POSE_MORPH_TIME;
+        self.amount = if self.is_matched {
+            (self.amount + step).min(1.0)
+        }
+        else {
+            (self.amount - step).max(0.0)
+        };
+
+        let target = self.target?;
+
+        if !self.is_matched && self.amount <= 0.0 {
+            self.target = None;
+        }
+
+        match (was_matched, self.is_matched) {
+            (false, true) => Some(PoseEvent::Matched(target)),
+            (true, false) => Some(PoseEvent::Released(target)),
+            _ => None,
+        }
+    }
+
+    /// Morphs the `live` value of the CC at `idx` towards the target scene.
+    pub fn morph(&self, idx: &MIDICCIndex, live: f32) -> f32 {
+        let Some(target) = self
+            .target
+            .and_then(|i| self.scenes.get(i))
+            .and_then(|scene| scene.cc_values.get(idx))
+        else {
+            return live;
+        };
+
+        interp::cosine(live as f64, *target as f64, self.amount) as f32
+    }
+
+    /// The index of the stored pose closest to `pose`, if any is within the
+    /// tolerance.
+    fn closest_match(&self, pose: &HandPose) -> Option<usize> {
+        self.scenes
+            .iter()
+            .map(|scene| scene.pose.distance(pose))
+            .enumerate()
+            .filter(|(_, dist)| *dist < self.tolerance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(idx, _)| idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hand(spread: f64, offset: DVec3, scale: f64) -> RawHand {
+        let mut hand = RawHand::default();
+
+        for (i, point) in hand.points.iter_mut().enumerate() {
+            let finger = (i / 4) as f64;
+            let joint = (i % 4) as f64 + 1.0;
+
+            let unscaled = dvec3(finger * spread, joint, finger * 0.1);
+            *point = unscaled * scale + offset;
+        }
+
+        hand
+    }
+
+    fn scene(spread: f64, value: f32) -> PoseScene {
+        let pose = HandPose::from_hand(&hand(spread, DVec3::ZERO, 1.0));
+
+        PoseScene::new(
+            pose.unwrap(),
+            HashMap::from([(MIDICCIndex::new(0, 1), value)]),
+        )
+    }
+
+    #[test]
+    fn pose_ignores_position_and_size() {
+        let near = HandPose::from_hand(&hand(0.5, DVec3::ZERO, 1.0)).unwrap();
+        let far =
+            HandPose::from_hand(&hand(0.5, dvec3(3.0, -2.0, 1.0), 0.25))
+                .unwrap();
+        let other = HandPose::from_hand(&hand(2.0, DVec3::ZERO, 1.0)).unwrap();
+
+        assert!(near.distance(&far) < 1e-9);
+        assert!(near.distance(&other) > DEFAULT_POSE_TOLERANCE);
+    }
+
+    #[test]
+    fn held_pose_morphs_in_and_out() {
+        let idx = MIDICCIndex::new(0, 1);
+        let mut recall = PoseRecall::new(DEFAULT_POSE_TOLERANCE);
+        recall.store(scene(0.5, 1.0));
+
+        let posed = hand(0.5, dvec3(1.0, 1.0, 0.0), 2.0);
+        let other = hand(2.0, DVec3::ZERO, 1.0);
+
+        assert_eq!(recall.update(Some(&other), 0.1), None);
+        assert_eq!(recall.morph(&idx, 0.0), 0.0);
+
+        assert_eq!(
+            recall.update(Some(&posed), POSE_MORPH_TIME * 0.5),
+            Some(PoseEvent::Matched(0))
+        );
+        assert!((recall.morph(&idx, 0.0) - 0.5).abs() < 1e-6);
+
+        _ = recall.update(Some(&posed), POSE_MORPH_TIME);
+        assert_eq!(recall.morph(&idx, 0.0), 1.0);
+
+        // other CCs are left alone
+        assert_eq!(recall.morph(&MIDICCIndex::new(0, 2), 0.3), 0.3);
+
+        assert_eq!(
+            recall.update(None, POSE_MORPH_TIME),
+            Some(PoseEvent::Released(0))
+        );
+        assert_eq!(recall.morph(&idx, 0.2), 0.2);
+    }
+
+    #[test]
+    fn scene_json_round_trips() {
+        let scene = scene(0.5, 0.75);
+        let parsed = PoseScene::from_json(&scene.to_json()).unwrap();
+
+        assert_eq!(parsed, scene);
+        assert!(PoseScene::from_json(&json!({ "ccs": [] })).is_err());
+    }
+}
@@ -0,0 +1,457 @@
+//! Named user profiles, which store each performer's personal setup.
+//!
+//! Profiles are stored together in a JSON file of the form:
+//!
+//! ```json
+//! {
+//!     "active": "Jamie",
+//!     "profiles": [
+//!         {
+//!             "name": "Jamie",
+//!             "reach": { "x": [0.1, 0.9], "y": [0.05, 0.8], "z": [0.0, 1.0] },
+//!             "tracking": { "x": { "edge": 0.02 } },
+//!             "sensitivity": 1.2,
+//!             "gesture_hold_scale": 1.5,
+//!             "preferred_modes": ["A", "C"]
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! All fields other than `"name"` are optional.
+
+use super::*;
+use crate::app::diagnostics::Diagnostics;
+use deadzone::TrackingSpaceConfig;
+use serde_json::{json, Value};
+
+/// The name of the profile which is used if no profiles are loaded.
+pub const DEFAULT_PROFILE_NAME: &str = "Default";
+
+const MIN_SENSITIVITY: f64 = 0.1;
+const MAX_SENSITIVITY: f64 = 10.0;
+const MIN_GESTURE_HOLD_SCALE: f64 = 0.25;
+const MAX_GESTURE_HOLD_SCALE: f64 = 5.0;
+
+/// A performer's calibration, sensitivity, and accessibility settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserProfile {
+    pub name: String,
+    /// The performer's calibrated reach.
+    pub reach: ReachExtents,
+    /// Dead-zones and hysteresis for the tracking space. If `None`, the
+    /// configuration passed via `--tracking-config` is used.
+    pub tracking: Option<TrackingSpaceConfig>,
+    /// Scales hand velocity before it is mapped, so values above `1` make
+    /// velocity-driven parameters more responsive.
+    pub sensitivity: f64,
+    /// Scales how long gestures must be held before they take effect. Values
+    /// above `1` give more time, which helps performers with less steady
+    /// hands avoid accidental triggers.
+    pub gesture_hold_scale: f64,
+    /// The modes which automatic mode changes choose from. All modes are used
+    /// if this is empty.
+    pub preferred_modes: Vec<Mode>,
+}
+
+impl UserProfile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            reach: ReachExtents::default(),
+            tracking: None,
+            sensitivity: 1.0,
+            gesture_hold_scale: 1.0,
+            preferred_modes: Vec::new(),
+        }
+    }
+
+    /// Parses a profile from a JSON value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` is not a valid profile.
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let Some(name) = value.get("name").and_then(Value::as_str)
+        else {
+            return Err(String::from("profile is missing a \"name\""));
+        };
+
+        let mut profile = Self::new(name);
+        let err = |e: String| format!("in profile \"{name}\": {e}");
+
+        if let Some(reach) = value.get("reach") {
+            profile.reach = ReachExtents::from_json(reach).map_err(err)?;
+        }
+
+        if let Some(tracking) = value.get("tracking") {
+            profile.tracking =
+                Some(TrackingSpaceConfig::from_json(tracking).map_err(err)?);
+        }
+
+        if let Some(v) = value.get("sensitivity") {
+            profile.sensitivity = v
+                .as_f64()
+                .ok_or_else(|| err("\"sensitivity\" must be a number".into()))?
+                .clamp(MIN_SENSITIVITY, MAX_SENSITIVITY);
+        }
+
+        if let Some(v) = value.get("gesture_hold_scale") {
+            profile.gesture_hold_scale = v
+                .as_f64()
+                .ok_or_else(|| {
+                    err("\"gesture_hold_scale\" must be a number".into())
+                })?
+                .clamp(MIN_GESTURE_HOLD_SCALE, MAX_GESTURE_HOLD_SCALE);
+        }
+
+        if let Some(modes) = value.get("preferred_modes") {
+            let Some(modes) = modes.as_array()
+            else {
+                return Err(err("\"preferred_modes\" must be an array".into()));
+            };
+
+            profile.preferred_modes = modes
+                .iter()
+                .map(|mode| {
+                    mode.as_str().and_then(Mode::from_name).ok_or_else(|| {
+                        err(format!("unknown mode {mode}"))
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+
+        Ok(profile)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let modes: Vec<&str> =
+            self.preferred_modes.iter().map(|mode| mode.name()).collect();
+
+        let mut value = json!({
+            "name": self.name,
+            "reach": self.reach.to_json(),
+            "sensitivity": self.sensitivity,
+            "gesture_hold_scale": self.gesture_hold_scale,
+            "preferred_modes": modes,
+        });
+
+        if let Some(tracking) = &self.tracking {
+            value["tracking"] = tracking.to_json();
+        }
+
+        value
+    }
+}
+
+impl Default for UserProfile {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROFILE_NAME)
+    }
+}
+
+// *** *** *** //
+
+/// All known profiles, and which one is active.
+#[derive(Clone, Debug)]
+pub struct ProfileStore {
+    /// Where the profiles are saved to, if anywhere.
+    path: Option<String>,
+    profiles: Vec<UserProfile>,
+    active_idx: usize,
+}
+
+impl ProfileStore {
+    /// A store holding only the default profile, which is never saved.
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            profiles: vec![UserProfile::default()],
+            active_idx: 0,
+        }
+    }
+
+    /// Loads profiles from the file at `path`, which they will also be saved
+    /// to. If the file doesn't exist yet, the store starts with the default
+    /// profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let mut store = Self::new();
+        store.path = Some(String::from(path));
+
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(store);
+            }
+            Err(e) => {
+                return Err(format!("failed to read profiles \"{path}\": {e}"));
+            }
+        };
+
+        let parsed = Self::from_json_str(&contents)?;
+        store.profiles = parsed.profiles;
+        store.active_idx = parsed.active_idx;
+
+        Ok(store)
+    }
+
+    /// Parses profiles from a JSON string. The returned store is never
+    /// saved.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` is not a valid profiles file.
+    pub fn from_json_str(json: &str) -> Result<Self, String> {
+        let mut store = Self::new();
+
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        let Some(profiles) = value.get("profiles").and_then(Value::as_array)
+        else {
+            return Err(String::from("expected a \"profiles\" array"));
+        };
+
+        let profiles = profiles
+            .iter()
+            .map(UserProfile::from_json)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if !profiles.is_empty() {
+            store.profiles = profiles;
+        }
+
+        if let Some(active) = value.get("active").and_then(Value::as_str) {
+            _ = store.select(active);
+        }
+
+        Ok(store)
+    }
+
+    /// Writes all profiles to the store's file, if it has one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be written.
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path
+        else {
+            return Ok(());
+        };
+
+        let contents = serde_json::to_string_pretty(&self.to_json())
+            .map_err(|e| e.to_string())?;
+
+        std::fs::write(path, contents)
+            .map_err(|e| format!("failed to write profiles \"{path}\": {e}"))
+    }
+
+    /// All profiles as JSON, in the same form which is parsed by
+    /// [`from_json_str()`](Self::from_json_str).
+    pub fn to_json(&self) -> Value {
+        let profiles: Vec<Value> =
+            self.profiles.iter().map(UserProfile::to_json).collect();
+
+        json!({
+            "active": self.active().name,
+            "profiles": profiles,
+        })
+    }
+
+    /// The file which the profiles are saved to, if any.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn active(&self) -> &UserProfile {
+        &self.profiles[self.active_idx]
+    }
+
+    pub fn active_mut(&mut self) -> &mut UserProfile {
+        &mut self.profiles[self.active_idx]
+    }
+
+    /// Makes the profile called `name` active, creating it if it doesn't
+    /// exist. Returns `true` if the profile was created.
+    pub fn select_or_create(&mut self, name: &str) -> bool {
+        if self.select(name).is_ok() {
+            return false;
+        }
+
+        self.profiles.push(UserProfile::new(name));
+        self.active_idx = self.profiles.len() - 1;
+
+        true
+    }
+
+    /// Makes the profile called `name` active.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no profile called `name`.
+    pub fn select(&mut self, name: &str) -> Result<&UserProfile, String> {
+        let Some(idx) = self.profiles.iter().position(|p| p.name == name)
+        else {
+            return Err(format!("no profile called \"{name}\""));
+        };
+
+        self.active_idx = idx;
+        Ok(self.active())
+    }
+
+    /// Makes the next profile active, wrapping around to the first.
+    pub fn select_next(&mut self) -> &UserProfile {
+        self.active_idx = (self.active_idx + 1) % self.profiles.len();
+        self.active()
+    }
+
+    pub fn len(&self) -> usize {
+        self.profiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+}
+
+impl Default for ProfileStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// *** *** *** //
+
+/// Checks the profiles at `path` for duplicate names, out-of-range values,
+/// and ignored entries.
+pub fn diagnose_profiles(path: &str) -> Diagnostics {
+    let mut diags = Diagnostics::new("profiles");
+
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        // the file is created when a profile is first saved
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return diags,
+        Err(e) => {
+            diags.error(format!("failed to read \"{path}\": {e}"));
+            return diags;
+        }
+    };
+
+    if let Err(e) = ProfileStore::from_json_str(&contents) {
+        diags.error(e).with_hint("only the default profile is available");
+    }
+
+    let Ok(value) = serde_json::from_str::<Value>(&contents)
+    else {
+        return diags;
+    };
+
+    diags.check_keys(&value, "the profiles file", &["active", "profiles"]);
+
+    let profiles = value.get("profiles").and_then(Value::as_array);
+    let mut names: Vec<&str> = Vec::new();
+
+    for profile in profiles.into_iter().flatten() {
+        let Some(name) = profile.get("name").and_then(Value::as_str)
+        else {
+            continue;
+        };
+
+        diags.check_keys(
+            profile,
+            &format!("profile \"{name}\""),
+            &[
+                "name",
+                "reach",
+                "tracking",
+                "sensitivity",
+                "gesture_hold_scale",
+                "preferred_modes",
+            ],
+        );
+
+        if names.contains(&name) {
+            diags
+                .warning(format!(
+                    "profile \"{name}\" is defined more than once"
+                ))
+                .with_hint("only the first is used, so rename the others");
+        }
+
+        names.push(name);
+
+        diagnose_profile_values(profile, name, &mut diags);
+    }
+
+    if let Some(active) = value.get("active").and_then(Value::as_str)
+        && !names.contains(&active)
+    {
+        diags
+            .warning(format!("the active profile \"{active}\" doesn't exist"))
+            .with_hint("the first profile is used instead");
+    }
+
+    diags
+}
+
+fn diagnose_profile_values(
+    profile: &Value,
+    name: &str,
+    diags: &mut Diagnostics,
+) {
+    if let Ok(reach) =
+        ReachExtents::from_json(profile.get("reach").unwrap_or(&Value::Null))
+    {
+        let axes = [("x", reach.x), ("y", reach.y), ("z", reach.z)];
+
+        for (axis, range) in axes {
+            if range.min < 0.0 || range.max > 1.0 {
+                diags
+                    .warning(format!(
+                        "the reach of \"{axis}\" in profile \"{name}\" is outside [0, 1]"
+                    ))
+                    .with_hint("recalibrate with shift + 'C'");
+            }
+        }
+    }
+
+    let clamped = [
+        ("sensitivity", MIN_SENSITIVITY, MAX_SENSITIVITY),
+        ("gesture_hold_scale", MIN_GESTURE_HOLD_SCALE, MAX_GESTURE_HOLD_SCALE),
+    ];
+
+    for (key, min, max) in clamped {
+        if let Some(v) = profile.get(key).and_then(Value::as_f64)
+            && !(min..=max).contains(&v)
+        {
+            diags
+                .warning(format!(
+                    "\"{key}\" in profile \"{name}\" is clamped to [{min}, {max}]"
+                ))
+                .with_hint(format!("use a value from {min} to {max}"));
+        }
+    }
+
+    let modes = profile.get("preferred_modes").and_then(Value::as_array);
+    let mut seen: Vec<Mode> = Vec::new();
+
+    let modes = modes
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.as_str().and_then(Mode::from_name));
+
+    for mode in modes {
+        if seen.contains(&mode) {
+            diags.warning(format!(
+                "mode {} is listed more than once in profile \"{name}\"",
+                mode.name()
+            ));
+        }
+
+        seen.push(mode);
+    }
+}
@@ -0,0 +1,250 @@
+//! Snapshots of the parameter updater's state, used to save and restore it.
+
+use super::*;
+use midi_types::{MIDICCIndex, NUM_MIDI_CCS, NUM_MIDI_CHANNELS};
+use pose::PoseScene;
+use serde_json::{json, Value};
+
+/// The state of the parameter updater at a moment in time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterSnapshot {
+    pub(super) mode: Mode,
+    pub(super) previous_mode: Mode,
+
+    pub(super) eme_arrangement: String,
+    pub(super) eme_playing: bool,
+    /// The position in the EME's space, from `-1` to `1` on each axis.
+    pub(super) eme_position: Vec2,
+    /// The EME's master volume, if it has been set.
+    pub(super) eme_volume: Option<f32>,
+
+    pub(super) bpm: f64,
+    pub(super) beats_per_bar: u32,
+    /// The total number of beats since the transport started.
+    pub(super) transport_beats: f64,
+
+    pub(super) reach: ReachExtents,
+    pub(super) cc_values: Vec<(MIDICCIndex, f32)>,
+    /// The stored poses and the scenes they recall.
+    pub(super) poses: Vec<PoseScene>,
+}
+
+impl ParameterSnapshot {
+    /// Parses a snapshot from a JSON value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` is not a valid snapshot.
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let mode = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .and_then(Mode::from_name)
+                .ok_or_else(|| format!("missing or unknown \"{key}\""))
+        };
+
+        let transport = value.get("transport").unwrap_or(&Value::Null);
+        let transport_number = |key: &str| {
+            transport
+                .get(key)
+                .and_then(Value::as_f64)
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| format!("transport \"{key}\" must be a number"))
+        };
+
+        let eme = value.get("eme").unwrap_or(&Value::Null);
+        let eme_position = match eme.get("position") {
+            Some(pos) => parse_eme_position(pos)?,
+            None => vec2(0.0, 0.5),
+        };
+
+        let reach = value.get("reach").map_or_else(
+            || Ok(ReachExtents::default()),
+            ReachExtents::from_json,
+        )?;
+
+        let cc_values = match value.get("ccs") {
+            Some(Value::Array(ccs)) => ccs
+                .iter()
+                .map(parse_cc_value)
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_) => return Err(String::from("\"ccs\" must be an array")),
+            None => Vec::new(),
+        };
+
+        let poses = match value.get("poses") {
+            Some(Value::Array(poses)) => poses
+                .iter()
+                .map(PoseScene::from_json)
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_) => return Err(String::from("\"poses\" must be an array")),
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            mode: mode("mode")?,
+            previous_mode: mode("previous_mode")?,
+
+            eme_arrangement: eme
+                .get("arrangement")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            eme_playing: eme
+                .get("playing")
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+            eme_position,
+            eme_volume: eme
+                .get("volume")
+                .and_then(Value::as_f64)
+                .map(|v| (v as f32).clamp(0.0, 1.0)),
+
+            bpm: transport_number("bpm")?.clamp(MIN_BPM, MAX_BPM),
+            beats_per_bar: transport_number("beats_per_bar")?.max(1.0) as u32,
+            transport_beats: transport_number("beats")?.max(0.0),
+
+            reach,
+            cc_values,
+            poses,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let ccs: Vec<Value> = self
+            .cc_values
+            .iter()
+            .map(|(idx, value)| {
+                json!({ "channel": idx.channel, "cc": idx.cc, "value": value })
+            })
+            .collect();
+
+        let poses: Vec<Value> =
+            self.poses.iter().map(PoseScene::to_json).collect();
+
+        json!({
+            "mode": self.mode.name(),
+            "previous_mode": self.previous_mode.name(),
+            "eme": {
+                "arrangement": self.eme_arrangement,
+                "playing": self.eme_playing,
+                "position": [self.eme_position.x, self.eme_position.y],
+                "volume": self.eme_volume,
+            },
+            "transport": {
+                "bpm": self.bpm,
+                "beats_per_bar": self.beats_per_bar,
+                "beats": self.transport_beats,
+            },
+            "reach": self.reach.to_json(),
+            "ccs": ccs,
+            "poses": poses,
+        })
+    }
+
+    pub const fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    pub const fn reach(&self) -> ReachExtents {
+        self.reach
+    }
+}
+
+/// Parses an EME position of the form `[x, y]`.
+fn parse_eme_position(value: &Value) -> Result<Vec2, String> {
+    let coords: Option<Vec<f64>> = value
+        .as_array()
+        .map(|arr| arr.iter().filter_map(Value::as_f64).collect());
+
+    match coords.as_deref() {
+        Some(&[x, y]) => Ok(vec2(
+            (x as f32).clamp(-1.0, 1.0),
+            (y as f32).clamp(-1.0, 1.0),
+        )),
+        _ => Err(String::from("EME \"position\" must be an [x, y] pair")),
+    }
+}
+
+/// Parses a CC value of the form `{ "channel": 0, "cc": 1, "value": 0.5 }`.
+pub(super) fn parse_cc_value(
+    value: &Value,
+) -> Result<(MIDICCIndex, f32), String> {
+    let field = |key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_u64)
+            .ok_or_else(|| format!("CC \"{key}\" must be a positive integer"))
+    };
+
+    let (channel, cc) = (field("channel")? as usize, field("cc")? as usize);
+
+    if channel >= NUM_MIDI_CHANNELS || cc >= NUM_MIDI_CCS {
+        return Err(format!("CC {cc} on channel {channel} is out of range"));
+    }
+
+    let Some(cc_value) = value.get("value").and_then(Value::as_f64)
+    else {
+        return Err(format!("CC {cc} on channel {channel} has no \"value\""));
+    };
+
+    Ok((MIDICCIndex { channel, cc }, (cc_value as f32).clamp(0.0, 1.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_survives_a_round_trip() {
+        let value = json!({
+            "mode": "C",
+            "previous_mode": "B",
+            "eme": { "arrangement": "default", "playing": true },
+            "transport": { "bpm": 120.0, "beats_per_bar": 4, "beats": 32.0 },
+            "ccs": [{ "channel": 1, "cc": 7, "value": 0.75 }]
+        });
+        let snapshot = ParameterSnapshot::from_json(&value).unwrap();
+
+        assert_eq!(snapshot.mode, Mode::C);
+        assert_eq!(snapshot.eme_position, vec2(0.0, 0.5));
+        assert_eq!(snapshot.eme_volume, None);
+        assert_eq!(snapshot.reach, ReachExtents::default());
+        assert_eq!(
+            snapshot.cc_values,
+            [(MIDICCIndex { channel: 1, cc: 7 }, 0.75)]
+        );
+
+        let json = snapshot.to_json();
+        assert_eq!(ParameterSnapshot::from_json(&json).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn invalid_snapshots_are_rejected() {
+        let transport = json!({ "bpm": 120.0, "beats_per_bar": 4, "beats": 0 });
+
+        for value in [
+            json!({
+                "mode": "D",
+                "previous_mode": "A",
+                "transport": transport
+            }),
+            json!({ "mode": "A", "previous_mode": "A" }),
+            json!({
+                "mode": "A",
+                "previous_mode": "A",
+                "transport": transport,
+                "ccs": [{ "channel": 16, "cc": 1, "value": 0.5 }]
+            }),
+            json!({
+                "mode": "A",
+                "previous_mode": "A",
+                "transport": transport,
+                "eme": { "position": [0.0] }
+            }),
+        ] {
+            assert!(ParameterSnapshot::from_json(&value).is_err(), "{value}");
+        }
+    }
+}
@@ -1,12 +1,6 @@
 use super::*;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct ParameterState {
     pub mode: Mode,
 }
-
-impl Default for ParameterState {
-    fn default() -> Self {
-        Self { mode: Mode::default() }
-    }
-}
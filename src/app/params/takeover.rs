@@ -0,0 +1,88 @@
+//! Soft-takeover ("pickup") of CC values when their controlling source
+//! changes.
+
+use super::*;
+use midi_types::MIDICCIndex;
+use std::collections::HashMap;
+
+/// Live values within this distance of the held value are picked up, even if
+/// they haven't crossed it.
+const PICKUP_TOLERANCE: f32 = 0.02;
+
+#[derive(Clone, Copy, Debug)]
+struct PickupState {
+    held: f32,
+    prev_live: Option<f32>,
+}
+
+/// A CC which is waiting to be picked up by its live source.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Pickup {
+    pub idx: MIDICCIndex,
+    /// The value the CC is being held at.
+    pub held: f32,
+    /// The latest value from the live source, if any.
+    pub live: Option<f32>,
+}
+
+/// Holds CC values after their controlling source changes, and only hands
+/// control back to the live source once it crosses the held value. This
+/// prevents parameters from jumping.
+#[derive(Clone, Debug, Default)]
+pub(super) struct SoftTakeover {
+    waiting: HashMap<MIDICCIndex, PickupState>,
+}
+
+impl SoftTakeover {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Holds the CC at `idx` at `held` until the live source picks it up.
+    pub fn arm(&mut self, idx: MIDICCIndex, held: f32) {
+        self.waiting.insert(idx, PickupState { held, prev_live: None });
+    }
+
+    pub fn is_waiting(&self, idx: &MIDICCIndex) -> bool {
+        self.waiting.contains_key(idx)
+    }
+
+    /// Returns the value the CC at `idx` should take, given the latest value
+    /// from its live source.
+    pub fn process(&mut self, idx: &MIDICCIndex, live: f32) -> f32 {
+        let Some(state) = self.waiting.get_mut(idx)
+        else {
+            return live;
+        };
+
+        let held = state.held;
+        let near = (live - held).abs() <= PICKUP_TOLERANCE;
+        let crossed = state.prev_live.is_some_and(|prev| {
+            (prev - held).signum() != (live - held).signum()
+        });
+
+        if near || crossed {
+            self.waiting.remove(idx);
+            return live;
+        }
+
+        state.prev_live = Some(live);
+        held
+    }
+
+    /// All CCs which are waiting to be picked up.
+    pub fn pickups(&self) -> Vec<Pickup> {
+        self.waiting
+            .iter()
+            .map(|(idx, state)| Pickup {
+                idx: *idx,
+                held: state.held,
+                live: state.prev_live,
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.waiting.clear();
+    }
+}
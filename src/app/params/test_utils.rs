@@ -0,0 +1,226 @@
+//! Utilities for unit testing CC attachments with synthetic hand data.
+//!
+//! Each attachment's callback is evaluated in isolation (without smoothing)
+//! for hands built from a few intuitive parameters, so that mappings can be
+//! checked for range and monotonicity without a tracker.
+
+use super::*;
+use attachment::MIDICCAttachment;
+use hands::hand_types::{
+    CCUpdateData, COMPair, HandGesture, RawHand, RawHandPair,
+};
+use hands::{
+    INDEX_TIP_VERTEX_INDEX, NUM_HAND_VERTICES, THUMB_TIP_VERTEX_INDEX,
+};
+use midi_types::MIDICCIndex;
+
+/// The number of values each grid parameter takes.
+pub const DEFAULT_GRID_STEPS: usize = 5;
+
+/// The allowed error when checking ranges and monotonicity.
+const TOLERANCE: f32 = 1e-6;
+
+/// The direction which a mapping should move in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Increasing,
+    Decreasing,
+}
+
+/// A hand described by the features which attachments respond to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SyntheticHand {
+    /// The hand's center of mass, in normalized coordinates.
+    pub com: DVec3,
+    /// The length of each thumb bone, which sets the hand's proximity.
+    pub size: f64,
+    /// How far the fingers are extended, where `1.0` is a relaxed hand.
+    pub spread: f64,
+    /// How far the thumb tip has moved towards the index finger tip, from
+    /// `0.0` to `1.0`.
+    pub pinch: f64,
+    pub gesture: HandGesture,
+}
+
+impl SyntheticHand {
+    /// The landmarks of the hand.
+    pub fn to_raw_hand(self) -> RawHand {
+        let Self { com, size: s, spread, pinch, gesture } = self;
+        let mut points = [DVec3::ZERO; NUM_HAND_VERTICES];
+
+        // the wrist and thumb, whose bones are each `size` long
+        points[0] = com + DVec3::new(0.0, 2.0 * s, 0.0);
+        points[1] = points[0] + DVec3::new(s, 0.0, 0.0);
+        points[2] = points[1] + DVec3::new(0.6 * s, -0.8 * s, 0.0);
+        points[3] = points[2] + DVec3::new(0.0, -s, 0.0);
+
+        // the index, middle, ring and pinky fingers extend from their
+        // knuckles by `spread`
+        for finger in 0..4 {
+            let knuckle =
+                com + DVec3::new((0.75 - 0.5 * finger as f64) * s, 0.0, 0.0);
+
+            for joint in 0..4 {
+                points[5 + finger * 4 + joint] = knuckle
+                    + DVec3::new(0.0, -spread * s * joint as f64, 0.0);
+            }
+        }
+
+        let relaxed_thumb_tip = points[3] + DVec3::new(0.0, -s, 0.0);
+        points[THUMB_TIP_VERTEX_INDEX] =
+            relaxed_thumb_tip.lerp(points[INDEX_TIP_VERTEX_INDEX], pinch);
+
+        RawHand { points, gesture }
+    }
+}
+
+impl Default for SyntheticHand {
+    fn default() -> Self {
+        Self {
+            com: DVec3::splat(0.5),
+            size: 0.045,
+            spread: 1.0,
+            pinch: 0.0,
+            gesture: HandGesture::Open,
+        }
+    }
+}
+
+/// Returns a hand pair holding only `first`, as it is seen by attachments.
+pub fn hands_from(first: Option<SyntheticHand>) -> RawHandPairCOM {
+    RawHandPairCOM {
+        pair: RawHandPair {
+            first: first.map(|hand| hand.to_raw_hand()),
+            second: None,
+        },
+        com: COMPair { first: first.map(|hand| hand.com), second: None },
+    }
+}
+
+/// Returns the attachment called `name`.
+///
+/// # Panics
+///
+/// Panics if there is no such attachment.
+pub(super) fn attachment_named<'a>(
+    attachments: &'a HashMap<MIDICCIndex, MIDICCAttachment>,
+    name: &str,
+) -> &'a MIDICCAttachment {
+    attachments
+        .values()
+        .find(|attachment| attachment.name() == name)
+        .unwrap_or_else(|| panic!("no attachment is called \"{name}\""))
+}
+
+/// Evaluates `attachment` for `hand` moving at `velocity`, starting from a
+/// CC value of `0.0`.
+pub fn evaluate(
+    attachment: &MIDICCAttachment,
+    hand: Option<SyntheticHand>,
+    velocity: f32,
+) -> f32 {
+    let hands = hands_from(hand);
+    let velocities = (velocity, 0.0);
+    let mut cc = 0.0;
+
+    attachment.evaluate(&CCUpdateData::synthetic(&hands, &velocities), &mut cc);
+    cc
+}
+
+/// Evaluates `attachment` with the default hand, changing one of its
+/// parameters with `vary` over `steps` values from `min` to `max`.
+pub fn sweep<F>(
+    attachment: &MIDICCAttachment,
+    steps: usize,
+    (min, max): (f64, f64),
+    vary: F,
+) -> Vec<f32>
+where
+    F: Fn(&mut SyntheticHand, f64),
+{
+    linspace(min, max, steps)
+        .map(|value| {
+            let mut hand = SyntheticHand::default();
+            vary(&mut hand, value);
+
+            evaluate(attachment, Some(hand), 0.0)
+        })
+        .collect()
+}
+
+/// Evaluates `attachment` for every combination of `steps` positions along
+/// `x` and `y`, spreads and pinches.
+pub fn evaluate_grid(
+    attachment: &MIDICCAttachment,
+    steps: usize,
+) -> Vec<(SyntheticHand, f32)> {
+    let mut results = Vec::with_capacity(steps.pow(4));
+
+    for x in linspace(0.0, 1.0, steps) {
+        for y in linspace(0.0, 1.0, steps) {
+            for spread in linspace(0.2, 1.5, steps) {
+                for pinch in linspace(0.0, 1.0, steps) {
+                    let hand = SyntheticHand {
+                        com: DVec3::new(x, y, 0.5),
+                        spread,
+                        pinch,
+                        ..SyntheticHand::default()
+                    };
+
+                    let value = evaluate(attachment, Some(hand), 0.0);
+                    results.push((hand, value));
+                }
+            }
+        }
+    }
+
+    results
+}
+
+/// Asserts that every value of `attachment` over the grid is within
+/// `[min, max]`.
+///
+/// # Panics
+///
+/// Panics if any value is out of range.
+pub fn assert_grid_in_range(
+    attachment: &MIDICCAttachment,
+    (min, max): (f32, f32),
+) {
+    for (hand, value) in evaluate_grid(attachment, DEFAULT_GRID_STEPS) {
+        assert!(
+            (min - TOLERANCE..=max + TOLERANCE).contains(&value),
+            "\"{}\" was {value} (outside [{min}, {max}]) for {hand:?}",
+            attachment.name()
+        );
+    }
+}
+
+/// Asserts that `values` never move against `direction`.
+///
+/// # Panics
+///
+/// Panics if any value moves the wrong way.
+pub fn assert_monotonic(name: &str, values: &[f32], direction: Direction) {
+    for (i, pair) in values.windows(2).enumerate() {
+        let delta = pair[1] - pair[0];
+        let is_ok = match direction {
+            Direction::Increasing => delta >= -TOLERANCE,
+            Direction::Decreasing => delta <= TOLERANCE,
+        };
+
+        assert!(
+            is_ok,
+            "\"{name}\" should be {direction:?}, but went from {} to {} at \
+             step {i}",
+            pair[0],
+            pair[1]
+        );
+    }
+}
+
+fn linspace(min: f64, max: f64, steps: usize) -> impl Iterator<Item = f64> {
+    let steps = steps.max(2);
+
+    (0..steps).map(move |i| min + (max - min) * i as f64 / (steps - 1) as f64)
+}
@@ -223,6 +223,8 @@ pub enum ExciterOscillator {
     Tri,
     Saw,
     Square,
+    Fm,
+    Pluck,
     #[default]
     Noise,
 }
@@ -234,6 +236,8 @@ impl Display for ExciterOscillator {
             Self::Tri => write!(f, "Tri"),
             Self::Saw => write!(f, "Saw"),
             Self::Square => write!(f, "Square"),
+            Self::Fm => write!(f, "FM"),
+            Self::Pluck => write!(f, "Pluck"),
             Self::Noise => write!(f, "Noise"),
         }
     }
@@ -11,37 +11,63 @@ use crate::app::{args::Arguments, hands::hand_types::Finger};
 
 use super::*;
 use attachment::*;
-use eme_request::{EMEPlayback, EMEPosition};
+use audio_attachments::{
+    build_audio_attachments, AudioControls, AudioParameterAttachment,
+};
+use broadcast::{HandFrame, StateFrame};
+use eme_request::{EMEJump, EMEPlayback, EMEPosition};
+use eme_response::{EMEEvent, EMERequestFailure};
 use hands::{
     hand_types::{CCUpdateData, COMPair},
     MAX_HAND_VELOCITY, VELOCITY_MAPPING_TENSION, VELOCITY_THRESHOLD,
 };
-use midi_cc_attachments::build_midi_cc_attachments;
+use latch::{CCLatch, LatchEvent};
+use axis_lock::{AxisLock, LockedAxis};
+use deadzone::{AxisRange, TrackingSpaceConfig, TrackingSpaceFilter};
+use interpolation::HandExtrapolator;
+use pose::{HandPose, PoseEvent, PoseRecall, PoseScene};
+use takeover::{Pickup, SoftTakeover};
+use midi::timecode::MTCGenerator;
+use midi_cc_attachments::{
+    build_mapped_cc_attachments, build_midi_cc_attachments,
+};
 use midi_types::*;
 use rand::seq::IndexedRandom;
+use sender::MIDIProtocol;
+use snapshot::ParameterSnapshot;
 use state::ParameterState;
+use tempo::DEFAULT_TEMPO_CONFIDENCE_THRESHOLD;
 
+use crate::util::interp;
 use std::cell::RefCell;
+use std::cmp::Reverse;
 
 const VELOCITY_EPSILON: f64 = 0.001;
 
 const PINCH_ENTER_THRESHOLD: f64 = 0.85;
 const PINCH_RELEASE_THRESHOLD: f64 = 0.60;
-const MODE_PINCH_RANGE_FROM_TOP: f64 = 0.20;
-const MODE_PINCH_X_DEADZONE: f64 = 0.35;
+const MODE_PINCH_X_RANGE: AxisRange = AxisRange::new(0.35, 0.65);
+const MODE_PINCH_Y_RANGE: AxisRange = AxisRange::new(0.0, 0.20);
 
 const MODE_CHANGE_MIDI_NOTE: u8 = 16;
 
 // TODO: change this to be appropriate
 const PINCH_TIME_GOAL_SECS: f64 = 1.0;
 
-const MIN_MODE_UPDATE_TIME: f64 = 20.0;
-const MAX_MODE_UPDATE_TIME: f64 = 45.0;
+// NOTE(jamie): roughly 20-45 seconds at the default tempo.
+const MIN_MODE_UPDATE_BARS: u32 = 10;
+const MAX_MODE_UPDATE_BARS: u32 = 22;
 const SWITCH_GESTURE_MODE_UPDATE_TIME: f64 = 0.2;
 const SWITCH_GESTURE_COOLDOWN: f64 = MODE_SWEEP_TIME * 1.0;
 
 const MODE_SWEEP_TIME: f64 = 1.0;
 
+/// Gesture tempo estimates closer than this to the current tempo are ignored.
+const MIN_TEMPO_CHANGE_BPM: f64 = 2.0;
+/// The smallest tempo change which the intensity arc sends, so that the EME
+/// isn't sent a new tempo on every update.
+const ARC_TEMPO_STEP_BPM: f64 = 0.5;
+
 fn velocity_map(input: f64, tension: f64, threshold: f64) -> f64 {
     let x = input.clamp(0.0, 1.0);
     let t = threshold.clamp(0.0, 1.0);
@@ -81,6 +107,30 @@ fn get_random_other(mut mode: Mode) -> Mode {
     mode
 }
 
+/// Returns a random mode from `preferred` which is neither `mode` nor
+/// `previous_mode` if possible, falling back to any other mode.
+fn get_preferred_mode_other_than(
+    mode: Mode,
+    previous_mode: Mode,
+    preferred: &[Mode],
+) -> Mode {
+    let fresh: Vec<Mode> = preferred
+        .iter()
+        .copied()
+        .filter(|m| *m != mode && *m != previous_mode)
+        .collect();
+
+    if let Some(m) = fresh.choose(&mut rand::rng()) {
+        return *m;
+    }
+
+    if let Some(m) = preferred.iter().find(|m| **m != mode) {
+        return *m;
+    }
+
+    get_random_mode_other_than(mode, previous_mode)
+}
+
 fn get_random_mode_other_than(mut mode: Mode, previous_mode: Mode) -> Mode {
     let init = mode;
     mode = Mode::random();
@@ -92,9 +142,46 @@ fn get_random_mode_other_than(mut mode: Mode, previous_mode: Mode) -> Mode {
     mode
 }
 
+/// Sends each of `pending` with `send`, which returns the number of bytes it
+/// took, until `bytes` exceeds [`MAX_MIDI_BUFFER_SIZE_BYTES`]. The CCs with
+/// the highest priority are sent first, so that they are never the ones
+/// truncated by the byte budget. Returns the CCs which weren't sent.
+fn send_ccs_by_priority(
+    pending: &mut [MIDICCIndex],
+    attachments: &HashMap<MIDICCIndex, MIDICCAttachment>,
+    bytes: &mut usize,
+    mut send: impl FnMut(MIDICCIndex) -> usize,
+) -> Vec<MIDICCIndex> {
+    pending.sort_unstable_by_key(|idx| {
+        let priority = attachments
+            .get(idx)
+            .map_or(CCPriority::default(), |att| att.priority());
+
+        (Reverse(priority), *idx)
+    });
+
+    let mut truncated = Vec::new();
+
+    for &idx in pending.iter() {
+        if *bytes > MAX_MIDI_BUFFER_SIZE_BYTES {
+            truncated.push(idx);
+            continue;
+        }
+
+        *bytes += send(idx);
+    }
+
+    truncated
+}
+
 #[allow(clippy::struct_excessive_bools)]
 pub(super) struct ParameterUpdater {
     senders: ParameterSenders,
+    /// Anything the EME reports back, such as failed requests.
+    eme_events: CCReceiver<EMEEvent>,
+    last_eme_failure: Option<(EMERequestFailure, Instant)>,
+    /// The arrangements which the EME has reported that it can play.
+    eme_arrangements: Vec<String>,
 
     midi_bank: RefCell<MIDIParameterBank>,
 
@@ -102,8 +189,33 @@ pub(super) struct ParameterUpdater {
     updated_note_indices: HashSet<MIDINoteIndex>,
 
     gesture_data: triple_buffer::Output<RawHandPairCOM>,
+    /// The envelopes of the audio output's perceptual bands.
+    band_energies: triple_buffer::Output<Vec<f64>>,
+    /// The latest band envelopes, from lowest to highest.
+    audio_bands: Vec<f64>,
+    /// The latest state, which is published to visualizers.
+    state_frames: triple_buffer::Input<StateFrame>,
 
     hands: RawHandPairCOM,
+    /// The most recently received hands, before any processing.
+    received_hands: RawHandPairCOM,
+    /// Estimates each hand's position between received frames.
+    extrapolators: [HandExtrapolator; 2],
+    /// The user's calibrated reach, which hand positions are rescaled to.
+    reach: ReachExtents,
+    /// Dead-zones and hysteresis applied to hand positions before mapping.
+    tracking_filter: TrackingSpaceFilter,
+    /// The tracking configuration passed via arguments, which is used unless
+    /// the active profile overrides it.
+    base_tracking_config: TrackingSpaceConfig,
+    /// Scales hand velocity before it is mapped.
+    velocity_sensitivity: f64,
+    /// Scales how long gestures must be held for.
+    gesture_hold_scale: f64,
+    /// The modes which automatic mode changes choose from.
+    preferred_modes: Vec<Mode>,
+    /// Axis locks for the first and second hands.
+    axis_locks: [AxisLock; 2],
     prev_com: COMPair,
     state: ParameterState,
 
@@ -116,14 +228,26 @@ pub(super) struct ParameterUpdater {
 
     eme_is_playing: bool,
     eme_arrangement: String,
+    /// The EME's master volume, if it has been set.
+    eme_volume: Option<f32>,
+    /// Whether the EME still needs to be sent the full state of a restored
+    /// snapshot, as the request channel was full.
+    eme_sync_pending: bool,
 
     cc_attachments: RefCell<HashMap<MIDICCIndex, MIDICCAttachment>>,
+    /// Attachments which target internal audio parameters.
+    audio_attachments: RefCell<Vec<AudioParameterAttachment>>,
+    audio_controls: AudioControls,
 
     time_tracker: Instant,
     computed_delta_time: bool,
     time: f32,
 
     midi_bytes: usize,
+    /// The number of MIDI bytes sent in the most recent tick.
+    last_tick_midi_bytes: usize,
+    /// The CCs which didn't fit in the most recent tick's MIDI buffer.
+    truncated_cc_indices: Vec<MIDICCIndex>,
 
     pinch_start_time: Instant,
     is_pinched: bool,
@@ -132,11 +256,31 @@ pub(super) struct ParameterUpdater {
     mode_change_midi_message: Option<MIDIMessage>,
     mode_change_posted: bool,
 
-    mode_change_time: Instant,
-    mode_change_time_goal: f64,
+    transport: Transport,
+    /// Generates MIDI Time Code from the transport, if it is sent.
+    mtc: Option<MTCGenerator>,
+    /// The bar at which the next mode change should start, if any.
+    mode_change_bar: Option<u32>,
+
+    tempo_estimator: Option<GestureTempoEstimator>,
+    /// A tempo change from the gesture estimator or the intensity arc which
+    /// hasn't been collected.
+    tempo_change: Option<f64>,
+
+    /// Biases mode changes, the tempo and the intensity CC over the session.
+    intensity_arc: Option<IntensityArc>,
+    /// How long the transport has run since it started, in seconds.
+    arc_elapsed: f64,
+    /// The tempo which the intensity arc biases.
+    arc_base_bpm: f64,
 
     mode_sweep_time: Instant,
     mode_sweep_active: bool,
+    /// The mode which the current sweep changes to, which is chosen when the
+    /// sweep starts so that the EME can move towards it.
+    next_mode: Option<Mode>,
+    /// The EME position when the current sweep started.
+    eme_sweep_start: Vec2,
 
     switch_gesture_cooldown: Instant,
     switch_gesture_posted: bool,
@@ -144,6 +288,14 @@ pub(super) struct ParameterUpdater {
     switch_gesture_time: Instant,
     switch_gesture_time_goal: f64,
 
+    latch: CCLatch,
+    takeover: SoftTakeover,
+    poses: PoseRecall,
+
+    /// The protocol which the MIDI destination receives, which decides the
+    /// resolution of CCs.
+    midi_protocol: MIDIProtocol,
+
     debug_mode: bool,
     print_updates: bool,
     auto_change_mode: bool,
@@ -152,11 +304,38 @@ pub(super) struct ParameterUpdater {
 impl ParameterUpdater {
     pub fn new(
         senders: ParameterSenders,
+        eme_events: CCReceiver<EMEEvent>,
         gesture_data: triple_buffer::Output<RawHandPairCOM>,
+        band_energies: triple_buffer::Output<Vec<f64>>,
+        state_frames: triple_buffer::Input<StateFrame>,
+        audio_controls: AudioControls,
         args: &Arguments,
     ) -> Self {
+        let tracking_config = args
+            .tracking_config_path
+            .as_deref()
+            .map_or_else(
+                || Ok(TrackingSpaceConfig::default()),
+                TrackingSpaceConfig::from_file,
+            )
+            .unwrap_or_else(|e| {
+                // reported by the config validator
+                eprintln!("failed to load tracking config: {e}");
+                TrackingSpaceConfig::default()
+            });
+
+        let cc_attachments =
+            build_mapped_cc_attachments(args.mapping.as_deref())
+                .unwrap_or_else(|e| {
+                    eprintln!("failed to load mapping template: {e}");
+                    build_midi_cc_attachments()
+                });
+
         let s = Self {
             senders,
+            eme_events,
+            last_eme_failure: None,
+            eme_arrangements: Vec::new(),
 
             midi_bank: RefCell::new(MIDIParameterBank::new()),
 
@@ -168,8 +347,20 @@ impl ParameterUpdater {
             ),
 
             gesture_data,
+            band_energies,
+            audio_bands: Vec::new(),
+            state_frames,
 
             hands: RawHandPairCOM::default(),
+            received_hands: RawHandPairCOM::default(),
+            extrapolators: [HandExtrapolator::new(); 2],
+            reach: ReachExtents::default(),
+            tracking_filter: TrackingSpaceFilter::new(tracking_config),
+            base_tracking_config: tracking_config,
+            velocity_sensitivity: 1.0,
+            gesture_hold_scale: 1.0,
+            preferred_modes: Vec::new(),
+            axis_locks: [AxisLock::new(); 2],
             prev_com: COMPair::default(),
             state: ParameterState::default(),
 
@@ -184,14 +375,20 @@ impl ParameterUpdater {
             eme_is_playing: false,
 
             eme_arrangement: String::new(),
+            eme_volume: None,
+            eme_sync_pending: false,
 
-            cc_attachments: RefCell::new(build_midi_cc_attachments()),
+            cc_attachments: RefCell::new(cc_attachments),
+            audio_attachments: RefCell::new(build_audio_attachments()),
+            audio_controls,
 
             time_tracker: Instant::now(),
             computed_delta_time: false,
 
             time: 0.0,
             midi_bytes: 0,
+            last_tick_midi_bytes: 0,
+            truncated_cc_indices: Vec::new(),
 
             pinch_start_time: Instant::now(),
             is_pinched: false,
@@ -200,11 +397,25 @@ impl ParameterUpdater {
             mode_change_midi_message: None,
             mode_change_posted: false,
 
-            mode_change_time: Instant::now(),
-            mode_change_time_goal: MAX_MODE_UPDATE_TIME,
+            transport: Transport::default(),
+            mtc: args.mtc_rate.map(MTCGenerator::new),
+            mode_change_bar: args
+                .auto_change_mode
+                .then_some(MAX_MODE_UPDATE_BARS),
+
+            tempo_estimator: args
+                .gesture_tempo
+                .then(GestureTempoEstimator::new),
+            tempo_change: None,
+
+            intensity_arc: args.intensity_arc.clone(),
+            arc_elapsed: 0.0,
+            arc_base_bpm: DEFAULT_BPM,
 
             mode_sweep_time: Instant::now(),
             mode_sweep_active: false,
+            next_mode: None,
+            eme_sweep_start: vec2(0.0, 0.5),
 
             switch_gesture_cooldown: Instant::now(),
             switch_gesture_posted: false,
@@ -212,6 +423,12 @@ impl ParameterUpdater {
             switch_gesture_time: Instant::now(),
             switch_gesture_time_goal: SWITCH_GESTURE_MODE_UPDATE_TIME,
 
+            latch: CCLatch::new(),
+            takeover: SoftTakeover::new(),
+            poses: PoseRecall::new(args.pose_tolerance),
+
+            midi_protocol: args.midi_protocol,
+
             debug_mode: args.debug,
             auto_change_mode: args.auto_change_mode,
             print_updates: args.print,
@@ -226,12 +443,17 @@ impl ParameterUpdater {
         let dt = self.delta_time();
         self.time += dt;
 
+        self.update_intensity_arc(dt);
+
         self.try_queue_mode_change_note_off();
+        self.handle_eme_events();
 
-        if self.auto_change_mode
-            && self.mode_change_time.elapsed().as_secs_f64()
-                >= self.mode_change_time_goal
+        if let Some(bar) = self.mode_change_bar
+            && !self.mode_sweep_active
+            && self.transport.is_running()
+            && self.transport.position().bar >= bar
         {
+            self.mode_change_bar = None;
             self.start_mode_change();
         }
 
@@ -256,9 +478,13 @@ impl ParameterUpdater {
         }
 
         self.update_hands(dt);
+        self.update_audio_bands();
         self.update_gestures();
+        self.update_latch();
+        self.update_poses(dt);
 
         let mut attachments = self.cc_attachments.borrow_mut();
+        let mut active_ccs = Vec::with_capacity(attachments.len());
 
         for (idx, attachment) in attachments.iter_mut() {
             if !attachment.is_active_for(&self.state) {
@@ -270,12 +496,39 @@ impl ParameterUpdater {
             let mut borrow = self.midi_bank.borrow_mut();
             let cc = borrow.get_cc_mut(idx);
             attachment.callback(&self.get_cc_update_data(), &mut cc.value, dt);
+
+            // the callback still runs so that smoothing stays up-to-date
+            if attachment.is_latchable()
+                && let Some(value) = self.latch.value_for(idx)
+            {
+                cc.value = value;
+            }
+            else {
+                if attachment.has_soft_takeover() {
+                    cc.value = self.takeover.process(idx, cc.value);
+                }
+
+                if attachment.is_latchable() {
+                    cc.value = self.poses.morph(idx, cc.value);
+                }
+            }
+
+            active_ccs.push((idx.channel as u8, idx.cc as u8, cc.value));
         }
 
         drop(attachments);
 
+        self.update_audio_attachments(dt);
+
         self.send_updated_midi_messages();
+        self.send_time_code();
+
+        if self.eme_sync_pending {
+            self.sync_eme();
+        }
+
         self.send_eme_message();
+        self.publish_state_frame(active_ccs);
 
         if self.time > 1.0 {
             println!("transmitted {} bytes of MIDI data", self.midi_bytes);
@@ -284,10 +537,33 @@ impl ParameterUpdater {
         }
     }
 
+    /// Updates the attachments which target internal audio parameters.
+    fn update_audio_attachments(&self, dt: f32) {
+        let update_data = self.get_cc_update_data();
+
+        for attachment in self.audio_attachments.borrow_mut().iter_mut() {
+            attachment.update(
+                &update_data,
+                &self.state,
+                &self.audio_controls,
+                dt,
+            );
+        }
+    }
+
     pub fn reset_delta_time(&mut self) {
         self.computed_delta_time = false;
     }
 
+    /// Replaces the CC attachments, and sends each of their values.
+    pub fn set_cc_attachments(
+        &mut self,
+        attachments: HashMap<MIDICCIndex, MIDICCAttachment>,
+    ) {
+        self.cc_attachments.replace(attachments);
+        self.mark_active_midi_ccs_for_update();
+    }
+
     pub fn mark_active_midi_ccs_for_update(&self) {
         for channel in 0..NUM_MIDI_CHANNELS {
             for cc in 0..NUM_MIDI_CCS {
@@ -365,10 +641,453 @@ impl ParameterUpdater {
         }
     }
 
+    /// Applies a user profile's calibration and settings.
+    pub fn apply_profile(&mut self, profile: &UserProfile) {
+        self.reach = profile.reach;
+        self.tracking_filter = TrackingSpaceFilter::new(
+            profile.tracking.unwrap_or(self.base_tracking_config),
+        );
+
+        self.velocity_sensitivity = profile.sensitivity;
+        self.gesture_hold_scale = profile.gesture_hold_scale;
+        self.switch_gesture_time_goal =
+            SWITCH_GESTURE_MODE_UPDATE_TIME * profile.gesture_hold_scale;
+        self.latch.set_hold_time_scale(profile.gesture_hold_scale);
+
+        self.preferred_modes.clone_from(&profile.preferred_modes);
+
+        self.arm_soft_takeover();
+
+        if self.print_updates || self.debug_mode {
+            println!("applied profile \"{}\"", profile.name);
+        }
+    }
+
+    pub fn set_reach_extents(&mut self, extents: ReachExtents) {
+        self.reach = extents;
+
+        // positions will jump to the new range
+        self.arm_soft_takeover();
+    }
+
+    pub const fn reach_extents(&self) -> ReachExtents {
+        self.reach
+    }
+
+    /// The tracking config which is used by profiles without their own.
+    pub const fn base_tracking_config(&self) -> TrackingSpaceConfig {
+        self.base_tracking_config
+    }
+
+    /// Captures the mode, transport, calibration, CC values and poses.
+    pub fn snapshot(&self) -> ParameterSnapshot {
+        let bank = self.midi_bank.borrow();
+
+        let mut cc_values: Vec<(MIDICCIndex, f32)> = self
+            .cc_attachments
+            .borrow()
+            .keys()
+            .map(|idx| (*idx, bank.get_cc(idx).value))
+            .collect();
+
+        cc_values.sort_unstable_by_key(|(idx, _)| *idx);
+
+        ParameterSnapshot {
+            mode: self.mode,
+            previous_mode: self.previous_mode,
+            eme_arrangement: self.eme_arrangement.clone(),
+            eme_playing: self.eme_is_playing,
+            eme_position: self.curr_eme_pos,
+            eme_volume: self.eme_volume,
+
+            // the arc's bias is reapplied once restored
+            bpm: self.arc_base_bpm,
+            beats_per_bar: self.transport.beats_per_bar(),
+            transport_beats: self.transport.beats(),
+
+            reach: self.reach,
+            cc_values,
+            poses: self.poses.scenes().to_vec(),
+        }
+    }
+
+    /// Restores the state captured by [`snapshot()`](Self::snapshot).
+    pub fn restore_snapshot(&mut self, snapshot: &ParameterSnapshot) {
+        self.mode = snapshot.mode;
+        self.previous_mode = snapshot.previous_mode;
+
+        self.transport.set_beats_per_bar(snapshot.beats_per_bar);
+        self.arc_base_bpm = snapshot.bpm.clamp(MIN_BPM, MAX_BPM);
+        self.transport.set_bpm(self.arc_base_bpm);
+        self.transport.seek(snapshot.transport_beats);
+        self.send_song_position();
+
+        if !snapshot.eme_arrangement.is_empty() {
+            self.eme_arrangement.clone_from(&snapshot.eme_arrangement);
+        }
+
+        self.eme_is_playing = snapshot.eme_playing;
+        self.curr_eme_pos = snapshot.eme_position;
+        self.eme_volume = snapshot.eme_volume.or(self.eme_volume);
+
+        // the EME is sent everything at once, so that it can't be left
+        // playing a different arrangement or tempo to the one restored
+        self.sync_eme();
+
+        self.reach = snapshot.reach;
+        self.poses.set_scenes(snapshot.poses.clone());
+
+        let mut bank = self.midi_bank.borrow_mut();
+        for (idx, value) in &snapshot.cc_values {
+            bank.get_cc_mut(idx).value = *value;
+        }
+        drop(bank);
+
+        self.mark_active_midi_ccs_for_update();
+
+        // the restored values are held until the hands reach them
+        self.arm_soft_takeover();
+
+        if self.print_updates || self.debug_mode {
+            println!(
+                "restored mode {:?} at {}",
+                self.mode,
+                self.transport.position()
+            );
+        }
+    }
+
+    /// The most recent EME request failure, if it occurred within the last
+    /// `max_age` seconds.
+    pub fn last_eme_failure(&self, max_age: f64) -> Option<&EMERequestFailure> {
+        self.last_eme_failure
+            .as_ref()
+            .filter(|(_, time)| time.elapsed().as_secs_f64() < max_age)
+            .map(|(failure, _)| failure)
+    }
+
+    fn handle_eme_events(&mut self) {
+        let events: Vec<EMEEvent> = self.eme_events.try_iter().collect();
+
+        for event in events {
+            match event {
+                EMEEvent::RequestFailed(failure) => {
+                    self.on_eme_request_failed(failure);
+                }
+                EMEEvent::Arrangements(names) => {
+                    self.on_eme_arrangements(names);
+                }
+            }
+        }
+    }
+
+    /// Checks that the current arrangement is one which the EME knows, and
+    /// switches to the first one it knows if not.
+    fn on_eme_arrangements(&mut self, names: Vec<String>) {
+        if self.print_updates || self.debug_mode {
+            println!("EME arrangements: {names:?}");
+        }
+
+        self.eme_arrangements = names;
+
+        if self.eme_arrangements.contains(&self.eme_arrangement) {
+            return;
+        }
+
+        let Some(first) = self.eme_arrangements.first().cloned()
+        else {
+            return;
+        };
+
+        eprintln!(
+            "EME has no arrangement \"{}\", using \"{first}\" instead",
+            self.eme_arrangement
+        );
+
+        self.set_eme_arrangement(&first);
+
+        if self.eme_is_playing {
+            self.set_eme_playback(true);
+        }
+    }
+
+    /// The names of the EME's arrangements, if it has reported them.
+    pub fn eme_arrangements(&self) -> &[String] {
+        &self.eme_arrangements
+    }
+
+    /// Moves to the arrangement after the current one, if the EME has
+    /// reported more than one.
+    fn cycle_eme_arrangement(&mut self) {
+        if self.eme_arrangements.len() < 2 {
+            return;
+        }
+
+        let next_idx = self
+            .eme_arrangements
+            .iter()
+            .position(|name| *name == self.eme_arrangement)
+            .map_or(0, |idx| (idx + 1) % self.eme_arrangements.len());
+
+        let next = self.eme_arrangements[next_idx].clone();
+        self.set_eme_arrangement(&next);
+    }
+
+    fn on_eme_request_failed(&mut self, failure: EMERequestFailure) {
+        eprintln!("EME request failed: {failure}");
+
+        // an arrangement the EME doesn't know leaves it with nothing to
+        // play, so fall back to one which it should know
+        let fallback = self
+            .eme_arrangements
+            .first()
+            .filter(|_| {
+                !self
+                    .eme_arrangements
+                    .iter()
+                    .any(|name| name == DEFAULT_EME_ARRANGEMENT_NAME)
+            })
+            .cloned()
+            .unwrap_or_else(|| String::from(DEFAULT_EME_ARRANGEMENT_NAME));
+
+        if failure
+            .request
+            .arrangement
+            .as_ref()
+            .is_some_and(|name| *name != fallback)
+        {
+            self.set_eme_arrangement(&fallback);
+
+            if self.eme_is_playing {
+                self.set_eme_playback(true);
+            }
+        }
+
+        self.last_eme_failure = Some((failure, Instant::now()));
+    }
+
+    /// Sets the EME's tempo, in beats per minute.
+    pub fn set_eme_tempo(&mut self, bpm: f64) {
+        self.send_eme_request(EMERequest::new().with_tempo(bpm));
+    }
+
+    /// Sets the EME's master volume, from `0` to `1`.
+    pub fn set_eme_volume(&mut self, volume: f32) {
+        self.eme_volume = Some(volume.clamp(0.0, 1.0));
+        self.send_eme_request(EMERequest::new().with_volume(volume));
+    }
+
+    /// Sends the arrangement, playback state, position, tempo and volume to
+    /// the EME in a single request. If the request can't be sent, it is
+    /// retried on the next update.
+    fn sync_eme(&mut self) {
+        let mut request = EMERequest::new().with_tempo(self.transport.bpm());
+
+        if !self.eme_arrangement.is_empty() {
+            request = request.with_arrangement(&self.eme_arrangement);
+        }
+
+        request.playback = Some(if self.eme_is_playing {
+            EMEPlayback::Start
+        }
+        else {
+            EMEPlayback::Stop
+        });
+        request.position =
+            Some(EMEPosition::new(self.curr_eme_pos.x, self.curr_eme_pos.y));
+        request.volume = self.eme_volume;
+
+        if self.print_updates || self.debug_mode {
+            println!("syncing eme with {request:?}");
+        }
+
+        let Ok(sender) = self.senders.eme_sender.lock()
+        else {
+            self.eme_sync_pending = true;
+            return;
+        };
+
+        self.eme_sync_pending = sender.is_full();
+
+        if !self.eme_sync_pending
+            && let Err(e) = sender.try_send(request)
+        {
+            eprintln!("failed to send EME request: {e}");
+        }
+    }
+
+    /// Jumps the EME to a section or marker, crossfading over `crossfade`
+    /// seconds if provided.
+    pub fn jump_eme_to(&mut self, jump: EMEJump, crossfade: Option<f32>) {
+        let mut request = EMERequest::new().with_jump(jump);
+        request.crossfade = crossfade.map(|secs| secs.max(0.0));
+
+        self.send_eme_request(request);
+    }
+
+    /// Sets how long the EME crossfades over when the arrangement or
+    /// position jumps.
+    pub fn set_eme_crossfade(&mut self, secs: f32) {
+        self.send_eme_request(EMERequest::new().with_crossfade(secs));
+    }
+
+    fn send_eme_request(&self, request: EMERequest) {
+        if self.print_updates || self.debug_mode {
+            println!("adding eme request {request:?}");
+        }
+
+        if let Ok(sender) = self.senders.eme_sender.lock()
+            && !sender.is_full()
+            && let Err(e) = sender.try_send(request)
+        {
+            eprintln!("failed to send EME request: {e}");
+        }
+    }
+
+    /// Starts the transport from the beginning, and sends its position.
+    pub fn start_transport(&mut self) {
+        self.transport.start();
+        self.arc_elapsed = 0.0;
+        self.send_song_position();
+    }
+
+    /// Continues the transport from where it was stopped, and sends its
+    /// position.
+    pub fn continue_transport(&mut self) {
+        self.transport.continue_playback();
+        self.send_song_position();
+    }
+
+    pub fn stop_transport(&mut self) {
+        self.transport.stop();
+    }
+
+    pub fn parameter_bank_diff(&self) -> ParameterBankDiff {
+        ParameterBankDiff::new(
+            &self.midi_bank.borrow(),
+            &self.updated_cc_indices.borrow(),
+            &self.truncated_cc_indices,
+            &self.cc_attachments.borrow(),
+            self.last_tick_midi_bytes,
+        )
+    }
+
+    pub fn latched_cc_indices(&self) -> Vec<MIDICCIndex> {
+        self.latch.latched_indices()
+    }
+
+    pub fn pickups(&self) -> Vec<Pickup> {
+        self.takeover.pickups()
+    }
+
+    pub fn musical_position(&self) -> MusicalPosition {
+        self.transport.position()
+    }
+
+    /// Sets the tempo of the transport. If there is an intensity arc, this
+    /// is the tempo which it biases.
+    pub fn set_bpm(&mut self, bpm: f64) {
+        self.arc_base_bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        let biased = self.arc_biased_bpm();
+
+        if biased != self.arc_base_bpm {
+            self.tempo_change = Some(biased);
+        }
+
+        self.transport.set_bpm(biased);
+        self.set_eme_tempo(biased);
+    }
+
+    /// The session's planned intensity, if it follows an intensity arc.
+    pub fn intensity(&self) -> Option<f64> {
+        self.intensity_arc
+            .as_ref()
+            .map(|arc| arc.intensity_at(self.arc_elapsed))
+    }
+
+    /// The base tempo, biased by the intensity arc if there is one.
+    fn arc_biased_bpm(&self) -> f64 {
+        let scale = self
+            .intensity_arc
+            .as_ref()
+            .zip(self.intensity())
+            .map_or(1.0, |(arc, intensity)| arc.tempo_scale(intensity));
+
+        (self.arc_base_bpm * scale).clamp(MIN_BPM, MAX_BPM)
+    }
+
+    /// Advances the intensity arc while the transport runs, and moves the
+    /// tempo towards the arc's bias in small steps.
+    fn update_intensity_arc(&mut self, dt: f32) {
+        if self.intensity_arc.is_none() || !self.transport.is_running() {
+            return;
+        }
+
+        self.arc_elapsed += dt as f64;
+
+        let bpm = self.arc_biased_bpm();
+
+        if (bpm - self.transport.bpm()).abs() >= ARC_TEMPO_STEP_BPM {
+            self.transport.set_bpm(bpm);
+            self.set_eme_tempo(bpm);
+            self.tempo_change = Some(bpm);
+        }
+    }
+
+    /// Returns the latest tempo set by the gesture tempo estimator or the
+    /// intensity arc, if it has changed since this was last called.
+    pub fn take_tempo_change(&mut self) -> Option<f64> {
+        self.tempo_change.take()
+    }
+
+    /// Schedules a mode change to start on the downbeat `num_bars` bars from
+    /// now.
+    pub fn schedule_mode_change_in_bars(&mut self, num_bars: u32) {
+        let bar = self.transport.position().bar;
+        self.mode_change_bar = Some(bar + num_bars.max(1));
+    }
+
+    /// How far the current mode change has progressed. The mode switches
+    /// halfway through the sweep, so the transition is from the previous
+    /// mode after that.
+    pub fn mode_transition(&self) -> ModeTransition {
+        if !self.mode_sweep_active {
+            return ModeTransition::settled(self.mode);
+        }
+
+        let progress = (self.mode_sweep_time.elapsed().as_secs_f64()
+            / MODE_SWEEP_TIME)
+            .clamp(0.0, 1.0);
+
+        match self.next_mode {
+            Some(next) => {
+                ModeTransition { from: self.mode, to: next, progress }
+            }
+            None => ModeTransition {
+                from: self.previous_mode,
+                to: self.mode,
+                progress,
+            },
+        }
+    }
+
+    pub const fn hand_velocities(&self) -> (f32, f32) {
+        self.hand_velocities
+    }
+
+    /// The latest perceptual band energies of the output, from lowest to
+    /// highest.
+    pub fn audio_bands(&self) -> &[f64] {
+        &self.audio_bands
+    }
+
     pub fn start_mode_change(&mut self) {
         self.mode_sweep_time = Instant::now();
         self.mode_sweep_active = true;
 
+        self.next_mode = Some(self.choose_next_mode());
+        self.eme_sweep_start = self.curr_eme_pos;
+
         self.set_midi_note(
             MODE_CHANGE_MIDI_NOTE, MIDI_CHANNEL_1, MAX_NOTE_VELOCITY, true,
         );
@@ -380,7 +1099,10 @@ impl ParameterUpdater {
 
     fn switch_mode(&mut self) {
         let mode = self.mode;
-        self.mode = get_random_mode_other_than(self.mode, self.previous_mode);
+        self.mode = self
+            .next_mode
+            .take()
+            .unwrap_or_else(|| self.choose_next_mode());
         self.previous_mode = mode;
 
         let note = self.mode.get_midi_note_value();
@@ -391,22 +1113,64 @@ impl ParameterUpdater {
 
         self.set_midi_note(note, MIDI_CHANNEL_1, MAX_NOTE_VELOCITY, true);
 
+        self.cycle_eme_arrangement();
+        self.arm_soft_takeover();
+
         if self.print_updates || self.debug_mode {
             println!("mode set to {:?}", self.mode);
         }
 
         if self.auto_change_mode {
-            self.mode_change_time_goal = if self.debug_mode {
-                MIN_MODE_UPDATE_TIME
+            let num_bars = if self.debug_mode {
+                MIN_MODE_UPDATE_BARS
+            }
+            else if let Some(arc) = &self.intensity_arc
+                && let Some(intensity) = self.intensity()
+            {
+                arc.mode_change_bars(
+                    intensity,
+                    MIN_MODE_UPDATE_BARS,
+                    MAX_MODE_UPDATE_BARS,
+                )
             }
             else {
-                random_range(MIN_MODE_UPDATE_TIME, MAX_MODE_UPDATE_TIME)
+                random_range(MIN_MODE_UPDATE_BARS, MAX_MODE_UPDATE_BARS)
             };
 
-            self.mode_change_time = Instant::now();
+            self.schedule_mode_change_in_bars(num_bars);
         }
     }
 
+    /// Chooses the mode to change to, biased by the intensity arc if there
+    /// is one.
+    fn choose_next_mode(&self) -> Mode {
+        if let Some(arc) = &self.intensity_arc
+            && let Some(intensity) = self.intensity()
+        {
+            let pool = if self.preferred_modes.is_empty() {
+                &Mode::ALL[..]
+            }
+            else {
+                &self.preferred_modes[..]
+            };
+
+            // the previous mode isn't excluded here, as that would often
+            // leave only one mode to choose from
+            let candidates: Vec<Mode> =
+                pool.iter().copied().filter(|m| *m != self.mode).collect();
+
+            if let Some(mode) = arc.choose_mode(&candidates, intensity) {
+                return mode;
+            }
+        }
+
+        get_preferred_mode_other_than(
+            self.mode,
+            self.previous_mode,
+            &self.preferred_modes,
+        )
+    }
+
     // fn note_mut(
     //     &mut self,
     //     channel: usize,
@@ -419,11 +1183,36 @@ impl ParameterUpdater {
     // }
 
     fn update_hands(&mut self, dt: f32) {
-        if !self.gesture_data.updated() {
-            return;
+        let is_new_frame = self.gesture_data.updated();
+
+        if is_new_frame {
+            self.received_hands = *self.gesture_data.read();
+
+            self.extrapolators[0].receive(self.received_hands.com.first);
+            self.extrapolators[1].receive(self.received_hands.com.second);
         }
 
-        self.hands = *self.gesture_data.read();
+        // positions are re-estimated on every update, so that they keep
+        // moving between frames
+        self.hands = self.received_hands;
+
+        let reach = self.reach;
+        let first = self.extrapolators[0].estimate();
+        let second = self.extrapolators[1].estimate();
+
+        self.hands.com.first = self
+            .tracking_filter
+            .process(0, first.map(|pos| reach.normalize(pos)));
+        self.hands.com.second = self
+            .tracking_filter
+            .process(1, second.map(|pos| reach.normalize(pos)));
+
+        self.update_axis_locks();
+
+        // velocity is only measured between received frames
+        if !is_new_frame {
+            return;
+        }
 
         // self.detect_pinch();
 
@@ -434,7 +1223,7 @@ impl ParameterUpdater {
         {
             let dist =
                 f64::abs(dvec2(curr.x, curr.y).distance(dvec2(prev.x, prev.y)));
-            let speed = dist / vel_dt;
+            let speed = dist / vel_dt * self.velocity_sensitivity;
             let normalized = (speed / MAX_HAND_VELOCITY).clamp(0.0, 1.0);
 
             velocity_map(
@@ -466,16 +1255,56 @@ impl ParameterUpdater {
 
         self.velocity_time_point = Instant::now();
 
+        if self.prev_com.first.is_none() && self.hands.com.first.is_some() {
+            self.arm_soft_takeover();
+        }
+
         self.prev_com = self.hands.com;
+
+        self.update_tempo_estimate();
+    }
+
+    fn update_audio_bands(&mut self) {
+        if !self.band_energies.updated() {
+            return;
+        }
+
+        let bands = self.band_energies.read();
+
+        self.audio_bands.clear();
+        self.audio_bands.extend_from_slice(bands);
+    }
+
+    fn update_tempo_estimate(&mut self) {
+        let Some(estimator) = &mut self.tempo_estimator
+        else {
+            return;
+        };
+
+        estimator.push(self.hand_velocities.0 as f64);
+
+        if let Some(bpm) =
+            estimator.confident_bpm(DEFAULT_TEMPO_CONFIDENCE_THRESHOLD)
+            && (bpm - self.transport.bpm()).abs() >= MIN_TEMPO_CHANGE_BPM
+        {
+            if self.print_updates || self.debug_mode {
+                println!("gesture tempo set to {bpm:.1} BPM");
+            }
+
+            self.set_bpm(bpm);
+            self.tempo_change = Some(self.transport.bpm());
+        }
     }
 
     fn get_cc_update_data(&self) -> CCUpdateData {
         CCUpdateData {
             hands: &self.hands,
             velocities: &self.hand_velocities,
+            audio_bands: &self.audio_bands,
             mode_sweep: self.mode_sweep_active.then_some(
                 self.mode_sweep_time.elapsed().as_secs_f64() / MODE_SWEEP_TIME,
             ),
+            intensity: self.intensity(),
         }
     }
 
@@ -507,7 +1336,7 @@ impl ParameterUpdater {
                     .get_note(idx)
                     .to_midi_message(idx.channel as u8);
 
-                bytes += msg.size_bytes();
+                bytes += self.midi_protocol.size_bytes(msg);
 
                 buf.push(msg);
 
@@ -526,26 +1355,39 @@ impl ParameterUpdater {
 
             let mut clear = HashSet::new();
 
-            for idx in self.updated_cc_indices.borrow().iter() {
-                if !threshold_values.contains(idx) {
-                    continue;
-                }
-
-                if bytes > MAX_MIDI_BUFFER_SIZE_BYTES {
-                    break;
-                }
-
-                let mut bank = self.midi_bank.borrow_mut();
-                bank.cache_cc(idx);
-
-                let msg = bank.get_cc(idx).to_midi_message(idx.channel as u8);
-
-                bytes += msg.size_bytes();
-
-                buf.push(msg);
-
-                clear.insert(*idx);
-            }
+            let mut pending: Vec<MIDICCIndex> = self
+                .updated_cc_indices
+                .borrow()
+                .intersection(&threshold_values)
+                .copied()
+                .collect();
+
+            // these stay marked, so are sent in a later tick
+            self.truncated_cc_indices = send_ccs_by_priority(
+                &mut pending,
+                &self.cc_attachments.borrow(),
+                &mut bytes,
+                |idx| {
+                    let mut bank = self.midi_bank.borrow_mut();
+                    bank.cache_cc(&idx);
+
+                    let param = bank.get_cc(&idx);
+                    let channel = idx.channel as u8;
+
+                    // UMP destinations receive CCs at full resolution
+                    let msg = match self.midi_protocol {
+                        MIDIProtocol::MIDI1 => param.to_midi_message(channel),
+                        MIDIProtocol::UMP => {
+                            param.to_high_res_midi_message(channel)
+                        }
+                    };
+
+                    buf.push(msg);
+                    clear.insert(idx);
+
+                    self.midi_protocol.size_bytes(msg)
+                },
+            );
 
             for idx in clear {
                 self.updated_cc_indices.borrow_mut().remove(&idx);
@@ -560,6 +1402,7 @@ impl ParameterUpdater {
             }
 
             self.midi_bytes += bytes;
+            self.last_tick_midi_bytes = bytes;
 
             // if self.debug_mode {
             //     println!("sent {bytes} bytes of MIDI data for broadcast");
@@ -567,7 +1410,76 @@ impl ParameterUpdater {
         }
     }
 
+    fn send_song_position(&mut self) {
+        let position = self.transport.position();
+
+        // the time code jumps along with the song position
+        if let Some(mtc) = &mut self.mtc {
+            mtc.relocate();
+        }
+
+        if self.print_updates || self.debug_mode {
+            println!("transport at {position}");
+        }
+
+        if let Ok(mut sender) = self.senders.midi_sender.lock() {
+            let msg =
+                MIDIMessage::song_position_pointer(position.song_position());
+
+            if let Err(e) = sender.try_send(vec![msg]) {
+                eprintln!("failed to send song position: {e}");
+            }
+        }
+    }
+
+    /// Sends the MIDI Time Code quarter frames which have come due since the
+    /// last tick, while the transport is running.
+    fn send_time_code(&mut self) {
+        let Some(mtc) = &mut self.mtc
+        else {
+            return;
+        };
+
+        if !self.transport.is_running() {
+            return;
+        }
+
+        let mut messages = Vec::new();
+        mtc.update(self.transport.secs(), &mut messages);
+
+        if messages.is_empty() {
+            return;
+        }
+
+        if let Ok(mut sender) = self.senders.midi_sender.lock()
+            && let Err(e) = sender.try_send(messages)
+        {
+            eprintln!("failed to send time code: {e}");
+        }
+    }
+
+    /// The EME position during a mode sweep, which moves from where the
+    /// sweep started to the new mode's entry point.
+    fn eme_sweep_position(&self) -> Option<Vec2> {
+        if !self.mode_sweep_active {
+            return None;
+        }
+
+        // the mode has already switched halfway through the sweep
+        let target = self.next_mode.unwrap_or(self.mode).eme_entry_point();
+        let start = self.eme_sweep_start;
+
+        let t = self.mode_sweep_time.elapsed().as_secs_f64() / MODE_SWEEP_TIME;
+
+        Some(vec2(
+            interp::cosine(start.x as f64, target.x as f64, t) as f32,
+            interp::cosine(start.y as f64, target.y as f64, t) as f32,
+        ))
+    }
+
     fn send_eme_message(&mut self) {
+        let sweep_pos = self.eme_sweep_position();
+
         if let Ok(mut sender) = self.senders.eme_sender.lock() {
             if sender.is_full() {
                 return;
@@ -575,7 +1487,13 @@ impl ParameterUpdater {
 
             let mut request = EMERequest::new();
 
-            if let Some(com) = self.hands.com.first {
+            // the hands take over again once the sweep has finished
+            if let Some(pos) = sweep_pos {
+                self.curr_eme_pos = pos;
+
+                request.position = Some(EMEPosition::new(pos.x, pos.y));
+            }
+            else if let Some(com) = self.hands.com.first {
                 let v2 = vec2(com.x as f32, com.y as f32);
                 self.curr_eme_pos = map_eme_pos(v2, self.mode);
 
@@ -599,6 +1517,37 @@ impl ParameterUpdater {
         }
     }
 
+    /// Publishes the mode, hands and active CC values for visualizers.
+    fn publish_state_frame(&mut self, mut ccs: Vec<(u8, u8, f32)>) {
+        ccs.sort_unstable_by_key(|&(channel, cc, _)| (channel, cc));
+
+        let (first_openness, second_openness) = self.hands.get_openness();
+        let hand = |com: Option<DVec3>, openness: Option<f64>, velocity| {
+            com.map(|com| HandFrame {
+                com,
+                openness: openness.unwrap_or_default(),
+                velocity,
+            })
+        };
+
+        self.state_frames.write(StateFrame {
+            mode: self.mode.name(),
+            hands: [
+                hand(
+                    self.hands.com.first,
+                    first_openness,
+                    self.hand_velocities.0,
+                ),
+                hand(
+                    self.hands.com.second,
+                    second_openness,
+                    self.hand_velocities.1,
+                ),
+            ],
+            ccs,
+        });
+    }
+
     fn mark_cc_as_updated(&self, idx: MIDICCIndex) {
         self.updated_cc_indices.borrow_mut().insert(idx);
     }
@@ -687,11 +1636,8 @@ impl ParameterUpdater {
             unsafe { self.hands.com.second.unwrap_unchecked() }
         };
 
-        let in_x_livezone = hand_pos.x >= MODE_PINCH_X_DEADZONE
-            && hand_pos.x <= (1.0 - MODE_PINCH_X_DEADZONE);
-        let in_y_livezone = hand_pos.y <= MODE_PINCH_RANGE_FROM_TOP;
-
-        let in_pinch_zone = in_x_livezone && in_y_livezone;
+        let in_pinch_zone = MODE_PINCH_X_RANGE.contains(hand_pos.x)
+            && MODE_PINCH_Y_RANGE.contains(hand_pos.y);
 
         if !self.pinch_at_edge && in_pinch_zone {
             if self.print_updates || self.debug_mode {
@@ -706,7 +1652,12 @@ impl ParameterUpdater {
         if self.pinch_at_edge {
             self.pinch_at_edge = false;
 
-            let goal = if self.debug_mode { 0.0 } else { PINCH_TIME_GOAL_SECS };
+            let goal = if self.debug_mode {
+                0.0
+            }
+            else {
+                PINCH_TIME_GOAL_SECS * self.gesture_hold_scale
+            };
 
             if self.pinch_start_time.elapsed().as_secs_f64() >= goal {
                 self.start_mode_change();
@@ -768,6 +1719,135 @@ impl ParameterUpdater {
         }
     }
 
+    fn update_latch(&mut self) {
+        let is_latch_gesture = self
+            .hands
+            .pair
+            .first
+            .is_some_and(|hand| hand.gesture.is_thumb_up());
+
+        match self.latch.update(is_latch_gesture) {
+            Some(LatchEvent::GestureStarted) => {
+                let snapshot = self.latchable_cc_values();
+                self.latch.set_snapshot(snapshot);
+            }
+            Some(LatchEvent::Latched) => {
+                if self.print_updates || self.debug_mode {
+                    println!("latched CC values");
+                }
+            }
+            Some(LatchEvent::Unlatched) => {
+                self.arm_soft_takeover();
+
+                if self.print_updates || self.debug_mode {
+                    println!("unlatched CC values");
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Stores the first hand's pose along with the current values of the
+    /// latchable CCs. Returns the pose's number.
+    pub fn store_pose(&mut self) -> Result<usize, String> {
+        let pose = self
+            .hands
+            .pair
+            .first
+            .as_ref()
+            .and_then(HandPose::from_hand)
+            .ok_or_else(|| String::from("no hand is being tracked"))?;
+
+        let scene = PoseScene::new(pose, self.latchable_cc_values());
+
+        Ok(self.poses.store(scene) + 1)
+    }
+
+    /// Removes all stored poses.
+    pub fn clear_poses(&mut self) {
+        self.poses.clear();
+    }
+
+    /// Morphs towards the scene of any stored pose which the first hand is
+    /// holding.
+    fn update_poses(&mut self, dt: f32) {
+        let event =
+            self.poses.update(self.hands.pair.first.as_ref(), dt as f64);
+
+        if !(self.print_updates || self.debug_mode) {
+            return;
+        }
+
+        match event {
+            Some(PoseEvent::Matched(idx)) => {
+                println!("matched pose {}", idx + 1);
+            }
+            Some(PoseEvent::Released(idx)) => {
+                println!("released pose {}", idx + 1);
+            }
+            None => {}
+        }
+    }
+
+    /// Constrains each hand's position to a single axis while it makes the
+    /// axis lock gesture.
+    fn update_axis_locks(&mut self) {
+        let gestures = [
+            self.hands.pair.first.is_some_and(|h| h.gesture.is_victory()),
+            self.hands.pair.second.is_some_and(|h| h.gesture.is_victory()),
+        ];
+
+        let (first, first_released) =
+            self.axis_locks[0].process(gestures[0], self.hands.com.first);
+        let (second, second_released) =
+            self.axis_locks[1].process(gestures[1], self.hands.com.second);
+
+        self.hands.com.first = first;
+        self.hands.com.second = second;
+
+        // the held axis jumps back to the live position on release
+        if first_released || second_released {
+            self.arm_soft_takeover();
+
+            if self.print_updates || self.debug_mode {
+                println!("released axis lock");
+            }
+        }
+    }
+
+    /// The axis which hand movement is locked to, if any. The outer option
+    /// is `None` if no lock is active, and the inner one is `None` if the
+    /// axis hasn't been chosen yet.
+    pub fn locked_axis(&self) -> Option<Option<LockedAxis>> {
+        self.axis_locks
+            .iter()
+            .find(|lock| lock.is_active())
+            .map(AxisLock::locked_axis)
+    }
+
+    /// Holds all soft-takeover CCs at their current values until their live
+    /// sources pick them up.
+    fn arm_soft_takeover(&mut self) {
+        let bank = self.midi_bank.borrow();
+
+        for (idx, att) in self.cc_attachments.borrow().iter() {
+            if att.has_soft_takeover() {
+                self.takeover.arm(*idx, bank.get_cc(idx).value);
+            }
+        }
+    }
+
+    fn latchable_cc_values(&self) -> HashMap<MIDICCIndex, f32> {
+        let bank = self.midi_bank.borrow();
+
+        self.cc_attachments
+            .borrow()
+            .iter()
+            .filter(|(_, att)| att.is_latchable())
+            .map(|(idx, _)| (*idx, bank.get_cc(idx).value))
+            .collect()
+    }
+
     fn update_gestures(&mut self) {
         if self.switch_gesture_cooldown.elapsed().as_secs_f64()
             < SWITCH_GESTURE_COOLDOWN
@@ -785,7 +1865,7 @@ impl ParameterUpdater {
             if self.switch_gesture_prev {
                 if !self.switch_gesture_posted
                     && self.switch_gesture_time.elapsed().as_secs_f64()
-                        >= SWITCH_GESTURE_MODE_UPDATE_TIME
+                        >= self.switch_gesture_time_goal
                 {
                     if self.debug_mode {
                         println!("thumb down detected and processed");
@@ -807,3 +1887,68 @@ impl ParameterUpdater {
         self.switch_gesture_prev = is_switch_gesture;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::attachment_named;
+
+    #[test]
+    fn high_priority_ccs_survive_truncation() {
+        let attachments = build_midi_cc_attachments();
+
+        // every attachment and enough unattached CCs to exceed the budget,
+        // all of which come before most attachments when unsorted
+        let mut pending: Vec<MIDICCIndex> = (0..NUM_MIDI_CCS)
+            .map(|cc| MIDICCIndex { channel: 0, cc })
+            .filter(|idx| !attachments.contains_key(idx))
+            .chain(attachments.keys().copied())
+            .collect();
+
+        let mut bytes = 0;
+        let mut sent = Vec::new();
+        let truncated =
+            send_ccs_by_priority(&mut pending, &attachments, &mut bytes, |idx| {
+                sent.push(idx);
+
+                match attachments.get(&idx) {
+                    Some(att) if att.is_14_bit() => 6,
+                    _ => 3,
+                }
+            });
+
+        assert!(!truncated.is_empty());
+        assert_eq!(sent.len() + truncated.len(), pending.len());
+        assert!(bytes > MAX_MIDI_BUFFER_SIZE_BYTES);
+
+        let index_of = |name: &str| {
+            let att = attachment_named(&attachments, name);
+            attachments
+                .iter()
+                .find_map(|(idx, a)| std::ptr::eq(a, att).then_some(*idx))
+                .unwrap()
+        };
+
+        for name in ["First hand x-pos", "First hand y-pos"] {
+            let att = attachment_named(&attachments, name);
+            assert_eq!(att.priority(), CCPriority::High);
+            assert!(sent.contains(&index_of(name)), "{name} was truncated");
+        }
+
+        // every high priority CC is sent before anything else
+        let num_high = attachments
+            .values()
+            .filter(|att| att.priority() >= CCPriority::High)
+            .count();
+        assert!(sent[..num_high].iter().all(|idx| {
+            attachments
+                .get(idx)
+                .is_some_and(|att| att.priority() >= CCPriority::High)
+        }));
+
+        // low priority CCs are truncated before unattached ones
+        let velocity = index_of("First hand velocity");
+        assert!(truncated.contains(&velocity));
+        assert_eq!(truncated.last(), Some(&velocity));
+    }
+}
@@ -0,0 +1,276 @@
+//! The text shown on screen, which may be replaced per language.
+//!
+//! Translations are loaded from a JSON file of the form:
+//!
+//! ```json
+//! {
+//!     "language": "de",
+//!     "strings": {
+//!         "status.title": "Prüfungen vor dem Start",
+//!         "status.ready": "Bereit ('T' zum Starten drücken)"
+//!     }
+//! }
+//! ```
+//!
+//! Any keys which aren't given keep their English text. Placeholders such
+//! as `{name}` are filled in when the text is shown, and may be moved or
+//! left out, but not renamed.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// The language of the built-in text.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// The built-in English text for every key.
+const DEFAULT_STRINGS: &[(&str, &str)] = &[
+    // state text
+    ("state.sending_active", "OSC/MIDI are active (press 'S' to stop)"),
+    (
+        "state.sending_inactive",
+        "OSC/MIDI are inactive (press 'T' to start, shift + 'T' to continue)",
+    ),
+    ("state.osc_bound", "Bound to OSC {receive} (receive) and {send} (send)"),
+    ("state.midi_bound", "Bound to MIDI port \"{port}\""),
+    (
+        "state.metronome_on",
+        "Metronome is on (press 'M' to toggle, shift + 'M' to count in)",
+    ),
+    (
+        "state.metronome_off",
+        "Metronome is off (press 'M' to toggle, shift + 'M' to count in)",
+    ),
+    ("state.tempo", "Tempo is {bpm} BPM (press 'B' to tap)"),
+    ("state.tracker", "Hand tracker is {health}"),
+    (
+        "state.profile",
+        "Profile is \"{name}\" (press 'P' to switch, shift + 'C' to calibrate)",
+    ),
+    ("state.room", "Room EQ is \"{name}\" (press 1-9 to switch, 0 for flat)"),
+    (
+        "state.true_peak_threshold",
+        "True-peak threshold is {threshold} dBTP (press 'R' to reset the meter)",
+    ),
+    ("state.pulse", "Visual pulse is at {percent}% (press up/down to adjust)"),
+    (
+        "state.midi_ping",
+        "Ready to ping {mode} #{value} to channel {channel} ({label})",
+    ),
+    ("state.midi_ping_unattached", "no attachment"),
+    ("state.midi_ping_14_bit", " (14-bit)"),
+    ("state.osc_failing", "{destinations} ({num_failing} failing)"),
+    ("state.tracker_last_packet", "{health} (last packet {age}s ago)"),
+    // status screen
+    ("status.title", "Pre-start checks"),
+    ("status.midi_port", "MIDI port"),
+    ("status.midi_unavailable", "sender is unavailable"),
+    ("status.osc_bind", "OSC bind"),
+    ("status.osc_detail", "{receive} (receive), {send} (send)"),
+    ("status.audio_device", "Audio device"),
+    ("status.audio_detail", "{sample_rate} Hz, {buffer_size} frames"),
+    ("status.audio_no_callback", "no callback for {secs}s"),
+    ("status.tracker", "Tracker"),
+    ("status.ready", "Ready (press 'T' to start)"),
+    (
+        "status.not_ready",
+        "Not ready: {num_failed} failed (press 'T' to start anyway)",
+    ),
+    // calibration
+    ("calibration.left", "Reach as far left as is comfortable"),
+    ("calibration.right", "Reach as far right as is comfortable"),
+    ("calibration.up", "Reach as high as is comfortable"),
+    ("calibration.down", "Reach as low as is comfortable"),
+    ("calibration.forward", "Reach towards the camera"),
+    ("calibration.back", "Pull your hand back away from the camera"),
+    (
+        "calibration.prompt",
+        "{prompt} ({remaining}s)\npress shift + 'C' to cancel calibration",
+    ),
+    // idle screen
+    ("attract.open", "Raise an open hand to play"),
+    ("attract.close", "Close your hand into a fist"),
+    ("attract.pinch", "Pinch your thumb and finger together"),
+    ("attract.tutorial", "Give a thumbs up to learn the gestures"),
+    // gesture tutorial
+    ("tutorial.title", "Gesture tutorial ({completed}/{num_steps})"),
+    ("tutorial.open", "Hold up an open hand"),
+    ("tutorial.close", "Now close it into a fist"),
+    ("tutorial.pinch", "Pinch your thumb and index finger together"),
+    ("tutorial.swipe", "Swipe your hand across the screen"),
+    ("tutorial.well_done", "Well done!"),
+    ("tutorial.finished", "That's every gesture, now play!"),
+    // overlays
+    ("overlay.latched", "Latched: {names}"),
+    ("overlay.pickup", "Pick up: {names}"),
+    ("overlay.pickup_raise", "{name} (raise)"),
+    ("overlay.pickup_lower", "{name} (lower)"),
+    ("overlay.axis_lock", "Axis lock: {axis} only"),
+    ("overlay.axis_lock_choose", "Axis lock: move along X or Y to choose"),
+    ("overlay.eme_failure", "EME request failed: {failure}"),
+    ("overlay.tracking_degraded", "Tracking degraded: {alert}"),
+    ("overlay.tracker_ignored", "Ignoring hand tracker: {reason}"),
+    (
+        "overlay.config_diagnostics",
+        "Config has {num_errors} error(s) and {num_warnings} warning(s):",
+    ),
+    (
+        "overlay.mapping_editor",
+        "Mapping editor{unsaved}\nup/down: route, tab: field, left/right: adjust (shift: x10), return: save, E: close",
+    ),
+    ("overlay.mapping_editor_unsaved", " (unsaved)"),
+    // meters
+    ("meter.input", "Input: {level} dB (gain {gain} dB{gate})"),
+    ("meter.input_gate", ", gate {gain} dB"),
+    ("meter.input_gate_learning", ", learning noise floor"),
+    ("meter.output", "Output: {level} dBTP (max {max} dBTP, {overs} overs)"),
+    ("meter.output_last", ", last {last}"),
+    ("meter.dsp", "DSP: {load} % (peak {peak} %)"),
+    ("meter.dsp_stage", "{stage}: {load} %"),
+    ("meter.feedback", "Feedback: {num_notches} notches (press 'X' to clear)"),
+    ("meter.feedback_none", "Feedback: no notches"),
+];
+
+/// The text shown on screen for one language, keyed by where it is shown.
+///
+/// The built-in keys are listed in [`DEFAULT_STRINGS`]; each one is always
+/// present, falling back to English if a translation doesn't provide it.
+#[derive(Clone, Debug)]
+pub struct StringTable {
+    language: String,
+    strings: HashMap<&'static str, String>,
+}
+
+impl StringTable {
+    /// The built-in English text.
+    pub fn new() -> Self {
+        Self {
+            language: String::from(DEFAULT_LANGUAGE),
+            strings: DEFAULT_STRINGS
+                .iter()
+                .map(|&(key, text)| (key, String::from(text)))
+                .collect(),
+        }
+    }
+
+    /// Loads a translation from the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read strings \"{path}\": {e}"))?;
+
+        Self::from_json_str(&contents)
+    }
+
+    /// Parses a translation from a JSON string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` is not a valid strings file, or if it
+    /// contains a key which isn't shown anywhere, as it is likely a typo.
+    pub fn from_json_str(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        let Some(strings) = value.get("strings").and_then(Value::as_object)
+        else {
+            return Err(String::from("expected a \"strings\" object"));
+        };
+
+        let mut table = Self::new();
+
+        if let Some(language) = value.get("language") {
+            let Some(language) = language.as_str()
+            else {
+                return Err(String::from(
+                    "expected \"language\" to be a string",
+                ));
+            };
+
+            table.language = String::from(language);
+        }
+
+        for (key, text) in strings {
+            let Some((&key, _)) = table.strings.get_key_value(key.as_str())
+            else {
+                return Err(format!("unknown string \"{key}\""));
+            };
+            let Some(text) = text.as_str()
+            else {
+                return Err(format!("expected string \"{key}\" to be text"));
+            };
+
+            table.strings.insert(key, String::from(text));
+        }
+
+        Ok(table)
+    }
+
+    /// The language of the table, as given by its file.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The text for `key`, or the key itself if it isn't known.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        debug_assert!(self.strings.contains_key(key), "unknown string {key}");
+
+        self.strings.get(key).map_or(key, String::as_str)
+    }
+
+    /// The text for `key`, with each `{name}` placeholder in it replaced by
+    /// the matching value in `args`.
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        args.iter().fold(String::from(self.get(key)), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), &value.to_string())
+        })
+    }
+}
+
+impl Default for StringTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        let strings = StringTable::new();
+
+        assert_eq!(
+            strings.format("status.not_ready", &[("num_failed", &2)]),
+            "Not ready: 2 failed (press 'T' to start anyway)"
+        );
+    }
+
+    #[test]
+    fn missing_translations_fall_back_to_english() {
+        let strings = StringTable::from_json_str(
+            r#"{
+                "language": "de",
+                "strings": { "status.title": "Prüfungen vor dem Start" }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(strings.language(), "de");
+        assert_eq!(strings.get("status.title"), "Prüfungen vor dem Start");
+        assert_eq!(strings.get("status.tracker"), "Tracker");
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let result = StringTable::from_json_str(
+            r#"{ "strings": { "status.titel": "Prüfungen" } }"#,
+        );
+
+        assert!(result.is_err());
+    }
+}
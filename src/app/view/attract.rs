@@ -0,0 +1,371 @@
+//! An instructional animation which plays when nobody is in front of the
+//! installation, showing the gestures which it responds to.
+//!
+//! A schematic hand loops through opening, closing and pinching, with a
+//! caption for each gesture. The hand is played from poses which were baked
+//! from tracked landmarks, and is drawn through the same path as tracked
+//! hands, so it takes on the current palette and post effects.
+
+use super::*;
+use crate::prelude::xfer::s_curve;
+use hands::hand_types::{HandGesture, RawHand, RawHandPair, RawHandPairCOM};
+use hands::NUM_HAND_VERTICES;
+use std::f64::consts::TAU;
+
+/// The default time without hands before the animation plays, in seconds.
+pub const DEFAULT_ATTRACT_DELAY: f64 = 20.0;
+
+/// The time which the hand takes to move into each pose, in seconds.
+const MOVE_TIME: f64 = 0.8;
+/// The time which each pose is held for, in seconds.
+const HOLD_TIME: f64 = 2.2;
+/// The tension of the easing between poses.
+const MOVE_TENSION: f64 = 0.6;
+/// The time which each caption takes to fade in and out, in seconds.
+const CAPTION_FADE_TIME: f64 = 0.4;
+/// How far the hand sways from side to side, as a proportion of the window.
+const SWAY_DISTANCE: f64 = 0.015;
+/// The time which each sway takes, in seconds.
+const SWAY_PERIOD: f64 = 6.0;
+const CAPTION_FONT_SIZE: u32 = 22;
+/// The height of the caption below the top of the window.
+const CAPTION_HEIGHT: f32 = 120.0;
+/// The time which the tutorial is offered for after hands return, in
+/// seconds.
+const INVITE_TIME: f64 = 10.0;
+
+/// A pose of the animation, and the caption which is shown while it is
+/// held.
+#[derive(Clone, Copy, Debug)]
+struct AttractStep {
+    pose: &'static [[f64; 3]; NUM_HAND_VERTICES],
+    gesture: HandGesture,
+    /// The key of the caption's text.
+    caption: &'static str,
+}
+
+/// The steps of the animation, in the order which they are played. The last
+/// step moves back into the first.
+const STEPS: [AttractStep; 4] = [
+    AttractStep {
+        pose: &OPEN_POSE,
+        gesture: HandGesture::Open,
+        caption: "attract.open",
+    },
+    AttractStep {
+        pose: &CLOSED_POSE,
+        gesture: HandGesture::Closed,
+        caption: "attract.close",
+    },
+    AttractStep {
+        pose: &OPEN_POSE,
+        gesture: HandGesture::Open,
+        caption: "attract.open",
+    },
+    AttractStep {
+        pose: &PINCH_POSE,
+        gesture: HandGesture::Unknown,
+        caption: "attract.pinch",
+    },
+];
+
+/// An open right hand, baked from tracked landmarks.
+const OPEN_POSE: [[f64; 3]; NUM_HAND_VERTICES] = [
+    [0.421, 0.802, 0.000],
+    [0.498, 0.750, -0.025],
+    [0.559, 0.659, -0.035],
+    [0.600, 0.580, -0.045],
+    [0.637, 0.518, -0.056],
+    [0.512, 0.488, -0.014],
+    [0.541, 0.369, -0.034],
+    [0.560, 0.296, -0.052],
+    [0.579, 0.237, -0.065],
+    [0.467, 0.463, -0.018],
+    [0.489, 0.329, -0.035],
+    [0.512, 0.253, -0.051],
+    [0.537, 0.198, -0.063],
+    [0.422, 0.468, -0.026],
+    [0.434, 0.332, -0.047],
+    [0.455, 0.257, -0.062],
+    [0.482, 0.206, -0.073],
+    [0.376, 0.498, -0.037],
+    [0.363, 0.391, -0.058],
+    [0.379, 0.342, -0.068],
+    [0.408, 0.315, -0.073],
+];
+
+/// The open hand closed into a fist.
+const CLOSED_POSE: [[f64; 3]; NUM_HAND_VERTICES] = [
+    [0.421, 0.802, 0.000],
+    [0.498, 0.750, -0.025],
+    [0.559, 0.659, -0.035],
+    [0.556, 0.534, -0.030],
+    [0.519, 0.484, -0.024],
+    [0.512, 0.488, -0.014],
+    [0.479, 0.513, -0.024],
+    [0.458, 0.530, -0.033],
+    [0.468, 0.521, -0.040],
+    [0.467, 0.463, -0.018],
+    [0.455, 0.500, -0.026],
+    [0.446, 0.524, -0.034],
+    [0.450, 0.512, -0.041],
+    [0.422, 0.468, -0.026],
+    [0.430, 0.502, -0.036],
+    [0.435, 0.525, -0.044],
+    [0.433, 0.514, -0.050],
+    [0.376, 0.498, -0.037],
+    [0.405, 0.519, -0.047],
+    [0.424, 0.532, -0.052],
+    [0.414, 0.526, -0.055],
+];
+
+/// The open hand with its thumb and index finger pinched together.
+const PINCH_POSE: [[f64; 3]; NUM_HAND_VERTICES] = [
+    [0.421, 0.802, 0.000],
+    [0.498, 0.750, -0.025],
+    [0.559, 0.659, -0.035],
+    [0.604, 0.499, -0.051],
+    [0.610, 0.384, -0.060],
+    [0.512, 0.488, -0.014],
+    [0.557, 0.371, -0.041],
+    [0.584, 0.337, -0.056],
+    [0.607, 0.370, -0.061],
+    [0.467, 0.463, -0.018],
+    [0.489, 0.329, -0.035],
+    [0.512, 0.253, -0.051],
+    [0.537, 0.198, -0.063],
+    [0.422, 0.468, -0.026],
+    [0.434, 0.332, -0.047],
+    [0.455, 0.257, -0.062],
+    [0.482, 0.206, -0.073],
+    [0.376, 0.498, -0.037],
+    [0.363, 0.391, -0.058],
+    [0.379, 0.342, -0.068],
+    [0.408, 0.315, -0.073],
+];
+
+/// Plays the animation once there have been no hands for a while.
+#[derive(Clone, Debug)]
+pub struct AttractLoop {
+    /// The time without hands before the animation plays, in seconds.
+    delay: f64,
+    /// The time since the last hands were seen, in seconds.
+    time_without_hands: f64,
+    /// The time which the tutorial is still offered for, in seconds.
+    invite_remaining: f64,
+}
+
+impl AttractLoop {
+    /// Plays the animation after `delay` seconds without hands.
+    pub fn new(delay: f64) -> Self {
+        Self {
+            delay: delay.max(0.0),
+            time_without_hands: 0.0,
+            invite_remaining: 0.0,
+        }
+    }
+
+    /// Moves the animation on by `dt` seconds, or stops it if there are
+    /// hands. Hands which stop the animation are offered the tutorial for a
+    /// while.
+    pub fn update(&mut self, hands_present: bool, dt: f64) {
+        if hands_present {
+            self.invite_remaining = if self.is_playing() {
+                INVITE_TIME
+            }
+            else {
+                (self.invite_remaining - dt).max(0.0)
+            };
+
+            self.time_without_hands = 0.0;
+        }
+        else {
+            self.time_without_hands += dt;
+        }
+    }
+
+    /// Whether the animation is playing.
+    pub fn is_playing(&self) -> bool {
+        self.time_without_hands >= self.delay
+    }
+
+    /// Whether the tutorial is offered, which is while the animation plays
+    /// and for a while after it is stopped.
+    pub fn is_inviting(&self) -> bool {
+        self.is_playing() || self.invite_remaining > 0.0
+    }
+
+    /// Stops offering the tutorial, such as once it is started.
+    pub fn dismiss_invite(&mut self) {
+        self.invite_remaining = 0.0;
+    }
+
+    /// The schematic hand at the current point in the animation, if it is
+    /// playing.
+    pub fn hands(&self) -> Option<RawHandPairCOM> {
+        let (step, time) = self.position()?;
+
+        let from = STEPS[(step + STEPS.len() - 1) % STEPS.len()];
+        let to = STEPS[step];
+
+        let progress = (time / MOVE_TIME).min(1.0);
+        let t = (s_curve(progress.mul_add(2.0, -1.0), MOVE_TENSION) + 1.0)
+            * 0.5;
+
+        let sway = (self.elapsed() * TAU / SWAY_PERIOD).sin() * SWAY_DISTANCE;
+        let mut hand = RawHand {
+            points: [DVec3::ZERO; NUM_HAND_VERTICES],
+            gesture: if t < 0.5 { from.gesture } else { to.gesture },
+        };
+
+        for (point, (a, b)) in hand
+            .points
+            .iter_mut()
+            .zip(from.pose.iter().zip(to.pose.iter()))
+        {
+            *point = DVec3::from(*a).lerp(DVec3::from(*b), t)
+                + DVec3::new(sway, 0.0, 0.0);
+        }
+
+        let mut hands = RawHandPairCOM {
+            pair: RawHandPair { first: Some(hand), second: None },
+            ..Default::default()
+        };
+        hands.com.set_from(&hands.pair);
+
+        Some(hands)
+    }
+
+    /// The key of the current caption's text and its opacity, if the
+    /// animation is playing.
+    pub fn caption(&self) -> Option<(&'static str, f32)> {
+        let (step, time) = self.position()?;
+
+        let until_end = MOVE_TIME + HOLD_TIME - time;
+        let alpha =
+            (time.min(until_end) / CAPTION_FADE_TIME).clamp(0.0, 1.0) as f32;
+
+        Some((STEPS[step].caption, alpha))
+    }
+
+    /// Draws the current caption near the top of the window, and the
+    /// tutorial's invitation below it.
+    pub fn draw_caption(
+        &self,
+        draw: &Draw,
+        frame: &Frame,
+        strings: &StringTable,
+    ) {
+        let r = frame.rect();
+        let base = if LIGHT_MODE {
+            Rgba::new(0.1, 0.1, 0.1, 1.0)
+        }
+        else {
+            Rgba::new(0.95, 0.95, 0.95, 1.0)
+        };
+
+        if let Some((key, alpha)) = self.caption() {
+            draw.text(strings.get(key))
+                .color(Rgba { alpha, ..base })
+                .xy(vec2(0.0, r.top() - CAPTION_HEIGHT))
+                .wh(vec2(r.w(), CAPTION_FONT_SIZE as f32 * 2.0))
+                .justify(text::Justify::Center)
+                .font_size(CAPTION_FONT_SIZE);
+        }
+
+        if self.is_inviting() {
+            draw.text(strings.get("attract.tutorial"))
+                .color(Rgba { alpha: 0.7, ..base })
+                .xy(vec2(0.0, r.top() - CAPTION_HEIGHT - 40.0))
+                .wh(vec2(r.w(), 30.0))
+                .justify(text::Justify::Center)
+                .font_size(16);
+        }
+    }
+
+    /// The time which the animation has been playing for, in seconds.
+    fn elapsed(&self) -> f64 {
+        (self.time_without_hands - self.delay).max(0.0)
+    }
+
+    /// The current step, and the time since it started in seconds, if the
+    /// animation is playing.
+    fn position(&self) -> Option<(usize, f64)> {
+        if !self.is_playing() {
+            return None;
+        }
+
+        let step_len = MOVE_TIME + HOLD_TIME;
+        let elapsed = self.elapsed() % (step_len * STEPS.len() as f64);
+        let step = (elapsed / step_len) as usize % STEPS.len();
+
+        Some((step, elapsed - step as f64 * step_len))
+    }
+}
+
+impl Default for AttractLoop {
+    fn default() -> Self {
+        Self::new(DEFAULT_ATTRACT_DELAY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP_LEN: f64 = MOVE_TIME + HOLD_TIME;
+
+    /// An animation which has played for `secs` seconds.
+    fn played_for(secs: f64) -> AttractLoop {
+        let mut attract = AttractLoop::new(5.0);
+        attract.update(false, 5.0 + secs);
+        attract
+    }
+
+    #[test]
+    fn plays_only_without_hands() {
+        let mut attract = AttractLoop::new(5.0);
+
+        attract.update(false, 4.0);
+        assert!(attract.hands().is_none());
+
+        attract.update(false, 2.0);
+        assert!(attract.hands().is_some());
+
+        attract.update(true, 0.01);
+        assert!(attract.hands().is_none());
+        assert!(attract.caption().is_none());
+
+        // the tutorial is still offered to the hands which stopped it
+        assert!(attract.is_inviting());
+        attract.update(true, INVITE_TIME);
+        assert!(!attract.is_inviting());
+    }
+
+    #[test]
+    fn holds_each_pose_with_its_gesture() {
+        let attract = played_for(STEP_LEN + MOVE_TIME + HOLD_TIME * 0.5);
+        let hand = attract.hands().unwrap().pair.first.unwrap();
+
+        assert_eq!(hand.gesture, HandGesture::Closed);
+
+        // the hand only sways from side to side
+        for (point, closed) in hand.points.iter().zip(CLOSED_POSE) {
+            assert!(epsilon_eq(point.y, closed[1]));
+            assert!(epsilon_eq(point.z, closed[2]));
+        }
+
+        assert_eq!(attract.caption().unwrap().0, "attract.close");
+    }
+
+    #[test]
+    fn captions_fade_between_steps_and_loop() {
+        let holding = played_for(MOVE_TIME + HOLD_TIME * 0.5);
+        let changing = played_for(STEP_LEN);
+        let looped = played_for(STEP_LEN * STEPS.len() as f64 + 1.5);
+
+        assert!(epsilon_eq(holding.caption().unwrap().1 as f64, 1.0));
+        assert!(changing.caption().unwrap().1 < 0.01);
+        assert_eq!(looped.caption().unwrap().0, "attract.open");
+    }
+}
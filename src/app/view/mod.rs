@@ -3,17 +3,150 @@
 use crate::prelude::xfer::s_curve;
 use nannou::geom::{path, Path};
 
-use super::{hands::LIGHT_MODE, *};
+use super::{hands::LIGHT_MODE, strings::StringTable, *};
+use attract::AttractLoop;
+use palette::PaletteLayer;
+use post_fx::draw_vignette;
+
+pub mod attract;
+pub mod palette;
+pub mod post_fx;
+pub mod pulse;
+pub mod ticker;
+pub mod tutorial;
+
+/// The height of each line of the status screen.
+const STATUS_LINE_HEIGHT: f32 = 28.0;
+
+/// The result of checking one of the app's subsystems, which is shown on
+/// the status screen before the transport starts.
+#[derive(Clone, Debug)]
+pub struct StatusCheck {
+    pub name: String,
+    pub is_ok: bool,
+    /// What was found, such as the name of the bound port.
+    pub detail: String,
+}
 
 /// The app's view callback (AKA "draw loop").
 pub fn view(app: &App, model: &Model, frame: Frame) {
-    let bg_col = if LIGHT_MODE { WHITE } else { BLACK };
-    frame.clear(bg_col);
     let frame = &frame;
+    let background = model.palette().color(PaletteLayer::Background);
+    let accent = model.palette().color(PaletteLayer::Accents);
+    let post_fx = model.palette().post_fx();
+    let pulse = model.pulse();
+
+    // the visuals are drawn offscreen first, so the text isn't affected by
+    // the post effects
+    let pulsed = pulse.background(background, accent);
+    model.post_fx().render(frame, &post_fx, pulsed, |draw| {
+        // the spectrum is drawn behind the hands
+        model.draw_spectrum(draw, frame);
+        model.hand_manager.damped_hands().draw_with_palette(
+            draw,
+            frame,
+            model.palette(),
+            post_fx.glow,
+            pulse.glyph_scale(),
+        );
+
+        // the schematic hand is drawn like a tracked one
+        if let Some(hands) = model.attract().and_then(AttractLoop::hands) {
+            hands.draw_with_palette(
+                draw,
+                frame,
+                model.palette(),
+                post_fx.glow,
+                pulse.glyph_scale(),
+            );
+        }
+    });
+
     let draw = &app.draw();
 
-    model.hand_manager.damped_hands().draw(draw, frame);
+    draw_vignette(draw, frame, &post_fx, background);
     model.draw(draw, frame);
 
+    if !model.is_sending() {
+        draw_status_screen(
+            draw,
+            frame,
+            &model.status_checks(),
+            model.strings(),
+        );
+    }
+
     _ = draw.to_frame(app, frame);
 }
+
+/// Draws each of `checks` in the middle of the window, followed by whether
+/// the transport is ready to start.
+fn draw_status_screen(
+    draw: &Draw,
+    frame: &Frame,
+    checks: &[StatusCheck],
+    strings: &StringTable,
+) {
+    let ok_color = Rgba::new(0.3, 0.9, 0.4, 1.0);
+    let failed_color = Rgba::new(1.0, 0.3, 0.3, 1.0);
+    let text_color = if LIGHT_MODE {
+        Rgba::new(0.2, 0.2, 0.2, 1.0)
+    }
+    else {
+        Rgba::new(0.8, 0.8, 0.8, 1.0)
+    };
+
+    let num_lines = checks.len() + 2;
+    let top = STATUS_LINE_HEIGHT * num_lines as f32 / 2.0;
+
+    draw.text(strings.get("status.title"))
+        .color(text_color)
+        .xy(vec2(0.0, top))
+        .wh(vec2(600.0, STATUS_LINE_HEIGHT))
+        .font_size(18);
+
+    for (i, check) in checks.iter().enumerate() {
+        let y = top - STATUS_LINE_HEIGHT * (i + 1) as f32;
+        let (mark, color) = if check.is_ok {
+            ("\u{2713}", ok_color)
+        }
+        else {
+            ("\u{2717}", failed_color)
+        };
+
+        draw.text(mark)
+            .color(color)
+            .xy(vec2(-280.0, y))
+            .wh(vec2(30.0, STATUS_LINE_HEIGHT))
+            .font_size(18);
+        draw.text(&check.name)
+            .color(color)
+            .xy(vec2(-180.0, y))
+            .wh(vec2(160.0, STATUS_LINE_HEIGHT))
+            .left_justify()
+            .font_size(14);
+        draw.text(&check.detail)
+            .color(text_color)
+            .xy(vec2(110.0, y))
+            .wh(vec2(400.0, STATUS_LINE_HEIGHT))
+            .left_justify()
+            .font_size(12);
+    }
+
+    let num_failed = checks.iter().filter(|check| !check.is_ok).count();
+    let (msg, color) = if num_failed == 0 {
+        (String::from(strings.get("status.ready")), ok_color)
+    }
+    else {
+        (
+            strings.format("status.not_ready", &[("num_failed", &num_failed)]),
+            failed_color,
+        )
+    };
+
+    draw.text(&msg)
+        .color(color)
+        .xy(vec2(0.0, top - STATUS_LINE_HEIGHT * (num_lines - 1) as f32))
+        .wh(vec2(600.0, STATUS_LINE_HEIGHT))
+        .font_size(16);
+}
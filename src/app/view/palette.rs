@@ -0,0 +1,489 @@
+//! Color palettes which the hands morph through, with one set of gradients
+//! per mode.
+//!
+//! Palettes are loaded from a JSON file of the form:
+//!
+//! ```json
+//! {
+//!     "A": {
+//!         "background": [
+//!             { "at": 0, "color": "#000000" },
+//!             { "at": 1, "color": "#1a0a2e" }
+//!         ],
+//!         "hands": [{ "at": 0, "color": "#40c0ff" }],
+//!         "accents": [{ "at": 0, "color": "#3380e64d" }],
+//!         "post": { "glow": 0.6, "trails": 0.8, "vignette": 0.5 }
+//!     }
+//! }
+//! ```
+//!
+//! Each gradient is a list of stops from `0` to `1`, whose colors are given
+//! as `"#rrggbb"` or `"#rrggbbaa"`. `"post"` sets the amount of each post
+//! effect (see [`PostFxSettings`]), from `0` to `1`. Modes, gradients and
+//! effects which aren't given keep their defaults.
+//!
+//! The position along each gradient is set by how open and how fast the
+//! first hand is, and the palette sweeps between modes along with the audio.
+
+use super::*;
+use crate::app::hands::hand_types::RawHandPairCOM;
+use post_fx::PostFxSettings;
+use crate::app::params::{Mode, ModeTransition};
+use serde_json::Value;
+
+/// The range of hand openness which is mapped along the gradients.
+const MIN_OPENNESS: f64 = 0.72;
+const MAX_OPENNESS: f64 = 2.0;
+/// How much of the morph is set by the hand's velocity, rather than its
+/// openness.
+const VELOCITY_WEIGHT: f64 = 0.4;
+/// The time (in seconds) which the morph takes to follow the hand.
+const MORPH_SMOOTHING_TIME: f64 = 0.3;
+/// How much of the palette's hand color is mixed into each gesture's color.
+const HAND_TINT_AMOUNT: f32 = 0.6;
+
+/// Which part of the visuals a color is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteLayer {
+    Background,
+    Hands,
+    /// The hands' centers of mass and the spectrum.
+    Accents,
+}
+
+/// A color at a position along a [`Gradient`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    pub position: f32,
+    pub color: Rgba,
+}
+
+/// Colors which are blended between along a range of `0.0` to `1.0`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    /// Sorted by position. There is always at least one.
+    stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    /// A gradient through `stops`, which are sorted by their positions.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `stops` is empty.
+    pub fn new(mut stops: Vec<GradientStop>) -> Result<Self, String> {
+        if stops.is_empty() {
+            return Err(String::from("expected at least one gradient stop"));
+        }
+
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+
+        Ok(Self { stops })
+    }
+
+    /// A gradient from `from` at `0.0` to `to` at `1.0`.
+    pub fn between(from: Rgba, to: Rgba) -> Self {
+        Self {
+            stops: vec![
+                GradientStop { position: 0.0, color: from },
+                GradientStop { position: 1.0, color: to },
+            ],
+        }
+    }
+
+    /// The color at `t`, which is held at the first and last stops past
+    /// either end.
+    pub fn sample(&self, t: f32) -> Rgba {
+        let first = self.stops[0];
+
+        if t <= first.position {
+            return first.color;
+        }
+
+        for pair in self.stops.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+
+            if t <= b.position {
+                let width = b.position - a.position;
+                let t =
+                    if width > 0.0 { (t - a.position) / width } else { 1.0 };
+
+                return mix(a.color, b.color, t);
+            }
+        }
+
+        self.stops[self.stops.len() - 1].color
+    }
+
+    fn from_json(value: &Value) -> Result<Self, String> {
+        let Some(stops) = value.as_array()
+        else {
+            return Err(String::from("expected an array of gradient stops"));
+        };
+
+        let stops = stops
+            .iter()
+            .map(|stop| {
+                let position = stop
+                    .get("at")
+                    .and_then(Value::as_f64)
+                    .ok_or("expected a stop's \"at\" to be a number")?;
+                let color = stop
+                    .get("color")
+                    .and_then(Value::as_str)
+                    .ok_or("expected a stop's \"color\" to be a string")?;
+
+                Ok(GradientStop {
+                    position: position.clamp(0.0, 1.0) as f32,
+                    color: parse_hex_color(color)?,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Self::new(stops)
+    }
+}
+
+/// The gradients of each layer for one mode, and how strongly the post
+/// effects are applied during it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModePalette {
+    pub background: Gradient,
+    pub hands: Gradient,
+    pub accents: Gradient,
+    pub post: PostFxSettings,
+}
+
+impl ModePalette {
+    const fn gradient(&self, layer: PaletteLayer) -> &Gradient {
+        match layer {
+            PaletteLayer::Background => &self.background,
+            PaletteLayer::Hands => &self.hands,
+            PaletteLayer::Accents => &self.accents,
+        }
+    }
+
+    /// The built-in palette for `mode`.
+    fn default_for(mode: Mode) -> Self {
+        let background = if LIGHT_MODE {
+            Rgba::new(1.0, 1.0, 1.0, 1.0)
+        }
+        else {
+            Rgba::new(0.0, 0.0, 0.0, 1.0)
+        };
+
+        let (bg_to, hands_from, hands_to, accent, post) = match mode {
+            Mode::A => (
+                Rgba::new(0.04, 0.06, 0.14, 1.0),
+                Rgba::new(0.2, 0.5, 1.0, 1.0),
+                Rgba::new(0.3, 1.0, 0.9, 1.0),
+                Rgba::new(0.2, 0.5, 0.7, 0.15),
+                PostFxSettings::new(0.5, 0.8, 0.5),
+            ),
+            Mode::B => (
+                Rgba::new(0.12, 0.04, 0.1, 1.0),
+                Rgba::new(0.9, 0.3, 0.6, 1.0),
+                Rgba::new(1.0, 0.7, 0.3, 1.0),
+                Rgba::new(0.7, 0.3, 0.5, 0.15),
+                PostFxSettings::new(0.8, 0.5, 0.4),
+            ),
+            Mode::C => (
+                Rgba::new(0.03, 0.1, 0.04, 1.0),
+                Rgba::new(0.3, 0.8, 0.3, 1.0),
+                Rgba::new(0.9, 1.0, 0.4, 1.0),
+                Rgba::new(0.4, 0.7, 0.3, 0.15),
+                PostFxSettings::new(0.4, 0.6, 0.6),
+            ),
+        };
+
+        let bg_to = if LIGHT_MODE {
+            mix(background, bg_to, 0.15)
+        }
+        else {
+            bg_to
+        };
+
+        Self {
+            background: Gradient::between(background, bg_to),
+            hands: Gradient::between(hands_from, hands_to),
+            accents: Gradient::between(accent, Rgba { alpha: 0.4, ..accent }),
+            post,
+        }
+    }
+
+    /// Replaces any gradients and post effects which are given in `value`.
+    fn apply_json(&mut self, value: &Value) -> Result<(), String> {
+        let Some(layers) = value.as_object()
+        else {
+            return Err(String::from("expected an object of gradients"));
+        };
+
+        for (name, gradient) in layers {
+            if name == "post" {
+                self.post.apply_json(gradient)?;
+                continue;
+            }
+
+            let gradient = Gradient::from_json(gradient)
+                .map_err(|e| format!("in \"{name}\": {e}"))?;
+
+            match name.as_str() {
+                "background" => self.background = gradient,
+                "hands" => self.hands = gradient,
+                "accents" => self.accents = gradient,
+                _ => return Err(format!("unknown gradient \"{name}\"")),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The visuals' colors, which morph with the hands and sweep between modes
+/// along with the audio.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    /// The palette of each mode, in the order of [`Mode::ALL`].
+    modes: [ModePalette; 3],
+    /// The position along each gradient, from `0.0` to `1.0`.
+    morph: f64,
+    transition: ModeTransition,
+}
+
+impl Palette {
+    /// The built-in palettes.
+    pub fn new() -> Self {
+        Self {
+            modes: Mode::ALL.map(ModePalette::default_for),
+            morph: 0.0,
+            transition: ModeTransition::settled(Mode::default()),
+        }
+    }
+
+    /// Loads palettes from the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read palette \"{path}\": {e}"))?;
+
+        Self::from_json_str(&contents)
+    }
+
+    /// Parses palettes from a JSON string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` is not a valid palette file.
+    pub fn from_json_str(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        let Some(modes) = value.as_object()
+        else {
+            return Err(String::from("expected an object of modes"));
+        };
+
+        let mut palette = Self::new();
+
+        for (name, layers) in modes {
+            let Some(mode) = Mode::from_name(name)
+            else {
+                return Err(format!("unknown mode \"{name}\""));
+            };
+
+            palette.modes[mode as usize]
+                .apply_json(layers)
+                .map_err(|e| format!("mode {}: {e}", mode.name()))?;
+        }
+
+        Ok(palette)
+    }
+
+    /// Moves the morph towards the first hand's openness and `velocity`
+    /// (from `0.0` to `1.0`), and follows `transition`.
+    pub fn update(
+        &mut self,
+        hands: &RawHandPairCOM,
+        velocity: f32,
+        transition: ModeTransition,
+        dt: f64,
+    ) {
+        let openness = hands.get_openness().0.map_or(0.0, |openness| {
+            normalize(openness, MIN_OPENNESS, MAX_OPENNESS).clamp(0.0, 1.0)
+        });
+        let target = openness.mul_add(
+            1.0 - VELOCITY_WEIGHT,
+            (velocity as f64).clamp(0.0, 1.0) * VELOCITY_WEIGHT,
+        );
+
+        let coeff = if MORPH_SMOOTHING_TIME > 0.0 {
+            1.0 - (-dt / MORPH_SMOOTHING_TIME).exp()
+        }
+        else {
+            1.0
+        };
+
+        self.morph += (target - self.morph) * coeff;
+        self.transition = transition;
+    }
+
+    /// The current color of `layer`.
+    pub fn color(&self, layer: PaletteLayer) -> Rgba {
+        let t = self.morph as f32;
+        let ModeTransition { from, to, .. } = self.transition;
+
+        let from = self.modes[from as usize].gradient(layer).sample(t);
+        let to = self.modes[to as usize].gradient(layer).sample(t);
+
+        mix(from, to, self.transition_amount())
+    }
+
+    /// How strongly each post effect is currently applied.
+    pub fn post_fx(&self) -> PostFxSettings {
+        let ModeTransition { from, to, .. } = self.transition;
+
+        self.modes[from as usize]
+            .post
+            .mix(self.modes[to as usize].post, self.transition_amount())
+    }
+
+    /// How far the palette has moved to the mode being changed to.
+    fn transition_amount(&self) -> f32 {
+        // eased like the sweep of the EME's position
+        interp::cosine(0.0, 1.0, self.transition.progress) as f32
+    }
+
+    /// Mixes the palette's hand color into `gesture_color`, keeping its
+    /// alpha.
+    pub fn tint_hand(&self, gesture_color: Rgba) -> Rgba {
+        let tinted = mix(
+            gesture_color,
+            self.color(PaletteLayer::Hands),
+            HAND_TINT_AMOUNT,
+        );
+
+        Rgba { alpha: gesture_color.alpha, ..tinted }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Linearly interpolates each component of `a` and `b`.
+pub(super) fn mix(a: Rgba, b: Rgba, t: f32) -> Rgba {
+    let t = t.clamp(0.0, 1.0);
+    let lerp = |a: f32, b: f32| (b - a).mul_add(t, a);
+
+    Rgba::new(
+        lerp(a.red, b.red),
+        lerp(a.green, b.green),
+        lerp(a.blue, b.blue),
+        lerp(a.alpha, b.alpha),
+    )
+}
+
+/// Parses a color of the form `"#rrggbb"` or `"#rrggbbaa"`.
+fn parse_hex_color(s: &str) -> Result<Rgba, String> {
+    let err = || format!("invalid color \"{s}\", expected \"#rrggbb(aa)\"");
+
+    let hex = s.strip_prefix('#').ok_or_else(err)?;
+
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(err());
+    }
+
+    let component = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16)
+            .map(|c| c as f32 / 255.0)
+            .map_err(|_| err())
+    };
+
+    Ok(Rgba::new(
+        component(0)?,
+        component(2)?,
+        component(4)?,
+        if hex.len() == 8 { component(6)? } else { 1.0 },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-6;
+
+    #[test]
+    fn gradients_blend_between_stops() {
+        let red = Rgba::new(1.0, 0.0, 0.0, 1.0);
+        let green = Rgba::new(0.0, 1.0, 0.0, 1.0);
+        let gradient = Gradient::new(vec![
+            GradientStop { position: 1.0, color: green },
+            GradientStop { position: 0.5, color: red },
+        ])
+        .unwrap();
+
+        assert_eq!(gradient.sample(0.0).red, 1.0);
+
+        let middle = gradient.sample(0.75);
+        assert!((middle.red - 0.5).abs() < EPSILON);
+        assert!((middle.green - 0.5).abs() < EPSILON);
+
+        assert_eq!(gradient.sample(2.0).green, 1.0);
+        assert!(Gradient::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn palettes_are_loaded_per_mode() {
+        let palette = Palette::from_json_str(
+            r##"{
+                "B": {
+                    "hands": [{ "at": 0, "color": "#ff000080" }],
+                    "post": { "trails": 0.25 }
+                }
+            }"##,
+        )
+        .unwrap();
+
+        let hands = palette.modes[Mode::B as usize].hands.sample(0.3);
+        assert_eq!((hands.red, hands.green), (1.0, 0.0));
+        assert!((hands.alpha - 128.0 / 255.0).abs() < EPSILON);
+
+        let post = palette.modes[Mode::B as usize].post;
+        assert_eq!(post.trails, 0.25);
+        assert_eq!(post.glow, ModePalette::default_for(Mode::B).post.glow);
+
+        // other modes and layers keep their defaults
+        assert_eq!(palette.modes[0], ModePalette::default_for(Mode::A));
+
+        assert!(Palette::from_json_str(r#"{ "D": {} }"#).is_err());
+        assert!(Palette::from_json_str(
+            r##"{ "A": { "hands": [{ "at": 0, "color": "#ff00" }] } }"##
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn colors_sweep_between_modes() {
+        let mut palette = Palette::new();
+        let hands = RawHandPairCOM::default();
+
+        let a = palette.color(PaletteLayer::Hands);
+
+        let halfway =
+            ModeTransition { from: Mode::A, to: Mode::B, progress: 0.5 };
+        palette.update(&hands, 0.0, halfway, 0.0);
+        let mid = palette.color(PaletteLayer::Hands);
+
+        palette.update(&hands, 0.0, ModeTransition::settled(Mode::B), 0.0);
+        let b = palette.color(PaletteLayer::Hands);
+
+        assert_ne!(a, b);
+        assert!((mid.red - (a.red + b.red) * 0.5).abs() < EPSILON);
+    }
+}
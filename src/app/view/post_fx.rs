@@ -0,0 +1,230 @@
+//! Offscreen rendering of the visuals, so that post effects can be applied
+//! before they are presented.
+//!
+//! The hands and spectrum are drawn to a texture which is kept between
+//! frames. Rather than being cleared, each frame fades the previous one
+//! towards the background, which leaves trails behind anything moving. The
+//! texture is then copied to the window, and a vignette is drawn over it
+//! beneath the text overlays.
+
+use super::*;
+use nannou::wgpu;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::f32::consts::{SQRT_2, TAU};
+
+/// The most which the previous frame is kept by each new frame.
+const MAX_TRAIL_PERSISTENCE: f32 = 0.92;
+/// How far into the window the vignette starts, as a proportion of the
+/// distance from the center to the corners.
+const VIGNETTE_START: f32 = 0.45;
+/// The number of segments around the vignette.
+const VIGNETTE_RESOLUTION: usize = 64;
+
+/// How strongly each post effect is applied, each from `0.0` (off) to
+/// `1.0`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PostFxSettings {
+    /// The glow around each hand point.
+    pub glow: f32,
+    /// How long moving visuals leave trails for.
+    pub trails: f32,
+    /// How dark the edges of the window are.
+    pub vignette: f32,
+}
+
+impl PostFxSettings {
+    pub const fn new(glow: f32, trails: f32, vignette: f32) -> Self {
+        Self { glow, trails, vignette }
+    }
+
+    /// Linearly interpolates each effect between `self` and `other`.
+    pub fn mix(self, other: Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |a: f32, b: f32| (b - a).mul_add(t, a);
+
+        Self {
+            glow: lerp(self.glow, other.glow),
+            trails: lerp(self.trails, other.trails),
+            vignette: lerp(self.vignette, other.vignette),
+        }
+    }
+
+    /// Replaces any effects which are given in `value`, which is an object
+    /// of effect names to amounts.
+    pub(super) fn apply_json(&mut self, value: &Value) -> Result<(), String> {
+        let Some(effects) = value.as_object()
+        else {
+            return Err(String::from("expected an object of post effects"));
+        };
+
+        for (name, amount) in effects {
+            let Some(amount) = amount.as_f64()
+            else {
+                return Err(format!("expected \"{name}\" to be a number"));
+            };
+            let amount = amount.clamp(0.0, 1.0) as f32;
+
+            match name.as_str() {
+                "glow" => self.glow = amount,
+                "trails" => self.trails = amount,
+                "vignette" => self.vignette = amount,
+                _ => return Err(format!("unknown post effect \"{name}\"")),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The texture which the visuals are drawn to, along with what is needed to
+/// draw to it and copy it to the window.
+struct RenderTarget {
+    texture: wgpu::Texture,
+    renderer: nannou::draw::Renderer,
+    reshaper: wgpu::TextureReshaper,
+}
+
+impl RenderTarget {
+    fn new(frame: &Frame) -> Self {
+        let device = frame.device_queue_pair().device();
+        let sample_count = frame.texture_msaa_samples();
+
+        let texture = wgpu::TextureBuilder::new()
+            .size(frame.texture_size())
+            .usage(
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+            )
+            .sample_count(sample_count)
+            .format(Frame::TEXTURE_FORMAT)
+            .build(device);
+
+        let renderer = nannou::draw::RendererBuilder::new()
+            .build_from_texture_descriptor(device, texture.descriptor());
+
+        let view = texture.view().build();
+        let reshaper = wgpu::TextureReshaper::new(
+            device,
+            &view,
+            sample_count,
+            texture.sample_type(),
+            sample_count,
+            Frame::TEXTURE_FORMAT,
+        );
+
+        Self { texture, renderer, reshaper }
+    }
+}
+
+/// Draws the visuals offscreen and applies the post effects to them.
+///
+/// The render target is created on the first frame, and again whenever the
+/// window is resized.
+#[derive(Default)]
+pub struct PostFxChain {
+    target: RefCell<Option<RenderTarget>>,
+}
+
+impl PostFxChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws `draw_scene` to the offscreen texture over the faded previous
+    /// frame, and copies the result to `frame`.
+    pub fn render(
+        &self,
+        frame: &Frame,
+        settings: &PostFxSettings,
+        background: Rgba,
+        draw_scene: impl FnOnce(&Draw),
+    ) {
+        let mut target = self.target.borrow_mut();
+
+        let is_stale = target
+            .as_ref()
+            .map_or(true, |t| t.texture.size() != frame.texture_size());
+
+        if is_stale {
+            *target = Some(RenderTarget::new(frame));
+        }
+
+        let Some(target) = target.as_mut()
+        else {
+            return;
+        };
+
+        // drawn in points, like the frame, though the texture is in pixels
+        let scale = frame.texture_size()[0] as f32 / frame.rect().w();
+        let draw = Draw::new();
+        let scaled = draw.scale(scale);
+
+        if is_stale {
+            draw.background().color(background);
+        }
+        else {
+            let persistence = settings.trails.clamp(0.0, 1.0)
+                * MAX_TRAIL_PERSISTENCE;
+
+            scaled
+                .rect()
+                .wh(frame.rect().wh())
+                .color(Rgba { alpha: 1.0 - persistence, ..background });
+        }
+
+        draw_scene(&scaled);
+
+        let device = frame.device_queue_pair().device();
+        let mut encoder = frame.command_encoder();
+
+        target.renderer.render_to_texture(
+            device,
+            &mut encoder,
+            &draw,
+            &target.texture,
+        );
+        target
+            .reshaper
+            .encode_render_pass(frame.texture_view(), &mut encoder);
+    }
+}
+
+/// Darkens the edges of the window towards `background`.
+pub fn draw_vignette(
+    draw: &Draw,
+    frame: &Frame,
+    settings: &PostFxSettings,
+    background: Rgba,
+) {
+    if settings.vignette <= 0.0 {
+        return;
+    }
+
+    let r = frame.rect();
+    let outer = r.wh() * 0.5 * SQRT_2;
+    let inner = outer * VIGNETTE_START;
+
+    let clear = Rgba { alpha: 0.0, ..background };
+    let edge = Rgba { alpha: settings.vignette.clamp(0.0, 1.0), ..background };
+
+    // an inner and outer point at each angle
+    let points = (0..VIGNETTE_RESOLUTION).flat_map(|i| {
+        let angle = i as f32 / VIGNETTE_RESOLUTION as f32 * TAU;
+        let dir = vec2(angle.cos(), angle.sin());
+
+        [
+            ((dir * inner).extend(0.0), clear),
+            ((dir * outer).extend(0.0), edge),
+        ]
+    });
+
+    let indices = (0..VIGNETTE_RESOLUTION).flat_map(|i| {
+        let inner = i * 2;
+        let next = (i + 1) % VIGNETTE_RESOLUTION * 2;
+
+        [inner, inner + 1, next + 1, inner, next + 1, next]
+    });
+
+    draw.mesh().indexed_colored(points, indices);
+}
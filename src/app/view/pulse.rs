@@ -0,0 +1,198 @@
+//! Background pulses and hand scaling which follow the music.
+//!
+//! Each beat of the transport flashes the background towards the accent
+//! color, with the downbeat flashing brightest, and the level of the lowest
+//! audio bands swells the background and the hand glyphs between beats. This
+//! lets the visuals carry the musical pulse without any explicit meters.
+
+use super::*;
+use palette::mix;
+
+/// The default intensity of the pulses.
+pub const DEFAULT_PULSE_INTENSITY: f32 = 0.5;
+
+/// The time (in seconds) which a beat's flash takes to fade to about a
+/// third of its brightness.
+const FLASH_DECAY_TIME: f64 = 0.15;
+/// The brightness of the flash on beats other than the downbeat.
+const BEAT_FLASH: f64 = 0.5;
+/// The proportion of the audio bands, from the lowest, which drive the
+/// swell.
+const LOW_BAND_PROPORTION: f64 = 0.25;
+/// The time (in seconds) which the swell takes to follow the audio.
+const SWELL_SMOOTHING_TIME: f64 = 0.08;
+/// How much of the swell is mixed into the pulse, rather than the flash.
+const SWELL_WEIGHT: f64 = 0.6;
+/// How far the background moves towards the accent color at full
+/// intensity.
+const MAX_BACKGROUND_PULSE: f32 = 0.25;
+/// How much larger the hand glyphs grow at full intensity.
+const MAX_GLYPH_GROWTH: f32 = 0.5;
+
+/// Follows the transport's beats and the output's low band energy, which
+/// are turned into background pulses and hand glyph scaling.
+#[derive(Clone, Debug)]
+pub struct BeatPulse {
+    /// How strongly the pulses are shown, from `0.0` (off) to `1.0`.
+    intensity: f32,
+    /// The last whole beat which the transport was on.
+    last_beat: Option<u32>,
+    flash: f64,
+    swell: f64,
+}
+
+impl BeatPulse {
+    pub fn new(intensity: f32) -> Self {
+        Self {
+            intensity: intensity.clamp(0.0, 1.0),
+            last_beat: None,
+            flash: 0.0,
+            swell: 0.0,
+        }
+    }
+
+    pub const fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Sets how strongly the pulses are shown.
+    ///
+    /// Clamped to `[0.0 - 1.0]`.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity.clamp(0.0, 1.0);
+    }
+
+    /// Flashes on each new beat of `position`, fades the previous flash,
+    /// and follows the level of the lowest of `bands`.
+    ///
+    /// `position` is `None` if the transport's position isn't known.
+    pub fn update(
+        &mut self,
+        position: Option<MusicalPosition>,
+        bands: &[f64],
+        dt: f64,
+    ) {
+        self.flash *= (-dt / FLASH_DECAY_TIME).exp();
+
+        if let Some(position) = position {
+            let beat = position.total_beats.floor() as u32;
+
+            // the position only moves while the transport is running, and
+            // restarting it lands on a new downbeat
+            if self.last_beat.is_some_and(|last| last != beat) {
+                self.flash = if position.beat == 0 { 1.0 } else { BEAT_FLASH };
+            }
+
+            self.last_beat = Some(beat);
+        }
+
+        let num_low = (bands.len() as f64 * LOW_BAND_PROPORTION).ceil();
+        let target = if num_low > 0.0 {
+            bands.iter().take(num_low as usize).sum::<f64>() / num_low
+        }
+        else {
+            0.0
+        };
+
+        let coeff = if SWELL_SMOOTHING_TIME > 0.0 {
+            1.0 - (-dt / SWELL_SMOOTHING_TIME).exp()
+        }
+        else {
+            1.0
+        };
+
+        self.swell += (target.clamp(0.0, 1.0) - self.swell) * coeff;
+    }
+
+    /// The current strength of the pulse, from `0.0` to `1.0`, scaled by
+    /// its intensity.
+    pub fn level(&self) -> f32 {
+        let level = lerp(self.flash, self.swell, SWELL_WEIGHT);
+
+        level.clamp(0.0, 1.0) as f32 * self.intensity
+    }
+
+    /// `background` moved towards `accent` by the pulse.
+    pub fn background(&self, background: Rgba, accent: Rgba) -> Rgba {
+        let amount = self.level() * MAX_BACKGROUND_PULSE;
+        let pulsed = mix(background, accent, amount);
+
+        // the accent's alpha shouldn't make the background translucent
+        Rgba { alpha: background.alpha, ..pulsed }
+    }
+
+    /// How much the hand glyphs are scaled by the pulse.
+    pub fn glyph_scale(&self) -> f32 {
+        self.level().mul_add(MAX_GLYPH_GROWTH, 1.0)
+    }
+}
+
+impl Default for BeatPulse {
+    fn default() -> Self {
+        Self::new(DEFAULT_PULSE_INTENSITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(total_beats: f64) -> MusicalPosition {
+        MusicalPosition {
+            bar: total_beats as u32 / 4,
+            beat: total_beats as u32 % 4,
+            beat_fraction: total_beats.fract(),
+            total_beats,
+        }
+    }
+
+    #[test]
+    fn downbeats_flash_brighter_than_other_beats() {
+        let mut pulse = BeatPulse::new(1.0);
+        pulse.update(Some(position(3.9)), &[], 0.01);
+
+        pulse.update(Some(position(4.0)), &[], 0.01);
+        let downbeat = pulse.level();
+
+        pulse.update(Some(position(4.9)), &[], 1.0);
+        pulse.update(Some(position(5.0)), &[], 0.01);
+        let beat = pulse.level();
+
+        assert!(downbeat > beat);
+        assert!(beat > 0.0);
+    }
+
+    #[test]
+    fn flash_fades_while_the_transport_is_stopped() {
+        let mut pulse = BeatPulse::new(1.0);
+        pulse.update(Some(position(3.9)), &[], 0.01);
+        pulse.update(Some(position(4.0)), &[], 0.01);
+        let flash = pulse.level();
+
+        for _ in 0..100 {
+            pulse.update(Some(position(4.0)), &[], 0.02);
+        }
+
+        assert!(pulse.level() < flash * 0.01);
+    }
+
+    #[test]
+    fn low_bands_swell_the_glyphs() {
+        let mut bands = vec![0.0; 32];
+        bands[..8].fill(1.0);
+
+        let mut quiet = BeatPulse::new(1.0);
+        let mut loud = BeatPulse::new(1.0);
+
+        for _ in 0..50 {
+            quiet.update(None, &[0.0; 32], 0.02);
+            loud.update(None, &bands, 0.02);
+        }
+
+        assert!(loud.glyph_scale() > quiet.glyph_scale());
+        assert!(epsilon_eq(quiet.glyph_scale() as f64, 1.0));
+
+        loud.set_intensity(0.0);
+        assert!(epsilon_eq(loud.glyph_scale() as f64, 1.0));
+    }
+}
@@ -0,0 +1,293 @@
+//! A ticker of text which curators set remotely, such as titles, credits
+//! or instructions, shown near the bottom of the window.
+//!
+//! Messages are set over the OSC control namespace:
+//!
+//! - `/maestro/ticker <text> [duration] [delay]` shows `text` after `delay`
+//!   seconds (or straight away), for `duration` seconds (or until it is
+//!   replaced). A duration of `0` also shows it until it is replaced.
+//! - `/maestro/ticker/clear` fades out every message, and drops any which
+//!   haven't started yet.
+//!
+//! Each message fades in when it starts, and fades out when it ends or when
+//! the next message starts. Messages which are too wide for the window
+//! scroll across it.
+
+use super::*;
+use nannou_osc::Type as OSCType;
+use std::time::{Duration, Instant};
+
+/// The time (in seconds) which messages take to fade in and out.
+const FADE_TIME: f64 = 1.0;
+/// The most messages which may be shown or waiting to be shown.
+const MAX_MESSAGES: usize = 16;
+/// The speed at which wide messages scroll, in points per second.
+const SCROLL_SPEED: f32 = 80.0;
+const FONT_SIZE: u32 = 20;
+/// The rough width of each character, as a proportion of the font size.
+const CHAR_WIDTH: f32 = 0.55;
+/// The height of the ticker above the bottom of the window.
+const TICKER_HEIGHT: f32 = 150.0;
+
+#[derive(Clone, Debug)]
+struct TickerMessage {
+    text: String,
+    start: Instant,
+    /// When the message has finished fading out, if it is shown until it is
+    /// replaced.
+    end: Option<Instant>,
+}
+
+/// The messages shown on the ticker, and those waiting to be shown.
+#[derive(Clone, Debug)]
+pub struct Ticker {
+    /// Every message, in the order which they start.
+    messages: Vec<TickerMessage>,
+    /// When the ticker was last updated, which it is drawn at.
+    now: Instant,
+}
+
+impl Ticker {
+    pub fn new() -> Self {
+        Self { messages: Vec::new(), now: Instant::now() }
+    }
+
+    /// Shows `text` from `delay` after `now`, for `duration` or until it is
+    /// replaced. The oldest message is dropped if there are too many.
+    pub fn schedule(
+        &mut self,
+        text: &str,
+        delay: Duration,
+        duration: Option<Duration>,
+        now: Instant,
+    ) {
+        let start = now + delay;
+        let message = TickerMessage {
+            text: String::from(text),
+            start,
+            end: duration.map(|duration| start + duration),
+        };
+
+        if self.messages.len() == MAX_MESSAGES {
+            _ = self.messages.remove(0);
+        }
+
+        // later messages with the same start are shown over earlier ones
+        let idx = self.messages.partition_point(|msg| msg.start <= start);
+        self.messages.insert(idx, message);
+
+        for i in idx.saturating_sub(1)..=idx {
+            self.end_when_replaced(i);
+        }
+    }
+
+    /// Schedules a message from the arguments of a `/maestro/ticker` OSC
+    /// message: its text, and optionally its duration and delay in seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arguments aren't a string followed by up to
+    /// two non-negative numbers.
+    pub fn schedule_from_osc(
+        &mut self,
+        args: &[OSCType],
+        now: Instant,
+    ) -> Result<(), String> {
+        let Some(OSCType::String(text)) = args.first()
+        else {
+            return Err(String::from("expected the ticker's text"));
+        };
+
+        let duration = osc_seconds(args.get(1))?
+            .filter(|&secs| secs > 0.0)
+            .map(Duration::from_secs_f64);
+        let delay = osc_seconds(args.get(2))?
+            .map_or(Duration::ZERO, Duration::from_secs_f64);
+
+        self.schedule(text, delay, duration, now);
+
+        Ok(())
+    }
+
+    /// Fades out each message which has started, and drops the rest.
+    pub fn clear(&mut self, now: Instant) {
+        let fade_end = now + Duration::from_secs_f64(FADE_TIME);
+
+        self.messages.retain(|msg| msg.start <= now);
+
+        for msg in &mut self.messages {
+            msg.end = Some(msg.end.map_or(fade_end, |end| end.min(fade_end)));
+        }
+    }
+
+    /// Moves the ticker to `now`, and drops the messages which have ended.
+    pub fn update(&mut self, now: Instant) {
+        self.now = now;
+        self.messages.retain(|msg| msg.end.map_or(true, |end| end > now));
+    }
+
+    /// Whether any message is shown or waiting to be shown.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Each message which is shown at `now`, with its opacity and how long
+    /// it has been shown for in seconds.
+    fn visible(&self, now: Instant) -> Vec<(&str, f32, f32)> {
+        self.messages
+            .iter()
+            .filter(|msg| msg.start <= now)
+            .filter_map(|msg| {
+                let shown_for = (now - msg.start).as_secs_f64();
+                let fade_in = shown_for / FADE_TIME;
+                let fade_out = msg.end.map_or(1.0, |end| {
+                    end.saturating_duration_since(now).as_secs_f64()
+                        / FADE_TIME
+                });
+
+                let alpha = fade_in.min(fade_out).clamp(0.0, 1.0) as f32;
+
+                (alpha > 0.0).then_some((
+                    msg.text.as_str(),
+                    alpha,
+                    shown_for as f32,
+                ))
+            })
+            .collect()
+    }
+
+    /// Ends the message at `idx` once the message after it has faded in,
+    /// if it would otherwise be shown for longer.
+    fn end_when_replaced(&mut self, idx: usize) {
+        let Some(next_start) = self.messages.get(idx + 1).map(|msg| msg.start)
+        else {
+            return;
+        };
+
+        let replaced = next_start + Duration::from_secs_f64(FADE_TIME);
+        let msg = &mut self.messages[idx];
+
+        msg.end = Some(msg.end.map_or(replaced, |end| end.min(replaced)));
+    }
+}
+
+impl Default for Ticker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drawable for Ticker {
+    fn draw(&self, draw: &Draw, frame: &Frame) {
+        let r = frame.rect();
+        let y = r.bottom() + TICKER_HEIGHT;
+        let base = if LIGHT_MODE {
+            Rgba::new(0.1, 0.1, 0.1, 1.0)
+        }
+        else {
+            Rgba::new(0.95, 0.95, 0.95, 1.0)
+        };
+
+        for (msg, alpha, shown_for) in self.visible(self.now) {
+            let width =
+                msg.chars().count() as f32 * FONT_SIZE as f32 * CHAR_WIDTH;
+
+            // wide messages enter from the right and loop around
+            let x = if width <= r.w() * 0.9 {
+                0.0
+            }
+            else {
+                let distance = (shown_for * SCROLL_SPEED) % (r.w() + width);
+                r.right() + width * 0.5 - distance
+            };
+
+            draw.text(msg)
+                .color(Rgba { alpha, ..base })
+                .xy(vec2(x, y))
+                .wh(vec2(width.max(r.w()), FONT_SIZE as f32 * 2.0))
+                .no_line_wrap()
+                .justify(text::Justify::Center)
+                .font_size(FONT_SIZE);
+        }
+    }
+}
+
+/// The number of seconds in `arg`, if it is given.
+fn osc_seconds(arg: Option<&OSCType>) -> Result<Option<f64>, String> {
+    let secs = match arg {
+        None => return Ok(None),
+        Some(OSCType::Float(secs)) => *secs as f64,
+        Some(OSCType::Double(secs)) => *secs,
+        Some(OSCType::Int(secs)) => *secs as f64,
+        Some(_) => return Err(String::from("expected a number of seconds")),
+    };
+
+    if secs.is_finite() && secs >= 0.0 {
+        Ok(Some(secs))
+    }
+    else {
+        Err(format!("expected a non-negative number of seconds, got {secs}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: f64) -> Duration {
+        Duration::from_secs_f64(secs)
+    }
+
+    #[test]
+    fn messages_fade_in_and_out() {
+        let now = Instant::now();
+        let mut ticker = Ticker::new();
+        ticker.schedule("Welcome", secs(2.0), Some(secs(10.0)), now);
+
+        assert!(ticker.visible(now + secs(1.0)).is_empty());
+
+        let fading_in = ticker.visible(now + secs(2.5))[0].1;
+        let shown = ticker.visible(now + secs(6.0))[0].1;
+        let fading_out = ticker.visible(now + secs(11.5))[0].1;
+
+        assert!(fading_in < shown);
+        assert!(fading_out < shown);
+
+        ticker.update(now + secs(12.5));
+        assert!(ticker.is_empty());
+    }
+
+    #[test]
+    fn next_message_replaces_the_last() {
+        let now = Instant::now();
+        let mut ticker = Ticker::new();
+        ticker.schedule("Title", Duration::ZERO, None, now);
+        ticker.schedule("Credits", secs(5.0), None, now);
+
+        let crossfade = ticker.visible(now + secs(5.5));
+        assert_eq!(crossfade.len(), 2);
+
+        ticker.update(now + secs(7.0));
+        let visible = ticker.visible(now + secs(7.0));
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].0, "Credits");
+    }
+
+    #[test]
+    fn osc_arguments_are_validated() {
+        let now = Instant::now();
+        let mut ticker = Ticker::new();
+
+        let text = OSCType::String(String::from("Credits"));
+        assert!(ticker
+            .schedule_from_osc(&[text.clone(), OSCType::Float(5.0)], now)
+            .is_ok());
+        assert!(ticker.schedule_from_osc(&[OSCType::Int(1)], now).is_err());
+        assert!(ticker
+            .schedule_from_osc(&[text, OSCType::Float(-1.0)], now)
+            .is_err());
+
+        ticker.clear(now);
+        assert!(ticker.visible(now + secs(2.0)).is_empty());
+    }
+}
@@ -0,0 +1,389 @@
+//! A guided tutorial which walks a new visitor through each gesture.
+//!
+//! The tutorial is offered on the idle screen, and is started by giving a
+//! thumbs up. Each step asks for one gesture and waits for it to be
+//! published as a [`GestureEvent`], then rewards the visitor with a check
+//! mark and a chime before moving on. All of its text is looked up in the
+//! string table, so it follows the installation's language.
+
+use super::*;
+use hands::gestures::GestureEvent;
+use std::f32::consts::TAU;
+
+/// The time which each step's reward is shown for, in seconds.
+const REWARD_TIME: f64 = 1.5;
+/// The time which the closing message is shown for, in seconds.
+const FINISHED_TIME: f64 = 4.0;
+/// The time without hands before the tutorial is abandoned, in seconds.
+const ABANDON_TIME: f64 = 8.0;
+/// The time which each caption takes to fade in, in seconds.
+const CAPTION_FADE_TIME: f64 = 0.4;
+const CAPTION_FONT_SIZE: u32 = 22;
+/// The height of the caption below the top of the window.
+const CAPTION_HEIGHT: f32 = 120.0;
+/// The largest radius of the ring around each reward's check mark.
+const REWARD_RING_RADIUS: f32 = 160.0;
+/// The number of segments around the reward's ring.
+const REWARD_RING_RESOLUTION: usize = 64;
+/// The space between the dots which show the tutorial's progress.
+const PROGRESS_DOT_SPACING: f32 = 24.0;
+
+/// A gesture which the tutorial teaches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TutorialStep {
+    Open,
+    Closed,
+    Pinch,
+    Swipe,
+}
+
+impl TutorialStep {
+    /// Every step, in the order which they are taught.
+    pub const ALL: [Self; 4] =
+        [Self::Open, Self::Closed, Self::Pinch, Self::Swipe];
+
+    /// The key of the text which asks for the step's gesture.
+    const fn caption(self) -> &'static str {
+        match self {
+            Self::Open => "tutorial.open",
+            Self::Closed => "tutorial.close",
+            Self::Pinch => "tutorial.pinch",
+            Self::Swipe => "tutorial.swipe",
+        }
+    }
+
+    /// Whether `event` is the step's gesture.
+    const fn is_completed_by(self, event: GestureEvent) -> bool {
+        matches!(
+            (self, event),
+            (Self::Open, GestureEvent::Open)
+                | (Self::Closed, GestureEvent::Closed)
+                | (Self::Pinch, GestureEvent::Pinch)
+                | (Self::Swipe, GestureEvent::Swipe(_))
+        )
+    }
+
+    /// The MIDI note of the chime which is played when the step is
+    /// completed. The steps rise through a major chord.
+    pub const fn reward_note(self) -> f64 {
+        match self {
+            Self::Open => 72.0,
+            Self::Closed => 76.0,
+            Self::Pinch => 79.0,
+            Self::Swipe => 84.0,
+        }
+    }
+}
+
+/// Where a visitor is in the tutorial.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TutorialState {
+    /// Waiting for the gesture of the step at `idx`, which has been asked
+    /// for over `elapsed` seconds.
+    Waiting { idx: usize, elapsed: f64 },
+    /// The step at `idx` was completed, and its reward has been shown for
+    /// `elapsed` seconds.
+    Rewarding { idx: usize, elapsed: f64 },
+    /// Every step was completed, which has been shown for `elapsed`
+    /// seconds.
+    Finished { elapsed: f64 },
+}
+
+/// Walks a visitor through each gesture, one step at a time.
+#[derive(Clone, Debug, Default)]
+pub struct Tutorial {
+    /// The tutorial's progress, if it is running.
+    state: Option<TutorialState>,
+    /// The time since hands were last seen, in seconds.
+    time_without_hands: f64,
+}
+
+impl Tutorial {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the tutorial is running.
+    pub const fn is_active(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Starts the tutorial from its first step.
+    pub fn start(&mut self) {
+        self.state = Some(TutorialState::Waiting { idx: 0, elapsed: 0.0 });
+        self.time_without_hands = 0.0;
+    }
+
+    /// Stops the tutorial, wherever it is.
+    pub fn stop(&mut self) {
+        self.state = None;
+    }
+
+    /// Moves the tutorial on by `dt` seconds, checking `events` for the
+    /// current step's gesture. The tutorial is abandoned if there have been
+    /// no hands for a while.
+    ///
+    /// Returns the step which was completed, if one was.
+    pub fn update(
+        &mut self,
+        events: &[GestureEvent],
+        hands_present: bool,
+        dt: f64,
+    ) -> Option<TutorialStep> {
+        let state = self.state?;
+
+        self.time_without_hands =
+            if hands_present { 0.0 } else { self.time_without_hands + dt };
+
+        if self.time_without_hands >= ABANDON_TIME {
+            self.stop();
+            return None;
+        }
+
+        let mut completed = None;
+
+        self.state = match state {
+            TutorialState::Waiting { idx, elapsed } => {
+                let step = TutorialStep::ALL[idx];
+
+                if events.iter().any(|&e| step.is_completed_by(e)) {
+                    completed = Some(step);
+                    Some(TutorialState::Rewarding { idx, elapsed: 0.0 })
+                }
+                else {
+                    Some(TutorialState::Waiting { idx, elapsed: elapsed + dt })
+                }
+            }
+            TutorialState::Rewarding { idx, elapsed } => {
+                let elapsed = elapsed + dt;
+
+                if elapsed < REWARD_TIME {
+                    Some(TutorialState::Rewarding { idx, elapsed })
+                }
+                else if idx + 1 < TutorialStep::ALL.len() {
+                    Some(TutorialState::Waiting { idx: idx + 1, elapsed: 0.0 })
+                }
+                else {
+                    Some(TutorialState::Finished { elapsed: 0.0 })
+                }
+            }
+            TutorialState::Finished { elapsed } => {
+                let elapsed = elapsed + dt;
+                (elapsed < FINISHED_TIME)
+                    .then_some(TutorialState::Finished { elapsed })
+            }
+        };
+
+        completed
+    }
+
+    /// The step which is being taught, if the tutorial is running.
+    pub fn current_step(&self) -> Option<TutorialStep> {
+        match self.state? {
+            TutorialState::Waiting { idx, .. }
+            | TutorialState::Rewarding { idx, .. } => {
+                Some(TutorialStep::ALL[idx])
+            }
+            TutorialState::Finished { .. } => None,
+        }
+    }
+
+    /// The number of steps which have been completed.
+    fn num_completed(&self) -> usize {
+        match self.state {
+            None => 0,
+            Some(TutorialState::Waiting { idx, .. }) => idx,
+            Some(TutorialState::Rewarding { idx, .. }) => idx + 1,
+            Some(TutorialState::Finished { .. }) => TutorialStep::ALL.len(),
+        }
+    }
+
+    /// Draws the current step's caption and the tutorial's progress near
+    /// the top of the window, and any reward in the middle of it in
+    /// `accent`.
+    pub fn draw(
+        &self,
+        draw: &Draw,
+        frame: &Frame,
+        strings: &StringTable,
+        accent: Rgba,
+    ) {
+        let Some(state) = self.state
+        else {
+            return;
+        };
+
+        let (caption, elapsed) = match state {
+            TutorialState::Waiting { idx, elapsed } => (
+                String::from(strings.get(TutorialStep::ALL[idx].caption())),
+                elapsed,
+            ),
+            TutorialState::Rewarding { elapsed, .. } => {
+                draw_reward(draw, elapsed, accent);
+                (String::from(strings.get("tutorial.well_done")), elapsed)
+            }
+            TutorialState::Finished { elapsed } => {
+                (String::from(strings.get("tutorial.finished")), elapsed)
+            }
+        };
+
+        let r = frame.rect();
+        let alpha = (elapsed / CAPTION_FADE_TIME).clamp(0.0, 1.0) as f32;
+        let color = if LIGHT_MODE {
+            Rgba::new(0.1, 0.1, 0.1, 1.0)
+        }
+        else {
+            Rgba::new(0.95, 0.95, 0.95, 1.0)
+        };
+
+        let title = strings.format(
+            "tutorial.title",
+            &[
+                ("completed", &self.num_completed()),
+                ("num_steps", &TutorialStep::ALL.len()),
+            ],
+        );
+
+        draw.text(&title)
+            .color(Rgba { alpha: 0.6, ..color })
+            .xy(vec2(0.0, r.top() - CAPTION_HEIGHT + 40.0))
+            .wh(vec2(r.w(), 30.0))
+            .justify(text::Justify::Center)
+            .font_size(14);
+        draw.text(&caption)
+            .color(Rgba { alpha, ..color })
+            .xy(vec2(0.0, r.top() - CAPTION_HEIGHT))
+            .wh(vec2(r.w(), CAPTION_FONT_SIZE as f32 * 2.0))
+            .justify(text::Justify::Center)
+            .font_size(CAPTION_FONT_SIZE);
+
+        self.draw_progress(draw, r.top() - CAPTION_HEIGHT - 40.0, accent);
+    }
+
+    /// Draws a dot for each step at `y`, which is filled once the step is
+    /// completed.
+    fn draw_progress(&self, draw: &Draw, y: f32, accent: Rgba) {
+        let num_steps = TutorialStep::ALL.len();
+        let left = -PROGRESS_DOT_SPACING * (num_steps - 1) as f32 * 0.5;
+
+        for i in 0..num_steps {
+            let xy = vec2(left + PROGRESS_DOT_SPACING * i as f32, y);
+
+            if i < self.num_completed() {
+                draw.ellipse().xy(xy).radius(6.0).color(accent);
+            }
+            else {
+                draw.ellipse()
+                    .xy(xy)
+                    .radius(6.0)
+                    .no_fill()
+                    .stroke_color(Rgba { alpha: 0.5, ..accent })
+                    .stroke_weight(1.5);
+            }
+        }
+    }
+}
+
+/// Draws a check mark in the middle of the window, with a ring which
+/// expands and fades over the reward's time.
+fn draw_reward(draw: &Draw, elapsed: f64, accent: Rgba) {
+    let t = (elapsed / REWARD_TIME).clamp(0.0, 1.0) as f32;
+    let radius = REWARD_RING_RADIUS * (1.0 - (1.0 - t).powi(3));
+    let alpha = accent.alpha.max(0.5) * (1.0 - t);
+
+    let points = (0..=REWARD_RING_RESOLUTION).map(|i| {
+        let angle = i as f32 / REWARD_RING_RESOLUTION as f32 * TAU;
+        vec2(angle.cos(), angle.sin()) * radius
+    });
+
+    draw.polyline()
+        .weight(4.0)
+        .color(Rgba { alpha, ..accent })
+        .points(points);
+
+    draw.text("\u{2713}")
+        .color(Rgba { alpha: 1.0 - t * t, ..accent })
+        .wh(vec2(200.0, 200.0))
+        .font_size(96);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hands::gestures::SwipeDirection;
+
+    const DT: f64 = 0.1;
+
+    /// Updates `tutorial` with no events until its reward is over.
+    fn finish_reward(tutorial: &mut Tutorial) {
+        for _ in 0..(REWARD_TIME / DT) as usize + 1 {
+            assert_eq!(tutorial.update(&[], true, DT), None);
+        }
+    }
+
+    #[test]
+    fn steps_are_completed_in_order() {
+        let mut tutorial = Tutorial::new();
+        tutorial.start();
+
+        // gestures for later steps don't count
+        assert_eq!(tutorial.update(&[GestureEvent::Pinch], true, DT), None);
+        assert_eq!(
+            tutorial.update(&[GestureEvent::Open], true, DT),
+            Some(TutorialStep::Open)
+        );
+
+        // nor do gestures made while the reward is shown
+        assert_eq!(tutorial.update(&[GestureEvent::Closed], true, DT), None);
+        finish_reward(&mut tutorial);
+
+        assert_eq!(tutorial.current_step(), Some(TutorialStep::Closed));
+    }
+
+    #[test]
+    fn finishes_after_every_step() {
+        let mut tutorial = Tutorial::new();
+        tutorial.start();
+
+        let events = [
+            GestureEvent::Open,
+            GestureEvent::Closed,
+            GestureEvent::Pinch,
+            GestureEvent::Swipe(SwipeDirection::Left),
+        ];
+
+        for (event, step) in events.into_iter().zip(TutorialStep::ALL) {
+            assert_eq!(tutorial.update(&[event], true, DT), Some(step));
+            finish_reward(&mut tutorial);
+        }
+
+        assert!(tutorial.is_active());
+        assert_eq!(tutorial.current_step(), None);
+
+        for _ in 0..(FINISHED_TIME / DT) as usize + 1 {
+            _ = tutorial.update(&[], true, DT);
+        }
+
+        assert!(!tutorial.is_active());
+    }
+
+    #[test]
+    fn is_abandoned_without_hands() {
+        let mut tutorial = Tutorial::new();
+        tutorial.start();
+
+        for _ in 0..(ABANDON_TIME / DT) as usize - 1 {
+            _ = tutorial.update(&[], false, DT);
+        }
+
+        // a hand returning restarts the timeout
+        _ = tutorial.update(&[], true, DT);
+        assert!(tutorial.is_active());
+
+        for _ in 0..(ABANDON_TIME / DT) as usize + 1 {
+            _ = tutorial.update(&[], false, DT);
+        }
+
+        assert!(!tutorial.is_active());
+    }
+}
@@ -2,10 +2,11 @@
 
 use super::*;
 
+#[allow(clippy::module_inception)]
 pub mod delay;
 pub mod stereo_delay;
 pub mod ring_buffer;
 
 pub use delay::Delay;
-pub use stereo_delay::StereoDelay;
+pub use stereo_delay::{DelayTap, DelayTime, StereoDelay};
 pub use ring_buffer::RingBuffer;
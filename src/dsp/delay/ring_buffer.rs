@@ -84,8 +84,26 @@ impl RingBuffer {
 
     /// Reads the delayed element from the `RingBuffer`.
     pub fn read(&mut self) -> f64 {
+        let delay_samples = self.sample_rate * self.delay_secs.next();
+        self.read_samples(delay_samples)
+    }
+
+    /// Reads the element `delay_secs` behind the write position, ignoring
+    /// (and without advancing) the buffer's own delay time. Used for extra
+    /// taps of the same buffer.
+    ///
+    /// `delay_secs` is clamped to the maximum delay time.
+    pub fn read_at(&self, delay_secs: f64) -> f64 {
+        let max_samples = (self.size() - 1) as f64;
+        let delay_samples =
+            (self.sample_rate * delay_secs).clamp(0.0, max_samples);
+
+        self.read_samples(delay_samples)
+    }
+
+    fn read_samples(&self, delay_samples: f64) -> f64 {
         use InterpType as IT;
-        let (read_pos, interp) = self.get_read_pos_and_interp();
+        let (read_pos, interp) = self.get_read_pos_and_interp(delay_samples);
         // r1 is the same as read_pos
         let (r0, r1, r2, r3) =
             if matches!(self.interpolation_type, IT::NoInterp) {
@@ -204,8 +222,7 @@ impl RingBuffer {
         size / self.sample_rate
     }
 
-    fn get_read_pos_and_interp(&mut self) -> (usize, f64) {
-        let delay_samples = self.sample_rate * self.delay_secs.next();
+    fn get_read_pos_and_interp(&self, delay_samples: f64) -> (usize, f64) {
         // the exact delay sample, i.e. read position
         let samples_exact = delay_samples.floor();
         // the interpolation between this sample and the next
@@ -1,15 +1,82 @@
-//! A simple stereo delay.
+//! A stereo delay with ping-pong, multi-tap and tempo-synced modes.
 
 use super::*;
+use std::f64::consts::FRAC_PI_4;
+
+/// The most extra taps a `StereoDelay` can use.
+pub const MAX_DELAY_TAPS: usize = 8;
+/// The time taken for a tap to reach a new delay time, in milliseconds.
+const TAP_SMOOTHING_MS: f64 = 100.0;
+
+/// A delay time, which may follow the tempo.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DelayTime {
+    Secs(f64),
+    /// A number of beats, e.g. `0.5` for an eighth note.
+    Beats(f64),
+}
+
+impl DelayTime {
+    /// The delay time in seconds at `bpm`.
+    pub fn secs(self, bpm: f64) -> f64 {
+        match self {
+            Self::Secs(secs) => secs,
+            Self::Beats(beats) => beats * 60.0 / bpm,
+        }
+    }
+}
+
+impl Default for DelayTime {
+    fn default() -> Self {
+        Self::Secs(0.0)
+    }
+}
+
+/// An extra read position of a `StereoDelay`, which is mixed into its
+/// output but doesn't feed back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DelayTap {
+    pub time: DelayTime,
+    /// The (linear) level of the tap.
+    pub level: f64,
+    /// The position of the tap, from `-1.0` (left) to `1.0` (right).
+    pub pan: f64,
+}
+
+impl DelayTap {
+    /// The left and right gains of the tap, which are panned with constant
+    /// power.
+    fn gains(&self) -> (f64, f64) {
+        let angle = (self.pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+        (self.level * angle.cos(), self.level * angle.sin())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ActiveTap {
+    tap: DelayTap,
+    time_secs: Smoother<f64>,
+}
 
 /// A stereo delay processor, capable of ping-pong effects via channel
 /// cross-feeding.
+///
+/// Up to [`MAX_DELAY_TAPS`] extra taps may read from the delay, and its
+/// feedback may be filtered. Any delay time may be set in beats, which
+/// follow the tempo set with [`set_bpm()`](Self::set_bpm).
 #[derive(Clone, Debug, Default)]
 pub struct StereoDelay {
     buffer_l: RingBuffer,
     buffer_r: RingBuffer,
     feedback_amount: f64,
     use_ping_pong: bool,
+
+    time: DelayTime,
+    bpm: f64,
+
+    taps: [Option<ActiveTap>; MAX_DELAY_TAPS],
+    /// The left and right filters of the feedback path, if it is filtered.
+    feedback_filters: Option<[BiquadFilter; 2]>,
 }
 
 impl StereoDelay {
@@ -26,6 +93,12 @@ impl StereoDelay {
             buffer_r: buffer,
             feedback_amount: 0.0,
             use_ping_pong: false,
+
+            time: DelayTime::default(),
+            bpm: DEFAULT_BPM,
+
+            taps: std::array::from_fn(|_| None),
+            feedback_filters: None,
         }
     }
 
@@ -48,8 +121,7 @@ impl StereoDelay {
 
     /// Sets the delay time of the delay in seconds.
     pub fn set_delay_time(&mut self, delay_secs: f64) {
-        self.buffer_l.set_delay_time(delay_secs);
-        self.buffer_r.set_delay_time(delay_secs);
+        self.set_time(DelayTime::Secs(delay_secs));
     }
 
     /// Sets the delay time of the delay in samples.
@@ -57,11 +129,99 @@ impl StereoDelay {
         self.set_delay_time(delay_samples / self.get_sample_rate());
     }
 
+    /// Sets the delay time of the delay, which is clamped to the maximum
+    /// delay time.
+    pub fn set_time(&mut self, time: DelayTime) {
+        self.time = time;
+
+        let secs = self.clamp_time(time);
+        self.buffer_l.set_delay_time(secs);
+        self.buffer_r.set_delay_time(secs);
+    }
+
+    /// Sets the tempo which delay times in beats follow.
+    pub fn set_bpm(&mut self, bpm: f64) {
+        self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        self.set_time(self.time);
+
+        let bpm = self.bpm;
+        let max_secs = self.max_delay_time_secs();
+
+        for active in self.taps.iter_mut().flatten() {
+            let secs = active.tap.time.secs(bpm).clamp(0.0, max_secs);
+            active.time_secs.set_target_value(secs);
+        }
+    }
+
     /// Sets the feedback level of the delay.
     pub fn set_feedback_amount(&mut self, feedback: f64) {
         self.feedback_amount = feedback.clamp(0.0, 1.0);
     }
 
+    /// Filters the feedback path with `params`, or removes the filter if
+    /// `params` is `None`.
+    pub fn set_feedback_filter(&mut self, params: Option<&BiquadParams>) {
+        let Some(params) = params
+        else {
+            self.feedback_filters = None;
+            return;
+        };
+
+        let sample_rate = self.get_sample_rate();
+        let filters = self.feedback_filters.get_or_insert_with(|| {
+            [BiquadFilter::new(sample_rate), BiquadFilter::new(sample_rate)]
+        });
+
+        for filter in filters {
+            filter.set_params(params);
+        }
+    }
+
+    /// Sets the tap at `idx`, whose time is clamped to the maximum delay
+    /// time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of range.
+    pub fn set_tap(&mut self, idx: usize, tap: Option<DelayTap>) {
+        let Some(tap) = tap
+        else {
+            self.taps[idx] = None;
+            return;
+        };
+
+        let secs = self.clamp_time(tap.time);
+
+        if let Some(active) = &mut self.taps[idx] {
+            active.tap = tap;
+            active.time_secs.set_target_value(secs);
+        }
+        else {
+            let mut time_secs =
+                Smoother::new(TAP_SMOOTHING_MS, secs, self.get_sample_rate());
+            time_secs.set_start_value(secs);
+
+            self.taps[idx] = Some(ActiveTap { tap, time_secs });
+        }
+    }
+
+    /// Adds `tap` in the first free slot, returning the slot, or `None` if
+    /// every slot is used.
+    pub fn add_tap(&mut self, tap: DelayTap) -> Option<usize> {
+        let idx = self.taps.iter().position(Option::is_none)?;
+        self.set_tap(idx, Some(tap));
+
+        Some(idx)
+    }
+
+    pub fn clear_taps(&mut self) {
+        self.taps = std::array::from_fn(|_| None);
+    }
+
+    pub fn taps(&self) -> impl Iterator<Item = &DelayTap> {
+        self.taps.iter().flatten().map(|active| &active.tap)
+    }
+
     /// Returns the maximum delay time of the stereo delay.
     pub fn max_delay_time_secs(&self) -> f64 {
         self.buffer_l.max_delay_secs()
@@ -80,10 +240,54 @@ impl StereoDelay {
             .set_smoothing(delay::DEFAULT_DELAY_SMOOTHING, smoothing_time_secs);
     }
 
+    /// Clears the contents of the delay, so nothing previously pushed is
+    /// heard.
+    pub fn clear(&mut self) {
+        self.buffer_l.clear();
+        self.buffer_r.clear();
+    }
+
     /// Resets the sample rate of the stereo delay.
     pub fn set_sample_rate(&mut self, sample_rate: f64) {
         self.buffer_l.set_sample_rate(sample_rate);
         self.buffer_r.set_sample_rate(sample_rate);
+
+        for filter in self.feedback_filters.iter_mut().flatten() {
+            filter.reset_sample_rate(sample_rate);
+        }
+    }
+
+    fn clamp_time(&self, time: DelayTime) -> f64 {
+        time.secs(self.bpm).clamp(0.0, self.max_delay_time_secs())
+    }
+
+    /// The feedback of each channel, after filtering.
+    fn feedback(&mut self, out_l: f64, out_r: f64) -> (f64, f64) {
+        let (fb_l, fb_r) = match &mut self.feedback_filters {
+            Some([filter_l, filter_r]) => {
+                BiquadFilter::process_pair(filter_l, filter_r, out_l, out_r)
+            }
+            None => (out_l, out_r),
+        };
+
+        (fb_l * self.feedback_amount, fb_r * self.feedback_amount)
+    }
+
+    /// The sum of every tap's output.
+    fn read_taps(&mut self) -> (f64, f64) {
+        let mut out = (0.0, 0.0);
+
+        for active in self.taps.iter_mut().flatten() {
+            let secs = active.time_secs.next();
+            let tap = 0.5
+                * (self.buffer_l.read_at(secs) + self.buffer_r.read_at(secs));
+            let (gain_l, gain_r) = active.tap.gains();
+
+            out.0 += tap * gain_l;
+            out.1 += tap * gain_r;
+        }
+
+        out
     }
 }
 
@@ -91,18 +295,21 @@ impl Effect for StereoDelay {
     fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
         let out_l = self.buffer_l.read();
         let out_r = self.buffer_r.read();
+        let (tap_l, tap_r) = self.read_taps();
+        let (fb_l, fb_r) = self.feedback(out_l, out_r);
+
         if self.use_ping_pong {
-            self.buffer_l.push(in_l + out_r * self.feedback_amount);
-            self.buffer_r.push(out_l * self.feedback_amount);
+            // the input is summed so that it starts on the left and
+            // bounces between the channels from there
+            self.buffer_l.push(0.5f64.mul_add(in_l + in_r, fb_r));
+            self.buffer_r.push(fb_l);
         }
         else {
-            self.buffer_l
-                .push(out_l.mul_add(self.feedback_amount, in_l));
-            self.buffer_r
-                .push(out_r.mul_add(self.feedback_amount, in_r));
+            self.buffer_l.push(in_l + fb_l);
+            self.buffer_r.push(in_r + fb_r);
         }
 
-        (out_l, out_r)
+        (out_l + tap_l, out_r + tap_r)
     }
 
     fn get_sample_rate(&self) -> f64 {
@@ -113,3 +320,103 @@ impl Effect for StereoDelay {
         "stereo_delay"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 1000.0;
+
+    fn delay() -> StereoDelay {
+        StereoDelay::new(1.0, SAMPLE_RATE).with_delay_time(0.01)
+    }
+
+    /// Processes silence until the delay time has finished smoothing.
+    fn settle(delay: &mut StereoDelay) {
+        for _ in 0..SAMPLE_RATE as usize {
+            delay.process_stereo(0.0, 0.0);
+        }
+    }
+
+    /// The output of `delay` for an impulse on the left channel.
+    fn impulse_response(
+        delay: &mut StereoDelay,
+        len: usize,
+    ) -> Vec<(f64, f64)> {
+        settle(delay);
+
+        (0..len)
+            .map(|i| {
+                let input = if i == 0 { 1.0 } else { 0.0 };
+                delay.process_stereo(input, 0.0)
+            })
+            .collect()
+    }
+
+    /// The index of the loudest sample of `values`.
+    fn peak(values: impl Iterator<Item = f64>) -> usize {
+        values
+            .enumerate()
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn ping_pong_alternates_channels() {
+        let mut delay = delay().with_ping_pong(true);
+        delay.set_feedback_amount(0.5);
+
+        let response = impulse_response(&mut delay, 35);
+
+        assert_eq!(peak(response.iter().map(|s| s.0)), 10);
+        assert_eq!(peak(response.iter().map(|s| s.1)), 20);
+        assert!(response[20].0.abs() < 1e-3);
+    }
+
+    #[test]
+    fn taps_are_panned() {
+        let mut delay = delay();
+        delay.add_tap(DelayTap {
+            time: DelayTime::Secs(0.025),
+            level: 1.0,
+            pan: 1.0,
+        });
+
+        let response = impulse_response(&mut delay, 35);
+        let right = response.iter().map(|s| s.1);
+
+        assert_eq!(peak(right), 25);
+        assert!(response[25].0.abs() < 1e-3);
+        assert_eq!(delay.taps().count(), 1);
+    }
+
+    #[test]
+    fn times_follow_the_tempo() {
+        let mut delay = delay();
+        delay.set_time(DelayTime::Beats(1.0));
+        delay.set_bpm(1500.0);
+
+        // the tempo is clamped to MAX_BPM, so a beat is 150 ms
+        let response = impulse_response(&mut delay, 200);
+
+        assert_eq!(peak(response.iter().map(|s| s.0)), 150);
+    }
+
+    #[test]
+    fn feedback_can_be_filtered() {
+        let mut delay = delay();
+        delay.set_feedback_amount(1.0);
+        delay.set_feedback_filter(Some(&BiquadParams {
+            freq: 50.0,
+            filter_type: FilterType::Lowpass,
+            ..Default::default()
+        }));
+
+        let response = impulse_response(&mut delay, 200);
+        let first = response[10].0.abs();
+        let last = response[190..].iter().map(|s| s.0.abs()).sum::<f64>();
+
+        assert!(last < first);
+    }
+}
@@ -1,5 +1,304 @@
-//! Module for signal decimation, AKA downsampling.
+//! Module for signal decimation, AKA downsampling, and bit crushing.
 
+use super::*;
+
+/// The lowest bit depth which a signal may be crushed to.
+pub const MIN_BIT_DEPTH: f64 = 1.0;
+/// The highest bit depth, at which the signal is left unquantized.
+pub const MAX_BIT_DEPTH: f64 = 24.0;
+/// The largest factor which a signal may be downsampled by.
+pub const MAX_DOWNSAMPLE_FACTOR: f64 = 64.0;
+
+/// Holds each sample of a signal for a number of samples, quantizing it to
+/// a lower bit depth as it is taken.
+///
+/// Both the bit depth and the downsample factor may be fractional, so that
+/// they can be swept smoothly. A fractional factor alternates between the
+/// hold lengths either side of it.
+#[derive(Clone, Debug)]
 pub struct Decimator {
-    // TODO
+    bit_depth: f64,
+    /// The number of quantization steps either side of zero.
+    num_levels: f64,
+
+    factor: f64,
+    /// How far each hold length is randomly varied, from `0.0` to `1.0`.
+    jitter: f64,
+    /// The number of samples until the next sample is taken.
+    phase: f64,
+    held: (f64, f64),
+
+    sample_rate: f64,
+}
+
+impl Decimator {
+    /// A decimator which leaves the signal unchanged until it is set.
+    pub fn new(sample_rate: f64) -> Self {
+        let mut decimator = Self {
+            bit_depth: MAX_BIT_DEPTH,
+            num_levels: 0.0,
+
+            factor: 1.0,
+            jitter: 0.0,
+            phase: 0.0,
+            held: (0.0, 0.0),
+
+            sample_rate,
+        };
+
+        decimator.set_bit_depth(MAX_BIT_DEPTH);
+        decimator
+    }
+
+    /// Sets the bit depth which samples are quantized to, which is clamped
+    /// between [`MIN_BIT_DEPTH`] and [`MAX_BIT_DEPTH`].
+    pub fn set_bit_depth(&mut self, bit_depth: f64) {
+        self.bit_depth = bit_depth.clamp(MIN_BIT_DEPTH, MAX_BIT_DEPTH);
+        self.num_levels = (self.bit_depth - 1.0).exp2();
+    }
+
+    pub const fn bit_depth(&self) -> f64 {
+        self.bit_depth
+    }
+
+    /// Sets how many samples each sample is held for, which is clamped
+    /// between `1.0` and [`MAX_DOWNSAMPLE_FACTOR`].
+    pub fn set_downsample_factor(&mut self, factor: f64) {
+        self.factor = factor.clamp(1.0, MAX_DOWNSAMPLE_FACTOR);
+    }
+
+    pub const fn downsample_factor(&self) -> f64 {
+        self.factor
+    }
+
+    /// Sets how far each hold length is randomly varied, from `0.0` (not at
+    /// all) to `1.0` (by up to half of the factor either way).
+    pub fn set_jitter(&mut self, jitter: f64) {
+        self.jitter = jitter.clamp(0.0, 1.0);
+    }
+
+    pub const fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    /// Clears the held sample, so the next sample is taken straight away.
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.held = (0.0, 0.0);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.reset();
+    }
+
+    /// Rounds `sample` to the nearest quantization step.
+    fn quantize(&self, sample: f64) -> f64 {
+        if self.bit_depth >= MAX_BIT_DEPTH {
+            return sample;
+        }
+
+        (sample * self.num_levels).round() / self.num_levels
+    }
+
+    /// The number of samples which the next sample is held for.
+    fn next_hold_length(&self) -> f64 {
+        if self.jitter == 0.0 {
+            return self.factor;
+        }
+
+        let offset = rand::random_range(-0.5..=0.5) * self.jitter;
+        (self.factor * (1.0 + offset)).max(1.0)
+    }
+}
+
+impl Default for Decimator {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE })
+    }
+}
+
+impl Effect for Decimator {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        if self.phase <= 0.0 {
+            self.phase += self.next_hold_length();
+            self.held = (self.quantize(in_l), self.quantize(in_r));
+        }
+
+        self.phase -= 1.0;
+
+        self.held
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "decimator"
+    }
+}
+
+/// A stereo bitcrusher, which blends a [`Decimator`] with the dry signal.
+///
+/// It is fully dry until its mix is raised.
+#[derive(Clone, Debug)]
+pub struct Bitcrusher {
+    crusher: DryWet<Decimator>,
+    mix: f64,
+}
+
+impl Bitcrusher {
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            crusher: DryWet::with_levels(Decimator::new(sample_rate), 1.0, 0.0),
+            mix: 0.0,
+        }
+    }
+
+    /// Sets the bit depth which the signal is crushed to (see
+    /// [`Decimator::set_bit_depth()`]).
+    pub fn set_bit_depth(&mut self, bit_depth: f64) {
+        self.crusher.set_bit_depth(bit_depth);
+    }
+
+    pub fn bit_depth(&self) -> f64 {
+        self.crusher.bit_depth()
+    }
+
+    /// Sets the factor which the signal is downsampled by (see
+    /// [`Decimator::set_downsample_factor()`]).
+    pub fn set_downsample_factor(&mut self, factor: f64) {
+        self.crusher.set_downsample_factor(factor);
+    }
+
+    pub fn downsample_factor(&self) -> f64 {
+        self.crusher.downsample_factor()
+    }
+
+    /// Sets how far the downsampling is randomly varied (see
+    /// [`Decimator::set_jitter()`]).
+    pub fn set_jitter(&mut self, jitter: f64) {
+        self.crusher.set_jitter(jitter);
+    }
+
+    pub fn jitter(&self) -> f64 {
+        self.crusher.jitter()
+    }
+
+    /// Sets the level of the crushed signal, from `0.0` (dry) to `1.0`
+    /// (fully crushed).
+    pub fn set_mix(&mut self, mix: f64) {
+        self.mix = mix.clamp(0.0, 1.0);
+
+        // the decimator may be skipped while dry, so its held sample is stale
+        if self.is_dry() && self.mix > 0.0 {
+            self.crusher.reset();
+        }
+
+        self.crusher.set_mix_equal_gain(self.mix);
+    }
+
+    /// The mix which is being moved towards.
+    pub const fn mix(&self) -> f64 {
+        self.mix
+    }
+
+    /// Whether only the dry signal is output, so the bitcrusher may be
+    /// skipped.
+    pub fn is_dry(&self) -> bool {
+        self.crusher.is_dry()
+    }
+
+    pub fn reset(&mut self) {
+        self.crusher.reset();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.crusher.reset_sample_rate(sample_rate);
+        self.crusher.set_sample_rate(sample_rate);
+    }
+}
+
+impl Default for Bitcrusher {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE })
+    }
+}
+
+impl Effect for Bitcrusher {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        self.crusher.process_stereo(in_l, in_r)
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.crusher.get_sample_rate()
+    }
+
+    fn get_identifier(&self) -> &str {
+        "bitcrusher"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    #[test]
+    fn decimator_is_transparent_by_default() {
+        let mut decimator = Decimator::new(SAMPLE_RATE);
+
+        for i in 0..100 {
+            let x = (i as f64 * 0.37).sin();
+            assert_eq!(decimator.process_stereo(x, -x), (x, -x));
+        }
+    }
+
+    #[test]
+    fn samples_are_held_and_quantized() {
+        let mut decimator = Decimator::new(SAMPLE_RATE);
+        decimator.set_bit_depth(3.0);
+        decimator.set_downsample_factor(4.0);
+
+        let outputs: Vec<f64> = (0..8)
+            .map(|i| decimator.process_stereo(0.1 * i as f64, 0.0).0)
+            .collect();
+
+        // 3 bits has four steps either side of zero
+        assert_eq!(outputs, [0.0, 0.0, 0.0, 0.0, 0.5, 0.5, 0.5, 0.5]);
+
+        // a fractional factor alternates between the lengths either side
+        decimator.reset();
+        decimator.set_downsample_factor(2.5);
+        decimator.set_bit_depth(MAX_BIT_DEPTH);
+
+        let num_taken = (0..100)
+            .map(|i| decimator.process_stereo(i as f64, 0.0).0)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .filter(|pair| pair[0] != pair[1])
+            .count();
+        assert_eq!(num_taken, 39);
+    }
+
+    #[test]
+    fn bitcrusher_is_dry_until_mixed() {
+        let mut crusher = Bitcrusher::new(SAMPLE_RATE);
+        crusher.set_bit_depth(MIN_BIT_DEPTH);
+
+        assert!(crusher.is_dry());
+        assert_eq!(crusher.process_stereo(0.3, -0.2), (0.3, -0.2));
+
+        crusher.set_mix(1.0);
+        assert!(!crusher.is_dry());
+
+        for _ in 0..1000 {
+            crusher.process_stereo(0.3, -0.2);
+        }
+
+        // 1 bit rounds to the nearest of -1, 0 and 1
+        assert_eq!(crusher.process_stereo(0.3, -0.7), (0.0, -1.0));
+    }
 }
@@ -0,0 +1,268 @@
+//! A harmonic exciter, which adds air by saturating the highs rather than
+//! boosting them.
+
+use super::*;
+
+/// The highest drive of the exciter, in decibels.
+pub const MAX_EXCITER_DRIVE_DB: f64 = 24.0;
+/// The default frequency above which the signal is excited.
+pub const DEFAULT_EXCITER_FREQ_HZ: f64 = 3000.0;
+
+/// How long changes to the drive and mix are smoothed over, in
+/// milliseconds.
+const SMOOTHING_TIME_MS: f64 = 30.0;
+
+/// A stereo harmonic exciter. The signal is split with a Linkwitz-Riley
+/// crossover, and the high band is saturated; only the harmonics which the
+/// saturation adds are blended back, so the dry signal is left untouched.
+///
+/// As the harmonics are generated from the high band alone, the exciter
+/// brightens without the noise floor rising as it would with an EQ boost.
+#[derive(Debug, Clone)]
+pub struct Exciter {
+    crossover: LinkwitzRileyFilter,
+    crossover_hz: f64,
+
+    /// The gain into the saturation, as a level.
+    drive: Smoother<f64>,
+    /// The level of the added harmonics, from `0.0` to `1.0`.
+    mix: Smoother<f64>,
+
+    sample_rate: f64,
+}
+
+impl Exciter {
+    /// An exciter above [`DEFAULT_EXCITER_FREQ_HZ`], which is silent until
+    /// its mix is raised.
+    pub fn new(sample_rate: f64) -> Self {
+        let mut drive = Smoother::new(SMOOTHING_TIME_MS, 1.0, sample_rate);
+        drive.set_start_value(1.0);
+        let mut mix = Smoother::new(SMOOTHING_TIME_MS, 0.0, sample_rate);
+        mix.set_start_value(0.0);
+
+        let mut exciter = Self {
+            crossover: LinkwitzRileyFilter::new(sample_rate),
+            crossover_hz: DEFAULT_EXCITER_FREQ_HZ,
+
+            drive,
+            mix,
+
+            sample_rate,
+        };
+
+        exciter.set_crossover(DEFAULT_EXCITER_FREQ_HZ);
+        exciter
+    }
+
+    /// Sets the drive into the saturation in decibels, which is clamped
+    /// between `0.0` and [`MAX_EXCITER_DRIVE_DB`].
+    pub fn set_drive_db(&mut self, drive_db: f64) {
+        let drive_db = drive_db.clamp(0.0, MAX_EXCITER_DRIVE_DB);
+        self.drive.set_target_value(db_to_level(drive_db));
+    }
+
+    /// The drive which is being moved towards, in decibels.
+    pub fn drive_db(&self) -> f64 {
+        level_to_db(self.drive.target_value())
+    }
+
+    /// Sets the level of the added harmonics, from `0.0` to `1.0`.
+    pub fn set_mix(&mut self, mix: f64) {
+        let mix = mix.clamp(0.0, 1.0);
+
+        // the crossover may be skipped while silent, so its state is stale
+        if self.is_silent() && mix > 0.0 {
+            self.reset();
+        }
+
+        self.mix.set_target_value(mix);
+    }
+
+    /// The mix which is being moved towards.
+    pub fn mix(&self) -> f64 {
+        self.mix.target_value()
+    }
+
+    /// Sets the frequency above which the signal is excited.
+    pub fn set_crossover(&mut self, freq: f64) {
+        self.crossover_hz = freq.clamp(20.0, self.sample_rate * 0.45);
+        self.crossover.set_cutoff_freq(self.crossover_hz);
+    }
+
+    pub const fn crossover(&self) -> f64 {
+        self.crossover_hz
+    }
+
+    /// Whether the exciter adds nothing, so it may be skipped.
+    pub fn is_silent(&self) -> bool {
+        self.mix.current_value() <= 0.0 && self.mix.target_value() <= 0.0
+    }
+
+    /// Clears the crossover's state.
+    pub fn reset(&mut self) {
+        self.crossover.reset(0.0);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.drive.reset_sample_rate(sample_rate);
+        self.mix.reset_sample_rate(sample_rate);
+
+        self.crossover = LinkwitzRileyFilter::new(sample_rate);
+        self.set_crossover(self.crossover_hz);
+    }
+
+    /// Excites a block of each channel in place. While the drive and mix
+    /// are steady, each channel is processed separately.
+    pub fn process_block(&mut self, left: &mut [f64], right: &mut [f64]) {
+        if self.drive.is_active() || self.mix.is_active() {
+            for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+                (*l, *r) = self.process_stereo(*l, *r);
+            }
+
+            return;
+        }
+
+        let drive = self.drive.current_value();
+        let mix = self.mix.current_value();
+
+        for (ch, block) in [left, right].into_iter().enumerate() {
+            for sample in block {
+                let high = self.crossover.process_high(*sample, ch);
+                *sample += Self::harmonics(high, drive) * mix;
+            }
+        }
+    }
+
+    /// The harmonics added by saturating `high` with `drive`. The
+    /// saturation has unity gain for quiet signals, so the high band itself
+    /// is removed.
+    fn harmonics(high: f64, drive: f64) -> f64 {
+        (high * drive).tanh() / drive - high
+    }
+}
+
+impl Default for Exciter {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE })
+    }
+}
+
+impl Effect for Exciter {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        let (_, (high_l, high_r)) =
+            self.crossover.process_high_low(in_l, in_r);
+
+        let drive = self.drive.next();
+        let mix = self.mix.next();
+
+        (
+            in_l + Self::harmonics(high_l, drive) * mix,
+            in_r + Self::harmonics(high_r, drive) * mix,
+        )
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "exciter"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+    const NUM_SAMPLES: usize = 9600;
+
+    /// The output of `exciter` for a sine at `freq` with an amplitude of
+    /// `amp`, after the smoothing has settled.
+    fn excite_sine(exciter: &mut Exciter, freq: f64, amp: f64) -> Vec<f64> {
+        (0..NUM_SAMPLES)
+            .map(|i| {
+                let x = amp * (TAU * freq * i as f64 / SAMPLE_RATE).sin();
+                exciter.process_stereo(x, x).0
+            })
+            .skip(NUM_SAMPLES / 2)
+            .collect()
+    }
+
+    /// The amplitude of the component of `signal` at `freq`.
+    fn amplitude_at(signal: &[f64], freq: f64) -> f64 {
+        let (re, im) = signal.iter().enumerate().fold(
+            (0.0, 0.0),
+            |(re, im), (i, x)| {
+                let phase = TAU * freq * i as f64 / SAMPLE_RATE;
+                (re + x * phase.cos(), im + x * phase.sin())
+            },
+        );
+
+        2.0 * re.hypot(im) / signal.len() as f64
+    }
+
+    #[test]
+    fn silent_exciter_is_unchanged() {
+        let mut exciter = Exciter::new(SAMPLE_RATE);
+        exciter.set_drive_db(MAX_EXCITER_DRIVE_DB);
+
+        assert!(exciter.is_silent());
+        assert_eq!(exciter.process_stereo(0.3, -0.7), (0.3, -0.7));
+    }
+
+    #[test]
+    fn highs_gain_harmonics() {
+        let mut exciter = Exciter::new(SAMPLE_RATE);
+        exciter.set_drive_db(12.0);
+        exciter.set_mix(1.0);
+
+        let out = excite_sine(&mut exciter, 6000.0, 0.5);
+        let third = amplitude_at(&out, 18000.0);
+
+        assert!(third > 0.01, "third harmonic was {third}");
+        assert!(!exciter.is_silent());
+    }
+
+    #[test]
+    fn blocks_match_samples() {
+        let mut exciter = Exciter::new(SAMPLE_RATE);
+        exciter.set_drive_db(12.0);
+        exciter.set_mix(0.8);
+        let mut per_sample = exciter.clone();
+
+        let signal: Vec<f64> =
+            (0..NUM_SAMPLES).map(|i| (i as f64 * 0.37).sin() * 0.8).collect();
+        let mut left = signal.clone();
+        let mut right: Vec<f64> = signal.iter().map(|x| -x).collect();
+
+        // the first blocks are smoothed, and the rest are steady
+        for (l, r) in left.chunks_mut(64).zip(right.chunks_mut(64)) {
+            exciter.process_block(l, r);
+        }
+
+        for (i, &x) in signal.iter().enumerate() {
+            let (l, r) = per_sample.process_stereo(x, -x);
+            assert_eq!((left[i], right[i]), (l, r));
+        }
+    }
+
+    #[test]
+    fn lows_are_left_alone() {
+        let mut exciter = Exciter::new(SAMPLE_RATE);
+        exciter.set_drive_db(MAX_EXCITER_DRIVE_DB);
+        exciter.set_mix(1.0);
+
+        let out = excite_sine(&mut exciter, 100.0, 0.5);
+        let third = amplitude_at(&out, 300.0);
+        let fundamental = amplitude_at(&out, 100.0);
+
+        assert!(third < 1e-3, "third harmonic was {third}");
+        assert!((fundamental - 0.5).abs() < 1e-3);
+
+        exciter.set_mix(2.0);
+        assert_eq!(exciter.mix(), 1.0);
+    }
+}
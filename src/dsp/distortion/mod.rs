@@ -3,6 +3,9 @@
 use super::*;
 
 pub mod decimation;
+pub mod exciter;
 pub mod waveshaper;
 
+pub use decimation::{Bitcrusher, Decimator};
+pub use exciter::Exciter;
 pub use waveshaper::Waveshaper;
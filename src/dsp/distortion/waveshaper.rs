@@ -1,14 +1,19 @@
 //! Signal waveshaping.
 
 use std::ops::RangeInclusive;
+use std::sync::Arc;
 
 use super::*;
 
 /// A waveshaper which dynamically accepts any transfer function and asymmetric
 /// drive levels.
 ///
+/// The waveshaper is memoryless, so it may run at any sample rate, such as
+/// inside an [`Oversampled`](crate::dsp::Oversampled) wrapper.
+///
 /// TODO: add asymmetric curve processing (currently only drive is applied
 /// asymmetrically).
+#[derive(Clone)]
 pub struct Waveshaper {
     curve: f64,
     // curve_lower: f64,
@@ -18,7 +23,7 @@ pub struct Waveshaper {
     drive_lower: f64,
     asymmetric: bool,
 
-    xfer_function: Box<dyn Fn(f64, f64) -> f64 + Send>,
+    xfer_function: Arc<dyn Fn(f64, f64) -> f64 + Send + Sync>,
 }
 
 impl Waveshaper {
@@ -40,7 +45,7 @@ impl Waveshaper {
             drive_lower: 1.0,
             asymmetric: false,
 
-            xfer_function: Box::new(smooth_soft_clip),
+            xfer_function: Arc::new(smooth_soft_clip),
         }
     }
 
@@ -83,9 +88,9 @@ impl Waveshaper {
     /// `0.0` to `1.0` is its "inverse" range).
     pub fn set_xfer_function<F>(&mut self, function: F)
     where
-        F: Fn(f64, f64) -> f64 + Send + 'static,
+        F: Fn(f64, f64) -> f64 + Send + Sync + 'static,
     {
-        self.xfer_function = Box::new(function);
+        self.xfer_function = Arc::new(function);
     }
 
     /// If the transfer function you want to pass only has a single argument
@@ -93,10 +98,10 @@ impl Waveshaper {
     /// to the waveshaper.
     pub fn set_xfer_function_single_argument<F>(&mut self, function: F)
     where
-        F: Fn(f64) -> f64 + Send + 'static,
+        F: Fn(f64) -> f64 + Send + Sync + 'static,
     {
         let xfer = move |x: f64, _: f64| -> f64 { function(x) };
-        self.xfer_function = Box::new(xfer);
+        self.xfer_function = Arc::new(xfer);
     }
 
     /// If the transfer function you want to pass does not cover negative
@@ -106,7 +111,7 @@ impl Waveshaper {
     /// Asymmetric processing is still available after calling this method.
     pub fn set_xfer_function_positive_only<F>(&mut self, function: F)
     where
-        F: Fn(f64, f64) -> f64 + Send + 'static,
+        F: Fn(f64, f64) -> f64 + Send + Sync + 'static,
     {
         let xfer = move |x: f64, d: f64| -> f64 {
             if x.is_sign_negative() {
@@ -116,7 +121,7 @@ impl Waveshaper {
                 function(x, d)
             }
         };
-        self.xfer_function = Box::new(xfer);
+        self.xfer_function = Arc::new(xfer);
     }
 
     /// `set_xfer_function_single_argument()` and `set_xfer_function_positive_only()`
@@ -125,7 +130,7 @@ impl Waveshaper {
         &mut self,
         function: F,
     ) where
-        F: Fn(f64) -> f64 + Send + 'static,
+        F: Fn(f64) -> f64 + Send + Sync + 'static,
     {
         let xfer = move |x: f64, _: f64| -> f64 {
             if x.is_sign_negative() {
@@ -135,7 +140,7 @@ impl Waveshaper {
                 function(x)
             }
         };
-        self.xfer_function = Box::new(xfer);
+        self.xfer_function = Arc::new(xfer);
     }
 
     /// Sets the drive of the waveshaper. If asymmetric distortion is enabled,
@@ -220,6 +225,37 @@ impl Default for Waveshaper {
     }
 }
 
+impl std::fmt::Debug for Waveshaper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Waveshaper")
+            .field("curve", &self.curve)
+            .field("curve_range", &self.curve_range)
+            .field("drive", &self.drive)
+            .field("drive_lower", &self.drive_lower)
+            .field("asymmetric", &self.asymmetric)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Effect for Waveshaper {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        (self.process(in_l), self.process(in_r))
+    }
+
+    fn process_mono(&mut self, input: f64, _: usize) -> f64 {
+        self.process(input)
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        // the waveshaper doesn't depend on the sample rate
+        unsafe { SAMPLE_RATE }
+    }
+
+    fn get_identifier(&self) -> &str {
+        "waveshaper"
+    }
+}
+
 /// Smooth soft saturation function. `input` is clamped between `-1.0` and `1.0`,
 /// and `c` is clamped between `0.0` and `1.0`. Outputs in the range `-1.0` to `1.0`.
 ///
@@ -0,0 +1,348 @@
+//! Upward and downward expansion, and gating.
+
+use super::*;
+
+/// The most which the expander may change the gain by, in decibels.
+pub const MAX_EXPANDER_RANGE_DB: f64 = 90.0;
+
+const DEFAULT_THRESHOLD_DB: f64 = -40.0;
+const DEFAULT_RATIO: f64 = 2.0;
+const DEFAULT_RANGE_DB: f64 = 40.0;
+const DEFAULT_HOLD_TIME_MS: f64 = 50.0;
+const DEFAULT_HYSTERESIS_DB: f64 = 3.0;
+const DEFAULT_ATTACK_TIME_MS: f64 = 1.0;
+const DEFAULT_RELEASE_TIME_MS: f64 = 150.0;
+/// How quickly the level detector falls, in milliseconds.
+const DETECTOR_RELEASE_TIME_MS: f64 = 100.0;
+
+/// Which side of the threshold an [`Expander`] acts on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExpanderDirection {
+    /// Levels below the threshold are pushed further down.
+    #[default]
+    Downward,
+    /// Levels above the threshold are pushed further up.
+    Upward,
+}
+
+/// A stereo expander, which widens the dynamics of a signal on one side of
+/// its threshold. Both channels are expanded together, by the louder of the
+/// two.
+///
+/// Like [`Compressor`], its level is found by a [`BallisticsFilter`], and
+/// its gain follows another, so that the attack and release set how quickly
+/// the gain rises and falls.
+///
+/// At an infinite ratio, a downward expander is a gate (see
+/// [`gate()`](Self::gate)). Once the level rises past the threshold, it
+/// stays open until the level falls below the threshold by the hysteresis,
+/// and then for the hold time, so that signals around the threshold don't
+/// make it chatter.
+#[derive(Clone, Debug)]
+pub struct Expander {
+    detector: BallisticsFilter,
+    gain_follower: BallisticsFilter,
+
+    direction: ExpanderDirection,
+    threshold_db: f64,
+    ratio: f64,
+    /// the most the gain is changed by, in decibels
+    range_db: f64,
+    /// how far below the threshold the level must fall to close, in
+    /// decibels
+    hysteresis_db: f64,
+
+    is_open: bool,
+    hold_time_ms: f64,
+    hold_samples: u32,
+    hold_remaining: u32,
+
+    attack_time_ms: f64,
+    release_time_ms: f64,
+    gain: f64,
+
+    sample_rate: f64,
+}
+
+impl Expander {
+    pub fn new(sample_rate: f64) -> Self {
+        let mut detector = BallisticsFilter::new(NUM_CHANNELS, sample_rate);
+        detector.set_level_type(BallisticsLevelType::Peak);
+        detector.set_attack_time_ms(0.0);
+        detector.set_release_time_ms(DETECTOR_RELEASE_TIME_MS);
+
+        let mut gain_follower =
+            BallisticsFilter::new(NUM_CHANNELS, sample_rate);
+        gain_follower.set_attack_time_ms(DEFAULT_ATTACK_TIME_MS);
+        gain_follower.set_release_time_ms(DEFAULT_RELEASE_TIME_MS);
+        gain_follower.reset(1.0);
+
+        let mut expander = Self {
+            detector,
+            gain_follower,
+
+            direction: ExpanderDirection::default(),
+            threshold_db: DEFAULT_THRESHOLD_DB,
+            ratio: DEFAULT_RATIO,
+            range_db: DEFAULT_RANGE_DB,
+            hysteresis_db: DEFAULT_HYSTERESIS_DB,
+
+            is_open: false,
+            hold_time_ms: DEFAULT_HOLD_TIME_MS,
+            hold_samples: 0,
+            hold_remaining: 0,
+
+            attack_time_ms: DEFAULT_ATTACK_TIME_MS,
+            release_time_ms: DEFAULT_RELEASE_TIME_MS,
+            gain: 1.0,
+
+            sample_rate,
+        };
+
+        expander.set_hold_time_ms(DEFAULT_HOLD_TIME_MS);
+        expander
+    }
+
+    /// A downward expander with an infinite ratio and the widest range,
+    /// which closes fully below its threshold.
+    pub fn gate(sample_rate: f64) -> Self {
+        let mut gate = Self::new(sample_rate);
+        gate.set_ratio(f64::INFINITY);
+        gate.set_range_db(MAX_EXPANDER_RANGE_DB);
+
+        gate
+    }
+
+    /// Sets which side of the threshold is expanded.
+    pub fn set_direction(&mut self, direction: ExpanderDirection) {
+        self.direction = direction;
+    }
+
+    /// Sets the expander's threshold in decibels.
+    pub fn set_threshold_db(&mut self, threshold_db: f64) {
+        self.threshold_db = threshold_db;
+    }
+
+    /// Sets the expansion ratio. An infinite ratio moves the gain straight
+    /// to the edge of the range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ratio` is less than `1.0`.
+    pub fn set_ratio(&mut self, ratio: f64) {
+        debug_assert!(ratio >= 1.0);
+
+        self.ratio = ratio.max(1.0);
+    }
+
+    /// Sets the most which the gain is changed by in decibels, which is
+    /// clamped to [`MAX_EXPANDER_RANGE_DB`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range_db` is negative.
+    pub fn set_range_db(&mut self, range_db: f64) {
+        debug_assert!(range_db >= 0.0);
+
+        self.range_db = range_db.clamp(0.0, MAX_EXPANDER_RANGE_DB);
+    }
+
+    /// Sets how far below the threshold the level must fall before the
+    /// expander closes, in decibels.
+    pub fn set_hysteresis_db(&mut self, hysteresis_db: f64) {
+        self.hysteresis_db = hysteresis_db.max(0.0);
+    }
+
+    /// Sets how long the expander stays open after the level falls below
+    /// the threshold and hysteresis, in milliseconds.
+    pub fn set_hold_time_ms(&mut self, time_ms: f64) {
+        self.hold_time_ms = time_ms.max(0.0);
+        self.hold_samples =
+            (self.hold_time_ms / 1000.0 * self.sample_rate).round() as u32;
+    }
+
+    /// Sets how quickly the gain rises, in milliseconds.
+    pub fn set_attack_time_ms(&mut self, time_ms: f64) {
+        self.attack_time_ms = time_ms.max(0.0);
+        self.gain_follower.set_attack_time_ms(self.attack_time_ms);
+    }
+
+    /// Sets how quickly the gain falls, in milliseconds.
+    pub fn set_release_time_ms(&mut self, time_ms: f64) {
+        self.release_time_ms = time_ms.max(0.0);
+        self.gain_follower.set_release_time_ms(self.release_time_ms);
+    }
+
+    /// Whether the level is past the threshold, or is being held there.
+    pub const fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    /// The gain applied to the latest sample, in decibels.
+    pub fn gain_db(&self) -> f64 {
+        level_to_db(self.gain)
+    }
+
+    /// Closes the expander and clears its detectors.
+    pub fn reset(&mut self) {
+        self.detector.reset(0.0);
+        self.gain_follower.reset(1.0);
+        self.is_open = false;
+        self.hold_remaining = 0;
+        self.gain = 1.0;
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        let mut expander = Self::new(sample_rate);
+
+        expander.direction = self.direction;
+        expander.threshold_db = self.threshold_db;
+        expander.ratio = self.ratio;
+        expander.range_db = self.range_db;
+        expander.hysteresis_db = self.hysteresis_db;
+        expander.set_hold_time_ms(self.hold_time_ms);
+        expander.set_attack_time_ms(self.attack_time_ms);
+        expander.set_release_time_ms(self.release_time_ms);
+
+        *self = expander;
+    }
+
+    /// Updates whether the expander is open for `level_db`.
+    fn update_state(&mut self, level_db: f64) {
+        if level_db >= self.threshold_db {
+            self.is_open = true;
+            self.hold_remaining = self.hold_samples;
+        }
+        else if level_db < self.threshold_db - self.hysteresis_db {
+            if self.hold_remaining > 0 {
+                self.hold_remaining -= 1;
+            }
+            else {
+                self.is_open = false;
+            }
+        }
+    }
+
+    /// The gain which the expander moves towards for `level_db`, in
+    /// decibels.
+    fn target_gain_db(&self, level_db: f64) -> f64 {
+        let over_db = level_db - self.threshold_db;
+
+        match self.direction {
+            ExpanderDirection::Downward if !self.is_open => {
+                if over_db >= 0.0 {
+                    0.0
+                }
+                else {
+                    (over_db * (self.ratio - 1.0)).max(-self.range_db)
+                }
+            }
+            ExpanderDirection::Upward if self.is_open => {
+                if over_db <= 0.0 {
+                    0.0
+                }
+                else {
+                    (over_db * (self.ratio - 1.0)).min(self.range_db)
+                }
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+impl Default for Expander {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE })
+    }
+}
+
+impl Effect for Expander {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        let (env_l, env_r) = self.detector.process_stereo(in_l, in_r);
+        let level = env_l.max(env_r).max(db_to_level(MINUS_INFINITY_DB));
+        let level_db = level_to_db(level);
+
+        self.update_state(level_db);
+
+        let target = db_to_level(self.target_gain_db(level_db));
+        (self.gain, _) = self.gain_follower.process_stereo(target, target);
+
+        (in_l * self.gain, in_r * self.gain)
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "expander"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// Passes `secs` seconds of a 1 kHz sine with a peak of `level_db`
+    /// through `expander`, and returns the gain of the last sample.
+    fn settle(expander: &mut Expander, level_db: f64, secs: f64) -> f64 {
+        let amp = db_to_level(level_db);
+
+        for i in 0..(secs * SAMPLE_RATE) as usize {
+            let x = amp * (TAU * 1000.0 * i as f64 / SAMPLE_RATE).sin();
+            expander.process_stereo(x, -x);
+        }
+
+        expander.gain_db()
+    }
+
+    #[test]
+    fn downward_expansion_is_limited_by_the_range() {
+        let mut expander = Expander::new(SAMPLE_RATE);
+
+        assert!(settle(&mut expander, -20.0, 1.0).abs() < 0.01);
+
+        // 10 dB below the threshold at 2:1 is another 10 dB down
+        let expanded = settle(&mut expander, -50.0, 1.0);
+        assert!((expanded + 10.0).abs() < 0.5, "gain was {expanded} dB");
+
+        let mut gate = Expander::gate(SAMPLE_RATE);
+        let closed = settle(&mut gate, -50.0, 1.0);
+        assert!((closed + MAX_EXPANDER_RANGE_DB).abs() < 0.1, "{closed}");
+    }
+
+    #[test]
+    fn upward_expansion_boosts_above_the_threshold() {
+        let mut expander = Expander::new(SAMPLE_RATE);
+        expander.set_direction(ExpanderDirection::Upward);
+        expander.set_range_db(6.0);
+
+        assert!(settle(&mut expander, -60.0, 1.0).abs() < 0.01);
+
+        let boosted = settle(&mut expander, -37.0, 1.0);
+        assert!((boosted - 3.0).abs() < 0.5, "gain was {boosted} dB");
+
+        let limited = settle(&mut expander, -20.0, 1.0);
+        assert!((limited - 6.0).abs() < 0.01, "gain was {limited} dB");
+    }
+
+    #[test]
+    fn hysteresis_and_hold_keep_the_gate_open() {
+        let mut gate = Expander::gate(SAMPLE_RATE);
+        settle(&mut gate, -30.0, 0.5);
+        assert!(gate.is_open());
+
+        // within the hysteresis
+        assert!(settle(&mut gate, -42.0, 1.0).abs() < 0.01);
+
+        // below it, but for less than the hold
+        settle(&mut gate, -60.0, DEFAULT_HOLD_TIME_MS / 2000.0);
+        assert!(gate.is_open());
+
+        settle(&mut gate, -60.0, 1.0);
+        assert!(!gate.is_open());
+    }
+}
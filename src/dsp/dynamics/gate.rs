@@ -0,0 +1,349 @@
+//! Noise gating and downward expansion.
+
+use super::*;
+
+/// The default offset of a learned threshold above the noise floor, in
+/// decibels.
+pub const DEFAULT_GATE_OFFSET_DB: f64 = 6.0;
+/// The most which the gate may attenuate by, in decibels.
+pub const MIN_GATE_REDUCTION_DB: f64 = -90.0;
+
+const DEFAULT_REDUCTION_DB: f64 = -40.0;
+const DEFAULT_RATIO: f64 = 4.0;
+const DEFAULT_ATTACK_TIME_MS: f64 = 1.0;
+const DEFAULT_HOLD_TIME_MS: f64 = 50.0;
+const DEFAULT_RELEASE_TIME_MS: f64 = 150.0;
+/// How quickly the level detector falls, in milliseconds.
+const DETECTOR_RELEASE_TIME_MS: f64 = 20.0;
+
+/// A stereo noise gate, which acts as a downward expander below its
+/// threshold. Both channels are gated together, by the louder of the two.
+///
+/// Like [`SpectralGate`], its threshold may be learned from a recording of
+/// the noise alone (see [`set_learning()`](Self::set_learning)), or fixed.
+#[derive(Clone, Debug)]
+pub struct NoiseGate {
+    detector: BallisticsFilter,
+
+    threshold: GateThreshold,
+    /// the most the gate attenuates by, in decibels
+    reduction_db: f64,
+    /// the expansion ratio below the threshold
+    ratio: f64,
+
+    /// the mean level of the signal while learning
+    noise_floor: f64,
+    learned_samples: u64,
+    is_learning: bool,
+
+    gain: f64,
+    hold_remaining: u32,
+
+    attack_time_ms: f64,
+    hold_time_ms: f64,
+    release_time_ms: f64,
+    attack_coef: f64,
+    hold_samples: u32,
+    release_coef: f64,
+
+    sample_rate: f64,
+}
+
+impl NoiseGate {
+    pub fn new(sample_rate: f64) -> Self {
+        let mut detector = BallisticsFilter::new(NUM_CHANNELS, sample_rate);
+        detector.set_level_type(BallisticsLevelType::Peak);
+        detector.set_attack_time_ms(0.0);
+        detector.set_release_time_ms(DETECTOR_RELEASE_TIME_MS);
+
+        let mut gate = Self {
+            detector,
+
+            threshold: GateThreshold::Learned {
+                offset_db: DEFAULT_GATE_OFFSET_DB,
+            },
+            reduction_db: DEFAULT_REDUCTION_DB,
+            ratio: DEFAULT_RATIO,
+
+            noise_floor: 0.0,
+            learned_samples: 0,
+            is_learning: false,
+
+            gain: 1.0,
+            hold_remaining: 0,
+
+            attack_time_ms: DEFAULT_ATTACK_TIME_MS,
+            hold_time_ms: DEFAULT_HOLD_TIME_MS,
+            release_time_ms: DEFAULT_RELEASE_TIME_MS,
+            attack_coef: 0.0,
+            hold_samples: 0,
+            release_coef: 0.0,
+
+            sample_rate,
+        };
+
+        gate.update_coefficients();
+        gate
+    }
+
+    /// Sets how the gate's threshold is found.
+    pub fn set_threshold(&mut self, threshold: GateThreshold) {
+        self.threshold = threshold;
+    }
+
+    /// Sets the most which the gate attenuates by, in decibels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reduction_db` is greater than `0.0`.
+    pub fn set_reduction_db(&mut self, reduction_db: f64) {
+        debug_assert!(reduction_db <= 0.0);
+
+        self.reduction_db = reduction_db.clamp(MIN_GATE_REDUCTION_DB, 0.0);
+    }
+
+    /// Sets the expansion ratio below the threshold. An infinite ratio
+    /// closes the gate fully as soon as the level falls below it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ratio` is less than `1.0`.
+    pub fn set_ratio(&mut self, ratio: f64) {
+        debug_assert!(ratio >= 1.0);
+
+        self.ratio = ratio.max(1.0);
+    }
+
+    /// Sets how quickly the gate opens, in milliseconds.
+    pub fn set_attack_time_ms(&mut self, time_ms: f64) {
+        self.attack_time_ms = time_ms.max(0.0);
+        self.update_coefficients();
+    }
+
+    /// Sets how long the gate stays open after the level falls below the
+    /// threshold, in milliseconds.
+    pub fn set_hold_time_ms(&mut self, time_ms: f64) {
+        self.hold_time_ms = time_ms.max(0.0);
+        self.update_coefficients();
+    }
+
+    /// Sets how quickly the gate closes after the hold, in milliseconds.
+    pub fn set_release_time_ms(&mut self, time_ms: f64) {
+        self.release_time_ms = time_ms.max(0.0);
+        self.update_coefficients();
+    }
+
+    /// Sets whether the gate is learning the noise floor. While learning,
+    /// the signal's level is averaged into the floor and the signal is
+    /// passed through unchanged. Starting to learn discards the previous
+    /// floor.
+    pub fn set_learning(&mut self, learning: bool) {
+        if learning && !self.is_learning {
+            self.noise_floor = 0.0;
+            self.learned_samples = 0;
+        }
+
+        self.is_learning = learning;
+    }
+
+    pub const fn is_learning(&self) -> bool {
+        self.is_learning
+    }
+
+    /// Whether a noise floor has been learned.
+    pub const fn has_noise_floor(&self) -> bool {
+        self.learned_samples > 0
+    }
+
+    /// The learned noise floor in decibels, if there is one.
+    pub fn noise_floor_db(&self) -> Option<f64> {
+        self.has_noise_floor().then(|| level_to_db(self.noise_floor))
+    }
+
+    /// The gain applied to the latest sample, in decibels.
+    pub fn gain_db(&self) -> f64 {
+        level_to_db(self.gain)
+    }
+
+    /// Opens the gate and clears its detector. The learned noise floor is
+    /// kept.
+    pub fn reset(&mut self) {
+        self.detector.reset(0.0);
+        self.gain = 1.0;
+        self.hold_remaining = 0;
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+
+        self.detector = BallisticsFilter::new(NUM_CHANNELS, sample_rate);
+        self.detector.set_level_type(BallisticsLevelType::Peak);
+        self.detector.set_attack_time_ms(0.0);
+        self.detector.set_release_time_ms(DETECTOR_RELEASE_TIME_MS);
+
+        self.update_coefficients();
+    }
+
+    /// The level below which the gate closes, if there is one.
+    fn threshold_level(&self) -> Option<f64> {
+        match self.threshold {
+            GateThreshold::Fixed { threshold_db } => {
+                Some(db_to_level(threshold_db))
+            }
+            GateThreshold::Learned { offset_db } => self
+                .has_noise_floor()
+                .then(|| self.noise_floor * db_to_level(offset_db)),
+        }
+    }
+
+    /// The gain which the gate moves towards for `level`.
+    fn target_gain(&mut self, level: f64) -> f64 {
+        // nothing has been learned, so nothing is gated
+        let Some(threshold) = self.threshold_level()
+        else {
+            return 1.0;
+        };
+
+        if level >= threshold {
+            self.hold_remaining = self.hold_samples;
+            return 1.0;
+        }
+
+        if self.hold_remaining > 0 {
+            self.hold_remaining -= 1;
+            return 1.0;
+        }
+
+        let below_db = level_to_db(level) - level_to_db(threshold);
+        let gain_db = (below_db * (self.ratio - 1.0)).max(self.reduction_db);
+
+        db_to_level(gain_db)
+    }
+
+    fn update_coefficients(&mut self) {
+        let coef = |time_ms: f64| {
+            if time_ms > 0.0 {
+                (-1000.0 / (time_ms * self.sample_rate)).exp()
+            }
+            else {
+                0.0
+            }
+        };
+
+        self.attack_coef = coef(self.attack_time_ms);
+        self.release_coef = coef(self.release_time_ms);
+        self.hold_samples =
+            (self.hold_time_ms / 1000.0 * self.sample_rate).round() as u32;
+    }
+}
+
+impl Effect for NoiseGate {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        let (env_l, env_r) = self.detector.process_stereo(in_l, in_r);
+        let level = env_l.max(env_r);
+
+        if self.is_learning {
+            // a running mean of the level
+            self.learned_samples += 1;
+            let weight = (self.learned_samples as f64).recip();
+            self.noise_floor += (level - self.noise_floor) * weight;
+
+            return (in_l, in_r);
+        }
+
+        let target = self.target_gain(level);
+        let coef = if target > self.gain {
+            self.attack_coef
+        }
+        else {
+            self.release_coef
+        };
+
+        self.gain = target + (self.gain - target) * coef;
+
+        (in_l * self.gain, in_r * self.gain)
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "noise_gate"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// Passes one second of a sine at `amp` through `gate`, and returns the
+    /// gain of the last sample.
+    fn settle(gate: &mut NoiseGate, amp: f64) -> f64 {
+        let num_samples = SAMPLE_RATE as usize;
+
+        for i in 0..num_samples {
+            let x = amp * (TAU * 100.0 * i as f64 / SAMPLE_RATE).sin();
+            gate.process_stereo(x, -x);
+        }
+
+        gate.gain_db()
+    }
+
+    #[test]
+    fn nothing_is_gated_until_learned() {
+        let mut gate = NoiseGate::new(SAMPLE_RATE);
+
+        assert!(!gate.has_noise_floor());
+        assert_eq!(settle(&mut gate, 0.001), 0.0);
+    }
+
+    #[test]
+    fn fixed_threshold_expands_quiet_signals() {
+        let mut gate = NoiseGate::new(SAMPLE_RATE);
+        gate.set_threshold(GateThreshold::Fixed { threshold_db: -40.0 });
+        gate.set_release_time_ms(20.0);
+
+        assert!(settle(&mut gate, 0.5).abs() < 1e-6);
+
+        // 20 dB below the threshold at 4:1 would be 60 dB down, which is
+        // limited by the reduction
+        let reduced = settle(&mut gate, 0.001);
+        assert!((reduced - DEFAULT_REDUCTION_DB).abs() < 0.1, "{reduced}");
+
+        gate.set_ratio(1.0);
+        assert!(settle(&mut gate, 0.001).abs() < 1e-6);
+    }
+
+    #[test]
+    fn learned_floor_gates_the_noise() {
+        let mut gate = NoiseGate::new(SAMPLE_RATE);
+
+        gate.set_learning(true);
+        assert_eq!(settle(&mut gate, 0.01), 0.0);
+        gate.set_learning(false);
+
+        let floor = gate.noise_floor_db().unwrap();
+        assert!(floor < -40.0 && floor > -46.0, "floor was {floor} dB");
+
+        assert!(settle(&mut gate, 0.01) < -12.0);
+        assert!(settle(&mut gate, 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn hold_keeps_the_gate_open() {
+        let mut gate = NoiseGate::new(SAMPLE_RATE);
+        gate.set_threshold(GateThreshold::Fixed { threshold_db: -20.0 });
+        settle(&mut gate, 0.5);
+
+        // silence for less than the hold and the detector's fall
+        for _ in 0..(DEFAULT_HOLD_TIME_MS / 2000.0 * SAMPLE_RATE) as usize {
+            gate.process_stereo(0.0, 0.0);
+        }
+
+        assert!(gate.gain_db().abs() < 1e-6);
+    }
+}
@@ -0,0 +1,289 @@
+//! A look-ahead brickwall limiter, which holds the true peak of a signal
+//! under a ceiling.
+
+use super::*;
+
+/// The default ceiling of the limiter, in decibels true peak.
+pub const DEFAULT_LIMITER_CEILING_DB: f64 = -1.0;
+/// The lowest and highest ceilings, in decibels true peak.
+pub const MIN_LIMITER_CEILING_DB: f64 = -24.0;
+pub const MAX_LIMITER_CEILING_DB: f64 = 0.0;
+/// The default release time of the limiter, in milliseconds.
+pub const DEFAULT_LIMITER_RELEASE_MS: f64 = 100.0;
+/// The shortest and longest release times, in milliseconds.
+pub const MIN_LIMITER_RELEASE_MS: f64 = 1.0;
+pub const MAX_LIMITER_RELEASE_MS: f64 = 1000.0;
+
+/// How far ahead the limiter looks, in milliseconds. This is also how long
+/// the gain takes to fall.
+const LOOKAHEAD_MS: f64 = 2.0;
+
+/// The settings of a [`Limiter`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LimiterParameters {
+    /// The level which the true peak is held under, in decibels.
+    pub ceiling_db: f64,
+    /// How long the gain takes to recover after a peak, in milliseconds.
+    pub release_ms: f64,
+}
+
+impl Default for LimiterParameters {
+    fn default() -> Self {
+        Self {
+            ceiling_db: DEFAULT_LIMITER_CEILING_DB,
+            release_ms: DEFAULT_LIMITER_RELEASE_MS,
+        }
+    }
+}
+
+/// A stereo look-ahead brickwall limiter.
+///
+/// Peaks are found between samples with a [`TruePeakDetector`], and the
+/// signal is delayed so that the gain has fallen smoothly by the time each
+/// peak is output. The gain is held over the look-ahead window, then
+/// averaged over it, so it never falls faster than a linear ramp.
+///
+/// The limiter adds [`latency()`](Self::latency) samples of delay.
+#[derive(Debug, Clone)]
+pub struct Limiter {
+    params: LimiterParameters,
+    ceiling: f64,
+    release_coef: f64,
+
+    detector: TruePeakDetector,
+
+    /// The delayed input of each channel.
+    delayed: [Vec<f64>; 2],
+    delay_pos: usize,
+
+    /// The gain needed by each of the latest detected peaks, which the
+    /// lowest is held from.
+    required: Vec<f64>,
+    required_pos: usize,
+
+    /// The held gain after release, which is averaged over the window.
+    released: f64,
+    averaged: Vec<f64>,
+    averaged_pos: usize,
+    averaged_sum: f64,
+
+    /// The gain applied to the latest output.
+    gain: f64,
+
+    sample_rate: f64,
+}
+
+impl Limiter {
+    pub fn new(params: LimiterParameters, sample_rate: f64) -> Self {
+        let window = Self::window_len(sample_rate);
+        let detector = TruePeakDetector::new();
+        // the detected peaks lag the input
+        let delay_len = window + detector.latency();
+
+        let mut limiter = Self {
+            params,
+            ceiling: 1.0,
+            release_coef: 0.0,
+
+            detector,
+
+            delayed: [vec![0.0; delay_len], vec![0.0; delay_len]],
+            delay_pos: 0,
+
+            // each detected peak lies between two samples, so it is held
+            // for one sample longer than the window on either side
+            required: vec![1.0; window + 2],
+            required_pos: 0,
+
+            released: 1.0,
+            averaged: vec![1.0; window],
+            averaged_pos: 0,
+            averaged_sum: window as f64,
+
+            gain: 1.0,
+
+            sample_rate,
+        };
+
+        limiter.set_parameters(params);
+        limiter
+    }
+
+    pub const fn parameters(&self) -> &LimiterParameters {
+        &self.params
+    }
+
+    pub fn set_parameters(&mut self, params: LimiterParameters) {
+        self.params = LimiterParameters {
+            ceiling_db: params
+                .ceiling_db
+                .clamp(MIN_LIMITER_CEILING_DB, MAX_LIMITER_CEILING_DB),
+            release_ms: params
+                .release_ms
+                .clamp(MIN_LIMITER_RELEASE_MS, MAX_LIMITER_RELEASE_MS),
+        };
+
+        self.ceiling = db_to_level(self.params.ceiling_db);
+        self.release_coef = (-1000.0
+            / (self.params.release_ms * self.sample_rate))
+            .exp();
+    }
+
+    /// The number of samples by which the limiter delays the signal.
+    pub fn latency(&self) -> usize {
+        self.delayed[0].len()
+    }
+
+    /// The gain reduction applied to the latest output, in decibels.
+    pub fn gain_reduction_db(&self) -> f64 {
+        -level_to_db(self.gain)
+    }
+
+    /// Clears the limiter's delay and releases its gain.
+    pub fn reset(&mut self) {
+        self.detector.reset();
+
+        for channel in &mut self.delayed {
+            channel.fill(0.0);
+        }
+
+        self.required.fill(1.0);
+        self.released = 1.0;
+        self.averaged.fill(1.0);
+        self.averaged_sum = self.averaged.len() as f64;
+        self.gain = 1.0;
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        *self = Self::new(self.params, sample_rate);
+    }
+
+    fn window_len(sample_rate: f64) -> usize {
+        ((LOOKAHEAD_MS / 1000.0 * sample_rate).round() as usize).max(1)
+    }
+
+    /// The gain for the sample which is about to be output, given the
+    /// latest detected peak.
+    fn next_gain(&mut self, peak: f64) -> f64 {
+        self.required[self.required_pos] =
+            if peak > self.ceiling { self.ceiling / peak } else { 1.0 };
+        self.required_pos = (self.required_pos + 1) % self.required.len();
+
+        let held = self.required.iter().copied().fold(1.0, f64::min);
+
+        self.released = if held < self.released {
+            held
+        }
+        else {
+            held + (self.released - held) * self.release_coef
+        };
+
+        self.averaged_sum +=
+            self.released - self.averaged[self.averaged_pos];
+        self.averaged[self.averaged_pos] = self.released;
+        self.averaged_pos = (self.averaged_pos + 1) % self.averaged.len();
+
+        // the running sum is recomputed once per window, so it can't drift
+        if self.averaged_pos == 0 {
+            self.averaged_sum = self.averaged.iter().sum();
+        }
+
+        self.averaged_sum / self.averaged.len() as f64
+    }
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self::new(LimiterParameters::default(), unsafe { SAMPLE_RATE })
+    }
+}
+
+impl Effect for Limiter {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        let peak = self.detector.process_stereo(in_l, in_r);
+        self.gain = self.next_gain(peak).min(1.0);
+
+        let [left, right] = &mut self.delayed;
+        let out_l = std::mem::replace(&mut left[self.delay_pos], in_l);
+        let out_r = std::mem::replace(&mut right[self.delay_pos], in_r);
+        self.delay_pos = (self.delay_pos + 1) % left.len();
+
+        (out_l * self.gain, out_r * self.gain)
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "limiter"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_PI_4, PI};
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn limiter() -> Limiter {
+        Limiter::new(LimiterParameters::default(), SAMPLE_RATE)
+    }
+
+    #[test]
+    fn quiet_signals_are_only_delayed() {
+        let mut limiter = limiter();
+        let latency = limiter.latency();
+
+        let out: Vec<f64> = (0..latency * 2)
+            .map(|i| limiter.process_stereo(i as f64 * 1e-3, 0.0).0)
+            .collect();
+
+        assert!(out[..latency].iter().all(|&x| x == 0.0));
+        assert!((out[latency + 10] - 0.01).abs() < 1e-12);
+        assert_eq!(limiter.gain_reduction_db(), 0.0);
+    }
+
+    #[test]
+    fn true_peaks_are_held_under_the_ceiling() {
+        let mut limiter = limiter();
+        let mut detector = TruePeakDetector::new();
+
+        // a loud sine whose true peaks fall between its samples
+        let sine =
+            |i: usize| 4.0 * (i as f64).mul_add(PI / 2.0, FRAC_PI_4).sin();
+
+        let peak = (0..4800)
+            .map(|i| {
+                let (l, r) = limiter.process_stereo(sine(i), -sine(i));
+                detector.process_stereo(l, r)
+            })
+            .fold(0.0, f64::max);
+
+        let ceiling = db_to_level(DEFAULT_LIMITER_CEILING_DB);
+        assert!(peak <= ceiling * 1.01, "true peak was {peak}");
+        assert!(limiter.gain_reduction_db() > 12.0);
+    }
+
+    #[test]
+    fn single_peaks_are_caught_ahead() {
+        let params = LimiterParameters {
+            release_ms: 10.0,
+            ..Default::default()
+        };
+        let mut limiter = Limiter::new(params, SAMPLE_RATE);
+
+        let out: Vec<f64> = (0..4800)
+            .map(|i| {
+                let x = if i == 1000 { 2.0 } else { 0.5 };
+                limiter.process_stereo(x, x).0
+            })
+            .collect();
+
+        let ceiling = db_to_level(DEFAULT_LIMITER_CEILING_DB);
+        assert!(out.iter().all(|&x| x <= ceiling + 1e-9));
+        // the gain has recovered after the release
+        assert!((out[4799] - 0.5).abs() < 1e-3);
+    }
+}
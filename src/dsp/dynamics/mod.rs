@@ -7,5 +7,15 @@ use super::*;
 
 pub mod adsr;
 pub mod compressor;
+pub mod expander;
+pub mod gate;
+pub mod limiter;
+pub mod transient;
+pub mod true_peak;
 
 pub use compressor::Compressor;
+pub use expander::{Expander, ExpanderDirection};
+pub use gate::NoiseGate;
+pub use limiter::{Limiter, LimiterParameters};
+pub use transient::TransientShaper;
+pub use true_peak::TruePeakDetector;
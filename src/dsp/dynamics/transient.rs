@@ -0,0 +1,284 @@
+//! Transient shaping, which reshapes the attacks and sustains of a signal
+//! regardless of its level.
+
+use super::*;
+
+/// The most which the attacks or sustains may be boosted or cut by, in
+/// decibels.
+pub const MAX_TRANSIENT_GAIN_DB: f64 = 12.0;
+
+/// The attack and release times of the envelopes. Attacks are found by
+/// comparing a fast and a slow attack, and sustains by comparing a fast and
+/// a slow release.
+const FAST_ATTACK_TIME_MS: f64 = 0.0;
+const SLOW_ATTACK_TIME_MS: f64 = 50.0;
+const FAST_RELEASE_TIME_MS: f64 = 200.0;
+const SLOW_RELEASE_TIME_MS: f64 = 1000.0;
+/// How far the envelopes may differ before any gain is applied, in
+/// decibels, which hides their ripple on steady signals.
+const DEAD_ZONE_DB: f64 = 1.5;
+/// How far beyond the dead zone the envelopes must differ for the full gain
+/// to be applied, in decibels.
+const DETECTION_RANGE_DB: f64 = 12.0;
+/// How long changes to the gains are smoothed over, in milliseconds.
+const SMOOTHING_TIME_MS: f64 = 30.0;
+
+/// A stereo transient shaper, which may boost or soften the attacks and
+/// sustains of a signal independently.
+///
+/// The signal's level is followed by a fast envelope, which is compared
+/// with two slower ones: while the envelope with a slower attack lags
+/// behind it, the signal is in an attack, and while the envelope with a
+/// faster release falls below it, the signal is sustaining. As only the
+/// differences between the envelopes are used, the shaping doesn't depend on
+/// the level of the signal. Both channels are shaped together, by the
+/// louder of the two.
+#[derive(Clone, Debug)]
+pub struct TransientShaper {
+    /// fast attack, slow release
+    follower: BallisticsFilter,
+    /// slow attack, slow release
+    lagging: BallisticsFilter,
+    /// fast attack, fast release
+    falling: BallisticsFilter,
+
+    /// The gain applied to attacks, in decibels.
+    attack_db: Smoother<f64>,
+    /// The gain applied to sustains, in decibels.
+    sustain_db: Smoother<f64>,
+
+    /// The gain applied to the latest sample, as a level.
+    gain: f64,
+
+    sample_rate: f64,
+}
+
+impl TransientShaper {
+    /// A shaper which leaves the signal unchanged until its gains are set.
+    pub fn new(sample_rate: f64) -> Self {
+        let mut attack_db = Smoother::new(SMOOTHING_TIME_MS, 0.0, sample_rate);
+        attack_db.set_start_value(0.0);
+        let mut sustain_db = Smoother::new(SMOOTHING_TIME_MS, 0.0, sample_rate);
+        sustain_db.set_start_value(0.0);
+
+        let [follower, lagging, falling] = Self::envelopes(sample_rate);
+
+        Self {
+            follower,
+            lagging,
+            falling,
+
+            attack_db,
+            sustain_db,
+
+            gain: 1.0,
+
+            sample_rate,
+        }
+    }
+
+    /// Sets the gain applied to attacks in decibels, which is clamped to
+    /// [`MAX_TRANSIENT_GAIN_DB`] either way.
+    pub fn set_attack_db(&mut self, gain_db: f64) {
+        self.wake(gain_db);
+        self.attack_db.set_target_value(
+            gain_db.clamp(-MAX_TRANSIENT_GAIN_DB, MAX_TRANSIENT_GAIN_DB),
+        );
+    }
+
+    /// The attack gain which is being moved towards, in decibels.
+    pub fn attack_db(&self) -> f64 {
+        self.attack_db.target_value()
+    }
+
+    /// Sets the gain applied to sustains in decibels, which is clamped to
+    /// [`MAX_TRANSIENT_GAIN_DB`] either way.
+    pub fn set_sustain_db(&mut self, gain_db: f64) {
+        self.wake(gain_db);
+        self.sustain_db.set_target_value(
+            gain_db.clamp(-MAX_TRANSIENT_GAIN_DB, MAX_TRANSIENT_GAIN_DB),
+        );
+    }
+
+    /// The sustain gain which is being moved towards, in decibels.
+    pub fn sustain_db(&self) -> f64 {
+        self.sustain_db.target_value()
+    }
+
+    /// Whether the shaper leaves the signal unchanged, so it may be skipped.
+    pub fn is_neutral(&self) -> bool {
+        [&self.attack_db, &self.sustain_db].iter().all(|gain| {
+            gain.current_value() == 0.0 && gain.target_value() == 0.0
+        })
+    }
+
+    /// The gain applied to the latest sample, in decibels.
+    pub fn gain_db(&self) -> f64 {
+        level_to_db(self.gain)
+    }
+
+    /// Clears the envelopes.
+    pub fn reset(&mut self) {
+        self.follower.reset(0.0);
+        self.lagging.reset(0.0);
+        self.falling.reset(0.0);
+        self.gain = 1.0;
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.attack_db.reset_sample_rate(sample_rate);
+        self.sustain_db.reset_sample_rate(sample_rate);
+
+        [self.follower, self.lagging, self.falling] =
+            Self::envelopes(sample_rate);
+    }
+
+    /// Clears the envelopes if the shaper is about to stop being neutral,
+    /// as they may be skipped while it is.
+    fn wake(&mut self, gain_db: f64) {
+        if self.is_neutral() && gain_db != 0.0 {
+            self.reset();
+        }
+    }
+
+    /// The follower, lagging and falling envelopes.
+    fn envelopes(sample_rate: f64) -> [BallisticsFilter; 3] {
+        [
+            (FAST_ATTACK_TIME_MS, SLOW_RELEASE_TIME_MS),
+            (SLOW_ATTACK_TIME_MS, SLOW_RELEASE_TIME_MS),
+            (FAST_ATTACK_TIME_MS, FAST_RELEASE_TIME_MS),
+        ]
+        .map(|(attack_time_ms, release_time_ms)| {
+            let mut envelope =
+                BallisticsFilter::new(NUM_CHANNELS, sample_rate);
+            envelope.set_level_type(BallisticsLevelType::Peak);
+            envelope.set_attack_time_ms(attack_time_ms);
+            envelope.set_release_time_ms(release_time_ms);
+
+            envelope
+        })
+    }
+
+    /// The level of `envelope` for the next sample, in decibels.
+    fn envelope_db(
+        envelope: &mut BallisticsFilter,
+        in_l: f64,
+        in_r: f64,
+    ) -> f64 {
+        let (env_l, env_r) = envelope.process_stereo(in_l, in_r);
+        level_to_db(env_l.max(env_r).max(db_to_level(MINUS_INFINITY_DB)))
+    }
+
+    /// How far the envelopes differ by `diff_db`, from `0.0` to `1.0`.
+    fn amount(diff_db: f64) -> f64 {
+        ((diff_db - DEAD_ZONE_DB) / DETECTION_RANGE_DB).clamp(0.0, 1.0)
+    }
+}
+
+impl Default for TransientShaper {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE })
+    }
+}
+
+impl Effect for TransientShaper {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        let follower_db = Self::envelope_db(&mut self.follower, in_l, in_r);
+        let lagging_db = Self::envelope_db(&mut self.lagging, in_l, in_r);
+        let falling_db = Self::envelope_db(&mut self.falling, in_l, in_r);
+
+        let attack = Self::amount(follower_db - lagging_db);
+        let sustain = Self::amount(follower_db - falling_db);
+
+        let gain_db = self.attack_db.next() * attack
+            + self.sustain_db.next() * sustain;
+
+        self.gain = db_to_level(gain_db);
+
+        (in_l * self.gain, in_r * self.gain)
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "transient_shaper"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// A hit of a sine at 200 Hz, which decays by 60 dB per second, after
+    /// 100 ms of silence.
+    fn hit(i: usize) -> f64 {
+        let t = i as f64 / SAMPLE_RATE - 0.1;
+
+        if t < 0.0 {
+            0.0
+        }
+        else {
+            0.5 * db_to_level(-60.0 * t) * (TAU * 200.0 * t).sin()
+        }
+    }
+
+    /// The gain of `shaper` at each of `times_ms` into the hit, in
+    /// decibels.
+    fn gains_at(shaper: &mut TransientShaper, times_ms: &[f64]) -> Vec<f64> {
+        let mut gains = Vec::new();
+
+        for i in 0..SAMPLE_RATE as usize {
+            shaper.process_stereo(hit(i), -hit(i));
+
+            let time_ms = (i as f64 / SAMPLE_RATE - 0.1) * 1000.0;
+
+            if times_ms.iter().any(|&t| (t - time_ms).abs() < 0.01) {
+                gains.push(shaper.gain_db());
+            }
+        }
+
+        gains
+    }
+
+    #[test]
+    fn neutral_shaper_is_unchanged() {
+        let mut shaper = TransientShaper::new(SAMPLE_RATE);
+
+        assert!(shaper.is_neutral());
+        assert_eq!(shaper.process_stereo(0.3, -0.7), (0.3, -0.7));
+
+        shaper.set_attack_db(-6.0);
+        assert!(!shaper.is_neutral());
+    }
+
+    #[test]
+    fn attacks_are_boosted() {
+        let mut shaper = TransientShaper::new(SAMPLE_RATE);
+        shaper.set_attack_db(MAX_TRANSIENT_GAIN_DB);
+
+        let gains = gains_at(&mut shaper, &[2.0, 500.0]);
+
+        assert!(gains[0] > 6.0, "attack gain was {} dB", gains[0]);
+        assert!(gains[1].abs() < 1e-6, "sustain gain was {} dB", gains[1]);
+    }
+
+    #[test]
+    fn sustains_are_softened() {
+        let mut shaper = TransientShaper::new(SAMPLE_RATE);
+        shaper.set_sustain_db(-MAX_TRANSIENT_GAIN_DB);
+
+        let gains = gains_at(&mut shaper, &[2.0, 500.0]);
+
+        assert!(gains[0].abs() < 1e-6, "attack gain was {} dB", gains[0]);
+        assert!(gains[1] < -3.0, "sustain gain was {} dB", gains[1]);
+
+        shaper.set_sustain_db(-100.0);
+        assert_eq!(shaper.sustain_db(), -MAX_TRANSIENT_GAIN_DB);
+    }
+}
@@ -0,0 +1,152 @@
+//! True-peak detection, which finds the peaks of a signal between its
+//! samples by oversampling it.
+
+use std::f64::consts::PI;
+
+/// The oversampling factor of a `TruePeakDetector`.
+pub const TRUE_PEAK_OVERSAMPLING: usize = 4;
+/// The number of input samples which each interpolated sample is found from.
+const TAPS_PER_PHASE: usize = 12;
+const NUM_TAPS: usize = TAPS_PER_PHASE * TRUE_PEAK_OVERSAMPLING;
+
+/// A stereo true-peak detector, which interpolates each channel to
+/// [`TRUE_PEAK_OVERSAMPLING`] times the sample rate with a windowed-sinc
+/// polyphase filter, in the manner of ITU-R BS.1770.
+#[derive(Clone, Debug)]
+pub struct TruePeakDetector {
+    /// The filter coefficients of each phase.
+    coefs: [[f64; TAPS_PER_PHASE]; TRUE_PEAK_OVERSAMPLING],
+    /// The latest input samples of each channel.
+    history: [[f64; TAPS_PER_PHASE]; 2],
+    write_pos: usize,
+}
+
+impl TruePeakDetector {
+    pub fn new() -> Self {
+        let mut coefs = [[0.0; TAPS_PER_PHASE]; TRUE_PEAK_OVERSAMPLING];
+        // the center lies on a tap, so the first phase passes the input
+        // samples through unchanged
+        let center = (NUM_TAPS / 2) as f64;
+
+        for n in 0..NUM_TAPS {
+            let offset = n as f64 - center;
+            let x = offset / TRUE_PEAK_OVERSAMPLING as f64;
+            let sinc = if x == 0.0 { 1.0 } else { (PI * x).sin() / (PI * x) };
+            let hann =
+                0.5f64.mul_add((PI * offset / (center + 1.0)).cos(), 0.5);
+
+            coefs[n % TRUE_PEAK_OVERSAMPLING][n / TRUE_PEAK_OVERSAMPLING] =
+                sinc * hann;
+        }
+
+        // each phase passes DC at unity gain
+        for phase in &mut coefs {
+            let sum: f64 = phase.iter().sum();
+            phase.iter_mut().for_each(|c| *c /= sum);
+        }
+
+        Self {
+            coefs,
+            history: [[0.0; TAPS_PER_PHASE]; 2],
+            write_pos: 0,
+        }
+    }
+
+    /// Processes one stereo frame, and returns the highest magnitude of the
+    /// interpolated samples of both channels.
+    pub fn process_stereo(&mut self, in_l: f64, in_r: f64) -> f64 {
+        self.history[0][self.write_pos] = in_l;
+        self.history[1][self.write_pos] = in_r;
+
+        let mut peak: f64 = 0.0;
+
+        for history in &self.history {
+            for phase in &self.coefs {
+                let sample: f64 = phase
+                    .iter()
+                    .enumerate()
+                    .map(|(j, c)| {
+                        let idx = (self.write_pos + TAPS_PER_PHASE - j)
+                            % TAPS_PER_PHASE;
+                        c * history[idx]
+                    })
+                    .sum();
+
+                peak = peak.max(sample.abs());
+            }
+        }
+
+        self.write_pos = (self.write_pos + 1) % TAPS_PER_PHASE;
+
+        peak
+    }
+
+    /// The number of samples by which the detected peaks lag the input.
+    pub const fn latency(&self) -> usize {
+        TAPS_PER_PHASE / 2
+    }
+
+    /// Clears the detector's history.
+    pub fn reset(&mut self) {
+        self.history = [[0.0; TAPS_PER_PHASE]; 2];
+        self.write_pos = 0;
+    }
+}
+
+impl Default for TruePeakDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_4;
+
+    /// The highest peak detected in `signal`, after the detector has
+    /// settled.
+    fn detect(signal: impl Fn(usize) -> (f64, f64)) -> f64 {
+        let mut detector = TruePeakDetector::new();
+
+        (0..256)
+            .map(|i| {
+                let (l, r) = signal(i);
+                detector.process_stereo(l, r)
+            })
+            .skip(NUM_TAPS)
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn finds_peaks_between_samples() {
+        // a sine at a quarter of the sample rate, whose samples all fall
+        // halfway between its peaks
+        let sine =
+            |i: usize| (i as f64).mul_add(PI / 2.0, FRAC_PI_4).sin();
+        let sample_peak = (0..64).map(|i| sine(i).abs()).fold(0.0, f64::max);
+
+        let peak = detect(|i| (sine(i), 0.0));
+
+        assert!(sample_peak < 0.71);
+        assert!((peak - 1.0).abs() < 0.02, "detected {peak}");
+    }
+
+    #[test]
+    fn steady_signals_are_unchanged() {
+        let peak = detect(|_| (0.25, -0.5));
+        assert!((peak - 0.5).abs() < 1e-9, "detected {peak}");
+    }
+
+    #[test]
+    fn reset_clears_the_history() {
+        let mut detector = TruePeakDetector::new();
+
+        for _ in 0..NUM_TAPS {
+            detector.process_stereo(1.0, 1.0);
+        }
+
+        detector.reset();
+        assert_eq!(detector.process_stereo(0.0, 0.0), 0.0);
+    }
+}
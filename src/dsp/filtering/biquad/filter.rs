@@ -8,6 +8,7 @@ use super::*;
 use crate::prelude::*;
 use std::f64::consts::{FRAC_1_SQRT_2, PI, TAU};
 use util::{db_to_level, level_to_db};
+use wide::f64x2;
 use FilterType as FT;
 
 /// Filter coefficients.
@@ -32,6 +33,29 @@ impl Coefs {
     fn identity() -> Self {
         Self { a0: 1.0, a1: 0.0, a2: 0.0, b0: 1.0, b1: 0.0, b2: 0.0 }
     }
+
+    /// The step which moves `self` to `target` over `num_steps`.
+    fn step_to(&self, target: &Self, num_steps: u32) -> Self {
+        let n = f64::from(num_steps);
+
+        Self {
+            a0: (target.a0 - self.a0) / n,
+            a1: (target.a1 - self.a1) / n,
+            a2: (target.a2 - self.a2) / n,
+            b0: (target.b0 - self.b0) / n,
+            b1: (target.b1 - self.b1) / n,
+            b2: (target.b2 - self.b2) / n,
+        }
+    }
+
+    fn add(&mut self, step: &Self) {
+        self.a0 += step.a0;
+        self.a1 += step.a1;
+        self.a2 += step.a2;
+        self.b0 += step.b0;
+        self.b1 += step.b1;
+        self.b2 += step.b2;
+    }
 }
 
 impl Default for Coefs {
@@ -81,6 +105,12 @@ impl Default for BiquadParams {
 /// Note that certain filter types do not use all parameters which can be
 /// passed to the filter. These values are ignored during processing, but
 /// updating them will still signal the filter to recompute.
+///
+/// # Smoothing
+///
+/// By default, new coefficients take effect immediately, which causes
+/// zipper noise if the parameters are moved abruptly. See
+/// [`set_coef_smoothing()`](Self::set_coef_smoothing) to ramp them instead.
 #[derive(Debug, Clone, Default)]
 pub struct BiquadFilter {
     coefs: Coefs,
@@ -91,6 +121,16 @@ pub struct BiquadFilter {
     sample_rate: f64,
 
     needs_recompute: bool,
+
+    /// The coefficients which are ramped towards.
+    target_coefs: Coefs,
+    /// The change in coefficients per sample.
+    coef_step: Coefs,
+    /// The number of samples until the target coefficients are reached.
+    ramp_samples_remaining: u32,
+    /// The number of samples over which each change to the coefficients is
+    /// ramped, or `0` if they change immediately.
+    coef_smoothing: u32,
 }
 
 impl Filter for BiquadFilter {
@@ -101,23 +141,10 @@ impl Filter for BiquadFilter {
     /// is computed — not the filter coefficients. In other words, this method
     /// will compute much faster if there is no parameter change between calls.
     fn process(&mut self, sample: f64) -> f64 {
+        self.update_coefs();
+        self.ramp_coefs();
         let Coefs { a0, a1, a2, b1, b2, b0 } = self.coefs;
 
-        if self.needs_recompute {
-            match self.params.filter_type {
-                FT::Peak => self.set_peak_coefs(),
-                FT::Lowpass => self.set_lowpass_coefs(),
-                FT::Highpass => self.set_highpass_coefs(),
-                FT::Lowshelf => self.set_lowshelf_coefs(),
-                FT::Highshelf => self.set_highshelf_coefs(),
-                FT::Bandpass => self.set_bandpass_coefs(),
-                FT::Notch => self.set_notch_coefs(),
-                FT::Allpass => self.set_allpass_coefs(),
-            };
-
-            self.needs_recompute = false;
-        }
-
         let bottom_sum = self.delayed_in.1 * b2 + self.delayed_out.1 * -a2;
         let middle_sum = self.delayed_in.0 * b1 + self.delayed_out.0 * -a1;
         let output = bottom_sum + middle_sum + (sample * b0);
@@ -134,6 +161,45 @@ impl Filter for BiquadFilter {
         //
         output
     }
+
+    /// Processes each sample of `buffer` in place.
+    ///
+    /// The coefficients are only checked for changes once per block, and the
+    /// filter's state is kept in locals for the duration of the loop. The
+    /// filter is recursive, so it isn't vectorized across samples.
+    ///
+    /// While the coefficients are ramping, those samples are processed one
+    /// at a time.
+    fn process_block(&mut self, buffer: &mut [f64]) {
+        self.update_coefs();
+
+        let num_ramped =
+            (self.ramp_samples_remaining as usize).min(buffer.len());
+        let (ramped, buffer) = buffer.split_at_mut(num_ramped);
+
+        for sample in ramped {
+            *sample = self.process(*sample);
+        }
+
+        let Coefs { a1, a2, b0, b1, b2, .. } = self.coefs;
+
+        let (mut in_1, mut in_2) = self.delayed_in;
+        let (mut out_1, mut out_2) = self.delayed_out;
+
+        for sample in buffer {
+            let input = *sample;
+            let bottom_sum = in_2 * b2 + out_2 * -a2;
+            let middle_sum = in_1 * b1 + out_1 * -a1;
+            let output = bottom_sum + middle_sum + (input * b0);
+
+            (in_2, in_1) = (in_1, input);
+            (out_2, out_1) = (out_1, output);
+            *sample = output;
+        }
+
+        self.delayed_in = (in_1, in_2);
+        self.delayed_out = (out_1, out_2);
+    }
 }
 
 // NOTE: the mul_add() method is used a lot here as it may improve performance on
@@ -148,6 +214,79 @@ impl BiquadFilter {
         Self { sample_rate, ..Self::default() }
     }
 
+    /// Returns the filter with each change to its coefficients ramped over
+    /// `num_samples`. See [`set_coef_smoothing()`](Self::set_coef_smoothing).
+    #[must_use]
+    pub fn with_coef_smoothing(mut self, num_samples: u32) -> Self {
+        self.set_coef_smoothing(num_samples);
+        self
+    }
+
+    /// Ramps each change to the coefficients over `num_samples`, so that
+    /// abrupt parameter changes don't cause zipper noise. `0` (the default)
+    /// changes them immediately.
+    ///
+    /// The coefficients are interpolated linearly, which keeps the poles
+    /// inside the unit circle throughout the ramp. A change during a ramp
+    /// starts a new ramp from the current coefficients.
+    pub fn set_coef_smoothing(&mut self, num_samples: u32) {
+        self.coef_smoothing = num_samples;
+
+        if num_samples == 0 && self.ramp_samples_remaining > 0 {
+            self.coefs = self.target_coefs;
+            self.ramp_samples_remaining = 0;
+        }
+    }
+
+    /// The number of samples over which each change to the coefficients is
+    /// ramped.
+    pub const fn coef_smoothing(&self) -> u32 {
+        self.coef_smoothing
+    }
+
+    /// Processes a sample through each of a stereo pair of filters at once,
+    /// with each channel in a SIMD lane.
+    ///
+    /// The filters may have different parameters. The output is identical
+    /// to processing each filter with [`process()`](Filter::process).
+    pub fn process_pair(
+        left: &mut Self,
+        right: &mut Self,
+        in_l: f64,
+        in_r: f64,
+    ) -> (f64, f64) {
+        left.update_coefs();
+        right.update_coefs();
+        left.ramp_coefs();
+        right.ramp_coefs();
+
+        let (l, r) = (left.coefs, right.coefs);
+        let pair = |a: f64, b: f64| f64x2::new([a, b]);
+
+        let sample = pair(in_l, in_r);
+        let (in_1, in_2) = (
+            pair(left.delayed_in.0, right.delayed_in.0),
+            pair(left.delayed_in.1, right.delayed_in.1),
+        );
+        let (out_1, out_2) = (
+            pair(left.delayed_out.0, right.delayed_out.0),
+            pair(left.delayed_out.1, right.delayed_out.1),
+        );
+
+        let bottom_sum = in_2 * pair(l.b2, r.b2) + out_2 * pair(-l.a2, -r.a2);
+        let middle_sum = in_1 * pair(l.b1, r.b1) + out_1 * pair(-l.a1, -r.a1);
+        let output = bottom_sum + middle_sum + (sample * pair(l.b0, r.b0));
+
+        let [out_l, out_r] = output.to_array();
+
+        left.delayed_in = (in_l, left.delayed_in.0);
+        right.delayed_in = (in_r, right.delayed_in.0);
+        left.delayed_out = (out_l, left.delayed_out.0);
+        right.delayed_out = (out_r, right.delayed_out.0);
+
+        (out_l, out_r)
+    }
+
     /// "Suspends" the filter, leaving any processed signal totally unaltered.
     ///
     /// See `force_recompute()` if you need to "resume" the filter's processing
@@ -164,6 +303,7 @@ impl BiquadFilter {
     pub fn suspend(&mut self) {
         self.coefs = Coefs::identity();
         self.needs_recompute = false;
+        self.ramp_samples_remaining = 0;
     }
 
     /// Resets the sample rate of the filter.
@@ -333,6 +473,57 @@ impl BiquadFilter {
         *a2 = (1.0 - alpha / amp) / *a0;
     }
 
+    /// Recomputes the filter coefficients if a parameter has changed, and
+    /// starts ramping towards them if they are smoothed.
+    fn update_coefs(&mut self) {
+        if !self.needs_recompute {
+            return;
+        }
+
+        // the coefficients are computed in place, so the current ones are
+        // restored if they are ramped
+        let current = self.coefs;
+
+        match self.params.filter_type {
+            FT::Peak => self.set_peak_coefs(),
+            FT::Lowpass => self.set_lowpass_coefs(),
+            FT::Highpass => self.set_highpass_coefs(),
+            FT::Lowshelf => self.set_lowshelf_coefs(),
+            FT::Highshelf => self.set_highshelf_coefs(),
+            FT::Bandpass => self.set_bandpass_coefs(),
+            FT::Notch => self.set_notch_coefs(),
+            FT::Allpass => self.set_allpass_coefs(),
+        };
+
+        self.needs_recompute = false;
+
+        if self.coef_smoothing > 0 {
+            self.target_coefs = self.coefs;
+            self.coefs = current;
+            self.coef_step =
+                current.step_to(&self.target_coefs, self.coef_smoothing);
+            self.ramp_samples_remaining = self.coef_smoothing;
+        }
+    }
+
+    /// Moves the coefficients one sample along their ramp, if they are
+    /// ramping.
+    fn ramp_coefs(&mut self) {
+        if self.ramp_samples_remaining == 0 {
+            return;
+        }
+
+        self.ramp_samples_remaining -= 1;
+
+        if self.ramp_samples_remaining == 0 {
+            // the ramp is finished exactly, despite rounding
+            self.coefs = self.target_coefs;
+        }
+        else {
+            self.coefs.add(&self.coef_step);
+        }
+    }
+
     /// Sets the filter coefficients for a lowpass filter.
     fn set_lowpass_coefs(&mut self) {
         let phi = self.get_phi();
@@ -473,7 +664,7 @@ impl BiquadFilter {
 
     /// Convenience method for obtaining the value of "alpha".
     fn get_alpha(&self, phi: f64) -> f64 {
-        (phi.sin()) / 2.0 * self.params.q
+        phi.sin() / (2.0 * self.params.q)
     }
 
     /// Debug assertions used whenever a parameter is changed.
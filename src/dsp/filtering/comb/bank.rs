@@ -0,0 +1,259 @@
+//! Bank of IIR comb filters which feed back into each other.
+
+use super::*;
+use crate::prelude::*;
+
+/// The lowest frequency which a comb filter can be tuned to.
+pub const MIN_COMB_FREQ_HZ: f64 = 20.0;
+/// The shortest time for the bank to decay by 60 dB.
+pub const MIN_COMB_DECAY_SECS: f64 = 0.05;
+/// The longest time for the bank to decay by 60 dB.
+pub const MAX_COMB_DECAY_SECS: f64 = 20.0;
+
+/// The most which may be fed back into each comb filter from the others,
+/// as the sum of the magnitudes of its row of the feedback matrix. Each comb
+/// filter's gain is at most one, so keeping this below one keeps the bank
+/// stable.
+const MAX_CROSS_FEEDBACK: f64 = 0.95;
+
+/// A bank of IIR comb filters, each tuned to a MIDI note, whose outputs are
+/// fed back into each other through a matrix of coefficients.
+///
+/// The cross-feedback smears energy between the comb filters, which gives
+/// metallic, plate-like textures, so the bank may be used as an alternative
+/// body to [`ResonatorBank`](crate::dsp::ResonatorBank).
+#[derive(Clone)]
+pub struct CombBank {
+    /// The comb filters of each channel.
+    combs: Vec<[IirCombFilter; NUM_CHANNELS]>,
+    /// The cross-feedback matrix, where `feedback[i * max + j]` is how much
+    /// of comb filter `j`'s output is fed into comb filter `i`.
+    feedback: Vec<f64>,
+    /// The last output of each comb filter, for each channel.
+    outputs: Vec<[f64; NUM_CHANNELS]>,
+    /// The input of each comb filter, reused each sample.
+    inputs: Vec<f64>,
+
+    notes: Vec<f64>,
+    decay_secs: f64,
+    num_active: usize,
+    sample_rate: f64,
+}
+
+impl CombBank {
+    /// Returns a new bank of `max_num_combs` comb filters, all tuned to
+    /// A4 with no cross-feedback.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_num_combs` is `0`.
+    pub fn new(sample_rate: f64, max_num_combs: usize) -> Self {
+        assert!(max_num_combs > 0);
+
+        // linear interpolation never has a gain above one, unlike the cubic
+        // forms, which would make the feedback unstable
+        let mut comb = IirCombFilter::with_interpolation(true, sample_rate);
+        comb.set_interpolation(InterpType::Linear);
+
+        let mut bank = Self {
+            combs: vec![[comb.clone(), comb]; max_num_combs],
+            feedback: vec![0.0; max_num_combs * max_num_combs],
+            outputs: vec![[0.0; NUM_CHANNELS]; max_num_combs],
+            inputs: vec![0.0; max_num_combs],
+
+            notes: vec![69.0; max_num_combs],
+            decay_secs: 2.0,
+            num_active: max_num_combs,
+            sample_rate,
+        };
+
+        bank.retune();
+        bank
+    }
+
+    /// Sets the number of active comb filters in the bank. Inactive comb
+    /// filters ring out, but aren't fed any more input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_combs` is `0` or greater than
+    /// [`max_num_combs()`](Self::max_num_combs).
+    pub fn set_num_combs(&mut self, num_combs: usize) {
+        assert!(num_combs != 0 && num_combs <= self.max_num_combs());
+
+        self.num_active = num_combs;
+    }
+
+    /// Returns the number of comb filters available in the bank.
+    pub fn max_num_combs(&self) -> usize {
+        self.combs.len()
+    }
+
+    /// Tunes each comb filter to the MIDI note at the same index of `notes`.
+    /// Notes are clamped between [`MIN_COMB_FREQ_HZ`] and the Nyquist rate.
+    ///
+    /// Each comb filter glides to its new pitch.
+    pub fn set_notes(&mut self, notes: &[f64]) {
+        let len = notes.len().min(self.notes.len());
+        self.notes[..len].copy_from_slice(&notes[..len]);
+
+        self.retune();
+    }
+
+    /// Tunes the bank to the pitches of `data`.
+    ///
+    /// Panning isn't applied, as each comb filter is fed into the others.
+    pub fn set_state_from_data(&mut self, data: &ResoBankData) {
+        self.set_notes(&data.pitches);
+    }
+
+    /// Sets the time for each comb filter to decay by 60 dB, in seconds.
+    ///
+    /// Clamped between [`MIN_COMB_DECAY_SECS`] and [`MAX_COMB_DECAY_SECS`].
+    pub fn set_decay(&mut self, decay_secs: f64) {
+        self.decay_secs =
+            decay_secs.clamp(MIN_COMB_DECAY_SECS, MAX_COMB_DECAY_SECS);
+
+        self.retune();
+    }
+
+    /// Sets how much of comb filter `from`'s output is fed into comb filter
+    /// `to`. Negative amounts are fed back with their polarity inverted.
+    ///
+    /// If the total fed into `to` would make the bank unstable, each of its
+    /// amounts is scaled down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` is out of bounds.
+    pub fn set_feedback(&mut self, from: usize, to: usize, amount: f64) {
+        let max = self.max_num_combs();
+        assert!(from < max && to < max);
+
+        self.feedback[to * max + from] = amount;
+        self.limit_feedback(to);
+    }
+
+    /// Spreads `amount` of feedback evenly from each active comb filter into
+    /// every other, alternating its polarity so that the comb filters
+    /// diffuse into each other rather than all ringing together.
+    ///
+    /// Clamped to `[0.0 - 1.0]`, where `1.0` is the most feedback which is
+    /// stable.
+    pub fn set_diffusion(&mut self, amount: f64) {
+        let max = self.max_num_combs();
+        let num_others = self.num_active.saturating_sub(1).max(1);
+        let gain = amount.clamp(0.0, 1.0) * MAX_CROSS_FEEDBACK
+            / num_others as f64;
+
+        for to in 0..max {
+            for from in 0..max {
+                let is_cross = to != from
+                    && to < self.num_active
+                    && from < self.num_active;
+                let sign = if (to + from) % 2 == 0 { 1.0 } else { -1.0 };
+
+                self.feedback[to * max + from] =
+                    if is_cross { gain * sign } else { 0.0 };
+            }
+        }
+    }
+
+    /// Clears the comb filters and their feedback.
+    pub fn reset(&mut self) {
+        for comb in self.combs.iter_mut().flatten() {
+            comb.clear();
+        }
+
+        self.outputs.iter_mut().for_each(|out| *out = [0.0; NUM_CHANNELS]);
+    }
+
+    /// Scales down the feedback into comb filter `to` if it would make the
+    /// bank unstable.
+    fn limit_feedback(&mut self, to: usize) {
+        let max = self.max_num_combs();
+        let row = &mut self.feedback[to * max..(to + 1) * max];
+        let total: f64 = row.iter().map(|x| x.abs()).sum();
+
+        if total > MAX_CROSS_FEEDBACK {
+            let scale = MAX_CROSS_FEEDBACK / total;
+            row.iter_mut().for_each(|x| *x *= scale);
+        }
+    }
+
+    /// Sets the frequency and decay of each comb filter from its note.
+    fn retune(&mut self) {
+        let nyquist = self.sample_rate * 0.5;
+
+        for (combs, &note) in self.combs.iter_mut().zip(self.notes.iter()) {
+            let freq = note_to_freq(note).clamp(MIN_COMB_FREQ_HZ, nyquist);
+
+            // the signal passes around the loop `freq` times per second, so
+            // each pass loses 60 dB over the decay time's worth of passes
+            let gain_db = -60.0 / (freq * self.decay_secs);
+
+            for comb in combs {
+                comb.set_freq(freq);
+                comb.set_gain_db(gain_db);
+            }
+        }
+    }
+}
+
+impl Effect for CombBank {
+    fn process_mono(&mut self, input: f64, ch_idx: usize) -> f64 {
+        let max = self.max_num_combs();
+        let num_active = self.num_active;
+
+        // the feedback is taken from the previous sample of each comb filter
+        for (i, comb_input) in
+            self.inputs.iter_mut().enumerate().take(num_active)
+        {
+            let row = &self.feedback[i * max..i * max + num_active];
+
+            *comb_input = row
+                .iter()
+                .zip(self.outputs.iter())
+                .fold(input, |acc, (&amount, out)| {
+                    amount.mul_add(out[ch_idx], acc)
+                });
+        }
+
+        let mut output = 0.0;
+
+        for (i, (combs, out)) in
+            self.combs.iter_mut().zip(self.outputs.iter_mut()).enumerate()
+        {
+            let comb_input = if i < num_active { self.inputs[i] } else { 0.0 };
+
+            out[ch_idx] = combs[ch_idx].process(comb_input);
+            output += out[ch_idx];
+        }
+
+        // each comb filter has a peak gain of one, so this keeps the bank's
+        // level similar as more are added
+        output / (num_active as f64).sqrt()
+    }
+
+    fn process_stereo(&mut self, left: f64, right: f64) -> (f64, f64) {
+        (self.process_mono(left, 0), self.process_mono(right, 1))
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "comb_bank"
+    }
+}
+
+impl std::fmt::Debug for CombBank {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CombBank")
+            .field("notes", &self.notes)
+            .field("decay_secs", &self.decay_secs)
+            .field("num_active", &self.num_active)
+            .finish_non_exhaustive()
+    }
+}
@@ -43,6 +43,11 @@ impl IirCombFilter {
         self.filter.reset_sample_rate(sample_rate);
     }
 
+    /// Clears the comb filter's internal buffer, so that it stops ringing.
+    pub fn clear(&mut self) {
+        self.filter.buffer.clear();
+    }
+
     /// Sets the frequency of the comb filter. Must be between 10 Hz and half
     /// the sample rate.
     ///
@@ -1,16 +1,79 @@
 //! FIR (finite impulse response) and IIR (infinite impulse response) comb filter forms.
 
 #![allow(clippy::must_use_candidate)]
+mod bank;
 mod filter;
 mod fir;
 mod iir;
 
 use crate::dsp::*;
 
+pub use bank::CombBank;
 pub use fir::FirCombFilter;
 pub use iir::IirCombFilter;
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// The peak level of the bank's output over `num_samples`, after an
+    /// impulse.
+    fn impulse_peak(bank: &mut CombBank, skip: usize, num: usize) -> f64 {
+        (0..skip + num)
+            .map(|i| bank.process_mono(if i == 0 { 1.0 } else { 0.0 }, 0))
+            .skip(skip)
+            .fold(0.0, |peak, x| f64::max(peak, x.abs()))
+    }
+
+    #[test]
+    fn comb_bank_decays_with_full_diffusion() {
+        let mut bank = CombBank::new(SAMPLE_RATE, 4);
+        bank.set_notes(&[48.0, 55.0, 62.0, 67.0]);
+        bank.set_decay(0.5);
+        bank.set_diffusion(1.0);
+
+        let early = impulse_peak(&mut bank, 0, 4800);
+        let late = impulse_peak(&mut bank, 48000, 4800);
+
+        assert!(early.is_finite());
+        assert!(late < early * 0.01);
+    }
+
+    #[test]
+    fn comb_bank_limits_its_feedback() {
+        let mut bank = CombBank::new(SAMPLE_RATE, 3);
+        bank.set_notes(&[60.0, 64.0, 67.0]);
+        bank.set_decay(20.0);
+
+        for from in 0..3 {
+            for to in 0..3 {
+                bank.set_feedback(from, to, 1.0);
+            }
+        }
+
+        let early = impulse_peak(&mut bank, 0, 4800);
+        let late = impulse_peak(&mut bank, 96000, 4800);
+
+        assert!(late <= early);
+    }
+
+    #[test]
+    fn comb_bank_resonates_at_its_notes() {
+        let level_at = |freq: f64| {
+            let mut bank = CombBank::new(SAMPLE_RATE, 1);
+            bank.set_notes(&[57.0]);
+
+            (0..48000)
+                .map(|i| {
+                    let t = i as f64 / SAMPLE_RATE;
+                    bank.process_mono((TAU * freq * t).sin(), 0)
+                })
+                .skip(24000)
+                .fold(0.0, |peak, x| f64::max(peak, x.abs()))
+        };
+
+        assert!(level_at(220.0) > level_at(233.0) * 4.0);
+    }
 }
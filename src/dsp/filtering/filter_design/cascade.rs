@@ -0,0 +1,402 @@
+//! Higher-order Butterworth and Chebyshev filters, realized as cascades of
+//! biquad filters.
+//!
+//! The poles of the analog prototype are placed for the requested order and
+//! response, and each conjugate pair is realized as a biquad stage with its
+//! own frequency and Q. Odd orders have one real pole left over, which is
+//! realized as a first-order stage.
+
+use super::*;
+use crate::dsp::{BiquadFilter, Effect, Filter, FilterType, FirstOrderFilter};
+
+/// The highest order which a [`CascadedFilter`] may be designed with.
+pub const MAX_CASCADE_ORDER: usize = 16;
+/// The lowest cutoff which a [`CascadedFilter`] may be designed with.
+pub const MIN_CASCADE_CUTOFF_HZ: f64 = 1.0;
+
+/// The shape of a [`CascadedFilter`]'s response.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CascadeResponse {
+    /// A maximally flat passband, which is -3 dB at the cutoff.
+    #[default]
+    Butterworth,
+    /// A passband which ripples by `ripple_db`, in exchange for a steeper
+    /// transition than a Butterworth response of the same order. The response
+    /// is `-ripple_db` at the cutoff.
+    Chebyshev { ripple_db: f64 },
+}
+
+/// A single stage of the analog prototype, normalized to a cutoff of 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PrototypeStage {
+    /// A conjugate pair of poles, with their frequency and Q.
+    SecondOrder { freq: f64, q: f64 },
+    /// A real pole, with its frequency.
+    FirstOrder { freq: f64 },
+}
+
+impl CascadeResponse {
+    /// The stages of an analog lowpass prototype of `order` with this
+    /// response, and its gain at DC.
+    fn prototype(self, order: usize) -> (Vec<PrototypeStage>, f64) {
+        let n = order as f64;
+
+        // Butterworth poles lie on the unit circle, and Chebyshev poles on an
+        // ellipse whose axes are set by the ripple
+        let (sigma_scale, omega_scale, dc_gain) = match self {
+            Self::Butterworth => (1.0, 1.0, 1.0),
+            Self::Chebyshev { ripple_db } => {
+                let epsilon = (10.0f64.powf(ripple_db / 10.0) - 1.0).sqrt();
+                let v0 = (1.0 / epsilon).asinh() / n;
+
+                // even orders start the passband at the bottom of the ripple
+                let dc_gain = if order % 2 == 0 {
+                    1.0 / epsilon.mul_add(epsilon, 1.0).sqrt()
+                }
+                else {
+                    1.0
+                };
+
+                (v0.sinh(), v0.cosh(), dc_gain)
+            }
+        };
+
+        let mut stages: Vec<_> = (0..order / 2)
+            .map(|k| {
+                let theta = PI * (2 * k + 1) as f64 / (2.0 * n);
+                let re = sigma_scale * theta.sin();
+                let im = omega_scale * theta.cos();
+                let freq = re.hypot(im);
+
+                PrototypeStage::SecondOrder { freq, q: freq / (2.0 * re) }
+            })
+            .collect();
+
+        if order % 2 == 1 {
+            stages.push(PrototypeStage::FirstOrder { freq: sigma_scale });
+        }
+
+        (stages, dc_gain)
+    }
+}
+
+/// Builds a [`CascadedFilter`].
+#[derive(Debug, Clone, Copy)]
+pub struct CascadedFilterBuilder {
+    order: usize,
+    cutoff: f64,
+    response: CascadeResponse,
+    filter_type: FilterType,
+    sample_rate: f64,
+}
+
+impl CascadedFilterBuilder {
+    /// Sets the order of the filter, which falls by `6 * order` dB per
+    /// octave. Clamped between `1` and [`MAX_CASCADE_ORDER`].
+    pub fn order(mut self, order: usize) -> Self {
+        self.order = order.clamp(1, MAX_CASCADE_ORDER);
+        self
+    }
+
+    /// Sets the cutoff of the filter in Hz.
+    pub fn cutoff(mut self, cutoff: f64) -> Self {
+        self.cutoff = cutoff;
+        self
+    }
+
+    /// Sets the shape of the filter's response.
+    pub fn response(mut self, response: CascadeResponse) -> Self {
+        self.response = response;
+        self
+    }
+
+    /// Sets whether the filter is a lowpass or a highpass.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `filter_type` isn't `Lowpass` or `Highpass`.
+    pub fn filter_type(mut self, filter_type: FilterType) -> Self {
+        assert!(matches!(
+            filter_type,
+            FilterType::Lowpass | FilterType::Highpass
+        ));
+
+        self.filter_type = filter_type;
+        self
+    }
+
+    /// Designs the filter.
+    pub fn build(self) -> CascadedFilter {
+        let mut filter = CascadedFilter {
+            stages: Default::default(),
+            first_order: Default::default(),
+            gain: 1.0,
+
+            order: self.order,
+            cutoff: self.cutoff,
+            response: self.response,
+            filter_type: self.filter_type,
+            sample_rate: self.sample_rate,
+        };
+
+        filter.design();
+        filter
+    }
+}
+
+/// A stereo Butterworth or Chebyshev lowpass or highpass of any order up to
+/// [`MAX_CASCADE_ORDER`], realized as a cascade of biquad filters.
+///
+/// ```ignore
+/// let mut rumble = CascadedFilter::builder(sample_rate)
+///     .order(8)
+///     .cutoff(40.0)
+///     .filter_type(FilterType::Highpass)
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct CascadedFilter {
+    /// The biquad stages of each channel, in series.
+    stages: [Vec<BiquadFilter>; NUM_CHANNELS],
+    /// The first-order stage of each channel, for odd orders.
+    first_order: [Option<FirstOrderFilter>; NUM_CHANNELS],
+    /// The gain applied after the stages, so that the top of a Chebyshev
+    /// response's ripple is at 0 dB.
+    gain: f64,
+
+    order: usize,
+    cutoff: f64,
+    response: CascadeResponse,
+    filter_type: FilterType,
+    sample_rate: f64,
+}
+
+impl CascadedFilter {
+    /// Returns a builder for a 4th order Butterworth lowpass at 1 kHz.
+    pub const fn builder(sample_rate: f64) -> CascadedFilterBuilder {
+        CascadedFilterBuilder {
+            order: 4,
+            cutoff: 1000.0,
+            response: CascadeResponse::Butterworth,
+            filter_type: FilterType::Lowpass,
+            sample_rate,
+        }
+    }
+
+    /// Sets the cutoff of the filter in Hz, and redesigns its stages.
+    pub fn set_cutoff(&mut self, cutoff: f64) {
+        self.cutoff = cutoff;
+        self.design();
+    }
+
+    pub const fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    pub const fn order(&self) -> usize {
+        self.order
+    }
+
+    pub const fn response(&self) -> CascadeResponse {
+        self.response
+    }
+
+    /// Sets the sample rate of the filter, and redesigns its stages.
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.design();
+    }
+
+    /// Clears the filter's state.
+    pub fn reset(&mut self) {
+        self.design();
+    }
+
+    /// Places the poles for the filter's design, and creates a stage for
+    /// each.
+    fn design(&mut self) {
+        let sr = self.sample_rate;
+        let nyquist = sr * 0.5;
+        let cutoff = self.cutoff.clamp(MIN_CASCADE_CUTOFF_HZ, nyquist * 0.99);
+        let is_highpass = self.filter_type == FilterType::Highpass;
+
+        // each stage's frequency is prewarped from the analog prototype, so
+        // that the whole response lines up with the cutoff
+        let warped_cutoff = (PI * cutoff / sr).tan();
+        let stage_freq = |proto_freq: f64| {
+            let scale =
+                if is_highpass { 1.0 / proto_freq } else { proto_freq };
+
+            (sr / PI) * (warped_cutoff * scale).atan()
+        };
+
+        let (prototype, gain) = self.response.prototype(self.order);
+        self.gain = gain;
+
+        let mut stages = Vec::with_capacity(prototype.len());
+        let mut first_order = None;
+
+        for stage in prototype {
+            match stage {
+                PrototypeStage::SecondOrder { freq, q } => {
+                    let mut biquad = BiquadFilter::new(sr);
+                    biquad.set_type(self.filter_type);
+                    biquad.set_q(q);
+                    biquad.set_freq(stage_freq(freq));
+
+                    stages.push(biquad);
+                }
+                PrototypeStage::FirstOrder { freq } => {
+                    let mut filter = FirstOrderFilter::new(sr);
+                    filter.set_type(self.filter_type);
+                    filter.set_freq(stage_freq(freq));
+
+                    first_order = Some(filter);
+                }
+            }
+        }
+
+        self.stages = [stages.clone(), stages];
+        self.first_order = [first_order.clone(), first_order];
+    }
+}
+
+impl Effect for CascadedFilter {
+    fn process_mono(&mut self, mut input: f64, ch_idx: usize) -> f64 {
+        for stage in &mut self.stages[ch_idx] {
+            input = stage.process(input);
+        }
+
+        if let Some(filter) = &mut self.first_order[ch_idx] {
+            input = filter.process(input);
+        }
+
+        input * self.gain
+    }
+
+    fn process_stereo(
+        &mut self,
+        mut left: f64,
+        mut right: f64,
+    ) -> (f64, f64) {
+        let [stages_l, stages_r] = &mut self.stages;
+
+        for (stage_l, stage_r) in stages_l.iter_mut().zip(stages_r) {
+            (left, right) =
+                BiquadFilter::process_pair(stage_l, stage_r, left, right);
+        }
+
+        if let [Some(filter_l), Some(filter_r)] = &mut self.first_order {
+            left = filter_l.process(left);
+            right = filter_r.process(right);
+        }
+
+        (left * self.gain, right * self.gain)
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "cascaded_filter"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::SQRT_2;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// The gain of `filter` for a sine at `freq`, in decibels.
+    fn gain_db(filter: &mut CascadedFilter, freq: f64) -> f64 {
+        filter.reset();
+        let num_samples = SAMPLE_RATE as usize;
+
+        let sum: f64 = (0..num_samples)
+            .map(|i| {
+                let x = (TAU * freq * i as f64 / SAMPLE_RATE).sin();
+                filter.process_mono(x, 0)
+            })
+            // skips the filter's transient
+            .skip(num_samples / 2)
+            .map(|y| y * y)
+            .sum();
+
+        level_to_db((sum / (num_samples / 2) as f64).sqrt() * SQRT_2)
+    }
+
+    #[test]
+    fn butterworth_is_half_power_at_cutoff() {
+        for order in [1, 2, 5, 8] {
+            for filter_type in [FilterType::Lowpass, FilterType::Highpass] {
+                let mut filter = CascadedFilter::builder(SAMPLE_RATE)
+                    .order(order)
+                    .cutoff(2000.0)
+                    .filter_type(filter_type)
+                    .build();
+
+                let cutoff = gain_db(&mut filter, 2000.0);
+                assert!((cutoff + 3.01).abs() < 0.05, "cutoff was {cutoff} dB");
+            }
+        }
+    }
+
+    #[test]
+    fn higher_orders_are_steeper() {
+        let mut attenuation = (1..=8).map(|order| {
+            let mut filter = CascadedFilter::builder(SAMPLE_RATE)
+                .order(order)
+                .cutoff(80.0)
+                .filter_type(FilterType::Highpass)
+                .build();
+
+            gain_db(&mut filter, 40.0)
+        });
+
+        let first = attenuation.next().unwrap();
+        assert!((first + 6.99).abs() < 0.1, "1st order was {first} dB");
+
+        attenuation.fold(first, |last, next| {
+            assert!(next < last - 5.0);
+            next
+        });
+    }
+
+    #[test]
+    fn chebyshev_ripples_within_its_passband() {
+        let ripple_db = 1.0;
+
+        for order in [4, 5] {
+            let mut filter = CascadedFilter::builder(SAMPLE_RATE)
+                .order(order)
+                .cutoff(4000.0)
+                .response(CascadeResponse::Chebyshev { ripple_db })
+                .build();
+
+            let passband: Vec<_> = (1..16)
+                .map(|i| gain_db(&mut filter, 250.0 * f64::from(i)))
+                .collect();
+
+            for gain in passband {
+                assert!(gain < 0.01 && gain > -ripple_db - 0.01);
+            }
+
+            let edge = gain_db(&mut filter, 4000.0);
+            assert!((edge + ripple_db).abs() < 0.05, "edge was {edge} dB");
+
+            // steeper than a Butterworth of the same order
+            let mut butterworth = CascadedFilter::builder(SAMPLE_RATE)
+                .order(order)
+                .cutoff(4000.0)
+                .build();
+
+            assert!(
+                gain_db(&mut filter, 8000.0)
+                    < gain_db(&mut butterworth, 8000.0) - 6.0
+            );
+        }
+    }
+}
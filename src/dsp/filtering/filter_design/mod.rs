@@ -1,13 +1,16 @@
-//! Filter design methods intended for oversampling. All based on the JUCE implementations.
+//! Filter design methods. The oversampling designs are all based on the JUCE
+//! implementations, and are unused in this project.
 //!
-//! Unused in this project.
+//! [`CascadedFilter`] designs higher-order Butterworth and Chebyshev filters.
 
 use crate::prelude::*;
 use realfft::num_complex::ComplexFloat;
 use std::rc::Rc;
 
+pub mod cascade;
 pub mod coefficients;
 pub mod design;
 
+pub use cascade::*;
 pub use coefficients::*;
 pub use design::*;
@@ -110,6 +110,32 @@ impl LinkwitzRileyFilter {
         (low_out, high_out)
     }
 
+    /// The high-passed output of one channel of
+    /// [`process_high_low()`](Self::process_high_low), so that each channel
+    /// may be processed separately.
+    pub fn process_high(&mut self, sample: f64, channel_idx: usize) -> f64 {
+        let LRFCoefs { g, h, r2 } = self.coefs;
+        let ch = channel_idx;
+
+        let high = (sample - (r2 + g) * self.delayed[ch] - self.delayed[2 + ch]) * h;
+
+        let band = g * high + self.delayed[ch];
+        self.delayed[ch] = g * high + band;
+
+        let low = g * band + self.delayed[2 + ch];
+        self.delayed[2 + ch] = g * band + low;
+
+        let high_2 = (low - (r2 + g) * self.delayed[4 + ch] - self.delayed[6 + ch]) * h;
+
+        let band_2 = g * high_2 + self.delayed[4 + ch];
+        self.delayed[4 + ch] = g * high_2 + band_2;
+
+        let low_2 = g * band_2 + self.delayed[6 + ch];
+        self.delayed[6 + ch] = g * band_2 + low_2;
+
+        low - r2 * band + high - low_2
+    }
+
     fn update(&mut self) {
         let LRFCoefs { g, h, r2 } = &mut self.coefs;
 
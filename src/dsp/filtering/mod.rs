@@ -6,17 +6,32 @@ pub const BUTTERWORTH_Q: f64 = std::f64::consts::FRAC_1_SQRT_2;
 
 pub mod biquad;
 pub mod comb;
+pub mod convolver;
 pub mod filter_design;
 pub mod first_order;
 pub mod lrf;
+pub mod parametric_eq;
 pub mod resonator;
 pub mod simple;
 pub mod svf;
+pub mod tilt;
 
 /// A trait which allows for filters to be dynamically dispatched.
 pub trait Filter: Send + DynClone {
     /// Generic processing method for a filter.
     fn process(&mut self, sample: f64) -> f64;
+
+    /// Processes each sample of `buffer` in place.
+    ///
+    /// This calls [`process()`](Self::process) for each sample by default.
+    /// Filters should override it where work can be moved out of the
+    /// per-sample loop, which also avoids dispatching each sample
+    /// dynamically.
+    fn process_block(&mut self, buffer: &mut [f64]) {
+        for sample in buffer {
+            *sample = self.process(*sample);
+        }
+    }
 }
 
 dyn_clone::clone_trait_object!(Filter);
@@ -25,7 +40,7 @@ dyn_clone::clone_trait_object!(Filter);
 ///
 /// Currently, peak, lowpass, highpass, bandpass, notch, and allpass biquad
 /// filters are implemented.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum FilterType {
     #[default]
     Peak,
@@ -40,3 +55,152 @@ pub enum FilterType {
 
 // TODO SIMD optimisations, vroom
 // Add more common methods to this trait.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::{
+        BiquadFilter, Convolver, OnePoleLowpass, StateVariableFilter,
+    };
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// Processes a test signal with a copy of `filter` per sample, and with
+    /// `filter` in blocks of `block_size`, and asserts that they match.
+    fn assert_blocks_match<F>(filter: &mut F, block_size: usize)
+    where
+        F: Filter + Clone,
+    {
+        let mut per_sample = filter.clone();
+        let signal: Vec<f64> =
+            (0..1000).map(|i| (i as f64 * 0.37).sin() * 0.8).collect();
+
+        let expected: Vec<f64> =
+            signal.iter().map(|&x| per_sample.process(x)).collect();
+
+        let mut blocks = signal;
+        for block in blocks.chunks_mut(block_size) {
+            filter.process_block(block);
+        }
+
+        assert_eq!(blocks, expected);
+    }
+
+    #[test]
+    fn biquad_blocks_match_samples() {
+        let mut filter = BiquadFilter::new(SAMPLE_RATE);
+        filter.set_type(FilterType::Lowpass);
+        filter.set_freq(1000.0);
+        filter.set_q(2.0);
+
+        assert_blocks_match(&mut filter, 64);
+
+        // the coefficients are recomputed in the next block
+        filter.set_freq(5000.0);
+        assert_blocks_match(&mut filter, 13);
+    }
+
+    #[test]
+    fn biquad_pairs_match_samples() {
+        let mut left = BiquadFilter::new(SAMPLE_RATE);
+        left.set_type(FilterType::Lowpass);
+        left.set_freq(800.0);
+
+        let mut right = BiquadFilter::new(SAMPLE_RATE);
+        right.set_type(FilterType::Peak);
+        right.set_freq(3000.0);
+        right.set_gain(6.0);
+
+        let (mut scalar_l, mut scalar_r) = (left.clone(), right.clone());
+
+        for i in 0..1000 {
+            let x = (i as f64 * 0.37).sin() * 0.8;
+            let expected = (scalar_l.process(x), scalar_r.process(-x));
+
+            assert_eq!(
+                BiquadFilter::process_pair(&mut left, &mut right, x, -x),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn smoothed_biquad_ramps_to_new_coefficients() {
+        let mut immediate = BiquadFilter::new(SAMPLE_RATE);
+        immediate.set_type(FilterType::Lowpass);
+        immediate.set_freq(500.0);
+
+        immediate.process(0.0);
+        let mut smoothed = immediate.clone().with_coef_smoothing(64);
+
+        immediate.set_freq(5000.0);
+        smoothed.set_freq(5000.0);
+        immediate.process(0.0);
+        smoothed.process(0.0);
+
+        let omega = 1.0;
+        assert!(smoothed.response_at(omega) < immediate.response_at(omega));
+
+        // blocks and samples match while the coefficients ramp
+        assert_blocks_match(&mut smoothed, 16);
+        assert_eq!(smoothed.response_at(omega), immediate.response_at(omega));
+    }
+
+    #[test]
+    fn svf_blocks_match_samples() {
+        for filter_type in
+            [FilterType::Lowpass, FilterType::Highpass, FilterType::Bandpass]
+        {
+            let mut filter = StateVariableFilter::new(1, SAMPLE_RATE);
+            filter.set_type(filter_type);
+            filter.set_cutoff_freq(800.0);
+
+            assert_blocks_match(&mut filter, 64);
+        }
+    }
+
+    #[test]
+    fn smoothed_svf_ramps_to_new_cutoff() {
+        let impulse_response = |filter: &mut StateVariableFilter| {
+            filter.reset(0.0);
+            let mut block = [0.0; 32];
+            block[0] = 1.0;
+            filter.process_block(&mut block);
+            block
+        };
+
+        let mut immediate = StateVariableFilter::new(1, SAMPLE_RATE);
+        immediate.set_cutoff_freq(300.0);
+
+        let mut smoothed = immediate.clone().with_coef_smoothing(64);
+        smoothed.set_cutoff_freq(3000.0);
+        immediate.set_cutoff_freq(3000.0);
+
+        // the first samples are still close to the old cutoff
+        let ramping = impulse_response(&mut smoothed);
+        assert_ne!(ramping, impulse_response(&mut immediate));
+
+        assert_blocks_match(&mut smoothed, 5);
+        assert_eq!(
+            impulse_response(&mut smoothed),
+            impulse_response(&mut immediate)
+        );
+    }
+
+    #[test]
+    fn one_pole_blocks_match_samples() {
+        let mut filter = OnePoleLowpass::new(SAMPLE_RATE);
+        filter.set_cutoff_freq(200.0);
+
+        assert_blocks_match(&mut filter, 7);
+    }
+
+    #[test]
+    fn convolver_blocks_match_samples() {
+        let kernel: Vec<f64> = (0..48).map(|i| 0.9f64.powi(i)).collect();
+        let mut filter = Convolver::new(&kernel);
+
+        assert_blocks_match(&mut filter, 64);
+        assert_blocks_match(&mut filter, 5);
+    }
+}
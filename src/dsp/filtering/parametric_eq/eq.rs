@@ -0,0 +1,162 @@
+//! A stereo parametric EQ built from biquad filters.
+
+use super::*;
+use crate::dsp::util::Effect;
+use crate::prelude::*;
+
+/// The most bands a `ParametricEq` can use.
+pub const MAX_EQ_BANDS: usize = 8;
+/// The lowest frequency of a band.
+pub const MIN_EQ_FREQ_HZ: f64 = 20.0;
+/// The lowest Q of a band.
+pub const MIN_EQ_Q: f64 = 0.1;
+/// The widest boost or cut of a band, in decibels.
+pub const MAX_EQ_GAIN_DB: f64 = 24.0;
+
+/// One band of a `ParametricEq`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EqBand {
+    pub filter_type: FilterType,
+    pub freq: f64,
+    /// The boost or cut of peak and shelf bands, in decibels.
+    pub gain_db: f64,
+    pub q: f64,
+}
+
+impl EqBand {
+    /// Whether the band changes the signal at all. Peaks and shelves
+    /// without gain have no effect.
+    pub fn is_flat(&self) -> bool {
+        matches!(
+            self.filter_type,
+            FilterType::Peak | FilterType::Lowshelf | FilterType::Highshelf
+        ) && self.gain_db == 0.0
+    }
+
+    /// The band's filter parameters, with its frequency, gain and Q clamped
+    /// to ranges which are stable at `sample_rate`.
+    fn params(&self, sample_rate: f64) -> BiquadParams {
+        BiquadParams {
+            freq: self.freq.clamp(MIN_EQ_FREQ_HZ, sample_rate * 0.45),
+            gain: self.gain_db.clamp(-MAX_EQ_GAIN_DB, MAX_EQ_GAIN_DB),
+            q: self.q.max(MIN_EQ_Q),
+            filter_type: self.filter_type,
+        }
+    }
+}
+
+impl Default for EqBand {
+    fn default() -> Self {
+        Self {
+            filter_type: FilterType::Peak,
+            freq: 1000.0,
+            gain_db: 0.0,
+            q: BUTTERWORTH_Q,
+        }
+    }
+}
+
+/// A stereo EQ with up to [`MAX_EQ_BANDS`] bands in series.
+#[derive(Debug, Clone)]
+pub struct ParametricEq {
+    bands: [Option<EqBand>; MAX_EQ_BANDS],
+    /// The left and right filters of each band.
+    filters: [[BiquadFilter; 2]; MAX_EQ_BANDS],
+    sample_rate: f64,
+}
+
+impl ParametricEq {
+    /// A flat EQ, without any bands.
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            bands: [None; MAX_EQ_BANDS],
+            filters: std::array::from_fn(|_| {
+                [BiquadFilter::new(sample_rate), BiquadFilter::new(sample_rate)]
+            }),
+            sample_rate,
+        }
+    }
+
+    /// Replaces the bands of the EQ. Bands after the first
+    /// [`MAX_EQ_BANDS`] are ignored.
+    pub fn set_bands<'a>(
+        &mut self,
+        bands: impl IntoIterator<Item = &'a EqBand>,
+    ) {
+        let mut bands = bands.into_iter();
+
+        for (slot, filters) in self.bands.iter_mut().zip(&mut self.filters) {
+            let band = bands.next().copied();
+
+            if let Some(band) = band
+                && *slot != Some(band)
+            {
+                let params = band.params(self.sample_rate);
+                filters.iter_mut().for_each(|f| f.set_params(&params));
+            }
+
+            *slot = band;
+        }
+    }
+
+    pub fn bands(&self) -> impl Iterator<Item = &EqBand> {
+        self.bands.iter().flatten()
+    }
+
+    /// Whether the EQ leaves the signal unchanged, so it may be skipped.
+    pub fn is_flat(&self) -> bool {
+        self.bands().all(EqBand::is_flat)
+    }
+
+    /// Equalizes `block` of channel `channel_idx` in place, one band at a
+    /// time.
+    pub fn process_block(&mut self, block: &mut [f64], channel_idx: usize) {
+        for (band, filters) in self.bands.iter().zip(&mut self.filters) {
+            if band.is_some_and(|band| !band.is_flat()) {
+                filters[channel_idx].process_block(block);
+            }
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+
+        for (band, filters) in self.bands.iter().zip(&mut self.filters) {
+            for filter in filters.iter_mut() {
+                filter.reset_sample_rate(sample_rate);
+
+                if let Some(band) = band {
+                    filter.set_params(&band.params(sample_rate));
+                }
+            }
+        }
+    }
+}
+
+impl Default for ParametricEq {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE })
+    }
+}
+
+impl Effect for ParametricEq {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        let mut out = (in_l, in_r);
+
+        for (band, [left, right]) in self.bands.iter().zip(&mut self.filters) {
+            if band.is_some_and(|band| !band.is_flat()) {
+                out = (left.process(out.0), right.process(out.1));
+            }
+        }
+
+        out
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "parametric eq"
+    }
+}
@@ -0,0 +1,87 @@
+//! Multi-band parametric EQ.
+
+pub mod eq;
+use super::biquad::{BiquadFilter, BiquadParams};
+use super::*;
+
+pub use eq::{EqBand, ParametricEq, MAX_EQ_BANDS};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::util::Effect;
+    use std::f64::consts::TAU;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// The RMS level of a sine at `freq` after it passes through `eq`.
+    fn sine_rms(eq: &mut ParametricEq, freq: f64) -> f64 {
+        let num_samples = 9600;
+
+        let sum: f64 = (0..num_samples)
+            .map(|i| {
+                let x = (TAU * freq * i as f64 / SAMPLE_RATE).sin();
+                eq.process_stereo(x, x).0
+            })
+            // skips the filters' transients
+            .skip(num_samples / 2)
+            .map(|y| y * y)
+            .sum();
+
+        (sum / (num_samples / 2) as f64).sqrt()
+    }
+
+    #[test]
+    fn flat_eq_is_unchanged() {
+        let mut eq = ParametricEq::new(SAMPLE_RATE);
+        assert!(eq.is_flat());
+        assert_eq!(eq.process_stereo(0.3, -0.7), (0.3, -0.7));
+
+        eq.set_bands(&[EqBand::default()]);
+        assert!(eq.is_flat());
+        assert_eq!(eq.process_stereo(0.3, -0.7), (0.3, -0.7));
+    }
+
+    #[test]
+    fn peak_boosts_its_frequency() {
+        let mut eq = ParametricEq::new(SAMPLE_RATE);
+        eq.set_bands(&[EqBand { gain_db: 6.0, ..EqBand::default() }]);
+        assert!(!eq.is_flat());
+
+        let boosted = sine_rms(&mut eq, 1000.0);
+        let expected = std::f64::consts::FRAC_1_SQRT_2 * 2.0;
+        assert!((boosted - expected).abs() < 0.05, "got {boosted}");
+
+        // far from the band, the level is barely changed
+        let mut eq = ParametricEq::new(SAMPLE_RATE);
+        eq.set_bands(&[EqBand { gain_db: 6.0, ..EqBand::default() }]);
+        let untouched = sine_rms(&mut eq, 50.0);
+        assert!((untouched - std::f64::consts::FRAC_1_SQRT_2).abs() < 0.05);
+    }
+
+    #[test]
+    fn extra_bands_are_ignored() {
+        let mut eq = ParametricEq::new(SAMPLE_RATE);
+        let bands = [EqBand::default(); MAX_EQ_BANDS + 4];
+
+        eq.set_bands(&bands);
+        assert_eq!(eq.bands().count(), MAX_EQ_BANDS);
+
+        eq.set_bands(&bands[..2]);
+        assert_eq!(eq.bands().count(), 2);
+    }
+
+    #[test]
+    fn unstable_parameters_are_clamped() {
+        let mut eq = ParametricEq::new(SAMPLE_RATE);
+        eq.set_bands(&[EqBand {
+            filter_type: FilterType::Lowpass,
+            freq: SAMPLE_RATE,
+            q: 0.0,
+            ..EqBand::default()
+        }]);
+
+        let rms = sine_rms(&mut eq, 1000.0);
+        assert!(rms.is_finite() && rms > 0.6);
+    }
+}
@@ -1,12 +1,91 @@
 //! Resonator bank with musical features.
+//!
+//! Most of the bank's CPU time is spent in its filters, so they are
+//! processed in groups of [`NUM_LANES`], with each resonator in a SIMD lane.
+//! Any resonators left over are processed one at a time.
 
 use super::*;
+use crate::app::musical::chord::ChordGen;
 use crate::dsp::*;
 use crate::prelude::*;
 use two_pole_resonator::TwoPoleResonator;
 
 type Resonator = AudioUtility<StereoWrapper<TwoPoleResonator>>;
 
+/// The number of resonators which are processed at once.
+const NUM_LANES: usize = 4;
+
+/// A group of [`NUM_LANES`] resonators, with each in a SIMD lane.
+///
+/// The coefficients and gains are copied from the resonators whenever they
+/// change, but the state of the filters is only kept here, so the
+/// resonators' own filters are left unprocessed.
+#[derive(Clone, Copy, Debug, Default)]
+struct ResonatorLanes {
+    /// The input gain of each channel, which is zero for the resonators
+    /// which are ringing out.
+    b0: [f64x4; NUM_CHANNELS],
+    a1: [f64x4; NUM_CHANNELS],
+    a2: [f64x4; NUM_CHANNELS],
+    /// The output gain of each channel, including panning.
+    gain: [f64x4; NUM_CHANNELS],
+    z1: [f64x4; NUM_CHANNELS],
+    z2: [f64x4; NUM_CHANNELS],
+}
+
+impl ResonatorLanes {
+    /// Copies the coefficients and gains of `resonators`, the first of which
+    /// is at `offset` in the bank.
+    fn gather(
+        &mut self,
+        resonators: &[Resonator],
+        offset: usize,
+        num_active: usize,
+    ) {
+        let mut b0 = [[0.0; NUM_LANES]; NUM_CHANNELS];
+        let mut a1 = b0;
+        let mut a2 = b0;
+        let mut gain = b0;
+
+        for (lane, res) in resonators.iter().enumerate() {
+            let is_active = offset + lane < num_active;
+            let (gain_l, gain_r) = res.output_gains();
+            let filters = [(&res.l, gain_l), (&res.r, gain_r)];
+
+            for (ch, (filter, out_gain)) in filters.into_iter().enumerate() {
+                let [b, a_1, a_2] = filter.coefs();
+
+                b0[ch][lane] = if is_active { b } else { 0.0 };
+                a1[ch][lane] = a_1;
+                a2[ch][lane] = a_2;
+                gain[ch][lane] = out_gain;
+            }
+        }
+
+        self.b0 = b0.map(f64x4::new);
+        self.a1 = a1.map(f64x4::new);
+        self.a2 = a2.map(f64x4::new);
+        self.gain = gain.map(f64x4::new);
+    }
+
+    /// Processes `input` through each resonator's filter for `ch_idx`, and
+    /// returns the output of each.
+    fn process(&mut self, input: f64, ch_idx: usize) -> f64x4 {
+        let z1 = self.z1[ch_idx];
+        let z2 = self.z2[ch_idx];
+
+        let output = f64x4::splat(input).mul_add(
+            self.b0[ch_idx],
+            z2.mul_add(-self.a2[ch_idx], z1 * -self.a1[ch_idx]),
+        );
+
+        self.z2[ch_idx] = z1;
+        self.z1[ch_idx] = output;
+
+        output * self.gain[ch_idx]
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct ResonatorBankParams {
     pub root_note: f64,
@@ -14,8 +93,8 @@ pub struct ResonatorBankParams {
 
     /// How much panning is applied to each resonator.
     pub panning_scale: f64,
-    /// Whether each resonator's left and right filter should have the same pitch.
-    // stereo_link: bool,
+    /// How far each resonator's left and right filters are detuned apart.
+    pub stereo_spread: f64,
     /// Whether each resonator's pitch should be quantised to the musical scale.
     pub quantize_to_scale: bool,
     /// The overall range of (original) resonator pitches.
@@ -24,6 +103,14 @@ pub struct ResonatorBankParams {
     pub freq_shift: f64,
     /// The amount each pitch can skew towards its original value.
     pub inharm: f64,
+    /// How far the pitches are stretched away from the root note, like the
+    /// partials of a stiff string. Negative values squash them together.
+    pub stretch: f64,
+    /// How much faster higher resonators decay than lower ones.
+    pub decay_scaling: f64,
+    /// How far each pitch has morphed from its original value towards its
+    /// morph target.
+    pub pitch_morph: f64,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -42,10 +129,20 @@ impl ResoBankData {
 pub struct ResonatorBank {
     resonators: Vec<Resonator>,
     original_pitches: Vec<f64>,
+    morph_pitches: Vec<f64>,
+    /// Which way each resonator's left and right filters are detuned, from
+    /// `-1.0` to `1.0`.
+    stereo_offsets: Vec<f64>,
     active_pitches: Vec<Smoother<f64>>,
     panning: Vec<Smoother<f64>>,
     params: ResonatorBankParams,
     num_active: usize,
+
+    /// The resonators which are processed in groups, in order.
+    lanes: Vec<ResonatorLanes>,
+    /// Whether the lanes' coefficients need to be copied from the
+    /// resonators again.
+    lanes_need_update: bool,
 }
 
 impl ResonatorBank {
@@ -53,6 +150,16 @@ impl ResonatorBank {
     pub const NOTE_MIDDLE: f64 = 79.0;
     pub const NOTE_MAX: f64 = 128.0;
     const INHARM_SCALE: f64 = 0.015;
+    /// The resonance of each filter at `NOTE_MIDDLE`.
+    const RESONANCE: f64 = 0.9999;
+    /// How much the distance of each pitch from the root note is stretched
+    /// at full stretch.
+    const MAX_STRETCH: f64 = 0.1;
+    /// How far each filter is detuned from its resonator's pitch at full
+    /// stereo spread, in semitones.
+    const MAX_STEREO_DETUNE: f64 = 0.5;
+    /// The most octaves which pitches set from a scale may cover.
+    const MAX_SCALE_OCTAVES: f64 = 4.0;
 
     pub fn new(sample_rate: f64, max_num_resonators: usize) -> Self {
         assert!(max_num_resonators > 0);
@@ -69,23 +176,36 @@ impl ResonatorBank {
                 max_num_resonators
             ],
             original_pitches: vec![0.0; max_num_resonators],
+            morph_pitches: vec![0.0; max_num_resonators],
+            stereo_offsets: (0..max_num_resonators)
+                .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+                .collect(),
             active_pitches: vec![pitch_smoother; max_num_resonators],
             panning: vec![pan_smoother; max_num_resonators],
             num_active: max_num_resonators,
             params: ResonatorBankParams {
                 panning_scale: 1.0,
+                stereo_spread: 0.0,
                 freq_shift: 0.0,
                 freq_spread: 0.0,
                 root_note: 69.0,
                 quantize_to_scale: false,
                 scale: Scale::default(),
                 inharm: 0.0,
+                stretch: 0.0,
+                decay_scaling: 0.0,
+                pitch_morph: 0.0,
             },
+            lanes: vec![
+                ResonatorLanes::default();
+                max_num_resonators / NUM_LANES
+            ],
+            lanes_need_update: true,
         };
 
         s.resonators.iter_mut().for_each(|res| {
-            res.l.set_resonance(0.9999);
-            res.r.set_resonance(0.9999);
+            res.l.set_resonance(Self::RESONANCE);
+            res.r.set_resonance(Self::RESONANCE);
             res.set_gain_db(-75.0);
         });
 
@@ -95,6 +215,7 @@ impl ResonatorBank {
     pub fn set_params(&mut self, params: ResonatorBankParams) {
         self.params = params;
         self.set_active_pitches();
+        self.retune_resonators();
     }
 
     /// Sets the number of active resonators in the bank.
@@ -112,6 +233,7 @@ impl ResonatorBank {
         );
 
         self.num_active = num_resonators;
+        self.lanes_need_update = true;
     }
 
     /// Returns the maximum number of resonators available in the bank.
@@ -127,6 +249,7 @@ impl ResonatorBank {
 
         self.set_active_pitches();
         self.randomize_panning();
+        self.randomize_stereo_offsets();
     }
 
     pub fn quantize_to_scale(&mut self, quantize_to_scale: bool) {
@@ -209,6 +332,115 @@ impl ResonatorBank {
         }
     }
 
+    /// Sets how far the pitches are stretched away from the root note, which
+    /// makes them inharmonic. Negative values squash them together instead.
+    ///
+    /// Clamped to `[-1.0 - 1.0]`.
+    pub fn set_stretch(&mut self, stretch: f64) {
+        if epsilon_eq(self.params.stretch, stretch) {
+            return;
+        }
+
+        self.params.stretch = stretch.clamp(-1.0, 1.0);
+        self.set_active_pitches();
+    }
+
+    /// Sets how far each resonator's left and right filters are detuned
+    /// apart, which widens the bank.
+    ///
+    /// Clamped to `[0.0 - 1.0]`.
+    pub fn set_stereo_spread(&mut self, spread: f64) {
+        if epsilon_eq(self.params.stereo_spread, spread) {
+            return;
+        }
+
+        self.params.stereo_spread = spread.clamp(0.0, 1.0);
+        self.retune_resonators();
+    }
+
+    /// Sets how much faster higher resonators decay than lower ones. At
+    /// `0.0`, every resonator decays at the same rate.
+    ///
+    /// Clamped to `[0.0 - 1.0]`.
+    pub fn set_decay_scaling(&mut self, scaling: f64) {
+        if epsilon_eq(self.params.decay_scaling, scaling) {
+            return;
+        }
+
+        self.params.decay_scaling = scaling.clamp(0.0, 1.0);
+        self.retune_resonators();
+    }
+
+    /// Sets the raw pitches which the bank morphs towards, one per
+    /// resonator.
+    ///
+    /// Use [`set_pitch_morph()`](Self::set_pitch_morph) to move between
+    /// the original pitches and these.
+    pub fn set_morph_pitches(&mut self, pitches: &[f64]) {
+        let len = pitches.len().min(self.morph_pitches.len());
+        self.morph_pitches[..len].copy_from_slice(&pitches[..len]);
+
+        if self.params.pitch_morph > 0.0 {
+            self.set_active_pitches();
+        }
+    }
+
+    /// Sets how far each pitch has morphed from its original value (`0.0`)
+    /// towards its morph target (`1.0`), so that the bank can glide between
+    /// two pitch sets rather than jumping.
+    ///
+    /// Clamped to `[0.0 - 1.0]`.
+    pub fn set_pitch_morph(&mut self, morph: f64) {
+        if epsilon_eq(self.params.pitch_morph, morph) {
+            return;
+        }
+
+        self.params.pitch_morph = morph.clamp(0.0, 1.0);
+        self.set_active_pitches();
+    }
+
+    /// Tunes the active resonators to `scale`, from `root` (a MIDI note) up
+    /// across `spread` of the bank's range, from one octave at `0.0` to four
+    /// at `1.0`.
+    ///
+    /// The tones of the scale's tonic chord are given out first, so they
+    /// sound in the most resonators. As the pitches are already spread, this
+    /// replaces the bank's frequency spread and shift.
+    ///
+    /// Each resonator glides to its new pitch, and the new pitches are
+    /// handed out from lowest to highest so that each moves as little as
+    /// possible, such as when the scale changes with the mode.
+    pub fn set_pitches_from_scale(
+        &mut self,
+        scale: Scale,
+        root: f64,
+        spread: f64,
+    ) {
+        let pitches =
+            Self::pitches_from_scale(scale, root, spread, self.num_active);
+
+        let mut order: Vec<usize> = (0..self.num_active).collect();
+        order.sort_by(|&a, &b| {
+            let a = self.active_pitches[a].current_value();
+            let b = self.active_pitches[b].current_value();
+
+            a.total_cmp(&b)
+        });
+
+        for (&i, &pitch) in order.iter().zip(pitches.iter()) {
+            self.original_pitches[i] = pitch;
+        }
+
+        self.params.scale = scale;
+        self.params.root_note = root;
+        self.params.quantize_to_scale = true;
+        self.params.freq_spread = 1.0;
+        self.params.freq_shift = 0.0;
+        self.params.pitch_morph = 0.0;
+
+        self.set_active_pitches();
+    }
+
     /// Returns a mutable reference to the raw resonator pitches.
     pub fn original_pitches_mut(&mut self) -> &mut [f64] {
         &mut self.original_pitches
@@ -254,7 +486,11 @@ impl ResonatorBank {
     }
 
     /// Returns a mutable reference to the internal resonators.
+    ///
+    /// Only the parameters of the resonators which are processed in groups
+    /// are used, as their filters' state is kept by the bank.
     pub fn inner_mut(&mut self) -> &mut [Resonator] {
+        self.lanes_need_update = true;
         &mut self.resonators
     }
 
@@ -267,6 +503,15 @@ impl ResonatorBank {
         self.update_panning();
     }
 
+    /// Randomises which way each resonator's filters are detuned.
+    fn randomize_stereo_offsets(&mut self) {
+        self.stereo_offsets.iter_mut().for_each(|offset| {
+            *offset = random_range(-1.0, 1.0);
+        });
+
+        self.retune_resonators();
+    }
+
     /// Updates the panning value of each resonator.
     fn update_panning(&mut self) {
         self.resonators
@@ -279,6 +524,8 @@ impl ResonatorBank {
                 // have low overhead when they are already at their target value.
                 res.set_pan(pan.next() * self.params.panning_scale);
             });
+
+        self.lanes_need_update = true;
     }
 
     /// Updates each resonator's pitch.
@@ -289,43 +536,165 @@ impl ResonatorBank {
         }
         let nyquist = self.get_sample_rate() * 0.5;
 
-        self.resonators
+        for (i, (res, p)) in self
+            .resonators
             .iter_mut()
-            .take(self.num_active)
             .zip(self.active_pitches.iter_mut())
-            .for_each(|(res, p)| {
-                let note = p.next();
-                let freq = note_to_freq(note);
+            .enumerate()
+        {
+            if i >= self.num_active && !p.is_active() {
+                continue;
+            }
 
-                res.l.set_cutoff(freq.min(nyquist));
-                res.r.set_cutoff(freq.min(nyquist));
-            });
+            let offset = self.stereo_offsets[i];
+            Self::tune(res, p.next(), offset, &self.params, nyquist);
+        }
 
-        for (res, p) in self
+        self.lanes_need_update = true;
+    }
+
+    /// Re-applies each resonator's current pitch, such as after its stereo
+    /// spread or decay has changed.
+    fn retune_resonators(&mut self) {
+        let nyquist = self.get_sample_rate() * 0.5;
+
+        for ((res, p), &offset) in self
             .resonators
             .iter_mut()
-            .skip(self.num_active)
-            .zip(self.active_pitches.iter_mut())
+            .zip(self.active_pitches.iter())
+            .zip(self.stereo_offsets.iter())
         {
-            if !p.is_active() {
-                continue;
-            }
+            Self::tune(res, p.current_value(), offset, &self.params, nyquist);
+        }
+
+        self.lanes_need_update = true;
+    }
+
+    /// Copies the coefficients and gains of the grouped resonators to their
+    /// lanes, if they have changed.
+    fn update_lanes(&mut self) {
+        if !self.lanes_need_update {
+            return;
+        }
+
+        let groups = self.resonators.chunks_exact(NUM_LANES);
+
+        for (i, group) in groups.enumerate() {
+            self.lanes[i].gather(group, i * NUM_LANES, self.num_active);
+        }
+
+        self.lanes_need_update = false;
+    }
+
+    /// The index of the first resonator which isn't processed in a group.
+    fn first_ungrouped(&self) -> usize {
+        self.lanes.len() * NUM_LANES
+    }
+
+    /// Sets the cutoff and resonance of both of `res`'s filters for `note`.
+    fn tune(
+        res: &mut Resonator,
+        note: f64,
+        stereo_offset: f64,
+        params: &ResonatorBankParams,
+        nyquist: f64,
+    ) {
+        let detune =
+            stereo_offset * params.stereo_spread * Self::MAX_STEREO_DETUNE;
+        let resonance = Self::resonance_at(note, params.decay_scaling);
+
+        // the resonance must be set first, as the cutoff depends on it
+        res.l.set_resonance(resonance);
+        res.r.set_resonance(resonance);
+        res.l.set_cutoff(note_to_freq(note - detune).min(nyquist));
+        res.r.set_cutoff(note_to_freq(note + detune).min(nyquist));
+    }
+
+    /// The resonance of a filter at `note`, which is lowered for higher
+    /// notes (so they decay faster) by `decay_scaling`.
+    ///
+    /// At full scaling, the decay time is inversely proportional to the
+    /// note's frequency.
+    fn resonance_at(note: f64, decay_scaling: f64) -> f64 {
+        let ratio = note_to_freq(note) / note_to_freq(Self::NOTE_MIDDLE);
 
-            let note = p.next();
-            let freq = note_to_freq(note);
+        Self::RESONANCE.powf(ratio.powf(decay_scaling))
+    }
 
-            res.l.set_cutoff(freq.min(nyquist));
-            res.r.set_cutoff(freq.min(nyquist));
+    /// `num_pitches` notes of `scale` from `root`, sorted from lowest to
+    /// highest. See [`set_pitches_from_scale()`](Self::set_pitches_from_scale).
+    fn pitches_from_scale(
+        scale: Scale,
+        root: f64,
+        spread: f64,
+        num_pitches: usize,
+    ) -> Vec<f64> {
+        let spread = spread.clamp(0.0, 1.0);
+        let octaves = lerp(1.0, Self::MAX_SCALE_OCTAVES, spread);
+        let top = octaves.mul_add(12.0, root);
+        let intervals = scale.get();
+
+        // the scale's tonic seventh chord, without any notes outside of it
+        let mut chord_gen = ChordGen::new();
+        let chord: Vec<f64> = match scale {
+            Scale::Minor | Scale::MinPentatonic => chord_gen.gen_minor7(0.0),
+            _ => chord_gen.gen_major7(0.0),
         }
+        .iter()
+        .copied()
+        .filter(|interval| intervals.contains(interval))
+        .collect();
+
+        let notes_of = |intervals: &[f64]| {
+            (0..=octaves.ceil() as u32)
+                .flat_map(|octave| {
+                    let lower = f64::from(octave).mul_add(12.0, root);
+                    intervals.iter().map(move |interval| lower + interval)
+                })
+                .filter(|&note| note <= top)
+                .collect::<Vec<f64>>()
+        };
+
+        let others: Vec<f64> = intervals
+            .iter()
+            .copied()
+            .filter(|interval| !chord.contains(interval))
+            .collect();
+
+        let chord_notes = notes_of(&chord);
+        let other_notes = notes_of(&others);
+
+        // if there are more resonators than notes, the chord tones are
+        // doubled
+        let mut pitches: Vec<f64> = chord_notes
+            .iter()
+            .chain(other_notes.iter())
+            .chain(chord_notes.iter().cycle())
+            .copied()
+            .take(num_pitches)
+            .collect();
+
+        pitches.sort_by(f64::total_cmp);
+        pitches
+    }
+
+    /// Stretches the distance of `note` from `root_note` by `stretch`.
+    fn stretched(note: f64, root_note: f64, stretch: f64) -> f64 {
+        let scale = stretch.mul_add(Self::MAX_STRETCH, 1.0);
+
+        (note - root_note).mul_add(scale, root_note)
     }
 
     fn set_active_pitches(&mut self) {
-        for (active, &original) in self
+        for ((active, &original), &morph) in self
             .active_pitches
             .iter_mut()
             .take(self.num_active)
             .zip(self.original_pitches.iter())
+            .zip(self.morph_pitches.iter())
         {
+            let original = lerp(original, morph, self.params.pitch_morph);
+
             // apply frequency spread and then add shift
             let spread_shift = if original < Self::NOTE_MIDDLE {
                 map(
@@ -354,7 +723,7 @@ impl ResonatorBank {
                 )
             } + self.params.freq_shift;
 
-            if self.params.quantize_to_scale {
+            let note = if self.params.quantize_to_scale {
                 // quantize to scale
                 let quantized = self
                     .params
@@ -362,13 +731,17 @@ impl ResonatorBank {
                     .quantize_to_scale(spread_shift, self.params.root_note);
 
                 // apply inharmonic skew
-                active.set_target_value(lerp(
-                    quantized, original, self.params.inharm,
-                ));
+                lerp(quantized, original, self.params.inharm)
             }
             else {
-                active.set_target_value(spread_shift);
-            }
+                spread_shift
+            };
+
+            active.set_target_value(Self::stretched(
+                note,
+                self.params.root_note,
+                self.params.stretch,
+            ));
         }
     }
 }
@@ -376,31 +749,45 @@ impl ResonatorBank {
 impl Effect for ResonatorBank {
     fn process_mono(&mut self, input: f64, ch_idx: usize) -> f64 {
         self.update_resonator_pitches();
+        self.update_lanes();
 
-        let mut output = 0.0;
-        for res in self.resonators.iter_mut().take(self.num_active) {
-            output += res.process_mono(input, ch_idx);
+        let mut sum = f64x4::ZERO;
+        for lanes in &mut self.lanes {
+            sum += lanes.process(input, ch_idx);
         }
 
-        for res in self.resonators.iter_mut().skip(self.num_active) {
-            output += res.process_mono(0.0, ch_idx);
+        let mut output = sum.reduce_add();
+        let first = self.first_ungrouped();
+
+        for (i, res) in self.resonators.iter_mut().enumerate().skip(first) {
+            let input = if i < self.num_active { input } else { 0.0 };
+            output += res.process_mono(input, ch_idx);
         }
 
         output
     }
 
-    fn process_stereo(&mut self, mut left: f64, mut right: f64) -> (f64, f64) {
+    fn process_stereo(&mut self, left: f64, right: f64) -> (f64, f64) {
         self.update_resonator_pitches();
+        self.update_lanes();
 
-        let (mut out_l, mut out_r) = (0.0, 0.0);
-        for res in self.resonators.iter_mut().take(self.num_active) {
-            let (l, r) = res.process_stereo(left, right);
-            out_l += l;
-            out_r += r;
+        let (mut sum_l, mut sum_r) = (f64x4::ZERO, f64x4::ZERO);
+        for lanes in &mut self.lanes {
+            sum_l += lanes.process(left, 0);
+            sum_r += lanes.process(right, 1);
         }
 
-        for res in self.resonators.iter_mut().skip(self.num_active) {
-            let (l, r) = res.process_stereo(0.0, 0.0);
+        let (mut out_l, mut out_r) = (sum_l.reduce_add(), sum_r.reduce_add());
+        let first = self.first_ungrouped();
+
+        for (i, res) in self.resonators.iter_mut().enumerate().skip(first) {
+            let (l, r) = if i < self.num_active {
+                res.process_stereo(left, right)
+            }
+            else {
+                res.process_stereo(0.0, 0.0)
+            };
+
             out_l += l;
             out_r += r;
         }
@@ -416,3 +803,149 @@ impl Effect for ResonatorBank {
         "resonator_bank"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stretch_widens_intervals_around_the_root() {
+        let root = 60.0;
+        let stretch = |note| ResonatorBank::stretched(note, root, 1.0);
+
+        assert!(epsilon_eq(stretch(root), root));
+        assert!(stretch(root + 12.0) > root + 12.0);
+        assert!(stretch(root - 12.0) < root - 12.0);
+        assert!(ResonatorBank::stretched(72.0, root, -1.0) < 72.0);
+    }
+
+    #[test]
+    fn higher_resonators_decay_faster() {
+        let low = ResonatorBank::resonance_at(40.0, 1.0);
+        let high = ResonatorBank::resonance_at(110.0, 1.0);
+
+        assert!(high < low);
+        assert!(low < 1.0);
+        assert!(epsilon_eq(
+            ResonatorBank::resonance_at(110.0, 0.0),
+            ResonatorBank::RESONANCE
+        ));
+    }
+
+    #[test]
+    fn pitch_morph_moves_between_pitch_sets() {
+        let mut bank = ResonatorBank::new(44100.0, 2);
+        bank.set_freq_spread(1.0);
+        bank.original_pitches_mut().copy_from_slice(&[50.0, 90.0]);
+        bank.set_morph_pitches(&[60.0, 70.0]);
+
+        bank.set_pitch_morph(0.5);
+
+        let targets: Vec<f64> =
+            bank.active_pitches.iter().map(Smoother::target_value).collect();
+        assert!(epsilon_eq(targets[0], 55.0));
+        assert!(epsilon_eq(targets[1], 80.0));
+    }
+
+    #[test]
+    fn scale_pitches_start_with_the_tonic_chord() {
+        let pitches =
+            ResonatorBank::pitches_from_scale(Scale::Major, 60.0, 0.0, 4);
+        assert_eq!(pitches, [60.0, 64.0, 67.0, 71.0]);
+
+        let narrow =
+            ResonatorBank::pitches_from_scale(Scale::Minor, 60.0, 0.0, 32);
+        let wide =
+            ResonatorBank::pitches_from_scale(Scale::Minor, 60.0, 1.0, 32);
+        assert!(narrow.iter().all(|&p| (60.0..=72.0).contains(&p)));
+        assert!(wide.last() > narrow.last());
+    }
+
+    /// A bank of `num_resonators` with a spread of pitches and panning,
+    /// and scalar copies of its resonators.
+    fn tuned_bank(num_resonators: usize) -> (ResonatorBank, Vec<Resonator>) {
+        let mut bank = ResonatorBank::new(44100.0, num_resonators);
+        let params = ResonatorBankParams { stereo_spread: 1.0, ..bank.params };
+        let nyquist = 22050.0;
+
+        // let the pitches settle, so the bank doesn't retune the resonators
+        while bank.active_pitches.iter().any(Smoother::is_active) {
+            bank.process_stereo(0.0, 0.0);
+        }
+
+        for (i, res) in bank.inner_mut().iter_mut().enumerate() {
+            let note = 4.0f64.mul_add(i as f64, 50.0);
+            let offset = if i % 2 == 0 { 1.0 } else { -1.0 };
+
+            ResonatorBank::tune(res, note, offset, &params, nyquist);
+            res.set_pan(i as f64 / num_resonators as f64 - 0.5);
+            res.set_gain_db(-20.0);
+        }
+
+        let reference = bank.inner().to_vec();
+        (bank, reference)
+    }
+
+    /// Whether `bank` matches `reference` processed one resonator at a
+    /// time, while only the first `num_active` resonators receive input.
+    fn matches_scalar(
+        bank: &mut ResonatorBank,
+        reference: &mut [Resonator],
+        num_active: usize,
+    ) -> bool {
+        (0..512).all(|i| {
+            let input = if i % 100 == 0 { 1.0 } else { 0.0 };
+            let (out_l, out_r) = bank.process_stereo(input, -input);
+
+            let (ref_l, ref_r) = reference.iter_mut().enumerate().fold(
+                (0.0, 0.0),
+                |(l, r), (j, res)| {
+                    let input = if j < num_active { input } else { 0.0 };
+                    let (res_l, res_r) = res.process_stereo(input, -input);
+                    (l + res_l, r + res_r)
+                },
+            );
+
+            (out_l - ref_l).abs() < 1e-9 && (out_r - ref_r).abs() < 1e-9
+        })
+    }
+
+    #[test]
+    fn grouped_resonators_match_scalar_processing() {
+        // one group of four, and two processed one at a time
+        let (mut bank, mut reference) = tuned_bank(6);
+        assert_eq!(bank.lanes.len(), 1);
+        assert!(matches_scalar(&mut bank, &mut reference, 6));
+
+        // fewer than a group
+        let (mut bank, mut reference) = tuned_bank(3);
+        assert!(bank.lanes.is_empty());
+        assert!(matches_scalar(&mut bank, &mut reference, 3));
+    }
+
+    #[test]
+    fn inactive_grouped_resonators_ring_out() {
+        let (mut bank, mut reference) = tuned_bank(8);
+        assert!(matches_scalar(&mut bank, &mut reference, 8));
+
+        // the inactive resonators in each group stop receiving input, but
+        // keep ringing
+        bank.set_num_resonators(3);
+        assert!(matches_scalar(&mut bank, &mut reference, 3));
+
+        let ringing = bank.lanes[1].z1[0].to_array();
+        assert!(ringing.iter().all(|z| z.abs() > 0.0));
+    }
+
+    #[test]
+    fn scale_retuning_moves_each_resonator_the_least() {
+        let mut bank = ResonatorBank::new(44100.0, 2);
+        bank.active_pitches[0] = Smoother::new(200.0, 80.0, 44100.0);
+        bank.active_pitches[1] = Smoother::new(200.0, 40.0, 44100.0);
+
+        bank.set_pitches_from_scale(Scale::Major, 60.0, 0.0);
+
+        assert!(epsilon_eq(bank.active_pitches[0].target_value(), 64.0));
+        assert!(epsilon_eq(bank.active_pitches[1].target_value(), 60.0));
+    }
+}
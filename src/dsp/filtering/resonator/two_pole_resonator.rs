@@ -128,6 +128,12 @@ impl TwoPoleResonator {
     pub fn sample_rate(&self) -> f64 {
         self.sample_rate
     }
+
+    /// Returns the coefficients of the filter, as `[b0, a1, a2]`, so that it
+    /// can be processed alongside other resonators.
+    pub const fn coefs(&self) -> [f64; 3] {
+        [self.coefs.b0, self.coefs.a1, self.coefs.a2]
+    }
 }
 
 impl Filter for TwoPoleResonator {
@@ -3,63 +3,166 @@
 use super::*;
 use crate::dsp::{BiquadFilter, Effect};
 use crate::prelude::*;
+use std::fmt::{Display, Formatter};
 
-/// A wrapper around a highpass [`FirstOrderFilter`](crate::dsp::FirstOrderFilter)
-/// tailored for filtering out 0.0 Hz (DC) signals.
-#[derive(Clone, Default, Debug)]
+/// The default cutoff of a [`DCFilter`].
+pub const DEFAULT_DC_FILTER_FREQ_HZ: f64 = 20.0;
+/// The lowest and highest cutoffs of a [`DCFilter`].
+pub const MIN_DC_FILTER_FREQ_HZ: f64 = 15.0;
+pub const MAX_DC_FILTER_FREQ_HZ: f64 = 30.0;
+
+/// The slope of a [`DCFilter`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DCFilterSlope {
+    /// 12 dB per octave, from a single Butterworth stage.
+    #[default]
+    Db12,
+    /// 24 dB per octave, from two stages which form a 4th order Butterworth
+    /// response.
+    Db24,
+}
+
+impl DCFilterSlope {
+    /// Returns the slope which falls by `db` per octave, if it is `12` or
+    /// `24`.
+    pub const fn from_db_per_octave(db: u32) -> Option<Self> {
+        match db {
+            12 => Some(Self::Db12),
+            24 => Some(Self::Db24),
+            _ => None,
+        }
+    }
+
+    /// The Q of each stage.
+    const fn stage_qs(self) -> &'static [f64] {
+        match self {
+            Self::Db12 => &[BUTTERWORTH_Q],
+            Self::Db24 => &[0.541_196_100_146_197, 1.306_562_964_876_376_5],
+        }
+    }
+}
+
+impl Display for DCFilterSlope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Db12 => write!(f, "12 dB/oct"),
+            Self::Db24 => write!(f, "24 dB/oct"),
+        }
+    }
+}
+
+/// A stereo Butterworth highpass tailored for filtering out 0.0 Hz (DC) and
+/// subsonic signals, with a selectable slope.
+#[derive(Clone, Debug)]
 pub struct DCFilter {
-    filters: Vec<BiquadFilter>,
+    /// The stages of each channel, in series.
+    filters: [Vec<BiquadFilter>; 2],
+    freq: f64,
+    slope: DCFilterSlope,
+    sample_rate: f64,
 }
 
 impl DCFilter {
+    /// A filter at [`DEFAULT_DC_FILTER_FREQ_HZ`].
+    ///
     /// # Panics
     ///
-    /// Panics if `num_filters == 0` or if `sample_rate` is negative.
-    pub fn new(sample_rate: f64, num_filters: usize) -> Self {
-        assert_ne!(num_filters, 0);
+    /// Panics if `sample_rate` is negative.
+    pub fn new(sample_rate: f64, slope: DCFilterSlope) -> Self {
         assert!(sample_rate.is_sign_positive());
 
-        Self {
-            filters: vec![Self::create_filter(sample_rate); num_filters],
+        let mut filter = Self {
+            filters: [Vec::new(), Vec::new()],
+            freq: DEFAULT_DC_FILTER_FREQ_HZ,
+            slope,
+            sample_rate,
+        };
+
+        filter.reset();
+        filter
+    }
+
+    /// Sets the cutoff of the filter, which is clamped between
+    /// [`MIN_DC_FILTER_FREQ_HZ`] and [`MAX_DC_FILTER_FREQ_HZ`].
+    pub fn set_freq(&mut self, freq: f64) {
+        self.freq = freq.clamp(MIN_DC_FILTER_FREQ_HZ, MAX_DC_FILTER_FREQ_HZ);
+
+        for filter in self.filters.iter_mut().flatten() {
+            filter.set_freq(self.freq);
         }
     }
 
+    pub const fn freq(&self) -> f64 {
+        self.freq
+    }
+
+    pub fn set_slope(&mut self, slope: DCFilterSlope) {
+        if slope != self.slope {
+            self.slope = slope;
+            self.reset();
+        }
+    }
+
+    pub const fn slope(&self) -> DCFilterSlope {
+        self.slope
+    }
+
     /// # Panics
     ///
     /// Panics if `sample rate` is negative.
     pub fn set_sample_rate(&mut self, sample_rate: f64) {
         assert!(sample_rate.is_sign_positive());
 
+        self.sample_rate = sample_rate;
         self.filters
             .iter_mut()
+            .flatten()
             .for_each(|fil| fil.reset_sample_rate(sample_rate));
     }
 
-    /// # Panics
-    ///
-    /// Panics if `num_filters == 0`.
-    pub fn set_num_filters(&mut self, num_filters: usize) {
-        assert_ne!(num_filters, 0);
-
-        // safety: there will always be at least one filter in the vector
-        let sr = self.filters[0].get_sample_rate();
+    /// Filters `block` of channel `channel_idx` in place, one stage at a
+    /// time.
+    pub fn process_block(&mut self, block: &mut [f64], channel_idx: usize) {
+        for filter in &mut self.filters[channel_idx] {
+            filter.process_block(block);
+        }
+    }
 
-        self.filters.resize(num_filters, Self::create_filter(sr));
+    /// Clears the filters' state.
+    pub fn reset(&mut self) {
+        for channel in &mut self.filters {
+            *channel = self
+                .slope
+                .stage_qs()
+                .iter()
+                .map(|&q| Self::create_filter(self.sample_rate, self.freq, q))
+                .collect();
+        }
     }
 
-    fn create_filter(sample_rate: f64) -> BiquadFilter {
+    fn create_filter(sample_rate: f64, freq: f64, q: f64) -> BiquadFilter {
         let mut filter = BiquadFilter::new(sample_rate);
-        filter.set_q(BUTTERWORTH_Q);
+        filter.set_q(q);
         filter.set_type(FilterType::Highpass);
-        filter.set_freq(20.0);
+        filter.set_freq(freq);
 
         filter
     }
 }
 
+impl Default for DCFilter {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE }, DCFilterSlope::default())
+    }
+}
+
 impl Effect for DCFilter {
-    fn process_mono(&mut self, mut input: f64, _: usize) -> f64 {
-        for filter in &mut self.filters {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        (self.process_mono(in_l, 0), self.process_mono(in_r, 1))
+    }
+
+    fn process_mono(&mut self, mut input: f64, channel_idx: usize) -> f64 {
+        for filter in &mut self.filters[channel_idx] {
             input = filter.process(input);
         }
 
@@ -67,13 +170,80 @@ impl Effect for DCFilter {
     }
 
     fn get_sample_rate(&self) -> f64 {
-        self.filters
-            .first()
-            .expect("expected to have a filter present")
-            .get_sample_rate()
+        self.sample_rate
     }
 
     fn get_identifier(&self) -> &str {
         "dc_filter"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{SQRT_2, TAU};
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// The gain of `filter` for a sine at `freq`, in decibels.
+    fn gain_db(filter: &mut DCFilter, freq: f64) -> f64 {
+        let num_samples = SAMPLE_RATE as usize * 2;
+
+        let sum: f64 = (0..num_samples)
+            .map(|i| {
+                let x = (TAU * freq * i as f64 / SAMPLE_RATE).sin();
+                filter.process_stereo(x, x).0
+            })
+            // skips the filters' transients
+            .skip(num_samples / 2)
+            .map(|y| y * y)
+            .sum();
+
+        level_to_db((sum / (num_samples / 2) as f64).sqrt() * SQRT_2)
+    }
+
+    #[test]
+    fn removes_dc() {
+        let mut filter = DCFilter::new(SAMPLE_RATE, DCFilterSlope::Db12);
+        let mut out = (0.0, 0.0);
+
+        for _ in 0..SAMPLE_RATE as usize {
+            out = filter.process_stereo(0.5, -0.5);
+        }
+
+        assert!(out.0.abs() < 1e-6 && out.1.abs() < 1e-6);
+    }
+
+    #[test]
+    fn slopes_fall_per_octave() {
+        let mut gentle = DCFilter::new(SAMPLE_RATE, DCFilterSlope::Db12);
+        let mut steep = DCFilter::new(SAMPLE_RATE, DCFilterSlope::Db24);
+
+        for filter in [&mut gentle, &mut steep] {
+            let cutoff = gain_db(filter, DEFAULT_DC_FILTER_FREQ_HZ);
+            assert!((cutoff + 3.0).abs() < 0.1, "cutoff was {cutoff} dB");
+
+            let pass = gain_db(filter, 1000.0);
+            assert!(pass.abs() < 0.01, "passband was {pass} dB");
+        }
+
+        // two octaves below the cutoff
+        let freq = DEFAULT_DC_FILTER_FREQ_HZ / 4.0;
+        let gentle_db = gain_db(&mut gentle, freq);
+        let steep_db = gain_db(&mut steep, freq);
+
+        assert!((gentle_db + 24.0).abs() < 1.0, "12 dB was {gentle_db} dB");
+        assert!((steep_db + 48.0).abs() < 1.0, "24 dB was {steep_db} dB");
+    }
+
+    #[test]
+    fn cutoff_is_clamped() {
+        let mut filter = DCFilter::new(SAMPLE_RATE, DCFilterSlope::Db24);
+        filter.set_freq(100.0);
+        assert_eq!(filter.freq(), MAX_DC_FILTER_FREQ_HZ);
+
+        filter.set_slope(DCFilterSlope::Db12);
+        assert_eq!(filter.slope(), DCFilterSlope::Db12);
+        assert_eq!(DCFilterSlope::from_db_per_octave(18), None);
+    }
+}
@@ -1,6 +1,6 @@
 //! One-pole lowpass filter.
 
-use crate::dsp::Effect;
+use crate::dsp::{Effect, Filter};
 use crate::prelude::*;
 
 /// Source: https://www.musicdsp.org/en/latest/Effects/169-compressor.html
@@ -81,12 +81,30 @@ impl OnePoleLowpass {
     }
 }
 
-impl Effect for OnePoleLowpass {
-    fn process_mono(&mut self, input: f64, _: usize) -> f64 {
+impl Filter for OnePoleLowpass {
+    fn process(&mut self, input: f64) -> f64 {
         self.old = self.a0 * input - self.b1 * self.old;
         self.old
     }
 
+    fn process_block(&mut self, buffer: &mut [f64]) {
+        let (a0, b1) = (self.a0, self.b1);
+        let mut old = self.old;
+
+        for sample in buffer {
+            old = a0 * *sample - b1 * old;
+            *sample = old;
+        }
+
+        self.old = old;
+    }
+}
+
+impl Effect for OnePoleLowpass {
+    fn process_mono(&mut self, input: f64, _: usize) -> f64 {
+        self.process(input)
+    }
+
     fn get_sample_rate(&self) -> f64 {
         self.sample_rate
     }
@@ -1,15 +1,47 @@
 use super::*;
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug)]
 struct SVFCoefs {
     g: f64,
     h: f64,
     r2: f64,
 }
 
+impl SVFCoefs {
+    /// The step which moves `self` to `target` over `num_steps`.
+    fn step_to(&self, target: &Self, num_steps: u32) -> Self {
+        let n = f64::from(num_steps);
+
+        Self {
+            g: (target.g - self.g) / n,
+            h: (target.h - self.h) / n,
+            r2: (target.r2 - self.r2) / n,
+        }
+    }
+
+    fn add(&mut self, step: &Self) {
+        self.g += step.g;
+        self.h += step.h;
+        self.r2 += step.r2;
+    }
+}
+
+/// A state variable filter with a state for each channel.
+///
+/// By default, new coefficients take effect immediately. See
+/// [`set_coef_smoothing()`](Self::set_coef_smoothing) to ramp them instead.
 #[derive(Clone, Debug)]
 pub struct StateVariableFilter {
     coefs: SVFCoefs,
+    /// The coefficients which are ramped towards.
+    target_coefs: SVFCoefs,
+    /// The change in coefficients per sample.
+    coef_step: SVFCoefs,
+    /// The number of samples until the target coefficients are reached.
+    ramp_samples_remaining: u32,
+    /// The number of samples over which each change to the coefficients is
+    /// ramped, or `0` if they change immediately.
+    coef_smoothing: u32,
 
     z1: Vec<f64>,
     z2: Vec<f64>,
@@ -26,6 +58,10 @@ impl StateVariableFilter {
     pub fn new(num_channels: usize, sample_rate: f64) -> Self {
         Self {
             coefs: SVFCoefs::default(),
+            target_coefs: SVFCoefs::default(),
+            coef_step: SVFCoefs::default(),
+            ramp_samples_remaining: 0,
+            coef_smoothing: 0,
             z1: vec![0.0; num_channels],
             z2: vec![0.0; num_channels],
             cutoff_freq: 440.0,
@@ -35,6 +71,35 @@ impl StateVariableFilter {
         }
     }
 
+    /// Returns the filter with each change to its coefficients ramped over
+    /// `num_samples`. See [`set_coef_smoothing()`](Self::set_coef_smoothing).
+    #[must_use]
+    pub fn with_coef_smoothing(mut self, num_samples: u32) -> Self {
+        self.set_coef_smoothing(num_samples);
+        self
+    }
+
+    /// Ramps each change to the coefficients over `num_samples`, so that
+    /// abrupt changes to the cutoff or Q don't cause zipper noise. `0` (the
+    /// default) changes them immediately.
+    ///
+    /// A change during a ramp starts a new ramp from the current
+    /// coefficients. Each channel ramps together.
+    pub fn set_coef_smoothing(&mut self, num_samples: u32) {
+        self.coef_smoothing = num_samples;
+
+        if num_samples == 0 && self.ramp_samples_remaining > 0 {
+            self.coefs = self.target_coefs;
+            self.ramp_samples_remaining = 0;
+        }
+    }
+
+    /// The number of samples over which each change to the coefficients is
+    /// ramped.
+    pub const fn coef_smoothing(&self) -> u32 {
+        self.coef_smoothing
+    }
+
     pub fn set_type(&mut self, filter_type: FilterType) {
         if !matches!(
             filter_type,
@@ -54,7 +119,7 @@ impl StateVariableFilter {
 
     pub fn set_q(&mut self, q: f64) {
         assert!(q.is_sign_positive());
-        self.cutoff_freq = q;
+        self.q = q;
         self.update();
     }
 
@@ -68,18 +133,108 @@ impl StateVariableFilter {
         self.z2.iter_mut().for_each(|x| *x = value);
     }
 
+    /// Computes the coefficients, and starts ramping towards them if they
+    /// are smoothed.
     fn update(&mut self) {
-        let SVFCoefs { g, h, r2 } = &mut self.coefs;
         let sr = unsafe { SAMPLE_RATE };
 
-        *g = (PI * self.cutoff_freq / sr).tan();
-        *r2 = self.q.recip();
-        *h = (1.0 + *r2 * *g + *g * *g).recip();
+        let g = (PI * self.cutoff_freq / sr).tan();
+        let r2 = self.q.recip();
+        let h = (1.0 + r2 * g + g * g).recip();
+        let coefs = SVFCoefs { g, h, r2 };
+
+        if self.coef_smoothing == 0 {
+            self.coefs = coefs;
+            return;
+        }
+
+        self.target_coefs = coefs;
+        self.coef_step = self.coefs.step_to(&coefs, self.coef_smoothing);
+        self.ramp_samples_remaining = self.coef_smoothing;
+    }
+
+    /// Moves the coefficients one sample along their ramp, if they are
+    /// ramping.
+    fn ramp_coefs(&mut self) {
+        if self.ramp_samples_remaining == 0 {
+            return;
+        }
+
+        self.ramp_samples_remaining -= 1;
+
+        if self.ramp_samples_remaining == 0 {
+            // the ramp is finished exactly, despite rounding
+            self.coefs = self.target_coefs;
+        }
+        else {
+            self.coefs.add(&self.coef_step);
+        }
+    }
+
+    /// Processes each sample of `buffer` in place on the first channel,
+    /// with the current coefficients.
+    fn process_first_channel(&mut self, buffer: &mut [f64]) {
+        let SVFCoefs { g, h, r2 } = self.coefs;
+        let filter_type = self.filter_type;
+
+        let mut ls_1 = self.z1[0];
+        let mut ls_2 = self.z2[0];
+
+        for sample in buffer {
+            let high = h * (*sample - ls_1 * (g + r2) - ls_2);
+
+            let band = high * g + ls_1;
+            ls_1 = high * g + band;
+
+            let low = band * g + ls_2;
+            ls_2 = band * g + low;
+
+            *sample = match filter_type {
+                FilterType::Lowpass => low,
+                FilterType::Highpass => high,
+                FilterType::Bandpass => band,
+                _ => *sample,
+            };
+        }
+
+        self.z1[0] = ls_1;
+        self.z2[0] = ls_2;
+    }
+}
+
+/// Filters the first channel, for use as a mono filter.
+impl Filter for StateVariableFilter {
+    fn process(&mut self, sample: f64) -> f64 {
+        let mut buffer = [sample];
+        self.process_block(&mut buffer);
+
+        buffer[0]
+    }
+
+    /// Processes each sample of `buffer` in place, on the first channel.
+    ///
+    /// The coefficients and filter type are read once per block, and the
+    /// filter's state is kept in locals for the duration of the loop.
+    ///
+    /// While the coefficients are ramping, those samples are processed one
+    /// at a time.
+    fn process_block(&mut self, buffer: &mut [f64]) {
+        let num_ramped =
+            (self.ramp_samples_remaining as usize).min(buffer.len());
+        let (ramped, buffer) = buffer.split_at_mut(num_ramped);
+
+        for sample in ramped.chunks_mut(1) {
+            self.ramp_coefs();
+            self.process_first_channel(sample);
+        }
+
+        self.process_first_channel(buffer);
     }
 }
 
 impl Effect for StateVariableFilter {
     fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        self.ramp_coefs();
         let SVFCoefs { g, h, r2 } = self.coefs;
         let input = [in_l, in_r];
 
@@ -0,0 +1,415 @@
+//! A spectral tilt, which brightens or darkens the whole signal about a
+//! pivot frequency.
+
+use super::*;
+use crate::dsp::util::Effect;
+use crate::prelude::*;
+use std::f64::consts::TAU;
+
+/// The widest tilt, in decibels between the low and high shelves.
+pub const MAX_TILT_DB: f64 = 12.0;
+/// The default frequency which the tilt pivots about.
+pub const DEFAULT_TILT_PIVOT_HZ: f64 = 800.0;
+
+/// The Q of both shelves, which is low so that the tilt is gentle.
+const SHELF_Q: f64 = 0.5;
+/// How long changes to the tilt are smoothed over, in milliseconds.
+const TILT_SMOOTHING_MS: f64 = 50.0;
+/// The number of samples over which the coefficients are interpolated
+/// between each time they are computed.
+const COEF_UPDATE_INTERVAL: u32 = 16;
+
+/// The normalized coefficients of a shelf.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ShelfCoefs {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl ShelfCoefs {
+    const IDENTITY: Self =
+        Self { b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0 };
+    const ZERO: Self = Self { b0: 0.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0 };
+
+    /// The coefficients of a low shelf (or a high shelf if `high` is true),
+    /// from the Audio EQ Cookbook.
+    fn shelf(high: bool, freq: f64, gain_db: f64, sample_rate: f64) -> Self {
+        if gain_db == 0.0 {
+            return Self::IDENTITY;
+        }
+
+        let phi = TAU * freq / sample_rate;
+        let cos_phi = phi.cos();
+        let alpha = phi.sin() / (2.0 * SHELF_Q);
+
+        let amp = 10.0f64.powf(gain_db / 40.0);
+        let root_amp_2 = 2.0 * amp.sqrt() * alpha;
+        // the high shelf mirrors the low shelf's cosine terms
+        let cos_phi = if high { -cos_phi } else { cos_phi };
+        let sign = if high { -1.0 } else { 1.0 };
+
+        let a0 = (amp + 1.0) + (amp - 1.0) * cos_phi + root_amp_2;
+
+        Self {
+            b0: amp * ((amp + 1.0) - (amp - 1.0) * cos_phi + root_amp_2) / a0,
+            b1: sign * 2.0 * amp * ((amp - 1.0) - (amp + 1.0) * cos_phi)
+                / a0,
+            b2: amp * ((amp + 1.0) - (amp - 1.0) * cos_phi - root_amp_2) / a0,
+            a1: sign * -2.0 * ((amp - 1.0) + (amp + 1.0) * cos_phi) / a0,
+            a2: ((amp + 1.0) + (amp - 1.0) * cos_phi - root_amp_2) / a0,
+        }
+    }
+
+    /// The step which moves `self` to `target` over `num_steps`.
+    fn step_to(&self, target: &Self, num_steps: u32) -> Self {
+        let n = f64::from(num_steps);
+
+        Self {
+            b0: (target.b0 - self.b0) / n,
+            b1: (target.b1 - self.b1) / n,
+            b2: (target.b2 - self.b2) / n,
+            a1: (target.a1 - self.a1) / n,
+            a2: (target.a2 - self.a2) / n,
+        }
+    }
+
+    fn add(&mut self, step: &Self) {
+        self.b0 += step.b0;
+        self.b1 += step.b1;
+        self.b2 += step.b2;
+        self.a1 += step.a1;
+        self.a2 += step.a2;
+    }
+}
+
+/// The direct form 1 state of one shelf of one channel.
+#[derive(Debug, Clone, Copy, Default)]
+struct ShelfState {
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl ShelfState {
+    fn process(&mut self, c: &ShelfCoefs, x: f64) -> f64 {
+        let y = c.b0 * x + c.b1 * self.x1 + c.b2 * self.x2
+            - c.a1 * self.y1
+            - c.a2 * self.y2;
+
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+
+        y
+    }
+}
+
+/// A stereo spectral tilt: a low shelf and a high shelf at the same pivot
+/// frequency, whose gains move in opposite directions.
+///
+/// Changes to the tilt are smoothed, and the coefficients are interpolated
+/// between updates so that the tilt may be moved continuously.
+#[derive(Debug, Clone)]
+pub struct SpectralTilt {
+    /// The tilt in decibels, where positive values are brighter.
+    tilt_db: Smoother<f64>,
+    pivot_hz: f64,
+
+    /// The current coefficients of the low and high shelves.
+    coefs: [ShelfCoefs; 2],
+    /// The coefficients which are reached at the next update.
+    target_coefs: [ShelfCoefs; 2],
+    /// The change in coefficients per sample.
+    coef_steps: [ShelfCoefs; 2],
+    samples_until_update: u32,
+    needs_update: bool,
+
+    /// The state of each shelf of each channel.
+    states: [[ShelfState; 2]; 2],
+
+    sample_rate: f64,
+}
+
+impl SpectralTilt {
+    /// A flat tilt about [`DEFAULT_TILT_PIVOT_HZ`].
+    pub fn new(sample_rate: f64) -> Self {
+        let mut tilt_db = Smoother::new(TILT_SMOOTHING_MS, 0.0, sample_rate);
+        tilt_db.set_start_value(0.0);
+
+        Self {
+            tilt_db,
+            pivot_hz: DEFAULT_TILT_PIVOT_HZ,
+
+            coefs: [ShelfCoefs::IDENTITY; 2],
+            target_coefs: [ShelfCoefs::IDENTITY; 2],
+            coef_steps: [ShelfCoefs::ZERO; 2],
+            samples_until_update: 0,
+            needs_update: false,
+
+            states: [[ShelfState::default(); 2]; 2],
+
+            sample_rate,
+        }
+    }
+
+    /// Sets the tilt in decibels, which is clamped to
+    /// ±[`MAX_TILT_DB`]. Positive values are brighter.
+    pub fn set_tilt_db(&mut self, tilt_db: f64) {
+        let tilt_db = tilt_db.clamp(-MAX_TILT_DB, MAX_TILT_DB);
+
+        if tilt_db != self.tilt_db.target_value() {
+            // the filters may be skipped while flat, so their state is stale
+            if self.is_flat() {
+                self.reset();
+            }
+
+            self.tilt_db.set_target_value(tilt_db);
+            self.needs_update = true;
+        }
+    }
+
+    /// The tilt which is being moved towards, in decibels.
+    pub fn tilt_db(&self) -> f64 {
+        self.tilt_db.target_value()
+    }
+
+    /// Sets the frequency which the tilt pivots about.
+    pub fn set_pivot(&mut self, freq: f64) {
+        self.pivot_hz = freq.clamp(20.0, self.sample_rate * 0.45);
+        self.needs_update = true;
+    }
+
+    pub const fn pivot(&self) -> f64 {
+        self.pivot_hz
+    }
+
+    /// Whether the tilt leaves the signal unchanged, so it may be skipped.
+    pub fn is_flat(&self) -> bool {
+        !self.needs_update
+            && self.coefs == [ShelfCoefs::IDENTITY; 2]
+            && self.target_coefs == [ShelfCoefs::IDENTITY; 2]
+    }
+
+    /// Clears the filters' state.
+    pub fn reset(&mut self) {
+        self.states = [[ShelfState::default(); 2]; 2];
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.tilt_db.reset_sample_rate(sample_rate);
+        self.set_pivot(self.pivot_hz);
+    }
+
+    /// Tilts a block of each channel in place. The coefficients are only
+    /// stepped while they are moving between updates.
+    pub fn process_block(&mut self, left: &mut [f64], right: &mut [f64]) {
+        debug_assert_eq!(left.len(), right.len());
+        let mut start = 0;
+
+        while start < left.len() {
+            if self.samples_until_update == 0 {
+                self.update_coefs();
+            }
+
+            let len =
+                (self.samples_until_update as usize).min(left.len() - start);
+            let end = start + len;
+            let is_moving = self.coef_steps != [ShelfCoefs::ZERO; 2];
+            let mut end_coefs = self.coefs;
+
+            let blocks = [&mut left[start..end], &mut right[start..end]];
+
+            for (block, [low, high]) in blocks.into_iter().zip(&mut self.states)
+            {
+                let mut coefs = self.coefs;
+
+                for sample in block {
+                    if is_moving {
+                        for (c, step) in coefs.iter_mut().zip(&self.coef_steps)
+                        {
+                            c.add(step);
+                        }
+                    }
+
+                    let [low_coefs, high_coefs] = &coefs;
+                    *sample = high
+                        .process(high_coefs, low.process(low_coefs, *sample));
+                }
+
+                end_coefs = coefs;
+            }
+
+            self.coefs = end_coefs;
+            self.samples_until_update -= len as u32;
+            start = end;
+        }
+    }
+
+    /// Computes the coefficients at the end of the next interval, and the
+    /// steps which interpolate towards them.
+    fn update_coefs(&mut self) {
+        // the last interval is finished exactly, despite rounding
+        self.coefs = self.target_coefs;
+        self.samples_until_update = COEF_UPDATE_INTERVAL;
+
+        if !self.needs_update {
+            self.coef_steps = [ShelfCoefs::ZERO; 2];
+            return;
+        }
+
+        let tilt_db = self.tilt_db.skip(COEF_UPDATE_INTERVAL);
+        let (freq, sr) = (self.pivot_hz, self.sample_rate);
+
+        self.target_coefs = [
+            ShelfCoefs::shelf(false, freq, -0.5 * tilt_db, sr),
+            ShelfCoefs::shelf(true, freq, 0.5 * tilt_db, sr),
+        ];
+
+        for ((step, current), target) in self
+            .coef_steps
+            .iter_mut()
+            .zip(&self.coefs)
+            .zip(&self.target_coefs)
+        {
+            *step = current.step_to(target, COEF_UPDATE_INTERVAL);
+        }
+
+        self.needs_update = self.tilt_db.is_active();
+    }
+}
+
+impl Default for SpectralTilt {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE })
+    }
+}
+
+impl Effect for SpectralTilt {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        if self.samples_until_update == 0 {
+            self.update_coefs();
+        }
+
+        self.samples_until_update -= 1;
+
+        for (coefs, step) in self.coefs.iter_mut().zip(&self.coef_steps) {
+            coefs.add(step);
+        }
+
+        let [low, high] = &self.coefs;
+        let [[left_low, left_high], [right_low, right_high]] =
+            &mut self.states;
+
+        (
+            left_high.process(high, left_low.process(low, in_l)),
+            right_high.process(high, right_low.process(low, in_r)),
+        )
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "spectral tilt"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// The RMS level of a sine at `freq` after it passes through `tilt`.
+    fn sine_rms(tilt: &mut SpectralTilt, freq: f64) -> f64 {
+        let num_samples = 9600;
+
+        let sum: f64 = (0..num_samples)
+            .map(|i| {
+                let x = (TAU * freq * i as f64 / SAMPLE_RATE).sin();
+                tilt.process_stereo(x, x).0
+            })
+            // skips the smoothing and the filters' transients
+            .skip(num_samples / 2)
+            .map(|y| y * y)
+            .sum();
+
+        (sum / (num_samples / 2) as f64).sqrt()
+    }
+
+    fn gain_db(tilt: &mut SpectralTilt, freq: f64) -> f64 {
+        level_to_db(sine_rms(tilt, freq) * std::f64::consts::SQRT_2)
+    }
+
+    #[test]
+    fn flat_tilt_is_unchanged() {
+        let mut tilt = SpectralTilt::new(SAMPLE_RATE);
+        assert!(tilt.is_flat());
+        assert_eq!(tilt.process_stereo(0.3, -0.7), (0.3, -0.7));
+    }
+
+    #[test]
+    fn tilt_moves_the_shelves_reciprocally() {
+        let mut tilt = SpectralTilt::new(SAMPLE_RATE);
+        tilt.set_tilt_db(8.0);
+        assert!(!tilt.is_flat());
+
+        let low = gain_db(&mut tilt, 40.0);
+        let high = gain_db(&mut tilt, 16000.0);
+        let pivot = gain_db(&mut tilt, DEFAULT_TILT_PIVOT_HZ);
+
+        assert!((low + 4.0).abs() < 0.5, "low was {low} dB");
+        assert!((high - 4.0).abs() < 0.5, "high was {high} dB");
+        assert!(pivot.abs() < 0.5, "pivot was {pivot} dB");
+
+        tilt.set_tilt_db(-8.0);
+        let low = gain_db(&mut tilt, 40.0);
+        assert!((low - 4.0).abs() < 0.5, "low was {low} dB");
+    }
+
+    #[test]
+    fn blocks_match_samples() {
+        let mut tilt = SpectralTilt::new(SAMPLE_RATE);
+        tilt.set_tilt_db(6.0);
+        let mut per_sample = tilt.clone();
+
+        let signal: Vec<f64> =
+            (0..4800).map(|i| (i as f64 * 0.37).sin() * 0.8).collect();
+        let mut left = signal.clone();
+        let mut right: Vec<f64> = signal.iter().map(|x| -x).collect();
+
+        // blocks which don't line up with the coefficient updates, while
+        // the tilt is smoothed and once it is steady
+        for (l, r) in left.chunks_mut(37).zip(right.chunks_mut(37)) {
+            tilt.process_block(l, r);
+        }
+
+        for (i, &x) in signal.iter().enumerate() {
+            let (l, r) = per_sample.process_stereo(x, -x);
+            assert_eq!((left[i], right[i]), (l, r));
+        }
+    }
+
+    #[test]
+    fn tilt_changes_are_smooth() {
+        let mut tilt = SpectralTilt::new(SAMPLE_RATE);
+        tilt.set_tilt_db(MAX_TILT_DB);
+
+        // a steady signal is only changed gradually by the low shelf
+        let out: Vec<f64> =
+            (0..4800).map(|_| tilt.process_stereo(1.0, 1.0).0).collect();
+
+        assert!(out.windows(2).all(|w| (w[1] - w[0]).abs() < 0.01));
+        assert!((out[4799] - db_to_level(-0.5 * MAX_TILT_DB)).abs() < 0.01);
+
+        tilt.set_tilt_db(100.0);
+        assert_eq!(tilt.tilt_db(), MAX_TILT_DB);
+    }
+}
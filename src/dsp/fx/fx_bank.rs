@@ -33,23 +33,11 @@ impl FXBank {
     }
 
     pub fn get_ids(&self) -> [Option<u32>; MAX_NUM_FX_PER_BANK] {
-        let mut output = [const { None }; MAX_NUM_FX_PER_BANK];
-
-        for i in 0..MAX_NUM_FX_PER_BANK {
-            output[i] = self.get_id_for(i);
-        }
-
-        output
+        std::array::from_fn(|i| self.get_id_for(i))
     }
 
     pub fn get_identifiers(&self) -> [Option<&str>; MAX_NUM_FX_PER_BANK] {
-        let mut output = [const { None }; MAX_NUM_FX_PER_BANK];
-
-        for i in 0..MAX_NUM_FX_PER_BANK {
-            output[i] = self.get_identifer_for(i);
-        }
-
-        output
+        std::array::from_fn(|i| self.get_identifer_for(i))
     }
 
     /// # Panics
@@ -13,27 +13,51 @@ pub mod spectral;
 pub mod synthesis;
 pub mod util;
 
-pub use delay::{Delay, RingBuffer, StereoDelay};
-pub use distortion::Waveshaper;
+pub use delay::{Delay, DelayTap, DelayTime, RingBuffer, StereoDelay};
+pub use distortion::{Bitcrusher, Decimator, Exciter, Waveshaper};
 pub use dynamics::adsr::{AdsrEnvelope, AdsrParameters};
-pub use dynamics::Compressor;
+pub use dynamics::{
+    Compressor, Expander, ExpanderDirection, Limiter, LimiterParameters,
+    NoiseGate, TransientShaper, TruePeakDetector,
+};
 pub use filtering::{
     biquad::{BiquadFilter, BiquadParams},
-    comb::{FirCombFilter, IirCombFilter},
+    comb::{CombBank, FirCombFilter, IirCombFilter},
+    convolver::Convolver,
+    filter_design::{CascadeResponse, CascadedFilter},
     first_order::FirstOrderFilter,
     lrf::LinkwitzRileyFilter,
+    parametric_eq::{EqBand, ParametricEq},
     resonator::{
         resonator_bank::{ResoBankData, ResonatorBank, ResonatorBankParams},
         two_pole_resonator::TwoPoleResonator,
     },
-    simple::{dc_filter::DCFilter, one_pole_lowpass::OnePoleLowpass},
+    simple::{
+        dc_filter::{DCFilter, DCFilterSlope},
+        one_pole_lowpass::OnePoleLowpass,
+    },
     svf::StateVariableFilter,
+    tilt::SpectralTilt,
     Filter, FilterType, BUTTERWORTH_Q,
 };
-pub use oversampling::{Oversampler, OversamplingBuffer};
+pub use modulation::{
+    Lfo, LfoParameters, LfoRate, LfoShape, ModDestination, ModMatrix,
+    ModRoute, ModSource,
+};
+pub use oversampling::{
+    Oversampled, Oversampler, OversamplingBuffer, OversamplingFactor,
+};
 pub use spectral::{
+    analyzer::SpectrumAnalyzer,
+    bands::{BandAnalyzer, BandScale},
+    feedback::{FeedbackNotch, FeedbackSuppressor},
+    gate::{GateThreshold, SpectralGate},
+    pitch_shifter::PitchShifter,
     spectral_filter::{mask::SpectralMask, SpectralFilter},
     StftHelper,
 };
-pub use synthesis::Generator;
+pub use synthesis::{
+    ClickGenerator, Generator, GranularEngine, NoiseColor, PluckParameters,
+    SamplePlayer, Wavetable, WavetableOsc,
+};
 pub use util::*;
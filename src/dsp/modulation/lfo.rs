@@ -0,0 +1,363 @@
+//! A low-frequency oscillator for modulation, with a free or tempo-synced
+//! rate.
+
+use super::*;
+use std::f64::consts::{PI, TAU};
+use std::fmt::{Display, Formatter};
+
+/// The fastest rate of an `Lfo`, in Hz.
+pub const MAX_LFO_RATE_HZ: f64 = 50.0;
+/// The slowest rate of an `Lfo`, in Hz.
+pub const MIN_LFO_RATE_HZ: f64 = 0.001;
+
+/// The waveform of an `Lfo`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LfoShape {
+    #[default]
+    Sine,
+    Triangle,
+    /// A rising ramp.
+    Saw,
+    Square,
+    /// A random value which is held for each cycle.
+    SampleAndHold,
+    /// A random value which is smoothly approached over each cycle.
+    SmoothRandom,
+}
+
+impl LfoShape {
+    /// Parses an `LfoShape` from its name, e.g. `"tri"` or `"s&h"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "sine" => Some(Self::Sine),
+            "tri" | "triangle" => Some(Self::Triangle),
+            "saw" => Some(Self::Saw),
+            "square" => Some(Self::Square),
+            "s&h" | "sample-hold" => Some(Self::SampleAndHold),
+            "smooth-random" => Some(Self::SmoothRandom),
+            _ => None,
+        }
+    }
+
+    const fn is_random(self) -> bool {
+        matches!(self, Self::SampleAndHold | Self::SmoothRandom)
+    }
+}
+
+impl Display for LfoShape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sine => write!(f, "Sine"),
+            Self::Triangle => write!(f, "Triangle"),
+            Self::Saw => write!(f, "Saw"),
+            Self::Square => write!(f, "Square"),
+            Self::SampleAndHold => write!(f, "Sample & hold"),
+            Self::SmoothRandom => write!(f, "Smooth random"),
+        }
+    }
+}
+
+/// The rate of an `Lfo`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LfoRate {
+    /// A fixed rate in Hz.
+    Free(f64),
+    /// The length of each cycle in beats, which follows the tempo.
+    Synced(f64),
+}
+
+impl LfoRate {
+    /// Parses a rate in Hz (e.g. `"0.5"`), or a note division (e.g.
+    /// `"1/8t"`) which is synced to the tempo. See [`note_division_beats()`].
+    pub fn parse(rate: &str) -> Option<Self> {
+        if rate.contains('/') {
+            return note_division_beats(rate).map(Self::Synced);
+        }
+
+        rate.parse::<f64>()
+            .ok()
+            .filter(|hz| (MIN_LFO_RATE_HZ..=MAX_LFO_RATE_HZ).contains(hz))
+            .map(Self::Free)
+    }
+
+    /// The rate in Hz at `bpm`.
+    pub fn freq_hz(self, bpm: f64) -> f64 {
+        let hz = match self {
+            Self::Free(hz) => hz,
+            Self::Synced(beats) => bpm / 60.0 / beats,
+        };
+
+        hz.clamp(MIN_LFO_RATE_HZ, MAX_LFO_RATE_HZ)
+    }
+}
+
+impl Default for LfoRate {
+    fn default() -> Self {
+        Self::Free(1.0)
+    }
+}
+
+/// The settings of an `Lfo`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LfoParameters {
+    pub shape: LfoShape,
+    pub rate: LfoRate,
+    /// Whether the output is from `0.0` to `1.0`, rather than `-1.0` to
+    /// `1.0`.
+    pub unipolar: bool,
+    /// Whether the phase is reset by each note.
+    pub retrigger: bool,
+}
+
+/// A low-frequency oscillator.
+#[derive(Debug, Clone)]
+pub struct Lfo {
+    params: LfoParameters,
+    bpm: f64,
+
+    /// The phase, from `0.0` to `1.0`.
+    phase: f64,
+    phase_increment: f64,
+
+    /// The random value at the start of the cycle.
+    random_start: f64,
+    /// The random value at the end of the cycle.
+    random_end: f64,
+
+    sample_rate: f64,
+}
+
+impl Lfo {
+    pub fn new(params: LfoParameters, sample_rate: f64) -> Self {
+        let mut lfo = Self {
+            params,
+            bpm: DEFAULT_BPM,
+
+            phase: 0.0,
+            phase_increment: 0.0,
+
+            random_start: 0.0,
+            random_end: random_f64().mul_add(2.0, -1.0),
+
+            sample_rate,
+        };
+
+        lfo.update_phase_increment();
+        lfo
+    }
+
+    pub const fn parameters(&self) -> LfoParameters {
+        self.params
+    }
+
+    pub fn set_parameters(&mut self, params: LfoParameters) {
+        self.params = params;
+        self.update_phase_increment();
+    }
+
+    /// Sets the tempo which synced rates follow.
+    pub fn set_bpm(&mut self, bpm: f64) {
+        self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        self.update_phase_increment();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.update_phase_increment();
+    }
+
+    /// The current rate in Hz.
+    pub fn freq_hz(&self) -> f64 {
+        self.params.rate.freq_hz(self.bpm)
+    }
+
+    /// Restarts the cycle if the LFO retriggers, otherwise does nothing.
+    pub fn trigger(&mut self) {
+        if self.params.retrigger {
+            self.reset();
+        }
+    }
+
+    /// Restarts the cycle.
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.next_cycle();
+    }
+
+    /// Produces the next value.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> f64 {
+        let value = self.value();
+
+        self.phase += self.phase_increment;
+
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+            self.next_cycle();
+        }
+
+        if self.params.unipolar {
+            value.mul_add(0.5, 0.5)
+        }
+        else {
+            value
+        }
+    }
+
+    /// The bipolar value at the current phase.
+    fn value(&self) -> f64 {
+        let p = self.phase;
+
+        match self.params.shape {
+            LfoShape::Sine => (TAU * p).sin(),
+            LfoShape::Triangle => {
+                if p < 0.25 {
+                    4.0 * p
+                }
+                else if p < 0.75 {
+                    4.0f64.mul_add(-p, 2.0)
+                }
+                else {
+                    4.0f64.mul_add(p, -4.0)
+                }
+            }
+            LfoShape::Saw => p.mul_add(2.0, -1.0),
+            LfoShape::Square => {
+                if p < 0.5 {
+                    1.0
+                }
+                else {
+                    -1.0
+                }
+            }
+            LfoShape::SampleAndHold => self.random_end,
+            LfoShape::SmoothRandom => {
+                let t = 0.5 - 0.5 * (PI * p).cos();
+                lerp(self.random_start, self.random_end, t)
+            }
+        }
+    }
+
+    /// Chooses the random values of the next cycle.
+    fn next_cycle(&mut self) {
+        if self.params.shape.is_random() {
+            self.random_start = self.random_end;
+            self.random_end = random_f64().mul_add(2.0, -1.0);
+        }
+    }
+
+    fn update_phase_increment(&mut self) {
+        self.phase_increment = self.freq_hz() / self.sample_rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 1000.0;
+
+    fn lfo(shape: LfoShape, rate: LfoRate) -> Lfo {
+        Lfo::new(
+            LfoParameters { shape, rate, ..Default::default() },
+            SAMPLE_RATE,
+        )
+    }
+
+    fn cycle(lfo: &mut Lfo) -> Vec<f64> {
+        let len = (SAMPLE_RATE / lfo.freq_hz()).round() as usize;
+        (0..len).map(|_| lfo.next()).collect()
+    }
+
+    #[test]
+    fn rates_are_parsed() {
+        assert_eq!(LfoRate::parse("2.5"), Some(LfoRate::Free(2.5)));
+        assert_eq!(LfoRate::parse("1/4"), Some(LfoRate::Synced(1.0)));
+        assert_eq!(LfoRate::parse("1/8."), Some(LfoRate::Synced(0.75)));
+        assert_eq!(LfoRate::parse("2/1"), Some(LfoRate::Synced(8.0)));
+
+        let Some(LfoRate::Synced(beats)) = LfoRate::parse("1/4t")
+        else {
+            panic!("expected a synced rate");
+        };
+        assert!((beats - 2.0 / 3.0).abs() < 1e-12);
+
+        assert_eq!(LfoRate::parse("0/4"), None);
+        assert_eq!(LfoRate::parse("1000"), None);
+        assert_eq!(LfoRate::parse("fast"), None);
+    }
+
+    #[test]
+    fn synced_rates_follow_the_tempo() {
+        let mut lfo = lfo(LfoShape::Sine, LfoRate::Synced(1.0));
+
+        lfo.set_bpm(120.0);
+        assert!((lfo.freq_hz() - 2.0).abs() < 1e-12);
+        lfo.set_bpm(90.0);
+        assert!((lfo.freq_hz() - 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn shapes_are_bipolar() {
+        for shape in [
+            LfoShape::Sine,
+            LfoShape::Triangle,
+            LfoShape::Saw,
+            LfoShape::Square,
+        ] {
+            let values = cycle(&mut lfo(shape, LfoRate::Free(10.0)));
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+            assert!((-1.0..-0.95).contains(&min), "{shape} min was {min}");
+            assert!((0.95..=1.0).contains(&max), "{shape} max was {max}");
+        }
+    }
+
+    #[test]
+    fn unipolar_output_is_positive() {
+        let mut lfo = lfo(LfoShape::Square, LfoRate::Free(10.0));
+        lfo.set_parameters(LfoParameters {
+            unipolar: true,
+            ..lfo.parameters()
+        });
+
+        let values = cycle(&mut lfo);
+        assert!(values.iter().all(|v| *v == 0.0 || *v == 1.0));
+        assert!(values.contains(&0.0));
+    }
+
+    #[test]
+    fn random_shapes_stay_in_range() {
+        let mut held = lfo(LfoShape::SampleAndHold, LfoRate::Free(10.0));
+        let values = cycle(&mut held);
+        assert!(values.iter().all(|v| *v == values[0]));
+
+        let mut smooth = lfo(LfoShape::SmoothRandom, LfoRate::Free(10.0));
+
+        for _ in 0..10 {
+            let values = cycle(&mut smooth);
+            assert!(values.iter().all(|v| (-1.0..=1.0).contains(v)));
+            assert!(values.windows(2).all(|w| (w[1] - w[0]).abs() < 0.1));
+        }
+    }
+
+    #[test]
+    fn triggers_only_reset_when_retriggering() {
+        let mut lfo = lfo(LfoShape::Saw, LfoRate::Free(10.0));
+
+        for _ in 0..30 {
+            lfo.next();
+        }
+
+        lfo.trigger();
+        assert!(lfo.next() > -0.9);
+
+        lfo.set_parameters(LfoParameters {
+            retrigger: true,
+            ..lfo.parameters()
+        });
+        lfo.trigger();
+        assert_eq!(lfo.next(), -1.0);
+    }
+}
@@ -0,0 +1,409 @@
+//! A modulation matrix, which routes modulation sources (such as LFOs and
+//! envelopes) to the parameters of other processors.
+//!
+//! Each destination has a base value and a range. Every route adds its
+//! source's output, shaped by its curve and scaled by its depth (as a
+//! proportion of the range), to the base value. Nothing is allocated after
+//! the matrix is created, so it may be processed on the audio thread.
+
+use super::*;
+use super::lfo::Lfo;
+use crate::dsp::synthesis::Generator;
+
+/// The most sources a `ModMatrix` can hold.
+pub const MAX_MOD_SOURCES: usize = 8;
+/// The most destinations a `ModMatrix` can hold.
+pub const MAX_MOD_DESTINATIONS: usize = 8;
+/// The most routes a `ModMatrix` can hold.
+pub const MAX_MOD_ROUTES: usize = 16;
+/// The range of each route's curve.
+pub const MOD_CURVE_RANGE: (f64, f64) = (0.1, 10.0);
+/// The smoothing time of external sources, in milliseconds.
+pub const EXTERNAL_MOD_SMOOTHING_MS: f64 = 20.0;
+
+/// A source of modulation.
+#[derive(Debug, Clone)]
+pub enum ModSource {
+    /// An oscillator, whose output is from `-1.0` to `1.0`.
+    Oscillator(Generator),
+    /// An LFO, whose output is either bipolar or unipolar.
+    Lfo(Lfo),
+    /// An envelope, whose output is from `0.0` to `1.0`.
+    Envelope(AdsrEnvelope),
+    /// A value set from outside the matrix, such as a gesture, which is
+    /// smoothed to avoid zipper noise.
+    External(Smoother<f64>),
+}
+
+impl ModSource {
+    /// An external source starting at `value`.
+    pub fn external(value: f64, sample_rate: f64) -> Self {
+        let mut smoother =
+            Smoother::new(EXTERNAL_MOD_SMOOTHING_MS, value, sample_rate);
+        smoother.set_start_value(value);
+
+        Self::External(smoother)
+    }
+
+    /// Produces the source's next value.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> f64 {
+        match self {
+            Self::Oscillator(osc) => osc.process().0,
+            Self::Lfo(lfo) => lfo.next(),
+            Self::Envelope(env) => env.next(),
+            Self::External(value) => value.next(),
+        }
+    }
+
+    /// Sets the trigger of the source if it is an envelope, or retriggers
+    /// it if it is an LFO and `trigger` is `true`.
+    pub fn set_trigger(&mut self, trigger: bool) {
+        match self {
+            Self::Envelope(env) => env.set_trigger(trigger),
+            Self::Lfo(lfo) if trigger => lfo.trigger(),
+            _ => {}
+        }
+    }
+}
+
+/// A connection from a source to a destination.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModRoute {
+    pub source: usize,
+    pub destination: usize,
+    /// The largest change the route makes to its destination, as a
+    /// proportion of the destination's range, from `-1.0` to `1.0`.
+    pub depth: f64,
+    /// The exponent which the source's magnitude is shaped by, where `1.0`
+    /// is linear.
+    pub curve: f64,
+}
+
+impl ModRoute {
+    /// A linear route.
+    pub fn new(source: usize, destination: usize, depth: f64) -> Self {
+        Self {
+            source,
+            destination,
+            depth: depth.clamp(-1.0, 1.0),
+            curve: 1.0,
+        }
+    }
+
+    /// Sets the curve of the route, which is clamped to `MOD_CURVE_RANGE`.
+    pub fn with_curve(mut self, curve: f64) -> Self {
+        let (min, max) = MOD_CURVE_RANGE;
+        self.curve = curve.clamp(min, max);
+        self
+    }
+
+    /// The amount which the route moves its destination for a source value
+    /// of `value`, as a proportion of the destination's range. The curve
+    /// keeps the sign of `value`, so bipolar sources remain bipolar.
+    pub fn amount(&self, value: f64) -> f64 {
+        let shaped = if self.curve == 1.0 {
+            value
+        }
+        else {
+            value.abs().powf(self.curve).copysign(value)
+        };
+
+        shaped * self.depth
+    }
+}
+
+/// A parameter which may be modulated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModDestination {
+    /// The parameter's value without modulation.
+    pub base: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ModDestination {
+    pub fn new(base: f64, min: f64, max: f64) -> Self {
+        debug_assert!(min <= max);
+
+        Self { base: base.clamp(min, max), min, max }
+    }
+}
+
+impl Default for ModDestination {
+    fn default() -> Self {
+        Self { base: 0.0, min: 0.0, max: 1.0 }
+    }
+}
+
+/// Routes modulation sources to destinations.
+#[derive(Debug, Clone)]
+pub struct ModMatrix {
+    sources: [Option<ModSource>; MAX_MOD_SOURCES],
+    destinations: [ModDestination; MAX_MOD_DESTINATIONS],
+    routes: [Option<ModRoute>; MAX_MOD_ROUTES],
+
+    /// The value of each destination for each sample of the latest block.
+    outputs: Vec<Vec<f64>>,
+    /// The length of the latest block.
+    block_len: usize,
+}
+
+impl ModMatrix {
+    /// Creates a matrix which processes blocks of up to `max_block_size`
+    /// samples.
+    pub fn new(max_block_size: usize) -> Self {
+        Self {
+            sources: std::array::from_fn(|_| None),
+            destinations: [ModDestination::default(); MAX_MOD_DESTINATIONS],
+            routes: [None; MAX_MOD_ROUTES],
+
+            outputs: vec![vec![0.0; max_block_size]; MAX_MOD_DESTINATIONS],
+            block_len: 0,
+        }
+    }
+
+    /// Sets the source at `idx`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of range.
+    pub fn set_source(&mut self, idx: usize, source: Option<ModSource>) {
+        self.sources[idx] = source;
+    }
+
+    pub fn source_mut(&mut self, idx: usize) -> Option<&mut ModSource> {
+        self.sources.get_mut(idx).and_then(Option::as_mut)
+    }
+
+    /// Sets the value of the external source at `idx`, if there is one.
+    pub fn set_external(&mut self, idx: usize, value: f64) {
+        if let Some(ModSource::External(smoother)) = self.source_mut(idx) {
+            smoother.set_target_value(value);
+        }
+    }
+
+    /// Sets the trigger of every envelope and LFO source.
+    pub fn set_trigger(&mut self, trigger: bool) {
+        for source in self.sources.iter_mut().flatten() {
+            source.set_trigger(trigger);
+        }
+    }
+
+    /// Sets the tempo which every synced LFO source follows.
+    pub fn set_bpm(&mut self, bpm: f64) {
+        for source in self.sources.iter_mut().flatten() {
+            if let ModSource::Lfo(lfo) = source {
+                lfo.set_bpm(bpm);
+            }
+        }
+    }
+
+    /// Sets the destination at `idx`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of range.
+    pub fn set_destination(&mut self, idx: usize, destination: ModDestination) {
+        self.destinations[idx] = destination;
+    }
+
+    /// Sets the unmodulated value of the destination at `idx`, which is
+    /// clamped to its range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of range.
+    pub fn set_base(&mut self, idx: usize, base: f64) {
+        let dest = &mut self.destinations[idx];
+        dest.base = base.clamp(dest.min, dest.max);
+    }
+
+    /// Sets the route in `slot`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slot` is out of range.
+    pub fn set_route(&mut self, slot: usize, route: Option<ModRoute>) {
+        self.routes[slot] = route;
+    }
+
+    /// Adds `route` in the first free slot, returning the slot, or `None` if
+    /// every slot is used.
+    pub fn add_route(&mut self, route: ModRoute) -> Option<usize> {
+        let slot = self.routes.iter().position(Option::is_none)?;
+        self.routes[slot] = Some(route);
+
+        Some(slot)
+    }
+
+    pub fn clear_routes(&mut self) {
+        self.routes = [None; MAX_MOD_ROUTES];
+    }
+
+    pub fn routes(&self) -> impl Iterator<Item = &ModRoute> {
+        self.routes.iter().flatten()
+    }
+
+    /// Whether any route targets the destination at `idx`.
+    pub fn is_modulated(&self, idx: usize) -> bool {
+        self.routes().any(|route| route.destination == idx)
+    }
+
+    /// Processes the sources and routes for `block_len` samples. The result
+    /// is available from [`values()`](Self::values).
+    pub fn process_block(&mut self, block_len: usize) {
+        let block_len = block_len.min(self.outputs[0].len());
+        self.block_len = block_len;
+
+        let mut source_values = [0.0; MAX_MOD_SOURCES];
+
+        for i in 0..block_len {
+            for (value, source) in
+                source_values.iter_mut().zip(&mut self.sources)
+            {
+                *value = source.as_mut().map_or(0.0, ModSource::next);
+            }
+
+            for (output, dest) in
+                self.outputs.iter_mut().zip(&self.destinations)
+            {
+                output[i] = dest.base;
+            }
+
+            for route in self.routes.iter().flatten() {
+                let Some(dest) = self.destinations.get(route.destination)
+                else {
+                    continue;
+                };
+
+                let value = source_values.get(route.source).unwrap_or(&0.0);
+                self.outputs[route.destination][i] +=
+                    route.amount(*value) * (dest.max - dest.min);
+            }
+
+            for (output, dest) in
+                self.outputs.iter_mut().zip(&self.destinations)
+            {
+                output[i] = output[i].clamp(dest.min, dest.max);
+            }
+        }
+    }
+
+    /// The value of the destination at `idx` for each sample of the latest
+    /// block.
+    pub fn values(&self, idx: usize) -> &[f64] {
+        &self.outputs[idx][..self.block_len]
+    }
+
+    /// The value of the destination at `idx` at the end of the latest block,
+    /// for parameters which are updated once per block.
+    pub fn value(&self, idx: usize) -> f64 {
+        self.values(idx)
+            .last()
+            .copied()
+            .unwrap_or(self.destinations[idx].base)
+    }
+}
+
+impl Default for ModMatrix {
+    fn default() -> Self {
+        Self::new(MAX_BUFFER_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::synthesis::SineOsc;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn matrix() -> ModMatrix {
+        let mut matrix = ModMatrix::new(64);
+        matrix.set_source(0, Some(ModSource::external(0.0, SAMPLE_RATE)));
+        matrix.set_destination(0, ModDestination::new(100.0, 0.0, 200.0));
+        matrix
+    }
+
+    #[test]
+    fn unrouted_destinations_hold_their_base() {
+        let mut matrix = matrix();
+        matrix.set_base(0, 50.0);
+        matrix.process_block(16);
+
+        assert_eq!(matrix.values(0), &[50.0; 16]);
+        assert!(!matrix.is_modulated(0));
+    }
+
+    #[test]
+    fn depth_is_scaled_by_the_range() {
+        let mut matrix = ModMatrix::new(64);
+        matrix.set_source(0, Some(ModSource::external(1.0, SAMPLE_RATE)));
+        matrix.set_destination(0, ModDestination::new(100.0, 0.0, 200.0));
+        matrix.add_route(ModRoute::new(0, 0, 0.25));
+        matrix.process_block(4);
+
+        assert_eq!(matrix.value(0), 150.0);
+
+        // routes to the same destination are summed, then clamped
+        matrix.add_route(ModRoute::new(0, 0, 1.0));
+        matrix.process_block(4);
+
+        assert_eq!(matrix.value(0), 200.0);
+        assert!(matrix.is_modulated(0));
+    }
+
+    #[test]
+    fn curves_keep_the_sign() {
+        let route = ModRoute::new(0, 0, 1.0).with_curve(2.0);
+
+        assert!((route.amount(0.5) - 0.25).abs() < 1e-12);
+        assert!((route.amount(-0.5) + 0.25).abs() < 1e-12);
+        assert_eq!(route.with_curve(100.0).curve, MOD_CURVE_RANGE.1);
+    }
+
+    #[test]
+    fn external_sources_are_smoothed() {
+        let mut matrix = matrix();
+        matrix.add_route(ModRoute::new(0, 0, 0.5));
+        matrix.set_external(0, 1.0);
+        matrix.process_block(64);
+
+        let values = matrix.values(0);
+        assert!(values[0] > 100.0 && values[0] < 101.0);
+        assert!(values.windows(2).all(|w| w[1] >= w[0]));
+    }
+
+    #[test]
+    fn oscillators_are_bipolar() {
+        let mut matrix = matrix();
+        let lfo = Generator::Sine(SineOsc::new(1000.0, SAMPLE_RATE));
+        matrix.set_source(1, Some(ModSource::Oscillator(lfo)));
+        matrix.add_route(ModRoute::new(1, 0, 0.5));
+        matrix.process_block(64);
+
+        let values = matrix.values(0);
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        assert!(min < 10.0 && max > 190.0);
+    }
+
+    #[test]
+    fn routes_fill_free_slots() {
+        let mut matrix = matrix();
+
+        for slot in 0..MAX_MOD_ROUTES {
+            assert_eq!(matrix.add_route(ModRoute::new(0, 0, 0.1)), Some(slot));
+        }
+
+        assert_eq!(matrix.add_route(ModRoute::new(0, 0, 0.1)), None);
+
+        matrix.set_route(3, None);
+        assert_eq!(matrix.add_route(ModRoute::new(0, 0, 0.1)), Some(3));
+
+        matrix.clear_routes();
+        assert_eq!(matrix.routes().count(), 0);
+    }
+}
@@ -1,4 +1,10 @@
-//! Time-based modulation effects. Unused in this project.
+//! Modulation: the routing of modulation sources to parameters, and
+//! time-based modulation effects.
 
 use super::*;
 pub mod chorus;
+pub mod lfo;
+pub mod matrix;
+
+pub use lfo::{Lfo, LfoParameters, LfoRate, LfoShape};
+pub use matrix::{ModDestination, ModMatrix, ModRoute, ModSource};
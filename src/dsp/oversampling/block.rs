@@ -3,9 +3,9 @@
 use std::ops::{Deref, DerefMut};
 
 use super::*;
-use nannou_audio::Buffer;
+use crate::dsp::util::AudioBuffer;
 
-/// This is a kind of reference to a `nannou_audio` `Buffer` type which holds mutable
+/// This is a kind of reference to an `AudioBuffer` which holds mutable
 /// pointers to the samples within it. It essentially makes it such that you can directly
 /// access the separate channels within the buffer.
 ///
@@ -23,7 +23,7 @@ pub struct OversamplingBlock {
 
 impl OversamplingBlock {
     /// Creates a new `OversamplingBlock` from a `Buffer`.
-    pub fn from_buffer(buffer: &mut Buffer<f64>) -> Self {
+    pub fn from_buffer(buffer: &mut AudioBuffer) -> Self {
         let num_channels = buffer.channels();
         let num_samples = buffer.len_frames();
 
@@ -118,7 +118,7 @@ impl OversamplingBuffer {
     ///
     /// Panics if the channel or sample count of `buffer` does not match the
     /// `OversamplingBuffer`'s.
-    pub fn copy_from_buffer(&mut self, buffer: &Buffer<f64>) {
+    pub fn copy_from_buffer(&mut self, buffer: &AudioBuffer) {
         let num_channels = buffer.channels();
         let num_samples = buffer.len_frames();
         debug_assert!(num_channels <= self.num_channels());
@@ -141,7 +141,7 @@ impl OversamplingBuffer {
     ///
     /// Panics if the channel or sample count of `buffer` does not match the
     /// `OversamplingBuffer`'s.
-    pub fn copy_to_buffer(&self, buffer: &mut Buffer<f64>) {
+    pub fn copy_to_buffer(&self, buffer: &mut AudioBuffer) {
         let num_channels = buffer.channels();
         let num_samples = buffer.len_frames();
         debug_assert!(num_channels <= self.num_channels());
@@ -6,6 +6,8 @@ use crate::prelude::*;
 mod block;
 mod lanczos;
 mod lanczos_stage;
+mod oversampler;
 
 pub use block::OversamplingBuffer;
-pub use lanczos::Lanczos3Oversampler as Oversampler;
+pub use lanczos::Lanczos3Oversampler;
+pub use oversampler::{Oversampled, Oversampler, OversamplingFactor};
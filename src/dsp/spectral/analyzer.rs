@@ -0,0 +1,190 @@
+//! Spectrum analysis for visualization.
+
+use super::{stft::stft_trait::StftInput, *};
+use crate::util::window::*;
+use realfft::{num_complex::Complex, RealFftPlanner, RealToComplex};
+use std::sync::Arc;
+
+/// The lowest level reported by the analyzer, in decibels.
+pub const SPECTRUM_FLOOR_DB: f64 = -100.0;
+
+const DEFAULT_RELEASE_TIME_MS: f64 = 300.0;
+
+/// A spectrum analyzer, which finds the smoothed level of each bin of an
+/// audio signal in decibels. The signal is only read, never modified.
+///
+/// Levels rise immediately, and fall according to the release time, so that
+/// peaks are easy to see.
+pub struct SpectrumAnalyzer {
+    /// stft processor
+    stft: StftHelper,
+
+    window_function: Vec<f64>,
+
+    /// scales bin magnitudes so that a full-scale sine has a level of 0 dB
+    magnitude_scale: f64,
+
+    /// frequency domain buffer, shared by all channels
+    complex_buffer: Vec<Complex<f64>>,
+
+    /// forward fft plan
+    fft: Arc<dyn RealToComplex<f64>>,
+
+    /// the magnitude of each bin in the current block, summed over channels
+    frame_magnitudes: Vec<f64>,
+    /// the smoothed level of each bin, in decibels
+    spectrum: Vec<f64>,
+    /// whether the spectrum has changed since it was last taken
+    updated: bool,
+
+    release_time_ms: f64,
+    release_coef: f64,
+
+    sample_rate: f64,
+}
+
+impl SpectrumAnalyzer {
+    const OVERLAP_FACTOR: usize = 2;
+    const DEFAULT_BLOCK_SIZE: usize = 1 << 11;
+
+    /// # Panics
+    ///
+    /// Panics if `num_channels` is `0`, or `block_size` is not a
+    /// power-of-two value.
+    pub fn new(num_channels: usize, block_size: usize) -> Self {
+        assert!(num_channels > 0 && block_size.is_power_of_two());
+
+        let num_bins = block_size / 2 + 1;
+        let window_function = hann(block_size);
+        let window_sum: f64 = window_function.iter().sum();
+
+        let mut analyzer = Self {
+            stft: StftHelper::new(num_channels, block_size, 0),
+
+            window_function,
+            magnitude_scale: 2.0 / window_sum,
+
+            complex_buffer: vec![Complex::default(); num_bins],
+
+            fft: RealFftPlanner::new().plan_fft_forward(block_size),
+
+            frame_magnitudes: vec![0.0; num_bins],
+            spectrum: vec![SPECTRUM_FLOOR_DB; num_bins],
+            updated: false,
+
+            release_time_ms: DEFAULT_RELEASE_TIME_MS,
+            release_coef: 0.0,
+
+            sample_rate: unsafe { SAMPLE_RATE },
+        };
+
+        analyzer.set_release_time_ms(DEFAULT_RELEASE_TIME_MS);
+        analyzer
+    }
+
+    /// Sets how quickly levels fall, in milliseconds.
+    pub fn set_release_time_ms(&mut self, time_ms: f64) {
+        self.release_time_ms = time_ms.max(0.0);
+
+        let hop_time_ms = (self.block_size() / Self::OVERLAP_FACTOR) as f64
+            / self.sample_rate
+            * 1000.0;
+
+        self.release_coef = if self.release_time_ms <= f64::EPSILON {
+            0.0
+        }
+        else {
+            (-hop_time_ms / self.release_time_ms).exp()
+        };
+    }
+
+    /// Analyzes a block of audio. This does not necessarily call the FFT
+    /// algorithm.
+    #[allow(clippy::missing_panics_doc)] // this function will not panic.
+    pub fn process_block<B>(&mut self, buffer: &B)
+    where
+        B: StftInput,
+    {
+        let last_channel = self.stft.num_channels() - 1;
+        let channel_scale =
+            self.magnitude_scale / self.stft.num_channels() as f64;
+        let release_coef = self.release_coef;
+
+        self.stft.process_forward_only(
+            buffer,
+            Self::OVERLAP_FACTOR,
+            |ch_idx, audio_block| {
+                // window the input
+                multiply_buffers(audio_block, &self.window_function);
+
+                // to freq domain
+                self.fft
+                    .process(audio_block, &mut self.complex_buffer)
+                    .unwrap();
+
+                if ch_idx == 0 {
+                    self.frame_magnitudes.fill(0.0);
+                }
+
+                for (mag, bin) in
+                    self.frame_magnitudes.iter_mut().zip(&self.complex_buffer)
+                {
+                    *mag += bin.norm() * channel_scale;
+                }
+
+                if ch_idx != last_channel {
+                    return;
+                }
+
+                // levels rise immediately, but fall smoothly
+                for (level, &mag) in
+                    self.spectrum.iter_mut().zip(&self.frame_magnitudes)
+                {
+                    let db = level_to_db(mag).max(SPECTRUM_FLOOR_DB);
+
+                    *level = if db >= *level {
+                        db
+                    }
+                    else {
+                        db + (*level - db) * release_coef
+                    };
+                }
+
+                self.updated = true;
+            },
+        );
+    }
+
+    /// The smoothed level of each bin, in decibels.
+    pub fn spectrum(&self) -> &[f64] {
+        &self.spectrum
+    }
+
+    /// Returns whether the spectrum has changed since this was last called.
+    pub fn take_updated(&mut self) -> bool {
+        std::mem::take(&mut self.updated)
+    }
+
+    /// The block size of the analyzer.
+    pub fn block_size(&self) -> usize {
+        self.window_function.len()
+    }
+
+    /// The number of bins in the spectrum.
+    pub fn num_bins(&self) -> usize {
+        self.spectrum.len()
+    }
+
+    /// Clears the analyzer's internal buffers.
+    pub fn clear(&mut self) {
+        self.stft.clear();
+        self.spectrum.fill(SPECTRUM_FLOOR_DB);
+        self.updated = true;
+    }
+}
+
+impl Default for SpectrumAnalyzer {
+    fn default() -> Self {
+        Self::new(NUM_CHANNELS, Self::DEFAULT_BLOCK_SIZE)
+    }
+}
@@ -0,0 +1,315 @@
+//! Perceptual (Mel or Bark) band energy analysis.
+
+use super::{stft::stft_trait::StftInput, *};
+use crate::util::window::*;
+use realfft::{num_complex::Complex, RealFftPlanner, RealToComplex};
+use std::sync::Arc;
+
+/// The fewest bands which the analyzer may use.
+pub const MIN_PERCEPTUAL_BANDS: usize = 24;
+/// The most bands which the analyzer may use.
+pub const MAX_PERCEPTUAL_BANDS: usize = 40;
+
+/// The level (in decibels) which maps to a band envelope of `0.0`.
+const ENVELOPE_FLOOR_DB: f64 = -80.0;
+/// The lowest frequency covered by the bands, in Hz.
+const MIN_BAND_FREQ: f64 = 20.0;
+
+const DEFAULT_NUM_BANDS: usize = 32;
+const DEFAULT_ATTACK_TIME_MS: f64 = 10.0;
+const DEFAULT_RELEASE_TIME_MS: f64 = 150.0;
+
+/// The perceptual scale which bands are evenly spaced on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BandScale {
+    /// The Mel scale, which is roughly linear below 1 kHz and logarithmic
+    /// above.
+    #[default]
+    Mel,
+    /// The Bark scale, which follows the ear's critical bands.
+    Bark,
+}
+
+impl BandScale {
+    /// Converts `freq` (in Hz) to this scale.
+    pub fn to_scale(self, freq: f64) -> f64 {
+        match self {
+            Self::Mel => 2595.0 * (1.0 + freq / 700.0).log10(),
+            // Traunmüller's approximation
+            Self::Bark => 26.81 * freq / (1960.0 + freq) - 0.53,
+        }
+    }
+
+    /// Converts `value` on this scale to a frequency in Hz.
+    pub fn to_hz(self, value: f64) -> f64 {
+        match self {
+            Self::Mel => 700.0 * (10.0f64.powf(value / 2595.0) - 1.0),
+            Self::Bark => 1960.0 * (value + 0.53) / (26.28 - value),
+        }
+    }
+}
+
+/// Folds the spectrum of an audio signal into perceptual bands, and follows
+/// the level of each with an envelope. The signal is only read, never
+/// modified.
+///
+/// Each envelope is in `[0.0, 1.0]`, where `0.0` is -80 dB or below and
+/// `1.0` is a full-scale signal.
+pub struct BandAnalyzer {
+    /// stft processor
+    stft: StftHelper,
+
+    window_function: Vec<f64>,
+
+    /// scales bin magnitudes so that a full-scale sine has a level of 0 dB
+    magnitude_scale: f64,
+
+    /// frequency domain buffer, shared by all channels
+    complex_buffer: Vec<Complex<f64>>,
+
+    /// forward fft plan
+    fft: Arc<dyn RealToComplex<f64>>,
+
+    /// the bins (and their weights) which make up each band
+    filters: Vec<Vec<(usize, f64)>>,
+    /// the power of each band in the current block, summed over channels
+    band_power: Vec<f64>,
+    /// the envelope of each band
+    envelopes: Vec<f64>,
+    /// whether the envelopes have changed since they were last taken
+    updated: bool,
+
+    attack_coef: f64,
+    release_coef: f64,
+
+    scale: BandScale,
+    sample_rate: f64,
+}
+
+impl BandAnalyzer {
+    const OVERLAP_FACTOR: usize = 2;
+    const DEFAULT_BLOCK_SIZE: usize = 1 << 11;
+
+    /// # Panics
+    ///
+    /// Panics if `num_channels` is `0`, `block_size` is not a power-of-two
+    /// value, or `num_bands` is outside of
+    /// `[MIN_PERCEPTUAL_BANDS, MAX_PERCEPTUAL_BANDS]`.
+    pub fn new(
+        num_channels: usize,
+        block_size: usize,
+        num_bands: usize,
+        scale: BandScale,
+    ) -> Self {
+        assert!(num_channels > 0 && block_size.is_power_of_two());
+        assert!(
+            (MIN_PERCEPTUAL_BANDS..=MAX_PERCEPTUAL_BANDS).contains(&num_bands)
+        );
+
+        let num_bins = block_size / 2 + 1;
+        let window_function = hann(block_size);
+        let window_sum: f64 = window_function.iter().sum();
+        let sample_rate = unsafe { SAMPLE_RATE };
+
+        let mut analyzer = Self {
+            stft: StftHelper::new(num_channels, block_size, 0),
+
+            window_function,
+            magnitude_scale: 2.0 / window_sum,
+
+            complex_buffer: vec![Complex::default(); num_bins],
+
+            fft: RealFftPlanner::new().plan_fft_forward(block_size),
+
+            filters: build_filters(
+                scale, num_bands, block_size, sample_rate,
+            ),
+            band_power: vec![0.0; num_bands],
+            envelopes: vec![0.0; num_bands],
+            updated: false,
+
+            attack_coef: 0.0,
+            release_coef: 0.0,
+
+            scale,
+            sample_rate,
+        };
+
+        analyzer.set_times_ms(DEFAULT_ATTACK_TIME_MS, DEFAULT_RELEASE_TIME_MS);
+        analyzer
+    }
+
+    /// Sets how quickly the envelopes rise and fall, in milliseconds.
+    pub fn set_times_ms(&mut self, attack_ms: f64, release_ms: f64) {
+        let hop_time_ms = (self.window_function.len() / Self::OVERLAP_FACTOR)
+            as f64
+            / self.sample_rate
+            * 1000.0;
+
+        let coef = |time_ms: f64| {
+            if time_ms <= f64::EPSILON {
+                0.0
+            }
+            else {
+                (-hop_time_ms / time_ms).exp()
+            }
+        };
+
+        self.attack_coef = coef(attack_ms);
+        self.release_coef = coef(release_ms);
+    }
+
+    /// Analyzes a block of audio. This does not necessarily call the FFT
+    /// algorithm.
+    #[allow(clippy::missing_panics_doc)] // this function will not panic.
+    pub fn process_block<B>(&mut self, buffer: &B)
+    where
+        B: StftInput,
+    {
+        let last_channel = self.stft.num_channels() - 1;
+        let magnitude_scale = self.magnitude_scale;
+        let channel_scale = (self.stft.num_channels() as f64).recip();
+        let attack_coef = self.attack_coef;
+        let release_coef = self.release_coef;
+
+        self.stft.process_forward_only(
+            buffer,
+            Self::OVERLAP_FACTOR,
+            |ch_idx, audio_block| {
+                // window the input
+                multiply_buffers(audio_block, &self.window_function);
+
+                // to freq domain
+                self.fft
+                    .process(audio_block, &mut self.complex_buffer)
+                    .unwrap();
+
+                if ch_idx == 0 {
+                    self.band_power.fill(0.0);
+                }
+
+                for (power, filter) in
+                    self.band_power.iter_mut().zip(&self.filters)
+                {
+                    for &(bin, weight) in filter {
+                        let mag = self.complex_buffer[bin].norm()
+                            * magnitude_scale;
+                        *power += mag * mag * weight * channel_scale;
+                    }
+                }
+
+                if ch_idx != last_channel {
+                    return;
+                }
+
+                for (env, &power) in
+                    self.envelopes.iter_mut().zip(&self.band_power)
+                {
+                    let db = 10.0 * power.max(f64::MIN_POSITIVE).log10();
+                    let target = (1.0 - db / ENVELOPE_FLOOR_DB).clamp(0.0, 1.0);
+
+                    let coef = if target > *env {
+                        attack_coef
+                    }
+                    else {
+                        release_coef
+                    };
+
+                    *env = target + (*env - target) * coef;
+                }
+
+                self.updated = true;
+            },
+        );
+    }
+
+    /// The envelope of each band, from lowest to highest, in `[0.0, 1.0]`.
+    pub fn envelopes(&self) -> &[f64] {
+        &self.envelopes
+    }
+
+    /// Returns whether the envelopes have changed since this was last
+    /// called.
+    pub fn take_updated(&mut self) -> bool {
+        std::mem::take(&mut self.updated)
+    }
+
+    pub fn num_bands(&self) -> usize {
+        self.envelopes.len()
+    }
+
+    pub const fn scale(&self) -> BandScale {
+        self.scale
+    }
+
+    /// Clears the analyzer's internal buffers.
+    pub fn clear(&mut self) {
+        self.stft.clear();
+        self.envelopes.fill(0.0);
+        self.updated = true;
+    }
+}
+
+impl Default for BandAnalyzer {
+    fn default() -> Self {
+        Self::new(
+            NUM_CHANNELS,
+            Self::DEFAULT_BLOCK_SIZE,
+            DEFAULT_NUM_BANDS,
+            BandScale::default(),
+        )
+    }
+}
+
+/// Builds a bank of triangular filters, evenly spaced on `scale` from
+/// [`MIN_BAND_FREQ`] to the Nyquist frequency. Each band holds the bins it
+/// covers and their weights, which sum to `1.0`.
+fn build_filters(
+    scale: BandScale,
+    num_bands: usize,
+    block_size: usize,
+    sample_rate: f64,
+) -> Vec<Vec<(usize, f64)>> {
+    let num_bins = block_size / 2 + 1;
+    let bin_width = sample_rate / block_size as f64;
+
+    let low = scale.to_scale(MIN_BAND_FREQ);
+    let high = scale.to_scale(sample_rate * 0.5);
+    let step = (high - low) / (num_bands + 1) as f64;
+
+    // the lower edge, center and upper edge of each band, in bins
+    let edge = |i: usize| scale.to_hz(low + step * i as f64) / bin_width;
+
+    (0..num_bands)
+        .map(|band| {
+            let (lower, center, upper) =
+                (edge(band), edge(band + 1), edge(band + 2));
+
+            let mut filter: Vec<(usize, f64)> = (lower.floor() as usize
+                ..=(upper.ceil() as usize).min(num_bins - 1))
+                .filter_map(|bin| {
+                    let k = bin as f64;
+                    let weight = if k <= center {
+                        (k - lower) / (center - lower)
+                    }
+                    else {
+                        (upper - k) / (upper - center)
+                    };
+
+                    (weight > 0.0).then_some((bin, weight))
+                })
+                .collect();
+
+            // low bands may be narrower than a bin, so they take the nearest
+            if filter.is_empty() {
+                let nearest = (center.round() as usize).min(num_bins - 1);
+                filter.push((nearest, 1.0));
+            }
+
+            let sum: f64 = filter.iter().map(|(_, w)| w).sum();
+            filter.iter_mut().for_each(|(_, w)| *w /= sum);
+
+            filter
+        })
+        .collect()
+}
@@ -0,0 +1,428 @@
+//! Acoustic feedback detection and suppression.
+
+use super::*;
+
+/// The most notches which the suppressor places at once.
+pub const MAX_FEEDBACK_NOTCHES: usize = 8;
+/// The deepest which a notch may become, in decibels.
+pub const MAX_FEEDBACK_NOTCH_DEPTH_DB: f64 = 30.0;
+
+/// The depth of a new notch, in decibels.
+const INITIAL_NOTCH_DEPTH_DB: f64 = 12.0;
+/// How much deeper a notch becomes if its frequency rings again.
+const NOTCH_DEPTH_STEP_DB: f64 = 6.0;
+/// The Q of each notch, which is narrow enough to leave the surrounding
+/// spectrum audibly untouched.
+const NOTCH_Q: f64 = 30.0;
+/// How close a ringing frequency must be to a notch to deepen it rather
+/// than place another, in octaves.
+const NOTCH_MERGE_OCTAVES: f64 = 1.0 / 12.0;
+
+/// The range of frequencies which are checked for ringing.
+const MIN_RINGING_FREQ_HZ: f64 = 80.0;
+const MAX_RINGING_FREQ_HZ: f64 = 16000.0;
+/// How quiet a peak may be and still be considered ringing, in decibels.
+const MIN_RINGING_LEVEL_DB: f64 = -50.0;
+/// How far a peak must stand above the spectrum around it, in decibels.
+const MIN_PROMINENCE_DB: f64 = 15.0;
+/// How many analyses in a row a peak must ring for to be notched.
+const RINGING_ANALYSES: u32 = 12;
+/// How much a ringing peak may fall between analyses, in decibels. Peaks
+/// which decay faster than this are treated as notes rather than feedback.
+const MAX_RINGING_DECAY_DB: f64 = 1.0;
+/// The number of bins on each side of a peak which its prominence is
+/// measured against. The bins of the peak's main lobe are skipped.
+const NEIGHBOUR_BINS: usize = 8;
+const MAIN_LOBE_BINS: usize = 2;
+/// The most peaks which are tracked at once.
+const MAX_CANDIDATES: usize = 32;
+
+/// A notch placed on a ringing frequency.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeedbackNotch {
+    pub freq: f64,
+    /// How far the notch cuts, in decibels.
+    pub depth_db: f64,
+}
+
+#[derive(Clone, Debug)]
+struct Notch {
+    notch: FeedbackNotch,
+    filters: [BiquadFilter; 2],
+}
+
+/// A spectral peak which may be ringing.
+#[derive(Clone, Copy, Debug)]
+struct Candidate {
+    bin: usize,
+    level_db: f64,
+    /// the number of analyses in a row which the peak has rung for
+    count: u32,
+    seen: bool,
+}
+
+/// A feedback suppressor, for when microphones and speakers share a space.
+///
+/// The suppressor is given each new spectrum from a [`SpectrumAnalyzer`]
+/// via [`analyze()`](Self::analyze). Narrow peaks which stand well above
+/// their surroundings and hold their level for long enough are treated as
+/// feedback, and a narrow notch is placed on each. If a notched frequency
+/// keeps ringing, its notch is deepened.
+///
+/// Notches are kept until [`clear()`](Self::clear) is called. Once
+/// [`MAX_FEEDBACK_NOTCHES`] are placed, the oldest is replaced.
+///
+/// Long, pure tones look the same as feedback, so the suppressor is best
+/// used where the input is mostly speech or acoustic sources.
+#[derive(Clone, Debug)]
+pub struct FeedbackSuppressor {
+    /// the notches, from oldest to newest
+    notches: Vec<Notch>,
+    candidates: Vec<Candidate>,
+    /// whether the notches have changed since they were last taken
+    updated: bool,
+
+    sample_rate: f64,
+}
+
+impl FeedbackSuppressor {
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            notches: Vec::with_capacity(MAX_FEEDBACK_NOTCHES),
+            candidates: Vec::with_capacity(MAX_CANDIDATES),
+            updated: false,
+
+            sample_rate,
+        }
+    }
+
+    /// Looks for ringing in `spectrum`, which holds the level of each bin in
+    /// decibels, and places or deepens notches on it. This does not
+    /// allocate.
+    pub fn analyze(&mut self, spectrum: &[f64]) {
+        let num_bins = spectrum.len();
+
+        if num_bins < (NEIGHBOUR_BINS + MAIN_LOBE_BINS) * 2 + 1 {
+            return;
+        }
+
+        let bin_width = self.sample_rate / ((num_bins - 1) * 2) as f64;
+        let margin = NEIGHBOUR_BINS + MAIN_LOBE_BINS;
+
+        let first = ((MIN_RINGING_FREQ_HZ / bin_width) as usize).max(margin);
+        let last = ((MAX_RINGING_FREQ_HZ / bin_width) as usize)
+            .min(num_bins - 1 - margin);
+
+        for bin in first..=last {
+            let level_db = spectrum[bin];
+
+            if level_db < MIN_RINGING_LEVEL_DB
+                || level_db <= spectrum[bin - 1]
+                || level_db < spectrum[bin + 1]
+                || Self::prominence_db(spectrum, bin) < MIN_PROMINENCE_DB
+            {
+                continue;
+            }
+
+            self.track_peak(bin, level_db);
+        }
+
+        // peaks which weren't found again have stopped ringing
+        self.candidates.retain(|cand| cand.seen);
+
+        for i in 0..self.candidates.len() {
+            let cand = &mut self.candidates[i];
+            cand.seen = false;
+
+            if cand.count < RINGING_ANALYSES {
+                continue;
+            }
+
+            // the next notch is only placed if this one doesn't work
+            cand.count = 0;
+            let bin = cand.bin;
+
+            let offset = Self::interpolate_peak(spectrum, bin);
+            self.place_notch((bin as f64 + offset) * bin_width);
+        }
+    }
+
+    /// The notches which have been placed, from oldest to newest.
+    pub fn notches(&self) -> impl Iterator<Item = FeedbackNotch> + '_ {
+        self.notches.iter().map(|notch| notch.notch)
+    }
+
+    /// Whether any notches have been placed.
+    pub fn is_active(&self) -> bool {
+        !self.notches.is_empty()
+    }
+
+    /// Returns whether the notches have changed since this was last called.
+    pub fn take_updated(&mut self) -> bool {
+        std::mem::take(&mut self.updated)
+    }
+
+    /// Removes all of the notches.
+    pub fn clear(&mut self) {
+        self.notches.clear();
+        self.candidates.clear();
+        self.updated = true;
+    }
+
+    /// Clears the notches' filter state, and forgets any peaks which were
+    /// being tracked. The notches are kept.
+    pub fn reset(&mut self) {
+        self.candidates.clear();
+
+        for notch in &mut self.notches {
+            notch.filters = Self::create_filters(self.sample_rate, notch.notch);
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.reset();
+    }
+
+    /// How far the peak at `bin` stands above the bins around its main
+    /// lobe, in decibels.
+    fn prominence_db(spectrum: &[f64], bin: usize) -> f64 {
+        let inner = MAIN_LOBE_BINS;
+        let outer = MAIN_LOBE_BINS + NEIGHBOUR_BINS;
+
+        let below = bin - outer..bin - inner;
+        let above = bin + inner + 1..=bin + outer;
+
+        let sum: f64 = spectrum[below].iter().chain(&spectrum[above]).sum();
+
+        spectrum[bin] - sum / (NEIGHBOUR_BINS * 2) as f64
+    }
+
+    /// The offset of the true peak from `bin` in bins, found by fitting a
+    /// parabola to its neighbours.
+    fn interpolate_peak(spectrum: &[f64], bin: usize) -> f64 {
+        let (a, b, c) = (spectrum[bin - 1], spectrum[bin], spectrum[bin + 1]);
+        let denom = a - 2.0 * b + c;
+
+        if denom.abs() < f64::EPSILON {
+            0.0
+        }
+        else {
+            (0.5 * (a - c) / denom).clamp(-0.5, 0.5)
+        }
+    }
+
+    fn track_peak(&mut self, bin: usize, level_db: f64) {
+        // the peak may drift into a neighbouring bin
+        if let Some(cand) = self
+            .candidates
+            .iter_mut()
+            .find(|cand| !cand.seen && cand.bin.abs_diff(bin) <= 1)
+        {
+            cand.count = if level_db >= cand.level_db - MAX_RINGING_DECAY_DB {
+                cand.count + 1
+            }
+            else {
+                1
+            };
+
+            cand.bin = bin;
+            cand.level_db = level_db;
+            cand.seen = true;
+        }
+        else if self.candidates.len() < MAX_CANDIDATES {
+            self.candidates.push(Candidate {
+                bin,
+                level_db,
+                count: 1,
+                seen: true,
+            });
+        }
+    }
+
+    /// Deepens the notch nearest to `freq`, or places a new one there.
+    fn place_notch(&mut self, freq: f64) {
+        self.updated = true;
+
+        if let Some(notch) = self.notches.iter_mut().find(|notch| {
+            (freq / notch.notch.freq).log2().abs() < NOTCH_MERGE_OCTAVES
+        }) {
+            let depth_db = (notch.notch.depth_db + NOTCH_DEPTH_STEP_DB)
+                .min(MAX_FEEDBACK_NOTCH_DEPTH_DB);
+
+            notch.notch = FeedbackNotch { freq, depth_db };
+
+            for filter in &mut notch.filters {
+                filter.set_freq(freq);
+                filter.set_gain(-depth_db);
+            }
+
+            return;
+        }
+
+        if self.notches.len() == MAX_FEEDBACK_NOTCHES {
+            self.notches.remove(0);
+        }
+
+        let notch = FeedbackNotch { freq, depth_db: INITIAL_NOTCH_DEPTH_DB };
+
+        self.notches.push(Notch {
+            notch,
+            filters: Self::create_filters(self.sample_rate, notch),
+        });
+    }
+
+    fn create_filters(
+        sample_rate: f64,
+        notch: FeedbackNotch,
+    ) -> [BiquadFilter; 2] {
+        let mut filter = BiquadFilter::new(sample_rate);
+        filter.set_type(FilterType::Peak);
+        filter.set_q(NOTCH_Q);
+        filter.set_freq(notch.freq);
+        filter.set_gain(-notch.depth_db);
+
+        [filter.clone(), filter]
+    }
+}
+
+impl Default for FeedbackSuppressor {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE })
+    }
+}
+
+impl Effect for FeedbackSuppressor {
+    fn process_stereo(&mut self, in_l: f64, in_r: f64) -> (f64, f64) {
+        (self.process_mono(in_l, 0), self.process_mono(in_r, 1))
+    }
+
+    fn process_mono(&mut self, mut input: f64, channel_idx: usize) -> f64 {
+        for notch in &mut self.notches {
+            input = notch.filters[channel_idx].process(input);
+        }
+
+        input
+    }
+
+    fn get_sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn get_identifier(&self) -> &str {
+        "feedback_suppressor"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{SQRT_2, TAU};
+
+    const SAMPLE_RATE: f64 = 48000.0;
+    const NUM_BINS: usize = 1025;
+    const BIN_WIDTH: f64 = SAMPLE_RATE / 2048.0;
+
+    /// A spectrum at -70 dB, with a peak at `freq` whose main lobe is
+    /// shaped like a Hann window's.
+    fn spectrum_with_peak(freq: f64, level_db: f64) -> Vec<f64> {
+        let centre = freq / BIN_WIDTH;
+
+        (0..NUM_BINS)
+            .map(|bin| {
+                let dist = (bin as f64 - centre).abs();
+
+                if dist < 2.0 {
+                    let lobe = 0.5 + 0.5 * (dist * TAU / 4.0).cos();
+                    (level_db + level_to_db(lobe)).max(-70.0)
+                }
+                else {
+                    -70.0
+                }
+            })
+            .collect()
+    }
+
+    /// The gain of `suppressor` for a sine at `freq`, in decibels.
+    fn gain_db(suppressor: &mut FeedbackSuppressor, freq: f64) -> f64 {
+        let num_samples = SAMPLE_RATE as usize;
+
+        let sum: f64 = (0..num_samples)
+            .map(|i| {
+                let x = (TAU * freq * i as f64 / SAMPLE_RATE).sin();
+                suppressor.process_stereo(x, x).0
+            })
+            .skip(num_samples / 2)
+            .map(|y| y * y)
+            .sum();
+
+        level_to_db((sum / (num_samples / 2) as f64).sqrt() * SQRT_2)
+    }
+
+    #[test]
+    fn ringing_peaks_are_notched() {
+        let mut suppressor = FeedbackSuppressor::new(SAMPLE_RATE);
+        let spectrum = spectrum_with_peak(1234.0, -10.0);
+
+        for _ in 1..RINGING_ANALYSES {
+            suppressor.analyze(&spectrum);
+        }
+
+        assert!(!suppressor.is_active());
+
+        suppressor.analyze(&spectrum);
+
+        assert!(suppressor.take_updated());
+        let notch = suppressor.notches().next().unwrap();
+        assert!((notch.freq - 1234.0).abs() < BIN_WIDTH * 0.2, "{notch:?}");
+
+        let cut = gain_db(&mut suppressor, notch.freq);
+        assert!((cut + INITIAL_NOTCH_DEPTH_DB).abs() < 0.5, "cut was {cut} dB");
+
+        // an octave away is untouched
+        let pass = gain_db(&mut suppressor, notch.freq * 2.0);
+        assert!(pass.abs() < 0.1, "passband was {pass} dB");
+    }
+
+    #[test]
+    fn decaying_and_quiet_peaks_are_ignored() {
+        let mut suppressor = FeedbackSuppressor::new(SAMPLE_RATE);
+
+        for i in 0..RINGING_ANALYSES * 4 {
+            let decay_db = (i % RINGING_ANALYSES) as f64 * 3.0;
+            suppressor.analyze(&spectrum_with_peak(800.0, -10.0 - decay_db));
+            suppressor.analyze(&spectrum_with_peak(3000.0, -60.0));
+        }
+
+        assert!(!suppressor.is_active());
+    }
+
+    #[test]
+    fn notches_deepen_and_are_replaced() {
+        let mut suppressor = FeedbackSuppressor::new(SAMPLE_RATE);
+        let ring = |suppressor: &mut FeedbackSuppressor, freq: f64| {
+            let spectrum = spectrum_with_peak(freq, -10.0);
+
+            for _ in 0..RINGING_ANALYSES {
+                suppressor.analyze(&spectrum);
+            }
+        };
+
+        ring(&mut suppressor, 500.0);
+        ring(&mut suppressor, 500.0);
+
+        let notch = suppressor.notches().next().unwrap();
+        let expected = INITIAL_NOTCH_DEPTH_DB + NOTCH_DEPTH_STEP_DB;
+        assert_eq!(notch.depth_db, expected);
+
+        for n in 1..=MAX_FEEDBACK_NOTCHES {
+            ring(&mut suppressor, 500.0 * 1.5f64.powi(n as i32));
+        }
+
+        assert_eq!(suppressor.notches().count(), MAX_FEEDBACK_NOTCHES);
+        assert!(suppressor.notches().all(|notch| notch.freq > 600.0));
+
+        suppressor.clear();
+        assert!(!suppressor.is_active());
+    }
+}
@@ -0,0 +1,369 @@
+//! Spectral noise gating.
+
+use super::{stft::stft_trait::StftInputMut, *};
+use crate::util::window::*;
+use realfft::{
+    num_complex::Complex, ComplexToReal, RealFftPlanner, RealToComplex,
+};
+use std::sync::Arc;
+
+const DEFAULT_THRESHOLD_DB: f64 = -60.0;
+const DEFAULT_REDUCTION_DB: f64 = -24.0;
+const DEFAULT_ATTACK_TIME_MS: f64 = 5.0;
+const DEFAULT_RELEASE_TIME_MS: f64 = 80.0;
+
+/// How the gate decides whether a bin is noise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GateThreshold {
+    /// Each bin is compared to the level learned for it while learning was
+    /// enabled, plus an offset in decibels.
+    Learned { offset_db: f64 },
+    /// Every bin is compared to the same level, in decibels.
+    Fixed { threshold_db: f64 },
+}
+
+/// A spectral gate, which attenuates each bin of an audio signal while its
+/// level is below a threshold. Useful for removing steady background noise,
+/// such as microphone hiss.
+///
+/// The threshold may be learned per bin from a recording of the noise alone
+/// (see [`set_learning()`](Self::set_learning)), or fixed for all bins.
+pub struct SpectralGate {
+    /// stft processor
+    stft: StftHelper,
+
+    window_function: Vec<f64>,
+
+    /// a window function with gain compensation
+    compensated_window_function: Vec<f64>,
+
+    /// scales bin magnitudes so that a full-scale sine has a level of 1.0
+    magnitude_scale: f64,
+
+    /// frequency domain buffer, shared by all channels
+    complex_buffer: Vec<Complex<f64>>,
+
+    /// forward fft plan
+    fft: Arc<dyn RealToComplex<f64>>,
+
+    /// inverse fft plan
+    ifft: Arc<dyn ComplexToReal<f64>>,
+
+    /// the learned level of each bin, per channel
+    noise_floor: Vec<Vec<f64>>,
+    /// how many blocks have been averaged into the noise floor, per channel
+    learned_blocks: Vec<usize>,
+    is_learning: bool,
+
+    /// the current gain of each bin, per channel
+    gains: Vec<Vec<f64>>,
+
+    threshold: GateThreshold,
+    /// the gain applied to gated bins
+    reduction: f64,
+
+    attack_time_ms: f64,
+    release_time_ms: f64,
+    attack_coef: f64,
+    release_coef: f64,
+
+    sample_rate: f64,
+}
+
+impl SpectralGate {
+    const OVERLAP_FACTOR: usize = 4;
+    const DEFAULT_BLOCK_SIZE: usize = 1 << 10;
+
+    /// # Panics
+    ///
+    /// Panics if `num_channels` or `max_block_size` is `0`.
+    pub fn new(num_channels: usize, max_block_size: usize) -> Self {
+        let num_bins = max_block_size / 2 + 1;
+
+        let mut gate = Self {
+            stft: StftHelper::new(num_channels, max_block_size, 0),
+
+            window_function: Vec::with_capacity(max_block_size),
+            compensated_window_function: Vec::with_capacity(max_block_size),
+            magnitude_scale: 1.0,
+
+            complex_buffer: vec![Complex::default(); num_bins],
+
+            fft: RealFftPlanner::new().plan_fft_forward(max_block_size),
+            ifft: RealFftPlanner::new().plan_fft_inverse(max_block_size),
+
+            noise_floor: vec![vec![0.0; num_bins]; num_channels],
+            learned_blocks: vec![0; num_channels],
+            is_learning: false,
+
+            gains: vec![vec![1.0; num_bins]; num_channels],
+
+            threshold: GateThreshold::Fixed {
+                threshold_db: DEFAULT_THRESHOLD_DB,
+            },
+            reduction: db_to_level(DEFAULT_REDUCTION_DB),
+
+            attack_time_ms: DEFAULT_ATTACK_TIME_MS,
+            release_time_ms: DEFAULT_RELEASE_TIME_MS,
+            attack_coef: 0.0,
+            release_coef: 0.0,
+
+            sample_rate: unsafe { SAMPLE_RATE },
+        };
+
+        gate.set_block_size(max_block_size);
+        gate
+    }
+
+    /// Sets the block size. Larger blocks separate noise from signal more
+    /// finely, but respond more slowly. This clears the internal buffers and
+    /// any learned noise floor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is not a power-of-two value, or is greater than
+    /// the max block size of the processor.
+    pub fn set_block_size(&mut self, block_size: usize) {
+        assert!(
+            block_size.is_power_of_two()
+                && block_size <= self.stft.max_block_size()
+        );
+
+        let num_bins = block_size / 2 + 1;
+
+        // window function
+        self.window_function = hann(block_size);
+
+        let sum: f64 = self.window_function.iter().sum();
+        self.magnitude_scale = 2.0 / sum;
+
+        let compensation_factor = self.compensation_factor();
+        self.compensated_window_function = self
+            .window_function
+            .iter()
+            .map(|x| x * compensation_factor)
+            .collect();
+
+        // stft
+        self.stft.set_block_size(block_size);
+
+        self.fft = RealFftPlanner::new().plan_fft_forward(block_size);
+        self.ifft = RealFftPlanner::new().plan_fft_inverse(block_size);
+
+        // bin buffers
+        self.complex_buffer.resize(num_bins, Complex::default());
+
+        for floor in &mut self.noise_floor {
+            floor.clear();
+            floor.resize(num_bins, 0.0);
+        }
+
+        for gains in &mut self.gains {
+            gains.clear();
+            gains.resize(num_bins, 1.0);
+        }
+
+        self.learned_blocks.fill(0);
+
+        // the ballistics depend on the hop size
+        self.update_coefficients();
+    }
+
+    /// Sets how the gate's threshold is found.
+    pub fn set_threshold(&mut self, threshold: GateThreshold) {
+        self.threshold = threshold;
+    }
+
+    /// Sets how much gated bins are attenuated by, in decibels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reduction_db` is greater than `0.0`.
+    pub fn set_reduction_db(&mut self, reduction_db: f64) {
+        debug_assert!(reduction_db <= 0.0);
+
+        self.reduction = db_to_level(reduction_db.min(0.0));
+    }
+
+    /// Sets how quickly a bin opens once it rises above the threshold, in
+    /// milliseconds.
+    pub fn set_attack_time_ms(&mut self, time_ms: f64) {
+        self.attack_time_ms = time_ms.max(0.0);
+        self.update_coefficients();
+    }
+
+    /// Sets how quickly a bin closes once it falls below the threshold, in
+    /// milliseconds.
+    pub fn set_release_time_ms(&mut self, time_ms: f64) {
+        self.release_time_ms = time_ms.max(0.0);
+        self.update_coefficients();
+    }
+
+    /// Sets whether the gate is learning the noise floor. While learning, the
+    /// level of each bin is averaged into the floor and the input is passed
+    /// through unchanged. Starting to learn discards the previous floor.
+    pub fn set_learning(&mut self, learning: bool) {
+        if learning && !self.is_learning {
+            for floor in &mut self.noise_floor {
+                floor.fill(0.0);
+            }
+
+            self.learned_blocks.fill(0);
+        }
+
+        self.is_learning = learning;
+    }
+
+    pub const fn is_learning(&self) -> bool {
+        self.is_learning
+    }
+
+    /// Whether a noise floor has been learned for every channel.
+    pub fn has_noise_floor(&self) -> bool {
+        self.learned_blocks.iter().all(|&n| n > 0)
+    }
+
+    /// Processes a block of audio. This does not necessarily call the FFT algorithms.
+    #[allow(clippy::missing_panics_doc)] // this function will not panic.
+    pub fn process_block<B>(&mut self, buffer: &mut B)
+    where
+        B: StftInputMut,
+    {
+        let magnitude_scale = self.magnitude_scale;
+        let is_learning = self.is_learning;
+        let reduction = self.reduction;
+        let attack_coef = self.attack_coef;
+        let release_coef = self.release_coef;
+
+        let (fixed_threshold, floor_offset) = match self.threshold {
+            GateThreshold::Fixed { threshold_db } => {
+                (Some(db_to_level(threshold_db)), 1.0)
+            }
+            GateThreshold::Learned { offset_db } => {
+                (None, db_to_level(offset_db))
+            }
+        };
+
+        self.stft.process_overlap_add(
+            buffer,
+            Self::OVERLAP_FACTOR,
+            |ch_idx, audio_block| {
+                // window the input
+                multiply_buffers(audio_block, &self.window_function);
+
+                // to freq domain
+                self.fft
+                    .process(audio_block, &mut self.complex_buffer)
+                    .unwrap();
+
+                let floor = &mut self.noise_floor[ch_idx];
+
+                if is_learning {
+                    // a running mean of each bin's level
+                    self.learned_blocks[ch_idx] += 1;
+                    let weight = (self.learned_blocks[ch_idx] as f64).recip();
+
+                    for (bin, level) in self.complex_buffer.iter().zip(floor)
+                    {
+                        let mag = bin.norm() * magnitude_scale;
+                        *level += (mag - *level) * weight;
+                    }
+                }
+                else {
+                    let has_floor = self.learned_blocks[ch_idx] > 0;
+
+                    for ((bin, gain), &level) in self
+                        .complex_buffer
+                        .iter_mut()
+                        .zip(&mut self.gains[ch_idx])
+                        .zip(floor.iter())
+                    {
+                        let threshold = match fixed_threshold {
+                            Some(threshold) => threshold,
+                            None if has_floor => level * floor_offset,
+                            // nothing has been learned, so nothing is gated
+                            None => 0.0,
+                        };
+
+                        let mag = bin.norm() * magnitude_scale;
+                        let target =
+                            if mag >= threshold { 1.0 } else { reduction };
+
+                        let coef = if target > *gain {
+                            attack_coef
+                        }
+                        else {
+                            release_coef
+                        };
+
+                        *gain = target + (*gain - target) * coef;
+                        *bin *= *gain;
+                    }
+                }
+
+                // back to time domain
+                self.ifft
+                    .process(&mut self.complex_buffer, audio_block)
+                    .unwrap();
+
+                // window the output
+                multiply_buffers(
+                    audio_block, &self.compensated_window_function,
+                );
+            },
+        );
+    }
+
+    /// The current block size of the gate.
+    pub fn block_size(&self) -> usize {
+        self.window_function.len()
+    }
+
+    /// Amount of latency produced by the gate in samples.
+    pub fn latency_samples(&self) -> u32 {
+        self.stft.latency_samples()
+    }
+
+    /// Clears the gate's internal buffers. The learned noise floor is kept.
+    pub fn clear(&mut self) {
+        self.stft.clear();
+
+        for gains in &mut self.gains {
+            gains.fill(1.0);
+        }
+    }
+
+    /// The compensation factor for the window, resulting in unity gain. The
+    /// window is applied before and after the (unnormalized) FFT.
+    fn compensation_factor(&self) -> f64 {
+        let sum_sq: f64 = self.window_function.iter().map(|w| w * w).sum();
+
+        (Self::OVERLAP_FACTOR as f64 * sum_sq).recip()
+    }
+
+    /// Recalculates the attack and release coefficients, which are applied
+    /// once per hop rather than per sample.
+    fn update_coefficients(&mut self) {
+        let hop_time_ms = (self.block_size() / Self::OVERLAP_FACTOR) as f64
+            / self.sample_rate
+            * 1000.0;
+
+        let coef = |time_ms: f64| {
+            if time_ms <= f64::EPSILON {
+                0.0
+            }
+            else {
+                (-hop_time_ms / time_ms).exp()
+            }
+        };
+
+        self.attack_coef = coef(self.attack_time_ms);
+        self.release_coef = coef(self.release_time_ms);
+    }
+}
+
+impl Default for SpectralGate {
+    fn default() -> Self {
+        Self::new(NUM_CHANNELS, Self::DEFAULT_BLOCK_SIZE)
+    }
+}
@@ -2,6 +2,11 @@
 
 use super::*;
 
+pub mod analyzer;
+pub mod bands;
+pub mod feedback;
+pub mod gate;
+pub mod pitch_shifter;
 pub mod spectral_filter;
 pub mod stft;
 pub use stft::StftHelper;
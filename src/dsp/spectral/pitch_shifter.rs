@@ -0,0 +1,313 @@
+//! Phase vocoder pitch shifting.
+
+use super::{stft::stft_trait::StftInputMut, *};
+use crate::util::window::*;
+use realfft::{
+    num_complex::Complex, ComplexToReal, RealFftPlanner, RealToComplex,
+};
+use std::sync::Arc;
+
+/// The maximum pitch shift in either direction, in semitones.
+pub const MAX_PITCH_SHIFT_SEMITONES: f64 = 24.0;
+
+/// A pitch shifter, which uses a phase vocoder to scale the frequency of each
+/// bin of an audio signal.
+pub struct PitchShifter {
+    /// stft processor
+    stft: StftHelper,
+
+    window_function: Vec<f64>,
+
+    /// a window function with gain compensation
+    compensated_window_function: Vec<f64>,
+
+    /// frequency domain buffer, shared by all channels
+    complex_buffer: Vec<Complex<f64>>,
+
+    /// forward fft plan
+    fft: Arc<dyn RealToComplex<f64>>,
+
+    /// inverse fft plan
+    ifft: Arc<dyn ComplexToReal<f64>>,
+
+    /// the phase of each input bin in the previous block, per channel
+    last_phase: Vec<Vec<f64>>,
+    /// the accumulated phase of each output bin, per channel
+    sum_phase: Vec<Vec<f64>>,
+
+    /// the magnitude and true frequency (in bins) of each input bin
+    analysis: Vec<(f64, f64)>,
+    /// the magnitude and true frequency (in bins) of each output bin
+    synthesis: Vec<(f64, f64)>,
+    /// the smoothed magnitudes of the input, used to preserve formants
+    envelope: Vec<f64>,
+
+    /// the shift as a frequency ratio
+    ratio: f64,
+    shift_semitones: f64,
+    preserve_formants: bool,
+}
+
+impl PitchShifter {
+    const OVERLAP_FACTOR: usize = 4;
+    const DEFAULT_BLOCK_SIZE: usize = 1 << 11;
+
+    /// The width of the smoothing used to find the spectral envelope, as a
+    /// fraction of the number of bins.
+    const ENVELOPE_WIDTH: f64 = 1.0 / 64.0;
+
+    /// # Panics
+    ///
+    /// Panics if `num_channels` or `max_block_size` is `0`.
+    pub fn new(num_channels: usize, max_block_size: usize) -> Self {
+        let num_bins = max_block_size / 2 + 1;
+
+        let mut shifter = Self {
+            stft: StftHelper::new(num_channels, max_block_size, 0),
+
+            window_function: Vec::with_capacity(max_block_size),
+            compensated_window_function: Vec::with_capacity(max_block_size),
+
+            complex_buffer: vec![Complex::default(); num_bins],
+
+            fft: RealFftPlanner::new().plan_fft_forward(max_block_size),
+            ifft: RealFftPlanner::new().plan_fft_inverse(max_block_size),
+
+            last_phase: vec![vec![0.0; num_bins]; num_channels],
+            sum_phase: vec![vec![0.0; num_bins]; num_channels],
+
+            analysis: vec![(0.0, 0.0); num_bins],
+            synthesis: vec![(0.0, 0.0); num_bins],
+            envelope: vec![0.0; num_bins],
+
+            ratio: 1.0,
+            shift_semitones: 0.0,
+            preserve_formants: false,
+        };
+
+        shifter.set_block_size(max_block_size);
+        shifter
+    }
+
+    /// Sets the block size. Larger blocks track lower pitches more
+    /// accurately, but smear transients and add latency. This clears the
+    /// internal buffers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is not a power-of-two value, or is greater than
+    /// the max block size of the processor.
+    pub fn set_block_size(&mut self, block_size: usize) {
+        assert!(
+            block_size.is_power_of_two()
+                && block_size <= self.stft.max_block_size()
+        );
+
+        let num_bins = block_size / 2 + 1;
+
+        // window function
+        self.window_function = hann(block_size);
+
+        let compensation_factor = self.compensation_factor();
+        self.compensated_window_function = self
+            .window_function
+            .iter()
+            .map(|x| x * compensation_factor)
+            .collect();
+
+        // stft
+        self.stft.set_block_size(block_size);
+
+        self.fft = RealFftPlanner::new().plan_fft_forward(block_size);
+        self.ifft = RealFftPlanner::new().plan_fft_inverse(block_size);
+
+        // bin buffers
+        self.complex_buffer.resize(num_bins, Complex::default());
+        self.analysis.resize(num_bins, (0.0, 0.0));
+        self.synthesis.resize(num_bins, (0.0, 0.0));
+        self.envelope.resize(num_bins, 0.0);
+
+        for phases in self.last_phase.iter_mut().chain(&mut self.sum_phase) {
+            phases.clear();
+            phases.resize(num_bins, 0.0);
+        }
+    }
+
+    /// Sets the pitch shift in semitones. The value is clamped to
+    /// `±MAX_PITCH_SHIFT_SEMITONES`.
+    pub fn set_shift_semitones(&mut self, semitones: f64) {
+        self.shift_semitones = semitones
+            .clamp(-MAX_PITCH_SHIFT_SEMITONES, MAX_PITCH_SHIFT_SEMITONES);
+        self.ratio = (self.shift_semitones / 12.0).exp2();
+    }
+
+    /// Sets whether the spectral envelope of the input is kept in place while
+    /// the pitch is shifted, which avoids the "chipmunk" effect on voices.
+    pub fn set_preserve_formants(&mut self, preserve: bool) {
+        self.preserve_formants = preserve;
+    }
+
+    /// Processes a block of audio. This does not necessarily call the FFT algorithms.
+    #[allow(clippy::missing_panics_doc)] // this function will not panic.
+    pub fn process_block<B>(&mut self, buffer: &mut B)
+    where
+        B: StftInputMut,
+    {
+        let block_size = self.window_function.len();
+        let num_bins = block_size / 2 + 1;
+
+        // the expected phase advance per bin between blocks
+        let expected = TAU / Self::OVERLAP_FACTOR as f64;
+        let ratio = self.ratio;
+        let envelope_radius =
+            (num_bins as f64 * Self::ENVELOPE_WIDTH).ceil() as usize;
+        let preserve_formants = self.preserve_formants;
+
+        self.stft.process_overlap_add(
+            buffer,
+            Self::OVERLAP_FACTOR,
+            |ch_idx, audio_block| {
+                // window the input
+                multiply_buffers(audio_block, &self.window_function);
+
+                // to freq domain
+                self.fft
+                    .process(audio_block, &mut self.complex_buffer)
+                    .unwrap();
+
+                // find the true frequency of each bin from its phase advance
+                let last_phase = &mut self.last_phase[ch_idx];
+
+                for (k, (bin, analysis)) in self
+                    .complex_buffer
+                    .iter()
+                    .zip(self.analysis.iter_mut())
+                    .enumerate()
+                {
+                    let (mag, phase) = bin.to_polar();
+                    let delta = wrap_phase(
+                        phase - last_phase[k] - k as f64 * expected,
+                    );
+                    last_phase[k] = phase;
+
+                    *analysis = (mag, k as f64 + delta / expected);
+                }
+
+                if preserve_formants {
+                    spectral_envelope(
+                        &self.analysis, &mut self.envelope, envelope_radius,
+                    );
+
+                    for ((mag, _), &env) in
+                        self.analysis.iter_mut().zip(&self.envelope)
+                    {
+                        *mag = if env > f64::EPSILON {
+                            *mag / env
+                        }
+                        else {
+                            0.0
+                        };
+                    }
+                }
+
+                // move each bin to its shifted frequency
+                self.synthesis.fill((0.0, 0.0));
+
+                for (k, &(mag, freq)) in self.analysis.iter().enumerate() {
+                    let target = (k as f64 * ratio).round() as usize;
+
+                    if let Some(synthesis) = self.synthesis.get_mut(target) {
+                        synthesis.0 += mag;
+                        synthesis.1 = freq * ratio;
+                    }
+                }
+
+                // rebuild the phase of each bin from its frequency
+                let sum_phase = &mut self.sum_phase[ch_idx];
+
+                for (k, (bin, &(mut mag, freq))) in self
+                    .complex_buffer
+                    .iter_mut()
+                    .zip(&self.synthesis)
+                    .enumerate()
+                {
+                    if preserve_formants {
+                        mag *= self.envelope[k];
+                    }
+
+                    sum_phase[k] = wrap_phase(sum_phase[k] + freq * expected);
+                    *bin = Complex::from_polar(mag, sum_phase[k]);
+                }
+
+                // the dc and nyquist bins must be real
+                self.complex_buffer[0].im = 0.0;
+                self.complex_buffer[num_bins - 1].im = 0.0;
+
+                // back to time domain
+                self.ifft
+                    .process(&mut self.complex_buffer, audio_block)
+                    .unwrap();
+
+                // window the output
+                multiply_buffers(
+                    audio_block, &self.compensated_window_function,
+                );
+            },
+        );
+    }
+
+    /// The current block size of the shifter.
+    pub fn block_size(&self) -> usize {
+        self.window_function.len()
+    }
+
+    /// The pitch shift in semitones.
+    pub const fn shift_semitones(&self) -> f64 {
+        self.shift_semitones
+    }
+
+    /// Amount of latency produced by the shifter in samples.
+    pub fn latency_samples(&self) -> u32 {
+        self.stft.latency_samples()
+    }
+
+    /// Clears the shifter's internal buffers.
+    pub fn clear(&mut self) {
+        self.stft.clear();
+
+        for phases in self.last_phase.iter_mut().chain(&mut self.sum_phase) {
+            phases.fill(0.0);
+        }
+    }
+
+    /// The compensation factor for the window, resulting in unity gain. The
+    /// window is applied before and after the (unnormalized) FFT.
+    fn compensation_factor(&self) -> f64 {
+        let sum_sq: f64 = self.window_function.iter().map(|w| w * w).sum();
+
+        (Self::OVERLAP_FACTOR as f64 * sum_sq).recip()
+    }
+}
+
+impl Default for PitchShifter {
+    fn default() -> Self {
+        Self::new(NUM_CHANNELS, Self::DEFAULT_BLOCK_SIZE)
+    }
+}
+
+/// Wraps `phase` to `[-π, π]`.
+fn wrap_phase(phase: f64) -> f64 {
+    phase - TAU * (phase / TAU).round()
+}
+
+/// Smooths the magnitudes of `bins` with a moving average of `radius` bins
+/// either side, which approximates their spectral envelope.
+fn spectral_envelope(bins: &[(f64, f64)], envelope: &mut [f64], radius: usize) {
+    for (k, env) in envelope.iter_mut().enumerate() {
+        let start = k.saturating_sub(radius);
+        let end = (k + radius + 1).min(bins.len());
+
+        let sum: f64 = bins[start..end].iter().map(|(mag, _)| mag).sum();
+        *env = sum / (end - start) as f64;
+    }
+}
@@ -5,7 +5,6 @@ use super::{
     *,
 };
 use crate::util::window::*;
-use nannou_audio::Buffer;
 use realfft::{
     num_complex::Complex, ComplexToReal, RealFftPlanner, RealToComplex,
 };
@@ -25,6 +24,11 @@ pub struct SpectralFilter {
 
     window_function: Vec<f64>,
 
+    window_type: WindowType,
+
+    /// how many windows overlap each sample
+    overlap_factor: usize,
+
     /// frequency domain buffers
     complex_buffers: Vec<Vec<Complex<f64>>>,
 
@@ -44,23 +48,23 @@ pub struct SpectralFilter {
 }
 
 impl SpectralFilter {
-    const OVERLAP_FACTOR: usize = 4;
+    const DEFAULT_OVERLAP_FACTOR: usize = 4;
+    const DEFAULT_WINDOW_TYPE: WindowType = WindowType::Hann;
 
     /// # Panics
     ///
     /// Panics if `num_channels` or `max_block_size` is `0`.
     pub fn new(num_channels: usize, max_block_size: usize) -> Self {
-        Self {
+        let mut filter = Self {
             stft: StftHelper::new(num_channels, max_block_size, 0),
 
-            compensated_window_function: hann(max_block_size)
-                .into_iter()
-                .map(|x| {
-                    x * ((max_block_size * Self::OVERLAP_FACTOR) as f64).recip()
-                })
-                .collect(),
+            compensated_window_function: vec![0.0; max_block_size],
+
+            window_function: vec![0.0; max_block_size],
 
-            window_function: hann(max_block_size),
+            window_type: Self::DEFAULT_WINDOW_TYPE,
+
+            overlap_factor: Self::DEFAULT_OVERLAP_FACTOR,
 
             complex_buffers: vec![
                 vec![
@@ -79,7 +83,10 @@ impl SpectralFilter {
 
             mask: SpectralMask::new(max_block_size)
                 .with_size(max_block_size / 2),
-        }
+        };
+
+        filter.update_window();
+        filter
     }
 
     /// # Panics
@@ -88,16 +95,10 @@ impl SpectralFilter {
     pub fn set_block_size(&mut self, block_size: usize) {
         assert!(block_size <= self.stft.max_block_size());
 
-        let compensation_factor = self.compensation_factor(block_size);
-
         // window function
-        self.window_function = hann(block_size);
-
-        self.compensated_window_function = self
-            .window_function
-            .iter()
-            .map(|x| x * compensation_factor)
-            .collect();
+        self.window_function.resize(block_size, 0.0);
+        self.compensated_window_function.resize(block_size, 0.0);
+        self.update_window();
 
         // stft
         self.stft.set_block_size(block_size);
@@ -117,6 +118,30 @@ impl SpectralFilter {
         // self.mask.resize(block_size / 2, 1.0);
     }
 
+    /// Sets the window which is applied to each block. Windows with lower
+    /// side-lobes smear less between bins, at the cost of time resolution.
+    pub fn set_window(&mut self, window_type: WindowType) {
+        self.window_type = window_type;
+        self.update_window();
+    }
+
+    /// Sets how many windows overlap each sample. Higher factors smooth
+    /// changes over time, at the cost of more FFTs per sample.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `overlap_factor` is not a power-of-two value, or is greater
+    /// than the block size.
+    pub fn set_overlap_factor(&mut self, overlap_factor: usize) {
+        assert!(
+            overlap_factor.is_power_of_two()
+                && overlap_factor <= self.window_function.len()
+        );
+
+        self.overlap_factor = overlap_factor;
+        self.update_window();
+    }
+
     /// Clones `mask` into the filter.
     ///
     /// Clones `min(self.block_size(), mask.len())` elements.
@@ -145,7 +170,7 @@ impl SpectralFilter {
 
         self.stft.process_overlap_add(
             buffer,
-            Self::OVERLAP_FACTOR,
+            self.overlap_factor,
             |ch_idx, audio_block| {
                 // window the input
                 multiply_buffers(audio_block, &self.window_function);
@@ -199,10 +224,40 @@ impl SpectralFilter {
         self.mask.fill(0.0);
     }
 
-    /// The compensation factor for a hanning window, resulting in unity gain for
-    /// overlap factors of 4 and above.
-    pub fn compensation_factor(&self, block_size: usize) -> f64 {
-        ((Self::OVERLAP_FACTOR as f64 / 4.0) * 1.5).recip() / block_size as f64
+    pub const fn window_type(&self) -> WindowType {
+        self.window_type
+    }
+
+    pub const fn overlap_factor(&self) -> usize {
+        self.overlap_factor
+    }
+
+    /// The compensation factor for the current window and overlap factor,
+    /// resulting in unity gain.
+    ///
+    /// As the window is applied both before and after the FFT, the overlapping
+    /// windows sum to `overlap_factor * mean(w²)`. The inverse FFT is also
+    /// unnormalized, which scales the output by the block size.
+    pub fn compensation_factor(&self) -> f64 {
+        let sum_sq: f64 = self.window_function.iter().map(|w| w * w).sum();
+
+        (self.overlap_factor as f64 * sum_sq).recip()
+    }
+
+    /// Rebuilds the window for the current block size, window type and
+    /// overlap factor.
+    fn update_window(&mut self) {
+        self.window_type.build_in_place(&mut self.window_function);
+
+        let compensation_factor = self.compensation_factor();
+
+        for (comp, &w) in self
+            .compensated_window_function
+            .iter_mut()
+            .zip(&self.window_function)
+        {
+            *comp = w * compensation_factor;
+        }
     }
 
     /// Stores the input data into a temporary scratch buffer, used for
@@ -249,7 +304,7 @@ impl SpectralFilter {
         let mix = self.mix.next();
 
         let dry = ((FRAC_PI_2 * mix).cos());
-        // * self.compensation_factor(self.mask.len()).recip();
+        // * self.compensation_factor().recip();
         let wet = ((FRAC_PI_2 * mix).sin());
 
         (dry, wet)
@@ -270,6 +325,8 @@ impl Default for SpectralFilter {
 
             compensated_window_function: Vec::default(),
             window_function: Vec::default(),
+            window_type: Self::DEFAULT_WINDOW_TYPE,
+            overlap_factor: Self::DEFAULT_OVERLAP_FACTOR,
 
             dry_buffer: Vec::default(),
 
@@ -2,7 +2,7 @@
 
 use crate::prelude::*;
 
-use nannou_audio::Buffer;
+use crate::dsp::util::AudioBuffer;
 
 /// A buffer which may be read by the `StftHelper`.
 pub trait StftInput {
@@ -13,6 +13,12 @@ pub trait StftInput {
     fn num_channels(&self) -> usize;
 
     /// Obtains a copy of a specific sample without any bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// `channel_idx` and `sample_idx` must be less than
+    /// [`num_channels()`](Self::num_channels) and
+    /// [`num_samples()`](Self::num_samples) respectively.
     unsafe fn get_sample_unchecked(&self, channel_idx: usize, sample_idx: usize) -> f64;
 }
 
@@ -20,6 +26,12 @@ pub trait StftInput {
 pub trait StftInputMut: StftInput {
     /// Obtains a mutable reference to a specific sample without any
     /// bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// `channel_idx` and `sample_idx` must be less than
+    /// [`num_channels()`](StftInput::num_channels) and
+    /// [`num_samples()`](StftInput::num_samples) respectively.
     unsafe fn get_sample_unchecked_mut(
         &mut self,
         channel_idx: usize,
@@ -27,7 +39,7 @@ pub trait StftInputMut: StftInput {
     ) -> &mut f64;
 }
 
-impl StftInput for Buffer<f64> {
+impl StftInput for AudioBuffer {
     #[inline]
     fn num_samples(&self) -> usize {
         self.len_frames()
@@ -45,7 +57,7 @@ impl StftInput for Buffer<f64> {
     }
 }
 
-impl StftInputMut for Buffer<f64> {
+impl StftInputMut for AudioBuffer {
     #[inline]
     unsafe fn get_sample_unchecked_mut(
         &mut self,
@@ -0,0 +1,525 @@
+//! A minimal reader for uncompressed WAV and AIFF audio files.
+//!
+//! Supported sample formats are 8-, 16-, 24- and 32-bit integer PCM and
+//! 32- and 64-bit floating point, in WAV (including `WAVE_FORMAT_EXTENSIBLE`),
+//! AIFF and uncompressed AIFF-C files.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// A stereo frame of audio.
+pub type StereoFrame = [f64; 2];
+
+/// The properties of an audio file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioFileInfo {
+    pub num_channels: usize,
+    pub num_frames: usize,
+    pub sample_rate: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    /// Unsigned integers, as used by 8-bit WAV files.
+    Unsigned,
+    /// Signed integers.
+    Signed,
+    Float,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SampleFormat {
+    encoding: Encoding,
+    bytes: usize,
+    big_endian: bool,
+}
+
+impl SampleFormat {
+    /// Decodes one sample from `b`, which holds exactly `self.bytes` bytes.
+    fn decode(self, b: &[u8]) -> f64 {
+        // the sample is assembled big-endian, then decoded
+        let mut raw = [0u8; 8];
+
+        for (i, byte) in raw[..self.bytes].iter_mut().enumerate() {
+            *byte = if self.big_endian { b[i] } else { b[self.bytes - 1 - i] };
+        }
+
+        match (self.encoding, self.bytes) {
+            (Encoding::Float, 4) => f64::from(f32::from_be_bytes([
+                raw[0], raw[1], raw[2], raw[3],
+            ])),
+            (Encoding::Float, _) => f64::from_be_bytes(raw),
+            (Encoding::Unsigned, _) => (f64::from(raw[0]) - 128.0) / 128.0,
+            (Encoding::Signed, _) => {
+                // the sample fills the top of an i32, which sign-extends it
+                let value =
+                    i32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]);
+                f64::from(value) / f64::from(1u32 << 31)
+            }
+        }
+    }
+}
+
+/// Reads frames of audio from a WAV or AIFF file, as stereo. Mono files are
+/// copied to both channels, and only the first two channels of other files
+/// are read.
+#[derive(Debug)]
+pub struct AudioFileReader<R> {
+    reader: R,
+    info: AudioFileInfo,
+    format: SampleFormat,
+    /// The position of the first frame in the file, in bytes.
+    data_offset: u64,
+    /// Holds the raw bytes of the frames being read.
+    raw: Vec<u8>,
+}
+
+impl AudioFileReader<BufReader<File>> {
+    /// Opens the audio file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, or is not a supported
+    /// WAV or AIFF file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> AudioFileReader<R> {
+    /// Reads the header of the audio file in `reader`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `reader` does not hold a supported WAV or AIFF
+    /// file.
+    pub fn new(mut reader: R) -> Result<Self, String> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header).map_err(|e| e.to_string())?;
+
+        let header = match (&header[0..4], &header[8..12]) {
+            (b"RIFF", b"WAVE") => read_wav_header(&mut reader)?,
+            (b"FORM", b"AIFF") => read_aiff_header(&mut reader, false)?,
+            (b"FORM", b"AIFC") => read_aiff_header(&mut reader, true)?,
+            _ => return Err(String::from("not a WAV or AIFF file")),
+        };
+
+        Ok(Self {
+            reader,
+            info: header.info,
+            format: header.format,
+            data_offset: header.data_offset,
+            raw: Vec::new(),
+        })
+    }
+
+    pub const fn info(&self) -> AudioFileInfo {
+        self.info
+    }
+
+    /// Reads frames from `start_frame` into `out`, returning the number of
+    /// frames read, which is fewer than `out.len()` at the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read.
+    pub fn read_frames(
+        &mut self,
+        start_frame: usize,
+        out: &mut [StereoFrame],
+    ) -> Result<usize, String> {
+        let num_frames =
+            out.len().min(self.info.num_frames.saturating_sub(start_frame));
+
+        if num_frames == 0 {
+            return Ok(0);
+        }
+
+        let bytes = self.format.bytes;
+        let frame_bytes = bytes * self.info.num_channels;
+
+        self.raw.resize(num_frames * frame_bytes, 0);
+        self.reader
+            .seek(SeekFrom::Start(
+                self.data_offset + (start_frame * frame_bytes) as u64,
+            ))
+            .and_then(|_| self.reader.read_exact(&mut self.raw))
+            .map_err(|e| e.to_string())?;
+
+        let second_channel = usize::from(self.info.num_channels > 1);
+
+        for (frame, raw) in out.iter_mut().zip(self.raw.chunks(frame_bytes)) {
+            let left = self.format.decode(&raw[..bytes]);
+            let right = self.format.decode(
+                &raw[second_channel * bytes..(second_channel + 1) * bytes],
+            );
+
+            *frame = [left, right];
+        }
+
+        Ok(num_frames)
+    }
+}
+
+// *** *** *** //
+
+struct Header {
+    info: AudioFileInfo,
+    format: SampleFormat,
+    data_offset: u64,
+}
+
+/// Reads a chunk's ID and size, returning `None` at the end of the file.
+fn read_chunk_header<R: Read>(
+    reader: &mut R,
+    big_endian: bool,
+) -> Result<Option<([u8; 4], u64)>, String> {
+    let mut buf = [0u8; 8];
+
+    match reader.read_exact(&mut buf) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            return Ok(None);
+        }
+        Err(e) => return Err(e.to_string()),
+    }
+
+    let size = [buf[4], buf[5], buf[6], buf[7]];
+    let size = if big_endian {
+        u32::from_be_bytes(size)
+    }
+    else {
+        u32::from_le_bytes(size)
+    };
+
+    Ok(Some(([buf[0], buf[1], buf[2], buf[3]], u64::from(size))))
+}
+
+/// Reads a chunk's body, skipping its pad byte.
+fn read_chunk<R: Read + Seek>(
+    reader: &mut R,
+    size: u64,
+) -> Result<Vec<u8>, String> {
+    let mut chunk = vec![0u8; size as usize];
+    reader.read_exact(&mut chunk).map_err(|e| e.to_string())?;
+    reader
+        .seek(SeekFrom::Current((size % 2) as i64))
+        .map_err(|e| e.to_string())?;
+
+    Ok(chunk)
+}
+
+/// Skips a chunk's body, including its pad byte.
+fn skip_chunk<R: Seek>(reader: &mut R, size: u64) -> Result<(), String> {
+    reader
+        .seek(SeekFrom::Current((size + size % 2) as i64))
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn read_wav_header<R: Read + Seek>(reader: &mut R) -> Result<Header, String> {
+    const PCM: u16 = 1;
+    const IEEE_FLOAT: u16 = 3;
+    const EXTENSIBLE: u16 = 0xFFFE;
+
+    let mut fmt = None;
+
+    while let Some((id, size)) = read_chunk_header(reader, false)? {
+        match &id {
+            b"fmt " => {
+                let c = read_chunk(reader, size)?;
+
+                if c.len() < 16 {
+                    return Err(String::from("invalid WAV format chunk"));
+                }
+
+                let u16_at = |i: usize| u16::from_le_bytes([c[i], c[i + 1]]);
+                let mut tag = u16_at(0);
+
+                // the real format is held in the first two bytes of the
+                // subformat GUID
+                if tag == EXTENSIBLE && c.len() >= 26 {
+                    tag = u16_at(24);
+                }
+
+                let channels = usize::from(u16_at(2));
+                let sample_rate =
+                    u32::from_le_bytes([c[4], c[5], c[6], c[7]]);
+                let bits = usize::from(u16_at(14));
+
+                let encoding = match (tag, bits) {
+                    (PCM, 8) => Encoding::Unsigned,
+                    (PCM, 16 | 24 | 32) => Encoding::Signed,
+                    (IEEE_FLOAT, 32 | 64) => Encoding::Float,
+                    _ => {
+                        return Err(format!(
+                            "unsupported WAV format (tag {tag}, {bits}-bit)"
+                        ));
+                    }
+                };
+
+                fmt = Some((
+                    channels,
+                    f64::from(sample_rate),
+                    SampleFormat {
+                        encoding,
+                        bytes: bits / 8,
+                        big_endian: false,
+                    },
+                ));
+            }
+            b"data" => {
+                let Some((num_channels, sample_rate, format)) = fmt
+                else {
+                    return Err(String::from(
+                        "WAV data appears before its format",
+                    ));
+                };
+
+                let data_offset =
+                    reader.stream_position().map_err(|e| e.to_string())?;
+
+                return header(
+                    num_channels, sample_rate, format, data_offset, size,
+                );
+            }
+            _ => skip_chunk(reader, size)?,
+        }
+    }
+
+    Err(String::from("WAV file has no audio data"))
+}
+
+fn read_aiff_header<R: Read + Seek>(
+    reader: &mut R,
+    is_aifc: bool,
+) -> Result<Header, String> {
+    let mut comm = None;
+
+    while let Some((id, size)) = read_chunk_header(reader, true)? {
+        match &id {
+            b"COMM" => {
+                let c = read_chunk(reader, size)?;
+
+                if c.len() < 18 {
+                    return Err(String::from("invalid AIFF common chunk"));
+                }
+
+                let channels = usize::from(u16::from_be_bytes([c[0], c[1]]));
+                let bits = usize::from(u16::from_be_bytes([c[6], c[7]]));
+                let sample_rate = extended_to_f64(&c[8..18]);
+
+                let compression = if is_aifc && c.len() >= 22 {
+                    [c[18], c[19], c[20], c[21]]
+                }
+                else {
+                    *b"NONE"
+                };
+
+                let (encoding, big_endian) = match &compression {
+                    b"NONE" | b"twos" => (Encoding::Signed, true),
+                    b"sowt" => (Encoding::Signed, false),
+                    b"fl32" | b"FL32" | b"fl64" | b"FL64" => {
+                        (Encoding::Float, true)
+                    }
+                    other => {
+                        return Err(format!(
+                            "unsupported AIFF-C compression \"{}\"",
+                            String::from_utf8_lossy(other)
+                        ));
+                    }
+                };
+
+                let bits = match &compression {
+                    b"fl32" | b"FL32" => 32,
+                    b"fl64" | b"FL64" => 64,
+                    _ => bits,
+                };
+
+                if !matches!(bits, 8 | 16 | 24 | 32 | 64)
+                    || (encoding == Encoding::Signed && bits == 64)
+                {
+                    return Err(format!("unsupported {bits}-bit AIFF"));
+                }
+
+                comm = Some((
+                    channels,
+                    sample_rate,
+                    SampleFormat { encoding, bytes: bits / 8, big_endian },
+                ));
+            }
+            b"SSND" => {
+                let Some((num_channels, sample_rate, format)) = comm
+                else {
+                    return Err(String::from(
+                        "AIFF sound data appears before its format",
+                    ));
+                };
+
+                let mut offset = [0u8; 8];
+                reader.read_exact(&mut offset).map_err(|e| e.to_string())?;
+                let offset = u32::from_be_bytes([
+                    offset[0], offset[1], offset[2], offset[3],
+                ]);
+
+                let data_offset =
+                    reader.stream_position().map_err(|e| e.to_string())?
+                        + u64::from(offset);
+
+                return header(
+                    num_channels,
+                    sample_rate,
+                    format,
+                    data_offset,
+                    size.saturating_sub(8 + u64::from(offset)),
+                );
+            }
+            _ => skip_chunk(reader, size)?,
+        }
+    }
+
+    Err(String::from("AIFF file has no sound data"))
+}
+
+fn header(
+    num_channels: usize,
+    sample_rate: f64,
+    format: SampleFormat,
+    data_offset: u64,
+    data_size: u64,
+) -> Result<Header, String> {
+    if num_channels == 0 || sample_rate <= 0.0 {
+        return Err(String::from("audio file has no channels or sample rate"));
+    }
+
+    let frame_bytes = (format.bytes * num_channels) as u64;
+
+    Ok(Header {
+        info: AudioFileInfo {
+            num_channels,
+            num_frames: (data_size / frame_bytes) as usize,
+            sample_rate,
+        },
+        format,
+        data_offset,
+    })
+}
+
+/// Converts an 80-bit IEEE 754 extended-precision float, as used for AIFF
+/// sample rates.
+fn extended_to_f64(b: &[u8]) -> f64 {
+    let sign = if b[0] & 0x80 == 0 { 1.0 } else { -1.0 };
+    let exponent = i32::from(u16::from_be_bytes([b[0] & 0x7F, b[1]]));
+    let mantissa = u64::from_be_bytes([
+        b[2], b[3], b[4], b[5], b[6], b[7], b[8], b[9],
+    ]);
+
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+
+    sign * mantissa as f64 * 2.0f64.powi(exponent - 16383 - 63)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn wav_16_bit(channels: u16, samples: &[i16]) -> Vec<u8> {
+        let data_len = (samples.len() * 2) as u32;
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&44100u32.to_le_bytes());
+        bytes.extend_from_slice(
+            &(44100 * 2 * u32::from(channels)).to_le_bytes(),
+        );
+        bytes.extend_from_slice(&(2 * channels).to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+
+        for s in samples {
+            bytes.extend_from_slice(&s.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn reads_stereo_wav() {
+        let bytes = wav_16_bit(2, &[16384, -16384, 0, 32767]);
+        let mut reader = AudioFileReader::new(Cursor::new(bytes)).unwrap();
+
+        let info = reader.info();
+        assert_eq!(info.num_channels, 2);
+        assert_eq!(info.num_frames, 2);
+        assert!((info.sample_rate - 44100.0).abs() < f64::EPSILON);
+
+        let mut out = [[0.0; 2]; 4];
+        assert_eq!(reader.read_frames(0, &mut out).unwrap(), 2);
+        assert!((out[0][0] - 0.5).abs() < 1e-9);
+        assert!((out[0][1] + 0.5).abs() < 1e-9);
+        assert!((out[1][1] - 32767.0 / 32768.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mono_wav_is_copied_to_both_channels() {
+        let bytes = wav_16_bit(1, &[0, 8192, -8192]);
+        let mut reader = AudioFileReader::new(Cursor::new(bytes)).unwrap();
+
+        let mut out = [[0.0; 2]; 2];
+        assert_eq!(reader.read_frames(1, &mut out).unwrap(), 2);
+        assert!((out[0][0] - 0.25).abs() < 1e-9);
+        assert!((out[0][1] - 0.25).abs() < 1e-9);
+        assert!((out[1][0] + 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reads_24_bit_aiff() {
+        let mut bytes = Vec::new();
+        let samples: [i32; 2] = [4_194_304, -4_194_304]; // +-0.5
+
+        bytes.extend_from_slice(b"FORM");
+        bytes.extend_from_slice(&(4 + 26 + 8 + 8 + 6u32).to_be_bytes());
+        bytes.extend_from_slice(b"AIFFCOMM");
+        bytes.extend_from_slice(&18u32.to_be_bytes());
+        bytes.extend_from_slice(&1u16.to_be_bytes());
+        bytes.extend_from_slice(&2u32.to_be_bytes());
+        bytes.extend_from_slice(&24u16.to_be_bytes());
+        // 48000 Hz as an 80-bit extended float
+        bytes.extend_from_slice(&[0x40, 0x0E, 0xBB, 0x80, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(b"SSND");
+        bytes.extend_from_slice(&(8 + 6u32).to_be_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+
+        for s in samples {
+            bytes.extend_from_slice(&s.to_be_bytes()[1..]);
+        }
+
+        let mut reader = AudioFileReader::new(Cursor::new(bytes)).unwrap();
+        let info = reader.info();
+        assert_eq!(info.num_frames, 2);
+        assert!((info.sample_rate - 48000.0).abs() < f64::EPSILON);
+
+        let mut out = [[0.0; 2]; 2];
+        reader.read_frames(0, &mut out).unwrap();
+        assert!((out[0][0] - 0.5).abs() < 1e-9);
+        assert!((out[1][1] + 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let bytes = b"OggS\0\0\0\0\0\0\0\0\0\0\0\0".to_vec();
+        assert!(AudioFileReader::new(Cursor::new(bytes)).is_err());
+    }
+}
@@ -8,7 +8,7 @@ pub mod sine;
 pub mod square;
 pub mod tri;
 
-pub use noise_osc::NoiseOsc;
+pub use noise_osc::{ColoredNoise, NoiseColor, NoiseOsc};
 pub use phasor::Phasor;
 pub use sine::SineOsc;
 pub use square::SquareOsc;
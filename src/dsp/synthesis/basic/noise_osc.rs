@@ -1,7 +1,20 @@
-//! Primitive white noise oscillator.
+//! Primitive white and colored noise oscillators.
 
 use super::*;
 use crate::prelude::*;
+use std::f64::consts::TAU;
+use std::fmt::{Display, Formatter};
+
+/// The number of rows summed by the Voss-McCartney pink noise algorithm,
+/// which sets the lowest octave that is shaped.
+const NUM_PINK_ROWS: usize = 16;
+/// The frequency below which brown noise is flattened, so that it doesn't
+/// drift away from zero.
+const BROWN_CUTOFF_HZ: f64 = 10.0;
+/// Approximately matches the level of blue noise to white noise.
+const BLUE_GAIN: f64 = 1.6;
+/// The average number of impulses per second in velvet noise.
+const VELVET_DENSITY: f64 = 2000.0;
 
 /// A white noise oscillator.
 #[derive(Debug, Clone, Copy)]
@@ -13,3 +26,248 @@ impl NoiseOsc {
         random_f64().mul_add(2.0, -1.0)
     }
 }
+
+// *** *** *** //
+
+/// The spectral slope of a `ColoredNoise` generator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NoiseColor {
+    /// Flat.
+    #[default]
+    White,
+    /// -3 dB per octave.
+    Pink,
+    /// -6 dB per octave.
+    Brown,
+    /// +3 dB per octave.
+    Blue,
+    /// Sparse, randomly placed impulses of random sign, which sound smoother
+    /// than white noise.
+    Velvet,
+}
+
+impl NoiseColor {
+    /// Parses a `NoiseColor` from its name, e.g. `"pink"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "white" => Some(Self::White),
+            "pink" => Some(Self::Pink),
+            "brown" => Some(Self::Brown),
+            "blue" => Some(Self::Blue),
+            "velvet" => Some(Self::Velvet),
+            _ => None,
+        }
+    }
+}
+
+impl Display for NoiseColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::White => write!(f, "White"),
+            Self::Pink => write!(f, "Pink"),
+            Self::Brown => write!(f, "Brown"),
+            Self::Blue => write!(f, "Blue"),
+            Self::Velvet => write!(f, "Velvet"),
+        }
+    }
+}
+
+/// The filter state of one channel of colored noise.
+#[derive(Debug, Clone, Copy, Default)]
+struct NoiseChannel {
+    pink_rows: [f64; NUM_PINK_ROWS],
+    pink_sum: f64,
+    pink_counter: u32,
+    prev_pink: f64,
+
+    brown: f64,
+
+    /// The sample in the current velvet period which has the impulse.
+    velvet_impulse_pos: usize,
+    velvet_pos: usize,
+}
+
+impl NoiseChannel {
+    /// Produces pink noise with the Voss-McCartney algorithm, which updates
+    /// one row per sample, where row `n` is updated every `2^(n + 1)`
+    /// samples.
+    fn pink(&mut self) -> f64 {
+        self.pink_counter = self.pink_counter.wrapping_add(1);
+        let row = self.pink_counter.trailing_zeros() as usize;
+
+        if row < NUM_PINK_ROWS {
+            let new = NoiseOsc::process();
+            self.pink_sum += new - self.pink_rows[row];
+            self.pink_rows[row] = new;
+        }
+
+        // the extra white sample fills in the top octave
+        (self.pink_sum + NoiseOsc::process())
+            / ((NUM_PINK_ROWS + 1) as f64).sqrt()
+    }
+
+    /// Produces brown noise by leaky integration of white noise. `leak` is
+    /// the integrator's coefficient.
+    fn brown(&mut self, leak: f64) -> f64 {
+        self.brown = leak.mul_add(self.brown, NoiseOsc::process());
+
+        // scales the integrator's level back to that of white noise
+        self.brown * (1.0 - leak * leak).sqrt()
+    }
+
+    /// Produces blue noise by differentiating pink noise.
+    fn blue(&mut self) -> f64 {
+        let pink = self.pink();
+        let out = (pink - self.prev_pink) * BLUE_GAIN;
+        self.prev_pink = pink;
+
+        out
+    }
+
+    /// Produces velvet noise, with one impulse in every `period` samples.
+    fn velvet(&mut self, period: usize) -> f64 {
+        if self.velvet_pos == 0 {
+            self.velvet_impulse_pos = random_range(0, period);
+        }
+
+        let out = if self.velvet_pos != self.velvet_impulse_pos {
+            0.0
+        }
+        else if random_f64() < 0.5 {
+            -1.0
+        }
+        else {
+            1.0
+        };
+
+        self.velvet_pos = (self.velvet_pos + 1) % period;
+
+        out
+    }
+}
+
+/// A stereo noise generator with a selectable spectral slope.
+#[derive(Debug, Clone)]
+pub struct ColoredNoise {
+    color: NoiseColor,
+    channels: [NoiseChannel; 2],
+
+    brown_leak: f64,
+    velvet_period: usize,
+}
+
+impl ColoredNoise {
+    pub fn new(color: NoiseColor, sample_rate: f64) -> Self {
+        let mut noise = Self {
+            color,
+            channels: [NoiseChannel::default(); 2],
+            brown_leak: 0.0,
+            velvet_period: 1,
+        };
+
+        noise.set_sample_rate(sample_rate);
+        noise
+    }
+
+    pub fn set_color(&mut self, color: NoiseColor) {
+        self.color = color;
+    }
+
+    pub const fn color(&self) -> NoiseColor {
+        self.color
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.brown_leak = (-TAU * BROWN_CUTOFF_HZ / sample_rate).exp();
+        self.velvet_period =
+            ((sample_rate / VELVET_DENSITY).round() as usize).max(1);
+    }
+
+    fn process_channel(&mut self, channel: usize) -> f64 {
+        let ch = &mut self.channels[channel];
+
+        match self.color {
+            NoiseColor::White => NoiseOsc::process(),
+            NoiseColor::Pink => ch.pink(),
+            NoiseColor::Brown => ch.brown(self.brown_leak),
+            NoiseColor::Blue => ch.blue(),
+            NoiseColor::Velvet => ch.velvet(self.velvet_period),
+        }
+    }
+}
+
+impl GeneratorProcessor for ColoredNoise {
+    /// Produces independent noise for each channel.
+    fn process(&mut self) -> (f64, f64) {
+        (self.process_channel(0), self.process_channel(1))
+    }
+
+    /// Noise has no frequency, so this does nothing.
+    fn set_freq(&mut self, freq_hz: f64, sample_rate: f64) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+    const NUM_SAMPLES: usize = 48000;
+
+    fn render(color: NoiseColor) -> Vec<f64> {
+        let mut noise = ColoredNoise::new(color, SAMPLE_RATE);
+
+        (0..NUM_SAMPLES).map(|_| noise.process().0).collect()
+    }
+
+    fn rms(signal: &[f64]) -> f64 {
+        (signal.iter().map(|x| x * x).sum::<f64>() / signal.len() as f64)
+            .sqrt()
+    }
+
+    /// The ratio of the signal's high-frequency energy (from its first
+    /// difference) to its total energy.
+    fn brightness(signal: &[f64]) -> f64 {
+        let diff: Vec<f64> = signal.windows(2).map(|w| w[1] - w[0]).collect();
+
+        rms(&diff) / rms(signal)
+    }
+
+    #[test]
+    fn test_colors_are_near_white_level() {
+        let white = rms(&render(NoiseColor::White));
+
+        for color in [NoiseColor::Pink, NoiseColor::Brown, NoiseColor::Blue] {
+            let level = rms(&render(color));
+
+            assert!(
+                (0.5..2.0).contains(&(level / white)),
+                "{color} noise had an RMS of {level}, but white had {white}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_colors_are_ordered_by_brightness() {
+        let order = [
+            NoiseColor::Brown,
+            NoiseColor::Pink,
+            NoiseColor::White,
+            NoiseColor::Blue,
+        ];
+
+        let values: Vec<f64> =
+            order.iter().map(|color| brightness(&render(*color))).collect();
+
+        assert!(values.windows(2).all(|w| w[0] < w[1]), "{values:?}");
+    }
+
+    #[test]
+    fn test_velvet_is_sparse() {
+        let velvet = render(NoiseColor::Velvet);
+        let num_impulses = velvet.iter().filter(|x| **x != 0.0).count();
+
+        // one impulse per period
+        assert_eq!(num_impulses, NUM_SAMPLES / 24);
+        assert!(velvet.iter().all(|x| x.abs() <= 1.0));
+    }
+}
@@ -21,6 +21,11 @@ impl SineOsc {
         }
     }
 
+    /// Sets the phase of the oscillator, from `0.0` to `1.0`.
+    pub fn set_phase(&mut self, phase: f64) {
+        self.phase = (phase - phase.floor()) * TAU;
+    }
+
     fn increment_phase(&mut self) {
         self.phase += self.phase_increment;
 
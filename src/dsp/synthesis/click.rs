@@ -0,0 +1,84 @@
+//! A short click generator, intended for metronomes.
+
+use super::*;
+use crate::prelude::*;
+
+const DEFAULT_CLICK_FREQ_HZ: f64 = 1500.0;
+const DEFAULT_DECAY_TIME_MS: f64 = 25.0;
+
+/// A short, exponentially-decaying sine "click".
+///
+/// The click is silent until [`trigger()`](Self::trigger) is called, after
+/// which it decays to silence over roughly its decay time.
+#[derive(Debug, Clone, Copy)]
+pub struct ClickGenerator {
+    osc: SineOsc,
+    envelope: f64,
+    decay_coef: f64,
+    amplitude: f64,
+
+    sample_rate: f64,
+}
+
+impl ClickGenerator {
+    /// Creates a new, silent `ClickGenerator`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sample_rate` is negative.
+    pub fn new(sample_rate: f64) -> Self {
+        assert!(sample_rate.is_sign_positive());
+
+        let mut s = Self {
+            osc: SineOsc::new(DEFAULT_CLICK_FREQ_HZ, sample_rate),
+            envelope: 0.0,
+            decay_coef: 0.0,
+            amplitude: 1.0,
+            sample_rate,
+        };
+
+        s.set_decay_time_ms(DEFAULT_DECAY_TIME_MS);
+        s
+    }
+
+    /// Triggers a new click at `freq_hz` with `amplitude` gain.
+    pub fn trigger(&mut self, freq_hz: f64, amplitude: f64) {
+        self.osc = SineOsc::new(
+            freq_hz.min(self.sample_rate / 2.0),
+            self.sample_rate,
+        );
+        self.amplitude = amplitude;
+        self.envelope = 1.0;
+    }
+
+    /// Sets the time it takes for a click to decay by 60 dB.
+    pub fn set_decay_time_ms(&mut self, time_ms: f64) {
+        let samples = (time_ms * 0.001 * self.sample_rate).max(1.0);
+        // -60 dB after `samples` samples
+        self.decay_coef = db_to_level(-60.0).powf(samples.recip());
+    }
+
+    /// Whether the click is currently audible.
+    pub fn is_active(&self) -> bool {
+        self.envelope > MINUS_INFINITY_GAIN
+    }
+
+    /// Produces the next sample of the click.
+    pub fn process(&mut self) -> f64 {
+        if !self.is_active() {
+            return 0.0;
+        }
+
+        let (out, _) = self.osc.process();
+        let out = out * self.envelope * self.amplitude;
+        self.envelope *= self.decay_coef;
+
+        out
+    }
+}
+
+impl Default for ClickGenerator {
+    fn default() -> Self {
+        Self::new(unsafe { SAMPLE_RATE })
+    }
+}
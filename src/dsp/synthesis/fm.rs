@@ -0,0 +1,280 @@
+//! Frequency modulation (FM) synthesis with a routable operator graph.
+
+use super::*;
+use std::f64::consts::TAU;
+
+/// The fewest operators an `FmSynth` can use.
+pub const MIN_FM_OPERATORS: usize = 4;
+/// The most operators an `FmSynth` can use.
+pub const MAX_FM_OPERATORS: usize = 6;
+
+/// The parameters shared by each `FmSynth`.
+///
+/// The modulation matrix is indexed as `[source][destination]`, so any
+/// operator may modulate any other (including itself, for feedback).
+/// Modulation is applied from the previous sample's operator outputs.
+#[derive(Debug, Clone, Copy)]
+pub struct FmParameters {
+    /// The number of active operators.
+    pub num_operators: usize,
+    /// The frequency ratio of each operator to the note's frequency.
+    pub ratios: [f64; MAX_FM_OPERATORS],
+    /// The modulation index of each operator, i.e. its peak phase deviation
+    /// in radians when it modulates another operator.
+    pub indices: [f64; MAX_FM_OPERATORS],
+    /// The amount that each operator modulates each other operator.
+    pub matrix: [[f64; MAX_FM_OPERATORS]; MAX_FM_OPERATORS],
+    /// The level of each operator in the output.
+    pub output_levels: [f64; MAX_FM_OPERATORS],
+    /// The envelope of each operator.
+    pub envelopes: [AdsrParameters; MAX_FM_OPERATORS],
+}
+
+impl FmParameters {
+    /// Sets the number of active operators, clamped between
+    /// `MIN_FM_OPERATORS` and `MAX_FM_OPERATORS`.
+    pub fn set_num_operators(&mut self, num_operators: usize) {
+        self.num_operators =
+            num_operators.clamp(MIN_FM_OPERATORS, MAX_FM_OPERATORS);
+    }
+
+    /// Sets how much `source` modulates `destination`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either operator is out of range.
+    pub fn set_modulation(
+        &mut self,
+        source: usize,
+        destination: usize,
+        amount: f64,
+    ) {
+        self.matrix[source][destination] = amount;
+    }
+}
+
+impl Default for FmParameters {
+    /// Two stacks of one modulator and one carrier.
+    fn default() -> Self {
+        let mut matrix = [[0.0; MAX_FM_OPERATORS]; MAX_FM_OPERATORS];
+        matrix[1][0] = 1.0;
+        matrix[3][2] = 1.0;
+
+        Self {
+            num_operators: MIN_FM_OPERATORS,
+            ratios: [1.0, 2.0, 1.0, 3.0, 1.0, 1.0],
+            indices: [0.0, 1.5, 0.0, 0.8, 0.0, 0.0],
+            matrix,
+            output_levels: [0.5, 0.0, 0.5, 0.0, 0.0, 0.0],
+            envelopes: [AdsrParameters::default(); MAX_FM_OPERATORS],
+        }
+    }
+}
+
+// *** *** *** //
+
+/// A sine operator with its own envelope.
+#[derive(Debug, Clone)]
+pub struct FmOperator {
+    phase: f64,
+    ratio: f64,
+    index: f64,
+    envelope: AdsrEnvelope,
+}
+
+impl FmOperator {
+    fn new(sample_rate: f64) -> Self {
+        Self {
+            phase: 0.0,
+            ratio: 1.0,
+            index: 0.0,
+            envelope: AdsrEnvelope::new(sample_rate),
+        }
+    }
+
+    /// Produces the next sample with `modulation` (in radians) added to its
+    /// phase, and then advances the phase.
+    fn process(&mut self, base_increment: f64, modulation: f64) -> f64 {
+        let env = self.envelope.next();
+        // an envelope which has never been triggered holds its initial value
+        let env = if self.envelope.is_idle() {
+            0.0
+        }
+        else {
+            env
+        };
+        let out = TAU.mul_add(self.phase, modulation).sin() * env;
+
+        self.phase += base_increment * self.ratio;
+        self.phase -= self.phase.floor();
+
+        out
+    }
+}
+
+// *** *** *** //
+
+/// An FM synthesizer of 4 to 6 sine operators.
+#[derive(Debug, Clone)]
+pub struct FmSynth {
+    operators: [FmOperator; MAX_FM_OPERATORS],
+    num_operators: usize,
+    matrix: [[f64; MAX_FM_OPERATORS]; MAX_FM_OPERATORS],
+    output_levels: [f64; MAX_FM_OPERATORS],
+
+    /// The previous output of each operator.
+    last_outputs: [f64; MAX_FM_OPERATORS],
+    base_increment: f64,
+}
+
+impl FmSynth {
+    pub fn new(
+        parameters: &FmParameters,
+        freq_hz: f64,
+        sample_rate: f64,
+    ) -> Self {
+        debug_assert!(0.0 < freq_hz && freq_hz <= sample_rate / 2.0);
+
+        let mut synth = Self {
+            operators: std::array::from_fn(|_| FmOperator::new(sample_rate)),
+            num_operators: MIN_FM_OPERATORS,
+            matrix: [[0.0; MAX_FM_OPERATORS]; MAX_FM_OPERATORS],
+            output_levels: [0.0; MAX_FM_OPERATORS],
+            last_outputs: [0.0; MAX_FM_OPERATORS],
+            base_increment: freq_hz / sample_rate,
+        };
+
+        synth.set_parameters(parameters);
+        synth
+    }
+
+    /// Updates the ratios, indices, routing, output levels and envelopes of
+    /// the operators.
+    pub fn set_parameters(&mut self, parameters: &FmParameters) {
+        self.num_operators = parameters
+            .num_operators
+            .clamp(MIN_FM_OPERATORS, MAX_FM_OPERATORS);
+        self.matrix = parameters.matrix;
+        self.output_levels = parameters.output_levels;
+
+        for (i, op) in self.operators.iter_mut().enumerate() {
+            op.ratio = parameters.ratios[i];
+            op.index = parameters.indices[i];
+            *op.envelope.parameters_mut() = parameters.envelopes[i];
+        }
+    }
+
+    /// Sets the trigger of each operator's envelope.
+    pub fn set_trigger(&mut self, trigger: bool) {
+        for op in &mut self.operators {
+            op.envelope.set_trigger(trigger);
+        }
+    }
+}
+
+impl GeneratorProcessor for FmSynth {
+    /// Creates two, identical FM samples.
+    fn process(&mut self) -> (f64, f64) {
+        let n = self.num_operators;
+        let mut outputs = [0.0; MAX_FM_OPERATORS];
+        let mut out = 0.0;
+
+        for (dst, output) in outputs.iter_mut().enumerate().take(n) {
+            let modulation: f64 = (0..n)
+                .map(|src| {
+                    self.matrix[src][dst]
+                        * self.operators[src].index
+                        * self.last_outputs[src]
+                })
+                .sum();
+
+            *output =
+                self.operators[dst].process(self.base_increment, modulation);
+            out += *output * self.output_levels[dst];
+        }
+
+        self.last_outputs = outputs;
+
+        (out, out)
+    }
+
+    /// Sets the frequency of the FM synth, which each operator's ratio is
+    /// relative to.
+    fn set_freq(&mut self, freq_hz: f64, sample_rate: f64) {
+        self.base_increment = freq_hz / sample_rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn carrier_only() -> FmParameters {
+        let mut params = FmParameters {
+            matrix: [[0.0; MAX_FM_OPERATORS]; MAX_FM_OPERATORS],
+            output_levels: [0.0; MAX_FM_OPERATORS],
+            ..FmParameters::default()
+        };
+        params.output_levels[0] = 1.0;
+
+        params
+    }
+
+    #[test]
+    fn test_silent_until_triggered() {
+        let mut fm = FmSynth::new(&FmParameters::default(), 440.0, 48000.0);
+
+        assert!((0..64).all(|_| fm.process().0 == 0.0));
+
+        fm.set_trigger(true);
+        assert!((0..1024).any(|_| fm.process().0 != 0.0));
+    }
+
+    #[test]
+    fn test_silent_after_release() {
+        let mut fm = FmSynth::new(&FmParameters::default(), 440.0, 48000.0);
+
+        fm.set_trigger(true);
+        for _ in 0..4800 {
+            fm.process();
+        }
+
+        // the default release is 50 ms
+        fm.set_trigger(false);
+        for _ in 0..4800 {
+            fm.process();
+        }
+
+        assert!((0..64).all(|_| fm.process().0 == 0.0));
+    }
+
+    #[test]
+    fn test_modulation_adds_harmonics() {
+        let params = carrier_only();
+        let mut modulated = params;
+        modulated.set_modulation(1, 0, 1.0);
+        modulated.indices[1] = 5.0;
+
+        let mut plain = FmSynth::new(&params, 440.0, 48000.0);
+        let mut fm = FmSynth::new(&modulated, 440.0, 48000.0);
+        plain.set_trigger(true);
+        fm.set_trigger(true);
+
+        let max_diff = (0..4800)
+            .map(|_| (plain.process().0 - fm.process().0).abs())
+            .fold(0.0, f64::max);
+
+        assert!(max_diff > 0.1);
+    }
+
+    #[test]
+    fn test_operator_count_is_clamped() {
+        let mut params = FmParameters::default();
+
+        params.set_num_operators(2);
+        assert_eq!(params.num_operators, MIN_FM_OPERATORS);
+
+        params.set_num_operators(12);
+        assert_eq!(params.num_operators, MAX_FM_OPERATORS);
+    }
+}
@@ -3,18 +3,22 @@
 use super::*;
 
 /// All the types of signal generators available.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Generator {
     /// A basic sine wave generator.
     Sine(SineOsc),
-    /// A basic triangle wave generator.
-    Tri(TriOsc),
-    /// A basic saw wave generator.
-    Saw(Phasor),
-    /// A basic square wave generator.
-    Square(SquareOsc),
-    /// A basic white noise generator.
-    Noise,
+    /// An anti-aliased triangle wave generator.
+    Tri(PolyBlepTri),
+    /// An anti-aliased saw wave generator.
+    Saw(PolyBlepSaw),
+    /// An anti-aliased square wave generator.
+    Square(PolyBlepSquare),
+    /// An FM synthesizer with 4 to 6 operators.
+    Fm(Box<FmSynth>),
+    /// A Karplus-Strong plucked string.
+    Pluck(KarplusStrong),
+    /// A white or colored noise generator.
+    Noise(Box<ColoredNoise>),
 }
 
 impl Generator {
@@ -24,7 +28,9 @@ impl Generator {
             Self::Tri(gen) => gen.process(),
             Self::Saw(gen) => gen.process(),
             Self::Square(gen) => gen.process(),
-            Self::Noise => (NoiseOsc::process(), NoiseOsc::process()),
+            Self::Fm(gen) => gen.process(),
+            Self::Pluck(gen) => gen.process(),
+            Self::Noise(gen) => gen.process(),
         }
     }
 
@@ -34,7 +40,31 @@ impl Generator {
             Self::Tri(gen) => gen.set_freq(freq_hz, sample_rate),
             Self::Saw(gen) => gen.set_freq(freq_hz, sample_rate),
             Self::Square(gen) => gen.set_freq(freq_hz, sample_rate),
-            Self::Noise => {}
+            Self::Fm(gen) => gen.set_freq(freq_hz, sample_rate),
+            Self::Pluck(gen) => gen.set_freq(freq_hz, sample_rate),
+            Self::Noise(gen) => gen.set_freq(freq_hz, sample_rate),
+        }
+    }
+
+    /// Sets the phase of the generator's oscillator, from `0.0` to `1.0`.
+    /// Generators without a single oscillator are unaffected.
+    pub fn set_phase(&mut self, phase: f64) {
+        match self {
+            Self::Sine(gen) => gen.set_phase(phase),
+            Self::Tri(gen) => gen.set_phase(phase),
+            Self::Saw(gen) => gen.set_phase(phase),
+            Self::Square(gen) => gen.set_phase(phase),
+            _ => {}
+        }
+    }
+
+    /// Sets the trigger of the generator's internal envelopes, if it has any.
+    /// Strings are plucked when triggered.
+    pub fn set_trigger(&mut self, trigger: bool) {
+        match self {
+            Self::Fm(gen) => gen.set_trigger(trigger),
+            Self::Pluck(gen) if trigger => gen.pluck(),
+            _ => {}
         }
     }
 }
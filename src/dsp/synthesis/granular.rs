@@ -0,0 +1,315 @@
+//! Granular synthesis of recently captured audio.
+
+use super::*;
+use crate::prelude::*;
+use std::f64::consts::TAU;
+
+/// The maximum number of grains which may play at once. Grains which would
+/// exceed this are skipped.
+pub const MAX_GRAINS: usize = 64;
+
+/// The shortest grain size.
+pub const MIN_GRAIN_SIZE_MS: f64 = 5.0;
+/// The longest grain size.
+pub const MAX_GRAIN_SIZE_MS: f64 = 1000.0;
+/// The most grains which may be spawned per second.
+pub const MAX_GRAIN_DENSITY: f64 = 200.0;
+/// The widest random pitch offset of each grain, in semitones.
+pub const MAX_PITCH_SPRAY: f64 = 24.0;
+
+const DEFAULT_DENSITY: f64 = 20.0;
+const DEFAULT_GRAIN_SIZE_MS: f64 = 80.0;
+
+/// A single windowed playback of a region of the capture buffer.
+#[derive(Clone, Copy, Debug, Default)]
+struct Grain {
+    active: bool,
+    /// The read position in the capture buffer, in samples.
+    read_pos: f64,
+    /// The playback rate of the grain.
+    increment: f64,
+    age: usize,
+    length: usize,
+}
+
+/// Records the last few seconds of a signal into a ring buffer, and plays
+/// it back as overlapping grains.
+///
+/// `position` is how far back in the recording grains start, from `0.0`
+/// (the newest audio) to `1.0` (the oldest).
+#[derive(Clone, Debug)]
+pub struct GranularEngine {
+    buffer_l: Vec<f64>,
+    buffer_r: Vec<f64>,
+    write_pos: usize,
+    /// The number of samples recorded, up to the size of the buffer.
+    num_recorded: usize,
+
+    grains: [Grain; MAX_GRAINS],
+    /// Counts up to the next grain, which is spawned at `1.0`.
+    spawn_phase: f64,
+
+    density: f64,
+    size_ms: f64,
+    pitch_semitones: f64,
+    pitch_spray: f64,
+    position: f64,
+    position_spray: f64,
+
+    sample_rate: f64,
+}
+
+impl GranularEngine {
+    /// Creates an engine which holds the last `capture_secs` seconds of
+    /// whatever is recorded.
+    pub fn new(capture_secs: f64, sample_rate: f64) -> Self {
+        let len = (capture_secs * sample_rate).ceil().max(1.0) as usize;
+
+        Self {
+            buffer_l: vec![0.0; len],
+            buffer_r: vec![0.0; len],
+            write_pos: 0,
+            num_recorded: 0,
+
+            grains: [Grain::default(); MAX_GRAINS],
+            spawn_phase: 0.0,
+
+            density: DEFAULT_DENSITY,
+            size_ms: DEFAULT_GRAIN_SIZE_MS,
+            pitch_semitones: 0.0,
+            pitch_spray: 0.0,
+            position: 0.0,
+            position_spray: 0.0,
+
+            sample_rate,
+        }
+    }
+
+    /// Sets how many grains are spawned per second, clamped to
+    /// `[0.0, MAX_GRAIN_DENSITY]`.
+    pub fn set_density(&mut self, grains_per_sec: f64) {
+        self.density = grains_per_sec.clamp(0.0, MAX_GRAIN_DENSITY);
+    }
+
+    /// Sets the length of new grains, clamped to
+    /// `[MIN_GRAIN_SIZE_MS, MAX_GRAIN_SIZE_MS]`.
+    pub fn set_size_ms(&mut self, size_ms: f64) {
+        self.size_ms = size_ms.clamp(MIN_GRAIN_SIZE_MS, MAX_GRAIN_SIZE_MS);
+    }
+
+    /// Sets the pitch of new grains in semitones.
+    pub fn set_pitch(&mut self, semitones: f64) {
+        self.pitch_semitones = semitones;
+    }
+
+    /// Sets the widest random pitch offset of new grains in semitones,
+    /// clamped to `[0.0, MAX_PITCH_SPRAY]`.
+    pub fn set_pitch_spray(&mut self, semitones: f64) {
+        self.pitch_spray = semitones.clamp(0.0, MAX_PITCH_SPRAY);
+    }
+
+    /// Sets where new grains start in the recording, clamped to
+    /// `[0.0, 1.0]`.
+    pub fn set_position(&mut self, position: f64) {
+        self.position = position.clamp(0.0, 1.0);
+    }
+
+    /// Sets the widest random offset of new grains' positions, clamped to
+    /// `[0.0, 1.0]`.
+    pub fn set_position_spray(&mut self, spray: f64) {
+        self.position_spray = spray.clamp(0.0, 1.0);
+    }
+
+    /// Returns the length of the capture buffer in seconds.
+    pub fn capture_secs(&self) -> f64 {
+        self.buffer_l.len() as f64 / self.sample_rate
+    }
+
+    /// Records one stereo sample into the capture buffer.
+    pub fn record(&mut self, left: f64, right: f64) {
+        self.buffer_l[self.write_pos] = left;
+        self.buffer_r[self.write_pos] = right;
+
+        self.write_pos = (self.write_pos + 1) % self.buffer_l.len();
+        self.num_recorded = (self.num_recorded + 1).min(self.buffer_l.len());
+    }
+
+    /// Records the interleaved stereo `buffer` from `block_start` to
+    /// `block_end`, and then adds the grains' output to it.
+    pub fn process_block(
+        &mut self,
+        buffer: &mut [f64],
+        block_start: usize,
+        block_end: usize,
+    ) {
+        for sample_idx in block_start..block_end {
+            self.record(buffer[sample_idx * 2], buffer[sample_idx * 2 + 1]);
+
+            let (l, r) = self.process();
+
+            buffer[sample_idx * 2] += l;
+            buffer[sample_idx * 2 + 1] += r;
+        }
+    }
+
+    /// Stops all grains and clears the recording.
+    pub fn clear(&mut self) {
+        self.buffer_l.fill(0.0);
+        self.buffer_r.fill(0.0);
+        self.write_pos = 0;
+        self.num_recorded = 0;
+
+        self.grains = [Grain::default(); MAX_GRAINS];
+        self.spawn_phase = 0.0;
+    }
+
+    /// Starts a new grain, if there is enough audio recorded for it and a
+    /// free grain slot.
+    fn spawn_grain(&mut self) {
+        let Some(grain) = self.grains.iter_mut().find(|g| !g.active)
+        else {
+            return;
+        };
+
+        let length = (self.size_ms * 0.001 * self.sample_rate) as usize;
+
+        let pitch = self.pitch_semitones
+            + self.pitch_spray * rand::random_range(-1.0..=1.0);
+        let increment = 2.0f64.powf(pitch / 12.0);
+
+        // faster grains catch up with the write position, so they must start
+        // further back, and slower grains fall behind the oldest audio as it
+        // is overwritten, so they must start further forward
+        let length_f = length as f64;
+        let min_delay = length_f.mul_add((increment - 1.0).max(0.0), 2.0);
+        let max_delay = length_f.mul_add(
+            -(1.0 - increment).max(0.0),
+            self.num_recorded as f64 - 2.0,
+        );
+
+        if length == 0 || max_delay < min_delay {
+            return;
+        }
+
+        let position = (self.position
+            + self.position_spray * rand::random_range(-1.0..=1.0))
+        .clamp(0.0, 1.0);
+        let delay = (max_delay - min_delay).mul_add(position, min_delay);
+
+        let len = self.buffer_l.len() as f64;
+
+        *grain = Grain {
+            active: true,
+            read_pos: (self.write_pos as f64 - delay).rem_euclid(len),
+            increment,
+            age: 0,
+            length,
+        };
+    }
+
+    /// Reads the capture buffer at `pos` with linear interpolation.
+    fn read(&self, pos: f64) -> (f64, f64) {
+        let len = self.buffer_l.len();
+        let idx = pos as usize % len;
+        let next = (idx + 1) % len;
+        let t = pos.fract();
+
+        (
+            lerp(self.buffer_l[idx], self.buffer_l[next], t),
+            lerp(self.buffer_r[idx], self.buffer_r[next], t),
+        )
+    }
+}
+
+impl GeneratorProcessor for GranularEngine {
+    /// Produces the sum of all active grains. Nothing is recorded.
+    fn process(&mut self) -> (f64, f64) {
+        self.spawn_phase += self.density / self.sample_rate;
+
+        while self.spawn_phase >= 1.0 {
+            self.spawn_phase -= 1.0;
+            self.spawn_grain();
+        }
+
+        let len = self.buffer_l.len() as f64;
+        let (mut out_l, mut out_r) = (0.0, 0.0);
+
+        for i in 0..MAX_GRAINS {
+            let grain = self.grains[i];
+
+            if !grain.active {
+                continue;
+            }
+
+            // hann window
+            let window = 0.5
+                - 0.5 * (TAU * grain.age as f64 / grain.length as f64).cos();
+            let (l, r) = self.read(grain.read_pos);
+
+            out_l += l * window;
+            out_r += r * window;
+
+            let grain = &mut self.grains[i];
+            grain.read_pos = (grain.read_pos + grain.increment) % len;
+            grain.age += 1;
+            grain.active = grain.age < grain.length;
+        }
+
+        // keeps the level consistent as grains overlap more
+        let overlap = (self.density * self.size_ms * 0.001).max(1.0);
+        let gain = overlap.sqrt().recip();
+
+        (out_l * gain, out_r * gain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn record_sine(engine: &mut GranularEngine, num_samples: usize) {
+        for i in 0..num_samples {
+            let x = (TAU * 440.0 * i as f64 / SAMPLE_RATE).sin();
+            engine.record(x, x);
+        }
+    }
+
+    fn peak(engine: &mut GranularEngine, num_samples: usize) -> f64 {
+        (0..num_samples)
+            .map(|_| engine.process().0.abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_silent_without_recording() {
+        let mut engine = GranularEngine::new(1.0, SAMPLE_RATE);
+        engine.set_density(100.0);
+
+        assert_eq!(peak(&mut engine, 4800), 0.0);
+    }
+
+    #[test]
+    fn test_grains_play_recorded_audio() {
+        let mut engine = GranularEngine::new(1.0, SAMPLE_RATE);
+        engine.set_density(100.0);
+        engine.set_pitch_spray(12.0);
+        engine.set_position(0.5);
+        engine.set_position_spray(0.5);
+
+        record_sine(&mut engine, 48000);
+
+        let peak = peak(&mut engine, 4800);
+        assert!(0.1 < peak && peak.is_finite(), "peak was {peak}");
+    }
+
+    #[test]
+    fn test_no_grains_at_zero_density() {
+        let mut engine = GranularEngine::new(1.0, SAMPLE_RATE);
+        engine.set_density(0.0);
+        record_sine(&mut engine, 48000);
+
+        assert_eq!(peak(&mut engine, 4800), 0.0);
+    }
+}
@@ -0,0 +1,229 @@
+//! Karplus-Strong plucked string synthesis.
+
+use super::*;
+use crate::prelude::*;
+
+/// The lowest frequency which a string can be tuned to.
+pub const MIN_STRING_FREQ_HZ: f64 = 20.0;
+/// The shortest time for a string to decay by 60 dB.
+pub const MIN_PLUCK_DECAY_SECS: f64 = 0.05;
+/// The longest time for a string to decay by 60 dB.
+pub const MAX_PLUCK_DECAY_SECS: f64 = 20.0;
+
+/// The damping filter's coefficient at zero brightness.
+const MAX_DAMPING: f64 = 0.9;
+
+/// The parameters shared by each `KarplusStrong` string.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PluckParameters {
+    /// How bright the pluck and the string's ring are, from `0.0` to `1.0`.
+    pub brightness: f64,
+    /// The time for the string to decay by 60 dB, in seconds.
+    pub decay_secs: f64,
+}
+
+impl Default for PluckParameters {
+    fn default() -> Self {
+        Self { brightness: 0.5, decay_secs: 2.0 }
+    }
+}
+
+/// A plucked string, modeled as a delay line whose output is fed back through
+/// a damping filter.
+///
+/// The string is silent until it is plucked, which fills the delay line with
+/// a burst of filtered noise.
+#[derive(Debug, Clone)]
+pub struct KarplusStrong {
+    buffer: Vec<f64>,
+    write_pos: usize,
+
+    /// The length of the delay line, in samples, excluding the delay of the
+    /// damping filter.
+    delay: f64,
+    /// The gain applied each time around the loop.
+    loop_gain: f64,
+    /// The coefficient of the one-pole damping filter.
+    damping: f64,
+    filter_state: f64,
+
+    parameters: PluckParameters,
+    freq_hz: f64,
+    sample_rate: f64,
+}
+
+impl KarplusStrong {
+    pub fn new(
+        parameters: &PluckParameters,
+        freq_hz: f64,
+        sample_rate: f64,
+    ) -> Self {
+        debug_assert!(0.0 < freq_hz && freq_hz <= sample_rate / 2.0);
+
+        let len = (sample_rate / MIN_STRING_FREQ_HZ).ceil() as usize + 4;
+
+        let mut string = Self {
+            buffer: vec![0.0; len],
+            write_pos: 0,
+
+            delay: 0.0,
+            loop_gain: 0.0,
+            damping: 0.0,
+            filter_state: 0.0,
+
+            parameters: *parameters,
+            freq_hz,
+            sample_rate,
+        };
+
+        string.set_parameters(parameters);
+        string
+    }
+
+    /// Updates the brightness and decay of the string.
+    pub fn set_parameters(&mut self, parameters: &PluckParameters) {
+        self.parameters = PluckParameters {
+            brightness: parameters.brightness.clamp(0.0, 1.0),
+            decay_secs: parameters
+                .decay_secs
+                .clamp(MIN_PLUCK_DECAY_SECS, MAX_PLUCK_DECAY_SECS),
+        };
+
+        self.damping = (1.0 - self.parameters.brightness) * MAX_DAMPING;
+        self.update_tuning();
+    }
+
+    /// Plucks the string, replacing whatever it was playing.
+    pub fn pluck(&mut self) {
+        self.buffer.fill(0.0);
+        self.filter_state = 0.0;
+
+        // the burst is as dark as the string, so that darker strings don't
+        // start with a bright click
+        let mut state = 0.0;
+
+        for _ in 0..self.delay.ceil() as usize {
+            state = lerp(NoiseOsc::process(), state, self.damping);
+            self.push(state);
+        }
+    }
+
+    /// Recalculates the delay line's length and the loop gain for the
+    /// current frequency and parameters.
+    fn update_tuning(&mut self) {
+        let period = self.sample_rate / self.freq_hz;
+
+        // the damping filter delays low frequencies by d / (1 - d) samples
+        let filter_delay = self.damping / (1.0 - self.damping);
+        let max_delay = (self.buffer.len() - 2) as f64;
+        self.delay = (period - filter_delay).clamp(1.0, max_delay);
+
+        // decays by 60 dB over decay_secs
+        let periods_per_decay = self.parameters.decay_secs * self.freq_hz;
+        self.loop_gain = 10.0f64.powf(-3.0 / periods_per_decay);
+    }
+
+    fn push(&mut self, sample: f64) {
+        self.buffer[self.write_pos] = sample;
+        self.write_pos = (self.write_pos + 1) % self.buffer.len();
+    }
+
+    /// Reads the sample `self.delay` samples ago with linear interpolation.
+    fn read(&self) -> f64 {
+        let len = self.buffer.len();
+        let pos = (self.write_pos as f64 - self.delay).rem_euclid(len as f64);
+
+        let idx = pos as usize % len;
+        let next = (idx + 1) % len;
+
+        lerp(self.buffer[idx], self.buffer[next], pos.fract())
+    }
+}
+
+impl GeneratorProcessor for KarplusStrong {
+    /// Creates two, identical string samples.
+    fn process(&mut self) -> (f64, f64) {
+        self.filter_state = lerp(self.read(), self.filter_state, self.damping);
+
+        let out = self.filter_state * self.loop_gain;
+        self.push(out);
+
+        (out, out)
+    }
+
+    fn set_freq(&mut self, freq_hz: f64, sample_rate: f64) {
+        self.freq_hz = freq_hz.max(MIN_STRING_FREQ_HZ);
+        self.sample_rate = sample_rate;
+        self.update_tuning();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn rms(string: &mut KarplusStrong, num_samples: usize) -> f64 {
+        let sum: f64 =
+            (0..num_samples).map(|_| string.process().0.powi(2)).sum();
+
+        (sum / num_samples as f64).sqrt()
+    }
+
+    #[test]
+    fn test_silent_until_plucked() {
+        let mut string =
+            KarplusStrong::new(&PluckParameters::default(), 220.0, SAMPLE_RATE);
+
+        assert_eq!(rms(&mut string, 4800), 0.0);
+
+        string.pluck();
+        assert!(rms(&mut string, 4800) > 0.01);
+    }
+
+    #[test]
+    fn test_rings_at_its_frequency() {
+        let freq = 220.0;
+        let mut string =
+            KarplusStrong::new(&PluckParameters::default(), freq, SAMPLE_RATE);
+        string.pluck();
+
+        // skips the start of the pluck, where the noise is most prominent
+        _ = rms(&mut string, 4800);
+        let signal: Vec<f64> = (0..4800).map(|_| string.process().0).collect();
+
+        let autocorrelation = |lag: usize| -> f64 {
+            (0..signal.len() - lag).map(|i| signal[i] * signal[i + lag]).sum()
+        };
+
+        let best_lag = (100..400)
+            .max_by(|a, b| {
+                autocorrelation(*a).total_cmp(&autocorrelation(*b))
+            })
+            .unwrap();
+
+        let period = SAMPLE_RATE / freq;
+        assert!(
+            (best_lag as f64 - period).abs() <= 2.0,
+            "expected a period of {period}, got {best_lag}"
+        );
+    }
+
+    #[test]
+    fn test_longer_decay_rings_longer() {
+        let short = PluckParameters { decay_secs: 0.2, ..Default::default() };
+        let long = PluckParameters { decay_secs: 4.0, ..Default::default() };
+
+        let mut short = KarplusStrong::new(&short, 220.0, SAMPLE_RATE);
+        let mut long = KarplusStrong::new(&long, 220.0, SAMPLE_RATE);
+        short.pluck();
+        long.pluck();
+
+        // skips half a second
+        _ = rms(&mut short, 24000);
+        _ = rms(&mut long, 24000);
+
+        assert!(rms(&mut long, 4800) > rms(&mut short, 4800) * 10.0);
+    }
+}
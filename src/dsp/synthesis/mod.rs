@@ -2,13 +2,28 @@
 
 use super::*;
 
+pub mod audio_file;
 pub mod basic;
+pub mod click;
+pub mod fm;
 pub mod generator;
+pub mod granular;
+pub mod karplus_strong;
+pub mod polyblep;
+pub mod sample_player;
+pub mod wavetable;
 
 pub use basic::*;
 
+pub use click::ClickGenerator;
+pub use fm::{FmParameters, FmSynth};
 pub use generator::Generator;
-pub use noise_osc::NoiseOsc;
+pub use granular::GranularEngine;
+pub use karplus_strong::{KarplusStrong, PluckParameters};
+pub use polyblep::{PolyBlepSaw, PolyBlepSquare, PolyBlepTri};
+pub use sample_player::SamplePlayer;
+pub use wavetable::{Wavetable, WavetableOsc};
+pub use noise_osc::{ColoredNoise, NoiseColor, NoiseOsc};
 pub use phasor::Phasor;
 pub use sine::SineOsc;
 
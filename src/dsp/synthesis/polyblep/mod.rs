@@ -0,0 +1,158 @@
+//! Anti-aliased oscillator types, which use polynomial band-limited steps
+//! (PolyBLEPs) to smooth their discontinuities.
+//!
+//! A PolyBLEP corrects the two samples either side of each discontinuity, so
+//! aliasing is greatly reduced at little cost. Slope discontinuities (such as
+//! a triangle wave's corners) are corrected with its integral, a PolyBLAMP.
+
+use super::*;
+
+pub mod saw;
+pub mod square;
+pub mod tri;
+
+pub use saw::PolyBlepSaw;
+pub use square::PolyBlepSquare;
+pub use tri::PolyBlepTri;
+
+/// The correction for a step of `+2` at phase `0`, at phase `t` with a phase
+/// increment of `dt`.
+pub fn poly_blep(t: f64, dt: f64) -> f64 {
+    if t < dt {
+        let t = t / dt;
+        2.0f64.mul_add(t, -t * t) - 1.0
+    }
+    else if t > 1.0 - dt {
+        let t = (t - 1.0) / dt;
+        t.mul_add(t, 2.0 * t) + 1.0
+    }
+    else {
+        0.0
+    }
+}
+
+/// The correction for a change in slope of `+2` per sample at phase `0`, at
+/// phase `t` with a phase increment of `dt`.
+pub fn poly_blamp(t: f64, dt: f64) -> f64 {
+    if t < dt {
+        let t = 1.0 - t / dt;
+        t * t * t / 3.0
+    }
+    else if t > 1.0 - dt {
+        let t = (t - 1.0) / dt + 1.0;
+        t * t * t / 3.0
+    }
+    else {
+        0.0
+    }
+}
+
+/// Wraps `phase` to `[0.0, 1.0)`.
+fn wrap_phase(phase: f64) -> f64 {
+    phase - phase.floor()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The power of the non-harmonic (aliased) components relative to the
+    /// total power, found with a DFT of `num_samples` samples.
+    fn aliasing_ratio<G: GeneratorProcessor>(
+        mut osc: G,
+        num_samples: usize,
+        freq_bin: usize,
+    ) -> f64 {
+        let samples: Vec<f64> =
+            (0..num_samples).map(|_| osc.process().0).collect();
+
+        let mut alias = 0.0;
+        let mut total = 0.0;
+
+        for k in 1..num_samples / 2 {
+            let (mut re, mut im) = (0.0, 0.0);
+
+            for (n, x) in samples.iter().enumerate() {
+                let w = std::f64::consts::TAU * (k * n) as f64
+                    / num_samples as f64;
+                re += x * w.cos();
+                im -= x * w.sin();
+            }
+
+            let power = re * re + im * im;
+            total += power;
+
+            // anything which isn't a harmonic is aliasing
+            if k % freq_bin != 0 {
+                alias += power;
+            }
+        }
+
+        alias / total
+    }
+
+    #[test]
+    fn test_blep_is_continuous_at_the_edges() {
+        let dt = 0.01;
+
+        assert!(poly_blep(dt, dt).abs() < 1e-12);
+        assert!(poly_blep(1.0 - dt, dt).abs() < 1e-12);
+        assert!((poly_blep(0.0, dt) + 1.0).abs() < 1e-12);
+
+        assert!(poly_blamp(dt, dt).abs() < 1e-12);
+        assert!(poly_blamp(1.0 - dt, dt).abs() < 1e-12);
+        assert!((poly_blamp(0.0, dt) - 1.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_less_aliasing_than_naive_oscillators() {
+        // a high pitch, at an exact DFT bin so that leakage isn't counted
+        let num_samples = 1024;
+        let freq_bin = 93;
+        let sample_rate = 48000.0;
+        let freq = freq_bin as f64 * sample_rate / num_samples as f64;
+
+        let pairs = [
+            (
+                aliasing_ratio(
+                    PolyBlepSaw::new(freq, sample_rate),
+                    num_samples,
+                    freq_bin,
+                ),
+                aliasing_ratio(
+                    Phasor::new(freq, sample_rate),
+                    num_samples,
+                    freq_bin,
+                ),
+            ),
+            (
+                aliasing_ratio(
+                    PolyBlepSquare::new(freq, sample_rate),
+                    num_samples,
+                    freq_bin,
+                ),
+                aliasing_ratio(
+                    SquareOsc::new(freq, sample_rate),
+                    num_samples,
+                    freq_bin,
+                ),
+            ),
+            (
+                aliasing_ratio(
+                    PolyBlepTri::new(freq, sample_rate),
+                    num_samples,
+                    freq_bin,
+                ),
+                aliasing_ratio(
+                    TriOsc::new(freq, sample_rate),
+                    num_samples,
+                    freq_bin,
+                ),
+            ),
+        ];
+
+        for (blep, naive) in pairs {
+            assert!(blep < naive * 0.5, "{blep} vs. {naive}");
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Anti-aliased saw wave generator.
+
+use super::*;
+
+/// PolyBLEP saw wave oscillator.
+#[derive(Debug, Clone, Copy)]
+pub struct PolyBlepSaw {
+    phase: f64,
+    phase_increment: f64,
+}
+
+impl PolyBlepSaw {
+    pub fn new(freq_hz: f64, sample_rate: f64) -> Self {
+        debug_assert!(0.0 < freq_hz && freq_hz <= sample_rate / 2.0);
+
+        Self {
+            phase: 0.0,
+            phase_increment: freq_hz / sample_rate,
+        }
+    }
+
+    /// Resets the phase of the oscillator.
+    pub fn reset_phase(&mut self) {
+        self.phase = 0.0;
+    }
+
+    /// Sets the phase of the oscillator, from `0.0` to `1.0`.
+    pub fn set_phase(&mut self, phase: f64) {
+        self.phase = wrap_phase(phase);
+    }
+}
+
+impl GeneratorProcessor for PolyBlepSaw {
+    /// Creates two, identical saw wave samples.
+    fn process(&mut self) -> (f64, f64) {
+        let (t, dt) = (self.phase, self.phase_increment);
+
+        // the saw falls by 2 as it wraps
+        let out = t.mul_add(2.0, -1.0) - poly_blep(t, dt);
+
+        self.phase = wrap_phase(t + dt);
+
+        (out, out)
+    }
+
+    /// Sets the frequency of the saw wave oscillator.
+    fn set_freq(&mut self, freq_hz: f64, sample_rate: f64) {
+        self.phase_increment = freq_hz / sample_rate;
+    }
+}
@@ -0,0 +1,70 @@
+//! Anti-aliased square wave generator, with pulse-width modulation.
+
+use super::*;
+
+/// The narrowest pulse width allowed, to keep the edges apart.
+const MIN_PULSE_WIDTH: f64 = 0.01;
+
+/// PolyBLEP square (pulse) wave oscillator.
+#[derive(Debug, Clone, Copy)]
+pub struct PolyBlepSquare {
+    phase: f64,
+    phase_increment: f64,
+    pulse_width: f64,
+}
+
+impl PolyBlepSquare {
+    pub fn new(freq_hz: f64, sample_rate: f64) -> Self {
+        debug_assert!(0.0 < freq_hz && freq_hz <= sample_rate / 2.0);
+
+        Self {
+            phase: 0.0,
+            phase_increment: freq_hz / sample_rate,
+            pulse_width: 0.5,
+        }
+    }
+
+    /// Sets the proportion of each period which is high, clamped to
+    /// `[0.01, 0.99]`. `0.5` is a square wave.
+    pub fn set_pulse_width(&mut self, pulse_width: f64) {
+        self.pulse_width =
+            pulse_width.clamp(MIN_PULSE_WIDTH, 1.0 - MIN_PULSE_WIDTH);
+    }
+
+    pub const fn pulse_width(&self) -> f64 {
+        self.pulse_width
+    }
+
+    /// Resets the phase of the oscillator.
+    pub fn reset_phase(&mut self) {
+        self.phase = 0.0;
+    }
+
+    /// Sets the phase of the oscillator, from `0.0` to `1.0`.
+    pub fn set_phase(&mut self, phase: f64) {
+        self.phase = wrap_phase(phase);
+    }
+}
+
+impl GeneratorProcessor for PolyBlepSquare {
+    /// Creates two, identical square wave samples.
+    fn process(&mut self) -> (f64, f64) {
+        let (t, dt) = (self.phase, self.phase_increment);
+        let pw = self.pulse_width;
+
+        let naive = if t < pw { 1.0 } else { -1.0 };
+
+        // rises by 2 at the start of the period, and falls by 2 at the pulse
+        // width
+        let out = naive + poly_blep(t, dt) - poly_blep(wrap_phase(t - pw), dt);
+
+        self.phase = wrap_phase(t + dt);
+
+        (out, out)
+    }
+
+    /// Sets the frequency of the square wave oscillator.
+    fn set_freq(&mut self, freq_hz: f64, sample_rate: f64) {
+        self.phase_increment = freq_hz / sample_rate;
+    }
+}
@@ -0,0 +1,55 @@
+//! Anti-aliased triangle wave generator.
+
+use super::*;
+
+/// PolyBLAMP triangle wave oscillator.
+#[derive(Debug, Clone, Copy)]
+pub struct PolyBlepTri {
+    phase: f64,
+    phase_increment: f64,
+}
+
+impl PolyBlepTri {
+    pub fn new(freq_hz: f64, sample_rate: f64) -> Self {
+        debug_assert!(0.0 < freq_hz && freq_hz <= sample_rate / 2.0);
+
+        Self {
+            phase: 0.0,
+            phase_increment: freq_hz / sample_rate,
+        }
+    }
+
+    /// Resets the phase of the oscillator.
+    pub fn reset_phase(&mut self) {
+        self.phase = 0.0;
+    }
+
+    /// Sets the phase of the oscillator, from `0.0` to `1.0`.
+    pub fn set_phase(&mut self, phase: f64) {
+        self.phase = wrap_phase(phase);
+    }
+}
+
+impl GeneratorProcessor for PolyBlepTri {
+    /// Creates two, identical triangle wave samples.
+    fn process(&mut self) -> (f64, f64) {
+        let (t, dt) = (self.phase, self.phase_increment);
+
+        let naive = 2.0f64.mul_add((2.0f64.mul_add(t, -1.0)).abs(), -1.0);
+
+        // the slope changes by 8 per period (8 * dt per sample) at each
+        // corner: downwards at the peak, and upwards at the trough
+        let correction =
+            poly_blamp(wrap_phase(t - 0.5), dt) - poly_blamp(t, dt);
+        let out = (4.0 * dt).mul_add(correction, naive);
+
+        self.phase = wrap_phase(t + dt);
+
+        (out, out)
+    }
+
+    /// Sets the frequency of the triangle wave oscillator.
+    fn set_freq(&mut self, freq_hz: f64, sample_rate: f64) {
+        self.phase_increment = freq_hz / sample_rate;
+    }
+}
@@ -0,0 +1,466 @@
+//! Playback of audio files, which are streamed from disk.
+
+use super::audio_file::{AudioFileReader, StereoFrame};
+use super::*;
+use crate::prelude::*;
+use std::collections::VecDeque;
+use std::path::Path;
+
+/// The number of frames at the start of the file which are held in memory,
+/// so that playback can start immediately.
+const PRELOAD_FRAMES: usize = 1 << 15;
+/// The number of frames in each streamed chunk.
+const CHUNK_FRAMES: usize = 1 << 13;
+/// The number of chunks which may be requested or buffered at once.
+const NUM_CHUNKS: usize = 8;
+/// How often (in samples) finished chunks are collected and new ones are
+/// requested.
+const SERVICE_INTERVAL: u32 = 64;
+
+/// The slowest and fastest playback rates.
+const MIN_RATE: f64 = 0.25;
+const MAX_RATE: f64 = 4.0;
+
+const DEFAULT_ROOT_NOTE: f64 = 60.0;
+const DEFAULT_FADE_OUT_TIME_MS: f64 = 5.0;
+
+/// A request to the streaming thread for a range of frames.
+struct ChunkRequest {
+    /// Which trigger the request belongs to.
+    generation: u32,
+    /// The first frame of the chunk on the playback timeline, which keeps
+    /// counting up through loops.
+    start: usize,
+    frames: Box<[StereoFrame]>,
+}
+
+/// A chunk of frames filled by the streaming thread.
+struct Chunk {
+    generation: u32,
+    start: usize,
+    len: usize,
+    frames: Box<[StereoFrame]>,
+}
+
+/// Plays an audio file at a variable rate, optionally looping, whilst
+/// streaming it from disk on a [`ThreadPool`].
+///
+/// The start of the file is held in memory, and the rest is read ahead in
+/// chunks by a job on the pool, so nothing is read or allocated on the audio
+/// thread. Files which fit in memory entirely are resampled to the output's
+/// rate when they are opened.
+pub struct SamplePlayer {
+    /// The first frames of the file.
+    preload: Vec<StereoFrame>,
+    num_frames: usize,
+    /// The file's sample rate divided by the output's.
+    base_increment: f64,
+
+    requests: CCSender<ChunkRequest>,
+    chunks: CCReceiver<Chunk>,
+    /// Received chunks, in playback order.
+    ready: VecDeque<Chunk>,
+    /// Chunk buffers which are free to be requested.
+    free: Vec<Box<[StereoFrame]>>,
+    /// The timeline frame which the next request starts at.
+    next_request: usize,
+    generation: u32,
+    samples_until_service: u32,
+
+    /// The playback position on the timeline, in frames.
+    position: f64,
+    is_playing: bool,
+    is_looping: bool,
+
+    rate: f64,
+    pitch_tracking: bool,
+    root_note: f64,
+    /// The rate from the most recent note, if pitch tracking is enabled.
+    note_rate: f64,
+
+    gain: f64,
+    /// The level of the fade-out, or `None` if the player isn't stopping.
+    fade: Option<f64>,
+    fade_step: f64,
+
+    /// The number of samples which were silent because a chunk wasn't
+    /// ready in time.
+    underruns: u64,
+}
+
+impl SamplePlayer {
+    /// Opens the audio file at `path`, which is streamed by a job on `pool`
+    /// for as long as the player exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or read.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        pool: &ThreadPool,
+        sample_rate: f64,
+    ) -> Result<Self, String> {
+        let mut reader = AudioFileReader::open(path)?;
+        let info = reader.info();
+
+        let mut preload =
+            vec![[0.0; 2]; PRELOAD_FRAMES.min(info.num_frames)];
+        reader.read_frames(0, &mut preload)?;
+
+        let mut num_frames = info.num_frames;
+        let mut base_increment = info.sample_rate / sample_rate;
+
+        // files which are held in memory entirely are resampled to the
+        // output's rate up front, rather than interpolated as they play
+        if num_frames <= PRELOAD_FRAMES && !epsilon_eq(base_increment, 1.0) {
+            preload = resample_frames(&preload, info.sample_rate, sample_rate);
+            num_frames = preload.len();
+            base_increment = 1.0;
+        }
+
+        let (requests, request_rx) =
+            bounded_channel::<ChunkRequest>(NUM_CHUNKS);
+        let (chunk_tx, chunks) = bounded_channel(NUM_CHUNKS);
+
+        pool.execute(move || {
+            // the job finishes once the player is dropped
+            while let Ok(request) = request_rx.recv() {
+                let ChunkRequest { generation, start, mut frames } = request;
+                let len = fill_chunk(&mut reader, start, &mut frames);
+
+                if chunk_tx
+                    .send(Chunk { generation, start, len, frames })
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let mut player = Self {
+            preload,
+            num_frames,
+            base_increment,
+
+            requests,
+            chunks,
+            ready: VecDeque::with_capacity(NUM_CHUNKS),
+            free: (0..NUM_CHUNKS)
+                .map(|_| vec![[0.0; 2]; CHUNK_FRAMES].into_boxed_slice())
+                .collect(),
+            next_request: 0,
+            generation: 0,
+            samples_until_service: 0,
+
+            position: 0.0,
+            is_playing: false,
+            is_looping: false,
+
+            rate: 1.0,
+            pitch_tracking: true,
+            root_note: DEFAULT_ROOT_NOTE,
+            note_rate: 1.0,
+
+            gain: 1.0,
+            fade: None,
+            fade_step: 0.0,
+
+            underruns: 0,
+        };
+
+        player.fade_step =
+            (DEFAULT_FADE_OUT_TIME_MS * 0.001 * sample_rate).max(1.0).recip();
+
+        Ok(player)
+    }
+
+    /// Starts or stops playback for a note event.
+    ///
+    /// Playback changes at the sample this is called on, so it should be
+    /// called at the event's timing — the audio callback does this by
+    /// splitting each buffer into blocks at its events.
+    pub fn handle_note_event(&mut self, event: &NoteEvent) {
+        match *event {
+            NoteEvent::NoteOn { note, .. } => self.trigger(note),
+            NoteEvent::NoteOff { .. } => self.release(),
+        }
+    }
+
+    /// Starts playback from the start of the file. If pitch tracking is
+    /// enabled, the rate follows `note` relative to the root note.
+    pub fn trigger(&mut self, note: f64) {
+        self.note_rate = if self.pitch_tracking {
+            ((note - self.root_note) / 12.0).exp2()
+        }
+        else {
+            1.0
+        };
+
+        self.position = 0.0;
+        self.is_playing = true;
+        self.fade = None;
+
+        // streaming continues from the end of the preloaded frames
+        self.restart_stream(self.preload.len());
+    }
+
+    /// Fades out and stops playback.
+    pub fn release(&mut self) {
+        if self.is_playing && self.fade.is_none() {
+            self.fade = Some(1.0);
+        }
+    }
+
+    /// Stops playback immediately.
+    pub fn stop(&mut self) {
+        self.is_playing = false;
+        self.fade = None;
+    }
+
+    /// Sets whether the file loops, rather than stopping at its end.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.is_looping = looping;
+    }
+
+    /// Sets the playback rate, which is clamped to `[0.25, 4.0]`.
+    pub fn set_rate(&mut self, rate: f64) {
+        self.rate = rate.clamp(MIN_RATE, MAX_RATE);
+    }
+
+    /// Sets whether the playback rate follows the pitch of each note.
+    pub fn set_pitch_tracking(&mut self, enabled: bool) {
+        self.pitch_tracking = enabled;
+    }
+
+    /// Sets the note at which the file plays at its original pitch.
+    pub fn set_root_note(&mut self, note: f64) {
+        self.root_note = note;
+    }
+
+    pub fn set_gain(&mut self, gain: f64) {
+        self.gain = gain;
+    }
+
+    pub const fn is_active(&self) -> bool {
+        self.is_playing
+    }
+
+    pub const fn is_looping(&self) -> bool {
+        self.is_looping
+    }
+
+    /// The number of samples which were silent because streaming fell
+    /// behind.
+    pub const fn underruns(&self) -> u64 {
+        self.underruns
+    }
+
+    /// How far the position moves each sample.
+    fn increment(&self) -> f64 {
+        self.base_increment * self.rate * self.note_rate
+    }
+
+    /// Restarts streaming from `start`, discarding any buffered chunks.
+    fn restart_stream(&mut self, start: usize) {
+        self.generation = self.generation.wrapping_add(1);
+
+        while let Some(chunk) = self.ready.pop_front() {
+            self.free.push(chunk.frames);
+        }
+
+        self.next_request = start;
+        self.samples_until_service = 0;
+    }
+
+    /// Collects streamed chunks, recycles finished ones, and requests more.
+    fn service_stream(&mut self) {
+        while let Ok(chunk) = self.chunks.try_recv() {
+            if chunk.generation == self.generation {
+                self.ready.push_back(chunk);
+            }
+            else {
+                self.free.push(chunk.frames);
+            }
+        }
+
+        let position = self.position as usize;
+
+        while self
+            .ready
+            .front()
+            .is_some_and(|chunk| chunk.start + chunk.len <= position)
+        {
+            if let Some(chunk) = self.ready.pop_front() {
+                self.free.push(chunk.frames);
+            }
+        }
+
+        // files which fit in the preload buffer are never streamed
+        let needs_streaming = self.preload.len() < self.num_frames;
+        let is_finished =
+            !self.is_looping && self.next_request >= self.num_frames;
+
+        if !needs_streaming || is_finished {
+            return;
+        }
+
+        while let Some(frames) = self.free.pop() {
+            let request = ChunkRequest {
+                generation: self.generation,
+                start: self.next_request,
+                frames,
+            };
+
+            match self.requests.try_send(request) {
+                Ok(()) => self.next_request += CHUNK_FRAMES,
+                Err(e) => {
+                    self.free.push(e.into_inner().frames);
+                    break;
+                }
+            }
+
+            if !self.is_looping && self.next_request >= self.num_frames {
+                break;
+            }
+        }
+    }
+
+    /// The frame at `idx` on the playback timeline, or `None` if it is past
+    /// the end of the file or hasn't been streamed yet.
+    fn frame_at(&self, idx: usize) -> Option<StereoFrame> {
+        let file_idx =
+            if self.is_looping { idx % self.num_frames } else { idx };
+
+        if file_idx >= self.num_frames {
+            return None;
+        }
+
+        if let Some(frame) = self.preload.get(file_idx) {
+            return Some(*frame);
+        }
+
+        self.ready
+            .iter()
+            .find(|chunk| (chunk.start..chunk.start + chunk.len).contains(&idx))
+            .map(|chunk| chunk.frames[idx - chunk.start])
+    }
+
+    /// Adds the player's output to the interleaved stereo `buffer` from
+    /// `block_start` to `block_end`.
+    pub fn process_block(
+        &mut self,
+        buffer: &mut [f64],
+        block_start: usize,
+        block_end: usize,
+    ) {
+        if !self.is_playing {
+            return;
+        }
+
+        for sample_idx in block_start..block_end {
+            let (l, r) = self.process();
+
+            buffer[sample_idx * 2] += l;
+            buffer[sample_idx * 2 + 1] += r;
+        }
+    }
+}
+
+impl GeneratorProcessor for SamplePlayer {
+    fn process(&mut self) -> (f64, f64) {
+        if !self.is_playing || self.num_frames == 0 {
+            return (0.0, 0.0);
+        }
+
+        if self.samples_until_service == 0 {
+            self.service_stream();
+            self.samples_until_service = SERVICE_INTERVAL;
+        }
+
+        self.samples_until_service -= 1;
+
+        let idx = self.position as usize;
+
+        if !self.is_looping && idx >= self.num_frames {
+            self.stop();
+            return (0.0, 0.0);
+        }
+
+        // linear interpolation between the two nearest frames
+        let Some(a) = self.frame_at(idx)
+        else {
+            self.underruns += 1;
+            self.position += self.increment();
+            return (0.0, 0.0);
+        };
+
+        let b = self.frame_at(idx + 1).unwrap_or(a);
+        let t = self.position.fract();
+
+        let mut amp = self.gain;
+
+        if let Some(fade) = &mut self.fade {
+            amp *= *fade;
+            *fade -= self.fade_step;
+
+            if *fade <= 0.0 {
+                self.stop();
+            }
+        }
+
+        self.position += self.increment();
+
+        (lerp(a[0], b[0], t) * amp, lerp(a[1], b[1], t) * amp)
+    }
+
+    /// Sets the playback rate so that the root note plays at `freq_hz`.
+    fn set_freq(&mut self, freq_hz: f64, _sample_rate: f64) {
+        self.set_rate(freq_hz / note_to_freq(self.root_note));
+    }
+}
+
+/// Resamples stereo `frames` from `source_rate` to `target_rate`.
+fn resample_frames(
+    frames: &[StereoFrame],
+    source_rate: f64,
+    target_rate: f64,
+) -> Vec<StereoFrame> {
+    let [left, right] = [0, 1].map(|ch| {
+        let channel: Vec<f64> = frames.iter().map(|frame| frame[ch]).collect();
+        resample(&channel, source_rate, target_rate)
+    });
+
+    left.into_iter().zip(right).map(|(l, r)| [l, r]).collect()
+}
+
+/// Fills `frames` from timeline frame `start`, wrapping around the end of
+/// the file if it is looping, and returns the number of frames filled.
+fn fill_chunk<R>(
+    reader: &mut AudioFileReader<R>,
+    start: usize,
+    frames: &mut [StereoFrame],
+) -> usize
+where
+    R: std::io::Read + std::io::Seek,
+{
+    let num_frames = reader.info().num_frames;
+    let mut filled = 0;
+
+    // chunks always wrap, so they stay valid if looping is toggled; the
+    // player ignores frames past the end when it isn't looping
+    while filled < frames.len() && num_frames > 0 {
+        let file_idx = (start + filled) % num_frames;
+
+        match reader.read_frames(file_idx, &mut frames[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) => {
+                eprintln!("failed to stream audio file: {e}");
+                frames[filled..].fill([0.0; 2]);
+                return frames.len();
+            }
+        }
+    }
+
+    filled
+}
@@ -0,0 +1,457 @@
+//! A wavetable oscillator with band-limited mip levels.
+
+use super::audio_file::AudioFileReader;
+use super::*;
+use crate::prelude::*;
+use realfft::{num_complex::Complex, RealFftPlanner};
+use std::f64::consts::TAU;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The number of samples in each table.
+pub const WAVETABLE_SIZE: usize = 2048;
+/// The length of each frame in most wavetable files.
+pub const DEFAULT_WAVETABLE_FRAME_LEN: usize = 2048;
+/// The largest number of frames in a wavetable.
+pub const MAX_WAVETABLE_FRAMES: usize = 256;
+
+/// The number of mip levels, each of which has half the harmonics of the
+/// previous one, down to only the fundamental.
+const NUM_MIP_LEVELS: usize = WAVETABLE_SIZE.trailing_zeros() as usize;
+/// How long changes to the table position are smoothed over, in
+/// milliseconds.
+const POSITION_SMOOTHING_TIME_MS: f64 = 20.0;
+
+/// A set of single-cycle frames, each of which holds band-limited mip
+/// levels so that it can be played at any pitch without aliasing.
+///
+/// Building a wavetable allocates and runs FFTs, so it shouldn't be done on
+/// the audio thread. A wavetable may be shared between oscillators.
+#[derive(Debug, Clone)]
+pub struct Wavetable {
+    /// The mip levels of each frame, from the most harmonics to the fewest.
+    frames: Vec<[Vec<f64>; NUM_MIP_LEVELS]>,
+}
+
+impl Wavetable {
+    /// Builds a wavetable from `samples`, which hold consecutive frames of
+    /// `frame_len` samples. Frames are resampled to [`WAVETABLE_SIZE`], and
+    /// incomplete frames at the end are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `samples` hold no complete frames, or more than
+    /// [`MAX_WAVETABLE_FRAMES`].
+    pub fn from_samples(
+        samples: &[f64],
+        frame_len: usize,
+    ) -> Result<Self, String> {
+        if frame_len < 2 {
+            return Err(format!("invalid wavetable frame length {frame_len}"));
+        }
+
+        let num_frames = samples.len() / frame_len;
+
+        if num_frames == 0 {
+            return Err(String::from("wavetable holds no complete frames"));
+        }
+        if num_frames > MAX_WAVETABLE_FRAMES {
+            return Err(format!(
+                "wavetable has {num_frames} frames, but may have at most \
+                 {MAX_WAVETABLE_FRAMES}"
+            ));
+        }
+
+        Ok(Self::build(samples.chunks_exact(frame_len)))
+    }
+
+    /// Builds a wavetable of `num_frames` frames from `f`, which is given
+    /// each frame's position (from `0.0` to `1.0`) and a phase (from `0.0`
+    /// to `1.0`), and returns the sample at that phase.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_frames` is `0` or greater than
+    /// [`MAX_WAVETABLE_FRAMES`].
+    pub fn from_fn<F: Fn(f64, f64) -> f64>(num_frames: usize, f: F) -> Self {
+        assert!(
+            (1..=MAX_WAVETABLE_FRAMES).contains(&num_frames),
+            "invalid number of wavetable frames: {num_frames}"
+        );
+
+        let last = (num_frames - 1).max(1) as f64;
+        let frames = (0..num_frames)
+            .map(|i| {
+                let position = i as f64 / last;
+
+                (0..WAVETABLE_SIZE)
+                    .map(|j| f(position, j as f64 / WAVETABLE_SIZE as f64))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        Self::build(frames.iter().map(Vec::as_slice))
+    }
+
+    /// A wavetable which morphs from a sine, to a triangle, to a saw, to a
+    /// square wave.
+    pub fn basic_shapes() -> Self {
+        let shapes: [fn(f64) -> f64; 4] = [
+            |t| (t * TAU).sin(),
+            |t| 1.0 - 4.0 * ((t + 0.25).fract() - 0.5).abs(),
+            |t| 2.0 * (t + 0.5).fract() - 1.0,
+            |t| if t < 0.5 { 1.0 } else { -1.0 },
+        ];
+
+        Self::from_fn(shapes.len(), |position, phase| {
+            let idx = (position * (shapes.len() - 1) as f64).round() as usize;
+            shapes[idx](phase)
+        })
+    }
+
+    /// Loads a wavetable from a WAV or AIFF file whose frames are
+    /// `frame_len` samples long. Stereo files are mixed to mono.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or doesn't hold a valid
+    /// wavetable (see [`Wavetable::from_samples()`]).
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        frame_len: usize,
+    ) -> Result<Self, String> {
+        let mut reader = AudioFileReader::open(path)?;
+        let num_samples = reader.info().num_frames;
+
+        if num_samples > frame_len.saturating_mul(MAX_WAVETABLE_FRAMES) {
+            return Err(format!(
+                "wavetable file is too long for {MAX_WAVETABLE_FRAMES} \
+                 frames of {frame_len} samples"
+            ));
+        }
+
+        let mut stereo = vec![[0.0; 2]; num_samples];
+        let len = reader.read_frames(0, &mut stereo)?;
+
+        let samples = stereo[..len]
+            .iter()
+            .map(|[l, r]| (l + r) * 0.5)
+            .collect::<Vec<_>>();
+
+        Self::from_samples(&samples, frame_len)
+    }
+
+    /// The number of frames in the wavetable.
+    pub fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Builds the mip levels of each frame.
+    fn build<'a, I>(frames: I) -> Self
+    where
+        I: IntoIterator<Item = &'a [f64]>,
+    {
+        let mut planner = RealFftPlanner::<f64>::new();
+        let ifft = planner.plan_fft_inverse(WAVETABLE_SIZE);
+
+        let num_bins = WAVETABLE_SIZE / 2 + 1;
+        let mut spectrum = vec![Complex::default(); num_bins];
+        let mut level_spectrum = spectrum.clone();
+
+        let mut built = Vec::new();
+
+        for frame in frames {
+            frame_spectrum(&mut planner, frame, &mut spectrum);
+
+            let levels = std::array::from_fn(|level| {
+                let harmonics = 1..=max_harmonic(level);
+                let mut table = vec![0.0; WAVETABLE_SIZE];
+
+                // DC is removed, as it would thump when notes start
+                level_spectrum.fill(Complex::default());
+                level_spectrum[harmonics.clone()]
+                    .copy_from_slice(&spectrum[harmonics]);
+
+                ifft.process(&mut level_spectrum, &mut table)
+                    .expect("wavetable buffers have the correct size");
+
+                table
+            });
+
+            built.push(levels);
+        }
+
+        let mut wavetable = Self { frames: built };
+        wavetable.normalize();
+        wavetable
+    }
+
+    /// Scales every table so that the loudest frame peaks at `1.0`, which
+    /// keeps the frames' relative levels.
+    fn normalize(&mut self) {
+        let peak = self
+            .frames
+            .iter()
+            .flat_map(|levels| &levels[0])
+            .fold(0.0_f64, |peak, x| peak.max(x.abs()));
+
+        if peak <= f64::EPSILON {
+            return;
+        }
+
+        let gain = peak.recip();
+
+        for table in self.frames.iter_mut().flatten() {
+            table.iter_mut().for_each(|x| *x *= gain);
+        }
+    }
+
+    /// The mip level which has no harmonics above Nyquist at
+    /// `phase_increment` (in cycles per sample).
+    fn mip_level(phase_increment: f64) -> usize {
+        // level `n` has up to `WAVETABLE_SIZE / 2^(n + 1)` harmonics
+        let level = (phase_increment * WAVETABLE_SIZE as f64).log2().ceil();
+
+        if level.is_nan() || level <= 0.0 {
+            0
+        }
+        else {
+            (level as usize).min(NUM_MIP_LEVELS - 1)
+        }
+    }
+
+    /// Reads `frame` at `level` and `phase`, with linear interpolation.
+    fn read(&self, frame: usize, level: usize, phase: f64) -> f64 {
+        let table = &self.frames[frame][level];
+
+        let pos = phase * WAVETABLE_SIZE as f64;
+        let idx = pos as usize % WAVETABLE_SIZE;
+        let next = (idx + 1) % WAVETABLE_SIZE;
+
+        lerp(table[idx], table[next], pos.fract())
+    }
+}
+
+/// The highest harmonic in mip `level`. The table's Nyquist bin is never
+/// used, as its phase is lost.
+const fn max_harmonic(level: usize) -> usize {
+    let harmonic = (WAVETABLE_SIZE / 2) >> level;
+
+    if harmonic == WAVETABLE_SIZE / 2 {
+        harmonic - 1
+    }
+    else {
+        harmonic
+    }
+}
+
+/// Writes the spectrum of `frame`, resampled to [`WAVETABLE_SIZE`], to
+/// `spectrum`.
+fn frame_spectrum(
+    planner: &mut RealFftPlanner<f64>,
+    frame: &[f64],
+    spectrum: &mut [Complex<f64>],
+) {
+    let len = frame.len();
+    let fft = planner.plan_fft_forward(len);
+
+    let mut input = frame.to_vec();
+    let mut bins = fft.make_output_vec();
+
+    fft.process(&mut input, &mut bins)
+        .expect("wavetable buffers have the correct size");
+
+    // the inverse FFT isn't normalized, and the frame's harmonics are kept
+    // at the same indices regardless of its length
+    let scale = (len as f64).recip();
+    spectrum.fill(Complex::default());
+
+    for (out, bin) in spectrum.iter_mut().zip(&bins) {
+        *out = *bin * scale;
+    }
+
+    // a Nyquist bin is only meaningful for the frame's own length
+    if len % 2 == 0 && len / 2 < spectrum.len() {
+        spectrum[len / 2] = Complex::default();
+    }
+}
+
+// *** *** *** //
+
+/// Plays a [`Wavetable`], choosing the mip level for its pitch to avoid
+/// aliasing, and morphing between frames with its table position.
+#[derive(Debug, Clone)]
+pub struct WavetableOsc {
+    table: Arc<Wavetable>,
+
+    phase: f64,
+    /// The phase increment, in cycles per sample.
+    phase_increment: f64,
+    mip_level: usize,
+
+    /// The position in the table, from `0.0` (the first frame) to `1.0`
+    /// (the last).
+    position: Smoother<f64>,
+}
+
+impl WavetableOsc {
+    pub fn new(table: Arc<Wavetable>, freq_hz: f64, sample_rate: f64) -> Self {
+        let mut osc = Self {
+            table,
+
+            phase: 0.0,
+            phase_increment: 0.0,
+            mip_level: 0,
+
+            position: Smoother::new(
+                POSITION_SMOOTHING_TIME_MS,
+                0.0,
+                sample_rate,
+            ),
+        };
+
+        osc.set_freq(freq_hz, sample_rate);
+        osc
+    }
+
+    /// Sets the position in the table, from `0.0` (the first frame) to
+    /// `1.0` (the last). Changes are smoothed, so this may be driven
+    /// directly by a control such as a hand axis.
+    pub fn set_position(&mut self, position: f64) {
+        self.position.set_target_value(position.clamp(0.0, 1.0));
+    }
+
+    /// Sets the position in the table without smoothing.
+    pub fn reset_position(&mut self, position: f64) {
+        let position = position.clamp(0.0, 1.0);
+
+        self.position.set_target_value(position);
+        self.position.set_start_value(position);
+    }
+
+    /// Replaces the wavetable, keeping the phase and position.
+    pub fn set_table(&mut self, table: Arc<Wavetable>) {
+        self.table = table;
+    }
+
+    pub fn reset_phase(&mut self) {
+        self.phase = 0.0;
+    }
+}
+
+impl GeneratorProcessor for WavetableOsc {
+    /// Produces two identical wavetable samples.
+    fn process(&mut self) -> (f64, f64) {
+        let num_frames = self.table.num_frames();
+        let position =
+            self.position.next() * num_frames.saturating_sub(1) as f64;
+
+        let frame = (position as usize).min(num_frames - 1);
+        let next_frame = (frame + 1).min(num_frames - 1);
+
+        let a = self.table.read(frame, self.mip_level, self.phase);
+        let b = self.table.read(next_frame, self.mip_level, self.phase);
+        let out = lerp(a, b, position.fract());
+
+        self.phase += self.phase_increment;
+
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+        }
+
+        (out, out)
+    }
+
+    /// Sets the frequency of the oscillator, which selects the mip level.
+    fn set_freq(&mut self, freq_hz: f64, sample_rate: f64) {
+        debug_assert!(0.0 < freq_hz && freq_hz <= sample_rate / 2.0);
+
+        self.phase_increment = freq_hz / sample_rate;
+        self.mip_level = Wavetable::mip_level(self.phase_increment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The magnitude of each harmonic of one cycle of `samples`.
+    fn harmonics(samples: &[f64]) -> Vec<f64> {
+        let mut planner = RealFftPlanner::<f64>::new();
+        let fft = planner.plan_fft_forward(samples.len());
+
+        let mut input = samples.to_vec();
+        let mut spectrum = fft.make_output_vec();
+        fft.process(&mut input, &mut spectrum).unwrap();
+
+        spectrum.iter().map(|bin| bin.norm() / samples.len() as f64).collect()
+    }
+
+    #[test]
+    fn test_mip_levels_are_band_limited() {
+        let table = Wavetable::basic_shapes();
+
+        for level in 0..NUM_MIP_LEVELS {
+            let mags = harmonics(&table.frames[2][level]);
+
+            assert!(mags[max_harmonic(level) + 1..].iter().all(|&m| m < 1e-9));
+            assert!(mags[1] > 0.1);
+        }
+    }
+
+    #[test]
+    fn test_mip_level_selection() {
+        // a low note uses every harmonic
+        assert_eq!(Wavetable::mip_level(20.0 / 48000.0), 0);
+        // only the fundamental fits below Nyquist
+        assert_eq!(Wavetable::mip_level(0.4), NUM_MIP_LEVELS - 1);
+
+        for freq in [100.0, 1000.0, 5000.0] {
+            let inc = freq / 48000.0;
+            let level = Wavetable::mip_level(inc);
+
+            assert!(max_harmonic(level) as f64 * inc <= 0.5);
+        }
+    }
+
+    #[test]
+    fn test_from_samples_resamples_frames() {
+        let frame_len = 600;
+        let samples = (0..frame_len * 2)
+            .map(|i| (i as f64 / frame_len as f64 * TAU).sin())
+            .collect::<Vec<_>>();
+
+        let table = Wavetable::from_samples(&samples, frame_len).unwrap();
+        assert_eq!(table.num_frames(), 2);
+
+        let mags = harmonics(&table.frames[0][0]);
+        assert!((mags[1] - 0.5).abs() < 1e-6);
+        assert!(mags[2..].iter().all(|&m| m < 1e-9));
+
+        assert!(Wavetable::from_samples(&samples[..10], frame_len).is_err());
+    }
+
+    #[test]
+    fn test_position_morphs_between_frames() {
+        let table = Arc::new(Wavetable::from_fn(2, |position, _| {
+            if position < 0.5 { 0.0 } else { 1.0 }
+        }));
+
+        // the frames are only DC, which is removed
+        let mut osc = WavetableOsc::new(table, 100.0, 48000.0);
+        osc.reset_position(0.5);
+        assert!(osc.process().0.abs() < 1e-9);
+
+        let table = Arc::new(Wavetable::from_fn(2, |position, phase| {
+            (phase * TAU).sin() * position
+        }));
+
+        let mut osc = WavetableOsc::new(table, 12000.0, 48000.0);
+        osc.reset_position(0.5);
+        osc.process();
+        let (out, _) = osc.process();
+
+        // a quarter of a cycle in, halfway between silence and full level
+        assert!((out - 0.5).abs() < 1e-3);
+    }
+}
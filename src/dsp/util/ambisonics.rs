@@ -0,0 +1,139 @@
+//! First-order ambisonic encoding, for recording a mix in a form which can
+//! later be decoded to any speaker layout, or rendered binaurally.
+//!
+//! The B-format is "AmbiX": the channels are in ACN order (`W`, `Y`, `Z`,
+//! `X`) with SN3D normalization, as most ambisonic tools expect.
+
+/// The number of channels in first-order B-format.
+pub const NUM_BFORMAT_CHANNELS: usize = 4;
+/// The azimuth of each speaker of a stereo pair, in degrees.
+pub const STEREO_SPEAKER_AZIMUTH: f64 = 30.0;
+
+/// The direction of a speaker (or any source), as seen by the listener.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpeakerPosition {
+    /// The angle anticlockwise from the front, in degrees, so that the left
+    /// is `90`.
+    pub azimuth: f64,
+    /// The angle up from the horizon, in degrees.
+    pub elevation: f64,
+}
+
+impl SpeakerPosition {
+    pub const fn new(azimuth: f64, elevation: f64) -> Self {
+        Self { azimuth, elevation }
+    }
+
+    /// The gain of each B-format channel for a source at this position.
+    fn bformat_gains(self) -> [f64; NUM_BFORMAT_CHANNELS] {
+        let azimuth = self.azimuth.to_radians();
+        let elevation = self.elevation.to_radians();
+
+        [
+            1.0,
+            azimuth.sin() * elevation.cos(),
+            elevation.sin(),
+            azimuth.cos() * elevation.cos(),
+        ]
+    }
+}
+
+/// Encodes the channels of a speaker layout into first-order B-format, as if
+/// each channel was played from its speaker.
+#[derive(Clone, Debug)]
+pub struct AmbisonicEncoder {
+    /// The B-format gains of each channel of the layout.
+    gains: Vec<[f64; NUM_BFORMAT_CHANNELS]>,
+}
+
+impl AmbisonicEncoder {
+    /// Returns an encoder for a layout with a speaker at each of `speakers`.
+    pub fn new(speakers: &[SpeakerPosition]) -> Self {
+        Self {
+            gains: speakers.iter().map(|s| s.bformat_gains()).collect(),
+        }
+    }
+
+    /// Returns an encoder for a stereo pair of speakers in front of the
+    /// listener.
+    pub fn stereo() -> Self {
+        Self::new(&[
+            SpeakerPosition::new(STEREO_SPEAKER_AZIMUTH, 0.0),
+            SpeakerPosition::new(-STEREO_SPEAKER_AZIMUTH, 0.0),
+        ])
+    }
+
+    /// The number of channels in the layout.
+    pub fn num_channels(&self) -> usize {
+        self.gains.len()
+    }
+
+    /// Encodes a frame with a sample for each channel of the layout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame` doesn't have a sample for each channel.
+    pub fn encode(&self, frame: &[f64]) -> [f64; NUM_BFORMAT_CHANNELS] {
+        assert_eq!(frame.len(), self.gains.len());
+
+        let mut bformat = [0.0; NUM_BFORMAT_CHANNELS];
+
+        for (sample, gains) in frame.iter().zip(&self.gains) {
+            for (out, gain) in bformat.iter_mut().zip(gains) {
+                *out += sample * gain;
+            }
+        }
+
+        bformat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_frames_eq(a: [f64; 4], b: [f64; 4]) {
+        for (a, b) in a.into_iter().zip(b) {
+            assert!((a - b).abs() < 1e-12, "{a} != {b}");
+        }
+    }
+
+    #[test]
+    fn sources_are_encoded_by_direction() {
+        let enc = |azimuth, elevation| {
+            AmbisonicEncoder::new(&[SpeakerPosition::new(azimuth, elevation)])
+                .encode(&[0.5])
+        };
+
+        assert_frames_eq(enc(0.0, 0.0), [0.5, 0.0, 0.0, 0.5]);
+        assert_frames_eq(enc(90.0, 0.0), [0.5, 0.5, 0.0, 0.0]);
+        assert_frames_eq(enc(180.0, 0.0), [0.5, 0.0, 0.0, -0.5]);
+        assert_frames_eq(enc(0.0, 90.0), [0.5, 0.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn stereo_is_encoded_in_front() {
+        let encoder = AmbisonicEncoder::stereo();
+        assert_eq!(encoder.num_channels(), 2);
+
+        // a centered source is straight ahead
+        let [w, y, z, x] = encoder.encode(&[0.5, 0.5]);
+        assert!((w - 1.0).abs() < 1e-12);
+        assert!(y.abs() < 1e-12 && z.abs() < 1e-12);
+        assert!((x - 30f64.to_radians().cos()).abs() < 1e-12);
+
+        // the left is positive Y, and the right negative
+        assert!(encoder.encode(&[1.0, 0.0])[1] > 0.0);
+        assert!(encoder.encode(&[0.0, 1.0])[1] < 0.0);
+    }
+
+    #[test]
+    fn sources_are_summed() {
+        let front = SpeakerPosition::new(0.0, 0.0);
+        let back = SpeakerPosition::new(180.0, 0.0);
+        let encoder = AmbisonicEncoder::new(&[front, back]);
+
+        // opposite sources cancel out of the directional channels
+        assert_frames_eq(encoder.encode(&[0.25, 0.25]), [0.5, 0.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,247 @@
+//! Binaural rendering of a speaker layout for headphones, by convolving each
+//! speaker with the head-related impulse responses (HRIRs) of its direction.
+//!
+//! The HRIRs are modelled on a spherical head, after Brown and Duda ("A
+//! Structural Model for Binaural Sound Synthesis", 1998): each ear hears a
+//! source delayed by its path around the head, and shadowed by the head
+//! above a few hundred hertz.
+
+use super::ambisonics::{SpeakerPosition, STEREO_SPEAKER_AZIMUTH};
+use crate::dsp::{Convolver, Filter};
+use crate::prelude::*;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+/// The radius of the modelled head, in meters.
+const HEAD_RADIUS_M: f64 = 0.0875;
+/// The speed of sound, in meters per second.
+const SPEED_OF_SOUND: f64 = 343.0;
+/// The gain of the highs at the point of the head which is most shadowed.
+const MIN_SHADOW_GAIN: f64 = 0.1;
+/// The angle from the ear at which the head shadows the most, in degrees.
+const MAX_SHADOW_ANGLE: f64 = 150.0;
+/// The length of each HRIR, in seconds, which holds the longest delay
+/// around the head and the decay of its shadow.
+const HRIR_LENGTH_SECS: f64 = 0.0025;
+
+/// The impulse responses of each ear for a source in one direction.
+#[derive(Clone, Debug)]
+pub struct Hrir {
+    pub left: Vec<f64>,
+    pub right: Vec<f64>,
+}
+
+impl Hrir {
+    /// The HRIRs of a spherical head for a source at `position`.
+    pub fn spherical_head(position: SpeakerPosition, sample_rate: f64) -> Self {
+        let azimuth = position.azimuth.to_radians();
+        let elevation = position.elevation.to_radians();
+
+        // the ears face directly left and right
+        let lateral = azimuth.sin() * elevation.cos();
+
+        Self {
+            left: ear_response(lateral.acos(), sample_rate),
+            right: ear_response((-lateral).acos(), sample_rate),
+        }
+    }
+}
+
+/// The impulse response of an ear for a source `angle` radians from it.
+fn ear_response(angle: f64, sample_rate: f64) -> Vec<f64> {
+    let len = (HRIR_LENGTH_SECS * sample_rate).ceil() as usize;
+    let head_secs = HEAD_RADIUS_M / SPEED_OF_SOUND;
+
+    // the path is straight to the near side of the head, and wraps around
+    // it to the far side
+    let delay_secs = if angle < FRAC_PI_2 {
+        head_secs * (1.0 - angle.cos())
+    }
+    else {
+        head_secs * (1.0 + angle - FRAC_PI_2)
+    };
+
+    let delay = delay_secs * sample_rate;
+    let (idx, frac) = (delay.floor() as usize, delay.fract());
+
+    // the fractional delay is linearly interpolated
+    let mut response = vec![0.0; len];
+    response[idx] = 1.0 - frac;
+    response[idx + 1] = frac;
+
+    // the shadow is a shelf, whose highs are boosted near the ear and cut
+    // behind the head. it is unity at DC
+    let shadow_gain = (1.0 + MIN_SHADOW_GAIN / 2.0)
+        + (1.0 - MIN_SHADOW_GAIN / 2.0)
+            * (angle / MAX_SHADOW_ANGLE.to_radians() * PI).cos();
+
+    // the shelf's corner is bilinear-transformed
+    let k = sample_rate * head_secs;
+    let a0 = 1.0 + k;
+    let b0 = (1.0 + shadow_gain * k) / a0;
+    let b1 = (1.0 - shadow_gain * k) / a0;
+    let a1 = (1.0 - k) / a0;
+
+    let (mut x1, mut y1) = (0.0, 0.0);
+
+    for sample in &mut response {
+        let x = *sample;
+        *sample = b0 * x + b1 * x1 - a1 * y1;
+        (x1, y1) = (x, *sample);
+    }
+
+    response
+}
+
+/// Renders each channel of a speaker layout binaurally, as if it was played
+/// from its speaker.
+#[derive(Clone, Debug)]
+pub struct BinauralDecoder {
+    /// The convolvers of each speaker, for the left and right ears.
+    convolvers: Vec<[Convolver; NUM_CHANNELS]>,
+    /// The gain of each speaker, so that the lows of a signal which is
+    /// played by every speaker are heard at unity gain.
+    gain: f64,
+}
+
+impl BinauralDecoder {
+    /// Returns a decoder for a layout with a speaker at each of `speakers`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `speakers` is empty.
+    pub fn new(speakers: &[SpeakerPosition], sample_rate: f64) -> Self {
+        assert!(!speakers.is_empty(), "the layout must have a speaker");
+
+        let convolvers = speakers
+            .iter()
+            .map(|&position| {
+                let hrir = Hrir::spherical_head(position, sample_rate);
+                [Convolver::new(&hrir.left), Convolver::new(&hrir.right)]
+            })
+            .collect();
+
+        Self { convolvers, gain: 1.0 / speakers.len() as f64 }
+    }
+
+    /// Returns a decoder for a stereo pair of speakers in front of the
+    /// listener.
+    pub fn stereo(sample_rate: f64) -> Self {
+        Self::new(
+            &[
+                SpeakerPosition::new(STEREO_SPEAKER_AZIMUTH, 0.0),
+                SpeakerPosition::new(-STEREO_SPEAKER_AZIMUTH, 0.0),
+            ],
+            sample_rate,
+        )
+    }
+
+    /// The number of channels in the layout.
+    pub fn num_channels(&self) -> usize {
+        self.convolvers.len()
+    }
+
+    /// Renders a frame with a sample for each channel of the layout, and
+    /// returns the left and right ears.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame` doesn't have a sample for each channel.
+    pub fn process(&mut self, frame: &[f64]) -> (f64, f64) {
+        assert_eq!(frame.len(), self.convolvers.len());
+
+        let (mut left, mut right) = (0.0, 0.0);
+
+        for (&sample, [conv_l, conv_r]) in
+            frame.iter().zip(&mut self.convolvers)
+        {
+            left += conv_l.process(sample);
+            right += conv_r.process(sample);
+        }
+
+        (left * self.gain, right * self.gain)
+    }
+
+    /// Clears the tails of each speaker.
+    pub fn reset(&mut self) {
+        for convolver in self.convolvers.iter_mut().flatten() {
+            convolver.reset();
+        }
+    }
+}
+
+impl Default for BinauralDecoder {
+    fn default() -> Self {
+        Self::stereo(unsafe { SAMPLE_RATE })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// The index of the first sample of `response` which is audible.
+    fn onset(response: &[f64]) -> usize {
+        response.iter().position(|x| x.abs() > 1e-3).unwrap()
+    }
+
+    /// The energy of the highs of `response`, as its first difference.
+    fn high_energy(response: &[f64]) -> f64 {
+        response.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum()
+    }
+
+    #[test]
+    fn far_ear_is_delayed_and_shadowed() {
+        let left = SpeakerPosition::new(90.0, 0.0);
+        let hrir = Hrir::spherical_head(left, SAMPLE_RATE);
+
+        assert!(onset(&hrir.left) < onset(&hrir.right));
+        assert!(high_energy(&hrir.left) > high_energy(&hrir.right) * 4.0);
+
+        // both ears hear the lows equally
+        for response in [&hrir.left, &hrir.right] {
+            assert!((response.iter().sum::<f64>() - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn sources_ahead_are_heard_equally() {
+        for position in
+            [SpeakerPosition::new(0.0, 0.0), SpeakerPosition::new(180.0, 0.0)]
+        {
+            let hrir = Hrir::spherical_head(position, SAMPLE_RATE);
+
+            for (l, r) in hrir.left.iter().zip(&hrir.right) {
+                assert!((l - r).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn centred_stereo_is_heard_at_unity() {
+        let mut decoder = BinauralDecoder::stereo(SAMPLE_RATE);
+        assert_eq!(decoder.num_channels(), 2);
+
+        let mut output = (0.0, 0.0);
+
+        for _ in 0..1000 {
+            output = decoder.process(&[0.5, 0.5]);
+        }
+
+        assert!((output.0 - 0.5).abs() < 1e-3);
+        assert!((output.0 - output.1).abs() < 1e-12);
+
+        // the left speaker is louder in the left ear
+        decoder.reset();
+        let mut energy = (0.0, 0.0);
+
+        for i in 0..1000 {
+            let (l, r) = decoder.process(&[(i as f64 * 1.3).sin(), 0.0]);
+            energy.0 += l * l;
+            energy.1 += r * r;
+        }
+
+        assert!(energy.0 > energy.1);
+    }
+}
@@ -0,0 +1,96 @@
+//! An interleaved buffer of audio, independent of any audio stream.
+
+use crate::prelude::*;
+use std::ops::{Deref, DerefMut};
+
+/// An interleaved, multi-channel buffer of audio.
+///
+/// The signal chain is processed in this rather than in an audio stream's
+/// own buffer, so that it can also be processed without a stream (such as
+/// when rendering offline).
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioBuffer {
+    data: Vec<f64>,
+    channels: usize,
+}
+
+impl AudioBuffer {
+    /// Returns a silent buffer of `len_frames` frames of `channels`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channels` is `0`.
+    pub fn new(channels: usize, len_frames: usize) -> Self {
+        assert!(channels > 0);
+
+        Self { data: vec![0.0; channels * len_frames], channels }
+    }
+
+    pub const fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn len_frames(&self) -> usize {
+        self.data.len() / self.channels
+    }
+
+    /// Resizes the buffer to `len_frames` frames of `channels`, and
+    /// silences it. This only allocates if the buffer grows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channels` is `0`.
+    pub fn clear(&mut self, channels: usize, len_frames: usize) {
+        assert!(channels > 0);
+
+        self.channels = channels;
+        self.data.clear();
+        self.data.resize(channels * len_frames, 0.0);
+    }
+
+    /// An iterator over each frame, which holds a sample for each channel.
+    pub fn frames(&self) -> std::slice::ChunksExact<'_, f64> {
+        self.data.chunks_exact(self.channels)
+    }
+}
+
+impl Default for AudioBuffer {
+    /// An empty buffer of [`NUM_CHANNELS`].
+    fn default() -> Self {
+        Self::new(NUM_CHANNELS, 0)
+    }
+}
+
+impl Deref for AudioBuffer {
+    type Target = [f64];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl DerefMut for AudioBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clearing_resizes_and_silences_the_buffer() {
+        let mut buffer = AudioBuffer::new(2, 4);
+        buffer.fill(1.0);
+        assert_eq!(buffer.len_frames(), 4);
+
+        buffer.clear(2, 3);
+        assert_eq!(buffer.len(), 6);
+        assert!(buffer.iter().all(|&x| x == 0.0));
+
+        buffer[5] = 0.5;
+        let last = buffer.frames().last().unwrap();
+        assert_eq!(last, &[0.0, 0.5]);
+    }
+}
@@ -23,6 +23,19 @@ impl<E: Effect> DryWet<E> {
         }
     }
 
+    /// A wrapper which starts at `dry_level` and `wet_level`, rather than
+    /// fading to them.
+    pub fn with_levels(effect: E, dry_level: f64, wet_level: f64) -> Self {
+        let sr = effect.get_sample_rate();
+
+        let mut dry = Smoother::new(5.0, dry_level, sr);
+        dry.set_start_value(dry_level);
+        let mut wet = Smoother::new(5.0, wet_level, sr);
+        wet.set_start_value(wet_level);
+
+        Self { dry, wet, effect }
+    }
+
     pub fn set_dry(&mut self, dry_level: f64) {
         self.dry.set_target_value(dry_level);
     }
@@ -45,8 +58,8 @@ impl<E: Effect> DryWet<E> {
     pub fn set_mix_equal_gain(&mut self, mut mix: f64) {
         mix = mix.clamp(0.0, 1.0);
 
-        self.set_dry(mix);
-        self.set_wet(1.0 - mix);
+        self.set_dry(1.0 - mix);
+        self.set_wet(mix);
     }
 
     /// `mix == 0.0` is 100% dry, and `mix == 1.0` is 100% wet.
@@ -59,6 +72,16 @@ impl<E: Effect> DryWet<E> {
         self.set_wet((FRAC_PI_2 * mix).sin());
     }
 
+    /// Whether only the dry signal is output, so the effect may be skipped.
+    pub fn is_dry(&self) -> bool {
+        self.wet.current_value() == 0.0 && self.wet.target_value() == 0.0
+    }
+
+    pub fn reset_sample_rate(&mut self, sample_rate: f64) {
+        self.dry.reset_sample_rate(sample_rate);
+        self.wet.reset_sample_rate(sample_rate);
+    }
+
     /// Unwraps the contained effect.
     pub fn unwrap(self) -> E {
         self.effect
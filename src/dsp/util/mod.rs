@@ -1,11 +1,19 @@
 //! DSP utility types.
 
+pub mod ambisonics;
+pub mod binaural;
+pub mod buffer;
 pub mod dry_wet;
 pub mod effect_trait;
+pub mod resampler;
 pub mod stereo_wrapper;
 pub mod utility;
 
+pub use ambisonics::{AmbisonicEncoder, SpeakerPosition};
+pub use binaural::BinauralDecoder;
+pub use buffer::AudioBuffer;
 pub use dry_wet::DryWet;
 pub use effect_trait::Effect;
+pub use resampler::{resample, Resampler};
 pub use stereo_wrapper::StereoWrapper;
 pub use utility::{AudioUtility, PanningLaw};
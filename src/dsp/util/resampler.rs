@@ -0,0 +1,228 @@
+//! Sample-rate conversion between arbitrary rates, by polyphase
+//! windowed-sinc interpolation.
+//!
+//! The interpolation kernel is tabulated at [`NUM_PHASES`] fractional
+//! offsets, and linearly interpolated between them, so any ratio can be
+//! converted without recomputing the kernel. When the rate is lowered, the
+//! kernel is widened so that it also filters out what would alias.
+
+use crate::prelude::*;
+
+/// The number of fractional offsets which the kernel is tabulated at.
+pub const NUM_PHASES: usize = 512;
+/// The number of zero crossings of the kernel on each side of its centre.
+const ZERO_CROSSINGS: usize = 32;
+/// The cutoff as a proportion of the lower Nyquist frequency, which leaves
+/// room for the transition band.
+const ROLLOFF: f64 = 0.92;
+
+/// Converts a signal from one sample rate to another.
+///
+/// The resampler is streaming, so a signal may be passed in blocks of any
+/// length. The output lags the input by [`latency()`](Self::latency) input
+/// samples; see [`resample()`] to convert a whole buffer without any lag.
+#[derive(Clone, Debug)]
+pub struct Resampler {
+    /// The kernel at each phase, whose taps line up with the history.
+    table: Vec<f64>,
+    /// The number of input samples which the kernel spans.
+    num_taps: usize,
+    /// The most recent input samples, which are each written twice (a
+    /// kernel's length apart) so that the last `num_taps` samples are
+    /// always contiguous.
+    history: Vec<f64>,
+    pos: usize,
+    /// The number of input samples per output sample.
+    step: f64,
+    /// The time of the next output, relative to the newest input sample
+    /// (less the latency), in input samples.
+    time: f64,
+}
+
+impl Resampler {
+    /// Returns a resampler from `source_rate` to `target_rate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either rate isn't positive.
+    pub fn new(source_rate: f64, target_rate: f64) -> Self {
+        assert!(source_rate > 0.0 && target_rate > 0.0);
+
+        let step = source_rate / target_rate;
+        // the cutoff, relative to the input's Nyquist frequency
+        let cutoff = step.recip().min(1.0) * ROLLOFF;
+        let half_taps = (ZERO_CROSSINGS as f64 / cutoff).ceil() as usize;
+        let num_taps = half_taps * 2;
+
+        let kernel = |t: f64| {
+            let x = t / half_taps as f64;
+            cutoff
+                * sinc(PI * cutoff * t)
+                * window::kaiser_at(x, window::KAISER_BETA)
+        };
+
+        // the tap which lines up with the newest sample is last, and one
+        // extra phase is tabulated so that the last phase can interpolate
+        let mut table = Vec::with_capacity((NUM_PHASES + 1) * num_taps);
+
+        for phase in 0..=NUM_PHASES {
+            let frac = phase as f64 / NUM_PHASES as f64;
+
+            table.extend((0..num_taps).map(|i| {
+                kernel(frac + (half_taps - 1) as f64 - i as f64)
+            }));
+        }
+
+        Self {
+            table,
+            num_taps,
+            history: vec![0.0; num_taps * 2],
+            pos: 0,
+            step,
+            time: 0.0,
+        }
+    }
+
+    /// The ratio of the target rate to the source rate.
+    pub fn ratio(&self) -> f64 {
+        self.step.recip()
+    }
+
+    /// How far the output lags the input, in input samples.
+    pub fn latency(&self) -> usize {
+        self.num_taps / 2
+    }
+
+    /// Clears the history, so that the tail of the previous input is
+    /// silenced.
+    pub fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.pos = 0;
+        self.time = 0.0;
+    }
+
+    /// Resamples `input`, and appends the output to `output`.
+    pub fn process(&mut self, input: &[f64], output: &mut Vec<f64>) {
+        for &sample in input {
+            self.history[self.pos] = sample;
+            self.history[self.pos + self.num_taps] = sample;
+            self.pos += 1;
+
+            if self.pos == self.num_taps {
+                self.pos = 0;
+            }
+
+            while self.time < 1.0 {
+                output.push(self.interpolate(self.time));
+                self.time += self.step;
+            }
+
+            self.time -= 1.0;
+        }
+    }
+
+    /// The output at `frac` (from `0.0` to `1.0`) between the samples
+    /// around the kernel's centre.
+    fn interpolate(&self, frac: f64) -> f64 {
+        let phase = frac * NUM_PHASES as f64;
+        let idx = (phase as usize).min(NUM_PHASES - 1);
+        let t = phase - idx as f64;
+
+        let len = self.num_taps;
+        let taps = &self.table[idx * len..(idx + 2) * len];
+        let (a, b) = taps.split_at(len);
+
+        self.history[self.pos..self.pos + len]
+            .iter()
+            .zip(a.iter().zip(b))
+            .map(|(x, (a, b))| x * lerp(*a, *b, t))
+            .sum()
+    }
+}
+
+/// Resamples the whole of `input` from `source_rate` to `target_rate`,
+/// without any lag. The output holds `input.len()` multiplied by the ratio
+/// of the rates (rounded up) samples.
+///
+/// # Panics
+///
+/// Panics if either rate isn't positive.
+pub fn resample(
+    input: &[f64],
+    source_rate: f64,
+    target_rate: f64,
+) -> Vec<f64> {
+    let mut resampler = Resampler::new(source_rate, target_rate);
+    let len = (input.len() as f64 * resampler.ratio()).ceil() as usize;
+    let mut output = Vec::with_capacity(len + 1);
+
+    // no output is produced until the first input sample reaches the
+    // kernel's centre, so the output lines up with the input
+    resampler.time = resampler.latency() as f64;
+    resampler.process(input, &mut output);
+
+    // the tail is flushed with silence
+    let silence = vec![0.0; resampler.latency() + 1];
+    while output.len() < len {
+        resampler.process(&silence, &mut output);
+    }
+
+    output.truncate(len);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f64, sample_rate: f64, len: usize) -> Vec<f64> {
+        (0..len)
+            .map(|i| (TAU * freq * i as f64 / sample_rate).sin())
+            .collect()
+    }
+
+    #[test]
+    fn tones_are_resampled_in_time() {
+        for (source, target) in [(44100.0, 48000.0), (48000.0, 22050.0)] {
+            let input = sine(1000.0, source, 4410);
+            let output = resample(&input, source, target);
+            let expected = sine(1000.0, target, output.len());
+
+            let len = (4410.0 * target / source).ceil() as usize;
+            assert_eq!(output.len(), len);
+
+            // away from the edges, which are cut off abruptly
+            for i in 200..len - 200 {
+                assert!((output[i] - expected[i]).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn downsampling_removes_what_would_alias() {
+        // 30 kHz is above the Nyquist frequency of the target rate
+        let input = sine(30000.0, 96000.0, 9600);
+        let output = resample(&input, 96000.0, 48000.0);
+
+        let peak =
+            output[500..4300].iter().fold(0.0_f64, |m, x| m.max(x.abs()));
+        assert!(peak < 1e-3);
+    }
+
+    #[test]
+    fn blocks_match_the_whole_buffer() {
+        let input = sine(440.0, 44100.0, 1000);
+        let mut whole = Resampler::new(44100.0, 48000.0);
+        let mut blocks = whole.clone();
+
+        let mut expected = Vec::new();
+        whole.process(&input, &mut expected);
+
+        let mut output = Vec::new();
+        for block in input.chunks(37) {
+            blocks.process(block, &mut output);
+        }
+
+        assert_eq!(output, expected);
+    }
+}
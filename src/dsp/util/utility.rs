@@ -71,6 +71,16 @@ impl<E: Effect> AudioUtility<E> {
         self.invert.1 = inverted;
     }
 
+    /// The gain which each channel's output is multiplied by, including
+    /// inversion and panning. Swapping the channels isn't a gain, so it
+    /// isn't included.
+    pub fn output_gains(&self) -> (f64, f64) {
+        let (gain_l, gain_r) = self.process_gain(1.0, 1.0);
+        let (pan_l, pan_r) = self.process_panning(1.0, 1.0);
+
+        (gain_l * pan_l, gain_r * pan_r)
+    }
+
     fn process_gain(&self, left: f64, right: f64) -> (f64, f64) {
         let gain_l = if self.invert.0 { -1.0 } else { 1.0 } * self.gain;
         let gain_r = if self.invert.1 { -1.0 } else { 1.0 } * self.gain;
@@ -151,7 +161,7 @@ impl<E: Effect + Clone> Effect for AudioUtility<E> {
     }
 
     fn get_identifier(&self) -> &str {
-        return "audio_utility";
+        "audio_utility"
     }
 }
 
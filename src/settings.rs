@@ -102,8 +102,18 @@ pub const DEFAULT_OVERSAMPLING_FACTOR: usize = 2; // 4x oversampling
 /// buffer.
 pub const MAX_NOTE_EVENTS_PER_BUFFER: usize = 12;
 
+/// The maximum number of metronome control messages handled per buffer.
+pub const MAX_METRONOME_MESSAGES_PER_BUFFER: usize = 8;
+
+/// The maximum number of effect control messages handled per buffer.
+pub const MAX_FX_MESSAGES_PER_BUFFER: usize = 16;
+
 /// The default BPM for the device.
 pub const DEFAULT_BPM: f64 = 120.0;
+/// The slowest BPM the internal clock may run at.
+pub const MIN_BPM: f64 = 20.0;
+/// The fastest BPM the internal clock may run at.
+pub const MAX_BPM: f64 = 400.0;
 
 pub const DEFAULT_SMOOTHLIFE_SIZE: usize = 32;
 
@@ -112,10 +122,43 @@ pub const MAX_SPECTRAL_BLOCK_SIZE: usize = 1 << 14; // 16,384
 
 pub const MIDI_SEND_RATE: f64 = 100.0;
 pub const OSC_SEND_RATE: f64 = 5.0;
+/// How often OSC heartbeat messages are sent.
+pub const OSC_HEARTBEAT_RATE: f64 = 1.0;
+/// How often the app's state is published to visualizers over OSC.
+pub const OSC_STATE_BROADCAST_RATE: f64 = 30.0;
+/// How long (in seconds) without a received OSC packet before the connection
+/// is considered stale.
+pub const DEFAULT_OSC_STALE_TIMEOUT: f64 = 0.5;
+/// How long (in seconds) without a received OSC packet before the connection
+/// is considered dead.
+pub const DEFAULT_OSC_DEAD_TIMEOUT: f64 = 5.0;
+/// How often heartbeats are exchanged with a redundant instance.
+pub const REDUNDANCY_HEARTBEAT_RATE: f64 = 10.0;
+/// How often the primary instance replicates its state to the standby.
+pub const REDUNDANCY_REPLICATION_RATE: f64 = 4.0;
+/// How long (in seconds) without a heartbeat from the other instance before
+/// the standby takes over.
+pub const REDUNDANCY_FAILOVER_TIMEOUT: f64 = 0.6;
 pub const PARAM_UPDATE_RATE: f64 = 110.0;
 
 pub const DEFAULT_EME_ARRANGEMENT_NAME: &str = "MAESTRO";
 pub const EME_OSC_REQUEST_CHANNEL: &str = "/127.0.0.1/rt_requests";
+/// The OSC address which queries (e.g. for the list of arrangements) are sent
+/// to.
+pub const EME_OSC_QUERY_CHANNEL: &str = "/127.0.0.1/rt_query";
+/// The OSC address which the EME sends its list of arrangements on.
+pub const EME_OSC_ARRANGEMENTS_CHANNEL: &str = "/127.0.0.1/rt_arrangements";
+/// The OSC address which the EME replies to requests on.
+pub const EME_OSC_RESPONSE_CHANNEL: &str = "/127.0.0.1/rt_responses";
+/// How long (in seconds) to wait for the EME to acknowledge a request.
+pub const EME_RESPONSE_TIMEOUT: f64 = 0.5;
+/// How many times a request is sent before it is considered failed.
+pub const MAX_EME_REQUEST_ATTEMPTS: u32 = 3;
+/// How long (in seconds) an EME request failure is shown on screen.
+pub const EME_FAILURE_DISPLAY_TIME: f64 = 5.0;
+/// How far ahead (in seconds) EME request bundles are scheduled, which gives
+/// the receiver time to align playback with the gesture data.
+pub const EME_OSC_BUNDLE_LATENCY: f64 = 0.02;
 
 pub const MAX_MIDI_BUFFER_SIZE_BYTES: usize = 250;
 
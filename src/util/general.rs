@@ -260,3 +260,25 @@ pub fn freq_lin_from_log(
     // "de-log" the original
     10.0_f64.powf(log)
 }
+
+/// Parses a note division such as `"1/4"` or `"3/16"` into a number of
+/// beats, where a whole note is four beats. The division may be followed by
+/// `t` for a triplet or `.` for a dotted note.
+pub fn note_division_beats(division: &str) -> Option<f64> {
+    let (num, den) = division.split_once('/')?;
+
+    let (den, scale) = if let Some(den) = den.strip_suffix('t') {
+        (den, 2.0 / 3.0)
+    }
+    else if let Some(den) = den.strip_suffix('.') {
+        (den, 1.5)
+    }
+    else {
+        (den, 1.0)
+    };
+
+    let num = num.parse::<u32>().ok().filter(|n| *n > 0)?;
+    let den = den.parse::<u32>().ok().filter(|d| *d > 0)?;
+
+    Some(4.0 * f64::from(num) / f64::from(den) * scale)
+}
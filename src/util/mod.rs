@@ -42,4 +42,18 @@ mod tests {
         assert!(within_tolerance(db, -6.020_599_913_279_624, f64::EPSILON));
         assert!(within_tolerance(db_to_level(db), level, f64::EPSILON));
     }
+
+    #[test]
+    fn test_note_divisions() {
+        assert_eq!(note_division_beats("1/4"), Some(1.0));
+        assert_eq!(note_division_beats("3/16"), Some(0.75));
+        assert_eq!(note_division_beats("1/8."), Some(0.75));
+        assert_eq!(note_division_beats("2/1"), Some(8.0));
+
+        let triplet = note_division_beats("1/4t").unwrap();
+        assert!(within_tolerance(triplet, 2.0 / 3.0, f64::EPSILON));
+
+        assert_eq!(note_division_beats("0/4"), None);
+        assert_eq!(note_division_beats("4"), None);
+    }
 }
@@ -22,6 +22,41 @@ pub enum WindowType {
     Sine,
     Parzen,
     Welch,
+    /// A Kaiser window with a shape parameter of [`KAISER_BETA`].
+    Kaiser,
+}
+
+/// The shape parameter used by [`WindowType::Kaiser`], which gives side-lobe
+/// levels similar to the Blackman-Harris window.
+pub const KAISER_BETA: f64 = 8.6;
+/// The lobe width used by [`WindowType::Tukey`].
+pub const TUKEY_WIDTH: f64 = 0.5;
+
+impl WindowType {
+    /// Returns a window of this type with `size` elements.
+    pub fn build(self, size: usize) -> Vec<f64> {
+        let mut vec = vec![0.0; size];
+        self.build_in_place(&mut vec);
+        vec
+    }
+
+    /// In-place variant of `build()`.
+    pub fn build_in_place(self, slice: &mut [f64]) {
+        match self {
+            Self::Hann => hann_in_place(slice),
+            Self::Hamming => hamming_in_place(slice),
+            Self::Nuttall => nuttall_in_place(slice),
+            Self::Blackman => blackman_in_place(slice),
+            Self::BlackmanNuttall => blackman_nuttall_in_place(slice),
+            Self::BlackmanHarris => blackman_harris_in_place(slice),
+            Self::FlatTop => flat_top_in_place(slice),
+            Self::Tukey => tukey_in_place(slice, TUKEY_WIDTH),
+            Self::Sine => sine_in_place(slice),
+            Self::Parzen => parzen_in_place(slice),
+            Self::Welch => welch_in_place(slice),
+            Self::Kaiser => kaiser_in_place(slice, KAISER_BETA),
+        }
+    }
 }
 
 /// Multiplies each element of both buffers together.
@@ -142,6 +177,57 @@ pub fn tukey_in_place(slice: &mut [f64], width: f64) {
     }
 }
 
+/// A Kaiser window, AKA a Kaiser-Bessel window.
+///
+/// `beta` trades main-lobe width for side-lobe level: `0.0` is rectangular,
+/// and higher values give lower side-lobes with a wider main lobe.
+pub fn kaiser(size: usize, beta: f64) -> Vec<f64> {
+    let mut vec = vec![0.0; size];
+    kaiser_in_place(&mut vec, beta);
+    vec
+}
+
+/// In-place variant of `kaiser()`.
+pub fn kaiser_in_place(slice: &mut [f64], beta: f64) {
+    // periodic, like the cosine-sum windows, so that it overlaps evenly
+    let size = slice.len() as f64;
+    let norm = bessel_i0(beta).recip();
+
+    for (n, x) in slice.iter_mut().enumerate() {
+        let r = 2.0 * n as f64 / size - 1.0;
+        *x = bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) * norm;
+    }
+}
+
+/// The value of a Kaiser window at `x`, which spans the window from `-1.0`
+/// to `1.0`, for windowing continuous functions such as interpolation
+/// kernels. It is `0.0` outside of the window.
+pub fn kaiser_at(x: f64, beta: f64) -> f64 {
+    if x.abs() > 1.0 {
+        return 0.0;
+    }
+
+    bessel_i0(beta * (1.0 - x * x).sqrt()) / bessel_i0(beta)
+}
+
+/// The zeroth-order modified Bessel function of the first kind, via its
+/// power series.
+fn bessel_i0(x: f64) -> f64 {
+    let half_x = x / 2.0;
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+
+    // the terms shrink quickly for the betas used by windows
+    while term > sum * 1e-12 {
+        term *= (half_x / k) * (half_x / k);
+        sum += term;
+        k += 1.0;
+    }
+
+    sum
+}
+
 /// This function is used for all the below window functions, which are
 /// known as "cosine sum" functions.
 fn cosine_sum(slice: &mut [f64], coeffs: &[f64]) {
@@ -261,5 +347,19 @@ pub fn flat_top_in_place(slice: &mut [f64]) {
 
 #[cfg(test)]
 mod tests {
-    // TODO
+    use super::*;
+
+    #[test]
+    fn kaiser_peaks_at_centre() {
+        let window = kaiser(64, KAISER_BETA);
+
+        assert!((window[32] - 1.0).abs() < 1e-12);
+        assert!(window[0] < 1e-2);
+        assert!(window.iter().all(|&x| (0.0..=1.0).contains(&x)));
+    }
+
+    #[test]
+    fn kaiser_with_zero_beta_is_rectangular() {
+        assert!(kaiser(16, 0.0).iter().all(|&x| (x - 1.0).abs() < 1e-12));
+    }
 }